@@ -30,6 +30,10 @@ A protobuf library extending [`prost`](https://crates.io/crates/prost) with refl
 
 Rust **1.60** or higher.
 
+Some optional features depend on crates which require a newer version of Rust. These include
+`arrow`, `parquet`, `yaml`, `time`, `rayon`, `tokio`, `csv`, `gzip`, `deflate`, `cel`,
+`protovalidate` and `simd-json`.
+
 The minimum supported Rust version may be changed in the future, but it will be
 done with a minor version bump.
 
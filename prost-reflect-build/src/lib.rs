@@ -137,7 +137,7 @@ impl Builder {
                 .type_attribute(full_name, "#[derive(::prost_reflect::ReflectMessage)]")
                 .type_attribute(
                     full_name,
-                    &format!(
+                    format!(
                         r#"#[prost_reflect(descriptor_pool = "{}", message_name = "{}")]"#,
                         self.file_descriptor_expr, full_name,
                     ),
@@ -5,7 +5,9 @@ use prost::{
     bytes::{Buf, BufMut},
     Message,
 };
-use prost_reflect::{text_format, DescriptorPool, DeserializeOptions, DynamicMessage};
+use prost_reflect::{
+    text_format, DescriptorPool, DeserializeOptions, DynamicMessage, SerializeOptions,
+};
 
 use prost_reflect_conformance_tests::conformance::{
     conformance_request, conformance_response, ConformanceRequest, ConformanceResponse,
@@ -68,7 +70,7 @@ fn handle_request(request: ConformanceRequest) -> conformance_response::Result {
             }
         };
 
-    let mut json_deserialize_options = DeserializeOptions::new();
+    let mut json_deserialize_options = DeserializeOptions::conformance();
 
     match request.test_category() {
         TestCategory::UnspecifiedTest => (),
@@ -132,10 +134,17 @@ fn handle_request(request: ConformanceRequest) -> conformance_response::Result {
                 dynamic_message.to_text_format_with_options(&options),
             )
         }
-        WireFormat::Json => match serde_json::to_string(&dynamic_message) {
-            Ok(s) => conformance_response::Result::JsonPayload(s),
-            Err(err) => conformance_response::Result::SerializeError(err.to_string()),
-        },
+        WireFormat::Json => {
+            let mut serializer = serde_json::Serializer::new(Vec::new());
+            match dynamic_message
+                .serialize_with_options(&mut serializer, &SerializeOptions::conformance())
+            {
+                Ok(()) => conformance_response::Result::JsonPayload(
+                    String::from_utf8(serializer.into_inner()).unwrap(),
+                ),
+                Err(err) => conformance_response::Result::SerializeError(err.to_string()),
+            }
+        }
         WireFormat::Protobuf => {
             conformance_response::Result::ProtobufPayload(dynamic_message.encode_to_vec())
         }
@@ -106,9 +106,37 @@ fn encode_wkt_multithread(c: &mut Criterion) {
     });
 }
 
+fn field_lookup(c: &mut Criterion) {
+    let pool = prost_reflect_tests::test_file_descriptor();
+    for message_name in ["test.Scalars", "test.WellKnownTypes"] {
+        let desc = pool.get_message_by_name(message_name).unwrap();
+        let names: Vec<String> = desc.fields().map(|f| f.name().to_owned()).collect();
+        let json_names: Vec<String> = desc.fields().map(|f| f.json_name().to_owned()).collect();
+
+        c.bench_function(&format!("get_field_by_name/{}", message_name), |b| {
+            b.iter(|| {
+                for name in &names {
+                    criterion::black_box(desc.get_field_by_name(name));
+                }
+            })
+        });
+        c.bench_function(&format!("get_field_by_json_name/{}", message_name), |b| {
+            b.iter(|| {
+                for json_name in &json_names {
+                    criterion::black_box(desc.get_field_by_json_name(json_name));
+                }
+            })
+        });
+        c.bench_function(
+            &format!("get_field_by_name_missing/{}", message_name),
+            |b| b.iter(|| criterion::black_box(desc.get_field_by_name("no_such_field"))),
+        );
+    }
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().sample_size(500);
-    targets = decode_wkt, encode_wkt, decode_wkt_multithread, encode_wkt_multithread
+    targets = decode_wkt, encode_wkt, decode_wkt_multithread, encode_wkt_multithread, field_lookup
 }
 criterion_main!(benches);
@@ -0,0 +1,86 @@
+//! Benchmarks for building descriptor pools.
+//!
+//! With the default `rayon` feature, the files of a set are decoded in parallel. Run with
+//! `--no-default-features` to compare with decoding them sequentially.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use prost::Message;
+use prost_reflect::DescriptorPool;
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+};
+
+/// Builds a set of `files` files, each defining `messages` messages with `fields` fields. Every
+/// third field refers to the message with the same name in the previous file.
+fn generated_file_descriptor_set(
+    files: usize,
+    messages: usize,
+    fields: usize,
+) -> FileDescriptorSet {
+    let file = |file: usize| FileDescriptorProto {
+        name: Some(format!("file{}.proto", file)),
+        package: Some(format!("package{}", file)),
+        dependency: if file > 0 {
+            vec![format!("file{}.proto", file - 1)]
+        } else {
+            vec![]
+        },
+        message_type: (0..messages)
+            .map(|message| DescriptorProto {
+                name: Some(format!("Message{}", message)),
+                field: (0..fields)
+                    .map(|field| {
+                        let nested = file > 0 && field % 3 == 0;
+                        FieldDescriptorProto {
+                            name: Some(format!("field_{}", field)),
+                            number: Some(field as i32 + 1),
+                            label: Some(Label::Optional as i32),
+                            r#type: Some(if nested { Type::Message } else { Type::String } as i32),
+                            type_name: if nested {
+                                Some(format!(".package{}.Message{}", file - 1, message))
+                            } else {
+                                None
+                            },
+                            ..Default::default()
+                        }
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect(),
+        syntax: Some("proto3".to_owned()),
+        ..Default::default()
+    };
+
+    FileDescriptorSet {
+        file: (0..files).map(file).collect(),
+    }
+}
+
+fn decode_test_pool(c: &mut Criterion) {
+    let bytes = prost_reflect_tests::test_file_descriptor().encode_to_vec();
+
+    c.bench_function("decode_pool/test", |b| {
+        b.iter(|| DescriptorPool::decode(bytes.as_slice()).unwrap())
+    });
+}
+
+fn decode_generated_pool(c: &mut Criterion) {
+    let file_descriptor_set = generated_file_descriptor_set(100, 20, 20);
+    let bytes = file_descriptor_set.encode_to_vec();
+
+    c.bench_function("decode_pool/generated", |b| {
+        b.iter(|| DescriptorPool::decode(bytes.as_slice()).unwrap())
+    });
+    c.bench_function("from_file_descriptor_set/generated", |b| {
+        b.iter(|| DescriptorPool::from_file_descriptor_set(file_descriptor_set.clone()).unwrap())
+    });
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = decode_test_pool, decode_generated_pool
+}
+criterion_main!(benches);
@@ -46,6 +46,11 @@ fn main() -> io::Result<()> {
                 "src/imports.proto",
                 "src/ext.proto",
                 "src/options.proto",
+                "src/echo.proto",
+                "src/library.proto",
+                "src/google/type/types.proto",
+                "src/validation.proto",
+                "src/lint.proto",
             ],
             &["src/"],
         )?;
@@ -0,0 +1,196 @@
+use prost::Message;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use prost_reflect::{
+    AnyRegistry, DescriptorPool, DynamicMessage, ReflectMessage, TypeResolver, Value,
+};
+
+use crate::{
+    proto::{Point, Scalars},
+    test_file_descriptor,
+};
+
+fn point() -> Point {
+    Point {
+        latitude: 1,
+        longitude: -2,
+    }
+}
+
+#[test]
+fn pack() {
+    let any = point().transcode_to_dynamic().pack_any();
+    assert_eq!(
+        any.transcode_to::<prost_types::Any>().unwrap(),
+        prost_types::Any {
+            type_url: "type.googleapis.com/test.Point".to_owned(),
+            value: point().encode_to_vec(),
+        }
+    );
+}
+
+#[test]
+fn pack_without_any_in_pool() {
+    let pool = prost_reflect::DescriptorPool::decode(
+        prost_types::FileDescriptorSet {
+            file: vec![test_file_descriptor()
+                .get_file_by_name("desc.proto")
+                .unwrap()
+                .file_descriptor_proto()
+                .clone()],
+        }
+        .encode_to_vec()
+        .as_slice(),
+    )
+    .unwrap();
+    let message = DynamicMessage::new(pool.get_message_by_name("my.package.MyMessage").unwrap());
+
+    let any = message.pack_any();
+    assert_eq!(any.descriptor().full_name(), "google.protobuf.Any");
+    assert_eq!(
+        any.get_field_by_name("type_url").unwrap().as_str(),
+        Some("type.googleapis.com/my.package.MyMessage")
+    );
+}
+
+#[test]
+fn unpack() {
+    let any = point().transcode_to_dynamic().pack_any();
+    let message = any.unpack_any().unwrap();
+    assert_eq!(message.transcode_to::<Point>().unwrap(), point());
+
+    let message = any.unpack_any_as(Point::default().descriptor()).unwrap();
+    assert_eq!(message.transcode_to::<Point>().unwrap(), point());
+}
+
+#[test]
+fn unpack_custom_type_url() {
+    let any = prost_types::Any {
+        type_url: "example.com/schemas/test.Point".to_owned(),
+        value: point().encode_to_vec(),
+    }
+    .transcode_to_dynamic();
+
+    let err = any.unpack_any().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "no message type found for type URL 'example.com/schemas/test.Point'"
+    );
+
+    let resolver = |type_url: &str| {
+        let name = type_url.strip_prefix("example.com/schemas/")?;
+        test_file_descriptor().get_message_by_name(name)
+    };
+    let message = any.unpack_any_with_resolver(&resolver).unwrap();
+    assert_eq!(message.transcode_to::<Point>().unwrap(), point());
+
+    let message = any.unpack_any_as(Point::default().descriptor()).unwrap();
+    assert_eq!(message.transcode_to::<Point>().unwrap(), point());
+}
+
+#[test]
+fn unpack_errors() {
+    let err = point().transcode_to_dynamic().unpack_any().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a message of type 'google.protobuf.Any', but found 'test.Point'"
+    );
+
+    let any = point().transcode_to_dynamic().pack_any();
+    let err = any
+        .unpack_any_as(Scalars::default().descriptor())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a payload of type 'test.Scalars', but the type URL is 'type.googleapis.com/test.Point'"
+    );
+
+    let mut any = any;
+    any.set_field_by_name("value", Value::Bytes(b"\x08".as_ref().into()));
+    let err = any.unpack_any().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to decode payload: failed to decode Protobuf message: invalid varint"
+    );
+}
+
+#[test]
+fn registry_caches_pool_lookups() {
+    let registry = AnyRegistry::new()
+        .pool(DescriptorPool::new())
+        .pool(test_file_descriptor());
+
+    assert_eq!(registry.get_cached("type.googleapis.com/test.Point"), None);
+    let desc = registry
+        .resolve_message_type("type.googleapis.com/test.Point")
+        .unwrap();
+    assert_eq!(desc, Point::default().descriptor());
+    assert_eq!(
+        registry
+            .clone()
+            .get_cached("type.googleapis.com/test.Point"),
+        Some(desc)
+    );
+    assert_eq!(
+        registry.resolve_message_type("example.com/test.Point"),
+        None
+    );
+
+    registry.clear_cache();
+    assert_eq!(registry.get_cached("type.googleapis.com/test.Point"), None);
+}
+
+#[test]
+fn registry_fetch() {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let fetch_calls = calls.clone();
+    let registry = AnyRegistry::new().fetch(move |type_url: &str| {
+        fetch_calls.fetch_add(1, Ordering::Relaxed);
+        test_file_descriptor().get_message_by_name(type_url.strip_prefix("example.com/")?)
+    });
+
+    let mut any = point().transcode_to_dynamic().pack_any();
+    any.set_field_by_name(
+        "type_url",
+        Value::String("example.com/test.Point".to_owned()),
+    );
+    for _ in 0..3 {
+        assert_eq!(
+            any.unpack_any_with_resolver(&registry).unwrap(),
+            point().transcode_to_dynamic()
+        );
+    }
+    assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+    assert_eq!(
+        registry.resolve_message_type("example.com/test.Unknown"),
+        None
+    );
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn registry_fetch_async() {
+    let registry =
+        AnyRegistry::new()
+            .fetch(|_: &str| None)
+            .fetch_async(|type_url: String| async move {
+                test_file_descriptor().get_message_by_name(type_url.strip_prefix("example.com/")?)
+            });
+
+    assert_eq!(
+        registry.resolve_message_type("example.com/test.Point"),
+        None
+    );
+    assert_eq!(
+        pollster::block_on(registry.resolve_async("example.com/test.Point")),
+        Some(Point::default().descriptor())
+    );
+    assert_eq!(
+        registry.resolve_message_type("example.com/test.Point"),
+        Some(Point::default().descriptor())
+    );
+}
@@ -0,0 +1,323 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator, sync::Arc};
+
+use arrow_array::{
+    cast::AsArray, types::Int32Type, Array, ArrayRef, Int32Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, TimeUnit};
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{arrow, DynamicMessage, ReflectMessage};
+
+use crate::{
+    proto::{
+        contains_group, message_with_oneof, ComplexType, ContainsGroup, MessageWithOneof, Point,
+        ScalarArrays, Scalars, WellKnownTypes,
+    },
+    test_file_descriptor,
+};
+
+fn roundtrip<T>(messages: &[T]) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let desc = T::default().descriptor();
+    let dynamic: Vec<DynamicMessage> = messages
+        .iter()
+        .map(ReflectMessage::transcode_to_dynamic)
+        .collect();
+
+    let batch = arrow::to_record_batch(&desc, &dynamic).unwrap();
+    prop_assert_eq!(batch.num_rows(), messages.len());
+    prop_assert_eq!(batch.schema(), Arc::new(arrow::schema(&desc).unwrap()));
+
+    let roundtripped: Vec<T> = arrow::from_record_batch(&desc, &batch)
+        .unwrap()
+        .into_iter()
+        .map(|message| message.transcode_to().unwrap())
+        .collect();
+    prop_assert_eq!(messages, roundtripped.as_slice());
+    Ok(())
+}
+
+#[test]
+fn schema_scalars() {
+    let schema = arrow::schema(&Scalars::default().descriptor()).unwrap();
+    let types: Vec<(&str, &DataType, bool)> = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            (
+                field.name().as_str(),
+                field.data_type(),
+                field.is_nullable(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        types,
+        vec![
+            ("double", &DataType::Float64, false),
+            ("float", &DataType::Float32, false),
+            ("int32", &DataType::Int32, false),
+            ("int64", &DataType::Int64, false),
+            ("uint32", &DataType::UInt32, false),
+            ("uint64", &DataType::UInt64, false),
+            ("sint32", &DataType::Int32, false),
+            ("sint64", &DataType::Int64, false),
+            ("fixed32", &DataType::UInt32, false),
+            ("fixed64", &DataType::UInt64, false),
+            ("sfixed32", &DataType::Int32, false),
+            ("sfixed64", &DataType::Int64, false),
+            ("bool", &DataType::Boolean, false),
+            ("string", &DataType::Utf8, false),
+            ("bytes", &DataType::Binary, false),
+        ]
+    );
+}
+
+#[test]
+fn schema_well_known_types() {
+    let schema = arrow::schema(&WellKnownTypes::default().descriptor()).unwrap();
+    assert_eq!(
+        schema.field_with_name("timestamp").unwrap().data_type(),
+        &DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+    );
+    assert_eq!(
+        schema.field_with_name("duration").unwrap().data_type(),
+        &DataType::Duration(TimeUnit::Nanosecond)
+    );
+    assert_eq!(
+        schema.field_with_name("struct").unwrap().data_type(),
+        &DataType::Utf8
+    );
+    assert_eq!(
+        schema.field_with_name("int32").unwrap().data_type(),
+        &DataType::Int32
+    );
+    assert!(schema.field_with_name("int32").unwrap().is_nullable());
+    assert_eq!(
+        schema.field_with_name("null").unwrap().data_type(),
+        &DataType::Int32
+    );
+    assert_eq!(
+        schema.field_with_name("empty").unwrap().data_type(),
+        &DataType::Struct(Default::default())
+    );
+}
+
+#[test]
+fn schema_recursive() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.DescriptorProto")
+        .unwrap();
+    let err = arrow::schema(&desc).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Schema error: message 'google.protobuf.DescriptorProto' is recursive and cannot be represented in an arrow schema"
+    );
+}
+
+#[test]
+fn roundtrip_scalars() {
+    roundtrip(&[
+        Scalars {
+            double: 1.1,
+            float: -2.5,
+            int32: 3,
+            int64: -4,
+            uint32: 5,
+            uint64: 6,
+            sint32: -7,
+            sint64: 8,
+            fixed32: 9,
+            fixed64: 10,
+            sfixed32: -11,
+            sfixed64: 12,
+            r#bool: true,
+            string: "hello".to_owned(),
+            bytes: b"\x00\xff".to_vec(),
+        },
+        Scalars::default(),
+    ])
+    .unwrap();
+    roundtrip::<Scalars>(&[]).unwrap();
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    roundtrip(&[
+        ComplexType {
+            string_map: HashMap::from_iter([
+                ("a".to_owned(), Scalars::default()),
+                (
+                    "b".to_owned(),
+                    Scalars {
+                        int32: 1,
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            int_map: HashMap::from_iter([(-1, Scalars::default())]),
+            nested: Some(Scalars::default()),
+            my_enum: vec![0, 1, 3, -4, 100],
+            optional_enum: 3,
+        },
+        ComplexType::default(),
+    ])
+    .unwrap();
+}
+
+#[test]
+fn roundtrip_presence() {
+    roundtrip(&[
+        MessageWithOneof {
+            test_oneof: Some(message_with_oneof::TestOneof::OneofField2(0)),
+        },
+        MessageWithOneof { test_oneof: None },
+        MessageWithOneof {
+            test_oneof: Some(message_with_oneof::TestOneof::OneofValueNull(
+                prost_types::Value {
+                    kind: Some(prost_types::value::Kind::NullValue(0)),
+                },
+            )),
+        },
+    ])
+    .unwrap();
+
+    roundtrip(&[
+        ContainsGroup {
+            requiredgroup: Some(contains_group::RequiredGroup {
+                a: String::new(),
+                b: Some(0),
+            }),
+            optionalgroup: None,
+            repeatedgroup: vec![contains_group::RepeatedGroup {
+                e: "foo".to_owned(),
+                f: None,
+            }],
+        },
+        ContainsGroup::default(),
+    ])
+    .unwrap();
+}
+
+#[test]
+fn roundtrip_well_known_types() {
+    roundtrip(&[
+        WellKnownTypes {
+            timestamp: Some(prost_types::Timestamp {
+                seconds: -1,
+                nanos: 5,
+            }),
+            duration: Some(prost_types::Duration {
+                seconds: -2,
+                nanos: -5,
+            }),
+            r#struct: Some(prost_types::Struct {
+                fields: std::collections::BTreeMap::from_iter([(
+                    "a".to_owned(),
+                    prost_types::Value {
+                        kind: Some(prost_types::value::Kind::NumberValue(1.5)),
+                    },
+                )]),
+            }),
+            int32: Some(0),
+            string: Some("hello".to_owned()),
+            empty: Some(()),
+            mask: Some(prost_types::FieldMask {
+                paths: vec!["a.b".to_owned()],
+            }),
+            ..Default::default()
+        },
+        WellKnownTypes::default(),
+    ])
+    .unwrap();
+}
+
+#[test]
+fn enum_values() {
+    let messages = [ComplexType {
+        optional_enum: 3,
+        ..Default::default()
+    }
+    .transcode_to_dynamic()];
+    let batch = arrow::to_record_batch(&ComplexType::default().descriptor(), &messages).unwrap();
+    let column = batch.column_by_name("optional_enum").unwrap();
+    assert_eq!(column.as_primitive::<Int32Type>().value(0), 3);
+    assert!(!column.is_nullable());
+}
+
+#[test]
+fn timestamp_out_of_range() {
+    let messages = [WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: i64::MAX,
+            nanos: 0,
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic()];
+    let err =
+        arrow::to_record_batch(&WellKnownTypes::default().descriptor(), &messages).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid argument error: google.protobuf.Timestamp is out of range for nanosecond precision"
+    );
+}
+
+#[test]
+fn wrong_message_type() {
+    let messages = [Point::default().transcode_to_dynamic()];
+    let err = arrow::to_record_batch(&Scalars::default().descriptor(), &messages).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid argument error: expected messages of type 'test.Scalars', but found 'test.Point'"
+    );
+}
+
+#[test]
+fn column_type_mismatch() {
+    let batch =
+        RecordBatch::try_from_iter([("int32", Arc::new(StringArray::from(vec!["1"])) as ArrayRef)])
+            .unwrap();
+    let err = arrow::from_record_batch(&Scalars::default().descriptor(), &batch).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Schema error: expected column 'int32' to have type Int32, but found Utf8"
+    );
+
+    let batch =
+        RecordBatch::try_from_iter([("int32", Arc::new(Int32Array::from(vec![1, 2])) as ArrayRef)])
+            .unwrap();
+    let messages = arrow::from_record_batch(&Scalars::default().descriptor(), &batch).unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        messages[1].transcode_to::<Scalars>().unwrap(),
+        Scalars {
+            int32: 2,
+            ..Default::default()
+        }
+    );
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(messages: Vec<Scalars>) {
+        roundtrip(&messages)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(messages: Vec<ScalarArrays>) {
+        roundtrip(&messages)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_complex_type(messages: Vec<ComplexType>) {
+        roundtrip(&messages)?;
+    }
+}
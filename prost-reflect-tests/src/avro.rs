@@ -0,0 +1,404 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator};
+
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{avro, DescriptorPool, DynamicMessage, MapKey, ReflectMessage, Value};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+};
+use serde_json::json;
+
+use crate::{
+    proto::{
+        contains_group, message_with_oneof, ComplexType, ContainsGroup, MessageWithOneof, Point,
+        ScalarArrays, Scalars, WellKnownTypes,
+    },
+    test_file_descriptor,
+};
+
+fn roundtrip<T>(message: &T) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let desc = message.descriptor();
+    prop_assert!(avro::schema(&desc).is_ok());
+
+    let datum = avro::to_datum(&message.transcode_to_dynamic()).unwrap();
+    let roundtripped: T = avro::from_datum(desc, &datum)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    prop_assert_eq!(message, &roundtripped);
+    Ok(())
+}
+
+fn message_field(name: &str, number: i32, type_name: &str) -> FieldDescriptorProto {
+    FieldDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        label: Some(Label::Optional as i32),
+        r#type: Some(Type::Message as i32),
+        type_name: Some(type_name.to_owned()),
+        ..Default::default()
+    }
+}
+
+fn message(name: &str, field: Vec<FieldDescriptorProto>) -> DescriptorProto {
+    DescriptorProto {
+        name: Some(name.to_owned()),
+        field,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn schema_point() {
+    assert_eq!(
+        avro::schema(&Point::default().descriptor()).unwrap(),
+        json!({
+            "type": "record",
+            "name": "Point",
+            "namespace": "test",
+            "fields": [
+                { "name": "latitude", "type": "int", "default": 0 },
+                { "name": "longitude", "type": "int", "default": 0 },
+            ],
+        })
+    );
+}
+
+#[test]
+fn schema_complex_type() {
+    assert_eq!(
+        avro::schema(&ComplexType::default().descriptor()).unwrap()["fields"],
+        json!([
+            {
+                "name": "string_map",
+                "type": {
+                    "type": "map",
+                    "values": avro::schema(&Scalars::default().descriptor()).unwrap(),
+                },
+                "default": {},
+            },
+            {
+                "name": "int_map",
+                "type": { "type": "map", "values": "test.Scalars" },
+                "default": {},
+            },
+            { "name": "nested", "type": ["null", "test.Scalars"], "default": null },
+            {
+                "name": "my_enum",
+                "type": {
+                    "type": "array",
+                    "items": {
+                        "type": "enum",
+                        "name": "MyEnum",
+                        "namespace": "test.ComplexType",
+                        "symbols": ["NEG", "DEFAULT", "FOO", "BAR"],
+                        "default": "DEFAULT",
+                    },
+                },
+                "default": [],
+            },
+            {
+                "name": "optional_enum",
+                "type": "test.ComplexType.MyEnum",
+                "default": "DEFAULT",
+            },
+        ])
+    );
+}
+
+#[test]
+fn schema_recursive() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.DescriptorProto")
+        .unwrap();
+    let schema = avro::schema(&desc).unwrap();
+    assert_eq!(
+        schema["fields"][2],
+        json!({
+            "name": "nested_type",
+            "type": { "type": "array", "items": "google.protobuf.DescriptorProto" },
+            "default": [],
+        })
+    );
+}
+
+#[test]
+fn schema_invalid_name() {
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_proto(FileDescriptorProto {
+        name: Some("invalid_name.proto".to_owned()),
+        package: Some("my.package".to_owned()),
+        message_type: vec![message("string", vec![])],
+        ..Default::default()
+    })
+    .unwrap();
+
+    let desc = pool.get_message_by_name("my.package.string").unwrap();
+    let err = avro::schema(&desc).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "'string' is not a valid name in an Avro schema"
+    );
+}
+
+#[test]
+fn schema_unqualified_reference() {
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_proto(FileDescriptorProto {
+        name: Some("no_package.proto".to_owned()),
+        message_type: vec![message("Foo", vec![])],
+        ..Default::default()
+    })
+    .unwrap();
+    pool.add_file_descriptor_proto(FileDescriptorProto {
+        name: Some("package.proto".to_owned()),
+        package: Some("my.package".to_owned()),
+        dependency: vec!["no_package.proto".to_owned()],
+        message_type: vec![message(
+            "Bar",
+            vec![message_field("a", 1, ".Foo"), message_field("b", 2, ".Foo")],
+        )],
+        ..Default::default()
+    })
+    .unwrap();
+
+    let desc = pool.get_message_by_name("Foo").unwrap();
+    assert_eq!(
+        avro::schema(&desc).unwrap(),
+        json!({ "type": "record", "name": "Foo", "namespace": "", "fields": [] })
+    );
+
+    let desc = pool.get_message_by_name("my.package.Bar").unwrap();
+    let err = avro::schema(&desc).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "type 'Foo' has no namespace, so it cannot be referenced from namespace 'my.package'"
+    );
+}
+
+#[test]
+fn encode_point() {
+    let message = Point {
+        latitude: 1,
+        longitude: -65,
+    };
+    assert_eq!(
+        avro::to_datum(&message.transcode_to_dynamic()).unwrap(),
+        b"\x02\x81\x01"
+    );
+}
+
+#[test]
+fn encode_presence() {
+    let message = MessageWithOneof {
+        test_oneof: Some(message_with_oneof::TestOneof::OneofField2(0)),
+    };
+    assert_eq!(
+        avro::to_datum(&message.transcode_to_dynamic()).unwrap(),
+        b"\x00\x02\x00\x00\x00"
+    );
+}
+
+#[test]
+fn roundtrip_scalars() {
+    roundtrip(&Scalars {
+        double: 1.1,
+        float: -2.5,
+        int32: 3,
+        int64: -4,
+        uint32: u32::MAX,
+        uint64: u64::MAX,
+        sint32: -7,
+        sint64: 8,
+        fixed32: 9,
+        fixed64: 10,
+        sfixed32: -11,
+        sfixed64: 12,
+        r#bool: true,
+        string: "hello".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+    })
+    .unwrap();
+    roundtrip(&Scalars::default()).unwrap();
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    roundtrip(&ComplexType {
+        string_map: HashMap::from_iter([
+            ("a".to_owned(), Scalars::default()),
+            (
+                "b".to_owned(),
+                Scalars {
+                    int32: 1,
+                    ..Default::default()
+                },
+            ),
+        ]),
+        int_map: HashMap::from_iter([(-1, Scalars::default()), (5, Scalars::default())]),
+        nested: Some(Scalars::default()),
+        my_enum: vec![0, 1, 3, -4],
+        optional_enum: 3,
+    })
+    .unwrap();
+    roundtrip(&ComplexType::default()).unwrap();
+}
+
+#[test]
+fn roundtrip_presence() {
+    roundtrip(&MessageWithOneof {
+        test_oneof: Some(message_with_oneof::TestOneof::OneofField2(0)),
+    })
+    .unwrap();
+    roundtrip(&MessageWithOneof { test_oneof: None }).unwrap();
+    roundtrip(&ContainsGroup {
+        requiredgroup: Some(contains_group::RequiredGroup {
+            a: String::new(),
+            b: Some(0),
+        }),
+        optionalgroup: None,
+        repeatedgroup: vec![contains_group::RepeatedGroup {
+            e: "foo".to_owned(),
+            f: None,
+        }],
+    })
+    .unwrap();
+}
+
+#[test]
+fn roundtrip_well_known_types() {
+    roundtrip(&WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: -1,
+            nanos: 5,
+        }),
+        duration: Some(prost_types::Duration {
+            seconds: -2,
+            nanos: -5,
+        }),
+        r#struct: Some(prost_types::Struct {
+            fields: std::collections::BTreeMap::from_iter([(
+                "a".to_owned(),
+                prost_types::Value {
+                    kind: Some(prost_types::value::Kind::ListValue(
+                        prost_types::ListValue {
+                            values: vec![prost_types::Value {
+                                kind: Some(prost_types::value::Kind::NumberValue(1.5)),
+                            }],
+                        },
+                    )),
+                },
+            )]),
+        }),
+        int32: Some(0),
+        string: Some("hello".to_owned()),
+        empty: Some(()),
+        ..Default::default()
+    })
+    .unwrap();
+}
+
+#[test]
+fn unknown_enum_value() {
+    let message = ComplexType {
+        optional_enum: 100,
+        ..Default::default()
+    };
+    let err = avro::to_datum(&message.transcode_to_dynamic()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "enum 'test.ComplexType.MyEnum' has no value with number 100"
+    );
+}
+
+#[test]
+fn decode_errors() {
+    let desc = Point::default().descriptor();
+    assert_eq!(
+        avro::from_datum(desc.clone(), b"\x02")
+            .unwrap_err()
+            .to_string(),
+        "unexpected end of datum"
+    );
+    assert_eq!(
+        avro::from_datum(desc.clone(), b"\x02\x02\x02")
+            .unwrap_err()
+            .to_string(),
+        "unexpected data after end of datum"
+    );
+    assert_eq!(
+        avro::from_datum(desc, b"\xfe\xff\xff\xff\x1f\x00")
+            .unwrap_err()
+            .to_string(),
+        "integer 4294967295 is out of range for the field type"
+    );
+
+    let desc = MessageWithOneof::default().descriptor();
+    assert_eq!(
+        avro::from_datum(desc, b"\x04").unwrap_err().to_string(),
+        "invalid union branch 2"
+    );
+}
+
+#[test]
+fn decode_negative_block_count() {
+    let desc = ScalarArrays::default().descriptor();
+    let mut datum = b"\x00\x00".to_vec();
+    // The int32 array is written as a block with a count of -2 and a size of 2 bytes.
+    datum.extend_from_slice(b"\x03\x04\x02\x04\x00");
+    datum.extend_from_slice(&[0; 12]);
+
+    let message: ScalarArrays = avro::from_datum(desc, &datum)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    assert_eq!(message.int32, vec![1, 2]);
+}
+
+#[test]
+fn decode_recursion_limit() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.Struct")
+        .unwrap();
+    let mut message = DynamicMessage::new(desc.clone());
+    for _ in 0..60 {
+        let mut value = DynamicMessage::new(
+            test_file_descriptor()
+                .get_message_by_name("google.protobuf.Value")
+                .unwrap(),
+        );
+        value.set_field_by_name("struct_value", Value::Message(message));
+        message = DynamicMessage::new(desc.clone());
+        message
+            .get_field_by_name_mut("fields")
+            .unwrap()
+            .as_map_mut()
+            .unwrap()
+            .insert(MapKey::String("a".to_owned()), Value::Message(value));
+    }
+
+    let datum = avro::to_datum(&message).unwrap();
+    let err = avro::from_datum(desc, &datum).unwrap_err();
+    assert_eq!(err.to_string(), "recursion limit reached");
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(message: Scalars) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(message: ScalarArrays) {
+        roundtrip(&message)?;
+    }
+}
@@ -0,0 +1,166 @@
+use axum_core::{
+    extract::FromRequest,
+    response::{IntoResponse, Response},
+};
+use http::{header, Request, StatusCode};
+use http_body::Full;
+use prost::{bytes::Bytes, Message};
+use prost_reflect::{
+    axum::{BodyFormat, DynamicProto, DynamicProtoRejection, MessageLookup},
+    ReflectMessage,
+};
+
+use crate::{proto::Point, test_file_descriptor};
+
+fn point_lookup() -> MessageLookup {
+    MessageLookup::from(Point::default().descriptor())
+}
+
+fn extract(
+    request: http::request::Builder,
+    body: impl Into<Bytes>,
+    lookup: &MessageLookup,
+) -> Result<DynamicProto, DynamicProtoRejection> {
+    let request = request.body(Full::new(body.into())).unwrap();
+    pollster::block_on(DynamicProto::from_request(request, lookup))
+}
+
+fn response_body(response: Response) -> Bytes {
+    pollster::block_on(Bytes::from_request(Request::new(response.into_body()), &())).unwrap()
+}
+
+#[test]
+fn extract_protobuf() {
+    let point = Point {
+        latitude: 1,
+        longitude: 2,
+    };
+    let request = Request::post("/").header(header::CONTENT_TYPE, "application/x-protobuf");
+    let extracted = extract(request, point.encode_to_vec(), &point_lookup()).unwrap();
+
+    assert_eq!(extracted.format, BodyFormat::Protobuf);
+    assert_eq!(extracted.message.transcode_to::<Point>().unwrap(), point);
+}
+
+#[test]
+fn extract_json() {
+    let request =
+        Request::post("/").header(header::CONTENT_TYPE, "application/json; charset=utf-8");
+    let extracted = extract(request, r#"{"latitude":1,"longitude":2}"#, &point_lookup()).unwrap();
+
+    assert_eq!(extracted.format, BodyFormat::Json);
+    assert_eq!(
+        extracted.message.transcode_to::<Point>().unwrap(),
+        Point {
+            latitude: 1,
+            longitude: 2,
+        }
+    );
+}
+
+#[test]
+fn extract_accept_overrides_response_format() {
+    let request = Request::post("/")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCEPT, "text/html, application/protobuf;q=0.9");
+    let extracted = extract(request, "{}", &point_lookup()).unwrap();
+
+    assert_eq!(extracted.format, BodyFormat::Protobuf);
+}
+
+#[test]
+fn extract_accept_quality() {
+    let cases = [
+        ("application/json", BodyFormat::Json),
+        (
+            "application/json;q=0.5, application/protobuf",
+            BodyFormat::Protobuf,
+        ),
+        (
+            "application/protobuf;q=0.5, application/json;Q=0.8",
+            BodyFormat::Json,
+        ),
+        (
+            "application/protobuf;q=0.5, application/json;q=0.5",
+            BodyFormat::Protobuf,
+        ),
+        (
+            "application/json;q=0, application/protobuf;q=0.1",
+            BodyFormat::Protobuf,
+        ),
+        ("application/json;q=0", BodyFormat::Protobuf),
+        (
+            "application/json;q=0, application/x-protobuf;q=0",
+            BodyFormat::Json,
+        ),
+        ("application/x-protobuf;q=0", BodyFormat::Json),
+        ("application/protobuf;q=2, text/html", BodyFormat::Json),
+    ];
+
+    for (accept, format) in cases {
+        let request = Request::post("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::ACCEPT, accept);
+        let extracted = extract(request, "{}", &point_lookup()).unwrap();
+
+        assert_eq!(extracted.format, format, "{}", accept);
+    }
+}
+
+#[test]
+fn extract_lookup_by_path() {
+    let lookup = MessageLookup::new(|parts| {
+        test_file_descriptor().get_message_by_name(parts.uri.path().trim_start_matches('/'))
+    });
+
+    let request = Request::post("/test.Point").header(header::CONTENT_TYPE, "application/json");
+    let extracted = extract(request, r#"{"latitude":3}"#, &lookup).unwrap();
+    assert_eq!(extracted.message.descriptor().full_name(), "test.Point");
+
+    let request = Request::post("/test.Missing").header(header::CONTENT_TYPE, "application/json");
+    let err = extract(request, "{}", &lookup).unwrap_err();
+    assert_eq!(err.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn extract_rejections() {
+    let request = Request::post("/").header(header::CONTENT_TYPE, "text/plain");
+    let err = extract(request, "{}", &point_lookup()).unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let err = extract(Request::post("/"), "{}", &point_lookup()).unwrap_err();
+    assert_eq!(err.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+    let request = Request::post("/").header(header::CONTENT_TYPE, "application/json");
+    let err = extract(request, r#"{"latitude":"foo"}"#, &point_lookup()).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+
+    let request = Request::post("/").header(header::CONTENT_TYPE, "application/x-protobuf");
+    let err = extract(request, &b"\x08"[..], &point_lookup()).unwrap_err();
+    assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn response() {
+    let message = Point {
+        latitude: 1,
+        longitude: 2,
+    }
+    .transcode_to_dynamic();
+
+    let response = DynamicProto::new(message.clone(), BodyFormat::Protobuf).into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/x-protobuf"
+    );
+    assert_eq!(response_body(response), message.encode_to_vec());
+
+    let response = DynamicProto::new(message, BodyFormat::Json).into_response();
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert_eq!(
+        response_body(response),
+        r#"{"latitude":1,"longitude":2}"#.as_bytes()
+    );
+}
@@ -0,0 +1,284 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator};
+
+use ::bson::{doc, oid::ObjectId, spec::BinarySubtype, Binary, Bson, DateTime, Decimal128};
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{DeserializeOptions, DynamicMessage, ReflectMessage, SerializeOptions};
+
+use crate::proto::{ComplexType, Point, ScalarArrays, Scalars, WellKnownTypes};
+
+fn roundtrip<T>(message: &T) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let dynamic = message.transcode_to_dynamic();
+
+    let document = dynamic.to_bson_document().unwrap();
+    let roundtripped: T = DynamicMessage::from_bson_document(message.descriptor(), &document)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    prop_assert_eq!(message, &roundtripped);
+
+    // Documents should survive being encoded, as they would be when stored.
+    let mut bytes = Vec::new();
+    document.to_writer(&mut bytes).unwrap();
+    let document = ::bson::Document::from_reader(bytes.as_slice()).unwrap();
+    let roundtripped: T = DynamicMessage::from_bson_document(message.descriptor(), &document)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    prop_assert_eq!(message, &roundtripped);
+
+    Ok(())
+}
+
+#[test]
+fn native_types() {
+    let message = Scalars {
+        double: f64::NAN,
+        float: 0.1,
+        int32: 3,
+        int64: -4,
+        uint32: u32::MAX,
+        uint64: u64::MAX,
+        fixed64: 10,
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    };
+    let document = message.transcode_to_dynamic().to_bson_document().unwrap();
+
+    assert!(document.get_f64("double").unwrap().is_nan());
+    assert_eq!(document.get_f64("float").unwrap(), 0.1);
+    assert_eq!(document.get_i32("int32").unwrap(), 3);
+    assert_eq!(document.get_i64("int64").unwrap(), -4);
+    assert_eq!(document.get_i64("uint32").unwrap(), i64::from(u32::MAX));
+    assert_eq!(document.get_str("uint64").unwrap(), "18446744073709551615");
+    assert_eq!(document.get_i64("fixed64").unwrap(), 10);
+    assert_eq!(
+        document.get("bytes").unwrap(),
+        &Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: b"\x00\xff".to_vec(),
+        })
+    );
+}
+
+#[test]
+fn well_known_types() {
+    let message = WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: 1_600_000_000,
+            nanos: 123_456_789,
+        }),
+        int64: Some(-5),
+        bytes: Some(b"abc".to_vec()),
+        ..Default::default()
+    };
+    let document = message.transcode_to_dynamic().to_bson_document().unwrap();
+    assert_eq!(
+        document,
+        doc! {
+            "bytes": Binary { subtype: BinarySubtype::Generic, bytes: b"abc".to_vec() },
+            "int64": -5i64,
+            "timestamp": DateTime::from_millis(1_600_000_000_123),
+        }
+    );
+
+    let roundtripped: WellKnownTypes =
+        DynamicMessage::from_bson_document(message.descriptor(), &document)
+            .unwrap()
+            .transcode_to()
+            .unwrap();
+    assert_eq!(
+        roundtripped.timestamp,
+        Some(prost_types::Timestamp {
+            seconds: 1_600_000_000,
+            nanos: 123_000_000,
+        })
+    );
+}
+
+#[test]
+fn serialize_options() {
+    let message = ComplexType {
+        my_enum: vec![1],
+        ..Default::default()
+    };
+    let options = SerializeOptions::new()
+        .use_enum_numbers(true)
+        .use_proto_field_name(true);
+    assert_eq!(
+        message
+            .transcode_to_dynamic()
+            .to_bson_document_with_options(&options)
+            .unwrap(),
+        doc! { "my_enum": [1] }
+    );
+
+    let options = SerializeOptions::new().use_field_numbers(true);
+    let document = message
+        .transcode_to_dynamic()
+        .to_bson_document_with_options(&options)
+        .unwrap();
+    assert_eq!(document, doc! { "4": ["FOO"] });
+}
+
+#[test]
+fn deserialize_json_representations() {
+    let document = doc! {
+        "int64": "-4",
+        "uint64": "5",
+        "bytes": "AP8=",
+        "string": "hello",
+    };
+    let message: Scalars =
+        DynamicMessage::from_bson_document(Scalars::default().descriptor(), &document)
+            .unwrap()
+            .transcode_to()
+            .unwrap();
+    assert_eq!(
+        message,
+        Scalars {
+            int64: -4,
+            uint64: 5,
+            bytes: b"\x00\xff".to_vec(),
+            string: "hello".to_owned(),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn deserialize_object_id() {
+    let id = ObjectId::parse_str("0123456789abcdef01234567").unwrap();
+    let document = doc! { "_id": id, "latitude": 1 };
+
+    let err =
+        DynamicMessage::from_bson_document(Point::default().descriptor(), &document).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to deserialize message: unrecognized field name '_id'"
+    );
+
+    let message: Point = DynamicMessage::from_bson_document_with_options(
+        Point::default().descriptor(),
+        &document,
+        &DeserializeOptions::new().deny_unknown_fields(false),
+    )
+    .unwrap()
+    .transcode_to()
+    .unwrap();
+    assert_eq!(
+        message,
+        Point {
+            latitude: 1,
+            longitude: 0,
+        }
+    );
+
+    let document = doc! { "string": id };
+    let message: Scalars =
+        DynamicMessage::from_bson_document(Scalars::default().descriptor(), &document)
+            .unwrap()
+            .transcode_to()
+            .unwrap();
+    assert_eq!(message.string, "0123456789abcdef01234567");
+}
+
+#[test]
+fn deserialize_unsupported_type() {
+    let document = doc! { "double": Decimal128::from_bytes([0; 16]) };
+    let err =
+        DynamicMessage::from_bson_document(Scalars::default().descriptor(), &document).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "BSON values of type Decimal128 are not supported"
+    );
+}
+
+#[test]
+fn roundtrip_scalars() {
+    roundtrip(&Scalars {
+        double: 1.1,
+        float: -2.5,
+        int32: 3,
+        int64: i64::MIN,
+        uint32: u32::MAX,
+        uint64: u64::MAX,
+        sint32: -7,
+        sint64: 8,
+        fixed32: 9,
+        fixed64: 10,
+        sfixed32: -11,
+        sfixed64: 12,
+        r#bool: true,
+        string: "hello".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+    })
+    .unwrap();
+    roundtrip(&Scalars {
+        double: f64::INFINITY,
+        float: f32::NEG_INFINITY,
+        ..Default::default()
+    })
+    .unwrap();
+    roundtrip(&Scalars::default()).unwrap();
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    roundtrip(&ComplexType {
+        string_map: HashMap::from_iter([
+            ("a".to_owned(), Scalars::default()),
+            (
+                "b".to_owned(),
+                Scalars {
+                    int64: 1,
+                    ..Default::default()
+                },
+            ),
+        ]),
+        int_map: HashMap::from_iter([(-1, Scalars::default()), (5, Scalars::default())]),
+        nested: Some(Scalars::default()),
+        my_enum: vec![0, 1, 3, -4],
+        optional_enum: 3,
+    })
+    .unwrap();
+}
+
+#[test]
+fn roundtrip_well_known_types() {
+    roundtrip(&WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: -1,
+            nanos: 5_000_000,
+        }),
+        duration: Some(prost_types::Duration {
+            seconds: 2,
+            nanos: 5,
+        }),
+        uint64: Some(u64::MAX),
+        bytes: Some(vec![]),
+        empty: Some(()),
+        ..Default::default()
+    })
+    .unwrap();
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(message: Scalars) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(message: ScalarArrays) {
+        roundtrip(&message)?;
+    }
+}
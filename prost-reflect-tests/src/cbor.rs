@@ -0,0 +1,137 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator};
+
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{DeserializeOptions, DynamicMessage, ReflectMessage, SerializeOptions};
+
+use crate::proto::{ComplexType, Point, ScalarArrays, Scalars, WellKnownTypes};
+
+fn roundtrip<T>(message: &T) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let dynamic = message.transcode_to_dynamic();
+
+    let bytes = dynamic.to_cbor().unwrap();
+    let roundtripped: T = DynamicMessage::from_cbor(message.descriptor(), &bytes)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    prop_assert_eq!(message, &roundtripped);
+
+    let bytes = dynamic
+        .to_cbor_with_options(&SerializeOptions::new().use_field_numbers(true))
+        .unwrap();
+    let roundtripped: T = DynamicMessage::from_cbor_with_options(
+        message.descriptor(),
+        &bytes,
+        &DeserializeOptions::new().allow_field_numbers(true),
+    )
+    .unwrap()
+    .transcode_to()
+    .unwrap();
+    prop_assert_eq!(message, &roundtripped);
+
+    Ok(())
+}
+
+#[test]
+fn roundtrip_scalars() {
+    roundtrip(&Scalars {
+        double: 1.1,
+        float: -2.5,
+        int32: 3,
+        int64: -4,
+        uint32: 5,
+        uint64: 6,
+        sint32: -7,
+        sint64: 8,
+        fixed32: 9,
+        fixed64: 10,
+        sfixed32: -11,
+        sfixed64: 12,
+        r#bool: true,
+        string: "hello".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+    })
+    .unwrap();
+    roundtrip(&Scalars::default()).unwrap();
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    roundtrip(&ComplexType {
+        string_map: HashMap::from_iter([
+            ("a".to_owned(), Scalars::default()),
+            (
+                "b".to_owned(),
+                Scalars {
+                    int32: 1,
+                    ..Default::default()
+                },
+            ),
+        ]),
+        int_map: HashMap::from_iter([(-1, Scalars::default())]),
+        nested: Some(Scalars::default()),
+        my_enum: vec![0, 1, 3, -4, 100],
+        optional_enum: 3,
+    })
+    .unwrap();
+}
+
+#[test]
+fn field_number_keys() {
+    let message = Point {
+        latitude: 1,
+        longitude: 2,
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        message.to_cbor().unwrap(),
+        b"\xbf\x68latitude\x01\x69longitude\x02\xff"
+    );
+    let bytes = message
+        .to_cbor_with_options(&SerializeOptions::new().use_field_numbers(true))
+        .unwrap();
+    assert_eq!(bytes, b"\xbf\x01\x01\x02\x02\xff");
+
+    let err = DynamicMessage::from_cbor(message.descriptor(), &bytes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to deserialize message: unrecognized field name '1'"
+    );
+}
+
+#[test]
+fn invalid_cbor() {
+    let err = DynamicMessage::from_cbor(Point::default().descriptor(), b"\xbf\x01").unwrap_err();
+    assert!(err.to_string().starts_with("i/o error"), "{}", err);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(message: Scalars) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(message: ScalarArrays) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_complex_type(message: ComplexType) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_well_known_types(message: WellKnownTypes) {
+        roundtrip(&message)?;
+    }
+}
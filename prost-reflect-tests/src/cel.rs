@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use prost_reflect::{
+    cel::{self, Activation, CelKey, CelValue, Program},
+    ReflectMessage,
+};
+
+use crate::proto::{ComplexType, Scalars, WellKnownTypes};
+
+fn eval(expression: &str) -> CelValue {
+    Program::compile(expression)
+        .unwrap()
+        .evaluate(&Activation::new())
+        .unwrap()
+}
+
+fn eval_err(expression: &str) -> String {
+    Program::compile(expression)
+        .unwrap()
+        .evaluate(&Activation::new())
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn literals() {
+    assert_eq!(eval("null"), CelValue::Null);
+    assert_eq!(eval("-9223372036854775808"), CelValue::Int(i64::MIN));
+    assert_eq!(eval("0x1F"), CelValue::Int(31));
+    assert_eq!(eval("18446744073709551615u"), CelValue::Uint(u64::MAX));
+    assert_eq!(eval("1.5e3"), CelValue::Double(1500.0));
+    assert_eq!(eval(r#""a\tb""#), CelValue::from("a\tb"));
+    assert_eq!(eval(r"r'\n'"), CelValue::from("\\n"));
+    assert_eq!(eval("'''a\nb'''"), CelValue::from("a\nb"));
+    assert_eq!(eval("b'\\x01a'"), CelValue::Bytes(b"\x01a".as_ref().into()));
+    assert_eq!(
+        eval("[1, 'a']"),
+        CelValue::List(vec![CelValue::Int(1), CelValue::from("a")])
+    );
+    assert_eq!(
+        eval("{'a': 1, 2: true}"),
+        CelValue::Map(BTreeMap::from([
+            (CelKey::String("a".to_owned()), CelValue::Int(1)),
+            (CelKey::Int(2), CelValue::Bool(true)),
+        ]))
+    );
+}
+
+#[test]
+fn operators() {
+    assert_eq!(eval("1 + 2 * 3 - 4 / 2 % 3"), CelValue::Int(5));
+    assert_eq!(eval("1u + 2u"), CelValue::Uint(3));
+    assert_eq!(eval("'ab' + 'c'"), CelValue::from("abc"));
+    assert_eq!(eval("[1] + [2] == [1, 2]"), CelValue::Bool(true));
+    assert_eq!(eval("1 == 1.0 && 1u < 2"), CelValue::Bool(true));
+    assert_eq!(
+        eval("!(2 in [1, 2]) || 'a' in {'a': 1}"),
+        CelValue::Bool(true)
+    );
+    assert_eq!(eval("1 > 2 ? 'yes' : 'no'"), CelValue::from("no"));
+    assert_eq!(eval("{'a': {'b': 1}}.a.b"), CelValue::Int(1));
+    assert_eq!(eval("[1, 2][1]"), CelValue::Int(2));
+
+    assert_eq!(eval_err("9223372036854775807 + 1"), "integer overflow");
+    assert_eq!(eval_err("1 / 0"), "division by zero");
+    assert_eq!(eval_err("[1][1]"), "index out of range: 1");
+    assert_eq!(eval_err("x"), "undeclared reference to 'x'");
+}
+
+#[test]
+fn errors_absorbed_by_logical_operators() {
+    assert_eq!(eval("false && 1 / 0 == 1"), CelValue::Bool(false));
+    assert_eq!(eval("1 / 0 == 1 || true"), CelValue::Bool(true));
+    assert_eq!(eval_err("1 / 0 == 1 || false"), "division by zero");
+}
+
+#[test]
+fn macros() {
+    assert_eq!(eval("[1, 2, 3].all(x, x > 0)"), CelValue::Bool(true));
+    assert_eq!(eval("[1, 2, 3].exists(x, x > 2)"), CelValue::Bool(true));
+    assert_eq!(
+        eval("[1, 2, 3].exists_one(x, x > 1)"),
+        CelValue::Bool(false)
+    );
+    assert_eq!(eval("[1, 2, 3].map(x, x * 2)"), eval("[2, 4, 6]"));
+    assert_eq!(eval("[1, 2, 3].map(x, x > 1, x * 2)"), eval("[4, 6]"));
+    assert_eq!(eval("[1, 2, 3].filter(x, x % 2 == 1)"), eval("[1, 3]"));
+    assert_eq!(
+        eval("{'a': 1, 'b': 2}.all(k, k != '')"),
+        CelValue::Bool(true)
+    );
+    assert_eq!(eval("has({'a': 1}.a)"), CelValue::Bool(true));
+    assert_eq!(eval("[0, 1].exists(x, 1 / x == 1)"), CelValue::Bool(true));
+}
+
+#[test]
+fn functions() {
+    assert_eq!(eval("size('héllo')"), CelValue::Int(5));
+    assert_eq!(eval("[1, 2].size()"), CelValue::Int(2));
+    assert_eq!(eval("'hello'.contains('ell')"), CelValue::Bool(true));
+    assert_eq!(eval("'hello'.startsWith('he')"), CelValue::Bool(true));
+    assert_eq!(eval("'hello'.endsWith('lo')"), CelValue::Bool(true));
+    assert_eq!(eval("'abc'.matches('^a.c$')"), CelValue::Bool(true));
+    assert_eq!(eval("int('12') + int(2.7)"), CelValue::Int(14));
+    assert_eq!(eval("uint(3)"), CelValue::Uint(3));
+    assert_eq!(eval("double(1)"), CelValue::Double(1.0));
+    assert_eq!(eval("string(1.5)"), CelValue::from("1.5"));
+    assert_eq!(eval("bytes('ab') == b'ab'"), CelValue::Bool(true));
+    assert_eq!(
+        eval("type(1) == int && type('') == string"),
+        CelValue::Bool(true)
+    );
+    assert_eq!(eval_err("uint(-1)"), "integer overflow");
+}
+
+#[test]
+fn durations_and_timestamps() {
+    assert_eq!(
+        eval("duration('1h30m') + duration('30m')"),
+        CelValue::Duration(prost_types::Duration {
+            seconds: 7200,
+            nanos: 0,
+        })
+    );
+    assert_eq!(
+        eval("timestamp('2023-01-01T00:00:00Z') + duration('24h')"),
+        CelValue::Timestamp(prost_types::Timestamp {
+            seconds: 1_672_617_600,
+            nanos: 0,
+        })
+    );
+    assert_eq!(
+        eval("timestamp('2023-01-02T00:00:00Z') - timestamp('2023-01-01T00:00:00Z')"),
+        eval("duration('86400s')")
+    );
+    assert_eq!(
+        eval("timestamp('2023-03-01T10:30:00Z').getDayOfYear()"),
+        CelValue::Int(59)
+    );
+    assert_eq!(
+        eval("timestamp('2023-03-01T10:30:00Z').getHours('+05:30')"),
+        CelValue::Int(16)
+    );
+    assert_eq!(eval("duration('90s').getMinutes()"), CelValue::Int(1));
+}
+
+#[test]
+fn message_fields() {
+    let message = Scalars {
+        int32: -5,
+        uint64: 7,
+        string: "hello".to_owned(),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        cel::evaluate("int32 * 2", &message).unwrap(),
+        CelValue::Int(-10)
+    );
+    assert_eq!(
+        cel::evaluate("uint64 + 1u", &message).unwrap(),
+        CelValue::Uint(8)
+    );
+    assert_eq!(
+        cel::evaluate("size(string) == 5 && !bool", &message).unwrap(),
+        CelValue::Bool(true)
+    );
+
+    let activation = Activation::new().with_variable("msg", message);
+    let program =
+        Program::compile("has(msg.string) && !has(msg.double) && msg.double == 0.0").unwrap();
+    assert_eq!(program.evaluate(&activation).unwrap(), CelValue::Bool(true));
+
+    let program = Program::compile("msg.missing").unwrap();
+    assert_eq!(
+        program.evaluate(&activation).unwrap_err().to_string(),
+        "no such field 'missing' in message 'test.Scalars'"
+    );
+}
+
+#[test]
+fn nested_messages() {
+    let message = ComplexType {
+        string_map: [(
+            "a".to_owned(),
+            Scalars {
+                int32: 1,
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect(),
+        my_enum: vec![1, 2],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        cel::evaluate("string_map['a'].int32", &message).unwrap(),
+        CelValue::Int(1)
+    );
+    assert_eq!(
+        cel::evaluate("string_map.all(k, string_map[k].int32 > 0)", &message).unwrap(),
+        CelValue::Bool(true)
+    );
+    assert_eq!(
+        cel::evaluate("my_enum == [1, 2] && size(int_map) == 0", &message).unwrap(),
+        CelValue::Bool(true)
+    );
+}
+
+#[test]
+fn well_known_types() {
+    let message = WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: 1_672_531_200,
+            nanos: 0,
+        }),
+        duration: Some(prost_types::Duration {
+            seconds: 5,
+            nanos: 0,
+        }),
+        int32: Some(3),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        cel::evaluate("timestamp.getFullYear()", &message).unwrap(),
+        CelValue::Int(2023)
+    );
+    assert_eq!(
+        cel::evaluate("duration > duration('1s')", &message).unwrap(),
+        CelValue::Bool(true)
+    );
+    assert_eq!(
+        cel::evaluate("int32 + 1", &message).unwrap(),
+        CelValue::Int(4)
+    );
+    assert_eq!(
+        cel::evaluate("int64 == null", &message).unwrap(),
+        CelValue::Bool(true)
+    );
+}
+
+#[test]
+fn custom_functions() {
+    let mut activation = Activation::new();
+    activation.add_function("double", |args| match args {
+        [CelValue::Int(value)] => Ok(CelValue::Int(value * 2)),
+        _ => Err("bad arguments".to_owned()),
+    });
+    activation.add_variable("x", 4i64);
+
+    let program = Program::compile("double(x) + x.double()").unwrap();
+    assert_eq!(program.evaluate(&activation).unwrap(), CelValue::Int(16));
+
+    let program = Program::compile("double('a')").unwrap();
+    assert_eq!(
+        program.evaluate(&activation).unwrap_err().to_string(),
+        "bad arguments"
+    );
+}
+
+#[test]
+fn syntax_errors() {
+    let err = Program::compile("1 +").unwrap_err();
+    assert_eq!(err.span(), Some(3..3));
+    assert_eq!(
+        err.to_string(),
+        "syntax error at offset 3: unexpected end of expression"
+    );
+
+    assert!(Program::compile("'unterminated").is_err());
+    assert!(Program::compile("Foo{bar: 1}").is_err());
+    assert!(Program::compile("has(a)").is_err());
+    assert!(Program::compile(&"(".repeat(1000)).is_err());
+}
@@ -0,0 +1,238 @@
+use prost_reflect::{
+    compat::{self, BreakingChange, CompatLevel},
+    DescriptorPool,
+};
+use prost_types::{
+    descriptor_proto::ReservedRange,
+    enum_descriptor_proto::EnumReservedRange,
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+    FileDescriptorProto, OneofDescriptorProto,
+};
+
+fn pool(message: DescriptorProto, enum_: EnumDescriptorProto) -> DescriptorPool {
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_proto(FileDescriptorProto {
+        name: Some("compat.proto".to_owned()),
+        package: Some("compat".to_owned()),
+        syntax: Some("proto3".to_owned()),
+        message_type: vec![message],
+        enum_type: vec![enum_],
+        ..Default::default()
+    })
+    .unwrap();
+    pool
+}
+
+fn message(fields: Vec<FieldDescriptorProto>) -> DescriptorProto {
+    DescriptorProto {
+        name: Some("Foo".to_owned()),
+        field: fields,
+        ..Default::default()
+    }
+}
+
+fn field(name: &str, number: i32, label: Label, ty: Type) -> FieldDescriptorProto {
+    FieldDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        label: Some(label as i32),
+        r#type: Some(ty as i32),
+        ..Default::default()
+    }
+}
+
+fn enum_(values: &[(&str, i32)]) -> EnumDescriptorProto {
+    EnumDescriptorProto {
+        name: Some("Status".to_owned()),
+        value: values
+            .iter()
+            .map(|&(name, number)| EnumValueDescriptorProto {
+                name: Some(name.to_owned()),
+                number: Some(number),
+                options: None,
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+fn messages(changes: &[BreakingChange]) -> Vec<&str> {
+    changes.iter().map(|change| change.message()).collect()
+}
+
+#[test]
+fn unchanged() {
+    let old = pool(
+        message(vec![field("a", 1, Label::Optional, Type::String)]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    assert!(compat::check(&old, &old, CompatLevel::WireJson).is_empty());
+}
+
+#[test]
+fn deleted_fields() {
+    let old = pool(
+        message(vec![
+            field("a", 1, Label::Optional, Type::String),
+            field("b", 2, Label::Optional, Type::String),
+            field("c", 3, Label::Optional, Type::String),
+        ]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    let new = pool(
+        DescriptorProto {
+            reserved_range: vec![ReservedRange {
+                start: Some(2),
+                end: Some(4),
+            }],
+            reserved_name: vec!["c".to_owned()],
+            ..message(vec![field("a", 1, Label::Optional, Type::String)])
+        },
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    assert!(compat::check(&old, &new, CompatLevel::Wire).is_empty());
+
+    let changes = compat::check(&old, &new, CompatLevel::WireJson);
+    assert_eq!(
+        messages(&changes),
+        ["field 'b' (2) of 'compat.Foo' was deleted without reserving its name"]
+    );
+    assert_eq!(changes[0].file(), "compat.proto");
+    assert_eq!(changes[0].path(), &[4, 0]);
+    assert_eq!(changes[0].line(), None);
+    assert!(!changes[0].is_wire_breaking());
+
+    let new = pool(
+        message(vec![field("a", 1, Label::Optional, Type::String)]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    let changes = compat::check(&old, &new, CompatLevel::Wire);
+    assert_eq!(
+        messages(&changes),
+        [
+            "field 'b' (2) of 'compat.Foo' was deleted without reserving its number",
+            "field 'c' (3) of 'compat.Foo' was deleted without reserving its number",
+        ]
+    );
+    assert!(changes[0].is_wire_breaking());
+}
+
+#[test]
+fn changed_fields() {
+    let old = pool(
+        DescriptorProto {
+            oneof_decl: vec![OneofDescriptorProto {
+                name: Some("choice".to_owned()),
+                options: None,
+            }],
+            ..message(vec![
+                field("a", 1, Label::Optional, Type::Int32),
+                field("b", 2, Label::Optional, Type::Int32),
+                field("c", 3, Label::Optional, Type::String),
+                field("d", 4, Label::Repeated, Type::Int64),
+                FieldDescriptorProto {
+                    oneof_index: Some(0),
+                    ..field("e", 5, Label::Optional, Type::Bool)
+                },
+                field("f", 6, Label::Optional, Type::Fixed32),
+            ])
+        },
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    let new = pool(
+        message(vec![
+            field("a", 1, Label::Optional, Type::Uint64),
+            field("renamed", 2, Label::Optional, Type::Int32),
+            field("c", 3, Label::Repeated, Type::Bytes),
+            field("d", 4, Label::Optional, Type::Int64),
+            field("e", 5, Label::Optional, Type::Bool),
+            field("f", 6, Label::Optional, Type::Float),
+        ]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+
+    let changes = compat::check(&old, &new, CompatLevel::Wire);
+    assert_eq!(
+        messages(&changes),
+        [
+            "field 'd' (4) of 'compat.Foo' changed from repeated to optional",
+            "field 'e' (5) of 'compat.Foo' was moved out of the oneof 'choice'",
+            "field 'f' (6) of 'compat.Foo' changed type from 'fixed32' to 'float'",
+        ]
+    );
+    assert_eq!(changes[0].path(), &[4, 0, 2, 3]);
+
+    let changes = compat::check(&old, &new, CompatLevel::WireJson);
+    assert_eq!(
+        messages(&changes),
+        [
+            "field 'a' (1) of 'compat.Foo' changed type from 'int32' to 'uint64'",
+            "field 'b' (2) of 'compat.Foo' changed name to 'renamed'",
+            "field 'c' (3) of 'compat.Foo' changed type from 'string' to 'bytes'",
+            "field 'c' (3) of 'compat.Foo' changed from optional to repeated",
+            "field 'd' (4) of 'compat.Foo' changed from repeated to optional",
+            "field 'e' (5) of 'compat.Foo' was moved out of the oneof 'choice'",
+            "field 'f' (6) of 'compat.Foo' changed type from 'fixed32' to 'float'",
+        ]
+    );
+}
+
+#[test]
+fn changed_json_name() {
+    let old = pool(
+        message(vec![field("a", 1, Label::Optional, Type::String)]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    let new = pool(
+        message(vec![FieldDescriptorProto {
+            json_name: Some("b".to_owned()),
+            ..field("a", 1, Label::Optional, Type::String)
+        }]),
+        enum_(&[("STATUS_UNSPECIFIED", 0)]),
+    );
+    assert!(compat::check(&old, &new, CompatLevel::Wire).is_empty());
+    assert_eq!(
+        messages(&compat::check(&old, &new, CompatLevel::WireJson)),
+        ["field 'a' (1) of 'compat.Foo' changed JSON name from 'a' to 'b'"]
+    );
+}
+
+#[test]
+fn changed_enums() {
+    let old = pool(
+        message(vec![]),
+        enum_(&[
+            ("STATUS_UNSPECIFIED", 0),
+            ("STATUS_OK", 1),
+            ("STATUS_ERROR", 2),
+        ]),
+    );
+    let new = pool(
+        message(vec![]),
+        EnumDescriptorProto {
+            reserved_range: vec![EnumReservedRange {
+                start: Some(2),
+                end: Some(2),
+            }],
+            ..enum_(&[("STATUS_UNSPECIFIED", 0), ("STATUS_SUCCESS", 1)])
+        },
+    );
+    assert!(compat::check(&old, &new, CompatLevel::Wire).is_empty());
+    assert_eq!(
+        messages(&compat::check(&old, &new, CompatLevel::WireJson)),
+        [
+            "enum value 'STATUS_OK' (1) of 'compat.Status' changed name to 'STATUS_SUCCESS'",
+            "enum value 'STATUS_ERROR' (2) of 'compat.Status' was deleted without reserving its name",
+        ]
+    );
+
+    let new = pool(message(vec![]), enum_(&[("STATUS_UNSPECIFIED", 0)]));
+    assert_eq!(
+        messages(&compat::check(&old, &new, CompatLevel::Wire)),
+        [
+            "enum value 'STATUS_OK' (1) of 'compat.Status' was deleted without reserving its number",
+            "enum value 'STATUS_ERROR' (2) of 'compat.Status' was deleted without reserving its number",
+        ]
+    );
+}
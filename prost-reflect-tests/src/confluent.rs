@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use prost::Message;
+use prost_reflect::{
+    confluent::{self, Header},
+    DynamicMessage, ReflectMessage, Value,
+};
+
+use crate::{
+    proto::{Point, Scalars},
+    test_file_descriptor,
+};
+
+fn resolver() -> HashMap<u32, prost_reflect::FileDescriptor> {
+    let pool = test_file_descriptor();
+    HashMap::from([
+        (1, pool.get_file_by_name("test.proto").unwrap()),
+        (
+            2,
+            pool.get_file_by_name("google/protobuf/descriptor.proto")
+                .unwrap(),
+        ),
+    ])
+}
+
+#[test]
+fn first_message() {
+    let message = Scalars {
+        int32: 5,
+        ..Default::default()
+    };
+    let bytes = confluent::encode_to_vec(1, &message.transcode_to_dynamic());
+    assert_eq!(bytes[..6], [0, 0, 0, 0, 1, 0]);
+    assert_eq!(bytes[6..], message.encode_to_vec());
+
+    let decoded = confluent::decode(&resolver(), bytes.as_slice()).unwrap();
+    assert_eq!(decoded.transcode_to::<Scalars>().unwrap(), message);
+}
+
+#[test]
+fn top_level_message() {
+    let message = Point {
+        latitude: 1,
+        longitude: -1,
+    };
+    let bytes = confluent::encode_to_vec(0x01020304, &message.transcode_to_dynamic());
+    // The path [5] is written as a count of 1 followed by the index, both zigzag encoded.
+    assert_eq!(bytes[..7], [0, 1, 2, 3, 4, 2, 10]);
+
+    let header = Header::decode(&mut bytes.as_slice()).unwrap();
+    assert_eq!(header.schema_id(), 0x01020304);
+    assert_eq!(header.message_indexes(), [5]);
+    assert_eq!(header.encoded_len(), 7);
+
+    let mut resolver = resolver();
+    resolver.insert(0x01020304, resolver[&1].clone());
+    let decoded = confluent::decode(&resolver, bytes.as_slice()).unwrap();
+    assert_eq!(decoded.transcode_to::<Point>().unwrap(), message);
+}
+
+#[test]
+fn nested_message() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.DescriptorProto.ExtensionRange")
+        .unwrap();
+    let mut message = DynamicMessage::new(desc.clone());
+    message.set_field_by_name("start", Value::I32(3));
+
+    let header = Header::new(2, &desc);
+    assert_eq!(header.message_indexes(), [2, 0]);
+
+    let bytes = confluent::encode_to_vec(2, &message);
+    assert_eq!(bytes[..8], [0, 0, 0, 0, 2, 4, 4, 0]);
+
+    let resolver = |schema_id: u32| resolver().remove(&schema_id);
+    assert_eq!(
+        confluent::decode(&resolver, bytes.as_slice()).unwrap(),
+        message
+    );
+}
+
+#[test]
+fn decode_errors() {
+    let error = |bytes: &[u8]| {
+        confluent::decode(&resolver(), bytes)
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(
+        error(b""),
+        "expected the magic byte, but the buffer is empty"
+    );
+    assert_eq!(
+        error(b"\x01\x00\x00\x00\x01\x00"),
+        "expected the magic byte 0, but found 1"
+    );
+    assert_eq!(error(b"\x00\x00\x00"), "invalid wire format header");
+    assert_eq!(
+        error(b"\x00\x00\x00\x00\x01\x01"),
+        "invalid wire format header"
+    );
+    assert_eq!(
+        error(b"\x00\x00\x00\x00\x01\x04\x02"),
+        "invalid wire format header"
+    );
+    assert_eq!(error(b"\x00\x00\x00\x00\x03\x00"), "schema 3 was not found");
+    assert_eq!(
+        error(b"\x00\x00\x00\x00\x01\x02\x64"),
+        "message index path [50] does not match a message in schema 1"
+    );
+    assert_eq!(
+        error(b"\x00\x00\x00\x00\x01\x00\x18"),
+        "failed to decode message: failed to decode Protobuf message: invalid varint"
+    );
+}
@@ -0,0 +1,339 @@
+use std::{collections::HashMap, iter::FromIterator};
+
+use prost_reflect::{
+    csv::{self, CsvOptions, RepeatedFieldPolicy},
+    DynamicMessage, ReflectMessage,
+};
+
+use crate::{
+    proto::{ComplexType, Point, ScalarArrays, Scalars, WellKnownTypes},
+    test_file_descriptor,
+};
+
+fn to_csv<T>(messages: &[T], options: &CsvOptions) -> String
+where
+    T: ReflectMessage + Default,
+{
+    let messages: Vec<DynamicMessage> = messages
+        .iter()
+        .map(ReflectMessage::transcode_to_dynamic)
+        .collect();
+    let mut buf = Vec::new();
+    csv::to_writer(&mut buf, &T::default().descriptor(), &messages, options).unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+fn from_csv<T>(data: &str, options: &CsvOptions) -> Vec<T>
+where
+    T: ReflectMessage + prost::Message + Default,
+{
+    csv::from_reader(data.as_bytes(), T::default().descriptor(), options)
+        .unwrap()
+        .into_iter()
+        .map(|message| message.transcode_to().unwrap())
+        .collect()
+}
+
+fn complex_type() -> ComplexType {
+    ComplexType {
+        string_map: HashMap::from_iter([(
+            "a".to_owned(),
+            Scalars {
+                int32: 1,
+                ..Default::default()
+            },
+        )]),
+        int_map: HashMap::from_iter([(2, Scalars::default()), (-1, Scalars::default())]),
+        nested: None,
+        my_enum: vec![1, 5],
+        optional_enum: 3,
+    }
+}
+
+fn point_arrays() -> ScalarArrays {
+    ScalarArrays {
+        int32: vec![1, 2],
+        string: vec!["a".to_owned()],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn headers_nested() {
+    let headers = csv::headers(
+        &ComplexType::default().descriptor(),
+        &[],
+        &CsvOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(headers.len(), 49);
+    assert_eq!(headers[..2], ["string_map.key", "string_map.value.double"]);
+    assert_eq!(headers[32..34], ["nested.double", "nested.float"]);
+    assert_eq!(headers[47..], ["my_enum", "optional_enum"]);
+}
+
+#[test]
+fn headers_well_known_types() {
+    // google.protobuf.Struct contains itself through google.protobuf.Value.
+    let err = csv::headers(
+        &WellKnownTypes::default().descriptor(),
+        &[],
+        &CsvOptions::new(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "message 'google.protobuf.Struct' is recursive, so it cannot be flattened into columns"
+    );
+}
+
+#[test]
+fn headers_recursive() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.DescriptorProto")
+        .unwrap();
+    let err = csv::headers(&desc, &[], &CsvOptions::new()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "message 'google.protobuf.DescriptorProto' is recursive, so it cannot be flattened into columns"
+    );
+}
+
+#[test]
+fn wrong_message_type() {
+    let mut buf = Vec::new();
+    let err = csv::to_writer(
+        &mut buf,
+        &Scalars::default().descriptor(),
+        &[Point::default().transcode_to_dynamic()],
+        &CsvOptions::new(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected messages of type 'test.Scalars', but found 'test.Point'"
+    );
+}
+
+#[test]
+fn write_scalars() {
+    let message = Scalars {
+        double: f64::INFINITY,
+        float: 1.5,
+        int64: -4,
+        uint64: u64::MAX,
+        r#bool: true,
+        string: "a,\"b\"".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_csv(&[message], &CsvOptions::new().delimiter(b'\t')),
+        "double\tfloat\tint32\tint64\tuint32\tuint64\tsint32\tsint64\tfixed32\tfixed64\tsfixed32\tsfixed64\tbool\tstring\tbytes\n\
+         Infinity\t1.5\t0\t-4\t0\t18446744073709551615\t0\t0\t0\t0\t0\t0\ttrue\t\"a,\"\"b\"\"\"\tAP8=\n"
+    );
+}
+
+#[test]
+fn write_join() {
+    assert_eq!(
+        to_csv(&[complex_type()], &CsvOptions::new().join_separator('|')),
+        "string_map.key,string_map.value.double,string_map.value.float,string_map.value.int32,\
+         string_map.value.int64,string_map.value.uint32,string_map.value.uint64,string_map.value.sint32,\
+         string_map.value.sint64,string_map.value.fixed32,string_map.value.fixed64,string_map.value.sfixed32,\
+         string_map.value.sfixed64,string_map.value.bool,string_map.value.string,string_map.value.bytes,\
+         int_map.key,int_map.value.double,int_map.value.float,int_map.value.int32,int_map.value.int64,\
+         int_map.value.uint32,int_map.value.uint64,int_map.value.sint32,int_map.value.sint64,\
+         int_map.value.fixed32,int_map.value.fixed64,int_map.value.sfixed32,int_map.value.sfixed64,\
+         int_map.value.bool,int_map.value.string,int_map.value.bytes,nested.double,nested.float,\
+         nested.int32,nested.int64,nested.uint32,nested.uint64,nested.sint32,nested.sint64,nested.fixed32,\
+         nested.fixed64,nested.sfixed32,nested.sfixed64,nested.bool,nested.string,nested.bytes,my_enum,\
+         optional_enum\n\
+         a,0,0,1,0,0,0,0,0,0,0,0,0,false,,,-1|2,0|0,0|0,0|0,0|0,0|0,0|0,0|0,0|0,0|0,0|0,0|0,0|0,false|false,|,|,\
+         ,,,,,,,,,,,,,,,FOO|5,BAR\n"
+    );
+}
+
+#[test]
+fn write_explode() {
+    let options = CsvOptions::new().repeated_fields(RepeatedFieldPolicy::Explode);
+    let data = to_csv(&[point_arrays(), ScalarArrays::default()], &options);
+    let mut lines = data.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "double,float,int32,int64,uint32,uint64,sint32,sint64,fixed32,fixed64,sfixed32,sfixed64,bool,string,bytes"
+    );
+    assert_eq!(lines.next().unwrap(), ",,1,,,,,,,,,,,a,");
+    assert_eq!(lines.next().unwrap(), ",,2,,,,,,,,,,,a,");
+    assert_eq!(lines.next().unwrap(), ",,,,,,,,,,,,,,");
+    assert_eq!(lines.next(), None);
+
+    let message = ComplexType {
+        my_enum: vec![0, 1],
+        int_map: HashMap::from_iter([(3, Scalars::default()), (4, Scalars::default())]),
+        ..Default::default()
+    };
+    let data = to_csv(&[message], &options);
+    let rows: Vec<(&str, &str)> = data
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let cells: Vec<&str> = line.split(',').collect();
+            (cells[16], cells[47])
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("3", "DEFAULT"),
+            ("3", "FOO"),
+            ("4", "DEFAULT"),
+            ("4", "FOO")
+        ]
+    );
+}
+
+#[test]
+fn write_index() {
+    let options = CsvOptions::new().repeated_fields(RepeatedFieldPolicy::Index);
+    let other = ScalarArrays {
+        int32: vec![3],
+        string: vec!["b".to_owned(), "c".to_owned(), "d".to_owned()],
+        ..Default::default()
+    };
+    assert_eq!(
+        to_csv(&[point_arrays(), other], &options),
+        "int32.0,int32.1,string.0,string.1,string.2\n1,2,a,,\n3,,b,c,d\n"
+    );
+
+    let data = to_csv(&[complex_type()], &options);
+    let headers: Vec<&str> = data.lines().next().unwrap().split(',').collect();
+    assert_eq!(
+        headers[..2],
+        ["string_map.0.key", "string_map.0.value.double"]
+    );
+    assert_eq!(headers[16..18], ["int_map.0.key", "int_map.0.value.double"]);
+    assert_eq!(headers[32..34], ["int_map.1.key", "int_map.1.value.double"]);
+    assert_eq!(
+        headers[48..],
+        [
+            "nested.double",
+            "nested.float",
+            "nested.int32",
+            "nested.int64",
+            "nested.uint32",
+            "nested.uint64",
+            "nested.sint32",
+            "nested.sint64",
+            "nested.fixed32",
+            "nested.fixed64",
+            "nested.sfixed32",
+            "nested.sfixed64",
+            "nested.bool",
+            "nested.string",
+            "nested.bytes",
+            "my_enum.0",
+            "my_enum.1",
+            "optional_enum"
+        ]
+    );
+}
+
+#[test]
+fn roundtrip_join() {
+    let messages = vec![point_arrays(), ScalarArrays::default()];
+    let options = CsvOptions::new();
+    assert_eq!(
+        from_csv::<ScalarArrays>(&to_csv(&messages, &options), &options),
+        messages
+    );
+}
+
+#[test]
+fn roundtrip_index() {
+    let messages = vec![
+        ComplexType {
+            nested: Some(Scalars {
+                double: 1.5,
+                bytes: b"\x01".to_vec(),
+                ..Default::default()
+            }),
+            my_enum: vec![1, 5],
+            optional_enum: 3,
+            ..Default::default()
+        },
+        ComplexType::default(),
+    ];
+    let options = CsvOptions::new().repeated_fields(RepeatedFieldPolicy::Index);
+    let data = to_csv(&messages, &options);
+    assert_eq!(from_csv::<ComplexType>(&data, &options), messages);
+}
+
+#[test]
+fn read_partial_columns() {
+    let data = "latitude\n5\n\n-3\n";
+    assert_eq!(
+        from_csv::<Point>(data, &CsvOptions::new()),
+        [
+            Point {
+                latitude: 5,
+                longitude: 0
+            },
+            Point {
+                latitude: -3,
+                longitude: 0
+            }
+        ]
+    );
+}
+
+#[test]
+fn read_errors() {
+    let error = |data: &str, options: &CsvOptions| {
+        csv::from_reader(
+            data.as_bytes(),
+            ComplexType::default().descriptor(),
+            options,
+        )
+        .unwrap_err()
+        .to_string()
+    };
+    let options = CsvOptions::new();
+
+    assert_eq!(
+        error("bogus\n", &options),
+        "column 'bogus' does not match a field"
+    );
+    assert_eq!(
+        error("nested\n", &options),
+        "column 'nested' does not match a field"
+    );
+    assert_eq!(
+        error("nested.int32.value\n", &options),
+        "column 'nested.int32.value' does not match a field"
+    );
+    assert_eq!(
+        error("string_map.key\n", &options),
+        "column 'string_map.key' cannot be read back into a message"
+    );
+    assert_eq!(
+        error(
+            "my_enum\n",
+            &options
+                .clone()
+                .repeated_fields(RepeatedFieldPolicy::Explode)
+        ),
+        "column 'my_enum' cannot be read back into a message"
+    );
+    assert_eq!(
+        error(
+            "my_enum.x\n",
+            &options.clone().repeated_fields(RepeatedFieldPolicy::Index)
+        ),
+        "column 'my_enum.x' does not match a field"
+    );
+    assert_eq!(
+        error("nested.int32\nfoo\n", &options),
+        "invalid value 'foo' in column 'nested.int32'"
+    );
+}
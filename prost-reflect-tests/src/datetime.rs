@@ -0,0 +1,195 @@
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use prost_reflect::{duration, DynamicMessage, ReflectMessage, Value};
+use time::{macros::datetime, OffsetDateTime};
+
+use crate::proto::Point;
+
+fn timestamp(seconds: i64, nanos: i32) -> DynamicMessage {
+    prost_types::Timestamp { seconds, nanos }.transcode_to_dynamic()
+}
+
+fn duration(seconds: i64, nanos: i32) -> DynamicMessage {
+    prost_types::Duration { seconds, nanos }.transcode_to_dynamic()
+}
+
+#[test]
+fn system_time() {
+    let time = UNIX_EPOCH + Duration::new(1_600_000_000, 5);
+    let message = DynamicMessage::try_from(time).unwrap();
+    assert_eq!(message, timestamp(1_600_000_000, 5));
+    assert_eq!(SystemTime::try_from(&message).unwrap(), time);
+
+    let time = UNIX_EPOCH - Duration::new(1, 250_000_000);
+    let message = DynamicMessage::try_from(time).unwrap();
+    assert_eq!(message, timestamp(-2, 750_000_000));
+    assert_eq!(SystemTime::try_from(&message).unwrap(), time);
+}
+
+#[test]
+fn std_duration() {
+    let message = DynamicMessage::try_from(Duration::new(3, 7)).unwrap();
+    assert_eq!(message, duration(3, 7));
+    assert_eq!(Duration::try_from(&message).unwrap(), Duration::new(3, 7));
+
+    let err = Duration::try_from(&duration(-1, 0)).unwrap_err();
+    assert_eq!(err.to_string(), "duration is out of range");
+    let err = DynamicMessage::try_from(Duration::from_secs(315_576_000_001)).unwrap_err();
+    assert_eq!(err.to_string(), "duration is out of range");
+}
+
+#[test]
+fn chrono_date_time() {
+    let time = Utc.timestamp_opt(-62_135_596_800, 0).unwrap();
+    let message = DynamicMessage::try_from(time).unwrap();
+    assert_eq!(message, timestamp(-62_135_596_800, 0));
+    assert_eq!(chrono::DateTime::<Utc>::try_from(&message).unwrap(), time);
+
+    let err = DynamicMessage::try_from(time - chrono::Duration::nanoseconds(1)).unwrap_err();
+    assert_eq!(err.to_string(), "timestamp is out of range");
+
+    let leap_second = NaiveDate::from_ymd_opt(2016, 12, 31)
+        .unwrap()
+        .and_hms_nano_opt(23, 59, 59, 1_500_000_000)
+        .unwrap()
+        .and_utc();
+    let message = DynamicMessage::try_from(leap_second).unwrap();
+    assert_eq!(message, timestamp(1_483_228_799, 999_999_999));
+}
+
+#[test]
+fn chrono_duration() {
+    let value = chrono::Duration::milliseconds(-1_500);
+    let message = DynamicMessage::try_from(value).unwrap();
+    assert_eq!(message, duration(-1, -500_000_000));
+    assert_eq!(chrono::Duration::try_from(&message).unwrap(), value);
+}
+
+#[test]
+fn time_offset_date_time() {
+    let time = datetime!(9999-12-31 23:59:59.999_999_999 UTC);
+    let message = DynamicMessage::try_from(time).unwrap();
+    assert_eq!(message, timestamp(253_402_300_799, 999_999_999));
+    assert_eq!(OffsetDateTime::try_from(&message).unwrap(), time);
+
+    let err =
+        DynamicMessage::try_from(datetime!(2000-01-01 0:00 UTC) + time::Duration::days(3_000_000))
+            .unwrap_err();
+    assert_eq!(err.to_string(), "timestamp is out of range");
+}
+
+#[test]
+fn time_duration() {
+    let value = time::Duration::new(-5, -3);
+    let message = DynamicMessage::try_from(value).unwrap();
+    assert_eq!(message, duration(-5, -3));
+    assert_eq!(time::Duration::try_from(&message).unwrap(), value);
+}
+
+#[test]
+fn values() {
+    let value = Value::try_from(Duration::from_millis(10)).unwrap();
+    assert_eq!(value, Value::Message(duration(0, 10_000_000)));
+    assert_eq!(
+        Duration::try_from(&value).unwrap(),
+        Duration::from_millis(10)
+    );
+
+    let err = Duration::try_from(&Value::I32(1)).unwrap_err();
+    assert_eq!(err.to_string(), "expected a message value");
+}
+
+#[test]
+fn invalid_messages() {
+    let err = SystemTime::try_from(&duration(1, 0)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a message of type 'google.protobuf.Timestamp', but found 'google.protobuf.Duration'"
+    );
+    let err = Duration::try_from(&Point::default().transcode_to_dynamic()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a message of type 'google.protobuf.Duration', but found 'test.Point'"
+    );
+
+    let err = SystemTime::try_from(&timestamp(0, -1)).unwrap_err();
+    assert_eq!(err.to_string(), "timestamp is out of range");
+    let err = chrono::Duration::try_from(&duration(1, -1)).unwrap_err();
+    assert_eq!(err.to_string(), "duration is out of range");
+}
+
+fn duration_message(seconds: i64, nanos: i32) -> DynamicMessage {
+    prost_types::Duration { seconds, nanos }.transcode_to_dynamic()
+}
+
+#[test]
+fn parse_duration() {
+    for (s, seconds, nanos) in [
+        ("0", 0, 0),
+        ("0s", 0, 0),
+        ("3.5s", 3, 500_000_000),
+        ("-3.000000001s", -3, -1),
+        ("+1.5h", 5400, 0),
+        ("1h2m3.25s", 3723, 250_000_000),
+        ("250ms", 0, 250_000_000),
+        ("1.5us", 0, 1_500),
+        ("2µs", 0, 2_000),
+        ("7ns", 0, 7),
+        ("1.0000000019s", 1, 1),
+        ("315576000000s", 315_576_000_000, 0),
+    ] {
+        assert_eq!(
+            duration::parse(s).unwrap(),
+            duration_message(seconds, nanos),
+            "{}",
+            s
+        );
+    }
+
+    for s in ["", "-", "s", "1", "1.s5", "3x", "1h-2m", "."] {
+        assert_eq!(
+            duration::parse(s).unwrap_err().to_string(),
+            format!("invalid duration '{}'", s)
+        );
+    }
+    assert_eq!(
+        duration::parse("315576000001s").unwrap_err().to_string(),
+        "duration is out of range"
+    );
+}
+
+#[test]
+fn format_duration() {
+    for (seconds, nanos, canonical, humanized) in [
+        (0, 0, "0s", "0s"),
+        (3, 500_000_000, "3.500s", "3.5s"),
+        (-3723, -250_000_000, "-3723.250s", "-1h2m3.25s"),
+        (3600, 0, "3600s", "1h"),
+        (3601, 0, "3601s", "1h1s"),
+        (0, 250_000_000, "0.250s", "250ms"),
+        (0, 1_500, "0.000001500s", "1.5µs"),
+        (0, 7, "0.000000007s", "7ns"),
+    ] {
+        let message = duration_message(seconds, nanos);
+        assert_eq!(duration::format(&message).unwrap(), canonical);
+        assert_eq!(duration::format_humanized(&message).unwrap(), humanized);
+        assert_eq!(duration::parse(humanized).unwrap(), message);
+        assert_eq!(duration::parse(canonical).unwrap(), message);
+    }
+}
+
+#[test]
+fn duration_value_helpers() {
+    let value = Value::parse_duration("90s").unwrap();
+    assert_eq!(value, Value::Message(duration_message(90, 0)));
+    assert_eq!(value.format_duration().unwrap(), "90s");
+    assert_eq!(value.format_duration_humanized().unwrap(), "1m30s");
+    assert_eq!(
+        Value::I32(1).format_duration().unwrap_err().to_string(),
+        "expected a message value"
+    );
+}
@@ -2,13 +2,17 @@
 
 use std::{
     collections::{BTreeMap, HashMap},
+    error::Error,
     fmt::Debug,
     iter::FromIterator,
 };
 
 use proptest::{prelude::*, test_runner::TestCaseError};
-use prost::{bytes::Bytes, Message};
-use prost_reflect::{DynamicMessage, MapKey, ReflectMessage, Value};
+use prost::{bytes::Bytes, encoding::WireType, Message};
+use prost_reflect::{
+    scan_field_numbers, verify, DecodeOptions, DynamicMessage, EncodeInterceptor, EncodeOptions,
+    ListEncoding, MapKey, ReflectMessage, SizePrefix, Utf8Policy, Value, VerifyOptions,
+};
 use prost_types::FileDescriptorSet;
 
 use crate::{
@@ -918,3 +922,1043 @@ where
 
     Ok(())
 }
+
+#[test]
+fn packed_fields_accept_unpacked_bytes() {
+    let desc = ScalarArrays::default().descriptor();
+    let int32 = desc.get_field_by_name("int32").unwrap();
+    let double = desc.get_field_by_name("double").unwrap();
+    assert!(int32.is_packed());
+
+    let (message, report) =
+        DynamicMessage::decode_with_report(desc.clone(), b"\x18\x01\x18\x96\x01".as_ref()).unwrap();
+    assert_eq!(
+        message.get_field(&int32).as_ref(),
+        &Value::List(vec![Value::I32(1), Value::I32(150)])
+    );
+    assert_eq!(report.list_encoding(&int32), Some(ListEncoding::Unpacked));
+    assert_eq!(report.list_encoding(&double), None);
+    assert!(report.has_mismatched_encoding(&desc));
+}
+
+#[test]
+fn decoded_scalar_lists() {
+    let scalar_arrays = ScalarArrays {
+        double: vec![1.5],
+        float: vec![2.5, -0.0],
+        int32: vec![1, 150, -1],
+        int64: vec![i64::MIN],
+        uint32: vec![u32::MAX, 0],
+        uint64: vec![3; 100],
+        sint32: vec![-4],
+        sint64: vec![-2, 3],
+        fixed32: vec![4, 5, 6, 7, 8],
+        fixed64: vec![9],
+        sfixed32: vec![-10, 11],
+        sfixed64: vec![-12],
+        bool: vec![true, false],
+        ..Default::default()
+    };
+    let mut message = scalar_arrays.transcode_to_dynamic();
+
+    let mut expected = DynamicMessage::new(message.descriptor());
+    for field in message.descriptor().fields() {
+        let value = message.get_field(&field).into_owned();
+        assert!(value.as_list().is_some());
+        expected.set_field(&field, value);
+    }
+    assert_eq!(message, expected);
+    assert_eq!(expected, message);
+    assert_eq!(message.encode_to_vec(), scalar_arrays.encode_to_vec());
+    assert_eq!(
+        message.transcode_to::<ScalarArrays>().unwrap(),
+        scalar_arrays
+    );
+
+    message
+        .get_field_by_name_mut("int32")
+        .unwrap()
+        .as_list_mut()
+        .unwrap()
+        .push(Value::I32(2));
+    message
+        .merge(
+            ScalarArrays {
+                int32: vec![3],
+                sint64: vec![4],
+                ..Default::default()
+            }
+            .encode_to_vec()
+            .as_slice(),
+        )
+        .unwrap();
+    assert_eq!(
+        message.get_field_by_name("int32").unwrap().as_ref(),
+        &Value::List(vec![
+            Value::I32(1),
+            Value::I32(150),
+            Value::I32(-1),
+            Value::I32(2),
+            Value::I32(3),
+        ])
+    );
+    assert_eq!(
+        message.get_field_by_name("sint64").unwrap().as_ref(),
+        &Value::List(vec![Value::I64(-2), Value::I64(3), Value::I64(4)])
+    );
+    assert_ne!(message, expected);
+}
+
+#[test]
+fn mixed_packed_and_unpacked_bytes() {
+    let desc = ScalarArrays::default().descriptor();
+    let int32 = desc.get_field_by_name("int32").unwrap();
+
+    let (message, report) = DynamicMessage::decode_with_report(
+        desc.clone(),
+        b"\x18\x01\x1a\x03\x02\x96\x01\x18\x04".as_ref(),
+    )
+    .unwrap();
+    assert_eq!(
+        message.get_field(&int32).as_ref(),
+        &Value::List(vec![
+            Value::I32(1),
+            Value::I32(2),
+            Value::I32(150),
+            Value::I32(4)
+        ])
+    );
+    assert_eq!(report.list_encoding(&int32), Some(ListEncoding::Mixed));
+    assert!(report.has_mismatched_encoding(&desc));
+}
+
+#[test]
+fn decode_report_unpacked_field() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.UnpackedScalarArray")
+        .unwrap();
+    let field = desc.get_field_by_name("unpacked_double").unwrap();
+
+    let (_, report) = DynamicMessage::decode_with_report(
+        desc.clone(),
+        b"\xd1\x02\x00\x00\x00\x00\x00\x00\xf0\x3f".as_ref(),
+    )
+    .unwrap();
+    assert_eq!(report.list_encoding(&field), Some(ListEncoding::Unpacked));
+    assert!(!report.has_mismatched_encoding(&desc));
+
+    let (message, report) = DynamicMessage::decode_with_report(
+        desc.clone(),
+        b"\xd2\x02\x08\x00\x00\x00\x00\x00\x00\xf0\x3f".as_ref(),
+    )
+    .unwrap();
+    assert_eq!(
+        message.get_field(&field).as_ref(),
+        &Value::List(vec![Value::F64(1.0)])
+    );
+    assert_eq!(report.list_encoding(&field), Some(ListEncoding::Packed));
+    assert!(report.has_mismatched_encoding(&desc));
+}
+
+#[test]
+fn scan_field_numbers_top_level() {
+    let message = ComplexType {
+        string_map: HashMap::from_iter([("a".to_owned(), Scalars::default())]),
+        nested: Some(Scalars {
+            int32: 5,
+            ..Default::default()
+        }),
+        my_enum: vec![0, 1],
+        optional_enum: 3,
+        ..Default::default()
+    };
+    let bytes = message.encode_to_vec();
+
+    assert_eq!(
+        scan_field_numbers(bytes.as_slice()).collect::<Vec<_>>(),
+        vec![
+            (1, WireType::LengthDelimited),
+            (3, WireType::LengthDelimited),
+            (4, WireType::LengthDelimited),
+            (5, WireType::Varint),
+        ]
+    );
+}
+
+#[test]
+fn scan_field_numbers_group() {
+    let bytes = ContainsGroup {
+        requiredgroup: Some(contains_group::RequiredGroup {
+            a: "foo".to_owned(),
+            b: None,
+        }),
+        ..Default::default()
+    }
+    .encode_to_vec();
+
+    assert_eq!(
+        scan_field_numbers(bytes.as_slice()).collect::<Vec<_>>(),
+        vec![(1, WireType::StartGroup)]
+    );
+}
+
+#[test]
+fn scan_field_numbers_invalid() {
+    // Field 1 (varint), then field 2 (length-delimited) with a length exceeding the buffer.
+    let bytes = b"\x08\x01\x12\x05\x01";
+    assert_eq!(
+        scan_field_numbers(bytes.as_ref()).collect::<Vec<_>>(),
+        vec![(1, WireType::Varint)]
+    );
+    assert_eq!(scan_field_numbers(b"\x00".as_ref()).count(), 0);
+}
+
+#[test]
+fn encode_interceptor_stamps_field() {
+    struct StampString(&'static str);
+
+    impl EncodeInterceptor for StampString {
+        fn before_encode(
+            &self,
+            message: &mut DynamicMessage,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            message.try_set_field_by_name("string", Value::String(self.0.to_owned()))?;
+            Ok(())
+        }
+    }
+
+    let message = Scalars {
+        int32: 5,
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let mut stamped = message.clone();
+    let bytes = stamped
+        .encode_to_vec_with_interceptor(&StampString("trace-id"))
+        .unwrap();
+    let expected = Scalars {
+        int32: 5,
+        string: "trace-id".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(Scalars::decode(bytes.as_slice()).unwrap(), expected);
+    assert_eq!(stamped.transcode_to::<Scalars>().unwrap(), expected);
+    assert_eq!(
+        message.get_field_by_name("string").unwrap().as_str(),
+        Some("")
+    );
+}
+
+#[test]
+fn encode_interceptor_rejects_message() {
+    let mut message = Scalars::default().transcode_to_dynamic();
+
+    let require_int32 = |message: &mut DynamicMessage| {
+        if message.has_field_by_name("int32") {
+            Ok(())
+        } else {
+            Err("int32 is required")
+        }
+    };
+    assert_eq!(
+        message
+            .encode_to_vec_with_interceptor(&require_int32)
+            .unwrap_err()
+            .to_string(),
+        "int32 is required"
+    );
+}
+
+#[test]
+fn encode_interceptor_registered_on_pool() {
+    let mut pool = test_file_descriptor();
+    pool.add_encode_interceptor(|message: &mut DynamicMessage| {
+        message.try_set_field_by_name("int32", Value::I32(1))
+    });
+    pool.add_encode_interceptor(|message: &mut DynamicMessage| {
+        match message.get_field_by_name("int32").unwrap().as_i32() {
+            Some(1) => Ok(()),
+            _ => Err("interceptors ran out of order"),
+        }
+    });
+
+    let mut message = DynamicMessage::new(pool.get_message_by_name("test.Scalars").unwrap());
+    let bytes = message.encode_to_vec_intercepted().unwrap();
+    assert_eq!(
+        Scalars::decode(bytes.as_slice()).unwrap(),
+        Scalars {
+            int32: 1,
+            ..Default::default()
+        }
+    );
+
+    let mut message = DynamicMessage::new(pool.get_message_by_name("test.Scalars").unwrap());
+    let reject = |_: &mut DynamicMessage| Err("rejected");
+    assert_eq!(
+        message
+            .encode_to_vec_with_interceptor(&reject)
+            .unwrap_err()
+            .to_string(),
+        "rejected"
+    );
+    assert_eq!(
+        message.get_field_by_name("int32").unwrap().as_i32(),
+        Some(1)
+    );
+}
+
+#[test]
+fn encode_interceptor_nested_messages() {
+    let mut pool = test_file_descriptor();
+    pool.add_encode_interceptor(|message: &mut DynamicMessage| {
+        if message.descriptor().full_name() == "test.Scalars" {
+            message.set_field_by_name("int32", Value::I32(1));
+        }
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    });
+
+    let desc = pool.get_message_by_name("test.ComplexType").unwrap();
+    let complex = ComplexType {
+        string_map: HashMap::from_iter([("a".to_owned(), Scalars::default())]),
+        nested: Some(Scalars::default()),
+        ..Default::default()
+    };
+    let mut message = DynamicMessage::decode(desc, complex.encode_to_vec().as_slice()).unwrap();
+
+    // Encoding without interceptors leaves the message unchanged.
+    assert_eq!(message.encode_to_vec(), complex.encode_to_vec());
+
+    let set_string = |message: &mut DynamicMessage| {
+        if message.descriptor().full_name() == "test.Scalars" {
+            message.set_field_by_name("string", Value::String("s".to_owned()));
+        }
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    };
+    let bytes = message.encode_to_vec_with_interceptor(&set_string).unwrap();
+
+    let expected = Scalars {
+        int32: 1,
+        string: "s".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        ComplexType::decode(bytes.as_slice()).unwrap(),
+        ComplexType {
+            string_map: HashMap::from_iter([("a".to_owned(), expected.clone())]),
+            nested: Some(expected),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn utf8_policy_strict() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    let options = DecodeOptions::new().utf8_policy(Utf8Policy::Strict);
+
+    let valid = b"\x0a\x05hello\x12\x01a";
+    assert_eq!(
+        DynamicMessage::decode_with_options(desc.clone(), valid.as_ref(), &options).unwrap(),
+        DynamicMessage::decode(desc.clone(), valid.as_ref()).unwrap()
+    );
+
+    let err = DynamicMessage::decode_with_options(desc, b"\x22\x03\x0a\x01\xff".as_ref(), &options)
+        .unwrap_err();
+    assert!(err.to_string().contains("invalid string value"));
+}
+
+#[test]
+fn utf8_policy_lossy() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    let options = DecodeOptions::new().utf8_policy(Utf8Policy::Lossy);
+
+    // name = "caf\xe9", tags = ["\xff"], labels = {"k\xfe": "v"}, nested.name = "x\xff"
+    let bytes = b"\x0a\x04caf\xe9\x12\x01\xff\x1a\x07\x0a\x02k\xfe\x12\x01v\x22\x04\x0a\x02x\xff";
+    let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+
+    assert_eq!(
+        message.get_field_by_name("name").unwrap().as_str(),
+        Some("caf\u{FFFD}")
+    );
+    assert_eq!(
+        message.get_field_by_name("tags").unwrap().as_ref(),
+        &Value::List(vec![Value::String("\u{FFFD}".to_owned())])
+    );
+    assert_eq!(
+        message.get_field_by_name("labels").unwrap().as_ref(),
+        &Value::Map(HashMap::from_iter([(
+            MapKey::String("k\u{FFFD}".to_owned()),
+            Value::String("v".to_owned())
+        )]))
+    );
+    assert_eq!(
+        message
+            .get_field_by_name("nested")
+            .unwrap()
+            .as_message()
+            .unwrap()
+            .get_field_by_name("name")
+            .unwrap()
+            .as_str(),
+        Some("x\u{FFFD}")
+    );
+}
+
+#[test]
+fn utf8_policy_permissive() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    let options = DecodeOptions::new().utf8_policy(Utf8Policy::Permissive);
+
+    // name = "caf\xe9", tags = ["a", "\xff", "c"], labels = {"k": "v", "j": "\xfe"}
+    let bytes: &[u8] = b"\x0a\x04caf\xe9\x12\x01a\x12\x01\xff\x12\x01c\x1a\x06\x0a\x01k\x12\x01v\x1a\x06\x0a\x01j\x12\x01\xfe";
+    let message = DynamicMessage::decode_with_options(desc, bytes, &options).unwrap();
+
+    assert!(!message.has_field_by_name("name"));
+    assert!(!message.has_field_by_name("tags"));
+    assert!(!message.has_field_by_name("labels"));
+    assert_eq!(message.encode_to_vec(), bytes);
+}
+
+#[test]
+fn utf8_policy_permissive_proto3() {
+    let desc = Scalars::default().descriptor();
+    let options = DecodeOptions::new().utf8_policy(Utf8Policy::Permissive);
+
+    let err =
+        DynamicMessage::decode_with_options(desc, b"\x72\x01\xff".as_ref(), &options).unwrap_err();
+    assert!(err.to_string().contains("invalid string value"));
+}
+
+#[test]
+fn encode_checks_required_fields() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.ContainsGroup")
+        .unwrap();
+    let group = |name: &str, fields: &[(&str, Value)]| {
+        let field = desc.get_field_by_name(name).unwrap();
+        let mut group = DynamicMessage::new(field.kind().as_message().unwrap().clone());
+        for (name, value) in fields {
+            group.set_field_by_name(name, value.clone());
+        }
+        Value::Message(group)
+    };
+
+    let mut message = DynamicMessage::new(desc.clone());
+    assert!(message.is_initialized());
+
+    message.set_field_by_name(
+        "requiredgroup",
+        group("requiredgroup", &[("b", Value::I32(1))]),
+    );
+    message.set_field_by_name(
+        "repeatedgroup",
+        Value::List(vec![
+            group("repeatedgroup", &[("e", Value::String("e".to_owned()))]),
+            group("repeatedgroup", &[]),
+        ]),
+    );
+    assert!(!message.is_initialized());
+    assert_eq!(
+        message.missing_required_fields(),
+        ["requiredgroup.a", "repeatedgroup[1].e"]
+    );
+
+    let options = EncodeOptions::new().check_required_fields(true);
+    let err = message.encode_to_vec_with_options(&options).unwrap_err();
+    assert_eq!(
+        err.missing_required_fields(),
+        ["requiredgroup.a", "repeatedgroup[1].e"]
+    );
+    assert_eq!(
+        err.to_string(),
+        "message is missing required fields: requiredgroup.a, repeatedgroup[1].e"
+    );
+    assert_eq!(
+        message
+            .encode_to_vec_with_options(&EncodeOptions::new())
+            .unwrap(),
+        message.encode_to_vec()
+    );
+
+    message.set_field_by_name(
+        "requiredgroup",
+        group("requiredgroup", &[("a", Value::String("a".to_owned()))]),
+    );
+    message.clear_field_by_name("repeatedgroup");
+    assert!(message.is_initialized());
+
+    let mut buf = Vec::new();
+    message.encode_with_options(&mut buf, &options).unwrap();
+    assert_eq!(buf, message.encode_to_vec());
+}
+
+#[test]
+fn deny_duplicate_map_keys() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // nested.labels = [{"k": "v"}, {"k": "w"}]
+    let bytes = b"\x22\x10\x1a\x06\x0a\x01k\x12\x01v\x1a\x06\x0a\x01k\x12\x01w";
+
+    let message =
+        DynamicMessage::decode_with_options(desc.clone(), bytes.as_ref(), &DecodeOptions::new())
+            .unwrap();
+    assert_eq!(
+        message
+            .get_field_by_name("nested")
+            .unwrap()
+            .as_message()
+            .unwrap()
+            .get_field_by_name("labels")
+            .unwrap()
+            .as_ref(),
+        &Value::Map(HashMap::from_iter([(
+            MapKey::String("k".to_owned()),
+            Value::String("w".to_owned())
+        )]))
+    );
+
+    let options = DecodeOptions::new().deny_duplicate_map_keys(true);
+    let err = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("duplicate key \"k\" in map field 'nested.labels'"));
+}
+
+#[test]
+fn closed_enums() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Enums")
+        .unwrap();
+    // value = 5, values = [2, 7, 3], labels = {"a": 4, "b": 1}, value = 3
+    let bytes =
+        b"\x08\x05\x12\x03\x02\x07\x03\x1a\x05\x0a\x01a\x10\x04\x1a\x05\x0a\x01b\x10\x01\x08\x03";
+
+    let message =
+        DynamicMessage::decode_with_options(desc.clone(), bytes.as_ref(), &DecodeOptions::new())
+            .unwrap();
+    assert_eq!(
+        message.get_field_by_name("values").unwrap().as_ref(),
+        &Value::List(vec![
+            Value::EnumNumber(2),
+            Value::EnumNumber(7),
+            Value::EnumNumber(3)
+        ])
+    );
+    assert_eq!(
+        message.get_field_by_name("labels").unwrap().as_ref(),
+        &Value::Map(HashMap::from_iter([
+            (MapKey::String("a".to_owned()), Value::EnumNumber(4)),
+            (MapKey::String("b".to_owned()), Value::EnumNumber(1)),
+        ]))
+    );
+
+    let options = DecodeOptions::new().closed_enums(true);
+    let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+    assert_eq!(
+        message.get_field_by_name("value").unwrap().as_ref(),
+        &Value::EnumNumber(3)
+    );
+    assert_eq!(
+        message.get_field_by_name("values").unwrap().as_ref(),
+        &Value::List(vec![Value::EnumNumber(2), Value::EnumNumber(3)])
+    );
+    assert_eq!(
+        message.get_field_by_name("labels").unwrap().as_ref(),
+        &Value::Map(HashMap::from_iter([(
+            MapKey::String("b".to_owned()),
+            Value::EnumNumber(1)
+        )]))
+    );
+
+    // Known values are re-encoded first, followed by the unknown values.
+    assert_eq!(
+        message.encode_to_vec(),
+        b"\x08\x03\x12\x02\x02\x03\x1a\x05\x0a\x01b\x10\x01\x08\x05\x10\x07\x1a\x05\x0a\x01a\x10\x04"
+    );
+}
+
+#[test]
+fn decode_max_depth() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // nested.nested.name = "a"
+    let bytes = b"\x22\x05\x22\x03\x0a\x01a";
+
+    let options = DecodeOptions::new().max_depth(2);
+    DynamicMessage::decode_with_options(desc.clone(), bytes.as_ref(), &options).unwrap();
+
+    let options = DecodeOptions::new().max_depth(1);
+    let err = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap_err();
+    assert!(err.to_string().contains("recursion limit reached"));
+}
+
+#[test]
+fn decode_max_message_bytes() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    let bytes = b"\x0a\x03foo";
+
+    let options = DecodeOptions::new().max_message_bytes(5);
+    DynamicMessage::decode_with_options(desc.clone(), bytes.as_ref(), &options).unwrap();
+
+    let options = DecodeOptions::new().max_message_bytes(4);
+    let err = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("message of 5 bytes exceeds the limit of 4 bytes"));
+}
+
+#[test]
+fn decode_max_unknown_field_bytes() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // name = "a", nested.(field 10) = 1, (field 11) = 2
+    let bytes = b"\x0a\x01a\x22\x02\x50\x01\x58\x02";
+
+    let options = DecodeOptions::new().max_unknown_field_bytes(4);
+    DynamicMessage::decode_with_options(desc.clone(), bytes.as_ref(), &options).unwrap();
+
+    let options = DecodeOptions::new().max_unknown_field_bytes(3);
+    let err = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unknown fields exceed the limit of 3 bytes"));
+}
+
+#[test]
+fn pool_decode_options() {
+    let mut pool = test_file_descriptor();
+    assert!(pool.decode_options().is_none());
+    pool.set_decode_options(DecodeOptions::new().max_depth(1));
+
+    let desc = pool.get_message_by_name("test2.Strings").unwrap();
+    // nested.nested.name = "a"
+    let bytes = b"\x22\x05\x22\x03\x0a\x01a";
+
+    let err = DynamicMessage::decode(desc.clone(), bytes.as_ref()).unwrap_err();
+    assert!(err.to_string().contains("recursion limit reached"));
+
+    let mut message = DynamicMessage::new(desc.clone());
+    let err = message
+        .merge_length_delimited(b"\x07\x22\x05\x22\x03\x0a\x01a".as_ref())
+        .unwrap_err();
+    assert!(err.to_string().contains("recursion limit reached"));
+
+    // Descriptors from the original pool are unaffected.
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    DynamicMessage::decode(desc, bytes.as_ref()).unwrap();
+}
+
+#[test]
+fn validate_value_types() {
+    let pool = test_file_descriptor();
+    let desc = pool.get_message_by_name("test2.Strings").unwrap();
+    let mut message = DynamicMessage::new(desc.clone());
+    message.set_field_by_name("name", Value::String("a".to_owned()));
+    message.set_field_by_name("nested", Value::Message(DynamicMessage::new(desc)));
+    assert_eq!(message.validate(), Ok(()));
+
+    *message.get_field_by_name_mut("name").unwrap() = Value::I32(1);
+    *message.get_field_by_name_mut("tags").unwrap() =
+        Value::List(vec![Value::String("a".to_owned()), Value::Bool(true)]);
+    *message.get_field_by_name_mut("labels").unwrap() = Value::Map(HashMap::from_iter([(
+        MapKey::I32(1),
+        Value::String("b".to_owned()),
+    )]));
+    *message.get_field_by_name_mut("nested").unwrap() = Value::Message(DynamicMessage::new(
+        pool.get_message_by_name("test2.Enums").unwrap(),
+    ));
+
+    let err = message.validate().unwrap_err();
+    let invalid_values: Vec<_> = err
+        .invalid_values()
+        .iter()
+        .map(|invalid_value| invalid_value.to_string())
+        .collect();
+    assert_eq!(
+        invalid_values,
+        [
+            "name: expected a value of type 'string', but found '1'",
+            "tags[1]: expected a value of type 'string', but found 'true'",
+            "labels[1]: expected a key of type 'string', but found '1'",
+            "nested: expected a message of type 'test2.Strings', but found 'test2.Enums'",
+        ]
+    );
+}
+
+#[test]
+fn validate_numeric_ranges() {
+    let mut message = DynamicMessage::new(Scalars::default().descriptor());
+    *message.get_field_by_name_mut("int32").unwrap() = Value::I64(1 << 40);
+    *message.get_field_by_name_mut("uint32").unwrap() = Value::I32(-1);
+    *message.get_field_by_name_mut("int64").unwrap() = Value::I32(5);
+    *message.get_field_by_name_mut("float").unwrap() = Value::F64(1e300);
+
+    let err = message.validate().unwrap_err();
+    let invalid_values = err.invalid_values();
+    assert_eq!(invalid_values.len(), 4);
+    assert_eq!(invalid_values[0].path(), "float");
+    assert!(invalid_values[0]
+        .reason()
+        .ends_with("is out of range for type 'float'"));
+    assert_eq!(
+        invalid_values[1].to_string(),
+        "int32: 1099511627776 is out of range for type 'int32'"
+    );
+    assert_eq!(
+        invalid_values[2].to_string(),
+        "int64: expected a value of type 'int64', but found '5'"
+    );
+    assert_eq!(
+        invalid_values[3].to_string(),
+        "uint32: -1 is out of range for type 'uint32'"
+    );
+}
+
+#[test]
+fn validate_closed_enums() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Enums")
+        .unwrap();
+    // value = 5, values = [2, 7, 3]
+    let bytes = b"\x08\x05\x12\x03\x02\x07\x03";
+
+    let message = DynamicMessage::decode(desc.clone(), bytes.as_ref()).unwrap();
+    let err = message.validate().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "message has invalid values: \
+        value: 5 is not a value of the closed enum 'test2.Proto2Enum'; \
+        values[1]: 7 is not a value of the closed enum 'test2.Proto2Enum'"
+    );
+
+    let options = DecodeOptions::new().closed_enums(true);
+    let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+    assert_eq!(message.validate(), Ok(()));
+}
+
+#[test]
+fn validate_utf8() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    let options = DecodeOptions::new().utf8_policy(Utf8Policy::Permissive);
+
+    let message =
+        DynamicMessage::decode_with_options(desc, b"\x0a\x01\xff".as_ref(), &options).unwrap();
+    let err = message.validate().unwrap_err();
+    assert_eq!(err.invalid_values()[0].path(), "name");
+    assert_eq!(
+        err.invalid_values()[0].reason(),
+        "string contains invalid UTF-8"
+    );
+}
+
+#[test]
+fn verify_stats() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // name = "a", labels = {"k": "v"}, nested.(field 10) = 1
+    let bytes = b"\x0a\x01a\x1a\x06\x0a\x01k\x12\x01v\x22\x02\x50\x01";
+
+    let stats = verify(&desc, bytes, &VerifyOptions::new()).unwrap();
+    assert_eq!(stats.messages(), 3);
+    assert_eq!(stats.fields(), 5);
+    assert_eq!(stats.unknown_fields(), 1);
+    assert_eq!(stats.unknown_field_bytes(), 2);
+    assert_eq!(stats.max_depth(), 1);
+
+    let options = VerifyOptions::new().deny_unknown_fields(true);
+    let err = verify(&desc, bytes, &options).unwrap_err();
+    assert_eq!(err.offset(), 13);
+    assert_eq!(err.path(), "nested");
+    assert!(err.to_string().contains("unknown field number 10"));
+}
+
+#[test]
+fn verify_groups() {
+    let desc = ContainsGroup::default().descriptor();
+    // requiredgroup.a = "x", repeatedgroup[0].f = 1
+    let stats = verify(
+        &desc,
+        b"\x0b\x0a\x01x\x0c\x1b\x10\x01\x1c",
+        &VerifyOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(stats.messages(), 3);
+    assert_eq!(stats.fields(), 4);
+    assert_eq!(stats.max_depth(), 1);
+
+    let err = verify(&desc, b"\x0b\x0a\x01x", &VerifyOptions::new()).unwrap_err();
+    assert_eq!(err.offset(), 4);
+    assert_eq!(err.path(), "requiredgroup");
+    assert!(err.to_string().contains("unexpected end of group"));
+
+    let err = verify(&desc, b"\x0b\x0a\x01x\x14", &VerifyOptions::new()).unwrap_err();
+    assert!(err.to_string().contains("unexpected end group tag"));
+}
+
+#[test]
+fn verify_invalid_input() {
+    let scalars = Scalars::default().descriptor();
+    let err = verify(&scalars, b"\x1a\x00", &VerifyOptions::new()).unwrap_err();
+    assert_eq!(err.offset(), 0);
+    assert_eq!(err.path(), "int32");
+    assert!(err
+        .to_string()
+        .contains("invalid wire type: LengthDelimited (expected Varint)"));
+
+    let scalar_arrays = ScalarArrays::default().descriptor();
+    // fixed32 = [1, 2, 3], int32 = [1, 2] (packed)
+    let err = verify(
+        &scalar_arrays,
+        b"\x1a\x02\x01\x02\x4a\x03\x01\x02\x03",
+        &VerifyOptions::new(),
+    )
+    .unwrap_err();
+    assert_eq!(err.offset(), 6);
+    assert_eq!(err.path(), "fixed32");
+    assert!(err.to_string().contains("buffer underflow"));
+
+    let strings = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // name = "a", nested.name = "\xff"
+    let bytes = b"\x0a\x01a\x22\x03\x0a\x01\xff";
+    let err = verify(&strings, bytes, &VerifyOptions::new()).unwrap_err();
+    assert_eq!(err.offset(), 5);
+    assert_eq!(err.path(), "nested.name");
+    assert!(err.to_string().contains("data is not UTF-8 encoded"));
+    DynamicMessage::decode(strings.clone(), bytes.as_ref()).unwrap_err();
+
+    let options = VerifyOptions::new().utf8_policy(Utf8Policy::Permissive);
+    verify(&strings, bytes, &options).unwrap();
+
+    let err = verify(
+        &strings,
+        b"\x22\x05\x22\x03\x0a\x01a",
+        &VerifyOptions::new().max_depth(1),
+    )
+    .unwrap_err();
+    assert_eq!(err.offset(), 2);
+    assert_eq!(err.path(), "nested.nested");
+    assert!(err.to_string().contains("recursion limit reached"));
+}
+
+#[test]
+fn decode_lazy_messages() {
+    let message = ComplexType {
+        int_map: HashMap::from_iter([(
+            3,
+            Scalars {
+                sint32: 7,
+                ..Default::default()
+            },
+        )]),
+        nested: Some(Scalars {
+            int32: 5,
+            string: "5".to_owned(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let bytes = message.encode_to_vec();
+
+    let options = DecodeOptions::new().lazy_messages(true);
+    let eager = DynamicMessage::decode(message.descriptor(), bytes.as_slice()).unwrap();
+    let mut lazy =
+        DynamicMessage::decode_with_options(message.descriptor(), bytes.as_slice(), &options)
+            .unwrap();
+    assert_eq!(lazy.encode_to_vec(), bytes);
+    assert_eq!(lazy, eager);
+    lazy.resolve().unwrap();
+
+    lazy.get_field_by_name_mut("nested")
+        .unwrap()
+        .as_message_mut()
+        .unwrap()
+        .set_field_by_name("int32", Value::I32(6));
+    assert_eq!(
+        lazy.transcode_to::<ComplexType>().unwrap(),
+        ComplexType {
+            nested: Some(Scalars {
+                int32: 6,
+                string: "5".to_owned(),
+                ..Default::default()
+            }),
+            ..message
+        }
+    );
+}
+
+#[test]
+fn decode_lazy_messages_invalid() {
+    let strings = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // name = "a", nested.name = "\xff"
+    let bytes = b"\x0a\x01a\x22\x03\x0a\x01\xff";
+    DynamicMessage::decode(strings.clone(), bytes.as_ref()).unwrap_err();
+
+    let options = DecodeOptions::new().lazy_messages(true);
+    let message = DynamicMessage::decode_with_options(strings, bytes.as_ref(), &options).unwrap();
+    assert_eq!(
+        message.get_field_by_name("name").unwrap().as_str(),
+        Some("a")
+    );
+    assert!(message
+        .resolve()
+        .unwrap_err()
+        .to_string()
+        .contains("data is not UTF-8 encoded"));
+
+    let nested = message.get_field_by_name("nested").unwrap();
+    let nested = nested.as_message().unwrap();
+    assert!(!nested.has_field_by_name("name"));
+    assert_eq!(message.encode_to_vec(), bytes);
+}
+
+#[test]
+fn decode_lazy_messages_invalid_modified() {
+    let strings = test_file_descriptor()
+        .get_message_by_name("test2.Strings")
+        .unwrap();
+    // name = "a", nested.name = "\xff"
+    let bytes = b"\x0a\x01a\x22\x03\x0a\x01\xff";
+
+    let options = DecodeOptions::new().lazy_messages(true);
+    let original = DynamicMessage::decode_with_options(strings, bytes.as_ref(), &options).unwrap();
+    let mut message = original.clone();
+
+    let nested = message.get_field_by_name_mut("nested").unwrap();
+    let nested = nested.as_message_mut().unwrap();
+    nested.set_field_by_name("name", Value::String("b".to_owned()));
+    assert_eq!(
+        nested.get_field_by_name("name").unwrap().as_str(),
+        Some("b")
+    );
+
+    assert_ne!(message, original);
+    assert!(message.resolve().is_err());
+    assert_eq!(
+        message.encode_to_vec(),
+        b"\x0a\x01a\x22\x06\x0a\x01\xff\x0a\x01b"
+    );
+    assert_eq!(message.encoded_len(), 11);
+}
+
+#[test]
+fn decode_from_reader() {
+    use std::io::{self, Cursor};
+
+    let message = Scalars {
+        int32: 5,
+        string: "five".to_owned(),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let mut output = Vec::new();
+    message.encode_to_writer(&mut output).unwrap();
+    assert_eq!(output, message.encode_to_vec());
+    let decoded =
+        DynamicMessage::decode_from_reader(message.descriptor(), output.as_slice()).unwrap();
+    assert_eq!(decoded, message);
+
+    for prefix in [SizePrefix::Varint, SizePrefix::Fixed32] {
+        let mut output = Vec::new();
+        message
+            .encode_length_delimited_to_writer(&mut output, prefix)
+            .unwrap();
+        message
+            .encode_length_delimited_to_writer(&mut output, prefix)
+            .unwrap();
+
+        let mut reader = Cursor::new(&output);
+        for _ in 0..2 {
+            let decoded = DynamicMessage::decode_length_delimited_from_reader(
+                message.descriptor(),
+                &mut reader,
+                prefix,
+            )
+            .unwrap();
+            assert_eq!(decoded, Some(message.clone()));
+        }
+        assert_eq!(
+            DynamicMessage::decode_length_delimited_from_reader(
+                message.descriptor(),
+                &mut reader,
+                prefix
+            )
+            .unwrap(),
+            None
+        );
+
+        let err = DynamicMessage::decode_length_delimited_from_reader(
+            message.descriptor(),
+            &output[..output.len() / 2 - 1],
+            prefix,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    let err =
+        DynamicMessage::decode_from_reader(message.descriptor(), b"\x08".as_ref()).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn decode_from_reader_max_message_bytes() {
+    use std::io;
+
+    let mut pool = test_file_descriptor();
+    pool.set_decode_options(DecodeOptions::new().max_message_bytes(4));
+    let desc = pool.get_message_by_name("test2.Strings").unwrap();
+
+    // The stream never ends, so this only returns if reading stops at the limit.
+    let err = DynamicMessage::decode_from_reader(desc.clone(), io::repeat(0)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(err
+        .to_string()
+        .contains("message exceeds the limit of 4 bytes"));
+
+    // Only the length prefix is present, so reading the message would fail with `UnexpectedEof`.
+    for (prefix, bytes) in [
+        (SizePrefix::Varint, b"\x80\x08".as_ref()),
+        (SizePrefix::Fixed32, b"\x00\x00\x04\x00".as_ref()),
+    ] {
+        let err = DynamicMessage::decode_length_delimited_from_reader(desc.clone(), bytes, prefix)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err
+            .to_string()
+            .contains("message of 1024 bytes exceeds the limit of 4 bytes"));
+    }
+
+    let decoded = DynamicMessage::decode_length_delimited_from_reader(
+        desc,
+        b"\x04\x0a\x02ab".as_ref(),
+        SizePrefix::Varint,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+        decoded.get_field_by_name("name").unwrap().as_ref(),
+        &Value::String("ab".to_owned())
+    );
+}
@@ -0,0 +1,98 @@
+use prost::Message;
+use prost_reflect::{DynamicMessage, Kind, ReflectMessage, Value};
+
+use crate::{
+    proto::{ComplexType, MessageWithOneof, Scalars, WellKnownTypes},
+    test_file_descriptor,
+};
+
+#[test]
+fn is_default_unset() {
+    assert!(DynamicMessage::new(Scalars::default().descriptor()).is_default());
+    assert!(DynamicMessage::new(ComplexType::default().descriptor()).is_default());
+    assert!(Scalars::default().transcode_to_dynamic().is_default());
+}
+
+#[test]
+fn is_default_explicit_defaults() {
+    let mut message = DynamicMessage::new(ComplexType::default().descriptor());
+    message.set_field_by_name(
+        "nested",
+        Value::Message(DynamicMessage::new(Scalars::default().descriptor())),
+    );
+    message.set_field_by_name("optional_enum", Value::EnumNumber(0));
+    assert_ne!(
+        message,
+        DynamicMessage::new(ComplexType::default().descriptor())
+    );
+    assert!(message.is_default());
+
+    let mut message = DynamicMessage::new(MessageWithOneof::default().descriptor());
+    message.set_field_by_name("oneof_field_2", Value::I32(0));
+    assert!(message.is_default());
+
+    let desc = test_file_descriptor()
+        .get_message_by_name("test2.DefaultValues")
+        .unwrap();
+    let mut message = DynamicMessage::new(desc);
+    message.set_field_by_name("int32", Value::I32(-3));
+    assert!(message.is_default());
+    message.set_field_by_name("int32", Value::I32(0));
+    assert!(!message.is_default());
+}
+
+#[test]
+fn is_default_non_default() {
+    let message = ComplexType {
+        nested: Some(Scalars {
+            bool: true,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    assert!(!message.is_default());
+
+    let message = ComplexType {
+        my_enum: vec![0],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    assert!(!message.is_default());
+
+    let message = WellKnownTypes {
+        int32: Some(0),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    assert!(message.is_default());
+}
+
+#[test]
+fn is_default_unknown_fields() {
+    let desc = Scalars::default().descriptor();
+    let message = DynamicMessage::decode(desc, b"\xa8\x1f\x01".as_ref()).unwrap();
+    assert!(message.is_default());
+    assert!(!message.is_default_including_unknown());
+
+    let mut outer = DynamicMessage::new(ComplexType::default().descriptor());
+    outer.set_field_by_name("nested", Value::Message(message));
+    assert!(outer.is_default());
+    assert!(!outer.is_default_including_unknown());
+    assert!(!outer.encode_to_vec().is_empty());
+}
+
+#[test]
+fn value_is_default() {
+    let desc = Scalars::default().descriptor();
+    let kind = Kind::Message(desc.clone());
+    let mut message = DynamicMessage::new(desc);
+    message.set_field_by_name("int32", Value::I32(0));
+    assert!(Value::Message(message).is_default(&kind));
+    assert!(
+        !Value::Message(DynamicMessage::new(ComplexType::default().descriptor())).is_default(&kind)
+    );
+    assert!(Value::I32(0).is_default(&Kind::Int32));
+    assert!(!Value::I32(1).is_default(&Kind::Int32));
+    assert!(Value::List(vec![]).is_default(&Kind::Int32));
+}
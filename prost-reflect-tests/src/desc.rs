@@ -1,5 +1,10 @@
 use prost::Message;
-use prost_reflect::{DescriptorPool, ReflectMessage, Syntax, Value};
+use prost_reflect::{DescriptorPool, LazyDescriptorPool, ReflectMessage, Syntax, Value};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    method_options::IdempotencyLevel,
+    DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+};
 
 use crate::{proto, test_file_descriptor, DESCRIPTOR_POOL_BYTES};
 
@@ -568,3 +573,231 @@ fn test_enum_extension_options() {
         &Value::EnumNumber(1)
     );
 }
+
+#[test]
+fn test_service_google_api_options() {
+    let pool = test_file_descriptor();
+
+    let service = pool.get_service_by_name("library.Library").unwrap();
+    assert_eq!(
+        service.default_host().as_deref(),
+        Some("library.example.com")
+    );
+    assert_eq!(
+        service.oauth_scopes(),
+        [
+            "https://www.example.com/auth/library",
+            "https://www.example.com/auth/library.readonly"
+        ]
+    );
+
+    let service = pool.get_service_by_name("echo.Echo").unwrap();
+    assert_eq!(service.default_host(), None);
+    assert!(service.oauth_scopes().is_empty());
+}
+
+#[test]
+fn test_method_options() {
+    let pool = test_file_descriptor();
+    let service = pool.get_service_by_name("library.Library").unwrap();
+
+    let get_book = service.methods().find(|m| m.name() == "GetBook").unwrap();
+    assert_eq!(
+        get_book.idempotency_level(),
+        IdempotencyLevel::NoSideEffects
+    );
+    assert_eq!(get_book.method_signatures(), [vec!["name".to_owned()]]);
+
+    let create_book = service
+        .methods()
+        .find(|m| m.name() == "CreateBook")
+        .unwrap();
+    assert_eq!(
+        create_book.idempotency_level(),
+        IdempotencyLevel::IdempotencyUnknown
+    );
+    assert_eq!(
+        create_book.method_signatures(),
+        [vec!["parent".to_owned(), "book".to_owned()], vec![]]
+    );
+
+    let update_book = service
+        .methods()
+        .find(|m| m.name() == "UpdateBook")
+        .unwrap();
+    assert!(update_book.method_signatures().is_empty());
+}
+
+#[test]
+fn test_lazy_descriptor_pool() {
+    let lazy_pool = LazyDescriptorPool::decode(DESCRIPTOR_POOL_BYTES).unwrap();
+    assert_eq!(lazy_pool.pool().files().len(), 0);
+
+    let message_desc = lazy_pool
+        .get_message_by_name("test2.ContainsGroup")
+        .unwrap()
+        .unwrap();
+    assert_eq!(message_desc.parent_file().name(), "test2.proto");
+    let pool = lazy_pool.pool();
+    assert_eq!(pool.files().len(), 1);
+    assert_eq!(message_desc.parent_pool(), &pool);
+
+    let nested_desc = lazy_pool
+        .get_message_by_name(".test2.ContainsGroup.RequiredGroup")
+        .unwrap()
+        .unwrap();
+    assert_eq!(nested_desc.parent_message(), Some(message_desc));
+    assert!(lazy_pool
+        .get_enum_by_name("test2.Proto2Enum")
+        .unwrap()
+        .is_some());
+    assert!(lazy_pool
+        .get_message_by_name("test2.Proto2Enum")
+        .unwrap()
+        .is_none());
+    assert!(lazy_pool
+        .get_message_by_name("test2.DoesNotExist")
+        .unwrap()
+        .is_none());
+    assert_eq!(lazy_pool.pool(), pool);
+
+    let pool = lazy_pool.load_all().unwrap();
+    assert_eq!(pool.files().len(), test_file_descriptor().files().len());
+}
+
+#[test]
+fn test_lazy_descriptor_pool_dependencies() {
+    let lazy_pool = LazyDescriptorPool::decode(DESCRIPTOR_POOL_BYTES).unwrap();
+
+    let file_desc = lazy_pool.get_file_by_name("test.proto").unwrap().unwrap();
+    let pool = lazy_pool.pool();
+    assert_eq!(pool.files().len(), file_desc.dependencies().len() + 1);
+    assert!(pool
+        .get_file_by_name("google/protobuf/timestamp.proto")
+        .is_some());
+    assert!(pool
+        .get_message_by_name("test.WellKnownTypes")
+        .unwrap()
+        .get_field_by_name("timestamp")
+        .is_some());
+}
+
+#[test]
+fn test_lazy_descriptor_pool_snapshots() {
+    let lazy_pool = LazyDescriptorPool::decode(DESCRIPTOR_POOL_BYTES).unwrap();
+
+    let group_desc = lazy_pool
+        .get_message_by_name("test2.ContainsGroup")
+        .unwrap()
+        .unwrap();
+    let well_known_desc = lazy_pool
+        .get_message_by_name("test.WellKnownTypes")
+        .unwrap()
+        .unwrap();
+    assert_eq!(group_desc.parent_pool(), well_known_desc.parent_pool());
+    assert_eq!(
+        lazy_pool
+            .get_message_by_name("test2.ContainsGroup")
+            .unwrap()
+            .unwrap(),
+        group_desc
+    );
+    assert!(group_desc
+        .parent_pool()
+        .get_message_by_name("test.WellKnownTypes")
+        .is_none());
+
+    let mut pool = lazy_pool.pool();
+    pool.set_decode_options(Default::default());
+    assert_ne!(&pool, group_desc.parent_pool());
+
+    let other_lazy_pool = LazyDescriptorPool::decode(DESCRIPTOR_POOL_BYTES).unwrap();
+    assert_ne!(
+        other_lazy_pool
+            .get_message_by_name("test2.ContainsGroup")
+            .unwrap()
+            .unwrap(),
+        group_desc
+    );
+}
+
+#[test]
+fn test_lazy_descriptor_pool_errors() {
+    let file_descriptor_set = FileDescriptorSet {
+        file: vec![
+            FileDescriptorProto {
+                name: Some("valid.proto".to_owned()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Valid".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            FileDescriptorProto {
+                name: Some("invalid.proto".to_owned()),
+                message_type: vec![DescriptorProto {
+                    name: Some("Invalid".to_owned()),
+                    field: vec![FieldDescriptorProto {
+                        name: Some("field".to_owned()),
+                        number: Some(1),
+                        label: Some(Label::Optional as i32),
+                        r#type: Some(Type::Message as i32),
+                        type_name: Some(".DoesNotExist".to_owned()),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+        ],
+    };
+    let lazy_pool =
+        LazyDescriptorPool::decode(file_descriptor_set.encode_to_vec().as_slice()).unwrap();
+
+    let err = lazy_pool.get_message_by_name("Invalid").unwrap_err();
+    assert_eq!(err.to_string(), "name '.DoesNotExist' is not defined");
+    assert!(lazy_pool.get_message_by_name("Valid").unwrap().is_some());
+    assert_eq!(lazy_pool.pool().files().len(), 1);
+}
+
+#[test]
+fn test_memory_usage() {
+    let pool = test_file_descriptor();
+    let usage = pool.memory_usage();
+
+    assert_eq!(usage.files().len(), pool.files().len());
+    for (file, file_usage) in pool.files().zip(usage.files()) {
+        assert_eq!(file.name(), file_usage.name());
+        assert!(file_usage.file_descriptor_protos() > 0);
+    }
+
+    let desc_usage = usage
+        .files()
+        .iter()
+        .find(|file| file.name() == "desc.proto")
+        .unwrap();
+    assert!(desc_usage.descriptors() > 0);
+    assert_eq!(
+        desc_usage.total(),
+        desc_usage.file_descriptor_protos() + desc_usage.descriptors()
+    );
+
+    assert!(usage.names() > 0);
+    assert!(usage.messages() > 0);
+    assert!(usage.enums() > 0);
+    assert!(usage.extensions() > 0);
+    assert!(usage.services() > 0);
+    assert_eq!(
+        usage.total(),
+        usage.file_descriptor_protos()
+            + usage.names()
+            + usage.messages()
+            + usage.enums()
+            + usage.extensions()
+            + usage.services()
+    );
+    let file_total: usize = usage.files().iter().map(|file| file.total()).sum();
+    assert!(file_total <= usage.total() - usage.names());
+
+    assert!(DescriptorPool::new().memory_usage().files().is_empty());
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use prost_types::FieldMask;
+
+use prost_reflect::{field_mask, ReflectMessage};
+
+use crate::proto::{message_with_oneof, AddressBook, ComplexType, MessageWithOneof, Scalars};
+
+#[test]
+fn diff_mask_equal() {
+    let message = ComplexType {
+        nested: Some(Scalars {
+            int32: 5,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert!(field_mask::diff_mask(&message, &message.clone())
+        .paths
+        .is_empty());
+}
+
+#[test]
+fn diff_mask_nested() {
+    let a = ComplexType {
+        nested: Some(Scalars {
+            int32: 5,
+            string: "foo".to_owned(),
+            ..Default::default()
+        }),
+        my_enum: vec![1, 3],
+        optional_enum: 1,
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    let b = ComplexType {
+        nested: Some(Scalars {
+            int32: 5,
+            string: "bar".to_owned(),
+            bytes: b"bytes".to_vec(),
+            ..Default::default()
+        }),
+        my_enum: vec![1],
+        string_map: HashMap::from([("key".to_owned(), Scalars::default())]),
+        optional_enum: 1,
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        field_mask::diff_mask(&a, &b).paths,
+        vec!["string_map", "nested.string", "nested.bytes", "my_enum"]
+    );
+}
+
+#[test]
+fn diff_mask_presence() {
+    let a = ComplexType {
+        nested: Some(Scalars::default()),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    let b = ComplexType::default().transcode_to_dynamic();
+
+    assert_eq!(field_mask::diff_mask(&a, &b).paths, vec!["nested"]);
+    assert_eq!(field_mask::diff_mask(&b, &a).paths, vec!["nested"]);
+}
+
+#[test]
+fn diff_mask_oneof() {
+    let a = MessageWithOneof {
+        test_oneof: Some(message_with_oneof::TestOneof::OneofField1("foo".to_owned())),
+    }
+    .transcode_to_dynamic();
+    let b = MessageWithOneof {
+        test_oneof: Some(message_with_oneof::TestOneof::OneofField2(0)),
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        field_mask::diff_mask(&a, &b).paths,
+        vec!["oneof_field_1", "oneof_field_2"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot compare messages of different types")]
+fn diff_mask_different_types() {
+    field_mask::diff_mask(
+        &ComplexType::default().transcode_to_dynamic(),
+        &Scalars::default().transcode_to_dynamic(),
+    );
+}
+
+fn parse_mask(s: &str) -> FieldMask {
+    field_mask::split_paths(s)
+}
+
+#[test]
+fn json_string_roundtrip() {
+    let mask = field_mask::from_json_string("stringMap,nested.int32,myEnum").unwrap();
+    assert_eq!(mask.paths, ["string_map", "nested.int32", "my_enum"]);
+    assert_eq!(
+        field_mask::to_json_string(&mask).unwrap(),
+        "stringMap,nested.int32,myEnum"
+    );
+
+    assert!(field_mask::from_json_string("").unwrap().paths.is_empty());
+    assert_eq!(
+        field_mask::from_json_string("string_map")
+            .unwrap_err()
+            .to_string(),
+        "invalid field mask path 'string_map'"
+    );
+    assert_eq!(
+        field_mask::to_json_string(&parse_mask("fooBar"))
+            .unwrap_err()
+            .to_string(),
+        "field mask path 'fooBar' cannot be written in camel case"
+    );
+}
+
+#[test]
+fn split_and_join_paths() {
+    let mask = parse_mask(" a.b , c,,d ");
+    assert_eq!(mask.paths, ["a.b", "c", "d"]);
+    assert_eq!(field_mask::join_paths(&mask), "a.b,c,d");
+}
+
+#[test]
+fn normalize() {
+    assert_eq!(
+        field_mask::normalize(&parse_mask("c,a.b,a.bc,a,c,b.d,b.d.e")).paths,
+        ["a", "b.d", "c"]
+    );
+    assert!(field_mask::normalize(&parse_mask("")).paths.is_empty());
+}
+
+#[test]
+fn set_operations() {
+    assert_eq!(
+        field_mask::union(&parse_mask("b,a.c"), &parse_mask("a,d")).paths,
+        ["a", "b", "d"]
+    );
+    assert_eq!(
+        field_mask::intersection(&parse_mask("a,b.c,d"), &parse_mask("a.x,b,e")).paths,
+        ["a.x", "b.c"]
+    );
+    assert_eq!(
+        field_mask::subtract(&parse_mask("a.b,c,d"), &parse_mask("a,d.e")).paths,
+        ["c", "d"]
+    );
+    assert_eq!(
+        field_mask::subtract(&parse_mask("a,c"), &parse_mask("a.b")).paths,
+        ["a", "c"]
+    );
+}
+
+#[test]
+fn validate() {
+    let desc = ComplexType::default().descriptor();
+    for paths in [
+        "string_map,int_map,nested,my_enum,optional_enum",
+        "nested.int32,nested.string",
+        "string_map.key.int32,int_map.`5`.string,string_map.*",
+        "*,nested.*",
+    ] {
+        assert!(
+            field_mask::validate(&parse_mask(paths), &desc).is_ok(),
+            "{}",
+            paths
+        );
+    }
+
+    for path in [
+        "unknown",
+        "nested.unknown",
+        "my_enum.foo",
+        "nested.int32.foo",
+        "string_map.key.unknown",
+    ] {
+        assert_eq!(
+            field_mask::validate(&parse_mask(path), &desc)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "field mask path '{}' is not valid for message 'test.ComplexType'",
+                path
+            )
+        );
+    }
+
+    let desc = AddressBook::default().descriptor();
+    assert!(field_mask::validate(
+        &parse_mask("contacts.*.name,contacts.location.latitude"),
+        &desc
+    )
+    .is_ok());
+    assert!(field_mask::validate(&parse_mask("contacts.*.unknown"), &desc).is_err());
+}
@@ -0,0 +1,219 @@
+use prost::Message;
+use prost_reflect::{
+    framing::{self, Compression, FrameDecoder},
+    ReflectMessage,
+};
+
+use crate::proto::Scalars;
+
+fn scalars() -> Scalars {
+    Scalars {
+        int32: 5,
+        string: "hello ".repeat(20),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn identity_round_trip() {
+    let message = scalars();
+    let bytes = framing::encode_to_vec(&message, Compression::Identity);
+    assert_eq!(bytes[0], 0);
+    assert_eq!(bytes[1..5], (message.encoded_len() as u32).to_be_bytes());
+    assert_eq!(bytes[5..], message.encode_to_vec());
+
+    let decoded = framing::decode(
+        message.descriptor(),
+        Compression::Identity,
+        bytes.as_slice(),
+    )
+    .unwrap();
+    assert_eq!(decoded.transcode_to::<Scalars>().unwrap(), message);
+}
+
+#[test]
+fn gzip_round_trip() {
+    let message = scalars();
+    let bytes = framing::encode_to_vec(&message, Compression::Gzip);
+    assert_eq!(bytes[0], 1);
+    assert!(bytes.len() < message.encoded_len());
+
+    let decoded =
+        framing::decode(message.descriptor(), Compression::Gzip, bytes.as_slice()).unwrap();
+    assert_eq!(decoded.transcode_to::<Scalars>().unwrap(), message);
+}
+
+#[test]
+fn deflate_round_trip() {
+    let message = scalars();
+    let bytes = framing::encode_to_vec(&message, Compression::Deflate);
+    assert_eq!(bytes[0], 1);
+
+    let decoded =
+        framing::decode(message.descriptor(), Compression::Deflate, bytes.as_slice()).unwrap();
+    assert_eq!(decoded.transcode_to::<Scalars>().unwrap(), message);
+}
+
+#[test]
+fn encoding_names() {
+    for compression in [
+        Compression::Identity,
+        Compression::Gzip,
+        Compression::Deflate,
+    ] {
+        assert_eq!(
+            Compression::from_encoding(compression.as_str()),
+            Some(compression)
+        );
+    }
+    assert_eq!(Compression::from_encoding("snappy"), None);
+    assert_eq!(Compression::default(), Compression::Identity);
+}
+
+#[test]
+fn decoder_split_frames() {
+    let message = scalars();
+    let mut bytes = framing::encode_to_vec(&message, Compression::Gzip);
+    bytes.extend(framing::encode_to_vec(
+        &Scalars::default(),
+        Compression::Identity,
+    ));
+    bytes.extend(framing::encode_to_vec(&message, Compression::Gzip));
+
+    let mut decoder = FrameDecoder::new(message.descriptor(), Compression::Gzip);
+    let mut decoded = Vec::new();
+    for chunk in bytes.chunks(3) {
+        decoder.push(chunk);
+        while let Some(message) = decoder.decode_next().unwrap() {
+            decoded.push(message.transcode_to::<Scalars>().unwrap());
+        }
+    }
+    decoder.finish().unwrap();
+    assert_eq!(decoded, [message.clone(), Scalars::default(), message]);
+}
+
+#[test]
+fn decoder_incomplete() {
+    let bytes = framing::encode_to_vec(&scalars(), Compression::Identity);
+
+    let mut decoder = FrameDecoder::new(scalars().descriptor(), Compression::Identity);
+    decoder.push(&bytes[..bytes.len() - 1]);
+    assert_eq!(decoder.decode_next().unwrap(), None);
+    assert_eq!(decoder.buffered_len(), bytes.len() - 1);
+    assert_eq!(
+        decoder.finish().unwrap_err().to_string(),
+        format!(
+            "unexpected end of input with {} bytes of an incomplete message",
+            bytes.len() - 1
+        )
+    );
+
+    let err = framing::decode(
+        scalars().descriptor(),
+        Compression::Identity,
+        &bytes[..bytes.len() - 1],
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("unexpected end of input"));
+}
+
+#[test]
+fn trailing_data() {
+    let mut bytes = framing::encode_to_vec(&scalars(), Compression::Identity);
+    bytes.push(0);
+
+    let err = framing::decode(
+        scalars().descriptor(),
+        Compression::Identity,
+        bytes.as_slice(),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "found 1 bytes of data after the message");
+}
+
+#[test]
+fn invalid_flag() {
+    let mut bytes = framing::encode_to_vec(&scalars(), Compression::Identity);
+    bytes[0] = 2;
+
+    let err = framing::decode(
+        scalars().descriptor(),
+        Compression::Identity,
+        bytes.as_slice(),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "invalid compressed flag 2");
+}
+
+#[test]
+fn compressed_without_compression() {
+    let bytes = framing::encode_to_vec(&scalars(), Compression::Gzip);
+
+    let err = framing::decode(
+        scalars().descriptor(),
+        Compression::Identity,
+        bytes.as_slice(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "received a compressed message, but no compression is configured"
+    );
+}
+
+#[test]
+fn invalid_compressed_data() {
+    let mut bytes = framing::encode_to_vec(&scalars(), Compression::Identity);
+    bytes[0] = 1;
+
+    let err =
+        framing::decode(scalars().descriptor(), Compression::Gzip, bytes.as_slice()).unwrap_err();
+    assert!(err.to_string().starts_with("failed to decompress message"));
+}
+
+#[test]
+fn max_message_size() {
+    let message = scalars();
+    let len = message.encoded_len();
+
+    let bytes = framing::encode_to_vec(&message, Compression::Identity);
+    let mut decoder =
+        FrameDecoder::new(message.descriptor(), Compression::Identity).max_message_size(len - 1);
+    // The length is checked as soon as the prefix is received.
+    decoder.push(&bytes[..5]);
+    assert_eq!(
+        decoder.decode_next().unwrap_err().to_string(),
+        format!(
+            "message of {} bytes exceeds the limit of {} bytes",
+            len,
+            len - 1
+        )
+    );
+
+    // Compressed messages are also limited by their decompressed size.
+    let bytes = framing::encode_to_vec(&message, Compression::Gzip);
+    assert!(bytes.len() - 5 < len - 1);
+    let mut decoder =
+        FrameDecoder::new(message.descriptor(), Compression::Gzip).max_message_size(len - 1);
+    decoder.push(&bytes);
+    assert_eq!(
+        decoder.decode_next().unwrap_err().to_string(),
+        format!(
+            "decompressed message exceeds the limit of {} bytes",
+            len - 1
+        )
+    );
+}
+
+#[test]
+fn decode_error() {
+    let bytes = [0, 0, 0, 0, 1, 0xff];
+
+    let err = framing::decode(
+        scalars().descriptor(),
+        Compression::Identity,
+        bytes.as_ref(),
+    )
+    .unwrap_err();
+    assert!(err.to_string().starts_with("failed to decode message"));
+}
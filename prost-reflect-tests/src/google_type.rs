@@ -0,0 +1,123 @@
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveTime};
+use prost_reflect::{google_type, DynamicMessage, MessageDescriptor, Value};
+use rust_decimal::Decimal;
+
+use crate::test_file_descriptor;
+
+fn desc(name: &str) -> MessageDescriptor {
+    test_file_descriptor()
+        .get_message_by_name(&format!("google.type.{}", name))
+        .unwrap()
+}
+
+#[test]
+fn lat_lng() {
+    let message = google_type::from_lat_lng(desc("LatLng"), (51.5, -0.12)).unwrap();
+    assert_eq!(
+        message.get_field_by_name("longitude").unwrap().as_f64(),
+        Some(-0.12)
+    );
+    assert_eq!(google_type::to_lat_lng(&message).unwrap(), (51.5, -0.12));
+
+    assert_eq!(
+        google_type::from_lat_lng(desc("LatLng"), (91.0, 0.0))
+            .unwrap_err()
+            .to_string(),
+        "value is out of range for message 'google.type.LatLng'"
+    );
+    assert_eq!(
+        google_type::to_lat_lng(&DynamicMessage::new(desc("Date")))
+            .unwrap_err()
+            .to_string(),
+        "expected a message of type 'google.type.LatLng', but found 'google.type.Date'"
+    );
+}
+
+#[test]
+fn date() {
+    let date = NaiveDate::from_ymd_opt(2022, 2, 28).unwrap();
+    let message = google_type::from_naive_date(desc("Date"), date).unwrap();
+    assert_eq!(
+        message.get_field_by_name("year").unwrap().as_i32(),
+        Some(2022)
+    );
+    assert_eq!(
+        message.get_field_by_name("month").unwrap().as_i32(),
+        Some(2)
+    );
+    assert_eq!(message.get_field_by_name("day").unwrap().as_i32(), Some(28));
+    assert_eq!(google_type::to_naive_date(&message).unwrap(), date);
+
+    let mut message = message;
+    message.set_field_by_name("day", Value::I32(30));
+    assert_eq!(
+        google_type::to_naive_date(&message)
+            .unwrap_err()
+            .to_string(),
+        "value is out of range for message 'google.type.Date'"
+    );
+    message.set_field_by_name("day", Value::I32(0));
+    assert_eq!(
+        google_type::to_naive_date(&message)
+            .unwrap_err()
+            .to_string(),
+        "cannot convert a date with a zero year, month or day"
+    );
+}
+
+#[test]
+fn time_of_day() {
+    let time = NaiveTime::from_hms_nano_opt(13, 45, 10, 500).unwrap();
+    let message = google_type::from_naive_time(desc("TimeOfDay"), time).unwrap();
+    assert_eq!(google_type::to_naive_time(&message).unwrap(), time);
+
+    let leap = NaiveTime::from_hms_nano_opt(23, 59, 59, 1_000_000_001).unwrap();
+    let message = google_type::from_naive_time(desc("TimeOfDay"), leap).unwrap();
+    assert_eq!(
+        message.get_field_by_name("seconds").unwrap().as_i32(),
+        Some(60)
+    );
+    assert_eq!(
+        message.get_field_by_name("nanos").unwrap().as_i32(),
+        Some(1)
+    );
+    assert_eq!(google_type::to_naive_time(&message).unwrap(), leap);
+
+    let mut message = message;
+    message.set_field_by_name("hours", Value::I32(24));
+    assert!(google_type::to_naive_time(&message).is_err());
+}
+
+#[test]
+fn money() {
+    let amount = Decimal::from_str("-12.345").unwrap();
+    let message = google_type::from_decimal(desc("Money"), "GBP", amount).unwrap();
+    assert_eq!(
+        message.get_field_by_name("units").unwrap().as_i64(),
+        Some(-12)
+    );
+    assert_eq!(
+        message.get_field_by_name("nanos").unwrap().as_i32(),
+        Some(-345_000_000)
+    );
+    assert_eq!(
+        google_type::to_decimal(&message).unwrap(),
+        ("GBP".to_owned(), amount)
+    );
+
+    assert!(google_type::from_decimal(
+        desc("Money"),
+        "GBP",
+        Decimal::from_str("0.0000000001").unwrap()
+    )
+    .is_err());
+
+    let mut message = message;
+    message.set_field_by_name("nanos", Value::I32(5));
+    assert_eq!(
+        google_type::to_decimal(&message).unwrap_err().to_string(),
+        "value is out of range for message 'google.type.Money'"
+    );
+}
@@ -0,0 +1,433 @@
+// Service implementations return errors as a `Status`, as required by tonic.
+#![allow(clippy::result_large_err)]
+
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use prost_reflect::{
+    grpc::{DynamicClient, DynamicCodec, DynamicService},
+    DynamicMessage, MethodDescriptor, ReflectMessage, ServiceDescriptor, Value,
+};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{
+    body::BoxBody,
+    codegen::{BoxFuture, Service},
+    server::{
+        ClientStreamingService, Grpc, ServerStreamingService, StreamingService, UnaryService,
+    },
+    Code, Request, Response, Status, Streaming,
+};
+
+use crate::test_file_descriptor;
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<DynamicMessage, Status>> + Send>>;
+
+fn echo_service() -> ServiceDescriptor {
+    test_file_descriptor()
+        .get_service_by_name("echo.Echo")
+        .unwrap()
+}
+
+fn echo_method(name: &str) -> MethodDescriptor {
+    echo_service()
+        .methods()
+        .find(|method| method.name() == name)
+        .unwrap()
+}
+
+fn echo_request(message: &str) -> DynamicMessage {
+    let mut request = DynamicMessage::new(echo_method("Unary").input());
+    request.set_field_by_name("message", Value::String(message.to_owned()));
+    request
+}
+
+fn echo_response(method: &MethodDescriptor, messages: Vec<String>) -> DynamicMessage {
+    let mut response = DynamicMessage::new(method.output());
+    response.set_field_by_name(
+        "messages",
+        Value::List(messages.into_iter().map(Value::String).collect()),
+    );
+    response
+}
+
+fn request_message(request: &DynamicMessage) -> String {
+    request
+        .get_field_by_name("message")
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_owned()
+}
+
+fn response_messages(response: &DynamicMessage) -> Vec<String> {
+    response
+        .get_field_by_name("messages")
+        .unwrap()
+        .as_list()
+        .unwrap()
+        .iter()
+        .map(|value| value.as_str().unwrap().to_owned())
+        .collect()
+}
+
+/// A server for the `echo.Echo` service, which is implemented using dynamic messages.
+#[derive(Clone)]
+struct EchoServer;
+
+#[derive(Clone)]
+struct EchoMethod(MethodDescriptor);
+
+impl Service<http::Request<BoxBody>> for EchoServer {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let method = req
+            .uri()
+            .path()
+            .strip_prefix("/echo.Echo/")
+            .map(echo_method)
+            .unwrap();
+        let mut grpc = Grpc::new(DynamicCodec::new(method.input()));
+        let service = EchoMethod(method);
+        Box::pin(async move {
+            Ok(
+                match (
+                    service.0.is_client_streaming(),
+                    service.0.is_server_streaming(),
+                ) {
+                    (false, false) => grpc.unary(service, req).await,
+                    (false, true) => grpc.server_streaming(service, req).await,
+                    (true, false) => grpc.client_streaming(service, req).await,
+                    (true, true) => grpc.streaming(service, req).await,
+                },
+            )
+        })
+    }
+}
+
+impl UnaryService<DynamicMessage> for EchoMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let message = request_message(request.get_ref());
+        let mut response = Response::new(echo_response(&self.0, vec![message.clone()]));
+        if let Some(value) = request.metadata().get("x-echo") {
+            response.metadata_mut().insert("x-echo", value.clone());
+        }
+        Box::pin(async move {
+            if message == "fail" {
+                Err(Status::not_found("echo failed"))
+            } else {
+                Ok(response)
+            }
+        })
+    }
+}
+
+impl ServerStreamingService<DynamicMessage> for EchoMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = ResponseStream;
+    type Future = BoxFuture<Response<ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let message = request_message(request.get_ref());
+        let responses: Vec<_> = (0..3)
+            .map(|index| {
+                Ok(echo_response(
+                    &self.0,
+                    vec![format!("{} {}", message, index)],
+                ))
+            })
+            .collect();
+        Box::pin(async move {
+            Ok(Response::new(
+                Box::pin(tokio_stream::iter(responses)) as ResponseStream
+            ))
+        })
+    }
+}
+
+impl ClientStreamingService<DynamicMessage> for EchoMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.0.clone();
+        Box::pin(async move {
+            let mut requests = request.into_inner();
+            let mut messages = Vec::new();
+            while let Some(request) = requests.message().await? {
+                messages.push(request_message(&request));
+            }
+            Ok(Response::new(echo_response(&method, messages)))
+        })
+    }
+}
+
+impl StreamingService<DynamicMessage> for EchoMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = ResponseStream;
+    type Future = BoxFuture<Response<ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.0.clone();
+        let responses = request.into_inner().map(move |request| {
+            request.map(|request| echo_response(&method, vec![request_message(&request)]))
+        });
+        Box::pin(async move { Ok(Response::new(Box::pin(responses) as ResponseStream)) })
+    }
+}
+
+#[test]
+fn unary() {
+    let method = echo_method("Unary");
+    let mut request = Request::new(echo_request("hello"));
+    request
+        .metadata_mut()
+        .insert("x-echo", "metadata".parse().unwrap());
+
+    let mut client = DynamicClient::new(EchoServer);
+    let response = pollster::block_on(client.unary(&method, request)).unwrap();
+
+    assert_eq!(response.metadata().get("x-echo").unwrap(), "metadata");
+    let response = response.into_inner();
+    assert_eq!(response.descriptor(), method.output());
+    assert_eq!(response_messages(&response), ["hello"]);
+}
+
+#[test]
+fn unary_error_status() {
+    let method = echo_method("Unary");
+    let mut client = DynamicClient::new(EchoServer);
+    let status =
+        pollster::block_on(client.unary(&method, Request::new(echo_request("fail")))).unwrap_err();
+
+    assert_eq!(status.code(), Code::NotFound);
+    assert_eq!(status.message(), "echo failed");
+}
+
+#[test]
+fn unary_invalid_request_type() {
+    let method = echo_method("Unary");
+    let mut client = DynamicClient::new(EchoServer);
+    let request = DynamicMessage::new(method.output());
+    let status = pollster::block_on(client.unary(&method, Request::new(request))).unwrap_err();
+
+    assert_eq!(status.code(), Code::InvalidArgument);
+    assert_eq!(
+        status.message(),
+        "expected a request of type 'echo.EchoRequest' for method 'echo.Echo.Unary', but found 'echo.EchoResponse'"
+    );
+}
+
+#[test]
+fn server_streaming() {
+    let method = echo_method("ServerStreaming");
+    let mut client = DynamicClient::new(EchoServer);
+    let mut responses =
+        pollster::block_on(client.server_streaming(&method, Request::new(echo_request("hello"))))
+            .unwrap()
+            .into_inner();
+
+    let mut messages = Vec::new();
+    while let Some(response) = pollster::block_on(responses.message()).unwrap() {
+        messages.extend(response_messages(&response));
+    }
+    assert_eq!(messages, ["hello 0", "hello 1", "hello 2"]);
+}
+
+#[test]
+fn client_streaming() {
+    let method = echo_method("ClientStreaming");
+    let mut client = DynamicClient::new(EchoServer);
+    let requests = tokio_stream::iter(vec![echo_request("a"), echo_request("b")]);
+    let response = pollster::block_on(client.client_streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response_messages(&response), ["a", "b"]);
+}
+
+#[test]
+fn streaming() {
+    let method = echo_method("Streaming");
+    let mut client = DynamicClient::new(EchoServer);
+    let requests = tokio_stream::iter(vec![echo_request("a"), echo_request("b")]);
+    let mut responses = pollster::block_on(client.streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+
+    let mut messages = Vec::new();
+    while let Some(response) = pollster::block_on(responses.message()).unwrap() {
+        messages.extend(response_messages(&response));
+    }
+    assert_eq!(messages, ["a", "b"]);
+}
+
+fn dynamic_echo_service() -> DynamicService {
+    DynamicService::new(echo_service())
+        .unary(&echo_method("Unary"), |request: Request<DynamicMessage>| {
+            let message = request_message(request.get_ref());
+            async move {
+                Ok(Response::new(echo_response(
+                    &echo_method("Unary"),
+                    vec![message],
+                )))
+            }
+        })
+        .server_streaming(
+            &echo_method("ServerStreaming"),
+            |request: Request<DynamicMessage>| {
+                let message = request_message(request.get_ref());
+                async move {
+                    let responses: Vec<_> = (0..2)
+                        .map(|index| {
+                            Ok(echo_response(
+                                &echo_method("ServerStreaming"),
+                                vec![format!("{} {}", message, index)],
+                            ))
+                        })
+                        .collect();
+                    Ok(Response::new(tokio_stream::iter(responses)))
+                }
+            },
+        )
+        .client_streaming(
+            &echo_method("ClientStreaming"),
+            |request: Request<Streaming<DynamicMessage>>| async move {
+                let mut requests = request.into_inner();
+                let mut messages = Vec::new();
+                while let Some(request) = requests.message().await? {
+                    messages.push(request_message(&request));
+                }
+                Ok(Response::new(echo_response(
+                    &echo_method("ClientStreaming"),
+                    messages,
+                )))
+            },
+        )
+        .streaming(
+            &echo_method("Streaming"),
+            |request: Request<Streaming<DynamicMessage>>| async move {
+                let method = echo_method("Streaming");
+                let responses = request.into_inner().map(move |request| {
+                    request.map(|request| echo_response(&method, vec![request_message(&request)]))
+                });
+                Ok(Response::new(responses))
+            },
+        )
+}
+
+#[test]
+fn dynamic_service_unary() {
+    let method = echo_method("Unary");
+    let mut client = DynamicClient::new(dynamic_echo_service());
+    let response = pollster::block_on(client.unary(&method, Request::new(echo_request("hello"))))
+        .unwrap()
+        .into_inner();
+    assert_eq!(response_messages(&response), ["hello"]);
+}
+
+#[test]
+fn dynamic_service_server_streaming() {
+    let method = echo_method("ServerStreaming");
+    let mut client = DynamicClient::new(dynamic_echo_service());
+    let mut responses =
+        pollster::block_on(client.server_streaming(&method, Request::new(echo_request("hello"))))
+            .unwrap()
+            .into_inner();
+
+    let mut messages = Vec::new();
+    while let Some(response) = pollster::block_on(responses.message()).unwrap() {
+        messages.extend(response_messages(&response));
+    }
+    assert_eq!(messages, ["hello 0", "hello 1"]);
+}
+
+#[test]
+fn dynamic_service_client_streaming() {
+    let method = echo_method("ClientStreaming");
+    let mut client = DynamicClient::new(dynamic_echo_service());
+    let requests = tokio_stream::iter(vec![echo_request("a"), echo_request("b")]);
+    let response = pollster::block_on(client.client_streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+    assert_eq!(response_messages(&response), ["a", "b"]);
+}
+
+#[test]
+fn dynamic_service_streaming() {
+    let method = echo_method("Streaming");
+    let mut client = DynamicClient::new(dynamic_echo_service());
+    let requests = tokio_stream::iter(vec![echo_request("a"), echo_request("b")]);
+    let mut responses = pollster::block_on(client.streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+
+    let mut messages = Vec::new();
+    while let Some(response) = pollster::block_on(responses.message()).unwrap() {
+        messages.extend(response_messages(&response));
+    }
+    assert_eq!(messages, ["a", "b"]);
+}
+
+#[test]
+fn dynamic_service_unregistered_method() {
+    let method = echo_method("Unary");
+    let mut client = DynamicClient::new(DynamicService::new(echo_service()));
+    let status =
+        pollster::block_on(client.unary(&method, Request::new(echo_request("hello")))).unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
+
+#[test]
+fn dynamic_service_invalid_response_type() {
+    let method = echo_method("Unary");
+    let service = DynamicService::new(echo_service()).unary(&method, |request| async move {
+        Ok(Response::new(request.into_inner()))
+    });
+    let mut client = DynamicClient::new(service);
+    let status =
+        pollster::block_on(client.unary(&method, Request::new(echo_request("hello")))).unwrap_err();
+    assert_eq!(status.code(), Code::Internal);
+    assert_eq!(
+        status.message(),
+        "expected a response of type 'echo.EchoResponse' for method 'echo.Echo.Unary', but found 'echo.EchoRequest'"
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "the handler for method 'echo.Echo.ServerStreaming' does not match its streaming type"
+)]
+fn dynamic_service_streaming_type_mismatch() {
+    let _ = DynamicService::new(echo_service()).unary(
+        &echo_method("ServerStreaming"),
+        |request: Request<DynamicMessage>| async move { Ok(Response::new(request.into_inner())) },
+    );
+}
+
+#[test]
+#[should_panic(expected = "method 'library.Library.GetBook' is not part of service 'echo.Echo'")]
+fn dynamic_service_method_from_other_service() {
+    let method = test_file_descriptor()
+        .get_service_by_name("library.Library")
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == "GetBook")
+        .unwrap();
+    let _ = DynamicService::new(echo_service())
+        .unary(&method, |request: Request<DynamicMessage>| async move {
+            Ok(Response::new(request.into_inner()))
+        });
+}
@@ -0,0 +1,225 @@
+use prost::Message;
+use prost_reflect::{
+    framing::{
+        self,
+        web::{self, Frame, FrameDecoder, Mode, Trailers},
+        Compression,
+    },
+    ReflectMessage,
+};
+
+use crate::proto::Scalars;
+
+fn scalars() -> Scalars {
+    Scalars {
+        int32: 5,
+        string: "hello".to_owned(),
+        ..Default::default()
+    }
+}
+
+fn ok_trailers() -> Trailers {
+    let mut trailers = Trailers::new();
+    trailers.append("grpc-status", "0");
+    trailers.append("grpc-message", "all%20good");
+    trailers
+}
+
+fn decode_all(mut decoder: FrameDecoder, body: &[u8], chunk_size: usize) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    for chunk in body.chunks(chunk_size) {
+        decoder.push(chunk);
+        while let Some(frame) = decoder.decode_next().unwrap() {
+            frames.push(frame);
+        }
+    }
+    decoder.finish().unwrap();
+    frames
+}
+
+fn expect_message(frame: &Frame) -> Scalars {
+    match frame {
+        Frame::Message(message) => message.transcode_to().unwrap(),
+        Frame::Trailers(_) => panic!("expected a message frame"),
+    }
+}
+
+#[test]
+fn content_types() {
+    for mode in [Mode::Binary, Mode::Text] {
+        assert_eq!(Mode::from_content_type(mode.content_type()), Some(mode));
+    }
+    assert_eq!(
+        Mode::from_content_type("application/grpc-web"),
+        Some(Mode::Binary)
+    );
+    assert_eq!(
+        Mode::from_content_type("application/grpc-web-text"),
+        Some(Mode::Text)
+    );
+    assert_eq!(Mode::from_content_type("application/grpc"), None);
+    assert_eq!(Mode::from_content_type("application/grpc-web+json"), None);
+}
+
+#[test]
+fn binary_matches_grpc_framing() {
+    let message = scalars();
+    let mut body = Vec::new();
+    web::encode(&message, Compression::Identity, Mode::Binary, &mut body);
+    assert_eq!(
+        body,
+        framing::encode_to_vec(&message, Compression::Identity)
+    );
+}
+
+#[test]
+fn trailers_frame() {
+    let mut body = Vec::new();
+    web::encode_trailers(&ok_trailers(), Mode::Binary, &mut body);
+
+    let block = b"grpc-status: 0\r\ngrpc-message: all%20good\r\n";
+    assert_eq!(body[0], web::TRAILERS_FLAG);
+    assert_eq!(body[1..5], (block.len() as u32).to_be_bytes());
+    assert_eq!(&body[5..], block);
+}
+
+#[test]
+fn binary_round_trip() {
+    let message = scalars();
+    let mut body = Vec::new();
+    web::encode(&message, Compression::Identity, Mode::Binary, &mut body);
+    web::encode(&message, Compression::Gzip, Mode::Binary, &mut body);
+    web::encode_trailers(&ok_trailers(), Mode::Binary, &mut body);
+
+    let decoder = FrameDecoder::new(message.descriptor(), Compression::Gzip, Mode::Binary);
+    let frames = decode_all(decoder, &body, 3);
+    assert_eq!(frames.len(), 3);
+    assert_eq!(expect_message(&frames[0]), message);
+    assert_eq!(expect_message(&frames[1]), message);
+    assert_eq!(frames[2], Frame::Trailers(ok_trailers()));
+}
+
+#[test]
+fn text_round_trip() {
+    let message = scalars();
+    let mut body = Vec::new();
+    web::encode(&message, Compression::Identity, Mode::Text, &mut body);
+    web::encode(
+        &Scalars::default(),
+        Compression::Identity,
+        Mode::Text,
+        &mut body,
+    );
+    web::encode_trailers(&ok_trailers(), Mode::Text, &mut body);
+    // Each frame is padded separately.
+    assert!(body[..body.len() - 1].contains(&b'='));
+
+    for chunk_size in [1, 5, body.len()] {
+        let decoder = FrameDecoder::new(message.descriptor(), Compression::Identity, Mode::Text);
+        let frames = decode_all(decoder, &body, chunk_size);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(expect_message(&frames[0]), message);
+        assert_eq!(expect_message(&frames[1]), Scalars::default());
+        assert_eq!(frames[2], Frame::Trailers(ok_trailers()));
+    }
+}
+
+#[test]
+fn parse_trailers() {
+    let block = b"Grpc-Status: 3\r\ngrpc-message:bad request \r\nx-custom: a: b\r\n";
+    let mut body = vec![web::TRAILERS_FLAG];
+    body.extend((block.len() as u32).to_be_bytes());
+    body.extend(block);
+
+    let mut decoder =
+        FrameDecoder::new(scalars().descriptor(), Compression::Identity, Mode::Binary);
+    decoder.push(&body);
+    let trailers = match decoder.decode_next().unwrap() {
+        Some(Frame::Trailers(trailers)) => trailers,
+        frame => panic!("expected trailers, found {:?}", frame),
+    };
+    assert_eq!(trailers.grpc_status(), Some(3));
+    assert_eq!(trailers.grpc_message(), Some("bad request"));
+    assert_eq!(trailers.get("X-Custom"), Some("a: b"));
+    assert_eq!(
+        trailers.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+        ["grpc-status", "grpc-message", "x-custom"]
+    );
+}
+
+#[test]
+fn invalid_trailers() {
+    let block = b"grpc-status 0\r\n";
+    let mut body = vec![web::TRAILERS_FLAG];
+    body.extend((block.len() as u32).to_be_bytes());
+    body.extend(block);
+
+    let mut decoder =
+        FrameDecoder::new(scalars().descriptor(), Compression::Identity, Mode::Binary);
+    decoder.push(&body);
+    assert_eq!(
+        decoder.decode_next().unwrap_err().to_string(),
+        "invalid trailers frame"
+    );
+}
+
+#[test]
+fn data_after_trailers() {
+    let mut body = Vec::new();
+    web::encode_trailers(&ok_trailers(), Mode::Binary, &mut body);
+    web::encode(&scalars(), Compression::Identity, Mode::Binary, &mut body);
+
+    let mut decoder =
+        FrameDecoder::new(scalars().descriptor(), Compression::Identity, Mode::Binary);
+    decoder.push(&body);
+    assert!(matches!(
+        decoder.decode_next().unwrap(),
+        Some(Frame::Trailers(_))
+    ));
+    assert_eq!(
+        decoder.decode_next().unwrap_err().to_string(),
+        format!(
+            "found {} bytes of data after the message",
+            scalars().encoded_len() + 5
+        )
+    );
+}
+
+#[test]
+fn trailers_flag_rejected_by_grpc_decoder() {
+    let mut body = Vec::new();
+    web::encode_trailers(&ok_trailers(), Mode::Binary, &mut body);
+
+    let mut decoder = framing::FrameDecoder::new(scalars().descriptor(), Compression::Identity);
+    decoder.push(&body);
+    assert_eq!(
+        decoder.decode_next().unwrap_err().to_string(),
+        "invalid compressed flag 128"
+    );
+}
+
+#[test]
+fn invalid_base64() {
+    let mut decoder = FrameDecoder::new(scalars().descriptor(), Compression::Identity, Mode::Text);
+    decoder.push("AAA*");
+    assert!(decoder
+        .decode_next()
+        .unwrap_err()
+        .to_string()
+        .starts_with("invalid base64 data"));
+}
+
+#[test]
+fn incomplete_text() {
+    let mut body = Vec::new();
+    web::encode(&scalars(), Compression::Identity, Mode::Text, &mut body);
+
+    let mut decoder = FrameDecoder::new(scalars().descriptor(), Compression::Identity, Mode::Text);
+    decoder.push(&body[..body.len() - 2]);
+    assert_eq!(decoder.decode_next().unwrap(), None);
+    assert!(decoder
+        .finish()
+        .unwrap_err()
+        .to_string()
+        .starts_with("unexpected end of input"));
+}
@@ -1,20 +1,27 @@
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::Debug,
+    io,
     iter::FromIterator,
+    sync::Arc,
 };
 
 use proptest::{prelude::*, test_runner::TestCaseError};
 use prost::Message;
-use prost_reflect::{DeserializeOptions, DynamicMessage, ReflectMessage, SerializeOptions};
-use prost_types::FileDescriptorSet;
+use prost_reflect::{
+    json_lines, Base64Encoding, CanonicalJsonFormatter, DescriptorPool, DeserializeOptions,
+    DynamicMessage, FieldNameStrategy, FloatFormatter, OutOfRangePolicy, ReflectMessage,
+    SerializeOptions, TimestampPrecision, UnknownFieldCollector,
+};
+use prost_types::{FieldMask, FileDescriptorSet};
 use serde_json::json;
 
 use crate::{
     arbitrary,
     proto::{
-        contains_group, message_with_oneof, ComplexType, ContainsGroup, MessageWithAliasedEnum,
-        MessageWithOneof, Point, ScalarArrays, Scalars, WellKnownTypes,
+        contains_group, message_with_oneof, AddressBook, ComplexType, ContainsGroup,
+        MessageWithAliasedEnum, MessageWithOneof, MessageWithPrefixedEnum, Point, ScalarArrays,
+        Scalars, WellKnownTypes,
     },
     test_file_descriptor,
 };
@@ -219,6 +226,35 @@ fn serialize_complex_type() {
     );
 }
 
+#[test]
+fn serialize_json_into_appends() {
+    let message = ComplexType {
+        int_map: HashMap::from_iter([(
+            3,
+            Scalars {
+                sint64: 8,
+                float: 2.2,
+                ..Default::default()
+            },
+        )]),
+        nested: Some(Scalars {
+            string: "5".to_owned(),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let mut buf = b"prefix ".to_vec();
+    message
+        .serialize_json_into(&mut buf, &SerializeOptions::new())
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(buf).unwrap(),
+        r#"prefix {"intMap":{"3":{"float":2.2,"sint64":"8"}},"nested":{"string":"5"}}"#
+    );
+}
+
 #[test]
 fn serialize_well_known_types() {
     let value = to_json(&WellKnownTypes {
@@ -333,6 +369,63 @@ fn serialize_no_stringify_64_bit_integers() {
     );
 }
 
+#[test]
+fn serialize_no_stringify_64_bit_integers_nested() {
+    let options = SerializeOptions::new().stringify_64_bit_integers(false);
+
+    let value = to_json_with_options(
+        &WellKnownTypes {
+            int64: Some(i64::MIN),
+            uint64: Some(u64::MAX),
+            ..Default::default()
+        },
+        &options,
+    );
+    assert_eq!(
+        value,
+        json!({
+            "int64": i64::MIN,
+            "uint64": u64::MAX,
+        })
+    );
+
+    let value = to_json_with_options(
+        &ScalarArrays {
+            int64: vec![1, -2],
+            fixed64: vec![3],
+            ..Default::default()
+        },
+        &options,
+    );
+    assert_eq!(
+        value,
+        json!({
+            "int64": [1, -2],
+            "fixed64": [3],
+        })
+    );
+
+    let value = to_json_with_options(
+        &ComplexType {
+            int_map: HashMap::from_iter([(
+                1,
+                Scalars {
+                    uint64: 2,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        },
+        &options,
+    );
+    assert_eq!(
+        value,
+        json!({
+            "intMap": { "1": { "uint64": 2 } },
+        })
+    );
+}
+
 #[test]
 fn serialize_use_proto_field_name() {
     let value = to_json_with_options(
@@ -351,6 +444,88 @@ fn serialize_use_proto_field_name() {
     );
 }
 
+#[test]
+fn roundtrip_use_proto_field_name() {
+    let message = ComplexType {
+        string_map: HashMap::from_iter([(
+            "mapKey".to_owned(),
+            Scalars {
+                sfixed32: 1,
+                ..Default::default()
+            },
+        )]),
+        nested: Some(Scalars {
+            sint64: 2,
+            ..Default::default()
+        }),
+        optional_enum: 1,
+        ..Default::default()
+    };
+    let value = to_json_with_options(
+        &message,
+        &SerializeOptions::new().use_proto_field_name(true),
+    );
+    assert_eq!(
+        value,
+        json!({
+            "string_map": {
+                "mapKey": { "sfixed32": 1 },
+            },
+            "nested": { "sint64": "2" },
+            "optional_enum": "FOO",
+        })
+    );
+
+    let roundtripped: ComplexType = from_json(value, "test.ComplexType");
+    assert_eq!(roundtripped, message);
+}
+
+#[test]
+fn serialize_field_name_strategy() {
+    let message = ComplexType {
+        my_enum: vec![1],
+        optional_enum: 3,
+        ..Default::default()
+    };
+    let to_json = |strategy: FieldNameStrategy, options: &SerializeOptions| {
+        let desc = pool_with_field_name_strategy(strategy)
+            .get_message_by_name("test.ComplexType")
+            .unwrap();
+        DynamicMessage::decode(desc, message.encode_to_vec().as_slice())
+            .unwrap()
+            .serialize_with_options(serde_json::value::Serializer, options)
+            .unwrap()
+    };
+
+    assert_eq!(
+        to_json(FieldNameStrategy::KebabCase, &SerializeOptions::new()),
+        json!({
+            "my-enum": ["FOO"],
+            "optional-enum": "BAR",
+        })
+    );
+    assert_eq!(
+        to_json(
+            FieldNameStrategy::KebabCase,
+            &SerializeOptions::new().use_proto_field_name(true),
+        ),
+        json!({
+            "my_enum": ["FOO"],
+            "optional_enum": "BAR",
+        })
+    );
+    assert_eq!(
+        to_json(
+            FieldNameStrategy::Custom(Arc::new(|field| format!("f{}", field.number()))),
+            &SerializeOptions::new(),
+        ),
+        json!({
+            "f4": ["FOO"],
+            "f5": "BAR",
+        })
+    );
+}
+
 #[test]
 fn serialize_use_enum_numbers() {
     let value = to_json_with_options(
@@ -369,6 +544,26 @@ fn serialize_use_enum_numbers() {
     );
 }
 
+#[test]
+fn roundtrip_use_enum_numbers() {
+    let message = ComplexType {
+        my_enum: vec![0, 1, 3, -4, 7],
+        optional_enum: 3,
+        ..Default::default()
+    };
+    let value = to_json_with_options(&message, &SerializeOptions::new().use_enum_numbers(true));
+    assert_eq!(
+        value,
+        json!({
+            "myEnum": [0, 1, 3, -4, 7],
+            "optionalEnum": 3,
+        })
+    );
+
+    let roundtripped: ComplexType = from_json(value, "test.ComplexType");
+    assert_eq!(roundtripped, message);
+}
+
 #[test]
 fn serialize_skip_default_fields() {
     let value = to_json_with_options(
@@ -433,6 +628,446 @@ fn serialize_string_skip_default_fields() {
     );
 }
 
+#[test]
+fn serialize_emit_nulls_for_absent_optionals() {
+    let options = SerializeOptions::new().emit_nulls_for_absent_optionals(true);
+
+    let value = to_json_with_options(
+        &ComplexType {
+            optional_enum: 1,
+            ..Default::default()
+        },
+        &options,
+    );
+    assert_eq!(value, json!({ "optionalEnum": "FOO", "nested": null }));
+
+    let value = to_json_with_options(
+        &ComplexType {
+            nested: Some(Scalars::default()),
+            ..Default::default()
+        },
+        &options.clone().skip_default_fields(false),
+    );
+    assert_eq!(value["nested"]["int32"], json!(0));
+    assert!(value.as_object().unwrap().values().all(|v| !v.is_null()));
+
+    let value = to_json_with_options(
+        &MessageWithOneof {
+            test_oneof: Some(message_with_oneof::TestOneof::OneofField2(5)),
+        },
+        &options,
+    );
+    assert_eq!(
+        value,
+        json!({
+            "oneofField2": 5,
+            "oneofField1": null,
+            "oneofNull": null,
+            "oneofValueNull": null,
+        })
+    );
+}
+
+#[test]
+fn serialize_timestamp_precision() {
+    fn timestamp_json(
+        seconds: i64,
+        nanos: i32,
+        precision: TimestampPrecision,
+    ) -> serde_json::Value {
+        to_json_with_options(
+            &prost_types::Timestamp { seconds, nanos },
+            &SerializeOptions::new().timestamp_precision(precision),
+        )
+    }
+
+    let cases = [
+        (0, TimestampPrecision::Standard, "1972-01-01T10:00:20Z"),
+        (0, TimestampPrecision::Minimal, "1972-01-01T10:00:20Z"),
+        (0, TimestampPrecision::Seconds, "1972-01-01T10:00:20Z"),
+        (0, TimestampPrecision::Millis, "1972-01-01T10:00:20.000Z"),
+        (0, TimestampPrecision::Micros, "1972-01-01T10:00:20.000000Z"),
+        (
+            0,
+            TimestampPrecision::Nanos,
+            "1972-01-01T10:00:20.000000000Z",
+        ),
+        (
+            21_000_000,
+            TimestampPrecision::Standard,
+            "1972-01-01T10:00:20.021Z",
+        ),
+        (
+            21_000_000,
+            TimestampPrecision::Minimal,
+            "1972-01-01T10:00:20.021Z",
+        ),
+        (
+            20_000_000,
+            TimestampPrecision::Minimal,
+            "1972-01-01T10:00:20.02Z",
+        ),
+        (
+            21_000_000,
+            TimestampPrecision::Seconds,
+            "1972-01-01T10:00:20Z",
+        ),
+        (
+            21_000_000,
+            TimestampPrecision::Micros,
+            "1972-01-01T10:00:20.021000Z",
+        ),
+        (
+            21_000_100,
+            TimestampPrecision::Standard,
+            "1972-01-01T10:00:20.021000100Z",
+        ),
+        (
+            21_000_100,
+            TimestampPrecision::Minimal,
+            "1972-01-01T10:00:20.0210001Z",
+        ),
+        (
+            21_000_100,
+            TimestampPrecision::Millis,
+            "1972-01-01T10:00:20.021Z",
+        ),
+        (
+            999_999_999,
+            TimestampPrecision::Micros,
+            "1972-01-01T10:00:20.999999Z",
+        ),
+        (
+            1,
+            TimestampPrecision::Minimal,
+            "1972-01-01T10:00:20.000000001Z",
+        ),
+    ];
+    for (nanos, precision, expected) in cases {
+        assert_eq!(
+            timestamp_json(63_108_020, nanos, precision),
+            json!(expected),
+            "{} {:?}",
+            nanos,
+            precision
+        );
+    }
+
+    assert_eq!(
+        timestamp_json(-1, 500_000_000, TimestampPrecision::Nanos),
+        json!("1969-12-31T23:59:59.500000000Z")
+    );
+}
+
+#[test]
+fn serialize_out_of_range_policy() {
+    let too_early = prost_types::Timestamp {
+        seconds: -62_135_596_801,
+        nanos: 5,
+    };
+    let too_late = prost_types::Timestamp {
+        seconds: 253_402_300_800,
+        nanos: 0,
+    };
+    let too_long = prost_types::Duration {
+        seconds: 315_576_000_001,
+        nanos: 0,
+    };
+    let too_short = prost_types::Duration {
+        seconds: -315_576_000_001,
+        nanos: -1,
+    };
+
+    let err = too_early
+        .transcode_to_dynamic()
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "timestamp out of range");
+    let err = too_long
+        .transcode_to_dynamic()
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new())
+        .unwrap_err();
+    assert_eq!(err.to_string(), "duration out of range");
+
+    let clamp = SerializeOptions::new().out_of_range_policy(OutOfRangePolicy::Clamp);
+    assert_eq!(
+        to_json_with_options(&too_early, &clamp),
+        json!("0001-01-01T00:00:00Z")
+    );
+    assert_eq!(
+        to_json_with_options(&too_late, &clamp),
+        json!("9999-12-31T23:59:59.999999999Z")
+    );
+    assert_eq!(
+        to_json_with_options(&too_long, &clamp),
+        json!("315576000000.999999999s")
+    );
+    assert_eq!(
+        to_json_with_options(&too_short, &clamp),
+        json!("-315576000000.999999999s")
+    );
+
+    let raw = SerializeOptions::new().out_of_range_policy(OutOfRangePolicy::Raw);
+    assert_eq!(
+        to_json_with_options(&too_early, &raw),
+        json!({ "seconds": "-62135596801", "nanos": 5 })
+    );
+    assert_eq!(
+        to_json_with_options(&too_short, &raw),
+        json!({ "seconds": "-315576000001", "nanos": -1 })
+    );
+
+    // Values in range are unaffected.
+    let in_range = WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: 0,
+            nanos: 0,
+        }),
+        duration: Some(prost_types::Duration {
+            seconds: -1,
+            nanos: -500_000_000,
+        }),
+        ..Default::default()
+    };
+    for options in [clamp, raw] {
+        assert_eq!(
+            to_json_with_options(&in_range, &options),
+            json!({
+                "timestamp": "1970-01-01T00:00:00Z",
+                "duration": "-1.500s",
+            })
+        );
+    }
+}
+
+#[test]
+fn serialize_field_mask() {
+    fn mask(paths: &[&str]) -> SerializeOptions {
+        SerializeOptions::new().field_mask(FieldMask {
+            paths: paths.iter().map(|&path| path.to_owned()).collect(),
+        })
+    }
+
+    let scalars = |int32| Scalars {
+        int32,
+        string: "foo".to_owned(),
+        ..Default::default()
+    };
+    let message = ComplexType {
+        string_map: HashMap::from_iter([
+            ("a".to_owned(), scalars(1)),
+            ("b.c".to_owned(), scalars(2)),
+        ]),
+        int_map: HashMap::from_iter([(3, scalars(3)), (4, scalars(4))]),
+        nested: Some(scalars(5)),
+        my_enum: vec![1],
+        optional_enum: 1,
+    };
+
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["optional_enum", "nested.int32"])),
+        json!({ "optionalEnum": "FOO", "nested": { "int32": 5 } })
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["nested", "nested.int32"])),
+        json!({ "nested": { "int32": 5, "string": "foo" } })
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["string_map.`b.c`", "int_map.4.string"])),
+        json!({
+            "stringMap": { "b.c": { "int32": 2, "string": "foo" } },
+            "intMap": { "4": { "string": "foo" } },
+        })
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["int_map.*.int32", "int_map.3"])),
+        json!({
+            "intMap": {
+                "3": { "int32": 3, "string": "foo" },
+                "4": { "int32": 4 },
+            },
+        })
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["*.int32", "my_enum"])),
+        json!({
+            "stringMap": {},
+            "intMap": {},
+            "nested": { "int32": 5 },
+            "myEnum": ["FOO"],
+            "optionalEnum": "FOO",
+        })
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["*"])),
+        to_json_with_options(&message, &SerializeOptions::new())
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&[])),
+        to_json_with_options(&message, &SerializeOptions::new())
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["missing", "nested.missing"])),
+        json!({ "nested": {} })
+    );
+
+    let message = ContainsGroup {
+        repeatedgroup: vec![
+            contains_group::RepeatedGroup {
+                e: "foo".to_owned(),
+                f: Some(1),
+            },
+            contains_group::RepeatedGroup {
+                e: "bar".to_owned(),
+                f: None,
+            },
+        ],
+        ..Default::default()
+    };
+    let expected = json!({ "repeatedgroup": [{ "e": "foo" }, { "e": "bar" }] });
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["repeatedgroup.e"])),
+        expected
+    );
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["repeatedgroup.*.e"])),
+        expected
+    );
+
+    let message = WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: 0,
+            nanos: 0,
+        }),
+        int32: Some(5),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_json_with_options(&message, &mask(&["timestamp.seconds", "int32.value"])),
+        json!({ "timestamp": "1970-01-01T00:00:00Z", "int32": 5 })
+    );
+
+    let options = mask(&["nested.int32", "optional_enum"])
+        .skip_default_fields(false)
+        .emit_nulls_for_absent_optionals(true);
+    assert_eq!(
+        to_json_with_options(&ComplexType::default(), &options),
+        json!({ "optionalEnum": "DEFAULT", "nested": null })
+    );
+}
+
+#[test]
+fn serialize_float_formatter() {
+    let message = Scalars {
+        double: -0.0,
+        float: 0.1,
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    let options = SerializeOptions::new().skip_default_fields(false);
+
+    let formatter = FloatFormatter::new().precision(2).negative_zero(false);
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), formatter);
+    message.serialize_with_options(&mut ser, &options).unwrap();
+    let json = String::from_utf8(ser.into_inner()).unwrap();
+    assert!(json.contains(r#""double":0.00,"float":0.10,"#), "{}", json);
+}
+
+#[test]
+fn serialize_canonical() {
+    let value = to_canonical_json_string(
+        Scalars {
+            double: 1e21,
+            float: 0.1,
+            int32: 3,
+            int64: 4,
+            bool: true,
+            string: "\u{7f}\u{1f}".to_owned(),
+            ..Default::default()
+        }
+        .transcode_to_dynamic(),
+    );
+    assert_eq!(
+        value,
+        "{\"bool\":true,\"double\":1e+21,\"float\":0.1,\"int32\":3,\"int64\":\"4\",\"string\":\"\u{7f}\\u001f\"}"
+    );
+}
+
+#[test]
+fn serialize_canonical_sorts_map_keys() {
+    let value = to_canonical_json_string(
+        ComplexType {
+            string_map: HashMap::from_iter([
+                ("b".to_owned(), Scalars::default()),
+                ("\u{e000}".to_owned(), Scalars::default()),
+                ("a".to_owned(), Scalars::default()),
+                ("\u{10000}".to_owned(), Scalars::default()),
+            ]),
+            int_map: HashMap::from_iter([
+                (10, Scalars::default()),
+                (-1, Scalars::default()),
+                (9, Scalars::default()),
+            ]),
+            ..Default::default()
+        }
+        .transcode_to_dynamic(),
+    );
+    assert_eq!(
+        value,
+        "{\"intMap\":{\"-1\":{},\"10\":{},\"9\":{}},\"stringMap\":{\"a\":{},\"b\":{},\"\u{10000}\":{},\"\u{e000}\":{}}}"
+    );
+}
+
+#[test]
+fn serialize_canonical_well_known_types() {
+    let value = to_canonical_json_string(
+        WellKnownTypes {
+            r#struct: Some(prost_types::Struct {
+                fields: BTreeMap::from_iter([
+                    (
+                        "\u{e000}".to_owned(),
+                        prost_types::Value {
+                            kind: Some(prost_types::value::Kind::NumberValue(2.0)),
+                        },
+                    ),
+                    (
+                        "\u{10000}".to_owned(),
+                        prost_types::Value {
+                            kind: Some(prost_types::value::Kind::NumberValue(0.000001)),
+                        },
+                    ),
+                ]),
+            }),
+            float: Some(1.5),
+            ..Default::default()
+        }
+        .transcode_to_dynamic(),
+    );
+    assert_eq!(
+        value,
+        "{\"float\":1.5,\"struct\":{\"\u{10000}\":0.000001,\"\u{e000}\":2}}"
+    );
+
+    let mut any = DynamicMessage::new(
+        test_file_descriptor()
+            .get_message_by_name("google.protobuf.Any")
+            .unwrap(),
+    );
+    any.transcode_from(&prost_types::Any {
+        type_url: "type.googleapis.com/test.Point".to_owned(),
+        value: Point {
+            longitude: 1,
+            latitude: 2,
+        }
+        .encode_to_vec(),
+    })
+    .unwrap();
+    assert_eq!(
+        to_canonical_json_string(any),
+        r#"{"@type":"type.googleapis.com/test.Point","latitude":2,"longitude":1}"#
+    );
+}
+
 #[test]
 fn deserialize_scalars() {
     let value: Scalars = from_json(
@@ -479,79 +1114,798 @@ fn deserialize_scalars() {
 }
 
 #[test]
-fn deserialize_scalars_float_extrema() {
-    let inf: Scalars = from_json(
+fn deserialize_scalars_float_extrema() {
+    let inf: Scalars = from_json(
+        json!({
+            "double": "Infinity",
+            "float": "Infinity",
+        }),
+        "test.Scalars",
+    );
+    let neg_inf: Scalars = from_json(
+        json!({
+            "double": "-Infinity",
+            "float": "-Infinity",
+        }),
+        "test.Scalars",
+    );
+    let nan: Scalars = from_json(
+        json!({
+            "double": "NaN",
+            "float": "NaN",
+        }),
+        "test.Scalars",
+    );
+
+    assert_eq!(
+        inf,
+        Scalars {
+            float: f32::INFINITY,
+            double: f64::INFINITY,
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        neg_inf,
+        Scalars {
+            float: f32::NEG_INFINITY,
+            double: f64::NEG_INFINITY,
+            ..Default::default()
+        },
+    );
+    assert!(nan.float.is_nan());
+    assert!(nan.double.is_nan());
+}
+
+#[test]
+fn deserialize_scalars_empty() {
+    let value: Scalars = from_json(json!({}), "test.Scalars");
+
+    assert_eq!(value, Scalars::default());
+}
+
+#[test]
+#[should_panic(expected = "unrecognized field name 'unknown_field'")]
+fn deserialize_deny_unknown_fields() {
+    from_json_with_options::<Scalars>(
+        json!({
+            "unknown_field": 123,
+        }),
+        "test.Scalars",
+        &DeserializeOptions::new(),
+    );
+}
+
+#[test]
+fn deserialize_relaxed_enum_names() {
+    fn status(json: &str, options: &DeserializeOptions) -> Option<i32> {
+        let json = format!(r#"{{ "status": "{}" }}"#, json);
+        let message =
+            try_from_json_string_with_options(&json, "test.MessageWithPrefixedEnum", options)
+                .ok()?;
+        Some(
+            message
+                .transcode_to::<MessageWithPrefixedEnum>()
+                .unwrap()
+                .status,
+        )
+    }
+
+    let strict = DeserializeOptions::new();
+    assert_eq!(status("CONTACT_STATUS_ACTIVE", &strict), Some(1));
+    assert_eq!(status("contact_status_active", &strict), None);
+    assert_eq!(status("ACTIVE", &strict), None);
+
+    let case_insensitive = DeserializeOptions::new().case_insensitive_enum_names(true);
+    assert_eq!(status("contact_status_active", &case_insensitive), Some(1));
+    assert_eq!(status("Inactive", &case_insensitive), Some(2));
+    assert_eq!(status("ACTIVE", &case_insensitive), None);
+
+    let unprefixed = DeserializeOptions::new().allow_unprefixed_enum_names(true);
+    assert_eq!(status("ACTIVE", &unprefixed), Some(1));
+    assert_eq!(status("UNSPECIFIED", &unprefixed), Some(0));
+    assert_eq!(status("INACTIVE", &unprefixed), Some(2));
+    assert_eq!(status("CONTACT_STATUS_INACTIVE", &unprefixed), Some(2));
+    assert_eq!(status("active", &unprefixed), None);
+    assert_eq!(status("STATUS_ACTIVE", &unprefixed), None);
+
+    let both = unprefixed.case_insensitive_enum_names(true);
+    assert_eq!(status("active", &both), Some(1));
+    assert_eq!(status("contact_status_inactive", &both), Some(2));
+    assert_eq!(status("Contact_Status_Active", &both), Some(1));
+
+    let value: ComplexType = from_json_with_options(
+        json!({ "myEnum": ["foo", "Bar"], "optionalEnum": "neg" }),
+        "test.ComplexType",
+        &DeserializeOptions::new().case_insensitive_enum_names(true),
+    );
+    assert_eq!(value.my_enum, vec![1, 3]);
+    assert_eq!(value.optional_enum, -4);
+}
+
+#[test]
+fn deserialize_closed_enum_numbers() {
+    // Undefined numbers are accepted by default.
+    let message = try_from_json_string_with_options(
+        r#"{ "value": 5 }"#,
+        "test2.Enums",
+        &DeserializeOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        message.get_field_by_name("value").unwrap().as_enum_number(),
+        Some(5)
+    );
+
+    let options = DeserializeOptions::new().closed_enums(true);
+    let message = try_from_json_string_with_options(
+        r#"{ "value": 3, "values": [2], "labels": { "a": 0, "b": "MAP_ONE" } }"#,
+        "test2.Enums",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        message.get_field_by_name("value").unwrap().as_enum_number(),
+        Some(3)
+    );
+    assert_eq!(
+        message.get_field_by_name("labels").unwrap().as_ref(),
+        &prost_reflect::Value::Map(HashMap::from_iter([
+            (
+                prost_reflect::MapKey::String("a".to_owned()),
+                prost_reflect::Value::EnumNumber(0)
+            ),
+            (
+                prost_reflect::MapKey::String("b".to_owned()),
+                prost_reflect::Value::EnumNumber(1)
+            ),
+        ]))
+    );
+
+    let err = try_from_json_string_with_options(r#"{ "value": 5 }"#, "test2.Enums", &options)
+        .unwrap_err();
+    assert!(err.to_string().contains("unrecognized enum value 5"));
+    assert!(try_from_json_string_with_options(
+        r#"{ "labels": { "a": 4 } }"#,
+        "test2.Enums",
+        &options
+    )
+    .is_err());
+
+    // Open enums accept any number.
+    let value: ComplexType = from_json(json!({ "optionalEnum": 42 }), "test.ComplexType");
+    assert_eq!(value.optional_enum, 42);
+}
+
+#[test]
+fn deserialize_deny_duplicate_keys() {
+    let message = try_from_json_string_with_options(
+        r#"{ "int32": 1, "int32": 2 }"#,
+        "test.Scalars",
+        &DeserializeOptions::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        message.get_field_by_name("int32").unwrap().as_i32(),
+        Some(2)
+    );
+
+    let options = DeserializeOptions::conformance();
+    let err = try_from_json_string_with_options(
+        r#"{ "int32": 1, "int32": 2 }"#,
+        "test.Scalars",
+        &options,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("duplicate field 'int32'"));
+
+    let err = try_from_json_string_with_options(
+        r#"{ "stringMap": {}, "string_map": {} }"#,
+        "test.ComplexType",
+        &options,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("duplicate field 'string_map'"));
+
+    let err = try_from_json_string_with_options(
+        r#"{ "intMap": { "1": {}, "1": {} } }"#,
+        "test.ComplexType",
+        &options,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("duplicate map key '1'"));
+
+    let message = try_from_json_string_with_options(
+        r#"{ "stringMap": { "a": {}, "b": {} }, "intMap": { "1": {} } }"#,
+        "test.ComplexType",
+        &options,
+    )
+    .unwrap();
+    assert!(message.has_field_by_name("string_map"));
+}
+
+#[test]
+fn deserialize_lenient_numbers() {
+    fn scalars(json: &str, options: &DeserializeOptions) -> Option<Scalars> {
+        try_from_json_string_with_options(json, "test.Scalars", options)
+            .ok()
+            .map(|message| message.transcode_to().unwrap())
+    }
+
+    let default = DeserializeOptions::new();
+    let value = scalars(
+        r#"{ "int32": "-5", "uint64": "18446744073709551615", "double": "1.5" }"#,
+        &default,
+    )
+    .unwrap();
+    assert_eq!(value.int32, -5);
+    assert_eq!(value.uint64, u64::MAX);
+    assert_eq!(value.double, 1.5);
+    assert_eq!(
+        scalars(r#"{ "int32": 1e3, "int64": 42.0 }"#, &default)
+            .unwrap()
+            .int32,
+        1000
+    );
+    assert!(scalars(r#"{ "int32": "1.0" }"#, &default).is_none());
+    assert!(scalars(r#"{ "int32": "1e3" }"#, &default).is_none());
+    assert!(scalars(r#"{ "int64": 9223372036854775808.0 }"#, &default).is_none());
+    assert!(scalars(r#"{ "uint64": 18446744073709551616.0 }"#, &default).is_none());
+
+    let unquoted = DeserializeOptions::new().quoted_numbers(false);
+    assert!(scalars(r#"{ "int32": "5" }"#, &unquoted).is_none());
+    assert!(scalars(r#"{ "float": "1.5" }"#, &unquoted).is_none());
+    assert_eq!(scalars(r#"{ "int32": 5 }"#, &unquoted).unwrap().int32, 5);
+    assert!(scalars(r#"{ "double": "NaN" }"#, &unquoted)
+        .unwrap()
+        .double
+        .is_nan());
+
+    let fractions = DeserializeOptions::new().allow_integer_fractions(true);
+    assert_eq!(
+        scalars(r#"{ "int32": "-7.00" }"#, &fractions)
+            .unwrap()
+            .int32,
+        -7
+    );
+    assert_eq!(
+        scalars(r#"{ "uint32": "0.0" }"#, &fractions)
+            .unwrap()
+            .uint32,
+        0
+    );
+    assert!(scalars(r#"{ "int32": "7.5" }"#, &fractions).is_none());
+    assert!(scalars(r#"{ "int32": "1e3" }"#, &fractions).is_none());
+    assert!(scalars(r#"{ "uint32": "-1.0" }"#, &fractions).is_none());
+
+    let exponents = DeserializeOptions::new().allow_integer_exponents(true);
+    assert_eq!(
+        scalars(r#"{ "int32": "1e3" }"#, &exponents).unwrap().int32,
+        1000
+    );
+    assert_eq!(
+        scalars(r#"{ "int32": "1.25E2" }"#, &exponents)
+            .unwrap()
+            .int32,
+        125
+    );
+    assert_eq!(
+        scalars(r#"{ "int32": "1200e-2" }"#, &exponents)
+            .unwrap()
+            .int32,
+        12
+    );
+    assert_eq!(
+        scalars(r#"{ "int64": "9.223372036854775807e18" }"#, &exponents)
+            .unwrap()
+            .int64,
+        i64::MAX
+    );
+    assert!(scalars(r#"{ "int64": "9.223372036854775808e18" }"#, &exponents).is_none());
+    assert!(scalars(r#"{ "int32": "1.5e0" }"#, &exponents).is_none());
+    assert!(scalars(r#"{ "int32": "1e10" }"#, &exponents).is_none());
+    assert!(scalars(r#"{ "int32": "1.0" }"#, &exponents).is_none());
+
+    let value: WellKnownTypes = from_json_with_options(
+        json!({ "int32": "2.0", "uint64": "3e1" }),
+        "test.WellKnownTypes",
+        &exponents.allow_integer_fractions(true),
+    );
+    assert_eq!(value.int32, Some(2));
+    assert_eq!(value.uint64, Some(30));
+}
+
+#[test]
+fn deserialize_error_path() {
+    fn error(json: &str, message_name: &str) -> (String, Option<serde_json::Value>, String) {
+        let desc = test_file_descriptor()
+            .get_message_by_name(message_name)
+            .unwrap();
+        let mut de = serde_json::Deserializer::from_str(json);
+        let err =
+            DynamicMessage::deserialize_with_error_path(desc, &mut de, &DeserializeOptions::new())
+                .unwrap_err();
+        (
+            err.path().to_owned(),
+            err.value().map(Result::unwrap),
+            err.inner().to_string(),
+        )
+    }
+
+    let (path, value, _) = error(r#"{"nested":{"int32":"x"}}"#, "test.ComplexType");
+    assert_eq!(path, "nested.int32");
+    assert_eq!(value, Some(json!("x")));
+
+    let (path, value, _) = error(
+        r#"{"contacts":[{},{"name":"a"},{"location":{"latitude":1.5}}]}"#,
+        "test.AddressBook",
+    );
+    assert_eq!(path, "contacts[2].location.latitude");
+    assert_eq!(value, Some(json!(1.5)));
+
+    let (path, value, _) = error(r#"{"myEnum":["FOO","BAZ"]}"#, "test.ComplexType");
+    assert_eq!(path, "myEnum[1]");
+    assert_eq!(value, Some(json!("BAZ")));
+
+    let (path, value, _) = error(r#"{"string_map":{"a.b":{"bool":1}}}"#, "test.ComplexType");
+    assert_eq!(path, "string_map[a.b].bool");
+    assert_eq!(value, Some(json!(1)));
+
+    let (path, value, message) = error(r#"{"intMap":{"x":{}}}"#, "test.ComplexType");
+    assert_eq!(path, "intMap[x]");
+    assert_eq!(value, Some(json!("x")));
+    assert!(message.starts_with("invalid digit found in string"));
+
+    let (path, value, message) = error(r#"{"nested":{"unknown":1}}"#, "test.ComplexType");
+    assert_eq!(path, "nested.unknown");
+    assert_eq!(value, None);
+    assert!(message.starts_with("unrecognized field name 'unknown'"));
+
+    let (path, value, _) = error(
+        r#"{"timestamp":"1970-01-01T00:00:00Z","int32":5,"list":[1,{}],"mask":"a.b","duration":"1"}"#,
+        "test.WellKnownTypes",
+    );
+    assert_eq!(path, "duration");
+    assert_eq!(value, Some(json!("1")));
+
+    let (path, value, _) = error(
+        r#"{"@type":"type.googleapis.com/test.Point","latitude":1,"longitude":[]}"#,
+        "google.protobuf.Any",
+    );
+    assert_eq!(path, "longitude");
+    assert_eq!(value, Some(json!([])));
+
+    let (path, value, message) = error(r#"{"nested":{"int32":1"#, "test.ComplexType");
+    assert_eq!(path, "nested");
+    assert_eq!(value, None);
+    assert!(message.starts_with("EOF while parsing an object"));
+
+    let (path, value, _) = error("[]", "test.ComplexType");
+    assert_eq!(path, "");
+    assert_eq!(value, None);
+}
+
+#[test]
+fn deserialize_collecting_errors() {
+    type Errors = Vec<(String, Option<serde_json::Value>, String)>;
+
+    fn collect(json: &str, message_name: &str) -> Result<(DynamicMessage, Errors), String> {
+        let desc = test_file_descriptor()
+            .get_message_by_name(message_name)
+            .unwrap();
+        let mut de = serde_json::Deserializer::from_str(json);
+        match DynamicMessage::deserialize_collecting_errors(
+            desc,
+            &mut de,
+            &DeserializeOptions::new(),
+        ) {
+            Ok((message, errors)) => {
+                de.end().unwrap();
+                let errors = errors
+                    .into_iter()
+                    .map(|err| {
+                        (
+                            err.path().to_owned(),
+                            err.value().map(Result::unwrap),
+                            err.inner().to_string(),
+                        )
+                    })
+                    .collect();
+                Ok((message, errors))
+            }
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    let (message, errors) = collect(
+        r#"{
+            "optionalEnum": "BAZ",
+            "myEnum": ["FOO", 1.5, "BAR", {}],
+            "nested": { "int32": "x", "int64": "5", "bogus": [1, {"a": 2}] },
+            "stringMap": { "a": { "bool": 1 }, "b": { "uint32": 3 } },
+            "intMap": { "x": { "int32": 1 }, "2": [true], "3": {} }
+        }"#,
+        "test.ComplexType",
+    )
+    .unwrap();
+    assert_eq!(
+        message.transcode_to::<ComplexType>().unwrap(),
+        ComplexType {
+            my_enum: vec![1, 3],
+            nested: Some(Scalars {
+                int64: 5,
+                ..Default::default()
+            }),
+            string_map: HashMap::from_iter([
+                ("a".to_owned(), Scalars::default()),
+                (
+                    "b".to_owned(),
+                    Scalars {
+                        uint32: 3,
+                        ..Default::default()
+                    }
+                ),
+            ]),
+            int_map: HashMap::from_iter([(3, Scalars::default())]),
+            ..Default::default()
+        }
+    );
+
+    let paths: Vec<_> = errors
+        .iter()
+        .map(|(path, value, _)| (path.as_str(), value.clone()))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            ("optionalEnum", Some(json!("BAZ"))),
+            ("myEnum[1]", Some(json!(1.5))),
+            ("myEnum[3]", Some(json!({}))),
+            ("nested.int32", Some(json!("x"))),
+            ("nested.bogus", None),
+            ("stringMap[a].bool", Some(json!(1))),
+            ("intMap[x]", Some(json!("x"))),
+            ("intMap[2]", None),
+        ]
+    );
+    assert_eq!(errors[4].2, "unrecognized field name 'bogus'");
+    assert!(errors[7]
+        .2
+        .starts_with("invalid type: sequence, expected a map"));
+
+    let (message, errors) = collect(
+        r#"{"contacts": [{"name": "a", "age": true}, 5, {"location": "here", "score": 1}]}"#,
+        "test.AddressBook",
+    )
+    .unwrap();
+    let contacts = message.transcode_to::<AddressBook>().unwrap().contacts;
+    assert_eq!(contacts.len(), 2);
+    assert_eq!(contacts[0].name, "a");
+    assert_eq!(contacts[1].score, 1.0);
+    let paths: Vec<_> = errors.iter().map(|(path, _, _)| path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec!["contacts[0].age", "contacts[1]", "contacts[2].location"]
+    );
+
+    let (message, errors) = collect(
+        r#"{"oneofField1": "a", "oneofField2": 2}"#,
+        "test.MessageWithOneof",
+    )
+    .unwrap();
+    assert_eq!(
+        message
+            .transcode_to::<MessageWithOneof>()
+            .unwrap()
+            .test_oneof,
+        Some(message_with_oneof::TestOneof::OneofField1("a".to_owned()))
+    );
+    assert_eq!(errors[0].0, "oneofField2");
+    assert_eq!(
+        errors[0].2,
+        "multiple fields provided for oneof 'test_oneof'"
+    );
+
+    let (_, errors) = collect(r#"{"nested": {"int32": 1}}"#, "test.ComplexType").unwrap();
+    assert!(errors.is_empty());
+
+    let err = collect(
+        r#"{"optionalEnum": "BAZ", "nested": {"int32": 1"#,
+        "test.ComplexType",
+    )
+    .unwrap_err();
+    assert!(err.starts_with("nested: EOF while parsing an object"));
+}
+
+#[test]
+fn deserialize_field_name_strategy() {
+    let desc = pool_with_field_name_strategy(FieldNameStrategy::KebabCase)
+        .get_message_by_name("test.ComplexType")
+        .unwrap();
+    let value: ComplexType = DynamicMessage::deserialize(
+        desc,
+        json!({
+            "my-enum": ["FOO"],
+            "optional_enum": "BAR",
+        }),
+    )
+    .unwrap()
+    .transcode_to()
+    .unwrap();
+
+    assert_eq!(
+        value,
+        ComplexType {
+            my_enum: vec![1],
+            optional_enum: 3,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn deserialize_field_name_strategy_added_files() {
+    let mut pool = DescriptorPool::new();
+    pool.set_field_name_strategy(FieldNameStrategy::KebabCase);
+    pool.add_file_descriptor_protos(test_file_descriptor().file_descriptor_protos().cloned())
+        .unwrap();
+    let desc = pool.get_message_by_name("test.ComplexType").unwrap();
+
+    let message = DynamicMessage::deserialize(desc, json!({ "optional-enum": "BAR" })).unwrap();
+    assert_eq!(
+        message
+            .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new())
+            .unwrap(),
+        json!({ "optional-enum": "BAR" })
+    );
+}
+
+#[test]
+#[should_panic(expected = "unrecognized field name 'my-enum'")]
+fn deserialize_field_name_strategy_unset() {
+    from_json_with_options::<ComplexType>(
         json!({
-            "double": "Infinity",
-            "float": "Infinity",
+            "my-enum": ["FOO"],
         }),
-        "test.Scalars",
+        "test.ComplexType",
+        &DeserializeOptions::new(),
     );
-    let neg_inf: Scalars = from_json(
+}
+
+fn pool_with_field_name_strategy(strategy: FieldNameStrategy) -> DescriptorPool {
+    let mut pool = test_file_descriptor();
+    pool.set_field_name_strategy(strategy);
+    pool
+}
+
+#[test]
+fn deserialize_allow_unknown_fields() {
+    let value = from_json_with_options::<Scalars>(
         json!({
-            "double": "-Infinity",
-            "float": "-Infinity",
+            "unknown_field": 123,
         }),
         "test.Scalars",
+        &DeserializeOptions::new().deny_unknown_fields(false),
     );
-    let nan: Scalars = from_json(
+
+    assert_eq!(value, Default::default());
+}
+
+#[test]
+fn deserialize_collect_unknown_fields() {
+    let collector = UnknownFieldCollector::new();
+    let value: ComplexType = from_json_with_options(
         json!({
-            "double": "NaN",
-            "float": "NaN",
+            "unknown": { "a": 1 },
+            "nested": { "int32": 1, "extra": "x" },
+            "stringMap": { "k": { "bogus": true } },
         }),
-        "test.Scalars",
+        "test.ComplexType",
+        &DeserializeOptions::new()
+            .deny_unknown_fields(false)
+            .collect_unknown_fields(collector.clone()),
     );
 
+    assert_eq!(value.nested.unwrap().int32, 1);
+    let mut fields: Vec<(String, serde_json::Value)> = collector
+        .take()
+        .into_iter()
+        .map(|field| (field.path().to_owned(), field.value().unwrap()))
+        .collect();
+    // Fields are collected in document order, which depends on whether serde_json preserves the
+    // order of object keys.
+    fields.sort_by(|a, b| a.0.cmp(&b.0));
     assert_eq!(
-        inf,
-        Scalars {
-            float: f32::INFINITY,
-            double: f64::INFINITY,
-            ..Default::default()
-        },
+        fields,
+        vec![
+            ("nested.extra".to_owned(), json!("x")),
+            ("stringMap[k].bogus".to_owned(), json!(true)),
+            ("unknown".to_owned(), json!({ "a": 1 })),
+        ]
     );
-    assert_eq!(
-        neg_inf,
-        Scalars {
-            float: f32::NEG_INFINITY,
-            double: f64::NEG_INFINITY,
-            ..Default::default()
-        },
+    assert!(collector.take().is_empty());
+}
+
+#[test]
+fn deserialize_collect_unknown_fields_list_index() {
+    let collector = UnknownFieldCollector::new();
+    let _: AddressBook = from_json_with_options(
+        json!({
+            "contacts": [
+                { "name": "a" },
+                { "name": "b", "location": { "latitude": 1, "extra": 2 } },
+            ],
+        }),
+        "test.AddressBook",
+        &DeserializeOptions::new()
+            .deny_unknown_fields(false)
+            .collect_unknown_fields(collector.clone()),
     );
-    assert!(nan.float.is_nan());
-    assert!(nan.double.is_nan());
+
+    let fields: Vec<(String, i32)> = collector
+        .take()
+        .into_iter()
+        .map(|field| (field.path().to_owned(), field.value().unwrap()))
+        .collect();
+    assert_eq!(fields, vec![("contacts[1].location.extra".to_owned(), 2)]);
 }
 
 #[test]
-fn deserialize_scalars_empty() {
-    let value: Scalars = from_json(json!({}), "test.Scalars");
+fn deserialize_collect_unknown_fields_concurrent() {
+    let collector = UnknownFieldCollector::new();
+    let options = DeserializeOptions::new()
+        .deny_unknown_fields(false)
+        .collect_unknown_fields(collector.clone());
+
+    let threads: Vec<_> = (0..8)
+        .map(|i| {
+            let options = options.clone();
+            std::thread::spawn(move || {
+                for _ in 0..100 {
+                    let _: ComplexType = from_json_with_options(
+                        json!({ "nested": { "int32": 1, "extra": i } }),
+                        "test.ComplexType",
+                        &options,
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
 
-    assert_eq!(value, Scalars::default());
+    let fields = collector.take();
+    assert_eq!(fields.len(), 800);
+    assert!(fields.iter().all(|field| field.path() == "nested.extra"));
 }
 
 #[test]
-#[should_panic(expected = "unrecognized field name 'unknown_field'")]
-fn deserialize_deny_unknown_fields() {
-    from_json_with_options::<Scalars>(
+fn deserialize_collect_unknown_fields_any() {
+    let collector = UnknownFieldCollector::new();
+    let options = DeserializeOptions::new()
+        .deny_unknown_fields(false)
+        .collect_unknown_fields(collector.clone());
+
+    let _: prost_types::Any = from_json_with_options(
         json!({
-            "unknown_field": 123,
+            "@type": "type.googleapis.com/test.Point",
+            "latitude": 1,
+            "altitude": 2,
         }),
-        "test.Scalars",
-        &DeserializeOptions::new(),
+        "google.protobuf.Any",
+        &options,
+    );
+    let _: prost_types::Any = from_json_with_options(
+        json!({
+            "@type": "type.googleapis.com/google.protobuf.Int32Value",
+            "value": 1,
+            "extra": 3,
+        }),
+        "google.protobuf.Any",
+        &options,
+    );
+
+    let fields: Vec<(String, i32)> = collector
+        .take()
+        .into_iter()
+        .map(|field| (field.path().to_owned(), field.value().unwrap()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![("altitude".to_owned(), 2), ("extra".to_owned(), 3)]
     );
 }
 
 #[test]
-fn deserialize_allow_unknown_fields() {
-    let value = from_json_with_options::<Scalars>(
+fn roundtrip_any_type_resolver() {
+    let resolver = |type_url: &str| {
+        let message_name = type_url.strip_prefix("example.com/schemas/")?;
+        test_file_descriptor().get_message_by_name(message_name)
+    };
+    let any = prost_types::Any {
+        type_url: "example.com/schemas/test.Point".to_owned(),
+        value: Point {
+            latitude: 1,
+            longitude: 2,
+        }
+        .encode_to_vec(),
+    };
+    let json = json!({
+        "@type": "example.com/schemas/test.Point",
+        "latitude": 1,
+        "longitude": 2,
+    });
+
+    assert_eq!(
+        to_json_with_options(&any, &SerializeOptions::new().type_resolver(resolver)),
+        json
+    );
+    let roundtripped: prost_types::Any = from_json_with_options(
+        json.clone(),
+        "google.protobuf.Any",
+        &DeserializeOptions::new().type_resolver(resolver),
+    );
+    assert_eq!(roundtripped, any);
+
+    let err = DynamicMessage::deserialize(
+        test_file_descriptor()
+            .get_message_by_name("google.protobuf.Any")
+            .unwrap(),
+        json,
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unsupported type url 'example.com/schemas/test.Point'"
+    );
+
+    let err = DynamicMessage::deserialize_with_options(
+        test_file_descriptor()
+            .get_message_by_name("google.protobuf.Any")
+            .unwrap(),
+        json!({ "@type": "example.com/schemas/test.NotFound" }),
+        &DeserializeOptions::new().type_resolver(resolver),
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "message 'test.NotFound' not found");
+}
+
+#[test]
+fn roundtrip_extension_bracket_names() {
+    let message_desc = test_file_descriptor()
+        .get_message_by_name("my.package2.MyMessage")
+        .unwrap();
+    let in_extendee = message_desc.get_extension(111).unwrap();
+    let in_other = message_desc.get_extension(113).unwrap();
+
+    let mut message = DynamicMessage::new(message_desc.clone());
+    message.set_extension(&in_extendee, prost_reflect::Value::I32(5));
+    message.set_extension(&in_other, prost_reflect::Value::F64(1.5));
+
+    let json = message
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new())
+        .unwrap();
+    assert_eq!(
+        json,
         json!({
-            "unknown_field": 123,
-        }),
-        "test.Scalars",
-        &DeserializeOptions::new().deny_unknown_fields(false),
+            "[my.package2.MyMessage.in_extendee]": 5,
+            "[my.package2.OtherMessage.in_other]": 1.5,
+        })
     );
 
-    assert_eq!(value, Default::default());
+    let roundtripped = DynamicMessage::deserialize(message_desc.clone(), json).unwrap();
+    assert_eq!(roundtripped, message);
+
+    let err = DynamicMessage::deserialize(
+        message_desc,
+        json!({ "[my.package2.OtherMessage.unknown]": 1 }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unrecognized field name '[my.package2.OtherMessage.unknown]'"
+    );
 }
 
 #[test]
@@ -1166,6 +2520,43 @@ fn bytes_forgiving_decode() {
     );
 }
 
+#[test]
+fn bytes_encoding() {
+    let message = Scalars {
+        bytes: b"\xfb\xff\xfe\x00".to_vec(),
+        ..Default::default()
+    };
+    let cases = [
+        (Base64Encoding::Standard, "+//+AA=="),
+        (Base64Encoding::StandardNoPad, "+//+AA"),
+        (Base64Encoding::UrlSafe, "-__-AA=="),
+        (Base64Encoding::UrlSafeNoPad, "-__-AA"),
+    ];
+    for (encoding, expected) in cases {
+        let options = SerializeOptions::new().bytes_encoding(encoding);
+        assert_eq!(
+            to_json_with_options(&message, &options),
+            json!({ "bytes": expected }),
+            "{:?}",
+            encoding
+        );
+        let decoded: Scalars = from_json(json!({ "bytes": expected }), "test.Scalars");
+        assert_eq!(decoded, message, "{:?}", encoding);
+    }
+
+    let options = SerializeOptions::new().bytes_encoding(Base64Encoding::UrlSafeNoPad);
+    assert_eq!(
+        to_json_with_options(
+            &WellKnownTypes {
+                bytes: Some(b"\xfb\xff".to_vec()),
+                ..Default::default()
+            },
+            &options
+        ),
+        json!({ "bytes": "-_8" })
+    );
+}
+
 #[test]
 fn duration_fractional_digits() {
     assert_eq!(
@@ -1272,6 +2663,24 @@ proptest! {
         let _ = try_from_json_string_with_options(&json, ".test.Scalars", &DeserializeOptions::default().deny_unknown_fields(false));
     }
 
+    #[test]
+    fn deserialize_error_path_matches(json in arbitrary::json()) {
+        for name in ["test.Scalars", "test.ComplexType", "test.WellKnownTypes"] {
+            let desc = test_file_descriptor().get_message_by_name(name).unwrap();
+            let options = DeserializeOptions::new();
+
+            let mut de = serde_json::Deserializer::from_str(&json);
+            let expected = DynamicMessage::deserialize_with_options(desc.clone(), &mut de, &options);
+            let mut de = serde_json::Deserializer::from_str(&json);
+            let actual = DynamicMessage::deserialize_with_error_path(desc, &mut de, &options);
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => prop_assert_eq!(expected, actual),
+                (Err(_), Err(_)) => (),
+                (expected, actual) => prop_assert!(false, "{:?} != {:?}", expected, actual),
+            }
+        }
+    }
+
     #[test]
     fn roundtrip_arb_scalar_arrays(message: ScalarArrays) {
         roundtrip_json(&message)?;
@@ -1375,6 +2784,295 @@ fn roundtrip_file_descriptor_set_with_options() {
     .unwrap();
 }
 
+#[test]
+fn roundtrip_file_descriptor_set_simd_json() {
+    let file: Vec<_> = test_file_descriptor()
+        .file_descriptor_protos()
+        .cloned()
+        .collect();
+    let message = FileDescriptorSet { file };
+
+    let mut json = serde_json::to_vec(&message.transcode_to_dynamic()).unwrap();
+    let roundtripped = DynamicMessage::from_simd_json_slice(message.descriptor(), &mut json)
+        .unwrap()
+        .transcode_to::<FileDescriptorSet>()
+        .unwrap();
+    assert_eq!(message, roundtripped);
+}
+
+#[test]
+fn deserialize_simd_json() {
+    let mut json = br#"{
+        "timestamp": "1970-01-01T00:00:01.5Z",
+        "duration": "-2.5s",
+        "struct": { "a": [1, "b", null, { "c": true }] },
+        "int64": "-9007199254740993",
+        "mask": "fooBar,baz"
+    }"#
+    .to_vec();
+    let message = DynamicMessage::from_simd_json_slice_with_options(
+        WellKnownTypes::default().descriptor(),
+        &mut json,
+        &DeserializeOptions::new().deny_unknown_fields(true),
+    )
+    .unwrap();
+
+    let expected = from_json::<WellKnownTypes>(
+        json!({
+            "timestamp": "1970-01-01T00:00:01.5Z",
+            "duration": "-2.5s",
+            "struct": { "a": [1, "b", null, { "c": true }] },
+            "int64": "-9007199254740993",
+            "mask": "fooBar,baz"
+        }),
+        ".test.WellKnownTypes",
+    );
+    assert_eq!(message.transcode_to::<WellKnownTypes>().unwrap(), expected);
+
+    let mut json = br#"{ "x": 1 } { "x": 2 }"#.to_vec();
+    DynamicMessage::from_simd_json_slice(Point::default().descriptor(), &mut json).unwrap_err();
+    let mut json = br#"{ "x": 1, "z": 2 }"#.to_vec();
+    DynamicMessage::from_simd_json_slice(Point::default().descriptor(), &mut json).unwrap_err();
+}
+
+#[test]
+fn roundtrip_json_lines() {
+    let desc = Point::default().descriptor();
+    let input = "{\"latitude\":1,\"longitude\":2}\n\n{\"latitude\":3}\n";
+
+    let mut progress = Vec::new();
+    let mut binary = Vec::new();
+    let count = json_lines::json_lines_to_length_delimited(
+        &desc,
+        input.as_bytes(),
+        &mut binary,
+        &DeserializeOptions::new(),
+        |p| progress.push((p.messages(), p.bytes_read())),
+    )
+    .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(progress, vec![(1, 29), (2, 45)]);
+
+    let mut output = Vec::new();
+    let count = json_lines::length_delimited_to_json_lines(
+        &desc,
+        binary.as_slice(),
+        &mut output,
+        &SerializeOptions::new(),
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "{\"latitude\":1,\"longitude\":2}\n{\"latitude\":3}\n"
+    );
+}
+
+#[test]
+fn json_lines_error_index() {
+    let desc = Point::default().descriptor();
+
+    let err = json_lines::json_lines_to_length_delimited(
+        &desc,
+        "{\"latitude\":1}\n{\"unknown\":1}\n".as_bytes(),
+        io::sink(),
+        &DeserializeOptions::new(),
+        |_| {},
+    )
+    .unwrap_err();
+    assert_eq!(err.index(), 1);
+    assert_eq!(err.to_string(), "invalid JSON for message 1");
+
+    let err = json_lines::length_delimited_to_json_lines(
+        &desc,
+        b"\x02\x08\x01\x05\x08".as_ref(),
+        io::sink(),
+        &SerializeOptions::new(),
+        |_| {},
+    )
+    .unwrap_err();
+    assert_eq!(err.index(), 1);
+}
+
+#[test]
+fn read_json_lines() {
+    fn read<T>(input: &str) -> Vec<Result<T, u64>>
+    where
+        T: ReflectMessage + Message + Default,
+    {
+        // Use a tiny buffer to check values spanning multiple reads.
+        let reader = io::BufReader::with_capacity(3, input.as_bytes());
+        json_lines::read_json_lines(
+            &T::default().descriptor(),
+            reader,
+            &DeserializeOptions::new(),
+        )
+        .map(|result| {
+            result
+                .map(|message| message.transcode_to().unwrap())
+                .map_err(|err| err.index())
+        })
+        .collect()
+    }
+
+    let point = |latitude, longitude| Point {
+        latitude,
+        longitude,
+    };
+
+    assert_eq!(
+        read::<Point>("{\"latitude\":1,\"longitude\":2}\n\n  \n{\"latitude\":3}"),
+        vec![Ok(point(1, 2)), Ok(point(3, 0))]
+    );
+    assert_eq!(
+        read::<Point>("{\"latitude\":1}\n{\"latitude\":\n{\"unknown\":1}\n{\"longitude\":4}\n"),
+        vec![Ok(point(1, 0)), Err(1), Err(2), Ok(point(0, 4))]
+    );
+    assert_eq!(read::<Point>(""), vec![]);
+    assert_eq!(read::<Point>(" \n "), vec![]);
+
+    assert_eq!(
+        read::<Point>(" [ {\"latitude\":1, \"longitude\": 2} ,{},\n{\"latitude\":\"x\"}, {\"longitude\":4}]\n"),
+        vec![Ok(point(1, 2)), Ok(point(0, 0)), Err(2), Ok(point(0, 4))]
+    );
+    assert_eq!(read::<Point>("[]"), vec![]);
+    assert_eq!(
+        read::<Point>("[{\"latitude\":1} {\"latitude\":2}]"),
+        vec![Ok(point(1, 0)), Err(1)]
+    );
+    assert_eq!(
+        read::<Point>("[{\"latitude\":1},{\"latitude\":2"),
+        vec![Ok(point(1, 0)), Err(1)]
+    );
+
+    assert_eq!(
+        read::<Scalars>(r#"[{"string":"a]}\"\\,"},{"string":"[{"}]"#),
+        vec![
+            Ok(Scalars {
+                string: "a]}\"\\,".to_owned(),
+                ..Default::default()
+            }),
+            Ok(Scalars {
+                string: "[{".to_owned(),
+                ..Default::default()
+            })
+        ]
+    );
+    assert_eq!(
+        read::<ComplexType>(r#"[{"myEnum":["FOO",1,[]]},{"myEnum":["BAR"]}]"#),
+        vec![
+            Err(0),
+            Ok(ComplexType {
+                my_enum: vec![3],
+                ..Default::default()
+            })
+        ]
+    );
+    assert_eq!(
+        read::<prost_types::Timestamp>(r#"["1970-01-01T00:00:01Z", "bad"]"#),
+        vec![
+            Ok(prost_types::Timestamp {
+                seconds: 1,
+                nanos: 0,
+            }),
+            Err(1)
+        ]
+    );
+    assert_eq!(read::<i32>("[1, 2 ,3]"), vec![Ok(1), Ok(2), Ok(3)]);
+    assert_eq!(read::<i32>("1\n2\n"), vec![Ok(1), Ok(2)]);
+}
+
+#[test]
+fn struct_json_value() {
+    let json = json!({
+        "null": null,
+        "number": 1.5,
+        "string": "hello",
+        "bool": true,
+        "struct": { "nested": [] },
+        "list": [{}, 2.0, [false]],
+    });
+    let message = DynamicMessage::struct_from_json_value(
+        prost_types::Struct::default().descriptor(),
+        json.clone(),
+    )
+    .unwrap();
+    assert_eq!(message.struct_to_json_value().unwrap(), json);
+    assert_eq!(
+        message.struct_to_json_value().unwrap(),
+        serde_json::to_value(&message).unwrap()
+    );
+
+    let value = DynamicMessage::struct_from_json_value(
+        prost_types::Value::default().descriptor(),
+        json!("text"),
+    )
+    .unwrap();
+    assert_eq!(
+        value.transcode_to::<prost_types::Value>().unwrap(),
+        prost_types::Value {
+            kind: Some(prost_types::value::Kind::StringValue("text".to_owned())),
+        }
+    );
+
+    let list = prost_types::ListValue {
+        values: vec![
+            prost_types::Value::default(),
+            prost_types::Value {
+                kind: Some(prost_types::value::Kind::NumberValue(3.0)),
+            },
+        ],
+    };
+    assert_eq!(
+        list.transcode_to_dynamic().struct_to_json_value().unwrap(),
+        json!([null, 3.0])
+    );
+}
+
+#[test]
+fn struct_json_value_errors() {
+    let err = Point::default()
+        .transcode_to_dynamic()
+        .struct_to_json_value()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected a message of type 'google.protobuf.Struct', 'google.protobuf.Value' or 'google.protobuf.ListValue', but found 'test.Point'"
+    );
+
+    let err = DynamicMessage::struct_from_json_value(
+        prost_types::Struct::default().descriptor(),
+        json!([]),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected an object for message 'google.protobuf.Struct'"
+    );
+    let err = DynamicMessage::struct_from_json_value(
+        prost_types::ListValue::default().descriptor(),
+        json!({}),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected an array for message 'google.protobuf.ListValue'"
+    );
+
+    let value = prost_types::Value {
+        kind: Some(prost_types::value::Kind::NumberValue(f64::NAN)),
+    };
+    let err = value
+        .transcode_to_dynamic()
+        .struct_to_json_value()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "cannot convert non-finite number NaN in 'google.protobuf.Value' to JSON"
+    );
+}
+
 fn to_json<T>(message: &T) -> serde_json::Value
 where
     T: PartialEq + Debug + ReflectMessage + Default,
@@ -1404,6 +3102,14 @@ where
     String::from_utf8(ser.into_inner()).unwrap()
 }
 
+fn to_canonical_json_string(message: DynamicMessage) -> String {
+    let mut ser = serde_json::Serializer::with_formatter(Vec::new(), CanonicalJsonFormatter::new());
+    message
+        .serialize_with_options(&mut ser, &SerializeOptions::new().canonical(true))
+        .unwrap();
+    String::from_utf8(ser.into_inner()).unwrap()
+}
+
 fn wkt_to_json<T>(message: &T, message_name: &str) -> serde_json::Value
 where
     T: Message,
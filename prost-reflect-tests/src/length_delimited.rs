@@ -0,0 +1,108 @@
+use std::io;
+
+use futures_util::SinkExt;
+use prost::Message;
+use prost_reflect::{
+    length_delimited::{MessageSink, MessageStream},
+    DynamicMessage, ReflectMessage,
+};
+use tokio_stream::StreamExt;
+
+use crate::proto::Scalars;
+
+fn scalars(string: &str) -> DynamicMessage {
+    Scalars {
+        int32: 5,
+        string: string.to_owned(),
+        ..Default::default()
+    }
+    .transcode_to_dynamic()
+}
+
+#[test]
+fn round_trip() {
+    let small = scalars("small");
+    let large = scalars(&"large".repeat(4000));
+
+    let output = pollster::block_on(async {
+        let mut sink = MessageSink::new(Vec::new());
+        for _ in 0..3 {
+            sink.feed(small.clone()).await.unwrap();
+            sink.feed(large.clone()).await.unwrap();
+        }
+        sink.close().await.unwrap();
+        sink.into_inner()
+    });
+
+    let mut expected = Vec::new();
+    for _ in 0..3 {
+        small.encode_length_delimited(&mut expected).unwrap();
+        large.encode_length_delimited(&mut expected).unwrap();
+    }
+    assert_eq!(output, expected);
+
+    let messages: Vec<DynamicMessage> = pollster::block_on(
+        MessageStream::new(small.descriptor(), output.as_slice()).collect::<Result<_, _>>(),
+    )
+    .unwrap();
+    assert_eq!(messages.len(), 6);
+    assert_eq!(messages[0], small);
+    assert_eq!(messages[1], large);
+}
+
+#[test]
+fn invalid_message_skipped() {
+    let message = scalars("a");
+    let mut input = Vec::new();
+    message.encode_length_delimited(&mut input).unwrap();
+    input.extend_from_slice(b"\x01\x80");
+    message.encode_length_delimited(&mut input).unwrap();
+
+    let results: Vec<_> =
+        pollster::block_on(MessageStream::new(message.descriptor(), input.as_slice()).collect());
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &message);
+    assert_eq!(
+        results[1].as_ref().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(results[2].as_ref().unwrap(), &message);
+}
+
+#[test]
+fn truncated_input() {
+    let message = scalars("a");
+    let input = message.encode_length_delimited_to_vec();
+
+    let results: Vec<_> = pollster::block_on(
+        MessageStream::new(message.descriptor(), &input[..input.len() - 1]).collect(),
+    );
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].as_ref().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn max_frame_size() {
+    let message = scalars(&"a".repeat(100));
+    let mut input = message.encode_length_delimited_to_vec();
+    input.extend(message.encode_length_delimited_to_vec());
+
+    let results: Vec<_> = pollster::block_on(
+        MessageStream::new(message.descriptor(), input.as_slice())
+            .max_frame_size(100)
+            .collect(),
+    );
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        results[0].as_ref().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    let mut sink = MessageSink::new(Vec::new()).max_frame_size(100);
+    let err = pollster::block_on(sink.send(message)).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(sink.into_inner().is_empty());
+}
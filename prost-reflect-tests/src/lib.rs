@@ -1,19 +1,87 @@
 use once_cell::sync::Lazy;
 use prost_reflect::DescriptorPool;
 
+#[cfg(test)]
+mod any;
 #[cfg(test)]
 mod arbitrary;
 #[cfg(test)]
+mod arrow;
+#[cfg(test)]
+mod avro;
+#[cfg(test)]
+mod axum;
+#[cfg(test)]
+mod bson;
+#[cfg(test)]
+mod cbor;
+#[cfg(test)]
+mod cel;
+#[cfg(test)]
+mod compat;
+#[cfg(test)]
+mod confluent;
+#[cfg(test)]
+mod csv;
+#[cfg(test)]
+mod datetime;
+#[cfg(test)]
 mod decode;
 #[cfg(test)]
+mod default;
+#[cfg(test)]
 mod desc;
 #[cfg(test)]
+mod field_mask;
+#[cfg(test)]
+mod framing;
+#[cfg(test)]
+mod google_type;
+#[cfg(test)]
+mod grpc;
+#[cfg(test)]
+mod grpc_web;
+#[cfg(test)]
 mod json;
 #[cfg(test)]
+mod length_delimited;
+#[cfg(test)]
+mod lint;
+#[cfg(test)]
+mod mock;
+#[cfg(test)]
+mod null;
+#[cfg(test)]
+mod openapi;
+#[cfg(test)]
+mod parquet;
+#[cfg(test)]
+mod positional;
+#[cfg(test)]
+mod protovalidate;
+#[cfg(test)]
+mod reflection;
+#[cfg(test)]
+mod sort;
+#[cfg(test)]
+mod template;
+#[cfg(test)]
 mod text_format;
+#[cfg(test)]
+mod transcoding;
+#[cfg(test)]
+mod usage;
+#[cfg(test)]
+mod wire;
+#[cfg(test)]
+mod wrapper;
+#[cfg(test)]
+mod xml;
+#[cfg(test)]
+mod yaml;
 
 pub mod proto {
-    #![allow(clippy::all)]
+    #![allow(clippy::all, non_local_definitions)]
     include!(concat!(env!("OUT_DIR"), "/test.rs"));
     include!(concat!(env!("OUT_DIR"), "/test2.rs"));
 
@@ -0,0 +1,118 @@
+use prost_reflect::lint::{Diagnostic, Linter, Rule};
+
+use crate::test_file_descriptor;
+
+fn lint(linter: &Linter) -> Vec<Diagnostic> {
+    let file = test_file_descriptor()
+        .get_file_by_name("lint.proto")
+        .unwrap();
+    linter.lint_file(&file)
+}
+
+fn summary(diagnostics: &[Diagnostic]) -> Vec<(Rule, usize)> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.rule(), diagnostic.line().unwrap()))
+        .collect()
+}
+
+#[test]
+fn default_rules() {
+    let diagnostics = lint(&Linter::new());
+    assert_eq!(
+        summary(&diagnostics),
+        [
+            (Rule::PackageDirectoryMatch, 3),
+            (Rule::MessagePascalCase, 14),
+            (Rule::FieldLowerSnakeCase, 15),
+            (Rule::OneofLowerSnakeCase, 16),
+            (Rule::EnumValueUpperSnakeCase, 26),
+            (Rule::EnumValuePrefix, 27),
+            (Rule::EnumZeroValueSuffix, 31),
+            (Rule::ServicePascalCase, 34),
+            (Rule::RpcPascalCase, 35),
+        ]
+    );
+
+    assert_eq!(
+        diagnostics[0].to_string(),
+        "lint.proto:3:1: files with package 'lint.v1' should be in the directory 'lint/v1', but this file is in '.' [PACKAGE_DIRECTORY_MATCH]"
+    );
+    assert_eq!(diagnostics[2].file(), "lint.proto");
+    assert_eq!(diagnostics[2].path(), &[4, 1, 2, 0]);
+    assert_eq!(diagnostics[2].column(), Some(3));
+    assert_eq!(
+        diagnostics[2].message(),
+        "field name 'camelCase' should be lower_snake_case"
+    );
+    assert_eq!(
+        diagnostics[5].message(),
+        "enum value name 'ACTIVE' should be prefixed with 'STATUS_'"
+    );
+}
+
+#[test]
+fn configure_rules() {
+    let linter = Linter::new()
+        .rule(Rule::PackageDirectoryMatch, false)
+        .rule(Rule::EnumValuePrefix, false)
+        .enum_zero_value_suffix("_DEFAULT");
+    assert_eq!(
+        summary(&lint(&linter)),
+        [
+            (Rule::MessagePascalCase, 14),
+            (Rule::FieldLowerSnakeCase, 15),
+            (Rule::OneofLowerSnakeCase, 16),
+            (Rule::EnumZeroValueSuffix, 25),
+            (Rule::EnumValueUpperSnakeCase, 26),
+            (Rule::ServicePascalCase, 34),
+            (Rule::RpcPascalCase, 35),
+        ]
+    );
+}
+
+#[test]
+fn comment_rules() {
+    let linter = Rule::ALL.iter().fold(Linter::new(), |linter, &rule| {
+        linter.rule(
+            rule,
+            matches!(
+                rule,
+                Rule::CommentMessage | Rule::CommentField | Rule::CommentEnumValue
+            ),
+        )
+    });
+    let diagnostics = lint(&linter);
+    assert_eq!(
+        summary(&diagnostics),
+        [
+            (Rule::CommentField, 11),
+            (Rule::CommentMessage, 14),
+            (Rule::CommentField, 15),
+            (Rule::CommentField, 17),
+            (Rule::CommentField, 19),
+            (Rule::CommentEnumValue, 26),
+            (Rule::CommentEnumValue, 27),
+            (Rule::CommentEnumValue, 31),
+        ]
+    );
+    assert_eq!(
+        diagnostics[0].message(),
+        "field 'counts' should have a non-empty comment"
+    );
+}
+
+#[test]
+fn ignore_files() {
+    let pool = test_file_descriptor();
+    let diagnostics = Linter::new().ignore("").lint(&pool);
+    assert!(diagnostics.is_empty());
+
+    let diagnostics = Linter::new().ignore("google/").lint(&pool);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| !diagnostic.file().starts_with("google/")));
+    assert!(diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.file() == "lint.proto"));
+}
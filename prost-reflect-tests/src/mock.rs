@@ -0,0 +1,208 @@
+use prost::Message;
+use prost_reflect::{
+    grpc::DynamicClient,
+    mock::{MockResponses, MockService, MockStrategy},
+    DynamicMessage, MethodDescriptor, ReflectMessage, SerializeOptions, Value,
+};
+use tonic::{Code, Request};
+
+use crate::test_file_descriptor;
+
+fn method(service: &str, name: &str) -> MethodDescriptor {
+    test_file_descriptor()
+        .get_service_by_name(service)
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == name)
+        .unwrap()
+}
+
+fn echo_request() -> DynamicMessage {
+    let mut request = DynamicMessage::new(method("echo.Echo", "Unary").input());
+    request.set_field_by_name("message", Value::String("hello".to_owned()));
+    request
+}
+
+fn echo_mock(responses: MockResponses) -> MockService {
+    MockService::new(
+        test_file_descriptor()
+            .get_service_by_name("echo.Echo")
+            .unwrap(),
+        responses,
+    )
+}
+
+#[test]
+fn default_strategy() {
+    let method = method("library.Library", "GetBook");
+    let mut mock = MockResponses::new(MockStrategy::Default);
+    assert_eq!(mock.response(&method), DynamicMessage::new(method.output()));
+}
+
+#[test]
+fn random_strategy_is_deterministic() {
+    let method = method("library.Library", "ListBooks");
+
+    let mut first = MockResponses::new(MockStrategy::Random { seed: 7 });
+    let mut second = MockResponses::new(MockStrategy::Random { seed: 7 });
+    let responses: Vec<_> = (0..5).map(|_| first.response(&method)).collect();
+    assert_eq!(
+        responses,
+        (0..5).map(|_| second.response(&method)).collect::<Vec<_>>()
+    );
+    assert_ne!(responses[0], responses[1]);
+
+    let mut other = MockResponses::new(MockStrategy::Random { seed: 8 });
+    assert_ne!(other.response(&method), responses[0]);
+}
+
+#[test]
+fn random_messages_are_valid() {
+    let pool = test_file_descriptor();
+    let mut mock = MockResponses::new(MockStrategy::Random { seed: 3 });
+    for name in [
+        "test.Scalars",
+        "test.ComplexType",
+        "test.WellKnownTypes",
+        "test.MessageWithOneof",
+        "test2.ContainsGroup",
+        "library.Book",
+    ] {
+        let desc = pool.get_message_by_name(name).unwrap();
+        for _ in 0..20 {
+            let message = mock.message(&desc);
+
+            DynamicMessage::decode(desc.clone(), message.encode_to_vec().as_slice()).unwrap();
+
+            let json = message
+                .serialize_with_options(
+                    serde_json::value::Serializer,
+                    &SerializeOptions::new().stringify_64_bit_integers(false),
+                )
+                .unwrap_or_else(|err| panic!("failed to serialize {}: {}", name, err));
+            DynamicMessage::deserialize(desc.clone(), json).unwrap();
+        }
+    }
+}
+
+#[test]
+fn random_enums_use_declared_values() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("library.Book")
+        .unwrap();
+    let genre = desc.get_field_by_name("genre").unwrap();
+    let genre_enum = genre.kind().as_enum().unwrap().clone();
+
+    let mut mock = MockResponses::new(MockStrategy::Random { seed: 11 });
+    for _ in 0..20 {
+        let message = mock.message(&desc);
+        let number = message.get_field(&genre).as_enum_number().unwrap();
+        assert!(genre_enum.get_value(number).is_some());
+    }
+}
+
+#[test]
+fn template_overrides_fields() {
+    let method = method("library.Library", "GetBook");
+    let mut template = DynamicMessage::new(method.output());
+    template.set_field_by_name("title", Value::String("Dune".to_owned()));
+
+    let mut mock =
+        MockResponses::new(MockStrategy::Random { seed: 5 }).with_template(&method, template);
+    let response = mock.response(&method);
+    assert_eq!(
+        response.get_field_by_name("title").unwrap().as_str(),
+        Some("Dune")
+    );
+    assert!(response.has_field_by_name("name"));
+
+    // Templates only apply to responses, not to messages generated directly.
+    let message = mock.message(&method.output());
+    assert_ne!(
+        message.get_field_by_name("title").unwrap().as_str(),
+        Some("Dune")
+    );
+}
+
+#[test]
+#[should_panic(expected = "template does not match the output type of 'library.Library.GetBook'")]
+fn template_type_mismatch() {
+    let method = method("library.Library", "GetBook");
+    let _ = MockResponses::new(MockStrategy::Default)
+        .with_template(&method, DynamicMessage::new(method.input()));
+}
+
+#[test]
+fn service_unary() {
+    let method = method("echo.Echo", "Unary");
+    let mut template = DynamicMessage::new(method.output());
+    template.set_field_by_name(
+        "messages",
+        Value::List(vec![Value::String("mock".to_owned())]),
+    );
+
+    let mut client = DynamicClient::new(echo_mock(
+        MockResponses::new(MockStrategy::Default).with_template(&method, template.clone()),
+    ));
+    let response = pollster::block_on(client.unary(&method, Request::new(echo_request())))
+        .unwrap()
+        .into_inner();
+    assert_eq!(response, template);
+}
+
+#[test]
+fn service_server_streaming() {
+    let method = method("echo.Echo", "ServerStreaming");
+    let mut client = DynamicClient::new(
+        echo_mock(MockResponses::new(MockStrategy::Random { seed: 1 })).streaming_responses(3),
+    );
+    let mut responses =
+        pollster::block_on(client.server_streaming(&method, Request::new(echo_request())))
+            .unwrap()
+            .into_inner();
+
+    let mut count = 0;
+    while let Some(response) = pollster::block_on(responses.message()).unwrap() {
+        assert_eq!(response.descriptor(), method.output());
+        count += 1;
+    }
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn service_client_streaming() {
+    let method = method("echo.Echo", "ClientStreaming");
+    let mut client = DynamicClient::new(echo_mock(MockResponses::new(MockStrategy::Default)));
+    let requests = tokio_stream::iter(vec![echo_request(), echo_request()]);
+    let response = pollster::block_on(client.client_streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+    assert_eq!(response, DynamicMessage::new(method.output()));
+}
+
+#[test]
+fn service_streaming() {
+    let method = method("echo.Echo", "Streaming");
+    let mut client = DynamicClient::new(echo_mock(MockResponses::new(MockStrategy::Default)));
+    let requests = tokio_stream::iter(vec![echo_request(), echo_request()]);
+    let mut responses = pollster::block_on(client.streaming(&method, Request::new(requests)))
+        .unwrap()
+        .into_inner();
+
+    let mut count = 0;
+    while pollster::block_on(responses.message()).unwrap().is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 2);
+}
+
+#[test]
+fn service_unknown_method() {
+    let method = method("library.Library", "GetBook");
+    let mut client = DynamicClient::new(echo_mock(MockResponses::new(MockStrategy::Default)));
+    let status = pollster::block_on(
+        client.unary(&method, Request::new(DynamicMessage::new(method.input()))),
+    )
+    .unwrap_err();
+    assert_eq!(status.code(), Code::Unimplemented);
+}
@@ -0,0 +1,73 @@
+use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+
+use crate::proto::{message_with_oneof, MessageWithOneof, WellKnownTypes};
+
+#[test]
+fn set_field_nullable_value() {
+    let mut message = DynamicMessage::new(MessageWithOneof::default().descriptor());
+    message.set_field_by_name_nullable("oneof_value_null", None);
+
+    assert!(message.has_field_by_name("oneof_value_null"));
+    assert!(message
+        .get_field_by_name("oneof_value_null")
+        .unwrap()
+        .is_null());
+    assert_eq!(message.get_field_by_name_nullable("oneof_value_null"), None);
+    assert_eq!(
+        message.transcode_to::<MessageWithOneof>().unwrap(),
+        MessageWithOneof {
+            test_oneof: Some(message_with_oneof::TestOneof::OneofValueNull(
+                prost_types::Value {
+                    kind: Some(prost_types::value::Kind::NullValue(0)),
+                }
+            )),
+        }
+    );
+}
+
+#[test]
+fn set_field_nullable_null_value() {
+    let mut message = DynamicMessage::new(MessageWithOneof::default().descriptor());
+    message.set_field_by_name_nullable("oneof_null", None);
+
+    assert!(message.has_field_by_name("oneof_null"));
+    assert_eq!(message.get_field_by_name_nullable("oneof_null"), None);
+    assert_eq!(
+        message.transcode_to::<MessageWithOneof>().unwrap(),
+        MessageWithOneof {
+            test_oneof: Some(message_with_oneof::TestOneof::OneofNull(0)),
+        }
+    );
+}
+
+#[test]
+fn set_field_nullable_other() {
+    let mut message = DynamicMessage::new(WellKnownTypes::default().descriptor());
+    message.set_field_by_name_nullable("int32", Some(Value::Message(3i32.transcode_to_dynamic())));
+    message.set_field_by_name_nullable("int32", None);
+    assert!(!message.has_field_by_name("int32"));
+    assert_eq!(message.get_field_by_name_nullable("int32"), None);
+
+    let mut message = DynamicMessage::new(MessageWithOneof::default().descriptor());
+    message.set_field_by_name_nullable("oneof_field_1", Some(Value::String("foo".to_owned())));
+    assert_eq!(
+        message
+            .get_field_by_name_nullable("oneof_field_1")
+            .as_deref(),
+        Some(&Value::String("foo".to_owned()))
+    );
+    message.set_field_by_name_nullable("oneof_field_1", None);
+    assert!(!message.has_field_by_name("oneof_field_1"));
+}
+
+#[test]
+fn is_null() {
+    let value = prost_types::Value {
+        kind: Some(prost_types::value::Kind::NumberValue(1.0)),
+    }
+    .transcode_to_dynamic();
+    assert!(!Value::Message(value).is_null());
+    assert!(Value::Message(prost_types::Value::default().transcode_to_dynamic()).is_null());
+    assert!(!Value::EnumNumber(0).is_null());
+    assert!(!Value::Message(prost_types::Struct::default().transcode_to_dynamic()).is_null());
+}
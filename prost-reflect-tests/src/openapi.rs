@@ -0,0 +1,226 @@
+use prost_reflect::transcoding::openapi::{document, OpenApiOptions};
+use serde_json::{json, Value};
+
+use crate::test_file_descriptor;
+
+fn service_document(name: &str, options: &OpenApiOptions) -> Value {
+    let service = test_file_descriptor().get_service_by_name(name).unwrap();
+    document([service], options).unwrap()
+}
+
+fn library_document() -> Value {
+    service_document(
+        "library.Library",
+        &OpenApiOptions::new("Library API", "1.0.0"),
+    )
+}
+
+#[test]
+fn info_and_tags() {
+    let document = library_document();
+    assert_eq!(document["openapi"], "3.0.3");
+    assert_eq!(
+        document["info"],
+        json!({ "title": "Library API", "version": "1.0.0" })
+    );
+    assert_eq!(
+        document["tags"],
+        json!([{ "name": "Library", "description": "Manages the books in a library." }])
+    );
+}
+
+#[test]
+fn paths_from_http_rules() {
+    let document = library_document();
+    let paths = document["paths"].as_object().unwrap();
+    let mut routes: Vec<String> = paths
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| format!("{} {}", method, path))
+        })
+        .collect();
+    routes.sort();
+    assert_eq!(
+        routes,
+        [
+            "get /v1/files/{name}",
+            "get /v1/{name}",
+            "get /v1/{parent}/books",
+            "patch /v1/{book.name}",
+            "post /v1/{name}:archive",
+            "post /v1/{parent}/books",
+            "put /v1/{book.name}",
+        ]
+    );
+}
+
+#[test]
+fn get_operation() {
+    let document = library_document();
+    assert_eq!(
+        document["paths"]["/v1/{name}"]["get"],
+        json!({
+            "tags": ["Library"],
+            "operationId": "Library_GetBook",
+            "description": "Gets a book.",
+            "parameters": [
+                { "name": "name", "in": "path", "required": true, "schema": { "type": "string" } },
+            ],
+            "responses": {
+                "200": {
+                    "description": "A successful response.",
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/library.Book" },
+                        },
+                    },
+                },
+                "default": {
+                    "description": "An error response.",
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/google.rpc.Status" },
+                        },
+                    },
+                },
+            },
+        })
+    );
+}
+
+#[test]
+fn query_parameters() {
+    let document = library_document();
+    let operation = &document["paths"]["/v1/{parent}/books"]["get"];
+    assert_eq!(
+        operation["parameters"],
+        json!([
+            { "name": "parent", "in": "path", "required": true, "schema": { "type": "string" } },
+            { "name": "pageSize", "in": "query", "schema": { "type": "integer", "format": "int32" } },
+            { "name": "tags", "in": "query", "schema": { "type": "array", "items": { "type": "string" } } },
+            { "name": "genre", "in": "query", "schema": { "$ref": "#/components/schemas/library.Genre" } },
+            { "name": "filter.author", "in": "query", "schema": { "type": "string" } },
+            { "name": "filter.inPrint", "in": "query", "schema": { "type": "boolean" } },
+            { "name": "includeDrafts", "in": "query", "schema": { "type": "boolean", "nullable": true } },
+        ])
+    );
+    assert_eq!(
+        operation["responses"]["200"]["content"]["application/json"]["schema"],
+        json!({ "type": "array", "items": { "$ref": "#/components/schemas/library.Book" } })
+    );
+}
+
+#[test]
+fn request_bodies() {
+    let document = library_document();
+    let create = &document["paths"]["/v1/{parent}/books"]["post"];
+    assert_eq!(
+        create["requestBody"],
+        json!({
+            "required": true,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": "#/components/schemas/library.Book" },
+                },
+            },
+        })
+    );
+    assert_eq!(create["parameters"].as_array().unwrap().len(), 1);
+
+    let update = &document["paths"]["/v1/{book.name}"]["put"];
+    assert_eq!(update["operationId"], "Library_UpdateBook1");
+    assert_eq!(
+        update["requestBody"]["content"]["application/json"]["schema"],
+        json!({ "$ref": "#/components/schemas/library.UpdateBookRequest" })
+    );
+}
+
+#[test]
+fn schemas() {
+    let document = library_document();
+    let schemas = &document["components"]["schemas"];
+    assert_eq!(
+        schemas["library.Book"],
+        json!({
+            "type": "object",
+            "description": "A book in a shelf.",
+            "properties": {
+                "name": { "type": "string" },
+                "title": { "type": "string", "description": "The title of the book." },
+                "pageCount": { "type": "integer", "format": "int32" },
+                "genre": { "$ref": "#/components/schemas/library.Genre" },
+                "tags": { "type": "array", "items": { "type": "string" } },
+            },
+        })
+    );
+    assert_eq!(
+        schemas["library.Genre"],
+        json!({
+            "type": "string",
+            "description": "The genre of a book.",
+            "enum": ["GENRE_UNSPECIFIED", "FICTION", "NON_FICTION"],
+        })
+    );
+    assert_eq!(
+        schemas["google.rpc.Status"]["properties"]["code"],
+        json!({ "type": "integer", "format": "int32" })
+    );
+}
+
+#[test]
+fn proto_field_names() {
+    let document = service_document(
+        "library.Library",
+        &OpenApiOptions::new("Library API", "1.0.0").use_proto_field_name(true),
+    );
+    let properties = document["components"]["schemas"]["library.Book"]["properties"]
+        .as_object()
+        .unwrap();
+    assert!(properties.contains_key("page_count"));
+
+    let parameters = document["paths"]["/v1/{parent}/books"]["get"]["parameters"]
+        .as_array()
+        .unwrap();
+    assert!(parameters
+        .iter()
+        .any(|parameter| parameter["name"] == "filter.in_print"));
+}
+
+#[test]
+fn post_convention_without_http_rules() {
+    let document = service_document("echo.Echo", &OpenApiOptions::new("Echo API", "1.0.0"));
+    let paths = document["paths"].as_object().unwrap();
+    assert_eq!(paths.keys().collect::<Vec<_>>(), ["/echo.Echo/Unary"]);
+
+    let operation = &paths["/echo.Echo/Unary"]["post"];
+    assert_eq!(operation["operationId"], "Echo_Unary");
+    assert_eq!(
+        operation["requestBody"]["content"]["application/json"]["schema"],
+        json!({ "$ref": "#/components/schemas/echo.EchoRequest" })
+    );
+    assert!(operation.get("parameters").is_none());
+}
+
+#[test]
+fn only_referenced_schemas() {
+    let document = library_document();
+    let mut names: Vec<_> = document["components"]["schemas"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "google.rpc.Status",
+            "library.ArchiveBookRequest",
+            "library.Book",
+            "library.Genre",
+            "library.UpdateBookRequest",
+        ]
+    );
+}
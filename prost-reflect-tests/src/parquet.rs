@@ -0,0 +1,198 @@
+use std::{collections::HashMap, iter::FromIterator};
+
+use ::parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use arrow_array::{
+    cast::AsArray,
+    types::{Int32Type, Int64Type},
+    Array, RecordBatch,
+};
+use arrow_schema::DataType;
+use prost::bytes::Bytes;
+use prost_reflect::{
+    arrow,
+    parquet::{self, ParquetOptions, ParquetWriter},
+    DynamicMessage, ReflectMessage,
+};
+
+use crate::proto::{ComplexType, Point, Scalars, WellKnownTypes};
+
+fn write(messages: &[DynamicMessage], options: &ParquetOptions) -> Vec<RecordBatch> {
+    let desc = messages[0].descriptor();
+    let mut writer = ParquetWriter::try_new(Vec::new(), desc, options, None).unwrap();
+    writer.write_all(messages).unwrap();
+    let file = writer.close().unwrap();
+
+    ParquetRecordBatchReaderBuilder::try_new(Bytes::from(file))
+        .unwrap()
+        .build()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+fn complex_type() -> ComplexType {
+    ComplexType {
+        string_map: HashMap::from_iter([(
+            "a".to_owned(),
+            Scalars {
+                int64: -1,
+                ..Default::default()
+            },
+        )]),
+        int_map: HashMap::from_iter([(2, Scalars::default()), (1, Scalars::default())]),
+        nested: Some(Scalars {
+            uint64: u64::MAX,
+            ..Default::default()
+        }),
+        my_enum: vec![1, 3],
+        optional_enum: 3,
+    }
+}
+
+#[test]
+fn schema_default() {
+    let schema =
+        parquet::schema(&ComplexType::default().descriptor(), &ParquetOptions::new()).unwrap();
+    match schema.field_with_name("string_map").unwrap().data_type() {
+        DataType::Map(entries, _) => assert_eq!(entries.name(), "key_value"),
+        data_type => panic!("expected map, found {}", data_type),
+    }
+    match schema.field_with_name("my_enum").unwrap().data_type() {
+        DataType::List(item) => assert_eq!(item.name(), "element"),
+        data_type => panic!("expected list, found {}", data_type),
+    }
+}
+
+#[test]
+fn schema_options() {
+    let options = ParquetOptions::new()
+        .maps_as_lists(true)
+        .compliant_nested_types(false)
+        .stringify_64_bit_integers(true);
+    let schema = parquet::schema(&ComplexType::default().descriptor(), &options).unwrap();
+    match schema.field_with_name("int_map").unwrap().data_type() {
+        DataType::List(item) => {
+            assert_eq!(item.name(), "item");
+            assert!(matches!(item.data_type(), DataType::Struct(fields) if fields.len() == 2));
+        }
+        data_type => panic!("expected list, found {}", data_type),
+    }
+
+    let schema = parquet::schema(&Scalars::default().descriptor(), &options).unwrap();
+    for name in ["int64", "uint64", "sint64", "fixed64", "sfixed64"] {
+        assert_eq!(
+            schema.field_with_name(name).unwrap().data_type(),
+            &DataType::Utf8
+        );
+    }
+    assert_eq!(
+        schema.field_with_name("int32").unwrap().data_type(),
+        &DataType::Int32
+    );
+}
+
+#[test]
+fn schema_well_known_types() {
+    let schema = parquet::schema(
+        &WellKnownTypes::default().descriptor(),
+        &ParquetOptions::new().stringify_64_bit_integers(true),
+    )
+    .unwrap();
+    assert_eq!(
+        schema.field_with_name("duration").unwrap().data_type(),
+        &DataType::Int64
+    );
+    assert!(schema.field_with_name("empty").is_err());
+}
+
+#[test]
+fn roundtrip_arrow_names() {
+    let messages = vec![complex_type(), ComplexType::default(), complex_type()];
+    let dynamic: Vec<DynamicMessage> = messages
+        .iter()
+        .map(ReflectMessage::transcode_to_dynamic)
+        .collect();
+    let options = ParquetOptions::new()
+        .compliant_nested_types(false)
+        .batch_size(2);
+    let batches = write(&dynamic, &options);
+    assert_eq!(batches.len(), 1);
+
+    let roundtripped: Vec<ComplexType> =
+        arrow::from_record_batch(&ComplexType::default().descriptor(), &batches[0])
+            .unwrap()
+            .into_iter()
+            .map(|message| message.transcode_to().unwrap())
+            .collect();
+    assert_eq!(roundtripped, messages);
+}
+
+#[test]
+fn write_default() {
+    let messages = vec![complex_type().transcode_to_dynamic()];
+    let batches = write(&messages, &ParquetOptions::new());
+    assert_eq!(batches[0].num_rows(), 1);
+
+    let map = batches[0].column_by_name("int_map").unwrap().as_map();
+    let keys = map.value(0).column(0).as_primitive::<Int32Type>().clone();
+    assert_eq!(keys.values(), &[1, 2]);
+}
+
+#[test]
+fn write_stringified_integers() {
+    let messages = vec![complex_type().transcode_to_dynamic()];
+    let batches = write(
+        &messages,
+        &ParquetOptions::new()
+            .stringify_64_bit_integers(true)
+            .maps_as_lists(true),
+    );
+
+    let nested = batches[0].column_by_name("nested").unwrap().as_struct();
+    let uint64 = nested.column_by_name("uint64").unwrap().as_string::<i32>();
+    assert_eq!(uint64.value(0), "18446744073709551615");
+
+    let entries = batches[0]
+        .column_by_name("string_map")
+        .unwrap()
+        .as_list::<i32>();
+    let entry = entries.value(0);
+    let value = entry.as_struct().column(1).as_struct();
+    let int64 = value.column_by_name("int64").unwrap().as_string::<i32>();
+    assert_eq!(int64.value(0), "-1");
+}
+
+#[test]
+fn write_duration() {
+    let messages = vec![WellKnownTypes {
+        duration: Some(prost_types::Duration {
+            seconds: 1,
+            nanos: 5,
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic()];
+    let batches = write(&messages, &ParquetOptions::new());
+
+    let duration = batches[0].column_by_name("duration").unwrap();
+    assert_eq!(duration.as_primitive::<Int64Type>().value(0), 1_000_000_005);
+    assert!(batches[0].column_by_name("timestamp").unwrap().is_null(0));
+}
+
+#[test]
+fn wrong_message_type() {
+    let mut writer = ParquetWriter::try_new(
+        Vec::new(),
+        Scalars::default().descriptor(),
+        &ParquetOptions::new(),
+        None,
+    )
+    .unwrap();
+    let err = writer
+        .write(&Point::default().transcode_to_dynamic())
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Parquet error: expected messages of type 'test.Scalars', but found 'test.Point'"
+    );
+}
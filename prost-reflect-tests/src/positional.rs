@@ -0,0 +1,158 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator};
+
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{DeserializeOptions, DynamicMessage, ReflectMessage, SerializeOptions};
+
+use crate::proto::{
+    contains_group, message_with_oneof, ComplexType, ContainsGroup, MessageWithOneof, Point,
+    ScalarArrays, Scalars, WellKnownTypes,
+};
+
+fn to_bincode<T>(message: &T) -> Vec<u8>
+where
+    T: ReflectMessage,
+{
+    let mut bytes = Vec::new();
+    message
+        .transcode_to_dynamic()
+        .serialize_with_options(
+            &mut bincode::Serializer::new(&mut bytes, bincode::options()),
+            &SerializeOptions::new().positional(true),
+        )
+        .unwrap();
+    bytes
+}
+
+fn from_bincode<T>(bytes: &[u8]) -> Result<T, bincode::Error>
+where
+    T: ReflectMessage + Message + Default,
+{
+    let message = DynamicMessage::deserialize_with_options(
+        T::default().descriptor(),
+        &mut bincode::Deserializer::from_slice(bytes, bincode::options()),
+        &DeserializeOptions::new().positional(true),
+    )?;
+    Ok(message.transcode_to().unwrap())
+}
+
+fn roundtrip<T>(message: &T) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let roundtripped: T = from_bincode(&to_bincode(message)).unwrap();
+    prop_assert_eq!(message, &roundtripped);
+    Ok(())
+}
+
+#[test]
+fn roundtrip_scalars() {
+    roundtrip(&Scalars {
+        double: 1.1,
+        float: -2.5,
+        int32: 3,
+        int64: -4,
+        uint32: 5,
+        uint64: 6,
+        sint32: -7,
+        sint64: 8,
+        fixed32: 9,
+        fixed64: 10,
+        sfixed32: -11,
+        sfixed64: 12,
+        r#bool: true,
+        string: "hello".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+    })
+    .unwrap();
+    roundtrip(&Scalars::default()).unwrap();
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    roundtrip(&ComplexType {
+        string_map: HashMap::from_iter([
+            ("a".to_owned(), Scalars::default()),
+            (
+                "b".to_owned(),
+                Scalars {
+                    int32: 1,
+                    ..Default::default()
+                },
+            ),
+        ]),
+        int_map: HashMap::from_iter([(-1, Scalars::default())]),
+        nested: Some(Scalars::default()),
+        my_enum: vec![0, 1, 3, -4, 100],
+        optional_enum: 3,
+    })
+    .unwrap();
+}
+
+#[test]
+fn roundtrip_presence() {
+    roundtrip(&MessageWithOneof {
+        test_oneof: Some(message_with_oneof::TestOneof::OneofField2(0)),
+    })
+    .unwrap();
+    roundtrip(&MessageWithOneof { test_oneof: None }).unwrap();
+
+    roundtrip(&ContainsGroup {
+        requiredgroup: Some(contains_group::RequiredGroup {
+            a: String::new(),
+            b: Some(0),
+        }),
+        optionalgroup: None,
+        repeatedgroup: vec![contains_group::RepeatedGroup {
+            e: "foo".to_owned(),
+            f: None,
+        }],
+    })
+    .unwrap();
+
+    roundtrip(&WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: -1,
+            nanos: 5,
+        }),
+        int32: Some(0),
+        ..Default::default()
+    })
+    .unwrap();
+}
+
+#[test]
+fn deserialize_wrong_type() {
+    let bytes = to_bincode(&Point {
+        latitude: 1,
+        longitude: 2,
+    });
+    assert!(from_bincode::<Scalars>(&bytes).is_err());
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(message: Scalars) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(message: ScalarArrays) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_complex_type(message: ComplexType) {
+        roundtrip(&message)?;
+    }
+
+    #[test]
+    fn roundtrip_arb_well_known_types(message: WellKnownTypes) {
+        roundtrip(&message)?;
+    }
+}
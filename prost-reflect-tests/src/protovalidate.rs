@@ -0,0 +1,373 @@
+use prost_reflect::{
+    protovalidate::{self, Validator},
+    DynamicMessage,
+};
+use serde_json::json;
+
+use crate::test_file_descriptor;
+
+fn message(name: &str, json: serde_json::Value) -> DynamicMessage {
+    let desc = test_file_descriptor()
+        .get_message_by_name(&format!("validation.{}", name))
+        .unwrap();
+    DynamicMessage::deserialize(desc, json).unwrap()
+}
+
+/// Gets the path and constraint id of each violation.
+fn violations(message: &DynamicMessage) -> Vec<(String, String)> {
+    match protovalidate::validate(message) {
+        Ok(()) => vec![],
+        Err(err) => {
+            assert!(!err.violations().is_empty(), "{}", err);
+            err.violations()
+                .iter()
+                .map(|v| (v.field_path().to_owned(), v.constraint_id().to_owned()))
+                .collect()
+        }
+    }
+}
+
+fn valid_user() -> serde_json::Value {
+    json!({
+        "name": "alice",
+        "email": "alice@example.com",
+        "age": 30,
+        "tags": ["admin", "staff"],
+        "scores": { "math": 90 },
+        "address": { "city": "London" },
+        "phone": "555-0100",
+        "status": "STATUS_ACTIVE",
+    })
+}
+
+#[test]
+fn valid_message() {
+    assert_eq!(violations(&message("User", valid_user())), vec![]);
+}
+
+#[test]
+fn field_violations() {
+    let user = message(
+        "User",
+        json!({
+            "name": "",
+            "email": "not an email",
+            "age": 200,
+            "tags": ["a", "B", "a", "d"],
+            "scores": { "x": 0, "ok": 1 },
+            "address": { "city": "" },
+            "previousAddresses": [{ "city": "Paris" }, { "city": "" }],
+            "website": "not a uri",
+            "status": 5,
+            "nickname": "al",
+            "id": "1234",
+        }),
+    );
+    let expected = [
+        ("name", "string.min_len"),
+        ("email", "string.email"),
+        ("age", "int32.gte_lt"),
+        ("tags", "repeated.max_items"),
+        ("tags", "repeated.unique"),
+        ("tags[1]", "string.pattern"),
+        ("scores[\"x\"]", "string.min_len"),
+        ("scores[\"x\"]", "int32.gt"),
+        ("address.city", "string.min_len"),
+        ("previous_addresses[1].city", "string.min_len"),
+        ("website", "string.uri"),
+        ("status", "enum.defined_only"),
+        ("nickname", "string.min_len"),
+        ("id", "string.uuid"),
+    ];
+    assert_eq!(
+        violations(&user),
+        expected
+            .iter()
+            .map(|&(path, id)| (path.to_owned(), id.to_owned()))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn violation_details() {
+    let mut json = valid_user();
+    json["scores"] = json!({ "x": 1 });
+    json["age"] = json!(-1);
+    let err = protovalidate::validate(&message("User", json)).unwrap_err();
+
+    let age = &err.violations()[0];
+    assert_eq!(
+        age.message(),
+        "value must be greater than or equal to 0 and less than 150"
+    );
+    assert!(!age.for_key());
+
+    let key = &err.violations()[1];
+    assert_eq!(key.field_path(), "scores[\"x\"]");
+    assert_eq!(key.message(), "value length must be at least 2 characters");
+    assert!(key.for_key());
+    assert_eq!(
+        key.to_string(),
+        "scores[\"x\"]: value length must be at least 2 characters [string.min_len]"
+    );
+    assert_eq!(
+        err.to_string(),
+        "validation failed: age: value must be greater than or equal to 0 and less than 150 [int32.gte_lt], scores[\"x\"]: value length must be at least 2 characters [string.min_len]"
+    );
+}
+
+#[test]
+fn required() {
+    let mut json = valid_user();
+    json.as_object_mut().unwrap().remove("address");
+    json.as_object_mut().unwrap().remove("phone");
+    assert_eq!(
+        violations(&message("User", json)),
+        [
+            ("contact".to_owned(), "required".to_owned()),
+            ("address".to_owned(), "required".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn ignore_if_unpopulated() {
+    let mut json = valid_user();
+    json["id"] = json!("");
+    assert_eq!(violations(&message("User", json.clone())), vec![]);
+
+    json["id"] = json!("5c9a1a1e-5f2b-4bde-8a6d-3e2f3c0e9d11");
+    assert_eq!(violations(&message("User", json)), vec![]);
+}
+
+#[test]
+fn fail_fast() {
+    let user = message(
+        "User",
+        json!({ "name": "", "email": "x", "age": -1, "phone": "555-0100" }),
+    );
+    let err = Validator::new()
+        .fail_fast(true)
+        .validate(&user)
+        .unwrap_err();
+    assert_eq!(err.violations().len(), 1);
+    assert_eq!(err.violations()[0].field_path(), "name");
+}
+
+#[test]
+fn numbers() {
+    let numbers = message(
+        "Numbers",
+        json!({
+            "ratio": 1.5,
+            "outside": 15,
+            "choice": 4,
+            "wrapped": 5,
+            "flag": false,
+        }),
+    );
+    assert_eq!(
+        violations(&numbers),
+        [
+            ("ratio".to_owned(), "double.gt_lte".to_owned()),
+            ("outside".to_owned(), "int64.gt_lt_exclusive".to_owned()),
+            ("choice".to_owned(), "uint32.in".to_owned()),
+            ("forbidden".to_owned(), "sint32.not_in".to_owned()),
+            ("wrapped".to_owned(), "int32.gt".to_owned()),
+            ("flag".to_owned(), "bool.const".to_owned()),
+        ]
+    );
+
+    let numbers = message(
+        "Numbers",
+        json!({
+            "ratio": "NaN",
+            "outside": 25,
+            "choice": 2,
+            "forbidden": -1,
+            "wrapped": 6,
+            "flag": true,
+        }),
+    );
+    assert_eq!(
+        violations(&numbers),
+        [
+            ("ratio".to_owned(), "double.finite".to_owned()),
+            ("ratio".to_owned(), "double.gt_lte".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn messages() {
+    let err = protovalidate::validate(&message("Numbers", json!({ "choice": 4 }))).unwrap_err();
+    let messages: Vec<_> = err.violations().iter().map(|v| v.message()).collect();
+    assert_eq!(
+        messages,
+        [
+            "value must be in list [1, 2, 3]",
+            "value must not be in list [0]"
+        ]
+    );
+}
+
+#[test]
+fn times() {
+    let times = message(
+        "Times",
+        json!({
+            "timeout": "90s",
+            "created": "2999-01-01T00:00:00Z",
+            "payload": {
+                "@type": "type.googleapis.com/google.protobuf.Duration",
+                "value": "1s",
+            },
+        }),
+    );
+    let err = protovalidate::validate(&times).unwrap_err();
+    let details: Vec<_> = err
+        .violations()
+        .iter()
+        .map(|v| (v.field_path(), v.constraint_id(), v.message()))
+        .collect();
+    assert_eq!(
+        details,
+        [
+            (
+                "timeout",
+                "duration.gte_lte",
+                "value must be greater than or equal to 1s and less than or equal to 60s"
+            ),
+            ("created", "timestamp.lt_now", "value must be less than now"),
+            ("payload", "any.in", "type URL must be in the allow list"),
+        ]
+    );
+
+    let times = message(
+        "Times",
+        json!({
+            "timeout": "1.5s",
+            "created": "2020-01-01T00:00:00Z",
+            "payload": {
+                "@type": "type.googleapis.com/google.protobuf.Empty",
+                "value": {},
+            },
+        }),
+    );
+    assert_eq!(violations(&times), vec![]);
+}
+
+#[test]
+fn formats() {
+    let formats = message(
+        "Formats",
+        json!({
+            "host": "-invalid.com",
+            "ip": "::1",
+            "prefix": "10.0.0.1/8",
+            "header": "bad header",
+            "data": "AQ==",
+            "address": "AQID",
+        }),
+    );
+    assert_eq!(
+        violations(&formats),
+        [
+            ("host".to_owned(), "string.hostname".to_owned()),
+            ("ip".to_owned(), "string.ipv4".to_owned()),
+            ("prefix".to_owned(), "string.ip_prefix".to_owned()),
+            (
+                "header".to_owned(),
+                "string.well_known_regex.header_name".to_owned()
+            ),
+            ("data".to_owned(), "bytes.min_len".to_owned()),
+            ("address".to_owned(), "bytes.ipv4".to_owned()),
+        ]
+    );
+
+    let formats = message(
+        "Formats",
+        json!({
+            "host": "example.com",
+            "ip": "192.168.0.1",
+            "prefix": "10.0.0.0/8",
+            "header": "content-type",
+            "data": "AQI=",
+            "address": "fwAAAQ==",
+        }),
+    );
+    assert_eq!(violations(&formats), vec![]);
+
+    let formats = message("Formats", json!({ "host": "", "data": "AgI=" }));
+    assert_eq!(
+        violations(&formats),
+        [
+            ("host".to_owned(), "string.hostname_empty".to_owned()),
+            ("data".to_owned(), "bytes.prefix".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn disabled() {
+    let disabled = message("Disabled", json!({ "name": "" }));
+    assert_eq!(violations(&disabled), vec![]);
+}
+
+#[test]
+fn expressions() {
+    let valid = message(
+        "Expressions",
+        json!({ "start": 1, "end": 2, "code": "X1", "hosts": ["example.com"] }),
+    );
+    assert_eq!(violations(&valid), vec![]);
+
+    let invalid = message(
+        "Expressions",
+        json!({ "start": 3, "end": 2, "code": "Y1", "hosts": ["a.com", "a.com", "-"] }),
+    );
+    assert_eq!(
+        violations(&invalid),
+        [
+            ("".to_owned(), "range.ordered".to_owned()),
+            ("code".to_owned(), "code.format".to_owned()),
+            ("hosts".to_owned(), "hosts.unique".to_owned()),
+            ("hosts[2]".to_owned(), "hosts.hostname".to_owned()),
+        ]
+    );
+
+    let err = protovalidate::validate(&invalid).unwrap_err();
+    let messages: Vec<_> = err.violations().iter().map(|v| v.message()).collect();
+    assert_eq!(
+        messages,
+        [
+            "start must not be after end",
+            "code must start with X",
+            "hosts must be unique",
+            "must be a hostname",
+        ]
+    );
+}
+
+#[test]
+fn invalid_constraints() {
+    let err = protovalidate::validate(&message("InvalidRules", json!({}))).unwrap_err();
+    assert!(err.violations().is_empty());
+    assert_eq!(
+        err.to_string(),
+        "invalid constraint for 'validation.InvalidRules.value': the 'string' rules cannot be applied to a field of type 'int32'"
+    );
+
+    let err =
+        protovalidate::validate(&message("InvalidPattern", json!({ "value": "a" }))).unwrap_err();
+    assert!(err.violations().is_empty());
+    assert!(err.to_string().starts_with(
+        "invalid constraint for 'validation.InvalidPattern.value': invalid regex pattern"
+    ));
+
+    let err = protovalidate::validate(&message("InvalidExpression", json!({}))).unwrap_err();
+    assert!(err.violations().is_empty());
+    assert!(err
+        .to_string()
+        .starts_with("invalid constraint for 'validation.InvalidExpression.value':"));
+}
@@ -0,0 +1,406 @@
+use http_body::{Body, Full};
+use prost::{
+    bytes::{Buf, BufMut, Bytes},
+    Message,
+};
+use prost_reflect::{
+    reflection::{ReflectionClient, ReflectionService},
+    DescriptorPool,
+};
+use prost_types::FileDescriptorProto;
+use tonic::{codegen::Service, Code};
+
+use crate::test_file_descriptor;
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionRequest {
+    #[prost(string, tag = "1")]
+    host: String,
+    #[prost(oneof = "MessageRequest", tags = "3, 4, 5, 6, 7")]
+    message_request: Option<MessageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageRequest {
+    #[prost(string, tag = "3")]
+    FileByFilename(String),
+    #[prost(string, tag = "4")]
+    FileContainingSymbol(String),
+    #[prost(message, tag = "5")]
+    FileContainingExtension(ExtensionRequest),
+    #[prost(string, tag = "6")]
+    AllExtensionNumbersOfType(String),
+    #[prost(string, tag = "7")]
+    ListServices(String),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ExtensionRequest {
+    #[prost(string, tag = "1")]
+    containing_type: String,
+    #[prost(int32, tag = "2")]
+    extension_number: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionResponse {
+    #[prost(string, tag = "1")]
+    valid_host: String,
+    #[prost(message, optional, tag = "2")]
+    original_request: Option<ServerReflectionRequest>,
+    #[prost(oneof = "MessageResponse", tags = "4, 5, 6, 7")]
+    message_response: Option<MessageResponse>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+#[allow(clippy::enum_variant_names)]
+enum MessageResponse {
+    #[prost(message, tag = "4")]
+    FileDescriptorResponse(FileDescriptorResponse),
+    #[prost(message, tag = "5")]
+    AllExtensionNumbersResponse(ExtensionNumberResponse),
+    #[prost(message, tag = "6")]
+    ListServicesResponse(ListServiceResponse),
+    #[prost(message, tag = "7")]
+    ErrorResponse(ErrorResponse),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorResponse {
+    #[prost(message, repeated, tag = "1")]
+    file_descriptor_proto: Vec<FileDescriptorProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ExtensionNumberResponse {
+    #[prost(string, tag = "1")]
+    base_type_name: String,
+    #[prost(int32, repeated, tag = "2")]
+    extension_number: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ListServiceResponse {
+    #[prost(message, repeated, tag = "1")]
+    service: Vec<ServiceResponse>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceResponse {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorResponse {
+    #[prost(int32, tag = "1")]
+    error_code: i32,
+    #[prost(string, tag = "2")]
+    error_message: String,
+}
+
+const V1_PATH: &str = "/grpc.reflection.v1.ServerReflection/ServerReflectionInfo";
+const V1ALPHA_PATH: &str = "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo";
+
+fn call<S>(
+    mut service: S,
+    path: &str,
+    requests: Vec<MessageRequest>,
+) -> (Option<String>, Vec<MessageResponse>)
+where
+    S: Service<
+        http::Request<Full<Bytes>>,
+        Response = http::Response<tonic::body::BoxBody>,
+        Error = std::convert::Infallible,
+    >,
+{
+    let mut body = Vec::new();
+    for request in requests {
+        let request = ServerReflectionRequest {
+            host: "localhost".to_owned(),
+            message_request: Some(request),
+        };
+        body.put_u8(0);
+        body.put_u32(request.encoded_len() as u32);
+        request.encode(&mut body).unwrap();
+    }
+
+    let request = http::Request::post(path)
+        .header("content-type", "application/grpc")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap();
+    let response = pollster::block_on(service.call(request)).unwrap();
+    let header_status = response
+        .headers()
+        .get("grpc-status")
+        .map(|status| status.to_str().unwrap().to_owned());
+    let collected = pollster::block_on(response.into_body().collect()).unwrap();
+    let status = collected
+        .trailers()
+        .and_then(|trailers| trailers.get("grpc-status"))
+        .map(|status| status.to_str().unwrap().to_owned())
+        .or(header_status);
+
+    let mut body = collected.to_bytes();
+    let mut responses = Vec::new();
+    while body.has_remaining() {
+        assert_eq!(body.get_u8(), 0);
+        let len = body.get_u32() as usize;
+        let response = ServerReflectionResponse::decode(body.split_to(len)).unwrap();
+        assert_eq!(response.valid_host, "localhost");
+        responses.push(response.message_response.unwrap());
+    }
+    (status, responses)
+}
+
+fn call_v1(requests: Vec<MessageRequest>) -> Vec<MessageResponse> {
+    let (status, responses) = call(
+        ReflectionService::new(test_file_descriptor()),
+        V1_PATH,
+        requests,
+    );
+    assert_eq!(status.as_deref(), Some("0"));
+    responses
+}
+
+fn file_names(response: &MessageResponse) -> Vec<&str> {
+    match response {
+        MessageResponse::FileDescriptorResponse(response) => response
+            .file_descriptor_proto
+            .iter()
+            .map(|file| file.name())
+            .collect(),
+        _ => panic!(
+            "expected a file descriptor response, but found {:?}",
+            response
+        ),
+    }
+}
+
+#[test]
+fn file_by_filename() {
+    let responses = call_v1(vec![MessageRequest::FileByFilename(
+        "imports.proto".to_owned(),
+    )]);
+
+    assert_eq!(
+        file_names(&responses[0]),
+        [
+            "imports.proto",
+            "google/protobuf/any.proto",
+            "google/protobuf/descriptor.proto"
+        ]
+    );
+}
+
+#[test]
+fn file_responses_omit_sent_dependencies() {
+    let responses = call_v1(vec![
+        MessageRequest::FileByFilename("imports.proto".to_owned()),
+        MessageRequest::FileByFilename("ext.proto".to_owned()),
+        MessageRequest::FileByFilename("google/protobuf/any.proto".to_owned()),
+    ]);
+
+    assert_eq!(file_names(&responses[1]), ["ext.proto"]);
+    assert_eq!(file_names(&responses[2]), ["google/protobuf/any.proto"]);
+}
+
+#[test]
+fn file_containing_symbol() {
+    let responses = call_v1(vec![
+        MessageRequest::FileContainingSymbol("custom.options.Aggregate".to_owned()),
+        MessageRequest::FileContainingSymbol("demo.Foo".to_owned()),
+        MessageRequest::FileContainingSymbol("custom.options.Service.rpc".to_owned()),
+        MessageRequest::FileContainingSymbol("custom.options.Aggregate.a".to_owned()),
+        MessageRequest::FileContainingSymbol("demo.len".to_owned()),
+    ]);
+
+    assert_eq!(
+        file_names(&responses[0]),
+        ["options.proto", "google/protobuf/descriptor.proto"]
+    );
+    assert_eq!(file_names(&responses[1]), ["ext.proto"]);
+    assert_eq!(file_names(&responses[2]), ["options.proto"]);
+    assert_eq!(file_names(&responses[3]), ["options.proto"]);
+    assert_eq!(file_names(&responses[4]), ["ext.proto"]);
+}
+
+#[test]
+fn file_containing_extension() {
+    let responses = call_v1(vec![MessageRequest::FileContainingExtension(
+        ExtensionRequest {
+            containing_type: "google.protobuf.EnumValueOptions".to_owned(),
+            extension_number: 50000,
+        },
+    )]);
+
+    assert_eq!(
+        file_names(&responses[0]),
+        ["ext.proto", "google/protobuf/descriptor.proto"]
+    );
+}
+
+#[test]
+fn all_extension_numbers_of_type() {
+    let responses = call_v1(vec![MessageRequest::AllExtensionNumbersOfType(
+        "google.protobuf.EnumValueOptions".to_owned(),
+    )]);
+
+    assert_eq!(
+        responses[0],
+        MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+            base_type_name: "google.protobuf.EnumValueOptions".to_owned(),
+            extension_number: vec![1001, 50000],
+        })
+    );
+}
+
+#[test]
+fn list_services() {
+    let responses = call_v1(vec![MessageRequest::ListServices(String::new())]);
+
+    match &responses[0] {
+        MessageResponse::ListServicesResponse(response) => {
+            let names: Vec<&str> = response
+                .service
+                .iter()
+                .map(|service| service.name.as_str())
+                .collect();
+            assert!(names.contains(&"custom.options.Service"));
+            assert!(names.contains(&"my.package.MyService"));
+        }
+        response => panic!(
+            "expected a list services response, but found {:?}",
+            response
+        ),
+    }
+}
+
+#[test]
+fn not_found() {
+    let responses = call_v1(vec![
+        MessageRequest::FileByFilename("missing.proto".to_owned()),
+        MessageRequest::FileContainingSymbol("custom.options.Missing".to_owned()),
+        MessageRequest::FileContainingExtension(ExtensionRequest {
+            containing_type: "google.protobuf.EnumValueOptions".to_owned(),
+            extension_number: 1,
+        }),
+        MessageRequest::AllExtensionNumbersOfType("Missing".to_owned()),
+    ]);
+
+    for response in &responses {
+        match response {
+            MessageResponse::ErrorResponse(error) => {
+                assert_eq!(error.error_code, Code::NotFound as i32)
+            }
+            response => panic!("expected an error response, but found {:?}", response),
+        }
+    }
+}
+
+#[test]
+fn v1alpha() {
+    let service = ReflectionService::new(test_file_descriptor()).into_v1alpha();
+    let (status, responses) = call(
+        service,
+        V1ALPHA_PATH,
+        vec![MessageRequest::FileByFilename("ext.proto".to_owned())],
+    );
+
+    assert_eq!(status.as_deref(), Some("0"));
+    assert_eq!(
+        file_names(&responses[0]),
+        ["ext.proto", "google/protobuf/descriptor.proto"]
+    );
+}
+
+#[test]
+fn unimplemented_method() {
+    let service = ReflectionService::new(test_file_descriptor());
+    let (status, responses) = call(service, V1ALPHA_PATH, vec![]);
+
+    assert_eq!(status.as_deref(), Some("12"));
+    assert!(responses.is_empty());
+}
+
+#[test]
+fn client_file_containing_symbol() {
+    let mut client = ReflectionClient::new(ReflectionService::new(test_file_descriptor()));
+    let file =
+        pollster::block_on(client.file_containing_symbol("custom.options.Service.rpc")).unwrap();
+
+    assert_eq!(file.name(), "options.proto");
+    assert!(client
+        .pool()
+        .get_file_by_name("google/protobuf/descriptor.proto")
+        .is_some());
+    assert!(client
+        .pool()
+        .get_message_by_name("custom.options.Aggregate")
+        .is_some());
+    assert!(client.pool().get_file_by_name("ext.proto").is_none());
+}
+
+#[test]
+fn client_file_by_filename() {
+    let mut client = ReflectionClient::new(ReflectionService::new(test_file_descriptor()));
+    pollster::block_on(client.file_by_filename("imports.proto")).unwrap();
+    pollster::block_on(client.file_by_filename("ext.proto")).unwrap();
+
+    let names: Vec<_> = client
+        .pool()
+        .files()
+        .map(|file| file.name().to_owned())
+        .collect();
+    assert_eq!(names.len(), 4);
+    assert!(names.contains(&"google/protobuf/any.proto".to_owned()));
+}
+
+#[test]
+fn client_extensions() {
+    let mut client = ReflectionClient::new(ReflectionService::new(test_file_descriptor()));
+    let numbers = pollster::block_on(
+        client.all_extension_numbers_of_type("google.protobuf.EnumValueOptions"),
+    )
+    .unwrap();
+    assert_eq!(numbers, [1001, 50000]);
+
+    let file = pollster::block_on(
+        client.file_containing_extension("google.protobuf.EnumValueOptions", 50000),
+    )
+    .unwrap();
+    assert_eq!(file.name(), "ext.proto");
+    assert!(client.pool().get_extension_by_name("demo.len").is_some());
+}
+
+#[test]
+fn client_uses_existing_pool() {
+    let server = ReflectionService::new(DescriptorPool::new());
+    let mut client = ReflectionClient::with_pool(server, test_file_descriptor());
+
+    let file = pollster::block_on(client.file_containing_symbol("demo.Foo")).unwrap();
+    assert_eq!(file.name(), "ext.proto");
+}
+
+#[test]
+fn client_falls_back_to_v1alpha() {
+    let server = ReflectionService::new(test_file_descriptor()).into_v1alpha();
+    let mut client = ReflectionClient::new(server);
+
+    let services = pollster::block_on(client.list_services()).unwrap();
+    assert!(services.contains(&"custom.options.Service".to_owned()));
+    let file = pollster::block_on(client.file_containing_symbol("demo.Foo")).unwrap();
+    assert_eq!(file.name(), "ext.proto");
+}
+
+#[test]
+fn client_not_found() {
+    let mut client = ReflectionClient::new(ReflectionService::new(test_file_descriptor()));
+    let err = pollster::block_on(client.file_by_filename("missing.proto")).unwrap_err();
+
+    assert_eq!(err.code(), Some(Code::NotFound));
+    assert_eq!(
+        err.to_string(),
+        "reflection request failed with status NotFound: file 'missing.proto' not found"
+    );
+}
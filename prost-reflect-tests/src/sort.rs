@@ -0,0 +1,170 @@
+use prost_reflect::{DynamicMessage, FieldDescriptor, ReflectMessage, Value};
+
+use crate::proto::{AddressBook, Contact, Point};
+
+fn contact(name: &str, age: i32, latitude: i32, score: f64) -> Contact {
+    Contact {
+        name: name.to_owned(),
+        age,
+        location: if latitude != 0 {
+            Some(Point {
+                latitude,
+                longitude: 0,
+            })
+        } else {
+            None
+        },
+        score,
+    }
+}
+
+fn names(message: &DynamicMessage) -> Vec<String> {
+    message
+        .transcode_to::<AddressBook>()
+        .unwrap()
+        .contacts
+        .into_iter()
+        .map(|contact| contact.name)
+        .collect()
+}
+
+fn address_book(contacts: Vec<Contact>) -> (DynamicMessage, FieldDescriptor) {
+    let message = AddressBook { contacts }.transcode_to_dynamic();
+    let field = message.descriptor().get_field_by_name("contacts").unwrap();
+    (message, field)
+}
+
+#[test]
+fn sort_by_string() {
+    let (mut message, field) = address_book(vec![
+        contact("carol", 0, 0, 0.0),
+        contact("alice", 0, 0, 0.0),
+        contact("bob", 0, 0, 0.0),
+    ]);
+
+    message.sort_field_by_paths(&field, &["name"]).unwrap();
+    assert_eq!(names(&message), vec!["alice", "bob", "carol"]);
+}
+
+#[test]
+fn sort_by_multiple_paths_is_stable() {
+    let (mut message, field) = address_book(vec![
+        contact("a", 30, 0, 0.0),
+        contact("b", 20, 2, 0.0),
+        contact("c", 30, 0, 0.0),
+        contact("d", 20, 1, 0.0),
+    ]);
+
+    message
+        .sort_field_by_paths(&field, &["age", "location.latitude"])
+        .unwrap();
+    assert_eq!(names(&message), vec!["d", "b", "a", "c"]);
+}
+
+#[test]
+fn sort_by_nested_path_unset_is_default() {
+    let (mut message, field) = address_book(vec![
+        contact("a", 0, 5, 0.0),
+        contact("b", 0, 0, 0.0),
+        contact("c", 0, -5, 0.0),
+    ]);
+
+    message
+        .sort_field_by_paths(&field, &["location.latitude"])
+        .unwrap();
+    assert_eq!(names(&message), vec!["c", "b", "a"]);
+}
+
+#[test]
+fn sort_by_double_nan_last() {
+    let (mut message, field) = address_book(vec![
+        contact("a", 0, 0, f64::NAN),
+        contact("b", 0, 0, 2.0),
+        contact("c", 0, 0, f64::NEG_INFINITY),
+    ]);
+
+    message.sort_field_by_paths(&field, &["score"]).unwrap();
+    assert_eq!(names(&message), vec!["c", "b", "a"]);
+}
+
+#[test]
+fn dedup_keeps_first_occurrence() {
+    let (mut message, field) = address_book(vec![
+        contact("a", 30, 0, 0.0),
+        contact("b", 20, 0, 0.0),
+        contact("c", 30, 0, 0.0),
+        contact("d", 20, 1, 0.0),
+        contact("e", 40, 0, 0.0),
+    ]);
+
+    message.dedup_field_by_paths(&field, &["age"]).unwrap();
+    assert_eq!(names(&message), vec!["a", "b", "e"]);
+}
+
+#[test]
+fn dedup_by_multiple_paths() {
+    let (mut message, field) = address_book(vec![
+        contact("a", 30, 0, 0.0),
+        contact("b", 20, 0, 0.0),
+        contact("c", 30, 0, 0.0),
+        contact("d", 20, 1, 0.0),
+    ]);
+
+    message
+        .dedup_field_by_paths(&field, &["age", "location.latitude"])
+        .unwrap();
+    assert_eq!(names(&message), vec!["a", "b", "d"]);
+}
+
+#[test]
+fn sort_unknown_path() {
+    let (mut message, field) = address_book(vec![contact("a", 0, 0, 0.0)]);
+    let err = message
+        .sort_field_by_paths(&field, &["location.missing"])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid path 'location.missing': message 'test.Point' has no field named 'missing'"
+    );
+    assert_eq!(names(&message), vec!["a"]);
+}
+
+#[test]
+fn sort_message_path() {
+    let (mut message, field) = address_book(vec![]);
+    let err = message
+        .sort_field_by_paths(&field, &["location"])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid path 'location': path does not refer to a scalar or enum field"
+    );
+}
+
+#[test]
+fn sort_non_list_field() {
+    let mut message = contact("a", 0, 0, 0.0).transcode_to_dynamic();
+    let field = message.descriptor().get_field_by_name("location").unwrap();
+    let err = message
+        .sort_field_by_paths(&field, &["latitude"])
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field 'test.Contact.location' is not a repeated message field"
+    );
+}
+
+#[test]
+fn dedup_invalid_element() {
+    let (mut message, field) = address_book(vec![contact("a", 0, 0, 0.0)]);
+    message
+        .get_field_mut(&field)
+        .as_list_mut()
+        .unwrap()
+        .push(Value::I32(1));
+    let err = message.dedup_field_by_paths(&field, &["age"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field 'test.AddressBook.contacts' contains a value which is not a message of type 'test.Contact'"
+    );
+}
@@ -0,0 +1,161 @@
+use prost_reflect::{DynamicMessage, TemplateFormat};
+
+use crate::test_file_descriptor;
+
+fn strip_json_comments(template: &str) -> String {
+    template
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn request_template_json() {
+    let method = test_file_descriptor()
+        .get_service_by_name("library.Library")
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == "ListBooks")
+        .unwrap();
+    assert_eq!(
+        method.request_template(TemplateFormat::Json),
+        r#"{
+  // string
+  "parent": "",
+  // int32
+  "pageSize": 0,
+  // repeated string
+  "tags": [
+    ""
+  ],
+  // library.Genre, one of: GENRE_UNSPECIFIED, FICTION, NON_FICTION
+  "genre": "GENRE_UNSPECIFIED",
+  // library.Filter
+  "filter": {
+    // string
+    "author": "",
+    // bool
+    "inPrint": false
+  },
+  // google.protobuf.BoolValue
+  "includeDrafts": false
+}
+"#
+    );
+}
+
+#[test]
+fn request_template_text_format() {
+    let method = test_file_descriptor()
+        .get_service_by_name("library.Library")
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == "ListBooks")
+        .unwrap();
+    assert_eq!(
+        method.request_template(TemplateFormat::TextFormat),
+        r#"# string
+parent: ""
+# int32
+page_size: 0
+# repeated string
+tags: ""
+# library.Genre, one of: GENRE_UNSPECIFIED, FICTION, NON_FICTION
+genre: GENRE_UNSPECIFIED
+# library.Filter
+filter {
+  # string
+  author: ""
+  # bool
+  in_print: false
+}
+# google.protobuf.BoolValue
+include_drafts {
+  # bool
+  value: false
+}
+"#
+    );
+}
+
+#[test]
+fn maps_and_oneofs() {
+    let pool = test_file_descriptor();
+    let complex = pool
+        .get_message_by_name("test.ComplexType")
+        .unwrap()
+        .template(TemplateFormat::TextFormat);
+    assert!(
+        complex.starts_with("# map<string, test.Scalars>\nstring_map {\n  key: \"\"\n  value {\n")
+    );
+    assert!(complex.contains("# map<int32, test.Scalars>\nint_map {\n  key: 0\n"));
+    assert!(complex.contains(
+        "# repeated test.ComplexType.MyEnum, one of: NEG, DEFAULT, FOO, BAR\nmy_enum: DEFAULT\n"
+    ));
+
+    let oneof = pool
+        .get_message_by_name("test.MessageWithOneof")
+        .unwrap()
+        .template(TemplateFormat::Json);
+    assert_eq!(
+        oneof,
+        r#"{
+  // string (oneof test_oneof)
+  "oneofField1": ""
+  // int32 (oneof test_oneof)
+  // "oneofField2": 0
+  // google.protobuf.NullValue (oneof test_oneof), one of: NULL_VALUE
+  // "oneofNull": null
+  // google.protobuf.Value (oneof test_oneof)
+  // "oneofValueNull": null
+}
+"#
+    );
+}
+
+#[test]
+fn proto2_labels_and_defaults() {
+    let pool = test_file_descriptor();
+    let defaults = pool
+        .get_message_by_name("test2.DefaultValues")
+        .unwrap()
+        .template(TemplateFormat::TextFormat);
+    assert!(defaults.contains("# optional float\nfloat: 2.2\n"));
+    assert!(defaults.contains("# optional string\nstring: \"hello\"\n"));
+    assert!(defaults
+        .contains("# optional test2.Proto2Enum, one of: DEFAULT, OTHER\ndefaulted_enum: OTHER\n"));
+
+    let group = pool
+        .get_message_by_name("test2.ContainsGroup")
+        .unwrap()
+        .template(TemplateFormat::TextFormat);
+    assert!(group.starts_with(
+        "# optional test2.ContainsGroup.RequiredGroup\nRequiredGroup {\n  # required string\n  a: \"\"\n"
+    ));
+}
+
+#[test]
+fn templates_parse() {
+    let pool = test_file_descriptor();
+    for name in [
+        "test.ComplexType",
+        "test.MessageWithOneof",
+        "test.WellKnownTypes",
+        "test2.DefaultValues",
+        "test2.ContainsGroup",
+        "library.ListBooksRequest",
+    ] {
+        let desc = pool.get_message_by_name(name).unwrap();
+
+        let json = strip_json_comments(&desc.template(TemplateFormat::Json));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        DynamicMessage::deserialize(desc.clone(), value)
+            .unwrap_or_else(|err| panic!("failed to parse JSON template for {}: {}", name, err));
+
+        let text = desc.template(TemplateFormat::TextFormat);
+        DynamicMessage::parse_text_format(desc, &text).unwrap_or_else(|err| {
+            panic!("failed to parse text format template for {}: {}", name, err)
+        });
+    }
+}
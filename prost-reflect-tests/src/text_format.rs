@@ -6,12 +6,15 @@ use std::{
 
 use proptest::prelude::*;
 use prost::Message;
-use prost_reflect::{text_format::FormatOptions, DynamicMessage, ReflectMessage, Value};
+use prost_reflect::{
+    text_format::{BytesFormat, FormatOptions, ParseOptions, UnsetFields},
+    DynamicMessage, ReflectMessage, Value,
+};
 
 use crate::{
     proto::{
-        contains_group, ComplexType, ContainsGroup, MessageWithAliasedEnum, Point, ScalarArrays,
-        Scalars, WellKnownTypes,
+        contains_group, ComplexType, ContainsGroup, MessageWithAliasedEnum, MessageWithOneof,
+        Point, ScalarArrays, Scalars, WellKnownTypes,
     },
     test_file_descriptor,
 };
@@ -156,6 +159,153 @@ fn fmt_complex_type() {
     assert_eq!(value.to_text_format_with_options(&FormatOptions::new().pretty(true)), "string_map: [{\n  key: \"1\"\n  value {\n    double: 1.1\n    float: 2.2\n    int32: 3\n  }\n}]\nint_map: [{\n  key: 3\n  value {\n    sint32: 7\n    sint64: 8\n    fixed32: 9\n  }\n}]\nnested {\n  sfixed32: 11\n  sfixed64: 12\n  bool: true\n  string: \"5\"\n  bytes: \"6\"\n}\nmy_enum: [DEFAULT, FOO, 2, BAR, NEG]\noptional_enum: FOO");
 }
 
+#[test]
+fn fmt_layout_options() {
+    let value = ComplexType {
+        int_map: HashMap::from_iter([(
+            3,
+            Scalars {
+                sint32: 7,
+                ..Default::default()
+            },
+        )]),
+        nested: Some(Scalars {
+            int32: 1,
+            r#bool: true,
+            ..Default::default()
+        }),
+        my_enum: vec![0, 1],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        value.to_text_format_with_options(&FormatOptions::new().pretty(true).single_line(true)),
+        "int_map: [{ key: 3 value { sint32: 7 } }] nested { int32: 1 bool: true } my_enum: [DEFAULT, FOO]"
+    );
+    assert_eq!(
+        value.to_text_format_with_options(&FormatOptions::new().single_line(true)),
+        value.to_text_format()
+    );
+    assert_eq!(
+        value.to_text_format_with_options(
+            &FormatOptions::new()
+                .pretty(true)
+                .indent_width(4)
+                .trailing_newline(true)
+        ),
+        "int_map: [{\n    key: 3\n    value {\n        sint32: 7\n    }\n}]\nnested {\n    int32: 1\n    bool: true\n}\nmy_enum: [DEFAULT, FOO]\n"
+    );
+    assert_eq!(
+        value.to_text_format_with_options(&FormatOptions::new().pretty(true).indent_width(0)),
+        "int_map: [{\nkey: 3\nvalue {\nsint32: 7\n}\n}]\nnested {\nint32: 1\nbool: true\n}\nmy_enum: [DEFAULT, FOO]"
+    );
+
+    let empty = ComplexType::default().transcode_to_dynamic();
+    assert_eq!(
+        empty
+            .to_text_format_with_options(&FormatOptions::new().pretty(true).trailing_newline(true)),
+        ""
+    );
+}
+
+#[test]
+fn fmt_declaration_order() {
+    let value = prost_types::FieldDescriptorProto {
+        name: Some("foo".to_owned()),
+        extendee: Some(".Bar".to_owned()),
+        number: Some(1),
+        options: Some(Default::default()),
+        json_name: Some("baz".to_owned()),
+        ..Default::default()
+    };
+    // Append an unknown field with number 100.
+    let mut bytes = value.encode_to_vec();
+    bytes.extend_from_slice(b"\xa0\x06\x05");
+    let value = DynamicMessage::decode(
+        test_file_descriptor()
+            .get_message_by_name("google.protobuf.FieldDescriptorProto")
+            .unwrap(),
+        bytes.as_slice(),
+    )
+    .unwrap();
+
+    let options = FormatOptions::new().skip_unknown_fields(false);
+    assert_eq!(
+        value.to_text_format_with_options(&options),
+        r#"name:"foo",extendee:".Bar",number:1,options{},json_name:"baz",100:5"#
+    );
+    assert_eq!(
+        value.to_text_format_with_options(&options.sort_fields_by_number(false)),
+        r#"name:"foo",number:1,extendee:".Bar",json_name:"baz",options{},100:5"#
+    );
+}
+
+#[test]
+fn fmt_default_fields() {
+    let empty = ComplexType::default().transcode_to_dynamic();
+    assert_eq!(
+        empty.to_text_format_with_options(&FormatOptions::new().skip_default_fields(false)),
+        "string_map:[],int_map:[],my_enum:[],optional_enum:DEFAULT"
+    );
+    assert_eq!(
+        empty.to_text_format_with_options(
+            &FormatOptions::new()
+                .pretty(true)
+                .skip_default_fields(false)
+                .unset_fields(UnsetFields::Comment)
+        ),
+        "string_map: []\nint_map: []\n# nested {}\nmy_enum: []\noptional_enum: DEFAULT"
+    );
+    assert_eq!(
+        empty.to_text_format_with_options(
+            &FormatOptions::new()
+                .pretty(true)
+                .single_line(true)
+                .unset_fields(UnsetFields::Comment)
+        ),
+        ""
+    );
+
+    let options = FormatOptions::new()
+        .skip_default_fields(false)
+        .unset_fields(UnsetFields::Default);
+    let text = empty.to_text_format_with_options(&options);
+    assert_eq!(
+        text,
+        "string_map:[],int_map:[],nested{},my_enum:[],optional_enum:DEFAULT"
+    );
+    let parsed = DynamicMessage::parse_text_format(empty.descriptor(), &text).unwrap();
+    assert_eq!(
+        parsed.transcode_to::<ComplexType>().unwrap(),
+        ComplexType {
+            nested: Some(Scalars::default()),
+            ..Default::default()
+        }
+    );
+
+    let nested = ComplexType {
+        nested: Some(Scalars::default()),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    assert_eq!(
+        nested.to_text_format_with_options(&FormatOptions::new().skip_default_fields(false)),
+        "string_map:[],int_map:[],nested{double:0.0,float:0.0,int32:0,int64:0,uint32:0,uint64:0,sint32:0,sint64:0,fixed32:0,fixed64:0,sfixed32:0,sfixed64:0,bool:false,string:\"\",bytes:\"\"},my_enum:[],optional_enum:DEFAULT"
+    );
+
+    let oneof = MessageWithOneof::default().transcode_to_dynamic();
+    assert_eq!(oneof.to_text_format_with_options(&options), "");
+    assert_eq!(
+        oneof.to_text_format_with_options(
+            &FormatOptions::new()
+                .pretty(true)
+                .unset_fields(UnsetFields::Comment)
+        ),
+        "# oneof_field_1: \"\"\n# oneof_field_2: 0\n# oneof_null: NULL_VALUE\n# oneof_value_null {}"
+    );
+}
+
 #[test]
 fn fmt_well_known_types() {
     let value = WellKnownTypes {
@@ -353,6 +503,125 @@ fn fmt_group() {
     );
 }
 
+#[test]
+fn fmt_field_numbers() {
+    let value = ComplexType {
+        int_map: HashMap::from_iter([(
+            3,
+            Scalars {
+                sint32: 7,
+                ..Default::default()
+            },
+        )]),
+        my_enum: vec![0, 1],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let format_options = FormatOptions::new().use_field_numbers(true);
+    let parse_options = ParseOptions::new().allow_field_numbers(true);
+
+    let text = value.to_text_format_with_options(&format_options);
+    assert_eq!(text, "2:[{1:3,2{7:7}}],4:[DEFAULT,FOO]");
+    assert_eq!(
+        value.to_text_format_with_options(&format_options.clone().pretty(true)),
+        "2: [{\n  1: 3\n  2 {\n    7: 7\n  }\n}]\n4: [DEFAULT, FOO]"
+    );
+    assert!(DynamicMessage::parse_text_format(value.descriptor(), &text).is_err());
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(value.descriptor(), &text, &parse_options)
+            .unwrap(),
+        value
+    );
+
+    let group = ContainsGroup {
+        requiredgroup: Some(contains_group::RequiredGroup {
+            a: "bar".to_owned(),
+            b: None,
+        }),
+        repeatedgroup: vec![contains_group::RepeatedGroup {
+            e: "hello".to_owned(),
+            f: Some(10),
+        }],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    let text = group.to_text_format_with_options(&format_options);
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(group.descriptor(), &text, &parse_options)
+            .unwrap(),
+        group
+    );
+
+    let err =
+        DynamicMessage::parse_text_format_with_options(value.descriptor(), "99: 1", &parse_options)
+            .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field '99' not found for message 'test.ComplexType'"
+    );
+    assert!(DynamicMessage::parse_text_format_with_options(
+        value.descriptor(),
+        "99: { 1: 2 } 99999999999: [1] 2: []",
+        &parse_options.allow_unknown_fields(true),
+    )
+    .is_ok());
+}
+
+#[test]
+fn fmt_bytes_format() {
+    let value = Scalars {
+        string: "a\x01".to_owned(),
+        bytes: b"ab\x01\xff".to_vec(),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let text =
+        value.to_text_format_with_options(&FormatOptions::new().bytes_format(BytesFormat::Hex));
+    assert_eq!(text, r#"string:"a\001",bytes:"\x61\x62\x01\xff""#);
+    assert_eq!(from_text::<Scalars>(&text), value.transcode_to().unwrap());
+
+    let options = FormatOptions::new()
+        .pretty(true)
+        .bytes_format(BytesFormat::Base64Comment);
+    let text = value.to_text_format_with_options(&options);
+    assert_eq!(text, "string: \"a\\001\"\n# bytes (base64): \"YWIB/w==\"");
+    assert_eq!(
+        from_text::<Scalars>(&text),
+        Scalars {
+            string: "a\x01".to_owned(),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        value.to_text_format_with_options(&options.clone().single_line(true)),
+        r#"string: "a\001" bytes: "ab\001\377""#
+    );
+
+    let list = ScalarArrays {
+        bytes: vec![b"abc".to_vec(), b"abcdef".to_vec()],
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+    assert_eq!(
+        list.to_text_format_with_options(&options.max_bytes_length(Some(4))),
+        r#"# bytes (base64): ["YWJj", "YWJjZA=="...]"#
+    );
+    assert_eq!(
+        list.to_text_format_with_options(&FormatOptions::new().max_bytes_length(Some(3))),
+        r#"bytes:["abc","abc"...]"#
+    );
+    assert_eq!(
+        list.to_text_format_with_options(
+            &FormatOptions::new()
+                .bytes_format(BytesFormat::Hex)
+                .max_bytes_length(Some(0))
+        ),
+        r#"bytes:[""...,""...]"#
+    );
+}
+
 #[test]
 fn parse_scalars() {
     let value: Scalars = from_text(
@@ -574,6 +843,63 @@ fn deserialize_any() {
     );
 }
 
+#[test]
+fn any_type_resolver() {
+    let resolver = |type_url: &str| {
+        let message_name = type_url.strip_prefix("type.example.com/")?;
+        test_file_descriptor().get_message_by_name(message_name)
+    };
+    let any = prost_types::Any {
+        type_url: "type.example.com/test.Point".to_owned(),
+        value: Point {
+            longitude: 1,
+            latitude: 2,
+        }
+        .encode_to_vec(),
+    };
+
+    let value = transcode_any(&any);
+    assert_eq!(
+        value.to_text_format(),
+        r#"type_url:"type.example.com/test.Point",value:"\010\002\020\001""#
+    );
+    assert_eq!(
+        value.to_text_format_with_options(&FormatOptions::new().type_resolver(resolver)),
+        "[type.example.com/test.Point]{latitude:2,longitude:1}"
+    );
+
+    let desc = test_file_descriptor()
+        .get_message_by_name("google.protobuf.Any")
+        .unwrap();
+    let text = "[type.example.com/test.Point]: { longitude: 1, latitude: 2 }";
+    let parsed: prost_types::Any = DynamicMessage::parse_text_format_with_options(
+        desc.clone(),
+        text,
+        &ParseOptions::new().type_resolver(resolver),
+    )
+    .unwrap()
+    .transcode_to()
+    .unwrap();
+    assert_eq!(parsed, any);
+
+    assert_eq!(
+        DynamicMessage::parse_text_format(desc.clone(), text)
+            .unwrap_err()
+            .to_string(),
+        "unknown domain 'type.example.com' for type url"
+    );
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(
+            desc,
+            "[type.googleapis.com/test.Point]: {}",
+            &ParseOptions::new().type_resolver(resolver),
+        )
+        .unwrap_err()
+        .to_string(),
+        "message type 'test.Point' not found"
+    );
+}
+
 #[test]
 fn parse_error() {
     fn error(s: &str) -> String {
@@ -722,6 +1048,219 @@ fn duplicate_oneof_field() {
     );
 }
 
+#[test]
+fn parse_error_location() {
+    let desc = test_file_descriptor()
+        .get_message_by_name("test.ComplexType")
+        .unwrap();
+
+    let input = "nested {\r\n  int32: 1\r\n  string: \"caf\u{e9}\" bool: maybe\r\n}";
+    let err = DynamicMessage::parse_text_format(desc.clone(), input).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "expected 'true' or 'false', but found 'maybe'"
+    );
+    assert_eq!(err.line(), Some(3));
+    assert_eq!(err.column(), Some(24));
+    assert_eq!(err.token(), Some("maybe"));
+    assert_eq!(&input[err.span().unwrap()], "maybe");
+    assert_eq!(
+        err.snippet().unwrap(),
+        "3 |   string: \"caf\u{e9}\" bool: maybe\n  |                        ^^^^^\n"
+    );
+
+    let err = DynamicMessage::parse_text_format(desc.clone(), "my_enum: [FOO, BAZ]").unwrap_err();
+    assert_eq!((err.line(), err.column()), (Some(1), Some(16)));
+    assert_eq!(err.token(), Some("BAZ"));
+
+    let input = "\n\n\n\n\n\n\n\n\n\nnested {\n  int32: 1\n  int32: 2\n}";
+    let err = DynamicMessage::parse_text_format(desc.clone(), input).unwrap_err();
+    assert_eq!(err.to_string(), "'int32' is already set");
+    assert_eq!(err.snippet().unwrap(), "13 |   int32: 2\n   |          ^\n");
+
+    let err = DynamicMessage::parse_text_format(desc, "nested {").unwrap_err();
+    assert_eq!(err.span(), None);
+    assert_eq!(err.line(), None);
+    assert_eq!(err.snippet(), None);
+}
+
+#[test]
+fn allow_duplicate_fields() {
+    let options = ParseOptions::new().deny_duplicate_fields(false);
+
+    let desc = test_file_descriptor()
+        .get_message_by_name("test.MessageWithOneof")
+        .unwrap();
+    let d = DynamicMessage::parse_text_format_with_options(
+        desc,
+        "oneof_field_1: 'hello', oneof_field_2: 5",
+        &options,
+    )
+    .unwrap();
+    assert!(!d.has_field_by_name("oneof_field_1"));
+    assert_eq!(
+        d.get_field_by_name("oneof_field_2").unwrap().as_ref(),
+        &Value::I32(5)
+    );
+
+    let desc = test_file_descriptor()
+        .get_message_by_name("test.Scalars")
+        .unwrap();
+    let d = DynamicMessage::parse_text_format_with_options(
+        desc.clone(),
+        "string: '5' ; string: '6'",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        d.get_field_by_name("string").unwrap().as_ref(),
+        &Value::String("6".to_owned())
+    );
+
+    // Other checks are unaffected.
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(desc.clone(), r#"string: "\xFF""#, &options)
+            .unwrap_err()
+            .to_string(),
+        "string is not valid utf-8"
+    );
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(desc, "bytes: '\\q'", &options)
+            .unwrap_err()
+            .to_string(),
+        "invalid string escape"
+    );
+}
+
+#[test]
+fn allow_unknown_fields() {
+    let options = ParseOptions::new().allow_unknown_fields(true);
+    let desc = test_file_descriptor()
+        .get_message_by_name("test.ComplexType")
+        .unwrap();
+
+    let d = DynamicMessage::parse_text_format_with_options(
+        desc.clone(),
+        r#"
+            unknown_scalar: -1.5e3
+            nested { int32: 1 unknown_nested < a: "x" 'y' b: true [c.d]: [] > }
+            unknown_list: [1, -inf, ENUM, { a: [{}, <>] }, "s"];
+            [unknown.extension] { e: 1 }
+            my_enum: FOO
+            unknown_message <>
+        "#,
+        &options,
+    )
+    .unwrap();
+    assert_eq!(
+        d.transcode_to::<ComplexType>().unwrap(),
+        ComplexType {
+            nested: Some(Scalars {
+                int32: 1,
+                ..Default::default()
+            }),
+            my_enum: vec![1],
+            ..Default::default()
+        }
+    );
+
+    // Unknown fields must still be syntactically valid.
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(desc.clone(), "unknown { a: }", &options)
+            .unwrap_err()
+            .to_string(),
+        "expected a value, but found '}'"
+    );
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(desc.clone(), "unknown: [1 2]", &options)
+            .unwrap_err()
+            .to_string(),
+        "expected ',' or ']', but found '2'"
+    );
+    assert_eq!(
+        DynamicMessage::parse_text_format(desc, "unknown: 1")
+            .unwrap_err()
+            .to_string(),
+        "field 'unknown' not found for message 'test.ComplexType'"
+    );
+}
+
+#[test]
+fn preserve_unknown_fields() {
+    let empty = test_file_descriptor()
+        .get_message_by_name("google.protobuf.Empty")
+        .unwrap();
+    let format_options = FormatOptions::new().skip_unknown_fields(false);
+    let parse_options = ParseOptions::new().preserve_unknown_fields(true);
+
+    let bytes = Scalars {
+        double: 1.1,
+        float: 2.2,
+        int32: -3,
+        sint64: -8,
+        fixed32: 9,
+        r#bool: true,
+        string: "hello".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    }
+    .encode_to_vec();
+    let value = DynamicMessage::decode(empty.clone(), bytes.as_slice()).unwrap();
+    let text = value.to_text_format_with_options(&format_options);
+    assert_eq!(
+        text,
+        r#"1:0x3ff199999999999a,2:0x400ccccd,3:18446744073709551613,8:15,9:0x00000009,13:1,14:"hello",15:"\000\377""#
+    );
+    let parsed =
+        DynamicMessage::parse_text_format_with_options(empty.clone(), &text, &parse_options)
+            .unwrap();
+    assert_eq!(parsed, value);
+    assert_eq!(parsed.encode_to_vec(), bytes);
+
+    let bytes = ComplexType {
+        nested: Some(Scalars {
+            int32: 5,
+            ..Default::default()
+        }),
+        my_enum: vec![1, 3],
+        ..Default::default()
+    }
+    .encode_to_vec();
+    let value = DynamicMessage::decode(empty.clone(), bytes.as_slice()).unwrap();
+    let text = value.to_text_format_with_options(&format_options.pretty(true));
+    let parsed =
+        DynamicMessage::parse_text_format_with_options(empty.clone(), &text, &parse_options)
+            .unwrap();
+    assert_eq!(parsed.encode_to_vec(), bytes);
+
+    let parsed = DynamicMessage::parse_text_format_with_options(
+        empty.clone(),
+        "1: -1 2: [1.5, -inf] 3 < 4: [] >",
+        &parse_options,
+    )
+    .unwrap();
+    assert_eq!(
+        parsed.to_text_format_with_options(&FormatOptions::new().skip_unknown_fields(false)),
+        "1:18446744073709551615,2:0x3ff8000000000000,2:0xfff0000000000000,3:\"\""
+    );
+
+    assert_eq!(
+        DynamicMessage::parse_text_format_with_options(empty.clone(), "0: 1", &parse_options)
+            .unwrap_err()
+            .to_string(),
+        "expected value to be a field number, but the value 0 is out of range"
+    );
+    assert!(DynamicMessage::parse_text_format_with_options(
+        empty.clone(),
+        "1 { foo: 1 }",
+        &parse_options
+    )
+    .is_err());
+    assert!(
+        DynamicMessage::parse_text_format_with_options(empty, "foo: 1", &parse_options).is_err()
+    );
+}
+
 proptest! {
     #![proptest_config(ProptestConfig {
         cases: 32,
@@ -0,0 +1,409 @@
+use http::{header, Method, Request, Response, StatusCode};
+use prost::bytes::Bytes;
+use prost_reflect::{
+    transcoding::{HttpRule, Transcoder},
+    DynamicMessage, MethodDescriptor, Value,
+};
+use serde_json::json;
+use tonic::{Code, Status};
+
+use crate::test_file_descriptor;
+
+fn library_method(name: &str) -> MethodDescriptor {
+    test_file_descriptor()
+        .get_service_by_name("library.Library")
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == name)
+        .unwrap()
+}
+
+fn transcoder() -> Transcoder {
+    Transcoder::from_pool(&test_file_descriptor()).unwrap()
+}
+
+fn request(method: Method, uri: &str, body: &str) -> Request<Vec<u8>> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .body(body.as_bytes().to_vec())
+        .unwrap()
+}
+
+fn transcode(method: Method, uri: &str, body: &str) -> (MethodDescriptor, serde_json::Value) {
+    let transcoded = transcoder()
+        .transcode_request(&request(method, uri, body))
+        .unwrap();
+    let json = serde_json::to_value(transcoded.message()).unwrap();
+    (transcoded.method().clone(), json)
+}
+
+fn response_json(response: &Response<Bytes>) -> serde_json::Value {
+    assert_eq!(
+        response.headers().get(header::CONTENT_TYPE).unwrap(),
+        "application/json"
+    );
+    serde_json::from_slice(response.body()).unwrap()
+}
+
+#[test]
+fn rules_from_options() {
+    let rules = HttpRule::from_method(&library_method("UpdateBook")).unwrap();
+    assert_eq!(
+        rules,
+        [
+            HttpRule::new(Method::PATCH, "/v1/{book.name=shelves/*/books/*}").body("book"),
+            HttpRule::new(Method::PUT, "/v1/{book.name=shelves/*/books/*}").body("*"),
+        ]
+    );
+
+    let rules = HttpRule::from_method(&library_method("ListBooks")).unwrap();
+    assert_eq!(
+        rules,
+        [HttpRule::new(Method::GET, "/v1/{parent=shelves/*}/books").response_body("books")]
+    );
+}
+
+#[test]
+fn path_variables() {
+    let (method, json) = transcode(Method::GET, "/v1/shelves/1/books/2", "");
+    assert_eq!(method, library_method("GetBook"));
+    assert_eq!(json, json!({ "name": "shelves/1/books/2" }));
+}
+
+#[test]
+fn path_variable_percent_decoding() {
+    let (_, json) = transcode(Method::GET, "/v1/shelves/a%20b/books/c%2Fd", "");
+    assert_eq!(json, json!({ "name": "shelves/a b/books/c%2Fd" }));
+}
+
+#[test]
+fn path_double_wildcard() {
+    let (method, json) = transcode(Method::GET, "/v1/files/a/b/c.txt", "");
+    assert_eq!(method, library_method("GetFile"));
+    assert_eq!(json, json!({ "name": "a/b/c.txt" }));
+}
+
+#[test]
+fn path_verb() {
+    let (method, json) = transcode(
+        Method::POST,
+        "/v1/shelves/1/books/2:archive",
+        r#"{"reason":"old"}"#,
+    );
+    assert_eq!(method, library_method("ArchiveBook"));
+    assert_eq!(
+        json,
+        json!({ "name": "shelves/1/books/2", "reason": "old" })
+    );
+}
+
+#[test]
+fn query_parameters() {
+    let (method, json) = transcode(
+        Method::GET,
+        "/v1/shelves/1/books?pageSize=10&tags=a&tags=b+c&genre=FICTION&filter.author=J%C3%B6rg&filter.in_print=true&include_drafts=false",
+        "",
+    );
+    assert_eq!(method, library_method("ListBooks"));
+    assert_eq!(
+        json,
+        json!({
+            "parent": "shelves/1",
+            "pageSize": 10,
+            "tags": ["a", "b c"],
+            "genre": "FICTION",
+            "filter": { "author": "Jörg", "inPrint": true },
+            "includeDrafts": false,
+        })
+    );
+}
+
+#[test]
+fn query_parameter_enum_number() {
+    let (_, json) = transcode(Method::GET, "/v1/shelves/1/books?genre=2", "");
+    assert_eq!(
+        json,
+        json!({ "parent": "shelves/1", "genre": "NON_FICTION" })
+    );
+}
+
+#[test]
+fn path_takes_precedence_over_query() {
+    let (_, json) = transcode(Method::GET, "/v1/shelves/1/books?parent=shelves/2", "");
+    assert_eq!(json, json!({ "parent": "shelves/1" }));
+}
+
+#[test]
+fn body_field() {
+    let (method, json) = transcode(
+        Method::POST,
+        "/v1/shelves/1/books",
+        r#"{"title":"Dune","pageCount":412}"#,
+    );
+    assert_eq!(method, library_method("CreateBook"));
+    assert_eq!(
+        json,
+        json!({
+            "parent": "shelves/1",
+            "book": { "title": "Dune", "pageCount": 412 },
+        })
+    );
+}
+
+#[test]
+fn body_field_with_nested_path_variable() {
+    let (method, json) = transcode(
+        Method::PATCH,
+        "/v1/shelves/1/books/2",
+        r#"{"name":"ignored","title":"Dune"}"#,
+    );
+    assert_eq!(method, library_method("UpdateBook"));
+    assert_eq!(
+        json,
+        json!({ "book": { "name": "shelves/1/books/2", "title": "Dune" } })
+    );
+}
+
+#[test]
+fn body_wildcard() {
+    let (method, json) = transcode(
+        Method::PUT,
+        "/v1/shelves/1/books/2?book.title=ignored",
+        r#"{"book":{"title":"Dune"}}"#,
+    );
+    assert_eq!(method, library_method("UpdateBook"));
+    assert_eq!(
+        json,
+        json!({ "book": { "name": "shelves/1/books/2", "title": "Dune" } })
+    );
+}
+
+#[test]
+fn no_route() {
+    let transcoder = transcoder();
+    let err = transcoder
+        .transcode_request(&request(Method::GET, "/v1/shelves/1", ""))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(err.to_string(), "no method found for GET /v1/shelves/1");
+
+    let err = transcoder
+        .transcode_request(&request(Method::DELETE, "/v1/shelves/1/books/2", ""))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn streaming_methods_are_skipped() {
+    let err = transcoder()
+        .transcode_request(&request(Method::GET, "/v1/books:watch", ""))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+
+    let err = Transcoder::new()
+        .add_rule(
+            library_method("WatchBooks"),
+            HttpRule::new(Method::GET, "/v1/books:watch"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid HTTP rule for method 'library.Library.WatchBooks': streaming methods cannot be transcoded"
+    );
+}
+
+#[test]
+fn unknown_query_parameter() {
+    let err = transcoder()
+        .transcode_request(&request(Method::GET, "/v1/shelves/1/books?foo=1", ""))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(err.to_string(), "unknown parameter 'foo'");
+}
+
+#[test]
+fn invalid_query_parameter() {
+    let err = transcoder()
+        .transcode_request(&request(
+            Method::GET,
+            "/v1/shelves/1/books?pageSize=ten",
+            "",
+        ))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert!(err
+        .to_string()
+        .starts_with("invalid value for parameter 'pageSize': "));
+}
+
+#[test]
+fn invalid_body() {
+    let err = transcoder()
+        .transcode_request(&request(Method::POST, "/v1/shelves/1/books", "{"))
+        .unwrap_err();
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert!(err.to_string().starts_with("invalid request body: "));
+}
+
+#[test]
+fn invalid_rules() {
+    let mut transcoder = Transcoder::new();
+    let method = library_method("GetBook");
+
+    let err = transcoder
+        .add_rule(method.clone(), HttpRule::new(Method::GET, "v1/books"))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid path template 'v1/books' for method 'library.Library.GetBook': the template must start with '/'"
+    );
+
+    let err = transcoder
+        .add_rule(method.clone(), HttpRule::new(Method::GET, "/v1/{missing}"))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid HTTP rule for method 'library.Library.GetBook': message 'library.GetBookRequest' has no field named 'missing'"
+    );
+
+    let err = transcoder
+        .add_rule(
+            method,
+            HttpRule::new(Method::GET, "/v1/books").response_body("missing"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid HTTP rule for method 'library.Library.GetBook': message 'library.Book' has no field named 'missing'"
+    );
+}
+
+#[test]
+fn explicit_rule() {
+    let mut transcoder = Transcoder::new();
+    transcoder
+        .add_rule(
+            library_method("GetBook"),
+            HttpRule::new(Method::GET, "/books/{name}"),
+        )
+        .unwrap();
+
+    let transcoded = transcoder
+        .transcode_request(&request(Method::GET, "/books/dune", ""))
+        .unwrap();
+    assert_eq!(transcoded.method(), &library_method("GetBook"));
+    assert_eq!(
+        transcoded
+            .message()
+            .get_field_by_name("name")
+            .unwrap()
+            .as_str(),
+        Some("dune")
+    );
+}
+
+#[test]
+fn handle() {
+    let mut request = request(Method::GET, "/v1/shelves/1/books/2", "");
+    request
+        .headers_mut()
+        .insert("x-request-id", "42".parse().unwrap());
+
+    let response = pollster::block_on(transcoder().handle(request, |method, request| async move {
+        assert_eq!(method, library_method("GetBook"));
+        assert_eq!(request.metadata().get("x-request-id").unwrap(), "42");
+
+        let mut book = DynamicMessage::new(method.output());
+        book.set_field_by_name(
+            "name",
+            request
+                .get_ref()
+                .get_field_by_name("name")
+                .unwrap()
+                .into_owned(),
+        );
+        book.set_field_by_name("title", Value::String("Dune".to_owned()));
+        let mut response = tonic::Response::new(book);
+        response
+            .metadata_mut()
+            .insert("x-response-id", "43".parse().unwrap());
+        Ok(response)
+    }));
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("x-response-id").unwrap(), "43");
+    assert_eq!(
+        response_json(&response),
+        json!({ "name": "shelves/1/books/2", "title": "Dune" })
+    );
+}
+
+#[test]
+fn handle_response_body() {
+    let request = request(Method::GET, "/v1/shelves/1/books", "");
+    let response = pollster::block_on(transcoder().handle(request, |method, _| async move {
+        let mut book = DynamicMessage::new(library_method("GetBook").output());
+        book.set_field_by_name("title", Value::String("Dune".to_owned()));
+        let mut response = DynamicMessage::new(method.output());
+        response.set_field_by_name("books", Value::List(vec![Value::Message(book)]));
+        response.set_field_by_name("next_page_token", Value::String("next".to_owned()));
+        Ok(tonic::Response::new(response))
+    }));
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_json(&response), json!([{ "title": "Dune" }]));
+}
+
+#[test]
+fn handle_empty_response_body() {
+    let request = request(Method::GET, "/v1/shelves/1/books", "");
+    let response = pollster::block_on(transcoder().handle(request, |method, _| async move {
+        Ok(tonic::Response::new(DynamicMessage::new(method.output())))
+    }));
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response_json(&response), json!([]));
+}
+
+#[test]
+fn handle_error_status() {
+    let request = request(Method::GET, "/v1/shelves/1/books/2", "");
+    let response = pollster::block_on(transcoder().handle(request, |_, _| async move {
+        Err(Status::new(Code::PermissionDenied, "not allowed"))
+    }));
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response_json(&response),
+        json!({ "code": 7, "message": "not allowed", "details": [] })
+    );
+}
+
+#[test]
+fn handle_no_route() {
+    let request = request(Method::GET, "/v2/books", "");
+    let response = pollster::block_on(transcoder().handle(request, |_, _| async move {
+        Err(Status::internal("the handler should not be called"))
+    }));
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response_json(&response),
+        json!({ "code": 5, "message": "no method found for GET /v2/books", "details": [] })
+    );
+}
+
+#[test]
+fn handle_invalid_response_type() {
+    let request = request(Method::GET, "/v1/shelves/1/books/2", "");
+    let response = pollster::block_on(transcoder().handle(request, |method, _| async move {
+        Ok(tonic::Response::new(DynamicMessage::new(method.input())))
+    }));
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(
+        response_json(&response)["message"],
+        "invalid response: expected a response of type 'library.Book', but found 'library.GetBookRequest'"
+    );
+}
@@ -0,0 +1,191 @@
+use std::sync::{Arc, Mutex};
+
+use prost_reflect::{
+    DecodeOptions, DeprecatedUsageCounter, DescriptorPool, DeserializeOptions, DynamicMessage,
+    EnumValueDescriptor, FieldDescriptor, MessageDescriptor, UsageObserver,
+};
+use prost_types::{
+    field_descriptor_proto::{Label, Type},
+    DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, EnumValueOptions,
+    FieldDescriptorProto, FieldOptions, FileDescriptorProto, MessageOptions,
+};
+
+fn message_descriptor() -> MessageDescriptor {
+    let deprecated = || FieldOptions {
+        deprecated: Some(true),
+        ..Default::default()
+    };
+
+    let mut pool = DescriptorPool::new();
+    pool.add_file_descriptor_proto(FileDescriptorProto {
+        name: Some("usage.proto".to_owned()),
+        package: Some("usage".to_owned()),
+        syntax: Some("proto3".to_owned()),
+        message_type: vec![DescriptorProto {
+            name: Some("Request".to_owned()),
+            field: vec![
+                FieldDescriptorProto {
+                    options: Some(deprecated()),
+                    ..field("old_id", 1, Label::Optional, Type::Int32, None)
+                },
+                field("id", 2, Label::Optional, Type::Int32, None),
+                field(
+                    "statuses",
+                    3,
+                    Label::Repeated,
+                    Type::Enum,
+                    Some(".usage.Status"),
+                ),
+                field(
+                    "labels",
+                    4,
+                    Label::Repeated,
+                    Type::Message,
+                    Some(".usage.Request.LabelsEntry"),
+                ),
+                field(
+                    "nested",
+                    5,
+                    Label::Optional,
+                    Type::Message,
+                    Some(".usage.Request"),
+                ),
+            ],
+            nested_type: vec![DescriptorProto {
+                name: Some("LabelsEntry".to_owned()),
+                field: vec![
+                    field("key", 1, Label::Optional, Type::String, None),
+                    field(
+                        "value",
+                        2,
+                        Label::Optional,
+                        Type::Enum,
+                        Some(".usage.Status"),
+                    ),
+                ],
+                options: Some(MessageOptions {
+                    map_entry: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+        enum_type: vec![EnumDescriptorProto {
+            name: Some("Status".to_owned()),
+            value: vec![
+                enum_value("STATUS_UNSPECIFIED", 0, false),
+                enum_value("STATUS_OLD", 1, true),
+                enum_value("STATUS_NEW", 2, false),
+            ],
+            ..Default::default()
+        }],
+        ..Default::default()
+    })
+    .unwrap();
+    pool.get_message_by_name("usage.Request").unwrap()
+}
+
+fn field(
+    name: &str,
+    number: i32,
+    label: Label,
+    ty: Type,
+    type_name: Option<&str>,
+) -> FieldDescriptorProto {
+    FieldDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        label: Some(label as i32),
+        r#type: Some(ty as i32),
+        type_name: type_name.map(ToOwned::to_owned),
+        ..Default::default()
+    }
+}
+
+fn enum_value(name: &str, number: i32, deprecated: bool) -> EnumValueDescriptorProto {
+    EnumValueDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        options: deprecated.then(|| EnumValueOptions {
+            deprecated: Some(true),
+            ..Default::default()
+        }),
+    }
+}
+
+#[derive(Clone, Default)]
+struct PathRecorder(Arc<Mutex<Vec<String>>>);
+
+impl UsageObserver for PathRecorder {
+    fn deprecated_field(&self, path: &str, field: &FieldDescriptor) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}: {}", path, field.full_name()));
+    }
+
+    fn deprecated_enum_value(&self, path: &str, value: &EnumValueDescriptor) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}: {}", path, value.full_name()));
+    }
+}
+
+// old_id = 1, statuses = [1, 2, 1], labels = {"a": 1}, nested.old_id = 2
+const ENCODED: &[u8] = b"\x08\x01\x1a\x03\x01\x02\x01\x22\x05\x0a\x01a\x10\x01\x2a\x02\x08\x02";
+
+const JSON: &str = r#"{
+    "oldId": 1,
+    "statuses": ["STATUS_OLD", "STATUS_NEW", "STATUS_OLD"],
+    "labels": { "a": "STATUS_OLD" },
+    "nested": { "oldId": 2 }
+}"#;
+
+#[test]
+fn decode_reports_deprecated_usage() {
+    let recorder = PathRecorder::default();
+    let options = DecodeOptions::new().usage_observer(recorder.clone());
+    DynamicMessage::decode_with_options(message_descriptor(), ENCODED, &options).unwrap();
+
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        [
+            "old_id: usage.Request.old_id",
+            "statuses[0]: usage.STATUS_OLD",
+            "statuses[2]: usage.STATUS_OLD",
+            "labels[\"a\"]: usage.STATUS_OLD",
+            "nested.old_id: usage.Request.old_id",
+        ]
+    );
+}
+
+#[test]
+fn deserialize_reports_deprecated_usage() {
+    let desc = message_descriptor();
+    let recorder = PathRecorder::default();
+    let options = DeserializeOptions::new().usage_observer(recorder.clone());
+    let mut deserializer = serde_json::Deserializer::from_str(JSON);
+    let message =
+        DynamicMessage::deserialize_with_options(desc.clone(), &mut deserializer, &options)
+            .unwrap();
+    assert_eq!(message, DynamicMessage::decode(desc, ENCODED).unwrap());
+
+    assert_eq!(recorder.0.lock().unwrap().len(), 5);
+}
+
+#[test]
+fn count_deprecated_usage() {
+    let counter = DeprecatedUsageCounter::new();
+    let options = DecodeOptions::new().usage_observer(counter.clone());
+    DynamicMessage::decode_with_options(message_descriptor(), ENCODED, &options).unwrap();
+    DynamicMessage::decode_with_options(message_descriptor(), b"\x10\x01".as_ref(), &options)
+        .unwrap();
+
+    let counts = counter.take();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts["usage.Request.old_id"], 2);
+    assert_eq!(counts["usage.STATUS_OLD"], 3);
+    assert!(counter.counts().is_empty());
+}
@@ -0,0 +1,104 @@
+use prost::Message;
+use prost_reflect::wire::{self, WireField, WireValue};
+
+use crate::proto::{contains_group, ComplexType, ContainsGroup, Scalars};
+
+#[test]
+fn dump_scalars() {
+    let value = Scalars {
+        double: 1.5,
+        float: 2.5,
+        int32: -1,
+        fixed32: 9,
+        string: "hello\nworld".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        wire::dump(&value.encode_to_vec()),
+        "1: 4609434218613702656i64\n\
+         2: 1075838976i32\n\
+         3: 18446744073709551615\n\
+         9: 9i32\n\
+         14: {\"hello\\nworld\"}\n\
+         15: {`00ff`}\n"
+    );
+}
+
+#[test]
+fn dump_nested() {
+    let value = ComplexType {
+        nested: Some(Scalars {
+            int32: 5,
+            string: "a".to_owned(),
+            ..Default::default()
+        }),
+        my_enum: vec![1, 3],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        wire::dump(&value.encode_to_vec()),
+        "3: {\n  3: 5\n  14: {\"a\"}\n}\n4: {`0103`}\n"
+    );
+}
+
+#[test]
+fn dump_group() {
+    let value = ContainsGroup {
+        requiredgroup: Some(contains_group::RequiredGroup {
+            a: "bar".to_owned(),
+            b: Some(1),
+        }),
+        ..Default::default()
+    };
+
+    let bytes = value.encode_to_vec();
+    assert_eq!(wire::dump(&bytes), "1: !{\n  1: {\"bar\"}\n  2: 1\n}\n");
+    assert_eq!(
+        wire::decode(&bytes).unwrap(),
+        vec![WireField {
+            number: 1,
+            offset: 0,
+            value: WireValue::Group(vec![
+                WireField {
+                    number: 1,
+                    offset: 0,
+                    value: WireValue::String("bar".to_owned()),
+                },
+                WireField {
+                    number: 2,
+                    offset: 5,
+                    value: WireValue::Varint(1),
+                },
+            ]),
+        }]
+    );
+}
+
+#[test]
+fn decode_malformed() {
+    assert!(wire::decode(b"\x08").is_err());
+    assert!(wire::decode(b"\x0c").is_err());
+    assert!(wire::decode(b"\x0b\x08\x01").is_err());
+    assert!(wire::decode(b"\x0b\x08\x01\x14").is_err());
+    assert_eq!(
+        wire::dump(b"\x08\x01\x0c"),
+        "1: 1\n# error at offset 2: failed to decode Protobuf message: unexpected end group tag\n# `0c`\n"
+    );
+}
+
+#[test]
+fn decode_deeply_nested() {
+    let mut bytes = b"\x08\x01".to_vec();
+    for _ in 0..200 {
+        let mut outer = vec![0x0a];
+        prost::encoding::encode_varint(bytes.len() as u64, &mut outer);
+        outer.extend(bytes);
+        bytes = outer;
+    }
+
+    let fields = wire::decode(&bytes).unwrap();
+    assert_eq!(fields.len(), 1);
+}
@@ -0,0 +1,62 @@
+use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+
+use crate::proto::WellKnownTypes;
+
+#[test]
+fn get_field_unwrapped() {
+    let message = WellKnownTypes {
+        int32: Some(0),
+        string: Some("foo".to_owned()),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    assert_eq!(
+        message.get_field_by_name_unwrapped("int32"),
+        Some(Value::I32(0))
+    );
+    assert_eq!(
+        message.get_field_by_name_unwrapped("string"),
+        Some(Value::String("foo".to_owned()))
+    );
+    assert_eq!(message.get_field_by_name_unwrapped("uint64"), None);
+    assert_eq!(message.get_field_by_name_unwrapped("notfound"), None);
+}
+
+#[test]
+fn set_field_unwrapped() {
+    let mut message = DynamicMessage::new(WellKnownTypes::default().descriptor());
+    message.set_field_by_name_unwrapped("bool", Some(Value::Bool(true)));
+    message.set_field_by_name_unwrapped("int64", Some(Value::I64(-3)));
+    message.set_field_by_name_unwrapped("bytes", Some(Value::Bytes(b"bar"[..].into())));
+
+    assert_eq!(
+        message.transcode_to::<WellKnownTypes>().unwrap(),
+        WellKnownTypes {
+            bool: Some(true),
+            int64: Some(-3),
+            bytes: Some(b"bar".to_vec()),
+            ..Default::default()
+        }
+    );
+
+    message.set_field_by_name_unwrapped("bool", None);
+    assert!(!message.has_field_by_name("bool"));
+    assert_eq!(message.get_field_by_name_unwrapped("bool"), None);
+}
+
+#[test]
+#[should_panic(
+    expected = "field 'test.WellKnownTypes.timestamp' is not a singular field of a wrapper type"
+)]
+fn get_field_unwrapped_not_wrapper() {
+    let message = DynamicMessage::new(WellKnownTypes::default().descriptor());
+    message.get_field_by_name_unwrapped("timestamp");
+}
+
+#[test]
+#[should_panic]
+fn set_field_unwrapped_invalid_type() {
+    let mut message = DynamicMessage::new(WellKnownTypes::default().descriptor());
+    message.set_field_by_name_unwrapped("int32", Some(Value::String("foo".to_owned())));
+}
@@ -0,0 +1,253 @@
+use std::{collections::HashMap, fmt::Debug, iter::FromIterator};
+
+use proptest::{prelude::*, test_runner::TestCaseError};
+use prost::Message;
+use prost_reflect::{DynamicMessage, ReflectMessage, XmlOptions};
+
+use crate::proto::{ComplexType, Point, ScalarArrays, Scalars, WellKnownTypes};
+
+fn to_xml<T>(message: &T, options: &XmlOptions) -> String
+where
+    T: ReflectMessage,
+{
+    message
+        .transcode_to_dynamic()
+        .to_xml_string_with_options(options)
+        .unwrap()
+}
+
+fn from_xml<T>(xml: &str, options: &XmlOptions) -> T
+where
+    T: ReflectMessage + Message + Default,
+{
+    DynamicMessage::from_xml_str_with_options(T::default().descriptor(), xml, options)
+        .unwrap()
+        .transcode_to()
+        .unwrap()
+}
+
+fn roundtrip<T>(message: &T, options: &XmlOptions) -> Result<(), TestCaseError>
+where
+    T: PartialEq + Debug + ReflectMessage + Message + Default,
+{
+    let xml = to_xml(message, options);
+    let roundtripped: T = from_xml(&xml, options);
+    prop_assert_eq!(message, &roundtripped);
+    Ok(())
+}
+
+fn complex_type() -> ComplexType {
+    ComplexType {
+        string_map: HashMap::from_iter([(
+            "a".to_owned(),
+            Scalars {
+                int32: 1,
+                ..Default::default()
+            },
+        )]),
+        int_map: HashMap::from_iter([(2, Scalars::default()), (-1, Scalars::default())]),
+        nested: Some(Scalars {
+            string: "<&>".to_owned(),
+            ..Default::default()
+        }),
+        my_enum: vec![1, 5, -4],
+        optional_enum: 3,
+    }
+}
+
+#[test]
+fn serialize_elements() {
+    assert_eq!(
+        to_xml(&complex_type(), &XmlOptions::new()),
+        "<ComplexType>\
+            <string_map><key>a</key><value><int32>1</int32></value></string_map>\
+            <int_map><key>-1</key><value/></int_map>\
+            <int_map><key>2</key><value/></int_map>\
+            <nested><string>&lt;&amp;&gt;</string></nested>\
+            <my_enum>FOO</my_enum><my_enum>5</my_enum><my_enum>NEG</my_enum>\
+            <optional_enum>BAR</optional_enum>\
+        </ComplexType>"
+    );
+}
+
+#[test]
+fn serialize_attributes() {
+    let options = XmlOptions::new().scalars_as_attributes(true);
+    assert_eq!(
+        to_xml(&complex_type(), &options),
+        r#"<ComplexType optional_enum="BAR">"#.to_owned()
+            + r#"<string_map key="a"><value int32="1"/></string_map>"#
+            + r#"<int_map key="-1"><value/></int_map>"#
+            + r#"<int_map key="2"><value/></int_map>"#
+            + r#"<nested string="&lt;&amp;&gt;"/>"#
+            + "<my_enum>FOO</my_enum><my_enum>5</my_enum><my_enum>NEG</my_enum>"
+            + "</ComplexType>"
+    );
+}
+
+#[test]
+fn serialize_wrapped() {
+    let message = ComplexType {
+        my_enum: vec![0, 1],
+        ..Default::default()
+    };
+    let options = XmlOptions::new().wrap_repeated(true);
+    assert_eq!(
+        to_xml(&message, &options),
+        "<ComplexType><my_enum><item>DEFAULT</item><item>FOO</item></my_enum></ComplexType>"
+    );
+}
+
+#[test]
+fn serialize_namespaces() {
+    let message = WellKnownTypes {
+        timestamp: Some(prost_types::Timestamp {
+            seconds: 1,
+            nanos: 0,
+        }),
+        int32: Some(2),
+        ..Default::default()
+    };
+    let options = XmlOptions::new().namespace("test", "urn:test");
+    assert_eq!(
+        to_xml(&message, &options),
+        "<WellKnownTypes xmlns=\"urn:test\">\
+            <timestamp xmlns=\"\"><seconds>1</seconds></timestamp>\
+            <int32 xmlns=\"\"><value>2</value></int32>\
+        </WellKnownTypes>"
+    );
+
+    let options = options.namespace("google.protobuf", "urn:google");
+    assert_eq!(
+        to_xml(&message, &options),
+        "<WellKnownTypes xmlns=\"urn:test\">\
+            <timestamp xmlns=\"urn:google\"><seconds>1</seconds></timestamp>\
+            <int32 xmlns=\"urn:google\"><value>2</value></int32>\
+        </WellKnownTypes>"
+    );
+    roundtrip(&message, &options).unwrap();
+}
+
+#[test]
+fn serialize_special_floats() {
+    let message = Scalars {
+        double: f64::INFINITY,
+        float: f32::NEG_INFINITY,
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    };
+    assert_eq!(
+        to_xml(&message, &XmlOptions::new()),
+        "<Scalars><double>INF</double><float>-INF</float><bytes>AP8=</bytes></Scalars>"
+    );
+}
+
+#[test]
+fn deserialize_lenient() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tns:Scalars xmlns:tns="urn:test" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:type="tns:Scalars" int32="5">
+    <tns:double>Infinity</tns:double>
+    <tns:bool>1</tns:bool>
+    <tns:string><![CDATA[ <hello> ]]></tns:string>
+</tns:Scalars>
+"#;
+    let message: Scalars = from_xml(xml, &XmlOptions::new());
+    assert_eq!(
+        message,
+        Scalars {
+            double: f64::INFINITY,
+            int32: 5,
+            r#bool: true,
+            string: " <hello> ".to_owned(),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn deserialize_unknown_fields() {
+    let xml = "<Point><latitude>1</latitude><altitude><meters>5</meters></altitude></Point>";
+    let err = DynamicMessage::from_xml_str(Point::default().descriptor(), xml).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "message 'test.Point' has no field named 'altitude'"
+    );
+
+    let message: Point = from_xml(xml, &XmlOptions::new().deny_unknown_fields(false));
+    assert_eq!(
+        message,
+        Point {
+            latitude: 1,
+            longitude: 0
+        }
+    );
+}
+
+#[test]
+fn deserialize_errors() {
+    let desc = ComplexType::default().descriptor();
+    let error = |xml: &str| {
+        DynamicMessage::from_xml_str(desc.clone(), xml)
+            .unwrap_err()
+            .to_string()
+    };
+
+    assert_eq!(error(""), "expected a root element");
+    assert_eq!(
+        error(r#"<ComplexType nested="1"/>"#),
+        "field 'test.ComplexType.nested' cannot be written as an attribute"
+    );
+    assert_eq!(
+        error("<ComplexType><optional_enum>BAZ</optional_enum></ComplexType>"),
+        "invalid value 'BAZ' for field 'test.ComplexType.optional_enum'"
+    );
+    assert_eq!(
+        error("<ComplexType>text</ComplexType>"),
+        "unexpected text in element 'ComplexType'"
+    );
+    assert_eq!(
+        error("<ComplexType><optional_enum><a/></optional_enum></ComplexType>"),
+        "unexpected child element in 'optional_enum'"
+    );
+    assert_eq!(
+        error("<ComplexType/><ComplexType/>"),
+        "unexpected element 'ComplexType' after the root element"
+    );
+    assert_eq!(
+        error("text<ComplexType/>"),
+        "unexpected text outside of the root element"
+    );
+}
+
+#[test]
+fn roundtrip_complex_type() {
+    for options in [
+        XmlOptions::new(),
+        XmlOptions::new().scalars_as_attributes(true),
+        XmlOptions::new().wrap_repeated(true),
+        XmlOptions::new()
+            .scalars_as_attributes(true)
+            .wrap_repeated(true)
+            .namespace("test", "urn:test"),
+    ] {
+        roundtrip(&complex_type(), &options).unwrap();
+        roundtrip(&ComplexType::default(), &options).unwrap();
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 32,
+        .. ProptestConfig::default()
+    })]
+
+    #[test]
+    fn roundtrip_arb_scalars(message: Scalars, attributes: bool) {
+        roundtrip(&message, &XmlOptions::new().scalars_as_attributes(attributes))?;
+    }
+
+    #[test]
+    fn roundtrip_arb_scalar_arrays(message: ScalarArrays, wrap: bool) {
+        roundtrip(&message, &XmlOptions::new().wrap_repeated(wrap))?;
+    }
+}
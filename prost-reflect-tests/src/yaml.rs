@@ -0,0 +1,111 @@
+use std::{collections::HashMap, iter::FromIterator};
+
+use prost_reflect::{DynamicMessage, ReflectMessage};
+
+use crate::proto::{ComplexType, Scalars};
+
+#[test]
+fn roundtrip_scalars() {
+    let message = Scalars {
+        double: 1.1,
+        int64: -4,
+        uint64: 6,
+        r#bool: true,
+        string: "0755".to_owned(),
+        bytes: b"\x00\xff".to_vec(),
+        ..Default::default()
+    };
+
+    let yaml = message.transcode_to_dynamic().to_yaml_string().unwrap();
+    assert_eq!(
+        yaml,
+        "double: 1.1\nint64: '-4'\nuint64: '6'\nbool: true\nstring: '0755'\nbytes: AP8=\n"
+    );
+
+    let roundtripped: Scalars = DynamicMessage::from_yaml_str(message.descriptor(), &yaml)
+        .unwrap()
+        .transcode_to()
+        .unwrap();
+    assert_eq!(roundtripped, message);
+}
+
+#[test]
+fn quote_ambiguous_strings() {
+    for string in ["010", "-017", "0o17", "0x1f", "1e3", "true", "null", "~"] {
+        let message = Scalars {
+            string: string.to_owned(),
+            ..Default::default()
+        };
+        let yaml = message.transcode_to_dynamic().to_yaml_string().unwrap();
+        assert_eq!(yaml, format!("string: '{}'\n", string));
+
+        let roundtripped: Scalars = DynamicMessage::from_yaml_str(message.descriptor(), &yaml)
+            .unwrap()
+            .transcode_to()
+            .unwrap();
+        assert_eq!(roundtripped, message);
+    }
+}
+
+#[test]
+fn resolve_anchors() {
+    let yaml = "
+nested: &scalars
+  int32: 5
+  string: hello
+stringMap:
+  a: *scalars
+  b: *scalars
+intMap:
+  1: *scalars
+  -2: {}
+";
+    let message: ComplexType =
+        DynamicMessage::from_yaml_str(ComplexType::default().descriptor(), yaml)
+            .unwrap()
+            .transcode_to()
+            .unwrap();
+
+    let scalars = Scalars {
+        int32: 5,
+        string: "hello".to_owned(),
+        ..Default::default()
+    };
+    assert_eq!(
+        message,
+        ComplexType {
+            nested: Some(scalars.clone()),
+            string_map: HashMap::from_iter([
+                ("a".to_owned(), scalars.clone()),
+                ("b".to_owned(), scalars.clone()),
+            ]),
+            int_map: HashMap::from_iter([(1, scalars), (-2, Scalars::default())]),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn reject_merge_keys() {
+    let yaml = "
+nested: &scalars
+  int32: 5
+stringMap:
+  a:
+    <<: *scalars
+    string: hello
+";
+    let err = DynamicMessage::from_yaml_str(ComplexType::default().descriptor(), yaml).unwrap_err();
+    assert_eq!(err.to_string(), "merge keys are not supported");
+}
+
+#[test]
+fn invalid_yaml() {
+    let err =
+        DynamicMessage::from_yaml_str(Scalars::default().descriptor(), "int32: [1").unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{}", err);
+
+    assert!(
+        DynamicMessage::from_yaml_str(Scalars::default().descriptor(), "int32: hello").is_err()
+    );
+}
@@ -12,12 +12,17 @@ use prost::{
     encoding::{self, WireType},
     DecodeError, EncodeError, Message,
 };
+#[cfg(feature = "services")]
+use prost_types::{
+    method_options::IdempotencyLevel, MethodDescriptorProto, ServiceDescriptorProto,
+};
 use prost_types::{
     DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
-    FileDescriptorProto, FileDescriptorSet, MethodDescriptorProto, OneofDescriptorProto,
-    ServiceDescriptorProto,
+    FileDescriptorProto, FileDescriptorSet, OneofDescriptorProto,
 };
 
+#[cfg(feature = "serde")]
+use crate::FieldNameStrategy;
 use crate::{
     descriptor::{
         error::DescriptorErrorKind,
@@ -25,13 +30,17 @@ use crate::{
         types::{self, Options},
         Definition, DefinitionKind, DescriptorIndex, EnumDescriptorInner, EnumValueDescriptorInner,
         ExtensionDescriptorInner, FieldDescriptorInner, FileDescriptorInner, KindIndex,
-        MessageDescriptorInner, MethodDescriptorInner, OneofDescriptorInner,
-        ServiceDescriptorInner, MAP_ENTRY_KEY_NUMBER, MAP_ENTRY_VALUE_NUMBER,
+        MessageDescriptorInner, OneofDescriptorInner, MAP_ENTRY_KEY_NUMBER, MAP_ENTRY_VALUE_NUMBER,
     },
     reflect::WELL_KNOWN_TYPES,
-    Cardinality, DescriptorError, DescriptorPool, DynamicMessage, EnumDescriptor,
-    EnumValueDescriptor, ExtensionDescriptor, FieldDescriptor, FileDescriptor, Kind,
-    MessageDescriptor, MethodDescriptor, OneofDescriptor, ServiceDescriptor, Syntax, Value,
+    Cardinality, DecodeOptions, DescriptorError, DescriptorPool, DynamicMessage, EncodeInterceptor,
+    EnumDescriptor, EnumValueDescriptor, ExtensionDescriptor, FieldDescriptor, FileDescriptor,
+    Kind, MessageDescriptor, OneofDescriptor, Syntax, Value,
+};
+#[cfg(feature = "services")]
+use crate::{
+    descriptor::{MethodDescriptorInner, ServiceDescriptorInner},
+    MethodDescriptor, ServiceDescriptor,
 };
 
 impl fmt::Debug for Syntax {
@@ -156,16 +165,19 @@ impl DescriptorPool {
 
     /// Decodes a [`FileDescriptorSet`] from its protobuf byte representation and
     /// creates a new [`DescriptorPool`] wrapping it.
+    ///
+    /// If the `rayon` feature is enabled, the files in the set are decoded in parallel. Resolving
+    /// the names in the files and validating them is always done sequentially.
     pub fn decode<B>(bytes: B) -> Result<Self, DescriptorError>
     where
         B: Buf,
     {
-        let file_descriptor_set = types::FileDescriptorSet::decode(bytes).map_err(|err| {
+        let files = types::FileDescriptorSet::decode_files(bytes).map_err(|err| {
             DescriptorError::new(vec![DescriptorErrorKind::DecodeFileDescriptorSet { err }])
         })?;
 
         let mut pool = DescriptorPool::new();
-        pool.build_files(file_descriptor_set.file.into_iter())?;
+        pool.build_files(files)?;
         Ok(pool)
     }
 
@@ -198,11 +210,26 @@ impl DescriptorPool {
     where
         I: IntoIterator<Item = FileDescriptorProto>,
     {
-        self.build_files(
-            files
-                .into_iter()
-                .map(types::FileDescriptorProto::from_prost),
-        )
+        #[cfg(feature = "rayon")]
+        {
+            use rayon1::prelude::*;
+
+            let files: Vec<_> = files.into_iter().collect();
+            self.build_files(
+                files
+                    .into_par_iter()
+                    .map(types::FileDescriptorProto::from_prost)
+                    .collect::<Vec<_>>(),
+            )
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            self.build_files(
+                files
+                    .into_iter()
+                    .map(types::FileDescriptorProto::from_prost),
+            )
+        }
     }
 
     /// Add a single file descriptor to the pool.
@@ -245,11 +272,130 @@ impl DescriptorPool {
     where
         B: Buf,
     {
-        let file = types::FileDescriptorSet::decode(bytes).map_err(|err| {
+        let files = types::FileDescriptorSet::decode_files(bytes).map_err(|err| {
             DescriptorError::new(vec![DescriptorErrorKind::DecodeFileDescriptorSet { err }])
         })?;
 
-        self.build_files(file.file)
+        self.build_files(files)
+    }
+
+    /// Sets the default options for decoding messages whose types are defined in this pool.
+    ///
+    /// Once set, [`DynamicMessage::decode`] and the [`Message::merge`] and
+    /// [`Message::merge_length_delimited`] implementations of [`DynamicMessage`] behave like
+    /// [`DynamicMessage::merge_with_options`] with these options. This is useful for enforcing
+    /// resource limits, such as [`DecodeOptions::max_message_bytes`], everywhere that untrusted
+    /// input is decoded, without having to pass the options to each call.
+    ///
+    /// Like other modifications, this does not affect existing clones of the pool or descriptors
+    /// obtained from it, so it should be called before getting any descriptors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DecodeOptions, DescriptorPool, DynamicMessage};
+    /// let mut pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// pool.set_decode_options(DecodeOptions::new().max_message_bytes(2));
+    ///
+    /// let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let err = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01".as_ref()).unwrap_err();
+    /// assert!(err.to_string().contains("message of 3 bytes exceeds the limit of 2 bytes"));
+    /// ```
+    pub fn set_decode_options(&mut self, options: DecodeOptions) {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.decode_options = Some(options);
+        inner.lazy_id = None;
+    }
+
+    /// Gets the default options for decoding messages set by
+    /// [`set_decode_options`][DescriptorPool::set_decode_options], if any.
+    pub fn decode_options(&self) -> Option<&DecodeOptions> {
+        self.inner.decode_options.as_ref()
+    }
+
+    /// Registers an interceptor to run before encoding messages whose types are defined in this
+    /// pool, with [`DynamicMessage::encode_to_vec_intercepted`] or
+    /// [`DynamicMessage::encode_to_vec_with_interceptor`].
+    ///
+    /// Interceptors run in the order they were registered, for the message being encoded and for
+    /// each message nested in it. They are only run by the methods above: encoding with
+    /// [`Message::encode`][prost::Message::encode], [`Message::encode_to_vec`][prost::Message::encode_to_vec]
+    /// or any other method skips them.
+    ///
+    /// Like other modifications, this does not affect existing clones of the pool or descriptors
+    /// obtained from it, so it should be called before getting any descriptors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+    /// let mut pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// pool.add_encode_interceptor(|message: &mut DynamicMessage| {
+    ///     message.try_set_field_by_name("foo", Value::I32(150))
+    /// });
+    ///
+    /// let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// assert_eq!(dynamic_message.encode_to_vec_intercepted().unwrap(), b"\x08\x96\x01");
+    /// ```
+    pub fn add_encode_interceptor<I>(&mut self, interceptor: I)
+    where
+        I: EncodeInterceptor + Send + Sync + 'static,
+    {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.encode_interceptors.push(Arc::new(interceptor));
+        inner.lazy_id = None;
+    }
+
+    pub(crate) fn encode_interceptors(&self) -> &[Arc<dyn EncodeInterceptor + Send + Sync>] {
+        &self.inner.encode_interceptors
+    }
+
+    /// Sets the strategy used to choose the JSON names of the fields of messages whose types are
+    /// defined in this pool.
+    ///
+    /// Once set, fields are serialized using the name chosen by the strategy, unless
+    /// [`SerializeOptions::use_proto_field_name`][crate::SerializeOptions::use_proto_field_name]
+    /// is set, and deserialization accepts that name as well as the JSON name and proto name of
+    /// each field. The names are computed once for each field, when the strategy is set or files
+    /// are added to the pool. Extension fields are always serialized using their full name in
+    /// brackets.
+    ///
+    /// Like other modifications, this does not affect existing clones of the pool or descriptors
+    /// obtained from it, so it should be called before getting any descriptors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DescriptorPool, DynamicMessage, FieldNameStrategy, Value};
+    /// # use serde1 as serde;
+    /// let mut pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// pool.set_field_name_strategy(FieldNameStrategy::Custom(std::sync::Arc::new(|f| f.name().to_uppercase())));
+    ///
+    /// let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x96\x01".as_ref()).unwrap();
+    /// assert_eq!(serde_json::to_string(&dynamic_message).unwrap(), r#"{"FOO":150}"#);
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"{ "FOO": 150 }"#);
+    /// let dynamic_message = DynamicMessage::deserialize(message_descriptor, &mut deserializer).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn set_field_name_strategy(&mut self, strategy: FieldNameStrategy) {
+        let inner = Arc::make_mut(&mut self.inner);
+        inner.field_name_strategy = Some(strategy);
+        inner.lazy_id = None;
+        self.build_field_strategy_names(0);
+        Arc::make_mut(&mut self.inner).interner.finish();
+    }
+
+    /// Gets the strategy used to choose the JSON names of fields set by
+    /// [`set_field_name_strategy`][DescriptorPool::set_field_name_strategy], if any.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn field_name_strategy(&self) -> Option<&FieldNameStrategy> {
+        self.inner.field_name_strategy.as_ref()
     }
 
     /// Gets an iterator over the file descriptors added to this pool.
@@ -362,6 +508,8 @@ impl DescriptorPool {
     }
 
     /// Gets an iterator over the services defined in these protobuf files.
+    #[cfg(feature = "services")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "services")))]
     pub fn services(&self) -> impl ExactSizeIterator<Item = ServiceDescriptor> + '_ {
         indices(&self.inner.services).map(|index| ServiceDescriptor {
             pool: self.clone(),
@@ -442,6 +590,8 @@ impl DescriptorPool {
     }
 
     /// Gets an [`ServiceDescriptor`] by its fully qualified name, for example `my.package.MyService`.
+    #[cfg(feature = "services")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "services")))]
     pub fn get_service_by_name(&self, name: &str) -> Option<ServiceDescriptor> {
         match self.inner.get_by_name(name) {
             Some(&Definition {
@@ -458,10 +608,11 @@ impl DescriptorPool {
 
 impl fmt::Debug for DescriptorPool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("DescriptorPool")
-            .field("files", &debug_fmt_iter(self.files()))
-            .field("services", &debug_fmt_iter(self.services()))
-            .field("all_messages", &debug_fmt_iter(self.all_messages()))
+        let mut s = f.debug_struct("DescriptorPool");
+        s.field("files", &debug_fmt_iter(self.files()));
+        #[cfg(feature = "services")]
+        s.field("services", &debug_fmt_iter(self.services()));
+        s.field("all_messages", &debug_fmt_iter(self.all_messages()))
             .field("all_enums", &debug_fmt_iter(self.all_enums()))
             .field("all_extensions", &debug_fmt_iter(self.all_extensions()))
             .finish()
@@ -470,7 +621,14 @@ impl fmt::Debug for DescriptorPool {
 
 impl PartialEq for DescriptorPool {
     fn eq(&self, other: &Self) -> bool {
-        Arc::ptr_eq(&self.inner, &other.inner)
+        if Arc::ptr_eq(&self.inner, &other.inner) {
+            return true;
+        }
+
+        match (&self.inner.lazy_id, &other.inner.lazy_id) {
+            (Some(lazy_id), Some(other_lazy_id)) => Arc::ptr_eq(lazy_id, other_lazy_id),
+            _ => false,
+        }
     }
 }
 
@@ -578,6 +736,8 @@ impl FileDescriptor {
     }
 
     /// Gets the services defined within this file.
+    #[cfg(feature = "services")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "services")))]
     pub fn services(&self) -> impl ExactSizeIterator<Item = ServiceDescriptor> + '_ {
         let pool = self.parent_pool();
         let raw_file = self.file_descriptor_proto();
@@ -787,7 +947,7 @@ impl MessageDescriptor {
         self.inner()
             .field_names
             .get(name)
-            .map(|&index| FieldDescriptor {
+            .map(|index| FieldDescriptor {
                 message: self.clone(),
                 index,
             })
@@ -798,12 +958,30 @@ impl MessageDescriptor {
         self.inner()
             .field_json_names
             .get(json_name)
-            .map(|&index| FieldDescriptor {
+            .filter(|field| field.is_json_name)
+            .map(|field| FieldDescriptor {
                 message: self.clone(),
-                index,
+                index: field.index,
             })
     }
 
+    /// Gets the [`FieldDescriptor`] with the given JSON name or, failing that, the given name or
+    /// the name chosen by the pool's [`FieldNameStrategy`].
+    ///
+    /// This is the order in which keys are matched when parsing JSON.
+    #[cfg(feature = "serde")]
+    pub(crate) fn get_field_by_json_key(&self, key: &str) -> Option<FieldDescriptor> {
+        let inner = self.inner();
+        let index = match inner.field_json_names.get(key) {
+            Some(field) => field.index,
+            None => inner.field_strategy_names.as_ref()?.index.get(key)?,
+        };
+        Some(FieldDescriptor {
+            message: self.clone(),
+            index,
+        })
+    }
+
     /// Returns `true` if this is an auto-generated message type to
     /// represent the entry type for a map field.
     //
@@ -967,6 +1145,16 @@ impl FieldDescriptor {
         &self.inner().json_name
     }
 
+    /// Gets the name used for JSON serialization, as chosen by the pool's [`FieldNameStrategy`],
+    /// or the [`json_name`][FieldDescriptor::json_name] if it does not have one.
+    #[cfg(feature = "serde")]
+    pub(crate) fn json_key(&self) -> &str {
+        match &self.message.inner().field_strategy_names {
+            Some(names) => &names.names[self.index as usize],
+            None => self.json_name(),
+        }
+    }
+
     /// Whether this field is encoded using the proto2 group encoding.
     pub fn is_group(&self) -> bool {
         matches!(self.inner().kind, KindIndex::Group(_))
@@ -994,6 +1182,11 @@ impl FieldDescriptor {
     }
 
     /// Whether this field is a list encoded using [packed encoding](https://developers.google.com/protocol-buffers/docs/encoding#packed).
+    ///
+    /// This only affects how the field is encoded. When decoding, both packed and unpacked
+    /// encodings are accepted for any repeated scalar field, regardless of this value. Use
+    /// [`DynamicMessage::decode_with_report`][crate::DynamicMessage::decode_with_report] to
+    /// find out which encoding was used.
     pub fn is_packed(&self) -> bool {
         self.inner().is_packed
     }
@@ -1346,6 +1539,17 @@ impl EnumDescriptor {
         )
     }
 
+    /// Returns `true` if this is a closed enum, which only accepts the values it defines.
+    ///
+    /// Enums defined in proto2 files are closed, and enums defined in proto3 files are open. Fields
+    /// of an open enum type may hold any number, but for a closed enum, an undefined number is
+    /// treated as an unknown field when decoding with
+    /// [`DecodeOptions::closed_enums`][crate::DecodeOptions::closed_enums], and rejected when
+    /// deserializing from JSON.
+    pub fn is_closed(&self) -> bool {
+        self.parent_file().syntax() == Syntax::Proto2
+    }
+
     /// Gets the default value for the enum type.
     pub fn default_value(&self) -> EnumValueDescriptor {
         EnumValueDescriptor {
@@ -1359,7 +1563,7 @@ impl EnumDescriptor {
         self.inner()
             .value_names
             .get(name)
-            .map(|&index| EnumValueDescriptor {
+            .map(|index| EnumValueDescriptor {
                 parent: self.clone(),
                 index,
             })
@@ -1573,6 +1777,7 @@ impl fmt::Debug for OneofDescriptor {
     }
 }
 
+#[cfg(feature = "services")]
 impl ServiceDescriptor {
     /// Create a new [`ServiceDescriptor`] referencing the service at `index` within the given [`DescriptorPool`].
     ///
@@ -1650,6 +1855,30 @@ impl ServiceDescriptor {
         )
     }
 
+    /// Gets the hostname of the service, as set by the `google.api.default_host` option, e.g.
+    /// `library.googleapis.com`.
+    ///
+    /// Returns `None` if the option is not set.
+    pub fn default_host(&self) -> Option<String> {
+        self.google_api_options().default_host
+    }
+
+    /// Gets the OAuth scopes needed by clients of the service, as set by the
+    /// `google.api.oauth_scopes` option.
+    ///
+    /// The option is a comma-separated list of scopes, which is split into its items. Returns an
+    /// empty list if the option is not set.
+    pub fn oauth_scopes(&self) -> Vec<String> {
+        self.google_api_options()
+            .oauth_scopes
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|scope| !scope.is_empty())
+            .map(str::to_owned)
+            .collect()
+    }
+
     /// Gets an iterator yielding a [`MethodDescriptor`] for each method defined in this service.
     pub fn methods(&self) -> impl ExactSizeIterator<Item = MethodDescriptor> + '_ {
         indices(&self.inner().methods).map(|index| MethodDescriptor {
@@ -1662,6 +1891,10 @@ impl ServiceDescriptor {
         &self.pool.inner.services[self.index as usize]
     }
 
+    fn google_api_options(&self) -> GoogleApiServiceOptions {
+        decode_extension_options(&self.raw().options)
+    }
+
     fn raw(&self) -> &types::ServiceDescriptorProto {
         let path = self.path();
         debug_assert!(!path.is_empty());
@@ -1673,6 +1906,7 @@ impl ServiceDescriptor {
     }
 }
 
+#[cfg(feature = "services")]
 impl fmt::Debug for ServiceDescriptor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ServiceDescriptor")
@@ -1684,6 +1918,7 @@ impl fmt::Debug for ServiceDescriptor {
     }
 }
 
+#[cfg(feature = "services")]
 impl MethodDescriptor {
     /// Create a new [`MethodDescriptor`] referencing the method at `index` within the [`ServiceDescriptor`].
     ///
@@ -1775,6 +2010,42 @@ impl MethodDescriptor {
         self.raw().server_streaming()
     }
 
+    /// Gets whether calling this method has side effects, as set by the `idempotency_level`
+    /// option.
+    ///
+    /// Returns [`IdempotencyUnknown`][IdempotencyLevel::IdempotencyUnknown] if the option is not
+    /// set.
+    pub fn idempotency_level(&self) -> IdempotencyLevel {
+        self.raw()
+            .options
+            .as_ref()
+            .map(|o| o.value.idempotency_level())
+            .unwrap_or(IdempotencyLevel::IdempotencyUnknown)
+    }
+
+    /// Gets the method signatures set by the `google.api.method_signature` option, which list the
+    /// request fields that client libraries may accept as arguments in place of the request
+    /// message.
+    ///
+    /// Each signature is a list of field names, which may be dot-separated paths to the fields of
+    /// nested messages. An empty signature indicates that the method may be called without any
+    /// arguments. Returns an empty list if the option is not set.
+    pub fn method_signatures(&self) -> Vec<Vec<String>> {
+        let options: GoogleApiMethodOptions = decode_extension_options(&self.raw().options);
+        options
+            .method_signature
+            .iter()
+            .map(|signature| {
+                signature
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|field| !field.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+            .collect()
+    }
+
     fn inner(&self) -> &MethodDescriptorInner {
         &self.service.inner().methods[self.index as usize]
     }
@@ -1784,6 +2055,7 @@ impl MethodDescriptor {
     }
 }
 
+#[cfg(feature = "services")]
 impl fmt::Debug for MethodDescriptor {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MethodDescriptor")
@@ -1817,7 +2089,7 @@ where
     Wrapper(i.collect())
 }
 
-fn indices<T>(f: &Vec<T>) -> Range<DescriptorIndex> {
+fn indices<T>(f: &[T]) -> Range<DescriptorIndex> {
     0..to_index(f.len())
 }
 
@@ -1845,6 +2117,36 @@ fn decode_options<T>(
     DynamicMessage::decode(message_desc, bytes).unwrap()
 }
 
+/// Decodes the extension options defined in `google/api/client.proto` from the encoded options of
+/// a service or method. Options which cannot be decoded are treated as unset.
+#[cfg(feature = "services")]
+fn decode_extension_options<T, O>(option: &Option<Options<O>>) -> T
+where
+    T: Message + Default,
+{
+    let bytes = option
+        .as_ref()
+        .map(|o| o.encoded.as_slice())
+        .unwrap_or_default();
+    T::decode(bytes).unwrap_or_default()
+}
+
+#[cfg(feature = "services")]
+#[derive(Clone, PartialEq, Message)]
+struct GoogleApiServiceOptions {
+    #[prost(string, optional, tag = "1049")]
+    default_host: Option<String>,
+    #[prost(string, optional, tag = "1050")]
+    oauth_scopes: Option<String>,
+}
+
+#[cfg(feature = "services")]
+#[derive(Clone, PartialEq, Message)]
+struct GoogleApiMethodOptions {
+    #[prost(string, repeated, tag = "1051")]
+    method_signature: Vec<String>,
+}
+
 fn find_message_proto_prost<'a>(
     file: &'a FileDescriptorProto,
     path: &[i32],
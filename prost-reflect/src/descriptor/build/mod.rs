@@ -5,19 +5,27 @@ mod visit;
 
 use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
+#[cfg(feature = "services")]
+use crate::descriptor::ServiceIndex;
 use crate::{
     descriptor::{
         to_index, types::FileDescriptorProto, Definition, DefinitionKind, DescriptorPoolInner,
-        EnumIndex, ExtensionIndex, FileIndex, MessageIndex, ServiceIndex,
+        EnumIndex, ExtensionIndex, FileIndex, MessageIndex,
     },
     DescriptorError, DescriptorPool,
 };
+#[cfg(feature = "serde")]
+use crate::{
+    descriptor::{NameIndex, StrategyNames},
+    FieldDescriptor, FieldNameStrategy, MessageDescriptor,
+};
 
 #[derive(Clone, Copy)]
 struct DescriptorPoolOffsets {
     file: FileIndex,
     message: MessageIndex,
     enum_: EnumIndex,
+    #[cfg(feature = "services")]
     service: ServiceIndex,
     extension: ExtensionIndex,
 }
@@ -28,6 +36,7 @@ impl DescriptorPoolOffsets {
             file: to_index(pool.files.len()),
             message: to_index(pool.messages.len()),
             enum_: to_index(pool.enums.len()),
+            #[cfg(feature = "services")]
             service: to_index(pool.services.len()),
             extension: to_index(pool.extensions.len()),
         }
@@ -38,6 +47,7 @@ impl DescriptorPoolOffsets {
         pool.messages.truncate(self.message as usize);
         pool.enums.truncate(self.enum_ as usize);
         pool.extensions.truncate(self.extension as usize);
+        #[cfg(feature = "services")]
         pool.services.truncate(self.service as usize);
         pool.names.retain(|name, definition| match definition.kind {
             DefinitionKind::Package => pool.files.iter().any(|f| {
@@ -50,6 +60,7 @@ impl DescriptorPoolOffsets {
             DefinitionKind::Message(message)
             | DefinitionKind::Field(message, _)
             | DefinitionKind::Oneof(message, _) => message < self.message,
+            #[cfg(feature = "services")]
             DefinitionKind::Service(service) | DefinitionKind::Method(service, _) => {
                 service < self.service
             }
@@ -60,6 +71,7 @@ impl DescriptorPoolOffsets {
         for message in &mut pool.messages {
             message.extensions.retain(|&message| message < self.message);
         }
+        pool.interner.finish();
     }
 }
 
@@ -88,6 +100,7 @@ impl DescriptorPool {
         I: IntoIterator<Item = FileDescriptorProto>,
     {
         let inner = Arc::make_mut(&mut self.inner);
+        inner.lazy_id = None;
         let deduped_files: Vec<_> = files
             .into_iter()
             .filter(|f| match inner.file_names.get(f.name()) {
@@ -104,12 +117,83 @@ impl DescriptorPool {
 
         debug_assert_eq!(Arc::strong_count(&self.inner), 1);
         let inner = Arc::get_mut(&mut self.inner).unwrap();
-        for file in &mut inner.files[offsets.file as usize..] {
+        let files = &mut inner.files[offsets.file as usize..];
+        #[cfg(feature = "rayon")]
+        {
+            use rayon1::prelude::*;
+
+            files
+                .par_iter_mut()
+                .for_each(|file| file.prost = file.raw.to_prost());
+        }
+        #[cfg(not(feature = "rayon"))]
+        for file in files {
             file.prost = file.raw.to_prost();
         }
+        for message in &mut inner.messages[offsets.message as usize..] {
+            message.field_names.build();
+            message.field_json_names.build();
+        }
+        for enum_ in &mut inner.enums[offsets.enum_ as usize..] {
+            enum_.value_names.build();
+        }
+        #[cfg(feature = "serde")]
+        self.build_field_strategy_names(offsets.message);
+        Arc::get_mut(&mut self.inner).unwrap().interner.finish();
 
         Ok(())
     }
+
+    /// Computes the names of the fields of the messages starting at `offset` using the pool's
+    /// [`FieldNameStrategy`], so that they can be serialized and looked up without calling it
+    /// again.
+    #[cfg(feature = "serde")]
+    pub(super) fn build_field_strategy_names(&mut self, offset: MessageIndex) {
+        let strategy = match &self.inner.field_name_strategy {
+            Some(FieldNameStrategy::JsonName) | None => None,
+            Some(strategy) => Some(strategy.clone()),
+        };
+
+        let names: Vec<Option<Vec<String>>> = (offset..to_index(self.inner.messages.len()))
+            .map(|index| {
+                let strategy = strategy.as_ref()?;
+                let message = MessageDescriptor {
+                    pool: self.clone(),
+                    index,
+                };
+                let fields = to_index(self.inner.messages[index as usize].fields.len());
+                Some(
+                    (0..fields)
+                        .map(|index| {
+                            let field = FieldDescriptor {
+                                message: message.clone(),
+                                index,
+                            };
+                            strategy.field_name(&field).into_owned()
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
+
+        let inner = Arc::make_mut(&mut self.inner);
+        for (message, names) in inner.messages[offset as usize..].iter_mut().zip(names) {
+            message.field_strategy_names = names.map(|names| {
+                let mut index = NameIndex::with_capacity(names.len());
+                let names: Box<[Arc<str>]> = names
+                    .iter()
+                    .map(|name| inner.interner.intern(name))
+                    .collect();
+                for (field, name) in names.iter().enumerate() {
+                    if index.get(name).is_none() {
+                        index.insert(name.clone(), to_index(field));
+                    }
+                }
+                index.build();
+                Box::new(StrategyNames { names, index })
+            });
+        }
+    }
 }
 
 fn to_json_name(name: &str) -> String {
@@ -131,7 +215,7 @@ fn to_json_name(name: &str) -> String {
 }
 
 fn resolve_name<'a, 'b>(
-    names: &'a HashMap<Box<str>, Definition>,
+    names: &'a HashMap<Arc<str>, Definition>,
     scope: &str,
     name: &'b str,
 ) -> Option<(Cow<'b, str>, &'a Definition)> {
@@ -143,7 +227,7 @@ fn resolve_name<'a, 'b>(
 }
 
 fn resolve_relative_name<'a>(
-    names: &'a HashMap<Box<str>, Definition>,
+    names: &'a HashMap<Arc<str>, Definition>,
     scope: &str,
     relative_name: &str,
 ) -> Option<(String, &'a Definition)> {
@@ -1,5 +1,10 @@
 use std::collections::{hash_map, BTreeMap, HashMap};
 
+#[cfg(feature = "services")]
+use crate::descriptor::{
+    types::{MethodDescriptorProto, ServiceDescriptorProto},
+    MethodIndex, ServiceIndex,
+};
 use crate::{
     descriptor::{
         build::{
@@ -12,13 +17,12 @@ use crate::{
         tag, to_index,
         types::{
             DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
-            FileDescriptorProto, MethodDescriptorProto, OneofDescriptorProto,
-            ServiceDescriptorProto,
+            FileDescriptorProto, OneofDescriptorProto,
         },
         Definition, DefinitionKind, DescriptorPoolInner, EnumDescriptorInner, EnumIndex,
         EnumValueDescriptorInner, EnumValueIndex, ExtensionIndex, FieldIndex, FileDescriptorInner,
-        FileIndex, Identity, MessageDescriptorInner, MessageIndex, MethodIndex,
-        OneofDescriptorInner, OneofIndex, ServiceIndex,
+        FileIndex, Identity, MessageDescriptorInner, MessageIndex, NameIndex, OneofDescriptorInner,
+        OneofIndex,
     },
     Syntax,
 };
@@ -71,7 +75,7 @@ impl<'a> Visitor for NameVisitor<'a> {
         if self
             .pool
             .file_names
-            .insert(file.name().into(), index)
+            .insert(self.pool.interner.intern(file.name()), index)
             .is_some()
         {
             self.errors.push(DescriptorErrorKind::DuplicateFileName {
@@ -124,11 +128,19 @@ impl<'a> Visitor for NameVisitor<'a> {
 
         debug_assert_eq!(to_index(self.pool.messages.len()), index);
         self.pool.messages.push(MessageDescriptorInner {
-            id: Identity::new(file, path, full_name, message.name()),
+            id: Identity::new(
+                &mut self.pool.interner,
+                file,
+                path,
+                full_name,
+                message.name(),
+            ),
             fields: Vec::with_capacity(message.field.len()),
             field_numbers: BTreeMap::new(),
-            field_names: HashMap::with_capacity(message.field.len()),
-            field_json_names: HashMap::with_capacity(message.field.len()),
+            field_names: NameIndex::with_capacity(message.field.len()),
+            field_json_names: NameIndex::with_capacity(message.field.len()),
+            #[cfg(feature = "serde")]
+            field_strategy_names: None,
             oneofs: Vec::with_capacity(message.oneof_decl.len()),
             extensions: Vec::new(),
             parent,
@@ -181,11 +193,12 @@ impl<'a> Visitor for NameVisitor<'a> {
         self.pool.messages[message as usize]
             .oneofs
             .push(OneofDescriptorInner {
-                id: Identity::new(file, path, full_name, oneof.name()),
+                id: Identity::new(&mut self.pool.interner, file, path, full_name, oneof.name()),
                 fields: Vec::new(),
             });
     }
 
+    #[cfg(feature = "services")]
     fn visit_service(
         &mut self,
         path: &[i32],
@@ -203,6 +216,7 @@ impl<'a> Visitor for NameVisitor<'a> {
         );
     }
 
+    #[cfg(feature = "services")]
     fn visit_method(
         &mut self,
         path: &[i32],
@@ -268,11 +282,11 @@ impl<'a> Visitor for NameVisitor<'a> {
 
         debug_assert_eq!(to_index(self.pool.enums.len()), index);
         self.pool.enums.push(EnumDescriptorInner {
-            id: Identity::new(file, path, full_name, enum_.name()),
+            id: Identity::new(&mut self.pool.interner, file, path, full_name, enum_.name()),
             parent,
             values: Vec::with_capacity(enum_.value.len()),
             value_numbers: Vec::with_capacity(enum_.value.len()),
-            value_names: HashMap::with_capacity(enum_.value.len()),
+            value_names: NameIndex::with_capacity(enum_.value.len()),
             allow_alias,
         });
     }
@@ -301,7 +315,7 @@ impl<'a> Visitor for NameVisitor<'a> {
         self.pool.enums[enum_ as usize]
             .values
             .push(EnumValueDescriptorInner {
-                id: Identity::new(file, path, full_name, value.name()),
+                id: Identity::new(&mut self.pool.interner, file, path, full_name, value.name()),
                 number: value.number(),
             });
     }
@@ -336,7 +350,7 @@ impl<'a> NameVisitor<'a> {
     ) {
         let path = join_path(path1, path2);
 
-        match self.pool.names.entry(name.into()) {
+        match self.pool.names.entry(self.pool.interner.intern(name)) {
             hash_map::Entry::Vacant(entry) => {
                 entry.insert(Definition { file, kind, path });
             }
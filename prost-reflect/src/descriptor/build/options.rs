@@ -2,6 +2,11 @@ use std::sync::Arc;
 
 use prost::{bytes::Bytes, Message};
 
+#[cfg(feature = "services")]
+use crate::descriptor::{
+    types::{MethodDescriptorProto, ServiceDescriptorProto},
+    MethodIndex, ServiceIndex,
+};
 use crate::{
     descriptor::{
         build::{
@@ -13,12 +18,11 @@ use crate::{
         tag,
         types::{
             uninterpreted_option, DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto,
-            FieldDescriptorProto, FileDescriptorProto, MethodDescriptorProto, OneofDescriptorProto,
-            Options, ServiceDescriptorProto, UninterpretedOption,
+            FieldDescriptorProto, FileDescriptorProto, OneofDescriptorProto, Options,
+            UninterpretedOption,
         },
         Definition, DefinitionKind, EnumIndex, EnumValueIndex, ExtensionIndex, FieldIndex,
-        FileIndex, MessageIndex, MethodIndex, OneofIndex, ServiceIndex, MAP_ENTRY_KEY_NUMBER,
-        MAP_ENTRY_VALUE_NUMBER,
+        FileIndex, MessageIndex, OneofIndex, MAP_ENTRY_KEY_NUMBER, MAP_ENTRY_VALUE_NUMBER,
     },
     dynamic::{fmt_string, FieldDescriptorLike},
     reflect::WELL_KNOWN_TYPES,
@@ -184,6 +188,7 @@ impl<'a> Visitor for OptionsVisitor<'a> {
         }
     }
 
+    #[cfg(feature = "services")]
     fn visit_service(
         &mut self,
         path: &[i32],
@@ -206,6 +211,7 @@ impl<'a> Visitor for OptionsVisitor<'a> {
         }
     }
 
+    #[cfg(feature = "services")]
     fn visit_method(
         &mut self,
         path: &[i32],
@@ -1,5 +1,10 @@
 use prost::bytes::Bytes;
 
+#[cfg(feature = "services")]
+use crate::descriptor::{
+    types::{MethodDescriptorProto, ServiceDescriptorProto},
+    MethodDescriptorInner, MethodIndex, ServiceDescriptorInner, ServiceIndex,
+};
 use crate::{
     descriptor::{
         build::{
@@ -11,14 +16,12 @@ use crate::{
         find_enum_proto, find_message_proto, tag, to_index,
         types::{
             field_descriptor_proto, DescriptorProto, EnumValueDescriptorProto,
-            FieldDescriptorProto, FileDescriptorProto, MethodDescriptorProto,
-            ServiceDescriptorProto,
+            FieldDescriptorProto, FileDescriptorProto,
         },
         Definition, DefinitionKind, DescriptorPoolInner, EnumIndex, EnumValueIndex,
         ExtensionDescriptorInner, ExtensionIndex, FieldDescriptorInner, FieldIndex, FileIndex,
-        Identity, KindIndex, MessageIndex, MethodDescriptorInner, MethodIndex, OneofIndex,
-        ServiceDescriptorInner, ServiceIndex, RESERVED_MESSAGE_FIELD_NUMBERS,
-        VALID_MESSAGE_FIELD_NUMBERS,
+        Identity, JsonFieldName, KindIndex, MessageIndex, OneofIndex,
+        RESERVED_MESSAGE_FIELD_NUMBERS, VALID_MESSAGE_FIELD_NUMBERS,
     },
     Cardinality, Syntax, Value,
 };
@@ -105,7 +108,8 @@ impl<'a> Visitor for ResolveVisitor<'a> {
         let kind =
             self.resolve_field_type(field.r#type(), field.type_name(), full_name, file, path);
 
-        let json_name: Box<str> = self.resolve_field_json_name(field, file, path).into();
+        let json_name = self.resolve_field_json_name(field, file, path).to_owned();
+        let json_name = self.pool.interner.intern(&json_name);
 
         let is_packed = cardinality == Cardinality::Repeated
             && kind.map_or(false, |k| k.is_packable())
@@ -136,7 +140,7 @@ impl<'a> Visitor for ResolveVisitor<'a> {
         });
 
         message.fields.push(FieldDescriptorInner {
-            id: Identity::new(file, path, full_name, field.name()),
+            id: Identity::new(&mut self.pool.interner, file, path, full_name, field.name()),
             number: field.number() as u32,
             kind: kind.unwrap_or(KindIndex::Double),
             oneof,
@@ -166,7 +170,10 @@ impl<'a> Visitor for ResolveVisitor<'a> {
                 ),
             });
         }
-        if let Some(existing) = message.field_names.insert(field.name().into(), index) {
+        if let Some(existing) = message
+            .field_names
+            .insert(self.pool.interner.intern(field.name()), index)
+        {
             self.errors.push(DescriptorErrorKind::DuplicateName {
                 name: full_name.to_owned(),
                 first: Label::new(
@@ -186,7 +193,15 @@ impl<'a> Visitor for ResolveVisitor<'a> {
                 ),
             });
         }
-        if let Some(existing) = message.field_json_names.insert(json_name, index) {
+        let json_field_name = JsonFieldName {
+            index,
+            is_json_name: true,
+        };
+        if let Some(existing) = message
+            .field_json_names
+            .insert(json_name, json_field_name)
+            .filter(|existing| existing.is_json_name)
+        {
             self.errors
                 .push(DescriptorErrorKind::DuplicateFieldJsonName {
                     name: field.json_name().to_owned(),
@@ -195,7 +210,7 @@ impl<'a> Visitor for ResolveVisitor<'a> {
                         "first defined here",
                         file,
                         join_path(
-                            &message.fields[existing as usize].id.path,
+                            &message.fields[existing.index as usize].id.path,
                             &[tag::field::NAME],
                         ),
                     ),
@@ -207,8 +222,18 @@ impl<'a> Visitor for ResolveVisitor<'a> {
                     ),
                 });
         }
+        if message.field_json_names.get(field.name()).is_none() {
+            let json_field_name = JsonFieldName {
+                index,
+                is_json_name: false,
+            };
+            message
+                .field_json_names
+                .insert(self.pool.interner.intern(field.name()), json_field_name);
+        }
     }
 
+    #[cfg(feature = "services")]
     fn visit_service(
         &mut self,
         path: &[i32],
@@ -220,11 +245,18 @@ impl<'a> Visitor for ResolveVisitor<'a> {
         debug_assert_eq!(to_index(self.pool.services.len()), index);
 
         self.pool.services.push(ServiceDescriptorInner {
-            id: Identity::new(file, path, full_name, service.name()),
+            id: Identity::new(
+                &mut self.pool.interner,
+                file,
+                path,
+                full_name,
+                service.name(),
+            ),
             methods: Vec::with_capacity(service.method.len()),
         });
     }
 
+    #[cfg(feature = "services")]
     fn visit_method(
         &mut self,
         path: &[i32],
@@ -261,7 +293,13 @@ impl<'a> Visitor for ResolveVisitor<'a> {
         self.pool.services[service as usize]
             .methods
             .push(MethodDescriptorInner {
-                id: Identity::new(file, path, full_name, method.name()),
+                id: Identity::new(
+                    &mut self.pool.interner,
+                    file,
+                    path,
+                    full_name,
+                    method.name(),
+                ),
                 input,
                 output,
             });
@@ -314,7 +352,10 @@ impl<'a> Visitor for ResolveVisitor<'a> {
             .value_numbers
             .insert(value_numbers_index, (value.number(), index));
 
-        if let Some(existing) = enum_.value_names.insert(value.name().into(), index) {
+        if let Some(existing) = enum_
+            .value_names
+            .insert(self.pool.interner.intern(value.name()), index)
+        {
             self.errors.push(DescriptorErrorKind::DuplicateName {
                 name: full_name.to_owned(),
                 first: Label::new(
@@ -390,10 +431,16 @@ impl<'a> Visitor for ResolveVisitor<'a> {
         });
 
         self.pool.extensions.push(ExtensionDescriptorInner {
-            id: Identity::new(file, path, full_name, extension.name()),
+            id: Identity::new(
+                &mut self.pool.interner,
+                file,
+                path,
+                full_name,
+                extension.name(),
+            ),
             parent: parent_message,
             number: extension.number() as u32,
-            json_name: format!("[{}]", full_name).into(),
+            json_name: self.pool.interner.intern(&format!("[{}]", full_name)),
             extendee: extendee.unwrap_or(MessageIndex::MAX),
             kind: kind.unwrap_or(KindIndex::Double),
             is_packed,
@@ -632,10 +679,7 @@ impl<'a> ResolveVisitor<'a> {
         file: FileIndex,
         path: &[i32],
     ) -> Option<Value> {
-        let default_value = match default_value {
-            Some(value) => value,
-            None => return None,
-        };
+        let default_value = default_value?;
 
         match kind {
             KindIndex::Double
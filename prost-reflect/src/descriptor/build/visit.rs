@@ -3,10 +3,14 @@ use crate::descriptor::{
     tag, to_index,
     types::{
         DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
-        FileDescriptorProto, MethodDescriptorProto, OneofDescriptorProto, ServiceDescriptorProto,
+        FileDescriptorProto, OneofDescriptorProto,
     },
-    EnumIndex, EnumValueIndex, ExtensionIndex, FieldIndex, FileIndex, MessageIndex, MethodIndex,
-    OneofIndex, ServiceIndex,
+    EnumIndex, EnumValueIndex, ExtensionIndex, FieldIndex, FileIndex, MessageIndex, OneofIndex,
+};
+#[cfg(feature = "services")]
+use crate::descriptor::{
+    types::{MethodDescriptorProto, ServiceDescriptorProto},
+    MethodIndex, ServiceIndex,
 };
 
 pub(super) trait Visitor {
@@ -45,6 +49,7 @@ pub(super) trait Visitor {
     ) {
     }
 
+    #[cfg(feature = "services")]
     fn visit_service(
         &mut self,
         _path: &[i32],
@@ -55,6 +60,7 @@ pub(super) trait Visitor {
     ) {
     }
 
+    #[cfg(feature = "services")]
     fn visit_method(
         &mut self,
         _path: &[i32],
@@ -149,13 +155,16 @@ impl Context {
         }
         self.pop_path();
 
-        self.push_path(tag::file::SERVICE);
-        for (i, service) in file.service.iter().enumerate() {
-            self.push_path(i as i32);
-            self.visit_service(service, visitor, index);
+        #[cfg(feature = "services")]
+        {
+            self.push_path(tag::file::SERVICE);
+            for (i, service) in file.service.iter().enumerate() {
+                self.push_path(i as i32);
+                self.visit_service(service, visitor, index);
+                self.pop_path();
+            }
             self.pop_path();
         }
-        self.pop_path();
 
         self.push_path(tag::file::EXTENSION);
         for (i, extension) in file.extension.iter().enumerate() {
@@ -258,6 +267,7 @@ impl Context {
         self.pop_scope(oneof.name());
     }
 
+    #[cfg(feature = "services")]
     fn visit_service(
         &mut self,
         service: &ServiceDescriptorProto,
@@ -280,6 +290,7 @@ impl Context {
         self.pop_scope(service.name());
     }
 
+    #[cfg(feature = "services")]
     fn visit_method(
         &mut self,
         method: &MethodDescriptorProto,
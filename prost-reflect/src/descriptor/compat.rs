@@ -0,0 +1,478 @@
+//! Detects changes between two versions of a schema which break compatibility with existing
+//! clients or stored data.
+//!
+//! The [`check`] function compares each message and enum in an old [`DescriptorPool`] with the
+//! type of the same name in a new pool, and reports a [`BreakingChange`] for each incompatible
+//! difference, such as a field being deleted without reserving its number, or changing to a type
+//! with a different encoding. The checks are modelled on the `WIRE` and `WIRE_JSON` categories of
+//! [`buf breaking`](https://buf.build/docs/breaking/rules).
+//!
+//! Types which are removed entirely are not reported, since this does not change how any
+//! remaining fields are encoded. Changes to a field which refers to a removed or renamed type are
+//! reported as type changes.
+//!
+//! # Examples
+//!
+//! ```
+//! use prost_reflect::{compat::{self, CompatLevel}, DescriptorPool};
+//! use prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+//!
+//! fn pool(field_type: i32) -> DescriptorPool {
+//!     let file = FileDescriptorProto {
+//!         name: Some("foo.proto".to_owned()),
+//!         package: Some("my.package".to_owned()),
+//!         message_type: vec![DescriptorProto {
+//!             name: Some("Foo".to_owned()),
+//!             field: vec![FieldDescriptorProto {
+//!                 name: Some("bar".to_owned()),
+//!                 number: Some(1),
+//!                 r#type: Some(field_type),
+//!                 ..Default::default()
+//!             }],
+//!             ..Default::default()
+//!         }],
+//!         ..Default::default()
+//!     };
+//!     let mut pool = DescriptorPool::new();
+//!     pool.add_file_descriptor_proto(file).unwrap();
+//!     pool
+//! }
+//!
+//! // Changing an int32 field to uint32 keeps the same encoding...
+//! let (int32, uint32, string) = (pool(5), pool(13), pool(9));
+//! assert!(compat::check(&int32, &uint32, CompatLevel::Wire).is_empty());
+//! // ...but the JSON mapping is different.
+//! let changes = compat::check(&int32, &uint32, CompatLevel::WireJson);
+//! assert_eq!(
+//!     changes[0].to_string(),
+//!     "foo.proto: field 'bar' (1) of 'my.package.Foo' changed type from 'int32' to 'uint32'",
+//! );
+//! assert!(!changes[0].is_wire_breaking());
+//!
+//! let changes = compat::check(&int32, &string, CompatLevel::Wire);
+//! assert_eq!(changes.len(), 1);
+//! assert!(changes[0].is_wire_breaking());
+//! ```
+
+use std::fmt;
+
+use super::find_source_span;
+use crate::{
+    Cardinality, DescriptorPool, EnumDescriptor, FieldDescriptor, FileDescriptor, Kind,
+    MessageDescriptor, OneofDescriptor,
+};
+
+/// The kinds of compatibility to check for with [`check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompatLevel {
+    /// Only report changes which break compatibility of the protobuf binary format.
+    Wire,
+    /// Report changes which break compatibility of either the protobuf binary format or the
+    /// JSON mapping, including renaming fields and enum values.
+    WireJson,
+}
+
+/// An incompatible change found by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakingChange {
+    message: String,
+    wire: bool,
+    file: String,
+    path: Vec<i32>,
+    span: Option<[i32; 4]>,
+}
+
+/// Compares two versions of a schema, returning all changes from `old` to `new` which break
+/// compatibility at the given level.
+///
+/// Messages and enums are matched by their full name. Each change is located at the affected
+/// definition in `new`, or, if the definition was deleted, at its parent.
+pub fn check(
+    old: &DescriptorPool,
+    new: &DescriptorPool,
+    level: CompatLevel,
+) -> Vec<BreakingChange> {
+    let mut checker = Checker {
+        level,
+        changes: Vec::new(),
+    };
+
+    for old_message in old.all_messages() {
+        if old_message.is_map_entry() {
+            continue;
+        }
+        if let Some(new_message) = new.get_message_by_name(old_message.full_name()) {
+            checker.check_message(&old_message, &new_message);
+        }
+    }
+    for old_enum in old.all_enums() {
+        if let Some(new_enum) = new.get_enum_by_name(old_enum.full_name()) {
+            checker.check_enum(&old_enum, &new_enum);
+        }
+    }
+
+    checker.changes
+}
+
+impl BreakingChange {
+    /// Gets a description of the change.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns `true` if this change breaks compatibility of the protobuf binary format, or
+    /// `false` if it only affects the JSON mapping.
+    pub fn is_wire_breaking(&self) -> bool {
+        self.wire
+    }
+
+    /// Gets the name of the file in the new schema containing the change.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Gets the path of the changed definition within the new
+    /// [`FileDescriptorProto`][prost_types::FileDescriptorProto].
+    ///
+    /// See [`path`][prost_types::source_code_info::Location::path] for more details on the structure of the path.
+    pub fn path(&self) -> &[i32] {
+        &self.path
+    }
+
+    /// The 1-based line number of the changed definition, if available.
+    ///
+    /// This is `None` if the [`source_code_info`](prost_types::FileDescriptorProto::source_code_info)
+    /// field was not populated for the file.
+    pub fn line(&self) -> Option<usize> {
+        self.span.map(|span| span[0] as usize + 1)
+    }
+
+    /// The 1-based column number of the changed definition, if available.
+    ///
+    /// This is `None` if the [`source_code_info`](prost_types::FileDescriptorProto::source_code_info)
+    /// field was not populated for the file.
+    pub fn column(&self) -> Option<usize> {
+        self.span.map(|span| span[1] as usize + 1)
+    }
+}
+
+impl fmt::Display for BreakingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let (Some(line), Some(column)) = (self.line(), self.column()) {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+struct Checker {
+    level: CompatLevel,
+    changes: Vec<BreakingChange>,
+}
+
+impl Checker {
+    fn check_message(&mut self, old: &MessageDescriptor, new: &MessageDescriptor) {
+        for old_field in old.fields() {
+            match new.get_field(old_field.number()) {
+                Some(new_field) => self.check_field(&old_field, &new_field),
+                None => {
+                    if !new
+                        .reserved_ranges()
+                        .any(|range| range.contains(&old_field.number()))
+                    {
+                        self.report(
+                            true,
+                            &new.parent_file(),
+                            new.path(),
+                            format!(
+                                "{} was deleted without reserving its number",
+                                describe_field(&old_field)
+                            ),
+                        );
+                    } else if !new.reserved_names().any(|name| name == old_field.name()) {
+                        self.report(
+                            false,
+                            &new.parent_file(),
+                            new.path(),
+                            format!(
+                                "{} was deleted without reserving its name",
+                                describe_field(&old_field)
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_field(&mut self, old: &FieldDescriptor, new: &FieldDescriptor) {
+        let file = new.parent_file();
+        let path = new.path();
+
+        if old.name() != new.name() {
+            self.report(
+                false,
+                &file,
+                path,
+                format!("{} changed name to '{}'", describe_field(old), new.name()),
+            );
+        } else if old.json_name() != new.json_name() {
+            self.report(
+                false,
+                &file,
+                path,
+                format!(
+                    "{} changed JSON name from '{}' to '{}'",
+                    describe_field(old),
+                    old.json_name(),
+                    new.json_name()
+                ),
+            );
+        }
+
+        if old.is_map() && new.is_map() {
+            let (old_kind, new_kind) = (old.kind(), new.kind());
+            let (old_entry, new_entry) = (
+                old_kind.as_message().unwrap(),
+                new_kind.as_message().unwrap(),
+            );
+            self.check_kind(
+                old,
+                &file,
+                path,
+                "key type",
+                &old_entry.map_entry_key_field().kind(),
+                &new_entry.map_entry_key_field().kind(),
+            );
+            self.check_kind(
+                old,
+                &file,
+                path,
+                "value type",
+                &old_entry.map_entry_value_field().kind(),
+                &new_entry.map_entry_value_field().kind(),
+            );
+        } else {
+            self.check_kind(old, &file, path, "type", &old.kind(), &new.kind());
+            if old.is_group() != new.is_group() {
+                self.report(
+                    true,
+                    &file,
+                    path,
+                    format!(
+                        "{} changed between a group and a message",
+                        describe_field(old)
+                    ),
+                );
+            }
+        }
+
+        if describe_cardinality(old) != describe_cardinality(new) {
+            // Length-delimited values are encoded identically as a single value or a list of
+            // one element, but packed lists of numbers are not. Maps are encoded as lists of
+            // entry messages, so a change between a map and a list is already reported if the
+            // message type changes. Any other change to or from required fields may cause
+            // messages to be rejected as uninitialized.
+            let wire = if old.is_map() || new.is_map() {
+                false
+            } else if old.is_list() != new.is_list() {
+                !matches!(old.kind(), Kind::String | Kind::Bytes | Kind::Message(_))
+            } else {
+                true
+            };
+            self.report(
+                wire,
+                &file,
+                path,
+                format!(
+                    "{} changed from {} to {}",
+                    describe_field(old),
+                    describe_cardinality(old),
+                    describe_cardinality(new)
+                ),
+            );
+        }
+
+        let old_oneof = real_oneof(old).map(|oneof| oneof.name().to_owned());
+        let new_oneof = real_oneof(new).map(|oneof| oneof.name().to_owned());
+        if old_oneof != new_oneof {
+            let message = match (old_oneof, new_oneof) {
+                (None, Some(new_oneof)) => format!(
+                    "{} was moved into the oneof '{}'",
+                    describe_field(old),
+                    new_oneof
+                ),
+                (Some(old_oneof), None) => format!(
+                    "{} was moved out of the oneof '{}'",
+                    describe_field(old),
+                    old_oneof
+                ),
+                (Some(old_oneof), Some(new_oneof)) => format!(
+                    "{} was moved from the oneof '{}' to '{}'",
+                    describe_field(old),
+                    old_oneof,
+                    new_oneof
+                ),
+                (None, None) => unreachable!(),
+            };
+            self.report(true, &file, path, message);
+        }
+    }
+
+    fn check_kind(
+        &mut self,
+        field: &FieldDescriptor,
+        file: &FileDescriptor,
+        path: &[i32],
+        what: &str,
+        old: &Kind,
+        new: &Kind,
+    ) {
+        if kind_name(old) == kind_name(new) {
+            return;
+        }
+
+        self.report(
+            !is_wire_compatible(old, new),
+            file,
+            path,
+            format!(
+                "{} changed {} from '{:?}' to '{:?}'",
+                describe_field(field),
+                what,
+                old,
+                new
+            ),
+        );
+    }
+
+    fn check_enum(&mut self, old: &EnumDescriptor, new: &EnumDescriptor) {
+        let file = new.parent_file();
+        for old_value in old.values() {
+            if new.get_value(old_value.number()).is_some() {
+                if !new
+                    .values()
+                    .any(|v| v.number() == old_value.number() && v.name() == old_value.name())
+                {
+                    let new_value = new.get_value(old_value.number()).unwrap();
+                    self.report(
+                        false,
+                        &file,
+                        new_value.path(),
+                        format!(
+                            "enum value '{}' ({}) of '{}' changed name to '{}'",
+                            old_value.name(),
+                            old_value.number(),
+                            old.full_name(),
+                            new_value.name()
+                        ),
+                    );
+                }
+            } else if !new
+                .reserved_ranges()
+                .any(|range| range.contains(&old_value.number()))
+            {
+                self.report(
+                    true,
+                    &file,
+                    new.path(),
+                    format!(
+                        "enum value '{}' ({}) of '{}' was deleted without reserving its number",
+                        old_value.name(),
+                        old_value.number(),
+                        old.full_name()
+                    ),
+                );
+            } else if !new.reserved_names().any(|name| name == old_value.name()) {
+                self.report(
+                    false,
+                    &file,
+                    new.path(),
+                    format!(
+                        "enum value '{}' ({}) of '{}' was deleted without reserving its name",
+                        old_value.name(),
+                        old_value.number(),
+                        old.full_name()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn report(&mut self, wire: bool, file: &FileDescriptor, path: &[i32], message: String) {
+        if !wire && self.level == CompatLevel::Wire {
+            return;
+        }
+
+        self.changes.push(BreakingChange {
+            message,
+            wire,
+            file: file.name().to_owned(),
+            path: path.to_vec(),
+            span: find_source_span(file, path),
+        });
+    }
+}
+
+fn describe_field(field: &FieldDescriptor) -> String {
+    format!(
+        "field '{}' ({}) of '{}'",
+        field.name(),
+        field.number(),
+        field.parent_message().full_name()
+    )
+}
+
+fn describe_cardinality(field: &FieldDescriptor) -> &'static str {
+    if field.is_map() {
+        "map"
+    } else if field.is_list() {
+        "repeated"
+    } else if field.cardinality() == Cardinality::Required {
+        "required"
+    } else {
+        "optional"
+    }
+}
+
+/// Gets the oneof containing a field, ignoring the synthetic oneofs of proto3 optional fields.
+fn real_oneof(field: &FieldDescriptor) -> Option<OneofDescriptor> {
+    if field.field_descriptor_proto().proto3_optional() {
+        None
+    } else {
+        field.containing_oneof()
+    }
+}
+
+/// Gets a name for a kind which can be compared between pools.
+fn kind_name(kind: &Kind) -> String {
+    format!("{:?}", kind)
+}
+
+/// Returns `true` if values of type `old` can be decoded as values of type `new`, and vice
+/// versa, following the
+/// [compatibility rules](https://protobuf.dev/programming-guides/proto3/#updating) for changing
+/// field types.
+fn is_wire_compatible(old: &Kind, new: &Kind) -> bool {
+    fn class(kind: &Kind) -> Option<u8> {
+        match kind {
+            Kind::Int32
+            | Kind::Int64
+            | Kind::Uint32
+            | Kind::Uint64
+            | Kind::Bool
+            | Kind::Enum(_) => Some(0),
+            Kind::Sint32 | Kind::Sint64 => Some(1),
+            Kind::Fixed32 | Kind::Sfixed32 => Some(2),
+            Kind::Fixed64 | Kind::Sfixed64 => Some(3),
+            Kind::String | Kind::Bytes => Some(4),
+            Kind::Double | Kind::Float | Kind::Message(_) => None,
+        }
+    }
+
+    match (class(old), class(new)) {
+        (Some(old), Some(new)) => old == new,
+        _ => false,
+    }
+}
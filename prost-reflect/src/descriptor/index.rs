@@ -0,0 +1,264 @@
+use std::{cmp::Reverse, convert::TryInto, mem, sync::Arc};
+
+/// A map from the names of the fields or values of a type to their indices.
+///
+/// Lookups by name are frequent when parsing text formats such as JSON. While files are being
+/// added to a pool, the names are kept sorted and found by binary search. Once they have all been
+/// added, a perfect hash table is built, so that a lookup only hashes a few bytes of the name and
+/// compares it with a single entry.
+#[derive(Clone)]
+pub(super) struct NameIndex<I> {
+    /// The entries, sorted by name.
+    pub(super) entries: Vec<(Arc<str>, I)>,
+    pub(super) table: PerfectHash,
+}
+
+/// A perfect hash function over a set of names, built using the hash and displace method.
+///
+/// Each name is assigned to a bucket by its hash, and each bucket has a displacement chosen so
+/// that the names in it are mapped to distinct, unused slots. Names are hashed by their length and
+/// their first and last eight bytes, unless that does not distinguish them, in which case all of
+/// their bytes are hashed.
+#[derive(Clone, Default)]
+pub(super) struct PerfectHash {
+    full: bool,
+    pub(super) displacements: Box<[u32]>,
+    /// The index in the entries of the name in each slot, or [`PerfectHash::EMPTY`].
+    pub(super) slots: Box<[u32]>,
+}
+
+impl<I: Copy> NameIndex<I> {
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        NameIndex {
+            entries: Vec::with_capacity(capacity),
+            table: PerfectHash::default(),
+        }
+    }
+
+    pub(super) fn get(&self, name: &str) -> Option<I> {
+        if self.table.slots.is_empty() {
+            let position = self.search(name).ok()?;
+            return Some(self.entries[position].1);
+        }
+
+        let (entry, index) = self.entries.get(self.table.get(name) as usize)?;
+        if entry.as_ref() == name {
+            Some(*index)
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a name, returning the index previously associated with it, if any.
+    pub(super) fn insert(&mut self, name: Arc<str>, index: I) -> Option<I> {
+        self.table = PerfectHash::default();
+        match self.search(&name) {
+            Ok(position) => Some(mem::replace(&mut self.entries[position].1, index)),
+            Err(position) => {
+                self.entries.insert(position, (name, index));
+                None
+            }
+        }
+    }
+
+    /// Builds the hash table, once all names have been inserted.
+    pub(super) fn build(&mut self) {
+        self.entries.shrink_to_fit();
+        self.table = PerfectHash::new(&self.entries);
+    }
+
+    fn search(&self, name: &str) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by(|(entry, _)| entry.as_ref().cmp(name))
+    }
+}
+
+impl PerfectHash {
+    const EMPTY: u32 = u32::MAX;
+
+    /// The number of times the table size is doubled before giving up on a hash function.
+    const MAX_ATTEMPTS: usize = 4;
+
+    /// Builds a table for the given entries. If no table can be found, the returned table is
+    /// empty, and lookups fall back to binary search.
+    fn new<I>(entries: &[(Arc<str>, I)]) -> Self {
+        if entries.is_empty() || entries.len() >= Self::EMPTY as usize {
+            return PerfectHash::default();
+        }
+
+        for &full in &[false, true] {
+            let hashes: Vec<u64> = entries
+                .iter()
+                .map(|(name, _)| hash(name.as_bytes(), full))
+                .collect();
+
+            let mut sorted_hashes = hashes.clone();
+            sorted_hashes.sort_unstable();
+            if sorted_hashes.windows(2).any(|pair| pair[0] == pair[1]) {
+                continue;
+            }
+
+            let mut slots_len = (entries.len() + entries.len() / 4).next_power_of_two();
+            for _ in 0..Self::MAX_ATTEMPTS {
+                if let Some(table) = Self::try_build(&hashes, full, slots_len) {
+                    return table;
+                }
+                slots_len *= 2;
+            }
+        }
+
+        PerfectHash::default()
+    }
+
+    fn try_build(hashes: &[u64], full: bool, slots_len: usize) -> Option<Self> {
+        let buckets_len = (hashes.len() / 2).next_power_of_two();
+        let mut buckets = vec![Vec::new(); buckets_len];
+        for (index, &hash) in hashes.iter().enumerate() {
+            buckets[bucket(hash, buckets_len)].push(index as u32);
+        }
+
+        // Place the largest buckets first, while there are the most free slots to choose from.
+        let mut order: Vec<usize> = (0..buckets_len).collect();
+        order.sort_by_key(|&bucket| Reverse(buckets[bucket].len()));
+
+        let mut slots = vec![Self::EMPTY; slots_len].into_boxed_slice();
+        let mut displacements = vec![0; buckets_len].into_boxed_slice();
+        let mut bucket_slots = Vec::new();
+        for bucket in order {
+            let indices = &buckets[bucket];
+            if indices.is_empty() {
+                break;
+            }
+
+            let displacement = (0..slots_len as u32).find(|&displacement| {
+                bucket_slots.clear();
+                indices.iter().all(|&index| {
+                    let slot = slot(hashes[index as usize], displacement, slots_len);
+                    if slots[slot] == Self::EMPTY && !bucket_slots.contains(&slot) {
+                        bucket_slots.push(slot);
+                        true
+                    } else {
+                        false
+                    }
+                })
+            })?;
+
+            for (&index, &slot) in indices.iter().zip(&bucket_slots) {
+                slots[slot] = index;
+            }
+            displacements[bucket] = displacement;
+        }
+
+        Some(PerfectHash {
+            full,
+            displacements,
+            slots,
+        })
+    }
+
+    /// Returns the index of the only entry which may have the given name, or
+    /// [`PerfectHash::EMPTY`].
+    fn get(&self, name: &str) -> u32 {
+        let hash = hash(name.as_bytes(), self.full);
+        let displacement = self.displacements[bucket(hash, self.displacements.len())];
+        self.slots[slot(hash, displacement, self.slots.len())]
+    }
+}
+
+fn bucket(hash: u64, buckets_len: usize) -> usize {
+    (hash >> 40) as usize & (buckets_len - 1)
+}
+
+fn slot(hash: u64, displacement: u32, slots_len: usize) -> usize {
+    let step = (hash >> 32) as u32 | 1;
+    (hash as u32).wrapping_add(displacement.wrapping_mul(step)) as usize & (slots_len - 1)
+}
+
+fn hash(bytes: &[u8], full: bool) -> u64 {
+    const K: u64 = 0x517c_c1b7_2722_0a95;
+
+    let len = bytes.len();
+    let (a, b) = if full {
+        // FNV-1a
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for &byte in bytes {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+        (hash, 0)
+    } else if len >= 8 {
+        (
+            u64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            u64::from_le_bytes(bytes[len - 8..].try_into().unwrap()),
+        )
+    } else if len >= 4 {
+        (
+            u64::from(u32::from_le_bytes(bytes[..4].try_into().unwrap())),
+            u64::from(u32::from_le_bytes(bytes[len - 4..].try_into().unwrap())),
+        )
+    } else if len > 0 {
+        (
+            u64::from(bytes[0]) | u64::from(bytes[len / 2]) << 8,
+            u64::from(bytes[len - 1]),
+        )
+    } else {
+        (0, 0)
+    };
+
+    let hash = (a ^ (len as u64).rotate_left(32)).wrapping_mul(K) ^ b.rotate_left(23);
+    let hash = hash.wrapping_mul(K);
+    hash ^ (hash >> 32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(names: &[String]) -> NameIndex<usize> {
+        let mut index = NameIndex::with_capacity(names.len());
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(index.insert(name.as_str().into(), i), None);
+        }
+        index.build();
+        index
+    }
+
+    #[test]
+    fn lookup() {
+        for len in [0, 1, 2, 3, 7, 16, 17, 100, 1000] {
+            let names: Vec<String> = (0..len).map(|i| format!("field_{}", i)).collect();
+            let index = index(&names);
+            assert_eq!(index.table.slots.is_empty(), len == 0);
+            for (i, name) in names.iter().enumerate() {
+                assert_eq!(index.get(name), Some(i));
+            }
+            assert_eq!(index.get("field"), None);
+            assert_eq!(index.get(""), None);
+            assert_eq!(index.get(&format!("field_{}", len)), None);
+        }
+    }
+
+    #[test]
+    fn lookup_similar_names() {
+        // These names have the same length and first and last eight bytes.
+        let names: Vec<String> = (0..50)
+            .map(|i| format!("prefix__{:02}__suffix", i))
+            .collect();
+        let index = index(&names);
+        assert!(index.table.full);
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(index.get(name), Some(i));
+        }
+        assert_eq!(index.get("prefix__50__suffix"), None);
+    }
+
+    #[test]
+    fn insert_replaces() {
+        let mut index = NameIndex::with_capacity(2);
+        assert_eq!(index.insert("a".into(), 1), None);
+        assert_eq!(index.insert("a".into(), 2), Some(1));
+        index.build();
+        assert_eq!(index.get("a"), Some(2));
+        assert_eq!(index.insert("b".into(), 3), None);
+        assert_eq!(index.get("b"), Some(3));
+    }
+}
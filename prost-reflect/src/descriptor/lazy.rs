@@ -0,0 +1,345 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use prost::{
+    bytes::{Buf, Bytes},
+    Message,
+};
+
+#[cfg(feature = "services")]
+use crate::ServiceDescriptor;
+use crate::{
+    descriptor::{error::DescriptorErrorKind, types},
+    DescriptorError, DescriptorPool, EnumDescriptor, ExtensionDescriptor, FileDescriptor,
+    MessageDescriptor,
+};
+
+/// A [`DescriptorPool`] which only builds the files in a [`FileDescriptorSet`][prost_types::FileDescriptorSet]
+/// when one of the types they define is first used.
+///
+/// Creating a [`DescriptorPool`] resolves every type reference and decodes every option in the
+/// file descriptor set up front, which can take a significant amount of time for large sets. By
+/// contrast, [`LazyDescriptorPool::decode`] only reads the names defined in each file. The first
+/// time a type is looked up, the file defining it and the files it depends on are built and
+/// added to the underlying pool, which can be retrieved with [`pool()`][LazyDescriptorPool::pool].
+///
+/// Building a file creates a new snapshot of the underlying pool. Each snapshot contains all the
+/// files of the earlier ones, and snapshots of the same lazy pool compare equal, so descriptors
+/// looked up before and after a file is built may be used together. However, a snapshot only
+/// contains the files which had been built when it was taken, so looking up a type through an
+/// earlier descriptor, for example with [`DescriptorPool::get_message_by_name`], may fail even
+/// though the lazy pool defines it. A pool obtained from a lazy pool stops comparing equal to its
+/// snapshots if it is modified, for example by adding files to it.
+///
+/// The pool is copied when a file is built while descriptors from an earlier snapshot are still
+/// alive, so building many files one at a time can take time quadratic in the number of files.
+/// If most of the files will be needed, look up the types up front, or call
+/// [`load_all()`][LazyDescriptorPool::load_all].
+///
+/// Extensions are only visible once the file defining them has been built. In particular,
+/// [`MessageDescriptor::extensions`] and
+/// [`get_extension`][MessageDescriptor::get_extension] do not find
+/// extensions in files which have not been built, and such extensions are decoded as unknown
+/// fields. Use [`get_extension_by_name`][LazyDescriptorPool::get_extension_by_name] or
+/// [`load_all()`][LazyDescriptorPool::load_all] to build them first.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::LazyDescriptorPool;
+/// let lazy_pool = LazyDescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// assert_eq!(lazy_pool.pool().files().len(), 0);
+///
+/// let message_descriptor = lazy_pool.get_message_by_name("package.MyMessage").unwrap().unwrap();
+/// assert_eq!(message_descriptor.full_name(), "package.MyMessage");
+/// assert!(lazy_pool.pool().get_file_by_name("doctest.proto").is_some());
+/// ```
+pub struct LazyDescriptorPool {
+    state: Mutex<LazyState>,
+}
+
+struct LazyState {
+    pool: DescriptorPool,
+    files: Vec<LazyFile>,
+    file_names: HashMap<Box<str>, usize>,
+    names: HashMap<Box<str>, usize>,
+}
+
+struct LazyFile {
+    bytes: Bytes,
+    dependencies: Vec<String>,
+    is_built: bool,
+}
+
+/// The subset of a `FileDescriptorProto` needed to find the names of the types it defines.
+#[derive(Clone, PartialEq, Message)]
+struct FileNames {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    package: Option<String>,
+    #[prost(string, repeated, tag = "3")]
+    dependency: Vec<String>,
+    #[prost(message, repeated, tag = "4")]
+    message_type: Vec<MessageNames>,
+    #[prost(message, repeated, tag = "5")]
+    enum_type: Vec<Name>,
+    #[prost(message, repeated, tag = "6")]
+    service: Vec<Name>,
+    #[prost(message, repeated, tag = "7")]
+    extension: Vec<Name>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct MessageNames {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+    #[prost(message, repeated, tag = "6")]
+    extension: Vec<Name>,
+    #[prost(message, repeated, tag = "3")]
+    nested_type: Vec<MessageNames>,
+    #[prost(message, repeated, tag = "4")]
+    enum_type: Vec<Name>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Name {
+    #[prost(string, optional, tag = "1")]
+    name: Option<String>,
+}
+
+impl LazyDescriptorPool {
+    /// Decodes a [`FileDescriptorSet`][prost_types::FileDescriptorSet] from its protobuf byte
+    /// representation, without building any of its files.
+    ///
+    /// Errors in the files, such as references to undefined types, are only reported when the
+    /// file is built.
+    pub fn decode<B>(mut bytes: B) -> Result<Self, DescriptorError>
+    where
+        B: Buf,
+    {
+        let file_set =
+            types::EncodedFileDescriptorSet::decode(bytes.copy_to_bytes(bytes.remaining()))
+                .map_err(|err| {
+                    DescriptorError::new(vec![DescriptorErrorKind::DecodeFileDescriptorSet { err }])
+                })?;
+
+        let mut pool = DescriptorPool::new();
+        Arc::make_mut(&mut pool.inner).lazy_id = Some(Arc::new(()));
+
+        let mut state = LazyState {
+            pool,
+            files: Vec::with_capacity(file_set.file.len()),
+            file_names: HashMap::with_capacity(file_set.file.len()),
+            names: HashMap::new(),
+        };
+        for bytes in file_set.file {
+            let file = FileNames::decode(bytes.clone()).map_err(|err| {
+                DescriptorError::new(vec![DescriptorErrorKind::DecodeFileDescriptorSet { err }])
+            })?;
+            if state.file_names.contains_key(file.name()) {
+                continue;
+            }
+
+            let index = state.files.len();
+            state.file_names.insert(file.name().into(), index);
+            state.add_names(index, file.package(), &file);
+            state.files.push(LazyFile {
+                bytes,
+                dependencies: file.dependency,
+                is_built: false,
+            });
+        }
+
+        Ok(LazyDescriptorPool {
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Gets a snapshot of the underlying [`DescriptorPool`], containing the files which have
+    /// been built so far.
+    pub fn pool(&self) -> DescriptorPool {
+        self.lock().pool.clone()
+    }
+
+    /// Builds all remaining files, and returns the underlying [`DescriptorPool`].
+    pub fn load_all(&self) -> Result<DescriptorPool, DescriptorError> {
+        let mut state = self.lock();
+        let indices: Vec<usize> = (0..state.files.len()).collect();
+        state.build_files(indices)?;
+        Ok(state.pool.clone())
+    }
+
+    /// Gets a file descriptor by its name, building it if necessary, or `None` if no such file is
+    /// in the set.
+    pub fn get_file_by_name(&self, name: &str) -> Result<Option<FileDescriptor>, DescriptorError> {
+        let mut state = self.lock();
+        match state.file_names.get(name) {
+            Some(&index) => {
+                state.build_files(vec![index])?;
+                Ok(state.pool.get_file_by_name(name))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Gets a [`MessageDescriptor`] by its fully qualified name, building the file which defines
+    /// it if necessary.
+    ///
+    /// See [`DescriptorPool::get_message_by_name`].
+    pub fn get_message_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<MessageDescriptor>, DescriptorError> {
+        self.load(name)
+            .map(|pool| pool.and_then(|pool| pool.get_message_by_name(name)))
+    }
+
+    /// Gets an [`EnumDescriptor`] by its fully qualified name, building the file which defines
+    /// it if necessary.
+    ///
+    /// See [`DescriptorPool::get_enum_by_name`].
+    pub fn get_enum_by_name(&self, name: &str) -> Result<Option<EnumDescriptor>, DescriptorError> {
+        self.load(name)
+            .map(|pool| pool.and_then(|pool| pool.get_enum_by_name(name)))
+    }
+
+    /// Gets an [`ExtensionDescriptor`] by its fully qualified name, building the file which
+    /// defines it if necessary.
+    ///
+    /// See [`DescriptorPool::get_extension_by_name`].
+    pub fn get_extension_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<ExtensionDescriptor>, DescriptorError> {
+        self.load(name)
+            .map(|pool| pool.and_then(|pool| pool.get_extension_by_name(name)))
+    }
+
+    /// Gets a [`ServiceDescriptor`] by its fully qualified name, building the file which defines
+    /// it if necessary.
+    ///
+    /// See [`DescriptorPool::get_service_by_name`].
+    #[cfg(feature = "services")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "services")))]
+    pub fn get_service_by_name(
+        &self,
+        name: &str,
+    ) -> Result<Option<ServiceDescriptor>, DescriptorError> {
+        self.load(name)
+            .map(|pool| pool.and_then(|pool| pool.get_service_by_name(name)))
+    }
+
+    /// Builds the file defining `name`, returning the updated pool, or `None` if the name is not
+    /// defined by any file in the set.
+    fn load(&self, name: &str) -> Result<Option<DescriptorPool>, DescriptorError> {
+        let name = name.strip_prefix('.').unwrap_or(name);
+
+        let mut state = self.lock();
+        match state.names.get(name) {
+            Some(&index) => {
+                state.build_files(vec![index])?;
+                Ok(Some(state.pool.clone()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LazyState> {
+        match self.state.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+impl fmt::Debug for LazyDescriptorPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("LazyDescriptorPool")
+            .field("file_count", &state.files.len())
+            .field("pool", &state.pool)
+            .finish()
+    }
+}
+
+impl LazyState {
+    fn add_names(&mut self, index: usize, package: &str, file: &FileNames) {
+        for message in &file.message_type {
+            self.add_message_names(index, package, message);
+        }
+        for name in file
+            .enum_type
+            .iter()
+            .chain(&file.service)
+            .chain(&file.extension)
+        {
+            self.add_name(index, package, name.name());
+        }
+    }
+
+    fn add_message_names(&mut self, index: usize, scope: &str, message: &MessageNames) {
+        let full_name = self.add_name(index, scope, message.name());
+        for nested in &message.nested_type {
+            self.add_message_names(index, &full_name, nested);
+        }
+        for name in message.enum_type.iter().chain(&message.extension) {
+            self.add_name(index, &full_name, name.name());
+        }
+    }
+
+    fn add_name(&mut self, index: usize, scope: &str, name: &str) -> String {
+        let full_name = if scope.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", scope, name)
+        };
+        self.names.entry(full_name.as_str().into()).or_insert(index);
+        full_name
+    }
+
+    /// Builds the given files and their transitive dependencies, if they have not been built yet.
+    fn build_files(&mut self, mut pending: Vec<usize>) -> Result<(), DescriptorError> {
+        let mut indices = Vec::new();
+        let mut visited = HashSet::new();
+        while let Some(index) = pending.pop() {
+            if self.files[index].is_built || !visited.insert(index) {
+                continue;
+            }
+
+            indices.push(index);
+            for dependency in &self.files[index].dependencies {
+                // Missing dependencies are reported when the file is built.
+                if let Some(&dependency_index) = self.file_names.get(dependency.as_str()) {
+                    pending.push(dependency_index);
+                }
+            }
+        }
+
+        if indices.is_empty() {
+            return Ok(());
+        }
+
+        let files = indices
+            .iter()
+            .map(|&index| types::FileDescriptorProto::decode(self.files[index].bytes.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| {
+                DescriptorError::new(vec![DescriptorErrorKind::DecodeFileDescriptorSet { err }])
+            })?;
+        // Building files clears the identity of the snapshots, so restore it, whether or not the
+        // files were built successfully.
+        let lazy_id = self.pool.inner.lazy_id.clone();
+        let result = self.pool.build_files(files);
+        Arc::make_mut(&mut self.pool.inner).lazy_id = lazy_id;
+        result?;
+
+        for index in indices {
+            self.files[index].is_built = true;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,639 @@
+//! Checks protobuf definitions against style conventions.
+//!
+//! A [`Linter`] walks the files of a [`DescriptorPool`] and reports a [`Diagnostic`] for each
+//! definition which breaks one of its [`Rule`]s. The rules are modelled on those of
+//! [`buf lint`](https://buf.build/docs/lint/rules), and cover the naming of packages, messages,
+//! fields, enums and services, the layout of files in directories matching their package, and
+//! the presence of comments. Since the linter only needs descriptors, it can be run from build
+//! scripts and tests, or against the descriptors of a running service.
+//!
+//! Source locations and comments are read from the
+//! [`source_code_info`](prost_types::FileDescriptorProto::source_code_info) of each file, so
+//! `protoc` should be run with `--include_source_info` to get the most out of the linter.
+//!
+//! # Examples
+//!
+//! ```
+//! use prost_reflect::{lint::{Linter, Rule}, DescriptorPool};
+//! use prost_types::{DescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+//!
+//! let file = FileDescriptorProto {
+//!     name: Some("my/package/foo.proto".to_owned()),
+//!     package: Some("my.package".to_owned()),
+//!     message_type: vec![DescriptorProto {
+//!         name: Some("Foo".to_owned()),
+//!         field: vec![FieldDescriptorProto {
+//!             name: Some("barBaz".to_owned()),
+//!             number: Some(1),
+//!             r#type: Some(9),
+//!             ..Default::default()
+//!         }],
+//!         ..Default::default()
+//!     }],
+//!     ..Default::default()
+//! };
+//! let mut pool = DescriptorPool::new();
+//! pool.add_file_descriptor_proto(file).unwrap();
+//!
+//! let diagnostics = Linter::new().lint(&pool);
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].rule(), Rule::FieldLowerSnakeCase);
+//! assert_eq!(
+//!     diagnostics[0].to_string(),
+//!     "my/package/foo.proto: field name 'barBaz' should be lower_snake_case [FIELD_LOWER_SNAKE_CASE]",
+//! );
+//! ```
+
+use std::{collections::HashSet, fmt};
+
+use super::{find_source_span, tag};
+use crate::{
+    DescriptorPool, EnumDescriptor, EnumValueDescriptor, FieldDescriptor, FileDescriptor,
+    MessageDescriptor,
+};
+
+/// Checks protobuf definitions against a configurable set of [`Rule`]s.
+#[derive(Debug, Clone)]
+pub struct Linter {
+    rules: HashSet<Rule>,
+    enum_zero_value_suffix: String,
+    ignore: Vec<String>,
+}
+
+/// A lint rule which may be enabled or disabled with [`Linter::rule`].
+///
+/// Each rule is identified by an upper snake case id matching the equivalent `buf lint` rule,
+/// available from [`Rule::id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// Files must declare a package. Enabled by default.
+    PackageDefined,
+    /// Each component of a package name must be `lower_snake_case`. Enabled by default.
+    PackageLowerSnakeCase,
+    /// Files must be in a directory matching their package, for example `foo/v1/bar.proto` for
+    /// the package `foo.v1`. Enabled by default.
+    PackageDirectoryMatch,
+    /// Message names must be `PascalCase`. Enabled by default.
+    MessagePascalCase,
+    /// Field and extension names must be `lower_snake_case`. Enabled by default.
+    FieldLowerSnakeCase,
+    /// Oneof names must be `lower_snake_case`. Enabled by default.
+    OneofLowerSnakeCase,
+    /// Enum names must be `PascalCase`. Enabled by default.
+    EnumPascalCase,
+    /// Enum value names must be `UPPER_SNAKE_CASE`. Enabled by default.
+    EnumValueUpperSnakeCase,
+    /// Enum value names must be prefixed with the name of their enum in `UPPER_SNAKE_CASE`, for
+    /// example `FOO_BAR_BAZ` for a value of the enum `FooBar`. Enabled by default.
+    EnumValuePrefix,
+    /// The name of the enum value with number zero must end with the suffix set by
+    /// [`Linter::enum_zero_value_suffix`], which is `_UNSPECIFIED` by default. Enabled by default.
+    EnumZeroValueSuffix,
+    /// Service names must be `PascalCase`. Enabled by default.
+    ServicePascalCase,
+    /// Method names must be `PascalCase`. Enabled by default.
+    RpcPascalCase,
+    /// Messages must have a leading or trailing comment.
+    CommentMessage,
+    /// Fields must have a leading or trailing comment.
+    CommentField,
+    /// Oneofs must have a leading or trailing comment.
+    CommentOneof,
+    /// Enums must have a leading or trailing comment.
+    CommentEnum,
+    /// Enum values must have a leading or trailing comment.
+    CommentEnumValue,
+    /// Services must have a leading or trailing comment.
+    CommentService,
+    /// Methods must have a leading or trailing comment.
+    CommentRpc,
+}
+
+/// A problem found by a [`Linter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    rule: Rule,
+    message: String,
+    file: String,
+    path: Vec<i32>,
+    span: Option<[i32; 4]>,
+}
+
+impl Linter {
+    /// Creates a new linter with the default rules enabled.
+    ///
+    /// All rules are enabled by default, except for the comment rules.
+    pub fn new() -> Self {
+        Linter {
+            rules: Rule::ALL
+                .iter()
+                .copied()
+                .filter(|rule| !rule.is_comment())
+                .collect(),
+            enum_zero_value_suffix: "_UNSPECIFIED".to_owned(),
+            ignore: Vec::new(),
+        }
+    }
+
+    /// Whether to check the given rule.
+    pub fn rule(mut self, rule: Rule, yes: bool) -> Self {
+        if yes {
+            self.rules.insert(rule);
+        } else {
+            self.rules.remove(&rule);
+        }
+        self
+    }
+
+    /// Sets the suffix required for the name of the zero value of each enum by
+    /// [`Rule::EnumZeroValueSuffix`].
+    ///
+    /// The default value is `_UNSPECIFIED`.
+    pub fn enum_zero_value_suffix(mut self, suffix: impl Into<String>) -> Self {
+        self.enum_zero_value_suffix = suffix.into();
+        self
+    }
+
+    /// Skips files whose names start with the given prefix when linting a pool, for example
+    /// `google/` to skip the well-known types and Google APIs.
+    pub fn ignore(mut self, prefix: impl Into<String>) -> Self {
+        self.ignore.push(prefix.into());
+        self
+    }
+
+    /// Checks all files in the pool, except those skipped by [`ignore`][Linter::ignore].
+    pub fn lint(&self, pool: &DescriptorPool) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for file in pool.files() {
+            if !self
+                .ignore
+                .iter()
+                .any(|prefix| file.name().starts_with(prefix.as_str()))
+            {
+                self.lint_file_inner(&file, &mut diagnostics);
+            }
+        }
+        diagnostics
+    }
+
+    /// Checks the definitions in a single file.
+    pub fn lint_file(&self, file: &FileDescriptor) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.lint_file_inner(file, &mut diagnostics);
+        diagnostics
+    }
+
+    fn lint_file_inner(&self, file: &FileDescriptor, diagnostics: &mut Vec<Diagnostic>) {
+        let mut cx = Context {
+            linter: self,
+            file,
+            diagnostics,
+        };
+
+        let package = file.package_name();
+        if package.is_empty() {
+            cx.report(
+                Rule::PackageDefined,
+                &[],
+                "files must have a package declared".to_owned(),
+            );
+        } else {
+            if !package.split('.').all(is_lower_snake_case) {
+                cx.report(
+                    Rule::PackageLowerSnakeCase,
+                    &[tag::file::PACKAGE],
+                    format!("package name '{}' should be lower_snake_case", package),
+                );
+            }
+
+            let expected_dir = package.replace('.', "/");
+            let dir = file.name().rsplit_once('/').map_or("", |(dir, _)| dir);
+            if dir != expected_dir {
+                cx.report(
+                    Rule::PackageDirectoryMatch,
+                    &[tag::file::PACKAGE],
+                    format!(
+                        "files with package '{}' should be in the directory '{}', but this file is in '{}'",
+                        package,
+                        expected_dir,
+                        if dir.is_empty() { "." } else { dir },
+                    ),
+                );
+            }
+        }
+
+        for message in file.messages() {
+            cx.lint_message(&message);
+        }
+        for enum_ in file.enums() {
+            cx.lint_enum(&enum_);
+        }
+        for extension in file.extensions() {
+            cx.lint_field_name(extension.name(), extension.path());
+        }
+
+        for (service_index, service) in file.file_descriptor_proto().service.iter().enumerate() {
+            let service_path = [tag::file::SERVICE, service_index as i32];
+            if !is_pascal_case(service.name()) {
+                cx.report(
+                    Rule::ServicePascalCase,
+                    &service_path,
+                    format!("service name '{}' should be PascalCase", service.name()),
+                );
+            }
+            cx.check_comment(
+                Rule::CommentService,
+                &service_path,
+                "service",
+                service.name(),
+            );
+
+            for (method_index, method) in service.method.iter().enumerate() {
+                let method_path = [
+                    tag::file::SERVICE,
+                    service_index as i32,
+                    tag::service::METHOD,
+                    method_index as i32,
+                ];
+                if !is_pascal_case(method.name()) {
+                    cx.report(
+                        Rule::RpcPascalCase,
+                        &method_path,
+                        format!("method name '{}' should be PascalCase", method.name()),
+                    );
+                }
+                cx.check_comment(Rule::CommentRpc, &method_path, "method", method.name());
+            }
+        }
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Linter::new()
+    }
+}
+
+impl Rule {
+    /// All available rules.
+    pub const ALL: &'static [Rule] = &[
+        Rule::PackageDefined,
+        Rule::PackageLowerSnakeCase,
+        Rule::PackageDirectoryMatch,
+        Rule::MessagePascalCase,
+        Rule::FieldLowerSnakeCase,
+        Rule::OneofLowerSnakeCase,
+        Rule::EnumPascalCase,
+        Rule::EnumValueUpperSnakeCase,
+        Rule::EnumValuePrefix,
+        Rule::EnumZeroValueSuffix,
+        Rule::ServicePascalCase,
+        Rule::RpcPascalCase,
+        Rule::CommentMessage,
+        Rule::CommentField,
+        Rule::CommentOneof,
+        Rule::CommentEnum,
+        Rule::CommentEnumValue,
+        Rule::CommentService,
+        Rule::CommentRpc,
+    ];
+
+    /// Gets the id of this rule, e.g. `FIELD_LOWER_SNAKE_CASE`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::PackageDefined => "PACKAGE_DEFINED",
+            Rule::PackageLowerSnakeCase => "PACKAGE_LOWER_SNAKE_CASE",
+            Rule::PackageDirectoryMatch => "PACKAGE_DIRECTORY_MATCH",
+            Rule::MessagePascalCase => "MESSAGE_PASCAL_CASE",
+            Rule::FieldLowerSnakeCase => "FIELD_LOWER_SNAKE_CASE",
+            Rule::OneofLowerSnakeCase => "ONEOF_LOWER_SNAKE_CASE",
+            Rule::EnumPascalCase => "ENUM_PASCAL_CASE",
+            Rule::EnumValueUpperSnakeCase => "ENUM_VALUE_UPPER_SNAKE_CASE",
+            Rule::EnumValuePrefix => "ENUM_VALUE_PREFIX",
+            Rule::EnumZeroValueSuffix => "ENUM_ZERO_VALUE_SUFFIX",
+            Rule::ServicePascalCase => "SERVICE_PASCAL_CASE",
+            Rule::RpcPascalCase => "RPC_PASCAL_CASE",
+            Rule::CommentMessage => "COMMENT_MESSAGE",
+            Rule::CommentField => "COMMENT_FIELD",
+            Rule::CommentOneof => "COMMENT_ONEOF",
+            Rule::CommentEnum => "COMMENT_ENUM",
+            Rule::CommentEnumValue => "COMMENT_ENUM_VALUE",
+            Rule::CommentService => "COMMENT_SERVICE",
+            Rule::CommentRpc => "COMMENT_RPC",
+        }
+    }
+
+    fn is_comment(&self) -> bool {
+        matches!(
+            self,
+            Rule::CommentMessage
+                | Rule::CommentField
+                | Rule::CommentOneof
+                | Rule::CommentEnum
+                | Rule::CommentEnumValue
+                | Rule::CommentService
+                | Rule::CommentRpc
+        )
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl Diagnostic {
+    /// Gets the rule which was broken.
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    /// Gets a description of the problem.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Gets the name of the file containing the problem.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Gets the path of the offending definition within the
+    /// [`FileDescriptorProto`][prost_types::FileDescriptorProto].
+    ///
+    /// See [`path`][prost_types::source_code_info::Location::path] for more details on the structure of the path.
+    pub fn path(&self) -> &[i32] {
+        &self.path
+    }
+
+    /// The 1-based line number of the offending definition, if available.
+    ///
+    /// This is `None` if the [`source_code_info`](prost_types::FileDescriptorProto::source_code_info)
+    /// field was not populated for the file.
+    pub fn line(&self) -> Option<usize> {
+        self.span.map(|span| span[0] as usize + 1)
+    }
+
+    /// The 1-based column number of the offending definition, if available.
+    ///
+    /// This is `None` if the [`source_code_info`](prost_types::FileDescriptorProto::source_code_info)
+    /// field was not populated for the file.
+    pub fn column(&self) -> Option<usize> {
+        self.span.map(|span| span[1] as usize + 1)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let (Some(line), Some(column)) = (self.line(), self.column()) {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {} [{}]", self.message, self.rule)
+    }
+}
+
+struct Context<'a> {
+    linter: &'a Linter,
+    file: &'a FileDescriptor,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl Context<'_> {
+    fn lint_message(&mut self, message: &MessageDescriptor) {
+        // Map entry messages are generated by the compiler, so cannot be changed.
+        if message.is_map_entry() {
+            return;
+        }
+
+        if !is_pascal_case(message.name()) {
+            self.report(
+                Rule::MessagePascalCase,
+                message.path(),
+                format!("message name '{}' should be PascalCase", message.name()),
+            );
+        }
+        self.check_comment(
+            Rule::CommentMessage,
+            message.path(),
+            "message",
+            message.name(),
+        );
+
+        for field in message.fields() {
+            self.lint_field(&field);
+        }
+        for oneof in message.oneofs() {
+            // Synthetic oneofs are generated by the compiler for proto3 optional fields.
+            if oneof
+                .fields()
+                .all(|field| field.field_descriptor_proto().proto3_optional())
+            {
+                continue;
+            }
+
+            if !is_lower_snake_case(oneof.name()) {
+                self.report(
+                    Rule::OneofLowerSnakeCase,
+                    oneof.path(),
+                    format!("oneof name '{}' should be lower_snake_case", oneof.name()),
+                );
+            }
+            self.check_comment(Rule::CommentOneof, oneof.path(), "oneof", oneof.name());
+        }
+        for extension in message.extensions() {
+            self.lint_field_name(extension.name(), extension.path());
+        }
+        for child in message.child_messages() {
+            self.lint_message(&child);
+        }
+        for child in message.child_enums() {
+            self.lint_enum(&child);
+        }
+    }
+
+    fn lint_field(&mut self, field: &FieldDescriptor) {
+        // The name of a group field is derived from the name of the group.
+        if !field.is_group() {
+            self.lint_field_name(field.name(), field.path());
+        }
+        self.check_comment(Rule::CommentField, field.path(), "field", field.name());
+    }
+
+    fn lint_field_name(&mut self, name: &str, path: &[i32]) {
+        if !is_lower_snake_case(name) {
+            self.report(
+                Rule::FieldLowerSnakeCase,
+                path,
+                format!("field name '{}' should be lower_snake_case", name),
+            );
+        }
+    }
+
+    fn lint_enum(&mut self, enum_: &EnumDescriptor) {
+        if !is_pascal_case(enum_.name()) {
+            self.report(
+                Rule::EnumPascalCase,
+                enum_.path(),
+                format!("enum name '{}' should be PascalCase", enum_.name()),
+            );
+        }
+        self.check_comment(Rule::CommentEnum, enum_.path(), "enum", enum_.name());
+
+        let prefix = format!("{}_", to_upper_snake_case(enum_.name()));
+        for value in enum_.values() {
+            self.lint_enum_value(&value, &prefix);
+        }
+    }
+
+    fn lint_enum_value(&mut self, value: &EnumValueDescriptor, prefix: &str) {
+        if !is_upper_snake_case(value.name()) {
+            self.report(
+                Rule::EnumValueUpperSnakeCase,
+                value.path(),
+                format!(
+                    "enum value name '{}' should be UPPER_SNAKE_CASE",
+                    value.name()
+                ),
+            );
+        }
+        if !value.name().starts_with(prefix) {
+            self.report(
+                Rule::EnumValuePrefix,
+                value.path(),
+                format!(
+                    "enum value name '{}' should be prefixed with '{}'",
+                    value.name(),
+                    prefix
+                ),
+            );
+        }
+
+        let suffix = self.linter.enum_zero_value_suffix.as_str();
+        if value.number() == 0 && !value.name().ends_with(suffix) {
+            self.report(
+                Rule::EnumZeroValueSuffix,
+                value.path(),
+                format!(
+                    "enum zero value name '{}' should be suffixed with '{}'",
+                    value.name(),
+                    suffix
+                ),
+            );
+        }
+        self.check_comment(
+            Rule::CommentEnumValue,
+            value.path(),
+            "enum value",
+            value.name(),
+        );
+    }
+
+    /// Reports a missing comment, unless the file has no source code info to find comments in.
+    fn check_comment(&mut self, rule: Rule, path: &[i32], kind: &str, name: &str) {
+        if !self.linter.rules.contains(&rule) {
+            return;
+        }
+
+        let source_code_info = match &self.file.file_descriptor_proto().source_code_info {
+            Some(source_code_info) => source_code_info,
+            None => return,
+        };
+        let has_comment = source_code_info
+            .location
+            .iter()
+            .find(|location| location.path == path)
+            .map_or(false, |location| {
+                let is_non_empty = |comment: &Option<String>| {
+                    comment
+                        .as_deref()
+                        .map_or(false, |comment| !comment.trim().is_empty())
+                };
+                is_non_empty(&location.leading_comments)
+                    || is_non_empty(&location.trailing_comments)
+            });
+        if !has_comment {
+            self.report(
+                rule,
+                path,
+                format!("{} '{}' should have a non-empty comment", kind, name),
+            );
+        }
+    }
+
+    fn report(&mut self, rule: Rule, path: &[i32], message: String) {
+        if !self.linter.rules.contains(&rule) {
+            return;
+        }
+
+        self.diagnostics.push(Diagnostic {
+            rule,
+            message,
+            file: self.file.name().to_owned(),
+            path: path.to_vec(),
+            span: find_source_span(self.file, path),
+        });
+    }
+}
+
+fn is_lower_snake_case(name: &str) -> bool {
+    is_snake_case(name, |ch| ch.is_ascii_lowercase())
+}
+
+fn is_upper_snake_case(name: &str) -> bool {
+    is_snake_case(name, |ch| ch.is_ascii_uppercase())
+}
+
+/// Checks that `name` consists of words of letters matching `is_letter` and digits, separated by
+/// single underscores, and starting with a letter.
+fn is_snake_case(name: &str, is_letter: impl Fn(char) -> bool) -> bool {
+    name.starts_with(&is_letter)
+        && !name.ends_with('_')
+        && !name.contains("__")
+        && name
+            .chars()
+            .all(|ch| is_letter(ch) || ch.is_ascii_digit() || ch == '_')
+}
+
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_uppercase())
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric())
+}
+
+/// Converts a `PascalCase` name to `UPPER_SNAKE_CASE`, treating a run of capitals as a single
+/// word, so that `HTTPStatus` becomes `HTTP_STATUS`.
+fn to_upper_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::with_capacity(name.len() + 4);
+    for (i, &ch) in chars.iter().enumerate() {
+        if i > 0 && ch.is_ascii_uppercase() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map_or(false, |c| c.is_ascii_lowercase());
+            if prev.is_ascii_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_ascii_uppercase() && next_is_lower)
+            {
+                result.push('_');
+            }
+        }
+        result.push(ch.to_ascii_uppercase());
+    }
+    result
+}
+
+#[test]
+fn case_conversions() {
+    assert!(is_lower_snake_case("foo_bar2"));
+    assert!(!is_lower_snake_case("fooBar"));
+    assert!(!is_lower_snake_case("_foo"));
+    assert!(!is_lower_snake_case("foo__bar"));
+    assert!(is_upper_snake_case("FOO_BAR"));
+    assert!(!is_upper_snake_case("FOO_bar"));
+    assert!(is_pascal_case("FooBar"));
+    assert!(!is_pascal_case("Foo_Bar"));
+    assert_eq!(to_upper_snake_case("FooBar"), "FOO_BAR");
+    assert_eq!(to_upper_snake_case("HTTPStatus"), "HTTP_STATUS");
+    assert_eq!(to_upper_snake_case("Status2Code"), "STATUS2_CODE");
+    assert_eq!(to_upper_snake_case("Foo_Bar"), "FOO_BAR");
+}
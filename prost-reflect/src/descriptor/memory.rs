@@ -0,0 +1,440 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+    sync::Arc,
+};
+
+#[cfg(feature = "serde")]
+use crate::descriptor::StrategyNames;
+#[cfg(feature = "services")]
+use crate::descriptor::{MethodDescriptorInner, ServiceDescriptorInner};
+use crate::{
+    descriptor::{
+        types, Definition, EnumDescriptorInner, EnumValueDescriptorInner, ExtensionDescriptorInner,
+        FieldDescriptorInner, FileDescriptorInner, Identity, MessageDescriptorInner, NameIndex,
+        OneofDescriptorInner,
+    },
+    DescriptorPool, Value,
+};
+
+/// An estimate of the memory used by a [`DescriptorPool`], as returned by
+/// [`DescriptorPool::memory_usage`].
+///
+/// All sizes are in bytes, and include both the heap allocations owned by the pool and the size
+/// of the structures which contain them. The sizes of hash maps and B-trees are estimated from
+/// their capacity, so they may differ slightly from the memory actually allocated. Strings which
+/// are shared between descriptors are only counted once, in the first category which uses them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    file_descriptor_protos: usize,
+    names: usize,
+    messages: usize,
+    enums: usize,
+    extensions: usize,
+    #[cfg(feature = "services")]
+    services: usize,
+    files: Vec<FileMemoryUsage>,
+}
+
+/// An estimate of the memory used by a single file in a [`DescriptorPool`], as reported by
+/// [`MemoryUsage::files`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMemoryUsage {
+    name: String,
+    file_descriptor_protos: usize,
+    descriptors: usize,
+}
+
+impl DescriptorPool {
+    /// Estimates the memory used by this pool, broken down by category and by file.
+    ///
+    /// Memory shared with other clones of the pool, or with descriptors obtained from it, is
+    /// included. Computing the estimate requires walking the whole pool, so it should not be
+    /// called frequently for large pools.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::DescriptorPool;
+    /// let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// let usage = pool.memory_usage();
+    /// assert!(usage.file_descriptor_protos() > 0);
+    /// assert!(usage.total() >= usage.messages() + usage.names());
+    ///
+    /// let file_usage = usage.files().iter().find(|file| file.name() == "doctest.proto").unwrap();
+    /// assert!(file_usage.descriptors() > 0);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let inner = &*self.inner;
+        let mut cx = Context::default();
+
+        let mut usage = MemoryUsage {
+            files: inner
+                .files
+                .iter()
+                .map(|file| FileMemoryUsage {
+                    name: file.prost.name().to_owned(),
+                    file_descriptor_protos: 0,
+                    descriptors: 0,
+                })
+                .collect(),
+            ..Default::default()
+        };
+
+        usage.names = inner.names.heap_size(&mut cx) + inner.file_names.heap_size(&mut cx);
+
+        usage.file_descriptor_protos = vec_size(&inner.files);
+        for (file, file_usage) in inner.files.iter().zip(&mut usage.files) {
+            let size = file.heap_size(&mut cx);
+            usage.file_descriptor_protos += size;
+            file_usage.file_descriptor_protos = size + mem::size_of_val(file);
+        }
+
+        usage.messages = count(&inner.messages, &mut usage.files, &mut cx, |m| &m.id);
+        usage.enums = count(&inner.enums, &mut usage.files, &mut cx, |e| &e.id);
+        usage.extensions = count(&inner.extensions, &mut usage.files, &mut cx, |e| &e.id);
+        #[cfg(feature = "services")]
+        {
+            usage.services = count(&inner.services, &mut usage.files, &mut cx, |s| &s.id);
+        }
+
+        usage
+    }
+}
+
+impl MemoryUsage {
+    /// Returns the total memory used by the pool.
+    pub fn total(&self) -> usize {
+        let total =
+            self.file_descriptor_protos + self.names + self.messages + self.enums + self.extensions;
+        #[cfg(feature = "services")]
+        let total = total + self.services;
+        total
+    }
+
+    /// Returns the memory used by the file descriptor protos the pool was built from.
+    ///
+    /// The pool stores two copies of each file: one in the form it was added in, which preserves
+    /// options not known to the pool, and one as a [`prost_types::FileDescriptorProto`].
+    pub fn file_descriptor_protos(&self) -> usize {
+        self.file_descriptor_protos
+    }
+
+    /// Returns the memory used by the index from the full names of types, fields and packages,
+    /// and the names of files, to their definitions.
+    pub fn names(&self) -> usize {
+        self.names
+    }
+
+    /// Returns the memory used by the descriptors of messages, including their fields, oneofs
+    /// and the tables used to look fields up by number and name.
+    pub fn messages(&self) -> usize {
+        self.messages
+    }
+
+    /// Returns the memory used by the descriptors of enums, including their values.
+    pub fn enums(&self) -> usize {
+        self.enums
+    }
+
+    /// Returns the memory used by the descriptors of extensions.
+    pub fn extensions(&self) -> usize {
+        self.extensions
+    }
+
+    /// Returns the memory used by the descriptors of services, including their methods.
+    #[cfg(feature = "services")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "services")))]
+    pub fn services(&self) -> usize {
+        self.services
+    }
+
+    /// Returns the memory used by each file in the pool, in the order the files were added.
+    ///
+    /// The name index is shared between files, so it is not included in the usage of any file.
+    pub fn files(&self) -> &[FileMemoryUsage] {
+        &self.files
+    }
+}
+
+impl FileMemoryUsage {
+    /// Returns the name of the file.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the memory used by the file descriptor protos for this file.
+    ///
+    /// See [`MemoryUsage::file_descriptor_protos`].
+    pub fn file_descriptor_protos(&self) -> usize {
+        self.file_descriptor_protos
+    }
+
+    /// Returns the memory used by the descriptors of the types and services defined in this file.
+    pub fn descriptors(&self) -> usize {
+        self.descriptors
+    }
+
+    /// Returns the total memory used by this file.
+    pub fn total(&self) -> usize {
+        self.file_descriptor_protos + self.descriptors
+    }
+}
+
+/// Counts the memory used by a list of descriptors, adding the size of each to the usage of the
+/// file which defines it.
+fn count<T: HeapSize>(
+    descriptors: &Vec<T>,
+    files: &mut [FileMemoryUsage],
+    cx: &mut Context,
+    id: impl Fn(&T) -> &Identity,
+) -> usize {
+    let mut total = vec_size(descriptors);
+    for descriptor in descriptors {
+        let size = descriptor.heap_size(cx);
+        total += size;
+        files[id(descriptor).file as usize].descriptors += size + mem::size_of::<T>();
+    }
+    total
+}
+
+fn vec_size<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * mem::size_of::<T>()
+}
+
+/// Tracks the shared strings which have already been counted.
+#[derive(Default)]
+struct Context {
+    seen: HashSet<*const u8>,
+}
+
+/// Estimates the size of the heap allocations owned by a value.
+trait HeapSize {
+    fn heap_size(&self, cx: &mut Context) -> usize;
+}
+
+macro_rules! impl_heap_size {
+    ($($ty:ty { $($field:ident),* })*) => {
+        $(
+            impl HeapSize for $ty {
+                #[allow(unused_variables)]
+                fn heap_size(&self, cx: &mut Context) -> usize {
+                    0 $(+ self.$field.heap_size(cx))*
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_heap_size_none {
+    ($($ty:ty),*) => {
+        $(
+            impl HeapSize for $ty {
+                fn heap_size(&self, _: &mut Context) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_heap_size_none!(
+    u8,
+    i32,
+    u32,
+    prost_types::descriptor_proto::ReservedRange,
+    prost_types::enum_descriptor_proto::EnumReservedRange
+);
+
+impl HeapSize for String {
+    fn heap_size(&self, _: &mut Context) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for Arc<str> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        if cx.seen.insert(self.as_ptr()) {
+            // The allocation also holds the strong and weak reference counts.
+            2 * mem::size_of::<usize>() + self.len()
+        } else {
+            0
+        }
+    }
+}
+
+impl<A: HeapSize, B: HeapSize> HeapSize for (A, B) {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        self.0.heap_size(cx) + self.1.heap_size(cx)
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        self.as_ref().map_or(0, |value| value.heap_size(cx))
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        vec_size(self) + self.iter().map(|value| value.heap_size(cx)).sum::<usize>()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Box<[T]> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        mem::size_of_val::<[T]>(self) + self.iter().map(|value| value.heap_size(cx)).sum::<usize>()
+    }
+}
+
+impl<K: HeapSize, V: HeapSize> HeapSize for HashMap<K, V> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        // Hash maps allocate a power of two number of buckets, with one control byte for each
+        // bucket plus one group, and are at most 7/8ths full.
+        let buckets = match self.capacity() {
+            0 => return 0,
+            1..=3 => 4,
+            4..=7 => 8,
+            capacity => (capacity * 8 / 7).next_power_of_two(),
+        };
+        let table = buckets * (mem::size_of::<(K, V)>() + 1) + 16;
+        table
+            + self
+                .iter()
+                .map(|(key, value)| key.heap_size(cx) + value.heap_size(cx))
+                .sum::<usize>()
+    }
+}
+
+impl<K, V> HeapSize for BTreeMap<K, V> {
+    fn heap_size(&self, _: &mut Context) -> usize {
+        // Each node holds up to 11 entries, and nodes are typically at least half full.
+        const NODE_CAPACITY: usize = 11;
+        let nodes = (self.len() + NODE_CAPACITY / 2 - 1) / (NODE_CAPACITY / 2);
+        nodes * (NODE_CAPACITY * (mem::size_of::<K>() + mem::size_of::<V>()) + 16)
+    }
+}
+
+impl<I> HeapSize for NameIndex<I> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        vec_size(&self.entries)
+            + self
+                .entries
+                .iter()
+                .map(|(name, _)| name.heap_size(cx))
+                .sum::<usize>()
+            + self.table.slots.heap_size(cx)
+            + self.table.displacements.heap_size(cx)
+    }
+}
+
+impl HeapSize for MessageDescriptorInner {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        let size = self.id.heap_size(cx)
+            + self.extensions.heap_size(cx)
+            + self.fields.heap_size(cx)
+            + self.field_numbers.heap_size(cx)
+            + self.field_names.heap_size(cx)
+            + self.field_json_names.heap_size(cx)
+            + self.oneofs.heap_size(cx);
+        #[cfg(feature = "serde")]
+        let size = size + self.field_strategy_names.heap_size(cx);
+        size
+    }
+}
+
+#[cfg(feature = "serde")]
+impl HeapSize for Box<StrategyNames> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        mem::size_of::<StrategyNames>() + self.names.heap_size(cx) + self.index.heap_size(cx)
+    }
+}
+
+impl HeapSize for Value {
+    fn heap_size(&self, _: &mut Context) -> usize {
+        // Default values are always scalars, strings or bytes.
+        match self {
+            Value::String(value) => value.capacity(),
+            Value::Bytes(value) => value.len(),
+            _ => 0,
+        }
+    }
+}
+
+impl<T: HeapSize> HeapSize for types::Options<T> {
+    fn heap_size(&self, cx: &mut Context) -> usize {
+        self.encoded.heap_size(cx) + self.value.heap_size(cx)
+    }
+}
+
+impl_heap_size! {
+    Definition { path }
+    Identity { path, full_name }
+    FileDescriptorInner { raw, prost, dependencies }
+    FieldDescriptorInner { id, json_name, default }
+    OneofDescriptorInner { id, fields }
+    ExtensionDescriptorInner { id, json_name, default }
+    EnumDescriptorInner { id, values, value_numbers, value_names }
+    EnumValueDescriptorInner { id }
+
+    types::FileDescriptorProto {
+        name, package, dependency, public_dependency, weak_dependency, message_type, enum_type,
+        service, extension, options, source_code_info, syntax
+    }
+    types::DescriptorProto {
+        name, field, extension, nested_type, enum_type, extension_range, oneof_decl, options,
+        reserved_range, reserved_name
+    }
+    types::descriptor_proto::ExtensionRange { options }
+    types::FieldDescriptorProto { name, type_name, extendee, default_value, json_name, options }
+    types::OneofDescriptorProto { name, options }
+    types::EnumDescriptorProto { name, value, options, reserved_range, reserved_name }
+    types::EnumValueDescriptorProto { name, options }
+    types::ServiceDescriptorProto { name, method, options }
+    types::MethodDescriptorProto { name, input_type, output_type, options }
+
+    prost_types::FileDescriptorProto {
+        name, package, dependency, public_dependency, weak_dependency, message_type, enum_type,
+        service, extension, options, source_code_info, syntax
+    }
+    prost_types::DescriptorProto {
+        name, field, extension, nested_type, enum_type, extension_range, oneof_decl, options,
+        reserved_range, reserved_name
+    }
+    prost_types::descriptor_proto::ExtensionRange { options }
+    prost_types::FieldDescriptorProto {
+        name, type_name, extendee, default_value, json_name, options
+    }
+    prost_types::OneofDescriptorProto { name, options }
+    prost_types::EnumDescriptorProto { name, value, options, reserved_range, reserved_name }
+    prost_types::EnumValueDescriptorProto { name, options }
+    prost_types::ServiceDescriptorProto { name, method, options }
+    prost_types::MethodDescriptorProto { name, input_type, output_type, options }
+
+    prost_types::FileOptions {
+        java_package, java_outer_classname, go_package, objc_class_prefix, csharp_namespace,
+        swift_prefix, php_class_prefix, php_namespace, php_metadata_namespace, ruby_package,
+        uninterpreted_option
+    }
+    prost_types::MessageOptions { uninterpreted_option }
+    prost_types::FieldOptions { uninterpreted_option }
+    prost_types::OneofOptions { uninterpreted_option }
+    prost_types::EnumOptions { uninterpreted_option }
+    prost_types::EnumValueOptions { uninterpreted_option }
+    prost_types::ServiceOptions { uninterpreted_option }
+    prost_types::MethodOptions { uninterpreted_option }
+    prost_types::ExtensionRangeOptions { uninterpreted_option }
+    prost_types::UninterpretedOption { name, identifier_value, string_value, aggregate_value }
+    prost_types::uninterpreted_option::NamePart { name_part }
+
+    prost_types::SourceCodeInfo { location }
+    prost_types::source_code_info::Location {
+        path, span, leading_comments, trailing_comments, leading_detached_comments
+    }
+}
+
+#[cfg(feature = "services")]
+impl_heap_size! {
+    ServiceDescriptorInner { id, methods }
+    MethodDescriptorInner { id }
+}
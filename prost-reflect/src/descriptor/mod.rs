@@ -1,23 +1,39 @@
 mod api;
 mod build;
+#[cfg(feature = "compat")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compat")))]
+pub mod compat;
 mod error;
+mod index;
+mod lazy;
+#[cfg(feature = "lint")]
+#[cfg_attr(docsrs, doc(cfg(feature = "lint")))]
+pub mod lint;
+mod memory;
 mod tag;
+mod template;
 #[cfg(test)]
 mod tests;
 mod types;
 
 pub use self::error::DescriptorError;
+use self::index::NameIndex;
+pub use self::lazy::LazyDescriptorPool;
+pub use self::memory::{FileMemoryUsage, MemoryUsage};
+pub use self::template::TemplateFormat;
 use self::types::{DescriptorProto, EnumDescriptorProto};
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     convert::TryInto,
     fmt,
     ops::Range,
     sync::Arc,
 };
 
-use crate::{descriptor::types::FileDescriptorProto, Value};
+#[cfg(feature = "serde")]
+use crate::FieldNameStrategy;
+use crate::{descriptor::types::FileDescriptorProto, DecodeOptions, EncodeInterceptor, Value};
 
 pub(crate) const MAP_ENTRY_KEY_NUMBER: u32 = 1;
 pub(crate) const MAP_ENTRY_VALUE_NUMBER: u32 = 2;
@@ -111,7 +127,9 @@ enum KindIndex {
 
 type DescriptorIndex = u32;
 type FileIndex = DescriptorIndex;
+#[cfg(feature = "services")]
 type ServiceIndex = DescriptorIndex;
+#[cfg(feature = "services")]
 type MethodIndex = DescriptorIndex;
 type MessageIndex = DescriptorIndex;
 type FieldIndex = DescriptorIndex;
@@ -129,6 +147,9 @@ type EnumValueIndex = DescriptorIndex;
 ///
 /// This type is uses reference counting internally so it is cheap to clone. Modifying an instance of a
 /// pool will not update any existing clones of the instance.
+///
+/// Pools compare equal if they are clones of the same instance, or if they are both snapshots of
+/// the same [`LazyDescriptorPool`].
 #[derive(Clone, Default)]
 pub struct DescriptorPool {
     inner: Arc<DescriptorPoolInner>,
@@ -136,20 +157,41 @@ pub struct DescriptorPool {
 
 #[derive(Clone, Default)]
 struct DescriptorPoolInner {
-    names: HashMap<Box<str>, Definition>,
-    file_names: HashMap<Box<str>, FileIndex>,
+    interner: Interner,
+    names: HashMap<Arc<str>, Definition>,
+    file_names: HashMap<Arc<str>, FileIndex>,
     files: Vec<FileDescriptorInner>,
     messages: Vec<MessageDescriptorInner>,
     enums: Vec<EnumDescriptorInner>,
     extensions: Vec<ExtensionDescriptorInner>,
+    #[cfg(feature = "services")]
     services: Vec<ServiceDescriptorInner>,
+    decode_options: Option<DecodeOptions>,
+    encode_interceptors: Vec<Arc<dyn EncodeInterceptor + Send + Sync>>,
+    #[cfg(feature = "serde")]
+    field_name_strategy: Option<FieldNameStrategy>,
+    /// Identifies the snapshots of a [`LazyDescriptorPool`]. Each snapshot contains the files of
+    /// all earlier snapshots at the same indices, so their descriptors can be used
+    /// interchangeably. This is cleared if the pool is modified in any other way.
+    lazy_id: Option<Arc<()>>,
+}
+
+/// The names used by the descriptors being added to a pool, so that each distinct name is only
+/// stored once.
+///
+/// Full names are shared between descriptors and the pool's name index, and short names and JSON
+/// names are shared between all fields or enum values with the same name. The set itself is only
+/// needed while building files, and is cleared afterwards to avoid its overhead.
+#[derive(Clone, Default)]
+struct Interner {
+    names: HashSet<Arc<str>>,
 }
 
 #[derive(Clone)]
 struct Identity {
     file: FileIndex,
     path: Box<[i32]>,
-    full_name: Box<str>,
+    full_name: Arc<str>,
     name_index: usize,
 }
 
@@ -161,12 +203,15 @@ struct Definition {
 }
 
 #[derive(Copy, Clone)]
+#[allow(dead_code)]
 enum DefinitionKind {
     Package,
     Message(MessageIndex),
     Field(MessageIndex, FieldIndex),
     Oneof(MessageIndex, OneofIndex),
+    #[cfg(feature = "services")]
     Service(ServiceIndex),
+    #[cfg(feature = "services")]
     Method(ServiceIndex, MethodIndex),
     Enum(EnumIndex),
     EnumValue(EnumIndex, EnumValueIndex),
@@ -202,11 +247,34 @@ struct MessageDescriptorInner {
     extensions: Vec<ExtensionIndex>,
     fields: Vec<FieldDescriptorInner>,
     field_numbers: BTreeMap<u32, FieldIndex>,
-    field_names: HashMap<Box<str>, FieldIndex>,
-    field_json_names: HashMap<Box<str>, FieldIndex>,
+    field_names: NameIndex<FieldIndex>,
+    /// The names accepted for fields when parsing JSON.
+    field_json_names: NameIndex<JsonFieldName>,
+    /// The names of the fields chosen by the pool's [`FieldNameStrategy`], if it has one other
+    /// than [`FieldNameStrategy::JsonName`].
+    #[cfg(feature = "serde")]
+    field_strategy_names: Option<Box<StrategyNames>>,
     oneofs: Vec<OneofDescriptorInner>,
 }
 
+/// A field with a name accepted when parsing JSON: its JSON name, or its name, unless that is
+/// the JSON name of another field.
+#[derive(Clone, Copy)]
+struct JsonFieldName {
+    index: FieldIndex,
+    is_json_name: bool,
+}
+
+/// The names chosen for the fields of a message by a [`FieldNameStrategy`].
+#[cfg(feature = "serde")]
+#[derive(Clone)]
+struct StrategyNames {
+    /// The name of each field, by index.
+    names: Box<[Arc<str>]>,
+    /// The fields by name. If several fields have the same name, the first one is used.
+    index: NameIndex<FieldIndex>,
+}
+
 /// A oneof field in a protobuf message.
 #[derive(Clone, PartialEq, Eq)]
 pub struct OneofDescriptor {
@@ -231,7 +299,7 @@ pub struct FieldDescriptor {
 struct FieldDescriptorInner {
     id: Identity,
     number: u32,
-    json_name: Box<str>,
+    json_name: Arc<str>,
     kind: KindIndex,
     oneof: Option<OneofIndex>,
     is_packed: bool,
@@ -252,7 +320,7 @@ pub struct ExtensionDescriptorInner {
     id: Identity,
     parent: Option<MessageIndex>,
     number: u32,
-    json_name: Box<str>,
+    json_name: Arc<str>,
     extendee: MessageIndex,
     kind: KindIndex,
     is_packed: bool,
@@ -273,7 +341,7 @@ struct EnumDescriptorInner {
     parent: Option<MessageIndex>,
     values: Vec<EnumValueDescriptorInner>,
     value_numbers: Vec<(i32, EnumValueIndex)>,
-    value_names: HashMap<Box<str>, EnumValueIndex>,
+    value_names: NameIndex<EnumValueIndex>,
     allow_alias: bool,
 }
 
@@ -291,12 +359,15 @@ struct EnumValueDescriptorInner {
 }
 
 /// A protobuf service definition.
+#[cfg(feature = "services")]
+#[cfg_attr(docsrs, doc(cfg(feature = "services")))]
 #[derive(Clone, PartialEq, Eq)]
 pub struct ServiceDescriptor {
     pool: DescriptorPool,
     index: ServiceIndex,
 }
 
+#[cfg(feature = "services")]
 #[derive(Clone)]
 struct ServiceDescriptorInner {
     id: Identity,
@@ -304,12 +375,15 @@ struct ServiceDescriptorInner {
 }
 
 /// A method definition for a [`ServiceDescriptor`].
+#[cfg(feature = "services")]
+#[cfg_attr(docsrs, doc(cfg(feature = "services")))]
 #[derive(Clone, PartialEq, Eq)]
 pub struct MethodDescriptor {
     service: ServiceDescriptor,
     index: MethodIndex,
 }
 
+#[cfg(feature = "services")]
 #[derive(Clone)]
 struct MethodDescriptorInner {
     id: Identity,
@@ -317,15 +391,39 @@ struct MethodDescriptorInner {
     output: MessageIndex,
 }
 
+impl Interner {
+    fn intern(&mut self, name: &str) -> Arc<str> {
+        match self.names.get(name) {
+            Some(name) => name.clone(),
+            None => {
+                let name: Arc<str> = name.into();
+                self.names.insert(name.clone());
+                name
+            }
+        }
+    }
+
+    /// Clears the names used by the current build.
+    fn finish(&mut self) {
+        self.names = HashSet::new();
+    }
+}
+
 impl Identity {
-    fn new(file: FileIndex, path: &[i32], full_name: &str, name: &str) -> Identity {
+    fn new(
+        interner: &mut Interner,
+        file: FileIndex,
+        path: &[i32],
+        full_name: &str,
+        name: &str,
+    ) -> Identity {
         debug_assert!(full_name.ends_with(name));
         let name_index = full_name.len() - name.len();
         debug_assert!(name_index == 0 || full_name.as_bytes()[name_index - 1] == b'.');
         Identity {
             file,
             path: path.into(),
-            full_name: full_name.into(),
+            full_name: interner.intern(full_name),
             name_index,
         }
     }
@@ -433,6 +531,23 @@ fn find_enum_proto<'a>(file: &'a FileDescriptorProto, path: &[i32]) -> &'a EnumD
     }
 }
 
+/// Finds the location of the element at `path` in the source code info of a file, as
+/// `[start_line, start_column, end_line, end_column]`.
+#[cfg(any(feature = "lint", feature = "compat"))]
+fn find_source_span(file: &FileDescriptor, path: &[i32]) -> Option<[i32; 4]> {
+    file.file_descriptor_proto()
+        .source_code_info
+        .as_ref()
+        .and_then(|s| s.location.iter().find(|l| l.path == path))
+        .and_then(|l| match *l.span {
+            [start_line, start_col, end_col] => Some([start_line, start_col, start_line, end_col]),
+            [start_line, start_col, end_line, end_col] => {
+                Some([start_line, start_col, end_line, end_col])
+            }
+            _ => None,
+        })
+}
+
 #[test]
 fn assert_descriptor_send_sync() {
     fn test_send_sync<T: Send + Sync>() {}
@@ -0,0 +1,462 @@
+use std::fmt::{self, Write};
+
+#[cfg(feature = "services")]
+use crate::MethodDescriptor;
+use crate::{Cardinality, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor, Syntax, Value};
+
+/// The format of a document generated by [`MessageDescriptor::template`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateFormat {
+    /// The [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json), with
+    /// comments starting with `//`.
+    ///
+    /// Comments are not part of standard JSON, so they must be removed before parsing the
+    /// document with a strict parser.
+    Json,
+    /// The [text format](https://protobuf.dev/reference/protobuf/textformat-spec/), with comments
+    /// starting with `#`.
+    TextFormat,
+}
+
+struct TemplateWriter {
+    format: TemplateFormat,
+    output: String,
+    indent: usize,
+    /// The full names of the messages currently being expanded, used to detect recursion.
+    stack: Vec<String>,
+}
+
+impl MessageDescriptor {
+    /// Generates a skeleton document for this message type, in which every field is set to an
+    /// example value and preceded by a comment describing its type.
+    ///
+    /// Each comment gives the field's cardinality, its type, the oneof containing it if any, and
+    /// the possible values of enum fields. Fields are set to their default value, lists contain a
+    /// single element and maps contain a single entry. Only the first field of each oneof is set,
+    /// with the remaining alternatives commented out. Fields of message types which are
+    /// already being expanded are left empty, to avoid infinite recursion.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::DescriptorPool;
+    /// use prost_reflect::TemplateFormat;
+    ///
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// let message_descriptor = pool.get_message_by_name("package.MyList").unwrap();
+    /// assert_eq!(
+    ///     message_descriptor.template(TemplateFormat::TextFormat),
+    ///     "# repeated int32\nvalues: 0\n# repeated package.MyMessage\nmessages {\n  # int32\n  foo: 0\n  # int32 (oneof optional)\n  bar: 0\n  # package.MyMessage\n  nested {}\n}\n",
+    /// );
+    /// ```
+    pub fn template(&self, format: TemplateFormat) -> String {
+        let mut writer = TemplateWriter {
+            format,
+            output: String::new(),
+            indent: 0,
+            stack: Vec::new(),
+        };
+        match format {
+            TemplateFormat::Json => {
+                writer.json_message(self);
+                writer.output.push('\n');
+            }
+            TemplateFormat::TextFormat => writer.text_fields(self),
+        }
+        writer.output
+    }
+}
+
+#[cfg(feature = "services")]
+impl MethodDescriptor {
+    /// Generates a skeleton document for the input type of this method, which can be filled in
+    /// to build a request.
+    ///
+    /// See [`MessageDescriptor::template`] for a description of the document.
+    pub fn request_template(&self, format: TemplateFormat) -> String {
+        self.input().template(format)
+    }
+}
+
+impl TemplateWriter {
+    fn json_message(&mut self, desc: &MessageDescriptor) {
+        if desc.fields().len() == 0 || self.is_expanding(desc) {
+            self.output.push_str("{}");
+            return;
+        }
+
+        self.stack.push(desc.full_name().to_owned());
+        self.output.push_str("{\n");
+        self.indent += 1;
+        let last_set = desc
+            .fields()
+            .enumerate()
+            .filter(|(_, field)| !is_oneof_alternative(field))
+            .map(|(index, _)| index)
+            .last();
+        for (index, field) in desc.fields().enumerate() {
+            self.comment(&field);
+            let start = self.output.len();
+            self.start_line();
+            write!(self.output, "\"{}\": ", field.json_name()).unwrap();
+            self.json_field_value(&field);
+            if is_oneof_alternative(&field) {
+                self.comment_out(start);
+            } else if Some(index) != last_set {
+                self.output.push(',');
+            }
+            self.output.push('\n');
+        }
+        self.indent -= 1;
+        self.start_line();
+        self.output.push('}');
+        self.stack.pop();
+    }
+
+    fn json_field_value(&mut self, field: &FieldDescriptor) {
+        if field.is_map() {
+            let entry = field.kind();
+            let entry = entry
+                .as_message()
+                .expect("map fields have an entry message");
+            let key = match Value::default_value(&entry.map_entry_key_field().kind()) {
+                Value::String(key) => key,
+                Value::Bool(key) => key.to_string(),
+                Value::I32(key) => key.to_string(),
+                Value::I64(key) => key.to_string(),
+                Value::U32(key) => key.to_string(),
+                Value::U64(key) => key.to_string(),
+                _ => unreachable!("invalid map key type"),
+            };
+
+            self.output.push_str("{\n");
+            self.indent += 1;
+            self.start_line();
+            self.output.push_str(&json_string(&key));
+            self.output.push_str(": ");
+            let value_field = entry.map_entry_value_field();
+            self.json_value(
+                &value_field.kind(),
+                &Value::default_value_for_field(&value_field),
+            );
+            self.output.push('\n');
+            self.indent -= 1;
+            self.start_line();
+            self.output.push('}');
+        } else if field.is_list() {
+            self.output.push_str("[\n");
+            self.indent += 1;
+            self.start_line();
+            self.json_value(&field.kind(), &Value::default_value(&field.kind()));
+            self.output.push('\n');
+            self.indent -= 1;
+            self.start_line();
+            self.output.push(']');
+        } else {
+            self.json_value(&field.kind(), &Value::default_value_for_field(field));
+        }
+    }
+
+    fn json_value(&mut self, kind: &Kind, default: &Value) {
+        match (kind, default) {
+            (Kind::Message(message), _) => match message.full_name() {
+                "google.protobuf.Timestamp" => self.output.push_str("\"1970-01-01T00:00:00Z\""),
+                "google.protobuf.Duration" => self.output.push_str("\"0s\""),
+                "google.protobuf.FieldMask" => self.output.push_str("\"\""),
+                "google.protobuf.Value" => self.output.push_str("null"),
+                "google.protobuf.ListValue" => self.output.push_str("[]"),
+                "google.protobuf.Struct" | "google.protobuf.Empty" => self.output.push_str("{}"),
+                "google.protobuf.Any" => self.output.push_str("{ \"@type\": \"\" }"),
+                "google.protobuf.BoolValue"
+                | "google.protobuf.StringValue"
+                | "google.protobuf.BytesValue"
+                | "google.protobuf.Int32Value"
+                | "google.protobuf.UInt32Value"
+                | "google.protobuf.Int64Value"
+                | "google.protobuf.UInt64Value"
+                | "google.protobuf.FloatValue"
+                | "google.protobuf.DoubleValue" => {
+                    let value_kind = message
+                        .get_field(1)
+                        .expect("wrapper types have a value field")
+                        .kind();
+                    self.json_value(&value_kind, &Value::default_value(&value_kind));
+                }
+                _ => self.json_message(message),
+            },
+            (Kind::Enum(enum_), _) if enum_.full_name() == "google.protobuf.NullValue" => {
+                self.output.push_str("null")
+            }
+            (Kind::Enum(enum_), Value::EnumNumber(number)) => match enum_.get_value(*number) {
+                Some(value) => self.output.push_str(&json_string(value.name())),
+                None => write!(self.output, "{}", number).unwrap(),
+            },
+            (_, Value::Bool(value)) => write!(self.output, "{}", value).unwrap(),
+            (_, Value::I32(value)) => write!(self.output, "{}", value).unwrap(),
+            (_, Value::U32(value)) => write!(self.output, "{}", value).unwrap(),
+            (_, Value::I64(value)) => write!(self.output, "\"{}\"", value).unwrap(),
+            (_, Value::U64(value)) => write!(self.output, "\"{}\"", value).unwrap(),
+            (_, Value::F32(value)) => self.json_float(*value),
+            (_, Value::F64(value)) => self.json_float(*value),
+            (_, Value::String(value)) => self.output.push_str(&json_string(value)),
+            // Non-empty default values of bytes fields would need to be base64-encoded.
+            (_, Value::Bytes(_)) => self.output.push_str("\"\""),
+            _ => unreachable!("default value does not match the field kind"),
+        }
+    }
+
+    fn json_float<T: Into<f64> + fmt::Display + Copy>(&mut self, value: T) {
+        let float: f64 = value.into();
+        if float.is_nan() {
+            self.output.push_str("\"NaN\"");
+        } else if float.is_infinite() && float > 0.0 {
+            self.output.push_str("\"Infinity\"");
+        } else if float.is_infinite() {
+            self.output.push_str("\"-Infinity\"");
+        } else {
+            write!(self.output, "{}", value).unwrap();
+        }
+    }
+
+    fn text_fields(&mut self, desc: &MessageDescriptor) {
+        self.stack.push(desc.full_name().to_owned());
+        for field in desc.fields() {
+            self.comment(&field);
+            let start = self.output.len();
+            self.start_line();
+            let name = match field.kind() {
+                Kind::Message(message) if field.is_group() => message.name().to_owned(),
+                _ => field.name().to_owned(),
+            };
+            self.output.push_str(&name);
+
+            if field.is_map() {
+                let entry = field.kind();
+                let entry = entry
+                    .as_message()
+                    .expect("map fields have an entry message");
+                self.output.push_str(" {\n");
+                self.indent += 1;
+                for entry_field in [entry.map_entry_key_field(), entry.map_entry_value_field()] {
+                    self.start_line();
+                    self.output.push_str(entry_field.name());
+                    self.text_value(
+                        &entry_field.kind(),
+                        &Value::default_value(&entry_field.kind()),
+                    );
+                    self.output.push('\n');
+                }
+                self.indent -= 1;
+                self.start_line();
+                self.output.push('}');
+            } else if field.is_list() {
+                self.text_value(&field.kind(), &Value::default_value(&field.kind()));
+            } else {
+                self.text_value(&field.kind(), &Value::default_value_for_field(&field));
+            }
+            if is_oneof_alternative(&field) {
+                self.comment_out(start);
+            }
+            self.output.push('\n');
+        }
+        self.stack.pop();
+    }
+
+    /// Writes the value of a field, including the separator between the field name and value.
+    fn text_value(&mut self, kind: &Kind, default: &Value) {
+        match (kind, default) {
+            (Kind::Message(message), _) => {
+                if message.fields().len() == 0 || self.is_expanding(message) {
+                    self.output.push_str(" {}");
+                } else {
+                    self.output.push_str(" {\n");
+                    self.indent += 1;
+                    self.text_fields(message);
+                    self.indent -= 1;
+                    self.start_line();
+                    self.output.push('}');
+                }
+            }
+            (Kind::Enum(enum_), Value::EnumNumber(number)) => match enum_.get_value(*number) {
+                Some(value) => write!(self.output, ": {}", value.name()).unwrap(),
+                None => write!(self.output, ": {}", number).unwrap(),
+            },
+            (_, Value::Bool(value)) => write!(self.output, ": {}", value).unwrap(),
+            (_, Value::I32(value)) => write!(self.output, ": {}", value).unwrap(),
+            (_, Value::U32(value)) => write!(self.output, ": {}", value).unwrap(),
+            (_, Value::I64(value)) => write!(self.output, ": {}", value).unwrap(),
+            (_, Value::U64(value)) => write!(self.output, ": {}", value).unwrap(),
+            (_, Value::F32(value)) => write!(self.output, ": {}", text_float(*value)).unwrap(),
+            (_, Value::F64(value)) => write!(self.output, ": {}", text_float(*value)).unwrap(),
+            (_, Value::String(value)) => {
+                write!(self.output, ": {}", text_string(value.as_bytes())).unwrap()
+            }
+            (_, Value::Bytes(value)) => write!(self.output, ": {}", text_string(value)).unwrap(),
+            _ => unreachable!("default value does not match the field kind"),
+        }
+    }
+
+    fn comment(&mut self, field: &FieldDescriptor) {
+        self.start_line();
+        let marker = self.comment_marker();
+        self.output.push_str(marker);
+
+        let is_proto3_optional = field.field_descriptor_proto().proto3_optional();
+        if field.cardinality() == Cardinality::Required {
+            self.output.push_str("required ");
+        } else if field.is_list() {
+            self.output.push_str("repeated ");
+        } else if is_proto3_optional
+            || (field.parent_file().syntax() == Syntax::Proto2
+                && field.containing_oneof().is_none())
+        {
+            self.output.push_str("optional ");
+        }
+
+        let value_kind = if field.is_map() {
+            let entry = field.kind();
+            let entry = entry
+                .as_message()
+                .expect("map fields have an entry message");
+            let value_kind = entry.map_entry_value_field().kind();
+            write!(
+                self.output,
+                "map<{}, {}>",
+                type_name(&entry.map_entry_key_field().kind()),
+                type_name(&value_kind)
+            )
+            .unwrap();
+            value_kind
+        } else {
+            let kind = field.kind();
+            self.output.push_str(&type_name(&kind));
+            kind
+        };
+
+        if let Some(oneof) = field.containing_oneof() {
+            if !is_proto3_optional {
+                write!(self.output, " (oneof {})", oneof.name()).unwrap();
+            }
+        }
+        if let Kind::Enum(enum_) = value_kind {
+            write!(self.output, ", one of: {}", enum_value_names(&enum_)).unwrap();
+        }
+        self.output.push('\n');
+    }
+
+    /// Turns everything written since `start` into comments, so only one field of each oneof is
+    /// set in the generated document.
+    fn comment_out(&mut self, start: usize) {
+        let marker = self.comment_marker();
+        let rendered = self.output.split_off(start);
+        for line in rendered.split_inclusive('\n') {
+            let content = line.trim_start_matches(' ');
+            self.output.push_str(&line[..line.len() - content.len()]);
+            self.output.push_str(marker);
+            self.output.push_str(content);
+        }
+    }
+
+    fn comment_marker(&self) -> &'static str {
+        match self.format {
+            TemplateFormat::Json => "// ",
+            TemplateFormat::TextFormat => "# ",
+        }
+    }
+
+    fn start_line(&mut self) {
+        for _ in 0..self.indent {
+            self.output.push_str("  ");
+        }
+    }
+
+    fn is_expanding(&self, desc: &MessageDescriptor) -> bool {
+        self.stack.iter().any(|name| name == desc.full_name())
+    }
+}
+
+/// Returns `true` if the field is part of a oneof but is not its first field.
+fn is_oneof_alternative(field: &FieldDescriptor) -> bool {
+    match field.containing_oneof() {
+        Some(oneof) => oneof.fields().next().as_ref() != Some(field),
+        None => false,
+    }
+}
+
+fn type_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Double => "double".to_owned(),
+        Kind::Float => "float".to_owned(),
+        Kind::Int32 => "int32".to_owned(),
+        Kind::Int64 => "int64".to_owned(),
+        Kind::Uint32 => "uint32".to_owned(),
+        Kind::Uint64 => "uint64".to_owned(),
+        Kind::Sint32 => "sint32".to_owned(),
+        Kind::Sint64 => "sint64".to_owned(),
+        Kind::Fixed32 => "fixed32".to_owned(),
+        Kind::Fixed64 => "fixed64".to_owned(),
+        Kind::Sfixed32 => "sfixed32".to_owned(),
+        Kind::Sfixed64 => "sfixed64".to_owned(),
+        Kind::Bool => "bool".to_owned(),
+        Kind::String => "string".to_owned(),
+        Kind::Bytes => "bytes".to_owned(),
+        Kind::Message(message) => message.full_name().to_owned(),
+        Kind::Enum(enum_) => enum_.full_name().to_owned(),
+    }
+}
+
+fn enum_value_names(desc: &EnumDescriptor) -> String {
+    desc.values()
+        .map(|value| value.name().to_owned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            ch if ch.is_control() => write!(result, "\\u{:04x}", ch as u32).unwrap(),
+            ch => result.push(ch),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn text_string(value: &[u8]) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for &byte in value {
+        match byte {
+            b'"' => result.push_str("\\\""),
+            b'\\' => result.push_str("\\\\"),
+            b'\n' => result.push_str("\\n"),
+            b'\r' => result.push_str("\\r"),
+            b'\t' => result.push_str("\\t"),
+            0x20..=0x7e => result.push(byte as char),
+            byte => write!(result, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn text_float<T: Into<f64> + fmt::Display + Copy>(value: T) -> String {
+    let float: f64 = value.into();
+    if float.is_nan() {
+        "nan".to_owned()
+    } else if float.is_infinite() && float > 0.0 {
+        "inf".to_owned()
+    } else if float.is_infinite() {
+        "-inf".to_owned()
+    } else {
+        value.to_string()
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use prost_types::{
     field_descriptor_proto::{self, Label, Type},
     DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
@@ -7,6 +9,7 @@ use prost_types::{
 use crate::DescriptorPool;
 
 #[test]
+#[cfg(feature = "services")]
 fn resolve_service_name() {
     let file_descriptor_set = FileDescriptorSet {
         file: vec![FileDescriptorProto {
@@ -39,6 +42,7 @@ fn resolve_service_name() {
 }
 
 #[test]
+#[cfg(feature = "services")]
 fn resolve_service_name_other_package() {
     let file_descriptor_set = FileDescriptorSet {
         file: vec![
@@ -329,6 +333,7 @@ fn add_conflicting_duplicate_file() {
 }
 
 #[test]
+#[cfg(feature = "services")]
 fn add_file_rollback_on_error() {
     let bad_file_descriptor_set = FileDescriptorSet {
         file: vec![FileDescriptorProto {
@@ -365,6 +370,35 @@ fn add_file_rollback_on_error() {
     assert_eq!(pool.get_message_by_name(".my.package.MyMessage"), None);
 }
 
+#[test]
+#[cfg(not(feature = "services"))]
+fn services_are_not_resolved() {
+    let file_descriptor_set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("myfile.proto".to_owned()),
+            package: Some("my.package".to_owned()),
+            syntax: Some("proto3".to_owned()),
+            service: vec![ServiceDescriptorProto {
+                name: Some("MyService".to_owned()),
+                method: vec![MethodDescriptorProto {
+                    name: Some("my_method".to_owned()),
+                    input_type: Some(".my.package.NopeMessage".to_owned()),
+                    output_type: Some(".my.package.NopeMessage".to_owned()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    };
+
+    let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set).unwrap();
+    assert_eq!(
+        pool.file_descriptor_protos().next().unwrap().service.len(),
+        1
+    );
+}
+
 #[test]
 fn add_file_missing_dependency() {
     let bad_file_descriptor_set = FileDescriptorSet {
@@ -389,6 +423,7 @@ fn add_file_missing_dependency() {
 }
 
 #[test]
+#[cfg(feature = "services")]
 fn service_method_type_not_message() {
     let file_descriptor_set = FileDescriptorSet {
         file: vec![FileDescriptorProto {
@@ -458,3 +493,79 @@ fn extension_extendee_type_not_message() {
         "'my.package.MyMessage' is not a message type"
     );
 }
+
+#[test]
+fn names_are_shared_within_build() {
+    let message = |name: &str| DescriptorProto {
+        name: Some(name.to_owned()),
+        field: vec![FieldDescriptorProto {
+            name: Some("my_field".to_owned()),
+            number: Some(1),
+            label: Some(Label::Optional as i32),
+            r#type: Some(Type::Int32 as i32),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let file_descriptor_set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("shared_names.proto".to_owned()),
+            package: Some("shared.names".to_owned()),
+            syntax: Some("proto3".to_owned()),
+            message_type: vec![message("MyMessage"), message("OtherMessage")],
+            ..Default::default()
+        }],
+    };
+
+    let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set).unwrap();
+
+    let message1 = &pool.inner.messages[0];
+    let message2 = &pool.inner.messages[1];
+    assert!(Arc::ptr_eq(
+        &message1.fields[0].json_name,
+        &message2.fields[0].json_name
+    ));
+    let (name, _) = pool
+        .inner
+        .names
+        .get_key_value(message1.id.full_name.as_ref())
+        .unwrap();
+    assert!(Arc::ptr_eq(name, &message1.id.full_name));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn json_names_take_priority_over_names() {
+    let field = |name: &str, number: i32, json_name: &str| FieldDescriptorProto {
+        name: Some(name.to_owned()),
+        number: Some(number),
+        label: Some(Label::Optional as i32),
+        r#type: Some(Type::Int32 as i32),
+        json_name: Some(json_name.to_owned()),
+        ..Default::default()
+    };
+    let file_descriptor_set = FileDescriptorSet {
+        file: vec![FileDescriptorProto {
+            name: Some("json_names.proto".to_owned()),
+            package: Some("json.names".to_owned()),
+            syntax: Some("proto3".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("MyMessage".to_owned()),
+                field: vec![field("b", 1, "c"), field("a", 2, "b")],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    };
+
+    let pool = DescriptorPool::from_file_descriptor_set(file_descriptor_set).unwrap();
+    let message = pool.get_message_by_name("json.names.MyMessage").unwrap();
+
+    assert_eq!(message.get_field_by_name("b").unwrap().number(), 1);
+    assert_eq!(message.get_field_by_json_name("b").unwrap().number(), 2);
+    assert_eq!(message.get_field_by_json_name("a"), None);
+    assert_eq!(message.get_field_by_json_key("a").unwrap().number(), 2);
+    assert_eq!(message.get_field_by_json_key("b").unwrap().number(), 2);
+    assert_eq!(message.get_field_by_json_key("c").unwrap().number(), 1);
+    assert_eq!(message.get_field_by_json_key("d"), None);
+}
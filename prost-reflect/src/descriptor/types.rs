@@ -1,7 +1,7 @@
 use std::fmt;
 
 use prost::{
-    bytes::{Buf, BufMut},
+    bytes::{Buf, BufMut, Bytes},
     encoding::{encode_key, skip_field, DecodeContext, WireType},
     DecodeError, Message,
 };
@@ -18,6 +18,13 @@ pub(crate) struct FileDescriptorSet {
     pub file: Vec<FileDescriptorProto>,
 }
 
+/// A `FileDescriptorSet` whose files have not been decoded yet.
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct EncodedFileDescriptorSet {
+    #[prost(bytes = "bytes", repeated, tag = "1")]
+    pub file: Vec<Bytes>,
+}
+
 #[derive(Clone, PartialEq, Message)]
 pub(crate) struct FileDescriptorProto {
     #[prost(string, optional, tag = "1")]
@@ -176,6 +183,31 @@ pub(crate) struct Options<T> {
     pub(crate) value: T,
 }
 
+impl FileDescriptorSet {
+    /// Decodes the files in an encoded `FileDescriptorSet`. If the `rayon` feature is enabled,
+    /// the files are decoded in parallel.
+    pub(crate) fn decode_files<B>(buf: B) -> Result<Vec<FileDescriptorProto>, DecodeError>
+    where
+        B: Buf,
+    {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon1::prelude::*;
+
+            let mut buf = buf;
+            EncodedFileDescriptorSet::decode(buf.copy_to_bytes(buf.remaining()))?
+                .file
+                .into_par_iter()
+                .map(FileDescriptorProto::decode)
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        {
+            FileDescriptorSet::decode(buf).map(|file_descriptor_set| file_descriptor_set.file)
+        }
+    }
+}
+
 impl FileDescriptorProto {
     pub(crate) fn from_prost(file: prost_types::FileDescriptorProto) -> FileDescriptorProto {
         FileDescriptorProto {
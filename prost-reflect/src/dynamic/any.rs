@@ -0,0 +1,170 @@
+use std::{error::Error, fmt};
+
+use prost::{bytes::Bytes, DecodeError, Message};
+
+use crate::{
+    descriptor::GOOGLE_APIS_DOMAIN, DynamicMessage, MessageDescriptor, ReflectMessage,
+    TypeResolver, Value,
+};
+
+const ANY_NAME: &str = "google.protobuf.Any";
+const TYPE_URL_NUMBER: u32 = 1;
+const VALUE_NUMBER: u32 = 2;
+
+/// An error that occurred while unpacking a `google.protobuf.Any` message.
+#[derive(Debug)]
+pub struct AnyError {
+    kind: AnyErrorKind,
+}
+
+#[derive(Debug)]
+enum AnyErrorKind {
+    NotAny { name: String },
+    UnknownType { type_url: String },
+    TypeMismatch { expected: String, type_url: String },
+    Decode(DecodeError),
+}
+
+impl DynamicMessage {
+    /// Packs this message into a `google.protobuf.Any` message.
+    ///
+    /// The type URL is the full name of this message's type, prefixed with `type.googleapis.com/`.
+    /// The `Any` message type is taken from the [`DescriptorPool`][crate::DescriptorPool] of this
+    /// message if it is defined there, or from the built-in well-known types otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut message = DynamicMessage::new(message_descriptor.clone());
+    /// message.set_field_by_name("foo", Value::I32(150));
+    ///
+    /// let any = message.pack_any();
+    /// assert_eq!(
+    ///     any.get_field_by_name("type_url").unwrap().as_str(),
+    ///     Some("type.googleapis.com/package.MyMessage"),
+    /// );
+    /// assert_eq!(any.unpack_any().unwrap(), message);
+    /// assert_eq!(any.unpack_any_as(message_descriptor).unwrap(), message);
+    /// ```
+    pub fn pack_any(&self) -> DynamicMessage {
+        let any_desc = self
+            .desc
+            .parent_pool()
+            .get_message_by_name(ANY_NAME)
+            .unwrap_or_else(|| prost_types::Any::default().descriptor());
+
+        let mut any = DynamicMessage::new(any_desc);
+        any.set_field_by_number(
+            TYPE_URL_NUMBER,
+            Value::String(format!("{}{}", GOOGLE_APIS_DOMAIN, self.desc.full_name())),
+        );
+        any.set_field_by_number(VALUE_NUMBER, Value::Bytes(self.encode_to_vec().into()));
+        any
+    }
+
+    /// Unpacks the payload of this `google.protobuf.Any` message.
+    ///
+    /// The payload type is looked up from the type URL in the
+    /// [`DescriptorPool`][crate::DescriptorPool] of this message, using the type URL prefixes
+    /// `type.googleapis.com/` and `type.googleprod.com/`. Use [`unpack_any_with_resolver`][Self::unpack_any_with_resolver] to
+    /// look up types elsewhere.
+    ///
+    /// An error is returned if this message is not of type `google.protobuf.Any`, the payload type
+    /// is not found, or the payload cannot be decoded.
+    pub fn unpack_any(&self) -> Result<DynamicMessage, AnyError> {
+        self.unpack_any_with_resolver(self.desc.parent_pool())
+    }
+
+    /// Unpacks the payload of this `google.protobuf.Any` message, looking up its type using the
+    /// given [`TypeResolver`].
+    ///
+    /// See [`unpack_any`][Self::unpack_any] for details.
+    pub fn unpack_any_with_resolver(
+        &self,
+        resolver: &dyn TypeResolver,
+    ) -> Result<DynamicMessage, AnyError> {
+        let (type_url, value) = self.any_fields()?;
+        let desc = match resolver.resolve_message_type(&type_url) {
+            Some(desc) => desc,
+            None => return Err(AnyErrorKind::UnknownType { type_url }.into()),
+        };
+        DynamicMessage::decode(desc, value).map_err(|err| AnyErrorKind::Decode(err).into())
+    }
+
+    /// Unpacks the payload of this `google.protobuf.Any` message as a message of type `desc`.
+    ///
+    /// The type URL must end with the full name of `desc`, after the last `/`, but its prefix is
+    /// not checked. An error is returned if this message is not of type `google.protobuf.Any`, the
+    /// type URL names a different type, or the payload cannot be decoded.
+    pub fn unpack_any_as(&self, desc: MessageDescriptor) -> Result<DynamicMessage, AnyError> {
+        let (type_url, value) = self.any_fields()?;
+        let type_name = type_url.rsplit('/').next().unwrap_or_default();
+        if type_name != desc.full_name() {
+            return Err(AnyErrorKind::TypeMismatch {
+                expected: desc.full_name().to_owned(),
+                type_url,
+            }
+            .into());
+        }
+        DynamicMessage::decode(desc, value).map_err(|err| AnyErrorKind::Decode(err).into())
+    }
+
+    /// Gets the type URL and payload of this message, if it is of type `google.protobuf.Any`.
+    fn any_fields(&self) -> Result<(String, Bytes), AnyError> {
+        if self.desc.full_name() != ANY_NAME {
+            return Err(AnyErrorKind::NotAny {
+                name: self.desc.full_name().to_owned(),
+            }
+            .into());
+        }
+
+        let type_url = self
+            .get_field_by_number(TYPE_URL_NUMBER)
+            .and_then(|value| value.as_str().map(ToOwned::to_owned))
+            .unwrap_or_default();
+        let value = self
+            .get_field_by_number(VALUE_NUMBER)
+            .and_then(|value| value.as_bytes().cloned())
+            .unwrap_or_default();
+        Ok((type_url, value))
+    }
+}
+
+impl From<AnyErrorKind> for AnyError {
+    fn from(kind: AnyErrorKind) -> Self {
+        AnyError { kind }
+    }
+}
+
+impl fmt::Display for AnyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            AnyErrorKind::NotAny { name } => write!(
+                f,
+                "expected a message of type '{}', but found '{}'",
+                ANY_NAME, name
+            ),
+            AnyErrorKind::UnknownType { type_url } => {
+                write!(f, "no message type found for type URL '{}'", type_url)
+            }
+            AnyErrorKind::TypeMismatch { expected, type_url } => write!(
+                f,
+                "expected a payload of type '{}', but the type URL is '{}'",
+                expected, type_url
+            ),
+            AnyErrorKind::Decode(err) => write!(f, "failed to decode payload: {}", err),
+        }
+    }
+}
+
+impl Error for AnyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            AnyErrorKind::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,550 @@
+use std::{borrow::Cow, sync::Arc};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{
+        DurationNanosecondType, Float32Type, Float64Type, Int32Type, Int64Type,
+        TimestampNanosecondType, UInt32Type, UInt64Type,
+    },
+    Array, ArrayRef, BinaryArray, BooleanArray, DurationNanosecondArray, Float32Array,
+    Float64Array, Int32Array, Int64Array, ListArray, MapArray, RecordBatch, RecordBatchOptions,
+    StringArray, StructArray, TimestampNanosecondArray, UInt32Array, UInt64Array,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer};
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Fields, Schema, TimeUnit};
+use prost::bytes::Bytes;
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// The representation of a message type in an Arrow schema.
+enum MessageType {
+    Struct,
+    Timestamp,
+    Duration,
+    Wrapper(FieldDescriptor),
+    Json,
+}
+
+/// Returns the Arrow schema for a record batch of messages of type `desc`.
+///
+/// See the [module documentation](self) for a description of the mapping. Returns an error if
+/// `desc` is recursive, since a recursive message type has no finite Arrow representation.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::DescriptorPool;
+/// use arrow_schema::DataType;
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let schema = prost_reflect::arrow::schema(&message_descriptor).unwrap();
+/// assert_eq!(schema.field(0).name(), "type_url");
+/// assert_eq!(schema.field(0).data_type(), &DataType::Utf8);
+/// assert_eq!(schema.field(1).data_type(), &DataType::Binary);
+///
+/// let recursive = pool.get_message_by_name("package.MyMessage").unwrap();
+/// assert!(prost_reflect::arrow::schema(&recursive).is_err());
+/// ```
+pub fn schema(desc: &MessageDescriptor) -> Result<Schema, ArrowError> {
+    Ok(Schema::new(message_fields(desc, &mut vec![desc.clone()])?))
+}
+
+/// Converts a batch of messages of type `desc` to an Arrow [`RecordBatch`].
+///
+/// The batch has the schema returned by [`schema`], and one row for each message. Returns an
+/// error if any message is not of type `desc`, or if a value cannot be represented, such as a
+/// timestamp outside the range of a 64-bit count of nanoseconds.
+pub fn to_record_batch(
+    desc: &MessageDescriptor,
+    messages: &[DynamicMessage],
+) -> Result<RecordBatch, ArrowError> {
+    let schema = Arc::new(schema(desc)?);
+    if let Some(message) = messages
+        .iter()
+        .find(|message| message.descriptor() != *desc)
+    {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "expected messages of type '{}', but found '{}'",
+            desc.full_name(),
+            message.descriptor().full_name()
+        )));
+    }
+
+    let rows: Vec<&DynamicMessage> = messages.iter().collect();
+    let columns = build_fields(desc, schema.fields(), &rows)?;
+    RecordBatch::try_new_with_options(
+        schema,
+        columns,
+        &RecordBatchOptions::new().with_row_count(Some(messages.len())),
+    )
+}
+
+/// Converts an Arrow [`RecordBatch`] to messages of type `desc`, one for each row.
+///
+/// Columns are matched to fields by name, and must have the data type given by [`schema`]. Fields
+/// without a matching column are left unset, and columns which do not match a field are ignored.
+pub fn from_record_batch(
+    desc: &MessageDescriptor,
+    batch: &RecordBatch,
+) -> Result<Vec<DynamicMessage>, ArrowError> {
+    let mut messages = vec![DynamicMessage::new(desc.clone()); batch.num_rows()];
+    for field in desc.fields() {
+        let column = match batch.column_by_name(field.name()) {
+            Some(column) => column,
+            None => continue,
+        };
+
+        let expected = field_data_type(&field, &mut vec![desc.clone()])?;
+        if column.data_type() != &expected {
+            return Err(ArrowError::SchemaError(format!(
+                "expected column '{}' to have type {}, but found {}",
+                field.name(),
+                expected,
+                column.data_type()
+            )));
+        }
+
+        for (row, message) in messages.iter_mut().enumerate() {
+            if let Some(value) = read_field(&field, column.as_ref(), row)? {
+                message.set_field(&field, value);
+            }
+        }
+    }
+    Ok(messages)
+}
+
+fn message_type(desc: &MessageDescriptor) -> MessageType {
+    match desc.full_name() {
+        "google.protobuf.Timestamp" => MessageType::Timestamp,
+        "google.protobuf.Duration" => MessageType::Duration,
+        "google.protobuf.DoubleValue"
+        | "google.protobuf.FloatValue"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value"
+        | "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.BoolValue"
+        | "google.protobuf.StringValue"
+        | "google.protobuf.BytesValue" => match desc.get_field(1) {
+            Some(field) => MessageType::Wrapper(field),
+            None => MessageType::Struct,
+        },
+        "google.protobuf.Struct" | "google.protobuf.Value" | "google.protobuf.ListValue" => {
+            MessageType::Json
+        }
+        _ => MessageType::Struct,
+    }
+}
+
+fn message_fields(
+    desc: &MessageDescriptor,
+    stack: &mut Vec<MessageDescriptor>,
+) -> Result<Fields, ArrowError> {
+    desc.fields()
+        .map(|field| {
+            Ok(Field::new(
+                field.name(),
+                field_data_type(&field, stack)?,
+                field.supports_presence(),
+            ))
+        })
+        .collect()
+}
+
+fn field_data_type(
+    field: &FieldDescriptor,
+    stack: &mut Vec<MessageDescriptor>,
+) -> Result<DataType, ArrowError> {
+    if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message().expect("map entry should be a message");
+        let key = kind_data_type(&entry.map_entry_key_field().kind(), stack)?;
+        let value = kind_data_type(&entry.map_entry_value_field().kind(), stack)?;
+        let entries = Field::new(
+            "entries",
+            DataType::Struct(Fields::from(vec![
+                Field::new("key", key, false),
+                Field::new("value", value, false),
+            ])),
+            false,
+        );
+        Ok(DataType::Map(Arc::new(entries), false))
+    } else if field.is_list() {
+        let item = kind_data_type(&field.kind(), stack)?;
+        Ok(DataType::List(Arc::new(Field::new("item", item, false))))
+    } else {
+        kind_data_type(&field.kind(), stack)
+    }
+}
+
+fn kind_data_type(kind: &Kind, stack: &mut Vec<MessageDescriptor>) -> Result<DataType, ArrowError> {
+    Ok(match kind {
+        Kind::Double => DataType::Float64,
+        Kind::Float => DataType::Float32,
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 | Kind::Enum(_) => DataType::Int32,
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => DataType::Int64,
+        Kind::Uint32 | Kind::Fixed32 => DataType::UInt32,
+        Kind::Uint64 | Kind::Fixed64 => DataType::UInt64,
+        Kind::Bool => DataType::Boolean,
+        Kind::String => DataType::Utf8,
+        Kind::Bytes => DataType::Binary,
+        Kind::Message(desc) => match message_type(desc) {
+            MessageType::Timestamp => DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+            MessageType::Duration => DataType::Duration(TimeUnit::Nanosecond),
+            MessageType::Wrapper(field) => kind_data_type(&field.kind(), stack)?,
+            MessageType::Json => DataType::Utf8,
+            MessageType::Struct => {
+                if stack.contains(desc) {
+                    return Err(ArrowError::SchemaError(format!(
+                        "message '{}' is recursive and cannot be represented in an arrow schema",
+                        desc.full_name()
+                    )));
+                }
+
+                stack.push(desc.clone());
+                let fields = message_fields(desc, stack)?;
+                stack.pop();
+                DataType::Struct(fields)
+            }
+        },
+    })
+}
+
+fn build_fields(
+    desc: &MessageDescriptor,
+    fields: &Fields,
+    rows: &[&DynamicMessage],
+) -> Result<Vec<ArrayRef>, ArrowError> {
+    desc.fields()
+        .zip(fields)
+        .map(|(field_desc, field)| {
+            let values: Vec<Option<Cow<Value>>> = rows
+                .iter()
+                .map(|message| {
+                    if field_desc.supports_presence() && !message.has_field(&field_desc) {
+                        None
+                    } else {
+                        Some(message.get_field(&field_desc))
+                    }
+                })
+                .collect();
+            let values: Vec<Option<&Value>> = values.iter().map(|value| value.as_deref()).collect();
+
+            match field.data_type() {
+                DataType::Map(entries, _) if field_desc.is_map() => {
+                    build_map(&field_desc, entries, &values)
+                }
+                DataType::List(item) if field_desc.is_list() => {
+                    build_list(&field_desc.kind(), item, &values)
+                }
+                data_type => build_kind(&field_desc.kind(), data_type, &values),
+            }
+        })
+        .collect()
+}
+
+fn build_list(
+    kind: &Kind,
+    item: &FieldRef,
+    values: &[Option<&Value>],
+) -> Result<ArrayRef, ArrowError> {
+    let lists: Vec<&[Value]> = values
+        .iter()
+        .map(|value| value.and_then(Value::as_list).unwrap_or_default())
+        .collect();
+    let offsets = OffsetBuffer::from_lengths(lists.iter().map(|list| list.len()));
+    let items: Vec<Option<&Value>> = lists
+        .iter()
+        .flat_map(|list| list.iter().map(Some))
+        .collect();
+
+    let items = build_kind(kind, item.data_type(), &items)?;
+    Ok(Arc::new(ListArray::try_new(
+        item.clone(),
+        offsets,
+        items,
+        None,
+    )?))
+}
+
+fn build_map(
+    field: &FieldDescriptor,
+    entries: &FieldRef,
+    values: &[Option<&Value>],
+) -> Result<ArrayRef, ArrowError> {
+    let entry_fields = match entries.data_type() {
+        DataType::Struct(fields) => fields,
+        _ => unreachable!("map entries should be a struct"),
+    };
+    let entry_desc = field.kind();
+    let entry_desc = entry_desc
+        .as_message()
+        .expect("map entry should be a message");
+
+    let mut lengths = Vec::with_capacity(values.len());
+    let mut keys = Vec::new();
+    let mut map_values = Vec::new();
+    for value in values {
+        let mut map: Vec<_> = value
+            .and_then(Value::as_map)
+            .map(|map| map.iter().collect())
+            .unwrap_or_default();
+        // Sort entries so the output does not depend on the iteration order of the map.
+        map.sort_by_key(|(key, _)| *key);
+
+        lengths.push(map.len());
+        for (key, value) in map {
+            keys.push(Value::from(key.clone()));
+            map_values.push(Some(value));
+        }
+    }
+    let keys: Vec<Option<&Value>> = keys.iter().map(Some).collect();
+
+    let keys = build_kind(
+        &entry_desc.map_entry_key_field().kind(),
+        entry_fields[0].data_type(),
+        &keys,
+    )?;
+    let map_values = build_kind(
+        &entry_desc.map_entry_value_field().kind(),
+        entry_fields[1].data_type(),
+        &map_values,
+    )?;
+    let entries_array = StructArray::try_new(entry_fields.clone(), vec![keys, map_values], None)?;
+    Ok(Arc::new(MapArray::try_new(
+        entries.clone(),
+        OffsetBuffer::from_lengths(lengths),
+        entries_array,
+        None,
+        false,
+    )?))
+}
+
+fn build_kind(
+    kind: &Kind,
+    data_type: &DataType,
+    values: &[Option<&Value>],
+) -> Result<ArrayRef, ArrowError> {
+    Ok(match kind {
+        Kind::Double => Arc::new(Float64Array::from(collect(values, Value::as_f64))),
+        Kind::Float => Arc::new(Float32Array::from(collect(values, Value::as_f32))),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            Arc::new(Int32Array::from(collect(values, Value::as_i32)))
+        }
+        Kind::Enum(_) => Arc::new(Int32Array::from(collect(values, Value::as_enum_number))),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            Arc::new(Int64Array::from(collect(values, Value::as_i64)))
+        }
+        Kind::Uint32 | Kind::Fixed32 => Arc::new(UInt32Array::from(collect(values, Value::as_u32))),
+        Kind::Uint64 | Kind::Fixed64 => Arc::new(UInt64Array::from(collect(values, Value::as_u64))),
+        Kind::Bool => Arc::new(BooleanArray::from(collect(values, Value::as_bool))),
+        Kind::String => Arc::new(StringArray::from(collect(values, Value::as_str))),
+        Kind::Bytes => Arc::new(BinaryArray::from_opt_vec(collect(values, |value| {
+            value.as_bytes().map(Bytes::as_ref)
+        }))),
+        Kind::Message(desc) => {
+            let messages = collect(values, Value::as_message);
+            build_message(desc, data_type, &messages)?
+        }
+    })
+}
+
+fn build_message(
+    desc: &MessageDescriptor,
+    data_type: &DataType,
+    messages: &[Option<&DynamicMessage>],
+) -> Result<ArrayRef, ArrowError> {
+    Ok(match message_type(desc) {
+        MessageType::Timestamp => {
+            let nanos = messages
+                .iter()
+                .map(|message| message.map(to_nanos).transpose())
+                .collect::<Result<Vec<_>, _>>()?;
+            Arc::new(TimestampNanosecondArray::from(nanos).with_timezone("UTC"))
+        }
+        MessageType::Duration => {
+            let nanos = messages
+                .iter()
+                .map(|message| message.map(to_nanos).transpose())
+                .collect::<Result<Vec<_>, _>>()?;
+            Arc::new(DurationNanosecondArray::from(nanos))
+        }
+        MessageType::Wrapper(field) => {
+            let values: Vec<Option<Cow<Value>>> = messages
+                .iter()
+                .map(|message| message.map(|message| message.get_field(&field)))
+                .collect();
+            let values: Vec<Option<&Value>> = values.iter().map(|value| value.as_deref()).collect();
+            build_kind(&field.kind(), data_type, &values)?
+        }
+        MessageType::Json => {
+            let json = messages
+                .iter()
+                .map(|message| message.map(serde_json::to_string).transpose())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| ArrowError::ExternalError(Box::new(err)))?;
+            Arc::new(StringArray::from(json))
+        }
+        MessageType::Struct => {
+            let fields = match data_type {
+                DataType::Struct(fields) => fields,
+                _ => unreachable!("message should be a struct"),
+            };
+
+            // Fields of null messages are taken from an empty message, since non-nullable
+            // children of a struct may not themselves be null.
+            let empty = DynamicMessage::new(desc.clone());
+            let rows: Vec<&DynamicMessage> = messages
+                .iter()
+                .map(|message| message.unwrap_or(&empty))
+                .collect();
+            let nulls = null_buffer(messages);
+
+            if fields.is_empty() {
+                Arc::new(StructArray::new_empty_fields(messages.len(), nulls))
+            } else {
+                let columns = build_fields(desc, fields, &rows)?;
+                Arc::new(StructArray::try_new(fields.clone(), columns, nulls)?)
+            }
+        }
+    })
+}
+
+fn collect<'a, T>(
+    values: &[Option<&'a Value>],
+    f: impl Fn(&'a Value) -> Option<T>,
+) -> Vec<Option<T>> {
+    values.iter().map(|value| value.and_then(&f)).collect()
+}
+
+fn null_buffer<T>(values: &[Option<T>]) -> Option<NullBuffer> {
+    if values.iter().all(Option::is_some) {
+        None
+    } else {
+        Some(NullBuffer::from(
+            values.iter().map(Option::is_some).collect::<Vec<bool>>(),
+        ))
+    }
+}
+
+/// Converts a `google.protobuf.Timestamp` or `google.protobuf.Duration` message to nanoseconds.
+fn to_nanos(message: &DynamicMessage) -> Result<i64, ArrowError> {
+    let seconds = message
+        .get_field_by_number(1)
+        .and_then(|value| value.as_i64())
+        .unwrap_or_default();
+    let nanos = message
+        .get_field_by_number(2)
+        .and_then(|value| value.as_i32())
+        .unwrap_or_default();
+    seconds
+        .checked_mul(NANOS_PER_SECOND)
+        .and_then(|value| value.checked_add(nanos.into()))
+        .ok_or_else(|| {
+            ArrowError::InvalidArgumentError(format!(
+                "{} is out of range for nanosecond precision",
+                message.descriptor().full_name()
+            ))
+        })
+}
+
+fn read_field(
+    field: &FieldDescriptor,
+    array: &dyn Array,
+    row: usize,
+) -> Result<Option<Value>, ArrowError> {
+    if array.is_null(row) {
+        return Ok(None);
+    }
+
+    if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message().expect("map entry should be a message");
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+
+        let entries = array.as_map().value(row);
+        let (keys, values) = (entries.column(0), entries.column(1));
+        let map = (0..entries.len())
+            .map(|index| {
+                let key = read_kind(&key_kind, keys.as_ref(), index)?
+                    .into_map_key()
+                    .expect("map key should be a valid key type");
+                Ok((key, read_kind(&value_kind, values.as_ref(), index)?))
+            })
+            .collect::<Result<_, ArrowError>>()?;
+        Ok(Some(Value::Map(map)))
+    } else if field.is_list() {
+        let kind = field.kind();
+        let items = array.as_list::<i32>().value(row);
+        let list = (0..items.len())
+            .map(|index| read_kind(&kind, items.as_ref(), index))
+            .collect::<Result<_, ArrowError>>()?;
+        Ok(Some(Value::List(list)))
+    } else {
+        read_kind(&field.kind(), array, row).map(Some)
+    }
+}
+
+fn read_kind(kind: &Kind, array: &dyn Array, row: usize) -> Result<Value, ArrowError> {
+    Ok(match kind {
+        Kind::Double => Value::F64(array.as_primitive::<Float64Type>().value(row)),
+        Kind::Float => Value::F32(array.as_primitive::<Float32Type>().value(row)),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            Value::I32(array.as_primitive::<Int32Type>().value(row))
+        }
+        Kind::Enum(_) => Value::EnumNumber(array.as_primitive::<Int32Type>().value(row)),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            Value::I64(array.as_primitive::<Int64Type>().value(row))
+        }
+        Kind::Uint32 | Kind::Fixed32 => Value::U32(array.as_primitive::<UInt32Type>().value(row)),
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(array.as_primitive::<UInt64Type>().value(row)),
+        Kind::Bool => Value::Bool(array.as_boolean().value(row)),
+        Kind::String => Value::String(array.as_string::<i32>().value(row).to_owned()),
+        Kind::Bytes => Value::Bytes(Bytes::copy_from_slice(array.as_binary::<i32>().value(row))),
+        Kind::Message(desc) => Value::Message(read_message(desc, array, row)?),
+    })
+}
+
+fn read_message(
+    desc: &MessageDescriptor,
+    array: &dyn Array,
+    row: usize,
+) -> Result<DynamicMessage, ArrowError> {
+    let mut message = DynamicMessage::new(desc.clone());
+    match message_type(desc) {
+        MessageType::Timestamp => {
+            let nanos = array.as_primitive::<TimestampNanosecondType>().value(row);
+            message.set_field_by_number(1, Value::I64(nanos.div_euclid(NANOS_PER_SECOND)));
+            message.set_field_by_number(2, Value::I32(nanos.rem_euclid(NANOS_PER_SECOND) as i32));
+        }
+        MessageType::Duration => {
+            let nanos = array.as_primitive::<DurationNanosecondType>().value(row);
+            message.set_field_by_number(1, Value::I64(nanos / NANOS_PER_SECOND));
+            message.set_field_by_number(2, Value::I32((nanos % NANOS_PER_SECOND) as i32));
+        }
+        MessageType::Wrapper(field) => {
+            message.set_field(&field, read_kind(&field.kind(), array, row)?);
+        }
+        MessageType::Json => {
+            let json = array.as_string::<i32>().value(row);
+            message = DynamicMessage::deserialize(
+                desc.clone(),
+                &mut serde_json::Deserializer::from_str(json),
+            )
+            .map_err(|err| ArrowError::ExternalError(Box::new(err)))?;
+        }
+        MessageType::Struct => {
+            let columns = array.as_struct().columns();
+            for (field, column) in desc.fields().zip(columns) {
+                if let Some(value) = read_field(&field, column.as_ref(), row)? {
+                    message.set_field(&field, value);
+                }
+            }
+        }
+    }
+    Ok(message)
+}
@@ -0,0 +1,647 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, str,
+};
+
+use prost::bytes::Bytes;
+use serde_json::json;
+
+use crate::{
+    DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    ReflectMessage, Value,
+};
+
+/// The maximum depth of nested messages to decode, matching the recursion limit used when
+/// decoding protobuf messages.
+const RECURSION_LIMIT: u32 = 100;
+
+/// The names of the Avro primitive types, which may not be used as the name of a record or enum.
+const PRIMITIVE_NAMES: &[&str] = &[
+    "null", "boolean", "int", "long", "float", "double", "bytes", "string",
+];
+
+/// An error that occurred while deriving an Avro schema, or converting a message to or from an
+/// Avro datum.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "avro")))]
+pub struct AvroError {
+    kind: AvroErrorKind,
+}
+
+#[derive(Debug)]
+enum AvroErrorKind {
+    InvalidName { name: String },
+    UnqualifiedReference { name: String, namespace: String },
+    UnknownEnumValue { name: String, number: i32 },
+    UnexpectedEof,
+    InvalidVarint,
+    InvalidLength(i64),
+    InvalidUnionIndex(i64),
+    InvalidEnumIndex { name: String, index: i64 },
+    IntegerOutOfRange(i64),
+    InvalidBool(u8),
+    InvalidUtf8(str::Utf8Error),
+    InvalidMapKey(String),
+    RecursionLimitReached,
+    TrailingData,
+}
+
+/// Returns the Avro schema for messages of type `desc`, as a JSON document.
+///
+/// See the [module documentation](self) for a description of the mapping. Returns an error if
+/// a name used by `desc` is not valid in an Avro schema.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::DescriptorPool;
+/// use serde_json::json;
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// assert_eq!(
+///     prost_reflect::avro::schema(&message_descriptor).unwrap(),
+///     json!({
+///         "type": "record",
+///         "name": "Any",
+///         "namespace": "google.protobuf",
+///         "fields": [
+///             { "name": "type_url", "type": "string", "default": "" },
+///             { "name": "value", "type": "bytes", "default": "" },
+///         ],
+///     }),
+/// );
+/// ```
+pub fn schema(desc: &MessageDescriptor) -> Result<serde_json::Value, AvroError> {
+    message_schema(desc, "", &mut HashSet::new())
+}
+
+/// Encodes a message as an Avro datum, using the binary encoding of the schema returned by
+/// [`schema`].
+///
+/// Returns an error if a field contains an enum number which is not defined by its enum type,
+/// since Avro enums cannot hold unknown values.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let mut message = DynamicMessage::new(message_descriptor.clone());
+/// message.set_field_by_name("type_url", Value::String("foo".to_owned()));
+///
+/// let datum = prost_reflect::avro::to_datum(&message).unwrap();
+/// assert_eq!(datum, b"\x06foo\x00");
+/// assert_eq!(prost_reflect::avro::from_datum(message_descriptor, &datum).unwrap(), message);
+/// ```
+pub fn to_datum(message: &DynamicMessage) -> Result<Vec<u8>, AvroError> {
+    let mut buf = Vec::new();
+    encode_message(message, &mut buf)?;
+    Ok(buf)
+}
+
+/// Decodes a message of type `desc` from an Avro datum.
+///
+/// The datum must have been written with the schema returned by [`schema`]: resolving
+/// differences between a writer's schema and the reader's schema is not supported.
+pub fn from_datum(desc: MessageDescriptor, mut buf: &[u8]) -> Result<DynamicMessage, AvroError> {
+    let message = decode_message(desc, &mut buf, RECURSION_LIMIT)?;
+    if !buf.is_empty() {
+        return Err(AvroErrorKind::TrailingData.into());
+    }
+    Ok(message)
+}
+
+fn message_schema(
+    desc: &MessageDescriptor,
+    enclosing_namespace: &str,
+    defined: &mut HashSet<String>,
+) -> Result<serde_json::Value, AvroError> {
+    if !defined.insert(desc.full_name().to_owned()) {
+        return reference(desc.full_name(), enclosing_namespace);
+    }
+
+    let namespace = check_type_name(desc.full_name(), desc.name())?;
+    let fields = desc
+        .fields()
+        .map(|field| field_schema(&field, namespace, defined))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(json!({
+        "type": "record",
+        "name": desc.name(),
+        "namespace": namespace,
+        "fields": fields,
+    }))
+}
+
+fn enum_schema(
+    desc: &EnumDescriptor,
+    enclosing_namespace: &str,
+    defined: &mut HashSet<String>,
+) -> Result<serde_json::Value, AvroError> {
+    if !defined.insert(desc.full_name().to_owned()) {
+        return reference(desc.full_name(), enclosing_namespace);
+    }
+
+    let namespace = check_type_name(desc.full_name(), desc.name())?;
+    let symbols = desc
+        .values()
+        .map(|value| {
+            check_name(value.name())?;
+            Ok(value.name().to_owned())
+        })
+        .collect::<Result<Vec<_>, AvroError>>()?;
+    Ok(json!({
+        "type": "enum",
+        "name": desc.name(),
+        "namespace": namespace,
+        "symbols": symbols,
+        "default": desc.default_value().name(),
+    }))
+}
+
+/// Returns a reference to a named type which has already been defined.
+///
+/// A name without a dot is resolved relative to the enclosing namespace, so types in the null
+/// namespace can only be referenced from within the null namespace.
+fn reference(full_name: &str, enclosing_namespace: &str) -> Result<serde_json::Value, AvroError> {
+    if full_name.contains('.') || enclosing_namespace.is_empty() {
+        Ok(json!(full_name))
+    } else {
+        Err(AvroErrorKind::UnqualifiedReference {
+            name: full_name.to_owned(),
+            namespace: enclosing_namespace.to_owned(),
+        }
+        .into())
+    }
+}
+
+fn field_schema(
+    field: &FieldDescriptor,
+    namespace: &str,
+    defined: &mut HashSet<String>,
+) -> Result<serde_json::Value, AvroError> {
+    check_name(field.name())?;
+
+    let schema = if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message().expect("map entry should be a message");
+        json!({
+            "type": "map",
+            "values": kind_schema(&entry.map_entry_value_field().kind(), namespace, defined)?,
+        })
+    } else if field.is_list() {
+        json!({
+            "type": "array",
+            "items": kind_schema(&field.kind(), namespace, defined)?,
+        })
+    } else {
+        kind_schema(&field.kind(), namespace, defined)?
+    };
+
+    if field.supports_presence() {
+        Ok(json!({
+            "name": field.name(),
+            "type": ["null", schema],
+            "default": null,
+        }))
+    } else {
+        Ok(json!({
+            "name": field.name(),
+            "type": schema,
+            "default": default_json(&field.kind(), &Value::default_value_for_field(field)),
+        }))
+    }
+}
+
+fn kind_schema(
+    kind: &Kind,
+    namespace: &str,
+    defined: &mut HashSet<String>,
+) -> Result<serde_json::Value, AvroError> {
+    Ok(match kind {
+        Kind::Double => json!("double"),
+        Kind::Float => json!("float"),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => json!("int"),
+        Kind::Int64
+        | Kind::Sint64
+        | Kind::Sfixed64
+        | Kind::Uint32
+        | Kind::Fixed32
+        | Kind::Uint64
+        | Kind::Fixed64 => json!("long"),
+        Kind::Bool => json!("boolean"),
+        Kind::String => json!("string"),
+        Kind::Bytes => json!("bytes"),
+        Kind::Enum(desc) => enum_schema(desc, namespace, defined)?,
+        Kind::Message(desc) => message_schema(desc, namespace, defined)?,
+    })
+}
+
+/// Returns the default value of a field without presence, in the JSON form used by Avro schemas.
+fn default_json(kind: &Kind, value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(value) => json!(value),
+        Value::I32(value) => json!(value),
+        Value::I64(value) => json!(value),
+        Value::U32(value) => json!(value),
+        Value::U64(value) => json!(*value as i64),
+        Value::F32(value) => json!(value),
+        Value::F64(value) => json!(value),
+        Value::String(value) => json!(value),
+        // Avro represents default bytes values as a string of code points in the range 0-255.
+        Value::Bytes(value) => json!(value.iter().map(|&b| char::from(b)).collect::<String>()),
+        Value::EnumNumber(number) => {
+            match kind.as_enum().and_then(|desc| desc.get_value(*number)) {
+                Some(value) => json!(value.name()),
+                None => serde_json::Value::Null,
+            }
+        }
+        Value::List(_) => json!([]),
+        Value::Map(_) => json!({}),
+        Value::Message(_) => serde_json::Value::Null,
+    }
+}
+
+/// Checks that a record or enum name is valid, and returns its namespace.
+fn check_type_name<'a>(full_name: &'a str, name: &str) -> Result<&'a str, AvroError> {
+    check_name(name)?;
+    if PRIMITIVE_NAMES.contains(&name) {
+        return Err(AvroErrorKind::InvalidName {
+            name: name.to_owned(),
+        }
+        .into());
+    }
+
+    let namespace = match full_name.rsplit_once('.') {
+        Some((namespace, _)) => namespace,
+        None => "",
+    };
+    if !namespace.is_empty() {
+        for part in namespace.split('.') {
+            check_name(part)?;
+        }
+    }
+    Ok(namespace)
+}
+
+/// Checks that a name matches the pattern `[A-Za-z_][A-Za-z0-9_]*` required by Avro.
+fn check_name(name: &str) -> Result<(), AvroError> {
+    let mut chars = name.chars();
+    let valid = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+        }
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(AvroErrorKind::InvalidName {
+            name: name.to_owned(),
+        }
+        .into())
+    }
+}
+
+fn encode_message(message: &DynamicMessage, buf: &mut Vec<u8>) -> Result<(), AvroError> {
+    for field in message.descriptor().fields() {
+        if field.supports_presence() {
+            if message.has_field(&field) {
+                encode_long(1, buf);
+                encode_field(&field, &message.get_field(&field), buf)?;
+            } else {
+                encode_long(0, buf);
+            }
+        } else {
+            encode_field(&field, &message.get_field(&field), buf)?;
+        }
+    }
+    Ok(())
+}
+
+fn encode_field(
+    field: &FieldDescriptor,
+    value: &Value,
+    buf: &mut Vec<u8>,
+) -> Result<(), AvroError> {
+    if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message().expect("map entry should be a message");
+        let value_kind = entry.map_entry_value_field().kind();
+
+        let mut map: Vec<_> = value.as_map().expect("expected map").iter().collect();
+        // Sort entries so the output does not depend on the iteration order of the map.
+        map.sort_by_key(|(key, _)| *key);
+
+        if !map.is_empty() {
+            encode_long(map.len() as i64, buf);
+            for (key, value) in map {
+                encode_bytes(map_key_string(key).as_bytes(), buf);
+                encode_kind(&value_kind, value, buf)?;
+            }
+        }
+        encode_long(0, buf);
+    } else if field.is_list() {
+        let kind = field.kind();
+        let list = value.as_list().expect("expected list");
+        if !list.is_empty() {
+            encode_long(list.len() as i64, buf);
+            for value in list {
+                encode_kind(&kind, value, buf)?;
+            }
+        }
+        encode_long(0, buf);
+    } else {
+        encode_kind(&field.kind(), value, buf)?;
+    }
+    Ok(())
+}
+
+fn encode_kind(kind: &Kind, value: &Value, buf: &mut Vec<u8>) -> Result<(), AvroError> {
+    match value {
+        Value::Bool(value) => buf.push(u8::from(*value)),
+        Value::I32(value) => encode_long((*value).into(), buf),
+        Value::I64(value) => encode_long(*value, buf),
+        Value::U32(value) => encode_long((*value).into(), buf),
+        Value::U64(value) => encode_long(*value as i64, buf),
+        Value::F32(value) => buf.extend_from_slice(&value.to_le_bytes()),
+        Value::F64(value) => buf.extend_from_slice(&value.to_le_bytes()),
+        Value::String(value) => encode_bytes(value.as_bytes(), buf),
+        Value::Bytes(value) => encode_bytes(value, buf),
+        Value::EnumNumber(number) => {
+            let desc = kind.as_enum().expect("expected enum");
+            match desc.values().position(|value| value.number() == *number) {
+                Some(index) => encode_long(index as i64, buf),
+                None => {
+                    return Err(AvroErrorKind::UnknownEnumValue {
+                        name: desc.full_name().to_owned(),
+                        number: *number,
+                    }
+                    .into())
+                }
+            }
+        }
+        Value::Message(message) => encode_message(message, buf)?,
+        Value::List(_) | Value::Map(_) => unreachable!("lists and maps are encoded by field"),
+    }
+    Ok(())
+}
+
+fn map_key_string(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(value) => value.to_string(),
+        MapKey::I32(value) => value.to_string(),
+        MapKey::I64(value) => value.to_string(),
+        MapKey::U32(value) => value.to_string(),
+        MapKey::U64(value) => value.to_string(),
+        MapKey::String(value) => value.clone(),
+    }
+}
+
+/// Encodes an `int` or `long` as a zig-zag encoded variable-length integer.
+fn encode_long(value: i64, buf: &mut Vec<u8>) {
+    let mut value = ((value << 1) ^ (value >> 63)) as u64;
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn encode_bytes(value: &[u8], buf: &mut Vec<u8>) {
+    encode_long(value.len() as i64, buf);
+    buf.extend_from_slice(value);
+}
+
+fn decode_message(
+    desc: MessageDescriptor,
+    buf: &mut &[u8],
+    depth: u32,
+) -> Result<DynamicMessage, AvroError> {
+    if depth == 0 {
+        return Err(AvroErrorKind::RecursionLimitReached.into());
+    }
+
+    let mut message = DynamicMessage::new(desc);
+    for field in message.descriptor().fields() {
+        if field.supports_presence() {
+            match decode_long(buf)? {
+                0 => continue,
+                1 => (),
+                index => return Err(AvroErrorKind::InvalidUnionIndex(index).into()),
+            }
+        }
+
+        let value = decode_field(&field, buf, depth)?;
+        if field.supports_presence() || !value.is_default_for_field(&field) {
+            message.set_field(&field, value);
+        }
+    }
+    Ok(message)
+}
+
+fn decode_field(field: &FieldDescriptor, buf: &mut &[u8], depth: u32) -> Result<Value, AvroError> {
+    if field.is_map() {
+        let entry = field.kind();
+        let entry = entry.as_message().expect("map entry should be a message");
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+
+        let mut map = HashMap::new();
+        decode_blocks(buf, |buf| {
+            let key = decode_string(buf)?;
+            let key = parse_map_key(&key_kind, key)?;
+            map.insert(key, decode_kind(&value_kind, buf, depth)?);
+            Ok(())
+        })?;
+        Ok(Value::Map(map))
+    } else if field.is_list() {
+        let kind = field.kind();
+        let mut list = Vec::new();
+        decode_blocks(buf, |buf| {
+            list.push(decode_kind(&kind, buf, depth)?);
+            Ok(())
+        })?;
+        Ok(Value::List(list))
+    } else {
+        decode_kind(&field.kind(), buf, depth)
+    }
+}
+
+fn decode_kind(kind: &Kind, buf: &mut &[u8], depth: u32) -> Result<Value, AvroError> {
+    Ok(match kind {
+        Kind::Double => Value::F64(f64::from_le_bytes(decode_fixed(buf)?)),
+        Kind::Float => Value::F32(f32::from_le_bytes(decode_fixed(buf)?)),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            let value = decode_long(buf)?;
+            Value::I32(i32::try_from(value).map_err(|_| AvroErrorKind::IntegerOutOfRange(value))?)
+        }
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(decode_long(buf)?),
+        Kind::Uint32 | Kind::Fixed32 => {
+            let value = decode_long(buf)?;
+            Value::U32(u32::try_from(value).map_err(|_| AvroErrorKind::IntegerOutOfRange(value))?)
+        }
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(decode_long(buf)? as u64),
+        Kind::Bool => match decode_fixed(buf)? {
+            [0] => Value::Bool(false),
+            [1] => Value::Bool(true),
+            [value] => return Err(AvroErrorKind::InvalidBool(value).into()),
+        },
+        Kind::String => Value::String(decode_string(buf)?),
+        Kind::Bytes => Value::Bytes(Bytes::copy_from_slice(decode_bytes(buf)?)),
+        Kind::Enum(desc) => {
+            let index = decode_long(buf)?;
+            let value = usize::try_from(index)
+                .ok()
+                .and_then(|index| desc.values().nth(index));
+            match value {
+                Some(value) => Value::EnumNumber(value.number()),
+                None => {
+                    return Err(AvroErrorKind::InvalidEnumIndex {
+                        name: desc.full_name().to_owned(),
+                        index,
+                    }
+                    .into())
+                }
+            }
+        }
+        Kind::Message(desc) => Value::Message(decode_message(desc.clone(), buf, depth - 1)?),
+    })
+}
+
+/// Decodes the blocks of an array or map, calling `f` to decode each item.
+fn decode_blocks(
+    buf: &mut &[u8],
+    mut f: impl FnMut(&mut &[u8]) -> Result<(), AvroError>,
+) -> Result<(), AvroError> {
+    loop {
+        let mut count = decode_long(buf)?;
+        if count == 0 {
+            return Ok(());
+        } else if count < 0 {
+            // A negative count is followed by the size of the block in bytes, which we don't need.
+            count = count
+                .checked_neg()
+                .ok_or(AvroErrorKind::InvalidLength(count))?;
+            decode_long(buf)?;
+        }
+
+        for _ in 0..count {
+            f(buf)?;
+        }
+    }
+}
+
+fn parse_map_key(kind: &Kind, key: String) -> Result<MapKey, AvroError> {
+    let parsed = match kind {
+        Kind::Bool => match key.as_str() {
+            "true" => Some(MapKey::Bool(true)),
+            "false" => Some(MapKey::Bool(false)),
+            _ => None,
+        },
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => key.parse().ok().map(MapKey::I32),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => key.parse().ok().map(MapKey::I64),
+        Kind::Uint32 | Kind::Fixed32 => key.parse().ok().map(MapKey::U32),
+        Kind::Uint64 | Kind::Fixed64 => key.parse().ok().map(MapKey::U64),
+        Kind::String => return Ok(MapKey::String(key)),
+        _ => None,
+    };
+    parsed.ok_or_else(|| AvroErrorKind::InvalidMapKey(key).into())
+}
+
+fn decode_long(buf: &mut &[u8]) -> Result<i64, AvroError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = buf.split_first().ok_or(AvroErrorKind::UnexpectedEof)?;
+        *buf = rest;
+        if shift == 63 && byte > 1 {
+            break;
+        }
+
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(((value >> 1) as i64) ^ -((value & 1) as i64));
+        }
+    }
+    Err(AvroErrorKind::InvalidVarint.into())
+}
+
+fn decode_bytes<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], AvroError> {
+    let len = decode_long(buf)?;
+    let len = usize::try_from(len).map_err(|_| AvroErrorKind::InvalidLength(len))?;
+    if len > buf.len() {
+        return Err(AvroErrorKind::UnexpectedEof.into());
+    }
+
+    let (value, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(value)
+}
+
+fn decode_string(buf: &mut &[u8]) -> Result<String, AvroError> {
+    let value = str::from_utf8(decode_bytes(buf)?).map_err(AvroErrorKind::InvalidUtf8)?;
+    Ok(value.to_owned())
+}
+
+fn decode_fixed<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], AvroError> {
+    if buf.len() < N {
+        return Err(AvroErrorKind::UnexpectedEof.into());
+    }
+
+    let (value, rest) = buf.split_at(N);
+    *buf = rest;
+    Ok(value.try_into().expect("slice should have length N"))
+}
+
+impl From<AvroErrorKind> for AvroError {
+    fn from(kind: AvroErrorKind) -> Self {
+        AvroError { kind }
+    }
+}
+
+impl fmt::Display for AvroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            AvroErrorKind::InvalidName { name } => {
+                write!(f, "'{}' is not a valid name in an Avro schema", name)
+            }
+            AvroErrorKind::UnqualifiedReference { name, namespace } => write!(
+                f,
+                "type '{}' has no namespace, so it cannot be referenced from namespace '{}'",
+                name, namespace
+            ),
+            AvroErrorKind::UnknownEnumValue { name, number } => {
+                write!(f, "enum '{}' has no value with number {}", name, number)
+            }
+            AvroErrorKind::UnexpectedEof => write!(f, "unexpected end of datum"),
+            AvroErrorKind::InvalidVarint => write!(f, "invalid variable-length integer"),
+            AvroErrorKind::InvalidLength(len) => write!(f, "invalid length {}", len),
+            AvroErrorKind::InvalidUnionIndex(index) => write!(f, "invalid union branch {}", index),
+            AvroErrorKind::InvalidEnumIndex { name, index } => {
+                write!(f, "invalid symbol index {} for enum '{}'", index, name)
+            }
+            AvroErrorKind::IntegerOutOfRange(value) => {
+                write!(f, "integer {} is out of range for the field type", value)
+            }
+            AvroErrorKind::InvalidBool(value) => write!(f, "invalid boolean value {}", value),
+            AvroErrorKind::InvalidUtf8(err) => write!(f, "invalid UTF-8 string: {}", err),
+            AvroErrorKind::InvalidMapKey(key) => write!(f, "invalid map key '{}'", key),
+            AvroErrorKind::RecursionLimitReached => write!(f, "recursion limit reached"),
+            AvroErrorKind::TrailingData => write!(f, "unexpected data after end of datum"),
+        }
+    }
+}
+
+impl Error for AvroError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            AvroErrorKind::InvalidUtf8(err) => Some(err),
+            _ => None,
+        }
+    }
+}
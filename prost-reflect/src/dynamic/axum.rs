@@ -0,0 +1,337 @@
+use std::{error::Error, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use axum_core::{
+    extract::{rejection::BytesRejection, FromRef, FromRequest},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use http::{header, request::Parts, HeaderMap, HeaderValue, Request, StatusCode};
+use prost::{bytes::Bytes, DecodeError, Message};
+
+use crate::{
+    dynamic::{deserialize_json, JsonParseError},
+    DynamicMessage, MessageDescriptor,
+};
+
+const PROTOBUF_CONTENT_TYPES: &[&str] = &["application/x-protobuf", "application/protobuf"];
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// The encoding of a message in an HTTP request or response body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub enum BodyFormat {
+    /// The protobuf binary format, with content type `application/x-protobuf`.
+    ///
+    /// Requests with the content type `application/protobuf` are also accepted.
+    Protobuf,
+    /// The JSON mapping, with content type `application/json`.
+    Json,
+}
+
+/// Chooses the message type of a request body, given the request's method, URI and headers.
+///
+/// The [`DynamicProto`] extractor obtains a `MessageLookup` from the router state using
+/// [`FromRef`], so it can be provided for a single route using `with_state`, or as part of a larger
+/// application state.
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub struct MessageLookup {
+    lookup: Arc<LookupFn>,
+}
+
+type LookupFn = dyn Fn(&Parts) -> Option<MessageDescriptor> + Send + Sync;
+
+/// An axum extractor and response for a [`DynamicMessage`] encoded in either the protobuf binary
+/// format or JSON.
+///
+/// When used as an extractor, the message type is chosen using the [`MessageLookup`] in the router
+/// state, and the body is decoded according to the `Content-Type` header of the request. The
+/// [`format`][DynamicProto::format] is set to the format the client prefers for the response: the
+/// supported type with the highest quality value in the `Accept` header if there is one, otherwise
+/// the format of the request. Types with a quality value of zero are never chosen.
+///
+/// When used as a response, the message is encoded using `format`, with the corresponding
+/// `Content-Type` header.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::axum::{DynamicProto, MessageLookup};
+///
+/// async fn echo(mut request: DynamicProto) -> DynamicProto {
+///     request.message.set_field_by_name("foo", Value::I32(150));
+///     request
+/// }
+///
+/// // With axum:
+/// // Router::new().route("/echo", post(echo).with_state(MessageLookup::from(message_descriptor)))
+/// # let _ = MessageLookup::from(message_descriptor);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub struct DynamicProto {
+    /// The message.
+    pub message: DynamicMessage,
+    /// The format used to encode the message when it is returned as a response.
+    pub format: BodyFormat,
+}
+
+/// The error returned when a [`DynamicProto`] cannot be extracted from a request.
+///
+/// This is converted into a response with an appropriate client error status code.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub struct DynamicProtoRejection {
+    kind: RejectionKind,
+}
+
+#[derive(Debug)]
+enum RejectionKind {
+    UnsupportedMediaType,
+    UnknownMessageType,
+    Body(BytesRejection),
+    Decode(DecodeError),
+    Json(JsonParseError),
+}
+
+impl BodyFormat {
+    /// Returns the content type used for responses in this format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BodyFormat::Protobuf => PROTOBUF_CONTENT_TYPES[0],
+            BodyFormat::Json => JSON_CONTENT_TYPE,
+        }
+    }
+
+    /// Returns the format for the given media type, ignoring any parameters, or `None` if it is
+    /// not supported.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+        if PROTOBUF_CONTENT_TYPES
+            .iter()
+            .any(|ty| media_type.eq_ignore_ascii_case(ty))
+        {
+            Some(BodyFormat::Protobuf)
+        } else if media_type.eq_ignore_ascii_case(JSON_CONTENT_TYPE) {
+            Some(BodyFormat::Json)
+        } else {
+            None
+        }
+    }
+
+    /// Chooses the format for a response from the `Accept` header of the request.
+    ///
+    /// The supported type with the highest quality is chosen, preferring the first on ties. Types
+    /// with a quality of zero are never chosen. If the header does not list an acceptable
+    /// supported type, the format of the request is used, unless the header rules it out.
+    fn from_accept(headers: &HeaderMap, request_format: Self) -> Self {
+        let mut best: Option<(BodyFormat, f32)> = None;
+        let mut rejected = Vec::new();
+        for media_range in headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+        {
+            let format = match BodyFormat::from_content_type(media_range) {
+                Some(format) => format,
+                None => continue,
+            };
+            match quality(media_range) {
+                Some(quality) if quality <= 0.0 => rejected.push(format),
+                Some(quality) if best.map_or(true, |(_, best)| quality > best) => {
+                    best = Some((format, quality))
+                }
+                _ => (),
+            }
+        }
+
+        match best {
+            Some((format, _)) => format,
+            None => [request_format, BodyFormat::Protobuf, BodyFormat::Json]
+                .into_iter()
+                .find(|format| !rejected.contains(format))
+                .unwrap_or(request_format),
+        }
+    }
+}
+
+/// Returns the quality value of a media range in an `Accept` header, which defaults to 1, or
+/// `None` if it is invalid.
+fn quality(media_range: &str) -> Option<f32> {
+    for param in media_range.split(';').skip(1) {
+        if let Some((name, value)) = param.split_once('=') {
+            if name.trim().eq_ignore_ascii_case("q") {
+                return value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|quality| (0.0..=1.0).contains(quality));
+            }
+        }
+    }
+    Some(1.0)
+}
+
+impl MessageLookup {
+    /// Creates a lookup which chooses the message type by calling `lookup` with the parts of the
+    /// request. If `lookup` returns `None`, the request is rejected with `404 Not Found`.
+    pub fn new<F>(lookup: F) -> Self
+    where
+        F: Fn(&Parts) -> Option<MessageDescriptor> + Send + Sync + 'static,
+    {
+        MessageLookup {
+            lookup: Arc::new(lookup),
+        }
+    }
+
+    /// Returns the message type for the request, or `None` if it is not known.
+    pub fn lookup(&self, parts: &Parts) -> Option<MessageDescriptor> {
+        (self.lookup)(parts)
+    }
+}
+
+impl From<MessageDescriptor> for MessageLookup {
+    /// Creates a lookup which always returns the given message type.
+    fn from(desc: MessageDescriptor) -> Self {
+        MessageLookup::new(move |_| Some(desc.clone()))
+    }
+}
+
+impl fmt::Debug for MessageLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageLookup").finish_non_exhaustive()
+    }
+}
+
+impl DynamicProto {
+    /// Creates a new [`DynamicProto`] which will be encoded using `format`.
+    pub fn new(message: DynamicMessage, format: BodyFormat) -> Self {
+        DynamicProto { message, format }
+    }
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for DynamicProto
+where
+    MessageLookup: FromRef<S>,
+    S: Send + Sync,
+    B: http_body::Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = DynamicProtoRejection;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+
+        let request_format = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(BodyFormat::from_content_type)
+            .ok_or(DynamicProtoRejection {
+                kind: RejectionKind::UnsupportedMediaType,
+            })?;
+        let format = BodyFormat::from_accept(&parts.headers, request_format);
+
+        let desc = MessageLookup::from_ref(state)
+            .lookup(&parts)
+            .ok_or(DynamicProtoRejection {
+                kind: RejectionKind::UnknownMessageType,
+            })?;
+
+        let bytes = Bytes::from_request(Request::from_parts(parts, body), state)
+            .await
+            .map_err(|err| DynamicProtoRejection {
+                kind: RejectionKind::Body(err),
+            })?;
+
+        let message = match request_format {
+            BodyFormat::Protobuf => {
+                DynamicMessage::decode(desc, bytes).map_err(|err| DynamicProtoRejection {
+                    kind: RejectionKind::Decode(err),
+                })?
+            }
+            BodyFormat::Json => deserialize_json(desc, &mut Vec::from(bytes), &Default::default())
+                .map_err(|err| DynamicProtoRejection {
+                    kind: RejectionKind::Json(err),
+                })?,
+        };
+
+        Ok(DynamicProto { message, format })
+    }
+}
+
+impl IntoResponse for DynamicProto {
+    fn into_response(self) -> Response {
+        let body = match self.format {
+            BodyFormat::Protobuf => self.message.encode_to_vec(),
+            BodyFormat::Json => match serde_json::to_vec(&self.message) {
+                Ok(body) => body,
+                Err(err) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+                }
+            },
+        };
+
+        (
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(self.format.content_type()),
+            )],
+            body,
+        )
+            .into_response()
+    }
+}
+
+impl DynamicProtoRejection {
+    /// Returns the status code of the response for this rejection.
+    pub fn status(&self) -> StatusCode {
+        match &self.kind {
+            RejectionKind::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            RejectionKind::UnknownMessageType => StatusCode::NOT_FOUND,
+            RejectionKind::Body(err) => err.status(),
+            RejectionKind::Decode(_) | RejectionKind::Json(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for DynamicProtoRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RejectionKind::UnsupportedMediaType => write!(
+                f,
+                "expected request with `Content-Type: {}` or `Content-Type: {}`",
+                BodyFormat::Protobuf.content_type(),
+                BodyFormat::Json.content_type()
+            ),
+            RejectionKind::UnknownMessageType => write!(f, "unknown message type for request"),
+            RejectionKind::Body(err) => write!(f, "failed to read request body: {}", err),
+            RejectionKind::Decode(err) => write!(f, "failed to decode request body: {}", err),
+            RejectionKind::Json(err) => write!(f, "failed to deserialize request body: {}", err),
+        }
+    }
+}
+
+impl Error for DynamicProtoRejection {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            RejectionKind::UnsupportedMediaType | RejectionKind::UnknownMessageType => None,
+            RejectionKind::Body(err) => Some(err),
+            RejectionKind::Decode(err) => Some(err),
+            RejectionKind::Json(err) => Some(err),
+        }
+    }
+}
+
+impl IntoResponse for DynamicProtoRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
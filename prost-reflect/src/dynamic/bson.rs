@@ -0,0 +1,245 @@
+use std::{collections::BTreeMap, convert::TryFrom, error::Error, fmt};
+
+use base64::{display::Base64Display, prelude::BASE64_STANDARD};
+use bson1::{spec::BinarySubtype, Binary, Bson, DateTime, Document};
+use serde_value::Value;
+
+use crate::{
+    dynamic::serde::SerializeWithOptions, DeserializeOptions, DynamicMessage, MessageDescriptor,
+    SerializeOptions,
+};
+
+/// An error that occurred while converting a message to or from a [BSON](https://bsonspec.org/)
+/// document.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "bson")))]
+pub struct BsonError {
+    kind: BsonErrorKind,
+}
+
+#[derive(Debug)]
+enum BsonErrorKind {
+    Serialize(serde_value::SerializerError),
+    Deserialize(serde_value::DeserializerError),
+    DateTime(bson1::datetime::Error),
+    UnsupportedType(bson1::spec::ElementType),
+}
+
+impl DynamicMessage {
+    /// Converts this message to a [BSON](https://bsonspec.org/) document, using the default
+    /// [`SerializeOptions`].
+    ///
+    /// Messages are converted using the same field names and value representations as the
+    /// [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json), except that
+    /// values which have a native BSON type keep it:
+    ///
+    /// - 64-bit integers are written as `int64` values, rather than strings. Since BSON has no
+    ///   unsigned 64-bit type, `uint64` and `fixed64` values larger than [`i64::MAX`] are written as
+    ///   strings.
+    /// - `bytes` fields and `google.protobuf.BytesValue` are written as binary values.
+    /// - `google.protobuf.Timestamp` is written as a UTC datetime. Datetimes have millisecond
+    ///   precision, so any smaller part of the timestamp is truncated.
+    /// - Infinite and NaN floating point values are written as doubles.
+    ///
+    /// The entries of each document are sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let document = dynamic_message.to_bson_document().unwrap();
+    /// assert_eq!(document.get_i32("foo").unwrap(), 150);
+    /// assert_eq!(document.get_document("nested").unwrap().get_i32("bar").unwrap(), 66);
+    ///
+    /// assert_eq!(DynamicMessage::from_bson_document(message_descriptor, &document).unwrap(), dynamic_message);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "bson")))]
+    pub fn to_bson_document(&self) -> Result<Document, BsonError> {
+        self.to_bson_document_with_options(&SerializeOptions::new())
+    }
+
+    /// Converts this message to a [BSON](https://bsonspec.org/) document, using the given
+    /// [`SerializeOptions`].
+    ///
+    /// The [`stringify_64_bit_integers`][SerializeOptions::stringify_64_bit_integers] and
+    /// [`bytes_encoding`][SerializeOptions::bytes_encoding] options are ignored, since these values
+    /// are always written using their native BSON types. See
+    /// [`to_bson_document`][Self::to_bson_document] for details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "bson")))]
+    pub fn to_bson_document_with_options(
+        &self,
+        options: &SerializeOptions,
+    ) -> Result<Document, BsonError> {
+        let options = options.clone().native_values(true);
+        let value = serde_value::to_value(SerializeWithOptions(self, &options))
+            .map_err(BsonErrorKind::Serialize)?;
+        match to_bson(value) {
+            Bson::Document(document) => Ok(document),
+            _ => unreachable!("messages should serialize to a document"),
+        }
+    }
+
+    /// Converts a [BSON](https://bsonspec.org/) document to a message of type `desc`, using the
+    /// default [`DeserializeOptions`].
+    ///
+    /// Documents written by [`to_bson_document`][Self::to_bson_document] are accepted, as well as
+    /// any document which uses the representations of the JSON mapping. Datetimes may be read into
+    /// `google.protobuf.Timestamp` fields, binary values into `bytes` fields, and object IDs are
+    /// read as their hexadecimal string representation. Other BSON types which have no
+    /// equivalent in the JSON mapping, such as decimals or regular expressions, are rejected.
+    ///
+    /// The `_id` field which MongoDB adds to stored documents is not a field of the message, so it
+    /// should be removed from the document or ignored using
+    /// [`DeserializeOptions::deny_unknown_fields`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "bson")))]
+    pub fn from_bson_document(
+        desc: MessageDescriptor,
+        document: &Document,
+    ) -> Result<Self, BsonError> {
+        Self::from_bson_document_with_options(desc, document, &DeserializeOptions::new())
+    }
+
+    /// Converts a [BSON](https://bsonspec.org/) document to a message of type `desc`, using the
+    /// given [`DeserializeOptions`].
+    ///
+    /// See [`from_bson_document`][Self::from_bson_document] for details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "bson")))]
+    pub fn from_bson_document_with_options(
+        desc: MessageDescriptor,
+        document: &Document,
+        options: &DeserializeOptions,
+    ) -> Result<Self, BsonError> {
+        let value = from_bson_document(document)?;
+        let message = DynamicMessage::deserialize_with_options(desc, value, options)
+            .map_err(BsonErrorKind::Deserialize)?;
+        Ok(message)
+    }
+}
+
+fn to_bson(value: Value) -> Bson {
+    match value {
+        Value::Bool(value) => Bson::Boolean(value),
+        Value::U8(value) => Bson::Int32(value.into()),
+        Value::U16(value) => Bson::Int32(value.into()),
+        Value::U32(value) => Bson::Int64(value.into()),
+        Value::U64(value) => match i64::try_from(value) {
+            Ok(value) => Bson::Int64(value),
+            Err(_) => Bson::String(value.to_string()),
+        },
+        Value::I8(value) => Bson::Int32(value.into()),
+        Value::I16(value) => Bson::Int32(value.into()),
+        Value::I32(value) => Bson::Int32(value),
+        Value::I64(value) => Bson::Int64(value),
+        // Widen through the shortest decimal representation, so that a float field set to `0.1`
+        // is stored as the double `0.1`.
+        Value::F32(value) if value.is_finite() => {
+            Bson::Double(value.to_string().parse().unwrap_or_else(|_| value.into()))
+        }
+        Value::F32(value) => Bson::Double(value.into()),
+        Value::F64(value) => Bson::Double(value),
+        Value::Char(value) => Bson::String(value.to_string()),
+        Value::String(value) => Bson::String(value),
+        Value::Unit | Value::Option(None) => Bson::Null,
+        Value::Option(Some(value)) => to_bson(*value),
+        Value::Newtype(value) => match *value {
+            // Only timestamps are serialized as newtypes, as the number of milliseconds since the
+            // epoch.
+            Value::I64(millis) => Bson::DateTime(DateTime::from_millis(millis)),
+            value => to_bson(value),
+        },
+        Value::Seq(values) => Bson::Array(values.into_iter().map(to_bson).collect()),
+        Value::Map(entries) => Bson::Document(
+            entries
+                .into_iter()
+                .map(|(key, value)| (to_bson_key(key), to_bson(value)))
+                .collect(),
+        ),
+        Value::Bytes(bytes) => Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes,
+        }),
+    }
+}
+
+/// BSON document keys are always strings, so field numbers and map keys are converted to their
+/// string representations.
+fn to_bson_key(key: Value) -> String {
+    match to_bson(key) {
+        Bson::String(key) => key,
+        Bson::Int32(key) => key.to_string(),
+        Bson::Int64(key) => key.to_string(),
+        Bson::Boolean(key) => key.to_string(),
+        key => unreachable!("unexpected map key {:?}", key),
+    }
+}
+
+fn from_bson_document(document: &Document) -> Result<Value, BsonError> {
+    let mut map = BTreeMap::new();
+    for (key, value) in document {
+        map.insert(Value::String(key.clone()), from_bson(value)?);
+    }
+    Ok(Value::Map(map))
+}
+
+fn from_bson(value: &Bson) -> Result<Value, BsonError> {
+    match value {
+        Bson::Double(value) => Ok(Value::F64(*value)),
+        Bson::String(value) => Ok(Value::String(value.clone())),
+        Bson::Array(values) => values
+            .iter()
+            .map(from_bson)
+            .collect::<Result<_, _>>()
+            .map(Value::Seq),
+        Bson::Document(document) => from_bson_document(document),
+        Bson::Boolean(value) => Ok(Value::Bool(*value)),
+        Bson::Null => Ok(Value::Unit),
+        Bson::Int32(value) => Ok(Value::I32(*value)),
+        Bson::Int64(value) => Ok(Value::I64(*value)),
+        Bson::DateTime(value) => {
+            let value = value
+                .try_to_rfc3339_string()
+                .map_err(BsonErrorKind::DateTime)?;
+            Ok(Value::String(value))
+        }
+        Bson::Binary(value) => Ok(Value::String(
+            Base64Display::new(&value.bytes, &BASE64_STANDARD).to_string(),
+        )),
+        Bson::ObjectId(value) => Ok(Value::String(value.to_hex())),
+        value => Err(BsonErrorKind::UnsupportedType(value.element_type()).into()),
+    }
+}
+
+impl From<BsonErrorKind> for BsonError {
+    fn from(kind: BsonErrorKind) -> Self {
+        BsonError { kind }
+    }
+}
+
+impl fmt::Display for BsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            BsonErrorKind::Serialize(err) => write!(f, "failed to serialize message: {}", err),
+            BsonErrorKind::Deserialize(err) => {
+                write!(f, "failed to deserialize message: {}", err)
+            }
+            BsonErrorKind::DateTime(err) => write!(f, "invalid datetime: {}", err),
+            BsonErrorKind::UnsupportedType(ty) => {
+                write!(f, "BSON values of type {:?} are not supported", ty)
+            }
+        }
+    }
+}
+
+impl Error for BsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            BsonErrorKind::Serialize(err) => Some(err),
+            BsonErrorKind::Deserialize(err) => Some(err),
+            BsonErrorKind::DateTime(err) => Some(err),
+            BsonErrorKind::UnsupportedType(_) => None,
+        }
+    }
+}
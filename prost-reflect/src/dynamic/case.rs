@@ -40,6 +40,30 @@ pub(crate) fn camel_case_to_snake_case(result: &mut String, part: &str) -> Resul
     Ok(())
 }
 
+/// Convert `src` to snake case, separating words with `sep`. Word boundaries are
+/// taken to be underscores, dashes, or the start of a run of uppercase letters.
+#[cfg(feature = "serde")]
+pub(crate) fn to_snake_case(src: &str, sep: char) -> String {
+    let mut result = String::with_capacity(src.len());
+    let mut prev_upper = true;
+    for ch in src.chars() {
+        if ch == '_' || ch == '-' {
+            result.push(sep);
+            prev_upper = true;
+        } else if ch.is_ascii_uppercase() {
+            if !prev_upper {
+                result.push(sep);
+            }
+            result.push(ch.to_ascii_lowercase());
+            prev_upper = true;
+        } else {
+            result.push(ch);
+            prev_upper = false;
+        }
+    }
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use proptest::prelude::*;
@@ -72,6 +96,16 @@ mod tests {
         buf.clear();
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn snake_case() {
+        assert_eq!(to_snake_case("foo", '_'), "foo");
+        assert_eq!(to_snake_case("foo_bar", '_'), "foo_bar");
+        assert_eq!(to_snake_case("fooBar", '_'), "foo_bar");
+        assert_eq!(to_snake_case("FooBAR", '_'), "foo_bar");
+        assert_eq!(to_snake_case("foo_barBaz", '-'), "foo-bar-baz");
+    }
+
     #[test]
     fn bad_roundtrips() {
         let mut buf = String::new();
@@ -0,0 +1,140 @@
+use std::{error::Error, fmt, io};
+
+use crate::{
+    dynamic::serde::SerializeWithOptions, DeserializeOptions, DynamicMessage, MessageDescriptor,
+    SerializeOptions,
+};
+
+/// An error that occurred while converting a message to or from [CBOR](https://cbor.io/).
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+pub struct CborError {
+    kind: CborErrorKind,
+}
+
+#[derive(Debug)]
+enum CborErrorKind {
+    Serialize(ciborium::ser::Error<io::Error>),
+    Deserialize(ciborium::de::Error<io::Error>),
+    Message(serde_value::DeserializerError),
+}
+
+impl DynamicMessage {
+    /// Serializes this message to [CBOR](https://cbor.io/), using the default [`SerializeOptions`].
+    ///
+    /// Messages are written as indefinite-length CBOR maps, using the same field names and value
+    /// representations as the [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json).
+    /// See [`to_cbor_with_options`][Self::to_cbor_with_options] for a more compact encoding.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x96\x01".as_ref()).unwrap();
+    /// let cbor = dynamic_message.to_cbor().unwrap();
+    /// assert_eq!(cbor, b"\xbf\x63foo\x18\x96\xff");
+    /// assert_eq!(DynamicMessage::from_cbor(message_descriptor, &cbor).unwrap(), dynamic_message);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub fn to_cbor(&self) -> Result<Vec<u8>, CborError> {
+        self.to_cbor_with_options(&SerializeOptions::new())
+    }
+
+    /// Serializes this message to [CBOR](https://cbor.io/), using the given [`SerializeOptions`].
+    ///
+    /// The [`use_field_numbers`][SerializeOptions::use_field_numbers] option gives a compact
+    /// encoding in which fields are keyed by their integer field number. It can be read back by
+    /// [`from_cbor_with_options`][Self::from_cbor_with_options] with
+    /// [`DeserializeOptions::allow_field_numbers`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, SerializeOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x96\x01".as_ref()).unwrap();
+    /// let cbor = dynamic_message
+    ///     .to_cbor_with_options(&SerializeOptions::new().use_field_numbers(true))
+    ///     .unwrap();
+    /// assert_eq!(cbor, b"\xbf\x01\x18\x96\xff");
+    ///
+    /// let options = DeserializeOptions::new().allow_field_numbers(true);
+    /// assert_eq!(
+    ///     DynamicMessage::from_cbor_with_options(message_descriptor, &cbor, &options).unwrap(),
+    ///     dynamic_message,
+    /// );
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub fn to_cbor_with_options(&self, options: &SerializeOptions) -> Result<Vec<u8>, CborError> {
+        let mut buf = Vec::new();
+        ciborium::ser::into_writer(&SerializeWithOptions(self, options), &mut buf)
+            .map_err(CborErrorKind::Serialize)?;
+        Ok(buf)
+    }
+
+    /// Deserializes a message of type `desc` from [CBOR](https://cbor.io/), using the default
+    /// [`DeserializeOptions`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub fn from_cbor(desc: MessageDescriptor, buf: &[u8]) -> Result<Self, CborError> {
+        Self::from_cbor_with_options(desc, buf, &DeserializeOptions::new())
+    }
+
+    /// Deserializes a message of type `desc` from [CBOR](https://cbor.io/), using the given
+    /// [`DeserializeOptions`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+    pub fn from_cbor_with_options(
+        desc: MessageDescriptor,
+        buf: &[u8],
+        options: &DeserializeOptions,
+    ) -> Result<Self, CborError> {
+        let value: serde_value::Value =
+            ciborium::de::from_reader(buf).map_err(CborErrorKind::Deserialize)?;
+        let message = DynamicMessage::deserialize_with_options(desc, value, options)
+            .map_err(CborErrorKind::Message)?;
+        Ok(message)
+    }
+}
+
+impl From<CborErrorKind> for CborError {
+    fn from(kind: CborErrorKind) -> Self {
+        CborError { kind }
+    }
+}
+
+impl fmt::Display for CborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CborErrorKind::Serialize(ciborium::ser::Error::Io(err))
+            | CborErrorKind::Deserialize(ciborium::de::Error::Io(err)) => {
+                write!(f, "i/o error: {}", err)
+            }
+            CborErrorKind::Serialize(ciborium::ser::Error::Value(msg)) => {
+                write!(f, "failed to serialize message: {}", msg)
+            }
+            CborErrorKind::Deserialize(ciborium::de::Error::Syntax(offset)) => {
+                write!(f, "invalid CBOR at offset {}", offset)
+            }
+            CborErrorKind::Deserialize(ciborium::de::Error::Semantic(_, msg)) => {
+                write!(f, "invalid CBOR: {}", msg)
+            }
+            CborErrorKind::Deserialize(ciborium::de::Error::RecursionLimitExceeded) => {
+                write!(f, "invalid CBOR: recursion limit exceeded")
+            }
+            CborErrorKind::Message(err) => write!(f, "failed to deserialize message: {}", err),
+        }
+    }
+}
+
+impl Error for CborError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            CborErrorKind::Serialize(ciborium::ser::Error::Io(err))
+            | CborErrorKind::Deserialize(ciborium::de::Error::Io(err)) => Some(err),
+            CborErrorKind::Serialize(_) | CborErrorKind::Deserialize(_) => None,
+            CborErrorKind::Message(err) => Some(err),
+        }
+    }
+}
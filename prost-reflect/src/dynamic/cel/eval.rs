@@ -0,0 +1,801 @@
+use std::{cmp::Ordering, collections::BTreeMap, str::FromStr};
+
+use prost::bytes::Bytes;
+use regex::Regex;
+
+use super::{
+    parse::{BinaryOp, Expr, MacroKind, UnaryOp},
+    Activation, CelKey, CelValue,
+};
+use crate::dynamic::datetime::{to_duration, NANOS_PER_SECOND};
+
+/// The range of valid durations, in seconds, matching the JSON mapping of `google.protobuf.Duration`.
+const MAX_DURATION_SECONDS: i64 = 315_576_000_000;
+/// The range of valid timestamps, in seconds, from `0001-01-01T00:00:00Z` to `9999-12-31T23:59:59Z`.
+const MIN_TIMESTAMP_SECONDS: i64 = -62_135_596_800;
+const MAX_TIMESTAMP_SECONDS: i64 = 253_402_300_799;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+type EvalResult<T = CelValue> = Result<T, String>;
+
+pub(super) struct Evaluator<'a> {
+    activation: &'a Activation,
+    /// Variables bound by macros such as `all`, innermost last.
+    locals: Vec<(&'a str, CelValue)>,
+}
+
+impl<'a> Evaluator<'a> {
+    pub fn new(activation: &'a Activation) -> Self {
+        Evaluator {
+            activation,
+            locals: Vec::new(),
+        }
+    }
+
+    pub fn eval(&mut self, expr: &'a Expr) -> EvalResult {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Ident(name) => self.resolve(name),
+            Expr::Select { operand, field } => {
+                let operand = self.eval(operand)?;
+                select(operand, field)
+            }
+            Expr::Has { operand, field } => match self.eval(operand)? {
+                CelValue::Message(message) => match message.desc.get_field_by_name(field) {
+                    Some(field) => Ok(CelValue::Bool(message.has_field(&field))),
+                    None => Err(no_such_field(message.desc.full_name(), field)),
+                },
+                CelValue::Map(map) => Ok(CelValue::Bool(
+                    map.contains_key(&CelKey::String(field.clone())),
+                )),
+                operand => Err(format!(
+                    "type '{}' does not support field selection",
+                    operand.type_name()
+                )),
+            },
+            Expr::Index { operand, index } => {
+                let operand = self.eval(operand)?;
+                let index = self.eval(index)?;
+                self.index(operand, index)
+            }
+            Expr::Call {
+                target,
+                function,
+                args,
+            } => {
+                let target = match target {
+                    Some(target) => Some(self.eval(target)?),
+                    None => None,
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<EvalResult<Vec<_>>>()?;
+                self.call(function, target, args)
+            }
+            Expr::List(items) => items
+                .iter()
+                .map(|item| self.eval(item))
+                .collect::<EvalResult<_>>()
+                .map(CelValue::List),
+            Expr::Map(entries) => {
+                let mut map = BTreeMap::new();
+                for (key, value) in entries {
+                    let key = self.eval(key)?;
+                    let type_name = key.type_name().to_owned();
+                    let key = key
+                        .into_key()
+                        .ok_or_else(|| format!("unsupported map key type '{}'", type_name))?;
+                    let value = self.eval(value)?;
+                    if map.insert(key.clone(), value).is_some() {
+                        return Err(format!("duplicate map key {:?}", key));
+                    }
+                }
+                Ok(CelValue::Map(map))
+            }
+            Expr::Unary(op, operand) => {
+                let operand = self.eval(operand)?;
+                unary(*op, operand)
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                self.binary(*op, lhs, rhs)
+            }
+            Expr::And(lhs, rhs) => self.logical(lhs, rhs, false, "&&"),
+            Expr::Or(lhs, rhs) => self.logical(lhs, rhs, true, "||"),
+            Expr::Conditional(condition, if_true, if_false) => match self.eval(condition)? {
+                CelValue::Bool(true) => self.eval(if_true),
+                CelValue::Bool(false) => self.eval(if_false),
+                condition => Err(no_such_overload("_?_:_", &[&condition])),
+            },
+            Expr::Comprehension {
+                kind,
+                range,
+                var,
+                filter,
+                body,
+            } => {
+                let items = match self.eval(range)? {
+                    CelValue::List(items) => items,
+                    CelValue::Map(map) => map.into_keys().map(CelValue::from).collect(),
+                    range => {
+                        return Err(format!(
+                            "type '{}' does not support iteration",
+                            range.type_name()
+                        ))
+                    }
+                };
+                self.comprehension(*kind, items, var, filter.as_deref(), body)
+            }
+        }
+    }
+
+    fn resolve(&self, name: &str) -> EvalResult {
+        if let Some((_, value)) = self.locals.iter().rev().find(|(local, _)| *local == name) {
+            return Ok(value.clone());
+        }
+        if let Some(value) = self.activation.variables.get(name) {
+            return Ok(value.clone());
+        }
+        if let Some(message) = &self.activation.message {
+            if let Some(field) = message.desc.get_field_by_name(name) {
+                return Ok(CelValue::from_field(message, &field));
+            }
+        }
+        match name {
+            "bool" | "int" | "uint" | "double" | "string" | "bytes" | "list" | "map"
+            | "null_type" | "type" => Ok(CelValue::Type(name.to_owned())),
+            _ => Err(format!("undeclared reference to '{}'", name)),
+        }
+    }
+
+    /// Evaluates `&&` or `||`, where `short_circuit` is the value which determines the result
+    /// regardless of the other operand. Errors are only returned if the result is not determined
+    /// by the other operand.
+    fn logical(
+        &mut self,
+        lhs: &'a Expr,
+        rhs: &'a Expr,
+        short_circuit: bool,
+        op: &str,
+    ) -> EvalResult {
+        let lhs = self.eval(lhs);
+        if lhs == Ok(CelValue::Bool(short_circuit)) {
+            return lhs;
+        }
+        let rhs = self.eval(rhs);
+        match (lhs, rhs) {
+            (_, Ok(CelValue::Bool(value))) if value == short_circuit => Ok(CelValue::Bool(value)),
+            (Ok(CelValue::Bool(_)), Ok(CelValue::Bool(value))) => Ok(CelValue::Bool(value)),
+            (Err(err), _) | (_, Err(err)) => Err(err),
+            (Ok(lhs), Ok(rhs)) => Err(no_such_overload(op, &[&lhs, &rhs])),
+        }
+    }
+
+    fn comprehension(
+        &mut self,
+        kind: MacroKind,
+        items: Vec<CelValue>,
+        var: &'a str,
+        filter: Option<&'a Expr>,
+        body: &'a Expr,
+    ) -> EvalResult {
+        match kind {
+            MacroKind::All | MacroKind::Exists => {
+                // Like `&&` and `||`, errors are ignored if any element determines the result.
+                let short_circuit = kind == MacroKind::Exists;
+                let mut error = None;
+                for item in items {
+                    match self.eval_with(var, item, body) {
+                        Ok(CelValue::Bool(value)) if value == short_circuit => {
+                            return Ok(CelValue::Bool(value))
+                        }
+                        Ok(CelValue::Bool(_)) => (),
+                        Ok(value) => {
+                            error.get_or_insert_with(|| {
+                                no_such_overload(macro_name(kind), &[&value])
+                            });
+                        }
+                        Err(err) => {
+                            error.get_or_insert(err);
+                        }
+                    }
+                }
+                match error {
+                    Some(err) => Err(err),
+                    None => Ok(CelValue::Bool(!short_circuit)),
+                }
+            }
+            MacroKind::ExistsOne => {
+                let mut count = 0;
+                for item in items {
+                    if self.predicate(kind, var, item, body)? {
+                        count += 1;
+                    }
+                }
+                Ok(CelValue::Bool(count == 1))
+            }
+            MacroKind::Filter => {
+                let mut results = Vec::new();
+                for item in items {
+                    if self.predicate(kind, var, item.clone(), body)? {
+                        results.push(item);
+                    }
+                }
+                Ok(CelValue::List(results))
+            }
+            MacroKind::Map => {
+                let mut results = Vec::new();
+                for item in items {
+                    if let Some(filter) = filter {
+                        if !self.predicate(kind, var, item.clone(), filter)? {
+                            continue;
+                        }
+                    }
+                    results.push(self.eval_with(var, item, body)?);
+                }
+                Ok(CelValue::List(results))
+            }
+        }
+    }
+
+    /// Evaluates an expression with a macro variable bound to `item`.
+    fn eval_with(&mut self, var: &'a str, item: CelValue, body: &'a Expr) -> EvalResult {
+        self.locals.push((var, item));
+        let result = self.eval(body);
+        self.locals.pop();
+        result
+    }
+
+    fn predicate(
+        &mut self,
+        kind: MacroKind,
+        var: &'a str,
+        item: CelValue,
+        body: &'a Expr,
+    ) -> EvalResult<bool> {
+        match self.eval_with(var, item, body)? {
+            CelValue::Bool(value) => Ok(value),
+            value => Err(no_such_overload(macro_name(kind), &[&value])),
+        }
+    }
+
+    fn index(&self, operand: CelValue, index: CelValue) -> EvalResult {
+        match operand {
+            CelValue::List(mut items) => {
+                let position = match index {
+                    CelValue::Int(index) => usize::try_from(index).ok(),
+                    CelValue::Uint(index) => usize::try_from(index).ok(),
+                    CelValue::Double(index) if index.fract() == 0.0 && index >= 0.0 => {
+                        Some(index as usize)
+                    }
+                    index => {
+                        return Err(no_such_overload("_[_]", &[&CelValue::List(items), &index]))
+                    }
+                };
+                match position {
+                    Some(position) if position < items.len() => Ok(items.swap_remove(position)),
+                    _ => Err(format!("index out of range: {}", display_index(&index))),
+                }
+            }
+            CelValue::Map(map) => match map_get(&map, &index) {
+                Some(value) => Ok(value.clone()),
+                None => Err(format!("no such key: {}", display_index(&index))),
+            },
+            operand => Err(no_such_overload("_[_]", &[&operand, &index])),
+        }
+    }
+
+    fn binary(&self, op: BinaryOp, lhs: CelValue, rhs: CelValue) -> EvalResult {
+        match op {
+            BinaryOp::Equal => Ok(CelValue::Bool(equals(&lhs, &rhs))),
+            BinaryOp::NotEqual => Ok(CelValue::Bool(!equals(&lhs, &rhs))),
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => {
+                let ordering = match compare(&lhs, &rhs) {
+                    Some(ordering) => ordering,
+                    None => return Err(no_such_overload(binary_op_name(op), &[&lhs, &rhs])),
+                };
+                Ok(CelValue::Bool(match (op, ordering) {
+                    (_, None) => false,
+                    (BinaryOp::Less, Some(ordering)) => ordering == Ordering::Less,
+                    (BinaryOp::LessEqual, Some(ordering)) => ordering != Ordering::Greater,
+                    (BinaryOp::Greater, Some(ordering)) => ordering == Ordering::Greater,
+                    (_, Some(ordering)) => ordering != Ordering::Less,
+                }))
+            }
+            BinaryOp::In => match &rhs {
+                CelValue::List(items) => {
+                    Ok(CelValue::Bool(items.iter().any(|item| equals(&lhs, item))))
+                }
+                CelValue::Map(map) => Ok(CelValue::Bool(map_get(map, &lhs).is_some())),
+                _ => Err(no_such_overload("@in", &[&lhs, &rhs])),
+            },
+            _ => arithmetic(op, lhs, rhs),
+        }
+    }
+
+    fn call(&self, function: &str, target: Option<CelValue>, args: Vec<CelValue>) -> EvalResult {
+        let is_method = target.is_some();
+        let args: Vec<CelValue> = target.into_iter().chain(args).collect();
+
+        if let Some(function) = self.activation.functions.get(function) {
+            return function(&args);
+        }
+
+        let result = match (function, args.as_slice()) {
+            ("size", [value]) => size(value),
+            ("contains", [CelValue::String(s), CelValue::String(t)]) => {
+                Some(Ok(CelValue::Bool(s.contains(t.as_str()))))
+            }
+            ("startsWith", [CelValue::String(s), CelValue::String(t)]) => {
+                Some(Ok(CelValue::Bool(s.starts_with(t.as_str()))))
+            }
+            ("endsWith", [CelValue::String(s), CelValue::String(t)]) => {
+                Some(Ok(CelValue::Bool(s.ends_with(t.as_str()))))
+            }
+            ("matches", [CelValue::String(s), CelValue::String(pattern)]) => Some(
+                Regex::new(pattern)
+                    .map(|regex| CelValue::Bool(regex.is_match(s)))
+                    .map_err(|err| format!("invalid regex pattern: {}", err)),
+            ),
+            (_, [value]) if !is_method => convert(function, value),
+            (_, [CelValue::Timestamp(timestamp)]) if is_method => {
+                timestamp_component(function, timestamp, "UTC")
+            }
+            (_, [CelValue::Timestamp(timestamp), CelValue::String(time_zone)]) if is_method => {
+                timestamp_component(function, timestamp, time_zone)
+            }
+            (_, [CelValue::Duration(duration)]) if is_method => {
+                duration_component(function, duration)
+            }
+            _ => None,
+        };
+
+        result.unwrap_or_else(|| {
+            let args: Vec<&CelValue> = args.iter().collect();
+            Err(no_such_overload(function, &args))
+        })
+    }
+}
+
+/// Gets a field of a message, or the value of a map with a string key.
+fn select(operand: CelValue, field: &str) -> EvalResult {
+    match operand {
+        CelValue::Message(message) => match message.desc.get_field_by_name(field) {
+            Some(field) => Ok(CelValue::from_field(&message, &field)),
+            None => Err(no_such_field(message.desc.full_name(), field)),
+        },
+        CelValue::Map(mut map) => map
+            .remove(&CelKey::String(field.to_owned()))
+            .ok_or_else(|| format!("no such key: '{}'", field)),
+        operand => Err(format!(
+            "type '{}' does not support field selection",
+            operand.type_name()
+        )),
+    }
+}
+
+/// Looks up a key in a map, treating numeric keys of different types as equal if they have the
+/// same value.
+fn map_get<'m>(map: &'m BTreeMap<CelKey, CelValue>, key: &CelValue) -> Option<&'m CelValue> {
+    let candidates = match *key {
+        CelValue::Int(value) => [
+            Some(CelKey::Int(value)),
+            u64::try_from(value).ok().map(CelKey::Uint),
+        ],
+        CelValue::Uint(value) => [
+            Some(CelKey::Uint(value)),
+            i64::try_from(value).ok().map(CelKey::Int),
+        ],
+        CelValue::Double(value) if value.fract() == 0.0 => [
+            int_from_double(value).ok().map(CelKey::Int),
+            uint_from_double(value).ok().map(CelKey::Uint),
+        ],
+        _ => [key.clone().into_key(), None],
+    };
+    candidates
+        .iter()
+        .flatten()
+        .find_map(|candidate| map.get(candidate))
+}
+
+fn unary(op: UnaryOp, operand: CelValue) -> EvalResult {
+    match (op, operand) {
+        (UnaryOp::Not, CelValue::Bool(value)) => Ok(CelValue::Bool(!value)),
+        (UnaryOp::Negate, CelValue::Int(value)) => {
+            value.checked_neg().map(CelValue::Int).ok_or_else(overflow)
+        }
+        (UnaryOp::Negate, CelValue::Double(value)) => Ok(CelValue::Double(-value)),
+        (UnaryOp::Negate, CelValue::Duration(value)) => {
+            make_duration(-duration_nanos(&value)).map(CelValue::Duration)
+        }
+        (UnaryOp::Not, operand) => Err(no_such_overload("!_", &[&operand])),
+        (UnaryOp::Negate, operand) => Err(no_such_overload("-_", &[&operand])),
+    }
+}
+
+fn arithmetic(op: BinaryOp, lhs: CelValue, rhs: CelValue) -> EvalResult {
+    use CelValue::*;
+
+    let result = match (op, &lhs, &rhs) {
+        (BinaryOp::Add, &Int(l), &Int(r)) => l.checked_add(r).map(Int).ok_or_else(overflow),
+        (BinaryOp::Subtract, &Int(l), &Int(r)) => l.checked_sub(r).map(Int).ok_or_else(overflow),
+        (BinaryOp::Multiply, &Int(l), &Int(r)) => l.checked_mul(r).map(Int).ok_or_else(overflow),
+        (BinaryOp::Divide, &Int(_), &Int(0)) => Err("division by zero".to_owned()),
+        (BinaryOp::Divide, &Int(l), &Int(r)) => l.checked_div(r).map(Int).ok_or_else(overflow),
+        (BinaryOp::Remainder, &Int(_), &Int(0)) => Err("modulus by zero".to_owned()),
+        (BinaryOp::Remainder, &Int(l), &Int(r)) => l.checked_rem(r).map(Int).ok_or_else(overflow),
+        (BinaryOp::Add, &Uint(l), &Uint(r)) => l.checked_add(r).map(Uint).ok_or_else(overflow),
+        (BinaryOp::Subtract, &Uint(l), &Uint(r)) => l.checked_sub(r).map(Uint).ok_or_else(overflow),
+        (BinaryOp::Multiply, &Uint(l), &Uint(r)) => l.checked_mul(r).map(Uint).ok_or_else(overflow),
+        (BinaryOp::Divide, &Uint(_), &Uint(0)) => Err("division by zero".to_owned()),
+        (BinaryOp::Divide, &Uint(l), &Uint(r)) => Ok(Uint(l / r)),
+        (BinaryOp::Remainder, &Uint(_), &Uint(0)) => Err("modulus by zero".to_owned()),
+        (BinaryOp::Remainder, &Uint(l), &Uint(r)) => Ok(Uint(l % r)),
+        (BinaryOp::Add, &Double(l), &Double(r)) => Ok(Double(l + r)),
+        (BinaryOp::Subtract, &Double(l), &Double(r)) => Ok(Double(l - r)),
+        (BinaryOp::Multiply, &Double(l), &Double(r)) => Ok(Double(l * r)),
+        (BinaryOp::Divide, &Double(l), &Double(r)) => Ok(Double(l / r)),
+        (BinaryOp::Add, String(l), String(r)) => Ok(String(format!("{}{}", l, r))),
+        (BinaryOp::Add, Bytes(l), Bytes(r)) => Ok(Bytes([l.as_ref(), r.as_ref()].concat().into())),
+        (BinaryOp::Add, List(l), List(r)) => Ok(List(l.iter().chain(r).cloned().collect())),
+        (BinaryOp::Add, Duration(l), Duration(r)) => {
+            make_duration(duration_nanos(l) + duration_nanos(r)).map(Duration)
+        }
+        (BinaryOp::Subtract, Duration(l), Duration(r)) => {
+            make_duration(duration_nanos(l) - duration_nanos(r)).map(Duration)
+        }
+        (BinaryOp::Add, Timestamp(t), Duration(d)) | (BinaryOp::Add, Duration(d), Timestamp(t)) => {
+            make_timestamp(timestamp_nanos(t) + duration_nanos(d)).map(Timestamp)
+        }
+        (BinaryOp::Subtract, Timestamp(t), Duration(d)) => {
+            make_timestamp(timestamp_nanos(t) - duration_nanos(d)).map(Timestamp)
+        }
+        (BinaryOp::Subtract, Timestamp(l), Timestamp(r)) => {
+            make_duration(timestamp_nanos(l) - timestamp_nanos(r)).map(Duration)
+        }
+        _ => Err(no_such_overload(binary_op_name(op), &[&lhs, &rhs])),
+    };
+    result
+}
+
+/// Compares two values using the CEL equality rules, where numbers of different types are equal
+/// if they have the same value.
+fn equals(lhs: &CelValue, rhs: &CelValue) -> bool {
+    match (lhs, rhs) {
+        (CelValue::List(lhs), CelValue::List(rhs)) => {
+            lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| equals(l, r))
+        }
+        (CelValue::Map(lhs), CelValue::Map(rhs)) => {
+            lhs.len() == rhs.len()
+                && lhs.iter().all(|(key, value)| {
+                    map_get(rhs, &CelValue::from(key.clone()))
+                        .map_or(false, |other| equals(value, other))
+                })
+        }
+        _ => match compare_numbers(lhs, rhs) {
+            Some(ordering) => ordering == Some(Ordering::Equal),
+            None => lhs == rhs,
+        },
+    }
+}
+
+/// Orders two values, returning `None` if they cannot be compared, or `Some(None)` if they are
+/// unordered numbers such as NaN.
+fn compare(lhs: &CelValue, rhs: &CelValue) -> Option<Option<Ordering>> {
+    if let Some(ordering) = compare_numbers(lhs, rhs) {
+        return Some(ordering);
+    }
+    let ordering = match (lhs, rhs) {
+        (CelValue::Bool(l), CelValue::Bool(r)) => l.cmp(r),
+        (CelValue::String(l), CelValue::String(r)) => l.cmp(r),
+        (CelValue::Bytes(l), CelValue::Bytes(r)) => l.cmp(r),
+        (CelValue::Duration(l), CelValue::Duration(r)) => duration_nanos(l).cmp(&duration_nanos(r)),
+        (CelValue::Timestamp(l), CelValue::Timestamp(r)) => {
+            timestamp_nanos(l).cmp(&timestamp_nanos(r))
+        }
+        _ => return None,
+    };
+    Some(Some(ordering))
+}
+
+/// Compares two numbers of any numeric type, returning `None` if either is not a number.
+fn compare_numbers(lhs: &CelValue, rhs: &CelValue) -> Option<Option<Ordering>> {
+    let ordering = match (lhs, rhs) {
+        (&CelValue::Int(l), &CelValue::Int(r)) => Some(l.cmp(&r)),
+        (&CelValue::Uint(l), &CelValue::Uint(r)) => Some(l.cmp(&r)),
+        (&CelValue::Int(l), &CelValue::Uint(r)) => Some(compare_int_uint(l, r)),
+        (&CelValue::Uint(l), &CelValue::Int(r)) => Some(compare_int_uint(r, l).reverse()),
+        (&CelValue::Double(l), &CelValue::Double(r)) => l.partial_cmp(&r),
+        (&CelValue::Int(l), &CelValue::Double(r)) => (l as f64).partial_cmp(&r),
+        (&CelValue::Double(l), &CelValue::Int(r)) => l.partial_cmp(&(r as f64)),
+        (&CelValue::Uint(l), &CelValue::Double(r)) => (l as f64).partial_cmp(&r),
+        (&CelValue::Double(l), &CelValue::Uint(r)) => l.partial_cmp(&(r as f64)),
+        _ => return None,
+    };
+    Some(ordering)
+}
+
+fn compare_int_uint(lhs: i64, rhs: u64) -> Ordering {
+    match u64::try_from(lhs) {
+        Ok(lhs) => lhs.cmp(&rhs),
+        Err(_) => Ordering::Less,
+    }
+}
+
+fn size(value: &CelValue) -> Option<EvalResult> {
+    let size = match value {
+        CelValue::String(value) => value.chars().count(),
+        CelValue::Bytes(value) => value.len(),
+        CelValue::List(value) => value.len(),
+        CelValue::Map(value) => value.len(),
+        _ => return None,
+    };
+    Some(Ok(CelValue::Int(size as i64)))
+}
+
+/// Evaluates the type conversion functions, such as `int(x)`.
+fn convert(function: &str, value: &CelValue) -> Option<EvalResult> {
+    let result = match (function, value) {
+        ("dyn", value) => Ok(value.clone()),
+        ("type", value) => Ok(CelValue::Type(value.type_name().to_owned())),
+        ("int", &CelValue::Int(value)) => Ok(CelValue::Int(value)),
+        ("int", &CelValue::Uint(value)) => i64::try_from(value)
+            .map(CelValue::Int)
+            .map_err(|_| overflow()),
+        ("int", &CelValue::Double(value)) => int_from_double(value).map(CelValue::Int),
+        ("int", CelValue::String(value)) => value
+            .parse()
+            .map(CelValue::Int)
+            .map_err(|_| format!("invalid int: '{}'", value)),
+        ("int", CelValue::Timestamp(value)) => Ok(CelValue::Int(value.seconds)),
+        ("uint", &CelValue::Uint(value)) => Ok(CelValue::Uint(value)),
+        ("uint", &CelValue::Int(value)) => u64::try_from(value)
+            .map(CelValue::Uint)
+            .map_err(|_| overflow()),
+        ("uint", &CelValue::Double(value)) => uint_from_double(value).map(CelValue::Uint),
+        ("uint", CelValue::String(value)) => value
+            .parse()
+            .map(CelValue::Uint)
+            .map_err(|_| format!("invalid uint: '{}'", value)),
+        ("double", &CelValue::Double(value)) => Ok(CelValue::Double(value)),
+        ("double", &CelValue::Int(value)) => Ok(CelValue::Double(value as f64)),
+        ("double", &CelValue::Uint(value)) => Ok(CelValue::Double(value as f64)),
+        ("double", CelValue::String(value)) => value
+            .parse()
+            .map(CelValue::Double)
+            .map_err(|_| format!("invalid double: '{}'", value)),
+        ("string", CelValue::String(value)) => Ok(CelValue::String(value.clone())),
+        ("string", CelValue::Bool(value)) => Ok(CelValue::String(value.to_string())),
+        ("string", CelValue::Int(value)) => Ok(CelValue::String(value.to_string())),
+        ("string", CelValue::Uint(value)) => Ok(CelValue::String(value.to_string())),
+        ("string", CelValue::Double(value)) => Ok(CelValue::String(value.to_string())),
+        ("string", CelValue::Bytes(value)) => std::str::from_utf8(value)
+            .map(|value| CelValue::String(value.to_owned()))
+            .map_err(|_| "bytes are not valid UTF-8".to_owned()),
+        ("string", CelValue::Duration(value)) => Ok(CelValue::String(value.to_string())),
+        ("string", CelValue::Timestamp(value)) => Ok(CelValue::String(value.to_string())),
+        ("bytes", CelValue::Bytes(value)) => Ok(CelValue::Bytes(value.clone())),
+        ("bytes", CelValue::String(value)) => {
+            Ok(CelValue::Bytes(Bytes::copy_from_slice(value.as_bytes())))
+        }
+        ("bool", &CelValue::Bool(value)) => Ok(CelValue::Bool(value)),
+        ("bool", CelValue::String(value)) => match value.as_str() {
+            "1" | "t" | "true" | "TRUE" | "True" => Ok(CelValue::Bool(true)),
+            "0" | "f" | "false" | "FALSE" | "False" => Ok(CelValue::Bool(false)),
+            _ => Err(format!("invalid bool: '{}'", value)),
+        },
+        ("duration", CelValue::Duration(value)) => Ok(CelValue::Duration(value.clone())),
+        ("duration", CelValue::String(value)) => crate::duration::parse(value)
+            .and_then(|message| to_duration(&message))
+            .map(|(seconds, nanos)| CelValue::Duration(prost_types::Duration { seconds, nanos }))
+            .map_err(|_| format!("invalid duration: '{}'", value)),
+        ("timestamp", CelValue::Timestamp(value)) => Ok(CelValue::Timestamp(value.clone())),
+        ("timestamp", &CelValue::Int(value)) => {
+            make_timestamp(i128::from(value) * i128::from(NANOS_PER_SECOND))
+                .map(CelValue::Timestamp)
+        }
+        ("timestamp", CelValue::String(value)) => prost_types::Timestamp::from_str(value)
+            .map_err(|_| format!("invalid timestamp: '{}'", value))
+            .and_then(|timestamp| make_timestamp(timestamp_nanos(&timestamp)))
+            .map(CelValue::Timestamp),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Evaluates the accessor methods of timestamps, such as `getFullYear()`, in a time zone given
+/// as `UTC` or a fixed offset such as `+05:30`.
+fn timestamp_component(
+    function: &str,
+    timestamp: &prost_types::Timestamp,
+    time_zone: &str,
+) -> Option<EvalResult> {
+    let offset = match parse_time_zone(time_zone) {
+        Some(offset) => offset,
+        None => return Some(Err(format!("unsupported time zone '{}'", time_zone))),
+    };
+    let local = timestamp.seconds + offset;
+    let days = local.div_euclid(SECONDS_PER_DAY);
+    let seconds_of_day = local.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    let value = match function {
+        "getFullYear" => year,
+        "getMonth" => month - 1,
+        "getDate" => day,
+        "getDayOfMonth" => day - 1,
+        "getDayOfWeek" => (days + 4).rem_euclid(7),
+        "getDayOfYear" => days - days_from_civil(year, 1, 1),
+        "getHours" => seconds_of_day / 3600,
+        "getMinutes" => seconds_of_day % 3600 / 60,
+        "getSeconds" => seconds_of_day % 60,
+        "getMilliseconds" => i64::from(timestamp.nanos / 1_000_000),
+        _ => return None,
+    };
+    Some(Ok(CelValue::Int(value)))
+}
+
+/// Evaluates the accessor methods of durations, such as `getHours()`, which return the total
+/// length of the duration in the given unit.
+fn duration_component(function: &str, duration: &prost_types::Duration) -> Option<EvalResult> {
+    let nanos = duration_nanos(duration);
+    let unit = match function {
+        "getHours" => 3600 * i128::from(NANOS_PER_SECOND),
+        "getMinutes" => 60 * i128::from(NANOS_PER_SECOND),
+        "getSeconds" => i128::from(NANOS_PER_SECOND),
+        "getMilliseconds" => 1_000_000,
+        _ => return None,
+    };
+    Some(Ok(CelValue::Int((nanos / unit) as i64)))
+}
+
+/// Parses a time zone as an offset in seconds from UTC.
+fn parse_time_zone(time_zone: &str) -> Option<i64> {
+    if time_zone == "UTC" || time_zone == "Z" {
+        return Some(0);
+    }
+    let (sign, offset) = match time_zone.as_bytes().first()? {
+        b'+' => (1, &time_zone[1..]),
+        b'-' => (-1, &time_zone[1..]),
+        _ => (1, time_zone),
+    };
+    let (hours, minutes) = offset.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let hours: i64 = hours.parse().ok()?;
+    let minutes: i64 = minutes.parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Converts a number of days since the Unix epoch to a year, month and day.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a year, month and day to a number of days since the Unix epoch.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn int_from_double(value: f64) -> EvalResult<i64> {
+    // The bounds are exclusive, since `i64::MAX` is not representable as an `f64`.
+    if value.is_finite() && value > -9.223_372_036_854_776e18 && value < 9.223_372_036_854_776e18 {
+        Ok(value as i64)
+    } else {
+        Err(overflow())
+    }
+}
+
+fn uint_from_double(value: f64) -> EvalResult<u64> {
+    if value.is_finite() && value > -1.0 && value < 1.844_674_407_370_955_2e19 {
+        Ok(value as u64)
+    } else {
+        Err(overflow())
+    }
+}
+
+fn duration_nanos(duration: &prost_types::Duration) -> i128 {
+    i128::from(duration.seconds) * i128::from(NANOS_PER_SECOND) + i128::from(duration.nanos)
+}
+
+fn timestamp_nanos(timestamp: &prost_types::Timestamp) -> i128 {
+    i128::from(timestamp.seconds) * i128::from(NANOS_PER_SECOND) + i128::from(timestamp.nanos)
+}
+
+fn make_duration(nanos: i128) -> EvalResult<prost_types::Duration> {
+    let seconds = nanos / i128::from(NANOS_PER_SECOND);
+    if seconds.abs() > i128::from(MAX_DURATION_SECONDS) {
+        return Err("duration out of range".to_owned());
+    }
+    Ok(prost_types::Duration {
+        seconds: seconds as i64,
+        nanos: (nanos % i128::from(NANOS_PER_SECOND)) as i32,
+    })
+}
+
+fn make_timestamp(nanos: i128) -> EvalResult<prost_types::Timestamp> {
+    let seconds = nanos.div_euclid(i128::from(NANOS_PER_SECOND));
+    if seconds < i128::from(MIN_TIMESTAMP_SECONDS) || seconds > i128::from(MAX_TIMESTAMP_SECONDS) {
+        return Err("timestamp out of range".to_owned());
+    }
+    Ok(prost_types::Timestamp {
+        seconds: seconds as i64,
+        nanos: nanos.rem_euclid(i128::from(NANOS_PER_SECOND)) as i32,
+    })
+}
+
+fn display_index(index: &CelValue) -> String {
+    match index {
+        CelValue::Int(value) => value.to_string(),
+        CelValue::Uint(value) => format!("{}u", value),
+        CelValue::Double(value) => value.to_string(),
+        CelValue::String(value) => format!("{:?}", value),
+        CelValue::Bool(value) => value.to_string(),
+        value => value.type_name().to_owned(),
+    }
+}
+
+fn binary_op_name(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "_+_",
+        BinaryOp::Subtract => "_-_",
+        BinaryOp::Multiply => "_*_",
+        BinaryOp::Divide => "_/_",
+        BinaryOp::Remainder => "_%_",
+        BinaryOp::Equal => "_==_",
+        BinaryOp::NotEqual => "_!=_",
+        BinaryOp::Less => "_<_",
+        BinaryOp::LessEqual => "_<=_",
+        BinaryOp::Greater => "_>_",
+        BinaryOp::GreaterEqual => "_>=_",
+        BinaryOp::In => "@in",
+    }
+}
+
+fn macro_name(kind: MacroKind) -> &'static str {
+    match kind {
+        MacroKind::All => "all",
+        MacroKind::Exists => "exists",
+        MacroKind::ExistsOne => "exists_one",
+        MacroKind::Map => "map",
+        MacroKind::Filter => "filter",
+    }
+}
+
+fn no_such_overload(function: &str, args: &[&CelValue]) -> String {
+    let types: Vec<&str> = args.iter().map(|arg| arg.type_name()).collect();
+    format!(
+        "no matching overload for '{}' applied to ({})",
+        function,
+        types.join(", ")
+    )
+}
+
+fn no_such_field(message: &str, field: &str) -> String {
+    format!("no such field '{}' in message '{}'", field, message)
+}
+
+fn overflow() -> String {
+    "integer overflow".to_owned()
+}
@@ -0,0 +1,391 @@
+use std::{fmt, iter::Peekable, ops::Range, str::CharIndices};
+
+use super::CelErrorKind;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Ident(String),
+    Int(u64),
+    Uint(u64),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Dot,
+    Comma,
+    Colon,
+    Question,
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Not,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+pub(super) struct Lexer<'a> {
+    source: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Lexer {
+            source,
+            chars: source.char_indices().peekable(),
+        }
+    }
+
+    /// Reads all tokens from the input, with their spans.
+    pub fn tokenize(mut self) -> Result<Vec<(Token, Range<usize>)>, CelErrorKind> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Range<usize>)>, CelErrorKind> {
+        self.skip_whitespace();
+        let (start, ch) = match self.chars.next() {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+
+        let token = match ch {
+            '.' if self.peek_char().map_or(false, |c| c.is_ascii_digit()) => self.number(start)?,
+            '.' => Token::Dot,
+            ',' => Token::Comma,
+            ':' => Token::Colon,
+            '?' => Token::Question,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            '[' => Token::LeftBracket,
+            ']' => Token::RightBracket,
+            '{' => Token::LeftBrace,
+            '}' => Token::RightBrace,
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '!' if self.eat('=') => Token::NotEqual,
+            '!' => Token::Not,
+            '=' if self.eat('=') => Token::Equal,
+            '<' if self.eat('=') => Token::LessEqual,
+            '<' => Token::Less,
+            '>' if self.eat('=') => Token::GreaterEqual,
+            '>' => Token::Greater,
+            '&' if self.eat('&') => Token::And,
+            '|' if self.eat('|') => Token::Or,
+            '"' | '\'' => self.string(start, ch, false, false)?,
+            '0'..='9' => self.number(start)?,
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let end = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                let ident = &self.source[start..end];
+                match (ident, self.peek_char()) {
+                    ("r" | "R", Some(quote @ ('"' | '\''))) => {
+                        self.chars.next();
+                        self.string(start, quote, true, false)?
+                    }
+                    ("b" | "B", Some(quote @ ('"' | '\''))) => {
+                        self.chars.next();
+                        self.string(start, quote, false, true)?
+                    }
+                    (
+                        "rb" | "rB" | "Rb" | "RB" | "br" | "bR" | "Br" | "BR",
+                        Some(quote @ ('"' | '\'')),
+                    ) => {
+                        self.chars.next();
+                        self.string(start, quote, true, true)?
+                    }
+                    _ => Token::Ident(ident.to_owned()),
+                }
+            }
+            _ => {
+                return Err(CelErrorKind::Syntax {
+                    message: format!("unexpected character '{}'", ch),
+                    span: start..start + ch.len_utf8(),
+                })
+            }
+        };
+        Ok(Some((token, start..self.offset())))
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            self.take_while(char::is_whitespace);
+            if self.source[self.offset()..].starts_with("//") {
+                self.take_while(|c| c != '\n');
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn number(&mut self, start: usize) -> Result<Token, CelErrorKind> {
+        let rest = &self.source[start..];
+        if rest.starts_with("0x") || rest.starts_with("0X") {
+            self.chars.next();
+            let digits_start = self.offset();
+            let end = self.take_while(|c| c.is_ascii_hexdigit());
+            let value = u64::from_str_radix(&self.source[digits_start..end], 16);
+            return self.integer(start, value.ok());
+        }
+
+        let mut end = self.take_while(|c| c.is_ascii_digit());
+        let mut is_double = rest.starts_with('.');
+        if self.source[end..].starts_with('.')
+            && self.source[end + 1..].starts_with(|c: char| c.is_ascii_digit())
+        {
+            self.chars.next();
+            end = self.take_while(|c| c.is_ascii_digit());
+            is_double = true;
+        }
+        if self.source[end..].starts_with(['e', 'E']) {
+            let exponent = &self.source[end + 1..];
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            if exponent.starts_with(|c: char| c.is_ascii_digit()) {
+                self.chars.next();
+                if let Some('+' | '-') = self.peek_char() {
+                    self.chars.next();
+                }
+                end = self.take_while(|c| c.is_ascii_digit());
+                is_double = true;
+            }
+        }
+
+        let text = &self.source[start..end];
+        if is_double {
+            text.parse()
+                .map(Token::Double)
+                .map_err(|_| CelErrorKind::Syntax {
+                    message: format!("invalid double literal '{}'", text),
+                    span: start..end,
+                })
+        } else {
+            self.integer(start, text.parse().ok())
+        }
+    }
+
+    fn integer(&mut self, start: usize, value: Option<u64>) -> Result<Token, CelErrorKind> {
+        let is_uint = self.eat('u') || self.eat('U');
+        let span = start..self.offset();
+        match value {
+            Some(value) if is_uint => Ok(Token::Uint(value)),
+            Some(value) => Ok(Token::Int(value)),
+            None => Err(CelErrorKind::Syntax {
+                message: format!("invalid integer literal '{}'", &self.source[span.clone()]),
+                span,
+            }),
+        }
+    }
+
+    fn string(
+        &mut self,
+        start: usize,
+        quote: char,
+        raw: bool,
+        bytes: bool,
+    ) -> Result<Token, CelErrorKind> {
+        let triple = self.at_quotes(quote);
+        if triple {
+            self.chars.next();
+            self.chars.next();
+        }
+
+        let mut result = Vec::new();
+        loop {
+            let (offset, ch) = match self.chars.next() {
+                Some(next) => next,
+                None => {
+                    return Err(CelErrorKind::Syntax {
+                        message: "unterminated string literal".to_owned(),
+                        span: start..self.source.len(),
+                    })
+                }
+            };
+
+            if ch == quote {
+                if !triple {
+                    break;
+                }
+                if self.at_quotes(quote) {
+                    self.chars.next();
+                    self.chars.next();
+                    break;
+                }
+            } else if (ch == '\n' || ch == '\r') && !triple {
+                return Err(CelErrorKind::Syntax {
+                    message: "unexpected newline in string literal".to_owned(),
+                    span: start..offset,
+                });
+            }
+
+            if ch == '\\' && !raw {
+                self.escape(offset, bytes, &mut result)?;
+            } else {
+                let mut buf = [0; 4];
+                result.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+
+        if bytes {
+            Ok(Token::Bytes(result))
+        } else {
+            String::from_utf8(result)
+                .map(Token::String)
+                .map_err(|_| CelErrorKind::Syntax {
+                    message: "string literal is not valid UTF-8".to_owned(),
+                    span: start..self.offset(),
+                })
+        }
+    }
+
+    fn escape(
+        &mut self,
+        start: usize,
+        bytes: bool,
+        result: &mut Vec<u8>,
+    ) -> Result<(), CelErrorKind> {
+        let invalid = |end: usize| CelErrorKind::Syntax {
+            message: "invalid escape sequence".to_owned(),
+            span: start..end,
+        };
+
+        let ch = match self.chars.next() {
+            Some((_, ch)) => ch,
+            None => return Err(invalid(self.source.len())),
+        };
+        let simple = match ch {
+            'a' => Some(b'\x07'),
+            'b' => Some(b'\x08'),
+            'f' => Some(b'\x0c'),
+            'n' => Some(b'\n'),
+            'r' => Some(b'\r'),
+            't' => Some(b'\t'),
+            'v' => Some(b'\x0b'),
+            '\\' | '?' | '"' | '\'' | '`' => Some(ch as u8),
+            _ => None,
+        };
+        if let Some(byte) = simple {
+            result.push(byte);
+            return Ok(());
+        }
+
+        let (digits, radix, is_byte) = match ch {
+            'x' | 'X' => (2, 16, true),
+            '0'..='3' => (2, 8, true),
+            'u' if !bytes => (4, 16, false),
+            'U' if !bytes => (8, 16, false),
+            _ => return Err(invalid(self.offset())),
+        };
+        let digits_start = if radix == 8 {
+            self.offset() - 1
+        } else {
+            self.offset()
+        };
+        for _ in 0..digits {
+            match self.chars.next() {
+                Some((_, c)) if c.is_digit(radix) => (),
+                _ => return Err(invalid(self.offset())),
+            }
+        }
+        let value = u32::from_str_radix(&self.source[digits_start..self.offset()], radix)
+            .map_err(|_| invalid(self.offset()))?;
+
+        if is_byte && bytes {
+            result.push(value as u8);
+        } else {
+            let ch = char::from_u32(value).ok_or_else(|| invalid(self.offset()))?;
+            let mut buf = [0; 4];
+            result.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+        }
+        Ok(())
+    }
+
+    /// Whether the next two characters are both `quote`, ending a triple-quoted string.
+    fn at_quotes(&mut self, quote: char) -> bool {
+        let mut rest = self.source[self.offset()..].chars();
+        rest.next() == Some(quote) && rest.next() == Some(quote)
+    }
+
+    fn take_while(&mut self, mut f: impl FnMut(char) -> bool) -> usize {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !f(c) {
+                break;
+            }
+            self.chars.next();
+        }
+        self.offset()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek_char() == Some(ch) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.source.len(), |&(offset, _)| offset)
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "{}", ident),
+            Token::Int(value) => write!(f, "{}", value),
+            Token::Uint(value) => write!(f, "{}u", value),
+            Token::Double(value) => write!(f, "{:?}", value),
+            Token::String(value) => write!(f, "{:?}", value),
+            Token::Bytes(_) => write!(f, "bytes literal"),
+            Token::Dot => write!(f, "."),
+            Token::Comma => write!(f, ","),
+            Token::Colon => write!(f, ":"),
+            Token::Question => write!(f, "?"),
+            Token::LeftParen => write!(f, "("),
+            Token::RightParen => write!(f, ")"),
+            Token::LeftBracket => write!(f, "["),
+            Token::RightBracket => write!(f, "]"),
+            Token::LeftBrace => write!(f, "{{"),
+            Token::RightBrace => write!(f, "}}"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
+            Token::Not => write!(f, "!"),
+            Token::Equal => write!(f, "=="),
+            Token::NotEqual => write!(f, "!="),
+            Token::Less => write!(f, "<"),
+            Token::LessEqual => write!(f, "<="),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+        }
+    }
+}
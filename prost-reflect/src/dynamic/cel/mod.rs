@@ -0,0 +1,186 @@
+mod eval;
+mod lex;
+mod parse;
+mod value;
+
+pub use self::value::{CelKey, CelValue};
+
+use std::{collections::HashMap, error::Error, fmt, ops::Range, sync::Arc};
+
+use self::{eval::Evaluator, parse::Parser};
+use crate::DynamicMessage;
+
+/// A compiled [CEL](https://github.com/google/cel-spec) expression.
+///
+/// The standard operators, macros (`has`, `all`, `exists`, `exists_one`, `map` and `filter`) and
+/// functions of CEL are supported, including string functions such as `startsWith` and `matches`,
+/// type conversions such as `int()` and `timestamp()`, and the accessor methods of timestamps and
+/// durations. Message construction expressions such as `Foo{bar: 1}` are not supported.
+///
+/// Expressions are evaluated dynamically against an [`Activation`], without type checking, so a
+/// type error is only reported if the affected part of the expression is evaluated.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// use prost_reflect::cel::{self, Activation, CelValue, Program};
+/// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// let mut message = DynamicMessage::new(message_descriptor);
+/// message.set_field_by_name("foo", Value::I32(150));
+///
+/// let program = Program::compile("request.foo >= 100 && !has(request.nested)").unwrap();
+/// let activation = Activation::new().with_variable("request", message.clone());
+/// assert_eq!(program.evaluate(&activation).unwrap(), CelValue::Bool(true));
+///
+/// assert_eq!(cel::evaluate("foo * 2", &message).unwrap(), CelValue::Int(300));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Program {
+    source: String,
+    expr: parse::Expr,
+}
+
+/// The variables and functions available to a CEL expression.
+///
+/// Variables may be bound individually, or an activation may be created from a message, in which
+/// case each field of the message is available as a variable with the same name.
+#[derive(Clone, Default)]
+pub struct Activation {
+    message: Option<DynamicMessage>,
+    variables: HashMap<String, CelValue>,
+    functions: HashMap<String, Function>,
+}
+
+type Function = Arc<dyn Fn(&[CelValue]) -> Result<CelValue, String> + Send + Sync>;
+
+/// An error that may occur while compiling or evaluating a CEL expression.
+#[derive(Debug)]
+pub struct CelError {
+    kind: CelErrorKind,
+}
+
+#[derive(Debug)]
+enum CelErrorKind {
+    Syntax { message: String, span: Range<usize> },
+    Evaluation { message: String },
+}
+
+/// Compiles a CEL expression and evaluates it with the fields of `message` as variables.
+///
+/// See [`Activation::from_message`] for details.
+pub fn evaluate(expression: &str, message: &DynamicMessage) -> Result<CelValue, CelError> {
+    Program::compile(expression)?.evaluate(&Activation::from_message(message.clone()))
+}
+
+impl Program {
+    /// Parses a CEL expression.
+    ///
+    /// An error is returned if the expression is not syntactically valid.
+    pub fn compile(source: &str) -> Result<Self, CelError> {
+        let expr = Parser::new(source)?.parse()?;
+        Ok(Program {
+            source: source.to_owned(),
+            expr,
+        })
+    }
+
+    /// Gets the source text of the expression.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression.
+    ///
+    /// An error is returned if the expression refers to an unknown variable, field or function,
+    /// or applies an operator to values of the wrong type. As in CEL, errors in one operand of
+    /// `&&`, `||`, `all` or `exists` are ignored if the result is determined by another operand.
+    pub fn evaluate(&self, activation: &Activation) -> Result<CelValue, CelError> {
+        Evaluator::new(activation)
+            .eval(&self.expr)
+            .map_err(|message| CelErrorKind::Evaluation { message }.into())
+    }
+}
+
+impl Activation {
+    /// Creates a new activation with no variables.
+    pub fn new() -> Self {
+        Activation::default()
+    }
+
+    /// Creates an activation where each field of `message` is available as a variable.
+    ///
+    /// Fields are converted to CEL values as they are used, following the rules for field
+    /// selection, so an unset field of message type evaluates to its default value. Variables
+    /// added with [`with_variable`][Activation::with_variable] take precedence over fields.
+    pub fn from_message(message: DynamicMessage) -> Self {
+        Activation {
+            message: Some(message),
+            ..Default::default()
+        }
+    }
+
+    /// Adds a variable.
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl Into<CelValue>) -> Self {
+        self.add_variable(name, value);
+        self
+    }
+
+    /// Adds a variable, replacing any existing variable with the same name.
+    pub fn add_variable(&mut self, name: impl Into<String>, value: impl Into<CelValue>) {
+        self.variables.insert(name.into(), value.into());
+    }
+
+    /// Adds a function, which takes precedence over any built-in function with the same name.
+    ///
+    /// The function is used for both global calls such as `f(x)` and method calls such as
+    /// `x.f()`. For method calls, the target is passed as the first argument. If the function
+    /// returns an error, it is reported as the result of the call.
+    pub fn add_function<F>(&mut self, name: impl Into<String>, function: F)
+    where
+        F: Fn(&[CelValue]) -> Result<CelValue, String> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Arc::new(function));
+    }
+}
+
+impl fmt::Debug for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Activation")
+            .field("message", &self.message)
+            .field("variables", &self.variables)
+            .field("functions", &self.functions.keys())
+            .finish()
+    }
+}
+
+impl CelError {
+    /// Returns the range of bytes in the expression which caused a syntax error, or `None` if
+    /// the error occurred during evaluation.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match &self.kind {
+            CelErrorKind::Syntax { span, .. } => Some(span.clone()),
+            CelErrorKind::Evaluation { .. } => None,
+        }
+    }
+}
+
+impl From<CelErrorKind> for CelError {
+    fn from(kind: CelErrorKind) -> Self {
+        CelError { kind }
+    }
+}
+
+impl fmt::Display for CelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CelErrorKind::Syntax { message, span } => {
+                write!(f, "syntax error at offset {}: {}", span.start, message)
+            }
+            CelErrorKind::Evaluation { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for CelError {}
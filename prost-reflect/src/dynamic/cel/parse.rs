@@ -0,0 +1,477 @@
+use std::ops::Range;
+
+use prost::bytes::Bytes;
+
+use super::{
+    lex::{Lexer, Token},
+    CelErrorKind, CelValue,
+};
+
+/// The maximum nesting depth of an expression, to avoid overflowing the stack.
+const RECURSION_LIMIT: usize = 100;
+
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Literal(CelValue),
+    Ident(String),
+    Select {
+        operand: Box<Expr>,
+        field: String,
+    },
+    Has {
+        operand: Box<Expr>,
+        field: String,
+    },
+    Index {
+        operand: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Call {
+        target: Option<Box<Expr>>,
+        function: String,
+        args: Vec<Expr>,
+    },
+    List(Vec<Expr>),
+    Map(Vec<(Expr, Expr)>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Comprehension {
+        kind: MacroKind,
+        range: Box<Expr>,
+        var: String,
+        filter: Option<Box<Expr>>,
+        body: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum UnaryOp {
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    In,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum MacroKind {
+    All,
+    Exists,
+    ExistsOne,
+    Map,
+    Filter,
+}
+
+pub(super) struct Parser {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+    depth: usize,
+    len: usize,
+}
+
+impl Parser {
+    pub fn new(source: &str) -> Result<Self, CelErrorKind> {
+        Ok(Parser {
+            tokens: Lexer::new(source).tokenize()?,
+            pos: 0,
+            depth: 0,
+            len: source.len(),
+        })
+    }
+
+    pub fn parse(mut self) -> Result<Expr, CelErrorKind> {
+        let expr = self.parse_expr()?;
+        match self.tokens.get(self.pos) {
+            None => Ok(expr),
+            Some((token, span)) => Err(syntax_error(
+                format!("unexpected '{}'", token),
+                span.clone(),
+            )),
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, CelErrorKind> {
+        self.enter()?;
+        let condition = self.parse_or()?;
+        let result = if self.eat(&Token::Question) {
+            let if_true = self.parse_or()?;
+            self.expect(&Token::Colon)?;
+            let if_false = self.parse_expr()?;
+            Expr::Conditional(Box::new(condition), Box::new(if_true), Box::new(if_false))
+        } else {
+            condition
+        };
+
+        self.depth -= 1;
+        Ok(result)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, CelErrorKind> {
+        let mut lhs = self.parse_and()?;
+        while self.eat(&Token::Or) {
+            let rhs = self.parse_and()?;
+            lhs = Expr::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, CelErrorKind> {
+        let mut lhs = self.parse_relation()?;
+        while self.eat(&Token::And) {
+            let rhs = self.parse_relation()?;
+            lhs = Expr::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_relation(&mut self) -> Result<Expr, CelErrorKind> {
+        let mut lhs = self.parse_addition()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Equal) => BinaryOp::Equal,
+                Some(Token::NotEqual) => BinaryOp::NotEqual,
+                Some(Token::Less) => BinaryOp::Less,
+                Some(Token::LessEqual) => BinaryOp::LessEqual,
+                Some(Token::Greater) => BinaryOp::Greater,
+                Some(Token::GreaterEqual) => BinaryOp::GreaterEqual,
+                Some(Token::Ident(ident)) if ident == "in" => BinaryOp::In,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.parse_addition()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_addition(&mut self) -> Result<Expr, CelErrorKind> {
+        let mut lhs = self.parse_multiplication()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Plus) => BinaryOp::Add,
+                Some(Token::Minus) => BinaryOp::Subtract,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.parse_multiplication()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_multiplication(&mut self) -> Result<Expr, CelErrorKind> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Star) => BinaryOp::Multiply,
+                Some(Token::Slash) => BinaryOp::Divide,
+                Some(Token::Percent) => BinaryOp::Remainder,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.parse_unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, CelErrorKind> {
+        self.enter()?;
+        let result = self.parse_unary_inner();
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_unary_inner(&mut self) -> Result<Expr, CelErrorKind> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                let operand = self.parse_unary()?;
+                Ok(Expr::Unary(UnaryOp::Not, Box::new(operand)))
+            }
+            Some(Token::Minus) => {
+                self.pos += 1;
+                // Negative integer literals are parsed directly, so that the minimum value of an
+                // int does not overflow.
+                if let Some(&(Token::Int(value), ref span)) = self.tokens.get(self.pos) {
+                    let span = span.clone();
+                    self.pos += 1;
+                    let value = if value == i64::MIN.unsigned_abs() {
+                        i64::MIN
+                    } else {
+                        i64::try_from(value)
+                            .map(|value| -value)
+                            .map_err(|_| syntax_error("integer literal is out of range", span))?
+                    };
+                    return self.parse_member_suffix(Expr::Literal(CelValue::Int(value)));
+                }
+                let operand = self.parse_unary()?;
+                Ok(Expr::Unary(UnaryOp::Negate, Box::new(operand)))
+            }
+            _ => {
+                let primary = self.parse_primary()?;
+                self.parse_member_suffix(primary)
+            }
+        }
+    }
+
+    fn parse_member_suffix(&mut self, mut expr: Expr) -> Result<Expr, CelErrorKind> {
+        loop {
+            if self.eat(&Token::Dot) {
+                let field = self.expect_ident()?;
+                if self.eat(&Token::LeftParen) {
+                    let args = self.parse_list(&Token::RightParen)?;
+                    expr = self.method_call(expr, field, args)?;
+                } else {
+                    expr = Expr::Select {
+                        operand: Box::new(expr),
+                        field,
+                    };
+                }
+            } else if self.eat(&Token::LeftBracket) {
+                let index = self.parse_expr()?;
+                self.expect(&Token::RightBracket)?;
+                expr = Expr::Index {
+                    operand: Box::new(expr),
+                    index: Box::new(index),
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, CelErrorKind> {
+        let (token, span) = match self.tokens.get(self.pos) {
+            Some((token, span)) => (token.clone(), span.clone()),
+            None => {
+                return Err(syntax_error(
+                    "unexpected end of expression",
+                    self.len..self.len,
+                ))
+            }
+        };
+        self.pos += 1;
+
+        match token {
+            Token::Int(value) => i64::try_from(value)
+                .map(|value| Expr::Literal(CelValue::Int(value)))
+                .map_err(|_| syntax_error("integer literal is out of range", span)),
+            Token::Uint(value) => Ok(Expr::Literal(CelValue::Uint(value))),
+            Token::Double(value) => Ok(Expr::Literal(CelValue::Double(value))),
+            Token::String(value) => Ok(Expr::Literal(CelValue::String(value))),
+            Token::Bytes(value) => Ok(Expr::Literal(CelValue::Bytes(Bytes::from(value)))),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Expr::Literal(CelValue::Bool(true))),
+                "false" => Ok(Expr::Literal(CelValue::Bool(false))),
+                "null" => Ok(Expr::Literal(CelValue::Null)),
+                _ if self.eat(&Token::LeftParen) => {
+                    let args = self.parse_list(&Token::RightParen)?;
+                    self.global_call(ident, args, span)
+                }
+                _ if self.peek() == Some(&Token::LeftBrace) => {
+                    Err(syntax_error("message construction is not supported", span))
+                }
+                _ => Ok(Expr::Ident(ident)),
+            },
+            Token::Dot => {
+                let ident = self.expect_ident()?;
+                Ok(Expr::Ident(ident))
+            }
+            Token::LeftParen => {
+                let expr = self.parse_expr()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expr)
+            }
+            Token::LeftBracket => Ok(Expr::List(self.parse_list(&Token::RightBracket)?)),
+            Token::LeftBrace => {
+                let mut entries = Vec::new();
+                while !self.eat(&Token::RightBrace) {
+                    let key = self.parse_expr()?;
+                    self.expect(&Token::Colon)?;
+                    let value = self.parse_expr()?;
+                    entries.push((key, value));
+                    if !self.eat(&Token::Comma) {
+                        self.expect(&Token::RightBrace)?;
+                        break;
+                    }
+                }
+                Ok(Expr::Map(entries))
+            }
+            token => Err(syntax_error(format!("unexpected '{}'", token), span)),
+        }
+    }
+
+    /// Parses a comma-separated list of expressions, allowing a trailing comma.
+    fn parse_list(&mut self, end: &Token) -> Result<Vec<Expr>, CelErrorKind> {
+        let mut items = Vec::new();
+        while !self.eat(end) {
+            items.push(self.parse_expr()?);
+            if !self.eat(&Token::Comma) {
+                self.expect(end)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    fn global_call(
+        &mut self,
+        function: String,
+        mut args: Vec<Expr>,
+        span: Range<usize>,
+    ) -> Result<Expr, CelErrorKind> {
+        if function == "has" {
+            return match args.pop() {
+                Some(Expr::Select { operand, field }) if args.is_empty() => {
+                    Ok(Expr::Has { operand, field })
+                }
+                _ => Err(syntax_error(
+                    "the argument of 'has' must be a field selection",
+                    span,
+                )),
+            };
+        }
+
+        Ok(Expr::Call {
+            target: None,
+            function,
+            args,
+        })
+    }
+
+    fn method_call(
+        &mut self,
+        target: Expr,
+        function: String,
+        mut args: Vec<Expr>,
+    ) -> Result<Expr, CelErrorKind> {
+        let kind = match function.as_str() {
+            "all" => Some(MacroKind::All),
+            "exists" => Some(MacroKind::Exists),
+            "exists_one" => Some(MacroKind::ExistsOne),
+            "map" => Some(MacroKind::Map),
+            "filter" => Some(MacroKind::Filter),
+            _ => None,
+        };
+
+        let kind = match kind {
+            Some(kind) if args.len() == 2 || (kind == MacroKind::Map && args.len() == 3) => kind,
+            _ => {
+                return Ok(Expr::Call {
+                    target: Some(Box::new(target)),
+                    function,
+                    args,
+                })
+            }
+        };
+
+        let body = args.pop().expect("macro has arguments");
+        let filter = if args.len() == 2 { args.pop() } else { None };
+        let var = match args.pop() {
+            Some(Expr::Ident(var)) => var,
+            _ => {
+                return Err(syntax_error(
+                    format!("the first argument of '{}' must be an identifier", function),
+                    self.peek_span(),
+                ))
+            }
+        };
+
+        Ok(Expr::Comprehension {
+            kind,
+            range: Box::new(target),
+            var,
+            filter: filter.map(Box::new),
+            body: Box::new(body),
+        })
+    }
+
+    fn enter(&mut self) -> Result<(), CelErrorKind> {
+        self.depth += 1;
+        if self.depth > RECURSION_LIMIT {
+            Err(syntax_error(
+                "expression is nested too deeply",
+                self.peek_span(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<String, CelErrorKind> {
+        match self.tokens.get(self.pos) {
+            Some((Token::Ident(ident), _)) => {
+                self.pos += 1;
+                Ok(ident.clone())
+            }
+            Some((token, span)) => Err(syntax_error(
+                format!("expected an identifier, but found '{}'", token),
+                span.clone(),
+            )),
+            None => Err(syntax_error(
+                "expected an identifier, but reached the end of the expression",
+                self.len..self.len,
+            )),
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), CelErrorKind> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) if token == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some((token, span)) => Err(syntax_error(
+                format!("expected '{}', but found '{}'", expected, token),
+                span.clone(),
+            )),
+            None => Err(syntax_error(
+                format!(
+                    "expected '{}', but reached the end of the expression",
+                    expected
+                ),
+                self.len..self.len,
+            )),
+        }
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn peek_span(&self) -> Range<usize> {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.len..self.len, |(_, span)| span.clone())
+    }
+}
+
+fn syntax_error(message: impl Into<String>, span: Range<usize>) -> CelErrorKind {
+    CelErrorKind::Syntax {
+        message: message.into(),
+        span,
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+
+use prost::bytes::Bytes;
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MapKey, Value};
+
+/// A value produced or consumed by a CEL expression.
+///
+/// Protobuf values are converted to CEL values using the standard CEL mapping: all signed integer
+/// types become [`Int`][CelValue::Int], unsigned integer types become [`Uint`][CelValue::Uint],
+/// floating point types become [`Double`][CelValue::Double] and enums become their number. The
+/// well-known types are unwrapped, so `google.protobuf.Duration` and `google.protobuf.Timestamp`
+/// messages become [`Duration`][CelValue::Duration] and [`Timestamp`][CelValue::Timestamp], wrapper
+/// types become their inner value (or `null` if unset), and `google.protobuf.Struct`,
+/// `google.protobuf.ListValue` and `google.protobuf.Value` become maps, lists and dynamic values.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CelValue {
+    /// The `null` value.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// A signed 64-bit integer.
+    Int(i64),
+    /// An unsigned 64-bit integer.
+    Uint(u64),
+    /// A 64-bit floating point number.
+    Double(f64),
+    /// A string.
+    String(String),
+    /// A byte string.
+    Bytes(Bytes),
+    /// A list of values.
+    List(Vec<CelValue>),
+    /// A map of values, ordered by key.
+    Map(BTreeMap<CelKey, CelValue>),
+    /// A protobuf message.
+    Message(DynamicMessage),
+    /// A `google.protobuf.Duration`.
+    Duration(prost_types::Duration),
+    /// A `google.protobuf.Timestamp`.
+    Timestamp(prost_types::Timestamp),
+    /// A type, such as the result of `type(1)`, identified by its name.
+    Type(String),
+}
+
+/// The key of a map in a [`CelValue`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CelKey {
+    /// A boolean key.
+    Bool(bool),
+    /// A signed integer key.
+    Int(i64),
+    /// An unsigned integer key.
+    Uint(u64),
+    /// A string key.
+    String(String),
+}
+
+impl CelValue {
+    /// Returns the value as a `bool`, if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            CelValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a `&str`, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            CelValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Gets the CEL name of the type of this value, such as `int` or `google.protobuf.Duration`.
+    pub fn type_name(&self) -> &str {
+        match self {
+            CelValue::Null => "null_type",
+            CelValue::Bool(_) => "bool",
+            CelValue::Int(_) => "int",
+            CelValue::Uint(_) => "uint",
+            CelValue::Double(_) => "double",
+            CelValue::String(_) => "string",
+            CelValue::Bytes(_) => "bytes",
+            CelValue::List(_) => "list",
+            CelValue::Map(_) => "map",
+            CelValue::Message(message) => message.desc.full_name(),
+            CelValue::Duration(_) => "google.protobuf.Duration",
+            CelValue::Timestamp(_) => "google.protobuf.Timestamp",
+            CelValue::Type(_) => "type",
+        }
+    }
+
+    /// Gets the value of a field of a message, following the CEL rules for unset fields.
+    ///
+    /// Unset wrapper and `google.protobuf.Value` fields are `null`, and other unset fields have
+    /// their default value.
+    pub(super) fn from_field(message: &DynamicMessage, field: &FieldDescriptor) -> Self {
+        if !field.is_list() && !field.is_map() && !message.has_field(field) {
+            if let Kind::Message(desc) = field.kind() {
+                if is_wrapper(desc.full_name()) || desc.full_name() == "google.protobuf.Value" {
+                    return CelValue::Null;
+                }
+            }
+        }
+        CelValue::from(message.get_field(field).into_owned())
+    }
+
+    pub(super) fn into_key(self) -> Option<CelKey> {
+        match self {
+            CelValue::Bool(value) => Some(CelKey::Bool(value)),
+            CelValue::Int(value) => Some(CelKey::Int(value)),
+            CelValue::Uint(value) => Some(CelKey::Uint(value)),
+            CelValue::String(value) => Some(CelKey::String(value)),
+            _ => None,
+        }
+    }
+}
+
+impl From<CelKey> for CelValue {
+    fn from(key: CelKey) -> Self {
+        match key {
+            CelKey::Bool(value) => CelValue::Bool(value),
+            CelKey::Int(value) => CelValue::Int(value),
+            CelKey::Uint(value) => CelValue::Uint(value),
+            CelKey::String(value) => CelValue::String(value),
+        }
+    }
+}
+
+impl From<MapKey> for CelKey {
+    fn from(key: MapKey) -> Self {
+        match key {
+            MapKey::Bool(value) => CelKey::Bool(value),
+            MapKey::I32(value) => CelKey::Int(value.into()),
+            MapKey::I64(value) => CelKey::Int(value),
+            MapKey::U32(value) => CelKey::Uint(value.into()),
+            MapKey::U64(value) => CelKey::Uint(value),
+            MapKey::String(value) => CelKey::String(value),
+        }
+    }
+}
+
+impl From<Value> for CelValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Bool(value) => CelValue::Bool(value),
+            Value::I32(value) => CelValue::Int(value.into()),
+            Value::I64(value) => CelValue::Int(value),
+            Value::U32(value) => CelValue::Uint(value.into()),
+            Value::U64(value) => CelValue::Uint(value),
+            Value::F32(value) => CelValue::Double(value.into()),
+            Value::F64(value) => CelValue::Double(value),
+            Value::String(value) => CelValue::String(value),
+            Value::Bytes(value) => CelValue::Bytes(value),
+            Value::EnumNumber(value) => CelValue::Int(value.into()),
+            Value::Message(message) => CelValue::from(message),
+            Value::List(values) => CelValue::List(values.into_iter().map(CelValue::from).collect()),
+            Value::Map(values) => CelValue::Map(
+                values
+                    .into_iter()
+                    .map(|(key, value)| (CelKey::from(key), CelValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<DynamicMessage> for CelValue {
+    fn from(message: DynamicMessage) -> Self {
+        let field = |number: u32| {
+            message
+                .get_field_by_number(number)
+                .map(|value| CelValue::from(value.into_owned()))
+        };
+
+        match message.desc.full_name() {
+            "google.protobuf.Duration" => CelValue::Duration(prost_types::Duration {
+                seconds: message
+                    .get_field_by_number(1)
+                    .and_then(|value| value.as_i64())
+                    .unwrap_or_default(),
+                nanos: message
+                    .get_field_by_number(2)
+                    .and_then(|value| value.as_i32())
+                    .unwrap_or_default(),
+            }),
+            "google.protobuf.Timestamp" => CelValue::Timestamp(prost_types::Timestamp {
+                seconds: message
+                    .get_field_by_number(1)
+                    .and_then(|value| value.as_i64())
+                    .unwrap_or_default(),
+                nanos: message
+                    .get_field_by_number(2)
+                    .and_then(|value| value.as_i32())
+                    .unwrap_or_default(),
+            }),
+            name if is_wrapper(name) => field(1).unwrap_or(CelValue::Null),
+            "google.protobuf.Struct" | "google.protobuf.ListValue" => {
+                field(1).unwrap_or(CelValue::Null)
+            }
+            "google.protobuf.Value" => {
+                if message.has_field_by_number(1) {
+                    CelValue::Null
+                } else {
+                    (2..=6)
+                        .find(|&number| message.has_field_by_number(number))
+                        .and_then(field)
+                        .unwrap_or(CelValue::Null)
+                }
+            }
+            "google.protobuf.Any" => match message.unpack_any() {
+                Ok(unpacked) => CelValue::from(unpacked),
+                Err(_) => CelValue::Message(message),
+            },
+            _ => CelValue::Message(message),
+        }
+    }
+}
+
+impl From<bool> for CelValue {
+    fn from(value: bool) -> Self {
+        CelValue::Bool(value)
+    }
+}
+
+impl From<i64> for CelValue {
+    fn from(value: i64) -> Self {
+        CelValue::Int(value)
+    }
+}
+
+impl From<u64> for CelValue {
+    fn from(value: u64) -> Self {
+        CelValue::Uint(value)
+    }
+}
+
+impl From<f64> for CelValue {
+    fn from(value: f64) -> Self {
+        CelValue::Double(value)
+    }
+}
+
+impl From<String> for CelValue {
+    fn from(value: String) -> Self {
+        CelValue::String(value)
+    }
+}
+
+impl From<&str> for CelValue {
+    fn from(value: &str) -> Self {
+        CelValue::String(value.to_owned())
+    }
+}
+
+impl From<Vec<CelValue>> for CelValue {
+    fn from(values: Vec<CelValue>) -> Self {
+        CelValue::List(values)
+    }
+}
+
+impl From<prost_types::Duration> for CelValue {
+    fn from(value: prost_types::Duration) -> Self {
+        CelValue::Duration(value)
+    }
+}
+
+impl From<prost_types::Timestamp> for CelValue {
+    fn from(value: prost_types::Timestamp) -> Self {
+        CelValue::Timestamp(value)
+    }
+}
+
+fn is_wrapper(name: &str) -> bool {
+    matches!(
+        name,
+        "google.protobuf.BoolValue"
+            | "google.protobuf.Int32Value"
+            | "google.protobuf.Int64Value"
+            | "google.protobuf.UInt32Value"
+            | "google.protobuf.UInt64Value"
+            | "google.protobuf.FloatValue"
+            | "google.protobuf.DoubleValue"
+            | "google.protobuf.StringValue"
+            | "google.protobuf.BytesValue"
+    )
+}
@@ -0,0 +1,295 @@
+use std::{collections::HashMap, error::Error, fmt, hash::BuildHasher};
+
+use prost::{
+    bytes::{Buf, BufMut},
+    encoding, DecodeError, Message,
+};
+
+use crate::{DynamicMessage, FileDescriptor, MessageDescriptor};
+
+/// The first byte of every message framed in the Confluent wire format.
+const MAGIC_BYTE: u8 = 0;
+
+/// Looks up the schema registered under a schema ID.
+///
+/// Each schema in a Confluent Schema Registry is a single `.proto` file. A resolver returns the
+/// [`FileDescriptor`] for that file, within a [`DescriptorPool`][crate::DescriptorPool] which also
+/// contains its dependencies. This allows the message index path of a framed message to be
+/// resolved to one of the message types defined in the file.
+///
+/// This trait is implemented for:
+///
+/// - [`HashMap`]s from schema IDs to files, for schemas which are known ahead of time.
+/// - Functions and closures with the signature `Fn(u32) -> Option<FileDescriptor>`, which can be
+///   used to plug in a registry client, for example one which fetches and caches schemas.
+pub trait SchemaResolver {
+    /// Returns the file registered under the given schema ID, or `None` if it is not known.
+    fn resolve_schema(&self, schema_id: u32) -> Option<FileDescriptor>;
+}
+
+impl<S> SchemaResolver for HashMap<u32, FileDescriptor, S>
+where
+    S: BuildHasher,
+{
+    fn resolve_schema(&self, schema_id: u32) -> Option<FileDescriptor> {
+        self.get(&schema_id).cloned()
+    }
+}
+
+impl<F> SchemaResolver for F
+where
+    F: Fn(u32) -> Option<FileDescriptor>,
+{
+    fn resolve_schema(&self, schema_id: u32) -> Option<FileDescriptor> {
+        self(schema_id)
+    }
+}
+
+/// The framing which precedes the encoded message in the Confluent wire format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    schema_id: u32,
+    message_indexes: Vec<i32>,
+}
+
+/// An error that occurred while decoding a message framed in the Confluent wire format.
+#[derive(Debug)]
+pub struct ConfluentError {
+    kind: ConfluentErrorKind,
+}
+
+#[derive(Debug)]
+enum ConfluentErrorKind {
+    InvalidMagicByte(Option<u8>),
+    InvalidHeader,
+    UnknownSchema {
+        schema_id: u32,
+    },
+    UnknownMessage {
+        schema_id: u32,
+        message_indexes: Vec<i32>,
+    },
+    Decode(DecodeError),
+}
+
+impl Header {
+    /// Creates the header for a message of type `desc`, registered under the given schema ID.
+    ///
+    /// The message index path is the position of `desc` within its file: the index of the
+    /// top-level message, followed by the index of each nested message leading to `desc`.
+    pub fn new(schema_id: u32, desc: &MessageDescriptor) -> Self {
+        Header {
+            schema_id,
+            message_indexes: desc.path().iter().skip(1).step_by(2).copied().collect(),
+        }
+    }
+
+    /// Gets the ID of the schema which the message was written with.
+    pub fn schema_id(&self) -> u32 {
+        self.schema_id
+    }
+
+    /// Gets the path of indexes identifying the message type within the schema.
+    pub fn message_indexes(&self) -> &[i32] {
+        &self.message_indexes
+    }
+
+    /// Finds the message type identified by the message index path within `file`, or `None` if
+    /// the path does not match a message.
+    pub fn resolve_message(&self, file: &FileDescriptor) -> Option<MessageDescriptor> {
+        let (&first, rest) = self.message_indexes.split_first()?;
+        let desc = file.messages().nth(usize::try_from(first).ok()?)?;
+        rest.iter().try_fold(desc, |desc, &index| {
+            let child = desc.child_messages().nth(usize::try_from(index).ok()?);
+            child
+        })
+    }
+
+    /// Gets the number of bytes required to encode this header.
+    pub fn encoded_len(&self) -> usize {
+        let indexes_len = if self.message_indexes == [0] {
+            1
+        } else {
+            encoding::encoded_len_varint(zigzag(self.message_indexes.len() as i32))
+                + self
+                    .message_indexes
+                    .iter()
+                    .map(|&index| encoding::encoded_len_varint(zigzag(index)))
+                    .sum::<usize>()
+        };
+        5 + indexes_len
+    }
+
+    /// Encodes this header to the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer has less than [`encoded_len`][Self::encoded_len] bytes of remaining
+    /// capacity.
+    pub fn encode<B>(&self, buf: &mut B)
+    where
+        B: BufMut,
+    {
+        buf.put_u8(MAGIC_BYTE);
+        buf.put_u32(self.schema_id);
+        // The common case of the first message in the file is abbreviated to an empty path.
+        if self.message_indexes == [0] {
+            encoding::encode_varint(0, buf);
+        } else {
+            encoding::encode_varint(zigzag(self.message_indexes.len() as i32), buf);
+            for &index in &self.message_indexes {
+                encoding::encode_varint(zigzag(index), buf);
+            }
+        }
+    }
+
+    /// Decodes a header from the start of the buffer, advancing it to the start of the encoded
+    /// message.
+    pub fn decode<B>(buf: &mut B) -> Result<Self, ConfluentError>
+    where
+        B: Buf,
+    {
+        if !buf.has_remaining() || buf.chunk()[0] != MAGIC_BYTE {
+            let byte = buf.has_remaining().then(|| buf.chunk()[0]);
+            return Err(ConfluentErrorKind::InvalidMagicByte(byte).into());
+        }
+        buf.advance(1);
+
+        if buf.remaining() < 4 {
+            return Err(ConfluentErrorKind::InvalidHeader.into());
+        }
+        let schema_id = buf.get_u32();
+
+        let count = decode_zigzag(buf)?;
+        let message_indexes = if count == 0 {
+            vec![0]
+        } else if count < 0 || count as usize > buf.remaining() {
+            return Err(ConfluentErrorKind::InvalidHeader.into());
+        } else {
+            (0..count)
+                .map(|_| decode_zigzag(buf))
+                .collect::<Result<_, _>>()?
+        };
+
+        Ok(Header {
+            schema_id,
+            message_indexes,
+        })
+    }
+}
+
+/// Encodes a message to a vector, preceded by the Confluent wire format header for the given
+/// schema ID.
+///
+/// The message must be defined in the file registered under `schema_id`, so that consumers can
+/// resolve its message index path.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// use prost_reflect::confluent;
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let file = pool.get_file_by_name("doctest.proto").unwrap();
+/// let mut message = DynamicMessage::new(pool.get_message_by_name("package.MyMessage").unwrap());
+/// message.set_field_by_name("foo", Value::I32(150));
+///
+/// let bytes = confluent::encode_to_vec(7, &message);
+/// assert_eq!(bytes, b"\x00\x00\x00\x00\x07\x00\x08\x96\x01");
+///
+/// let resolver = move |schema_id: u32| (schema_id == 7).then(|| file.clone());
+/// assert_eq!(confluent::decode(&resolver, bytes.as_slice()).unwrap(), message);
+/// ```
+pub fn encode_to_vec(schema_id: u32, message: &DynamicMessage) -> Vec<u8> {
+    let header = Header::new(schema_id, &message.desc);
+    let mut buf = Vec::with_capacity(header.encoded_len() + message.encoded_len());
+    header.encode(&mut buf);
+    message
+        .encode(&mut buf)
+        .expect("vectors have unlimited capacity");
+    buf
+}
+
+/// Decodes a message framed in the Confluent wire format.
+///
+/// The schema ID in the header is looked up using `resolver`, and the message type is found from
+/// the message index path within the resulting file.
+pub fn decode<R, B>(resolver: &R, mut buf: B) -> Result<DynamicMessage, ConfluentError>
+where
+    R: SchemaResolver + ?Sized,
+    B: Buf,
+{
+    let header = Header::decode(&mut buf)?;
+    let file =
+        resolver
+            .resolve_schema(header.schema_id)
+            .ok_or(ConfluentErrorKind::UnknownSchema {
+                schema_id: header.schema_id,
+            })?;
+    let desc = match header.resolve_message(&file) {
+        Some(desc) => desc,
+        None => {
+            return Err(ConfluentErrorKind::UnknownMessage {
+                schema_id: header.schema_id,
+                message_indexes: header.message_indexes,
+            }
+            .into())
+        }
+    };
+    DynamicMessage::decode(desc, buf).map_err(|err| ConfluentErrorKind::Decode(err).into())
+}
+
+fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+fn decode_zigzag<B>(buf: &mut B) -> Result<i32, ConfluentError>
+where
+    B: Buf,
+{
+    let value = encoding::decode_varint(buf).map_err(ConfluentErrorKind::Decode)?;
+    let value = u32::try_from(value).map_err(|_| ConfluentErrorKind::InvalidHeader)?;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
+
+impl From<ConfluentErrorKind> for ConfluentError {
+    fn from(kind: ConfluentErrorKind) -> Self {
+        ConfluentError { kind }
+    }
+}
+
+impl fmt::Display for ConfluentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConfluentErrorKind::InvalidMagicByte(None) => {
+                write!(f, "expected the magic byte, but the buffer is empty")
+            }
+            ConfluentErrorKind::InvalidMagicByte(Some(byte)) => {
+                write!(f, "expected the magic byte 0, but found {}", byte)
+            }
+            ConfluentErrorKind::InvalidHeader => write!(f, "invalid wire format header"),
+            ConfluentErrorKind::UnknownSchema { schema_id } => {
+                write!(f, "schema {} was not found", schema_id)
+            }
+            ConfluentErrorKind::UnknownMessage {
+                schema_id,
+                message_indexes,
+            } => write!(
+                f,
+                "message index path {:?} does not match a message in schema {}",
+                message_indexes, schema_id
+            ),
+            ConfluentErrorKind::Decode(err) => write!(f, "failed to decode message: {}", err),
+        }
+    }
+}
+
+impl Error for ConfluentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ConfluentErrorKind::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
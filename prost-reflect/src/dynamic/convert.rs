@@ -0,0 +1,300 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+};
+
+use prost::bytes::Bytes;
+
+use crate::{DynamicMessage, MapKey, Value};
+
+macro_rules! impl_value_conversions {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::$variant(value)
+                }
+            }
+
+            impl TryFrom<Value> for $ty {
+                type Error = Value;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    match value {
+                        Value::$variant(value) => Ok(value),
+                        value => Err(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_value_conversions! {
+    bool => Bool,
+    i32 => I32,
+    i64 => I64,
+    u32 => U32,
+    u64 => U64,
+    f32 => F32,
+    f64 => F64,
+    String => String,
+    Bytes => Bytes,
+    DynamicMessage => Message,
+}
+
+macro_rules! impl_map_key_conversions {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$ty> for MapKey {
+                fn from(value: $ty) -> Self {
+                    MapKey::$variant(value)
+                }
+            }
+
+            impl TryFrom<MapKey> for $ty {
+                type Error = MapKey;
+
+                fn try_from(value: MapKey) -> Result<Self, Self::Error> {
+                    match value {
+                        MapKey::$variant(value) => Ok(value),
+                        value => Err(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_map_key_conversions! {
+    bool => Bool,
+    i32 => I32,
+    i64 => I64,
+    u32 => U32,
+    u64 => U64,
+    String => String,
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_owned())
+    }
+}
+
+impl From<&str> for MapKey {
+    fn from(value: &str) -> Self {
+        MapKey::String(value.to_owned())
+    }
+}
+
+impl<T> From<Vec<T>> for Value
+where
+    T: Into<Value>,
+{
+    fn from(value: Vec<T>) -> Self {
+        Value::List(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K, V, S> From<HashMap<K, V, S>> for Value
+where
+    K: Into<MapKey>,
+    V: Into<Value>,
+{
+    fn from(value: HashMap<K, V, S>) -> Self {
+        Value::Map(
+            value
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+/// Converts an optional value, using the default value of `T` for `None`.
+///
+/// This matches fields without presence tracking, for which an unset field is equivalent to one
+/// set to its default value.
+impl<T> From<Option<T>> for Value
+where
+    T: Into<Value> + Default,
+{
+    fn from(value: Option<T>) -> Self {
+        value.unwrap_or_default().into()
+    }
+}
+
+/// Converts a `Value::List` into a vector, converting each element.
+///
+/// If the value is not a list, or any element fails to convert, the original value is returned
+/// as the error.
+impl<T> TryFrom<Value> for Vec<T>
+where
+    T: TryFrom<Value, Error = Value> + Into<Value>,
+{
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let list = match value {
+            Value::List(list) => list,
+            value => return Err(value),
+        };
+
+        let mut result = Vec::with_capacity(list.len());
+        let mut iter = list.into_iter();
+        while let Some(item) = iter.next() {
+            match T::try_from(item) {
+                Ok(item) => result.push(item),
+                Err(item) => {
+                    let list = result
+                        .into_iter()
+                        .map(Into::into)
+                        .chain(Some(item))
+                        .chain(iter)
+                        .collect();
+                    return Err(Value::List(list));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Converts a `Value::Map` into a hash map, converting each key and value.
+///
+/// If the value is not a map, or any entry fails to convert, the original value is returned
+/// as the error.
+impl<K, V, S> TryFrom<Value> for HashMap<K, V, S>
+where
+    K: TryFrom<MapKey, Error = MapKey> + Into<MapKey> + Eq + Hash,
+    V: TryFrom<Value, Error = Value> + Into<Value>,
+    S: BuildHasher + Default,
+{
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let map = match value {
+            Value::Map(map) => map,
+            value => return Err(value),
+        };
+
+        let mut result = HashMap::with_capacity_and_hasher(map.len(), S::default());
+        let mut iter = map.into_iter();
+        while let Some((key, value)) = iter.next() {
+            match (K::try_from(key), V::try_from(value)) {
+                (Ok(key), Ok(value)) => {
+                    result.insert(key, value);
+                }
+                (key, value) => {
+                    let key = key.map(Into::into).unwrap_or_else(|key| key);
+                    let value = value.map(Into::into).unwrap_or_else(|value| value);
+                    let map = result
+                        .into_iter()
+                        .map(|(key, value)| (key.into(), value.into()))
+                        .chain(Some((key, value)))
+                        .chain(iter)
+                        .collect();
+                    return Err(Value::Map(map));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Converts a value into an optional value, returning `None` if it is the default value of `T`.
+///
+/// This is the inverse of the `From<Option<T>>` conversion, so `Some` of a default value is
+/// converted back to `None`. If the value fails to convert, the original value is returned as the
+/// error.
+impl<T> TryFrom<Value> for Option<T>
+where
+    T: TryFrom<Value, Error = Value> + Default + PartialEq,
+{
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let value = T::try_from(value)?;
+        if value == T::default() {
+            Ok(None)
+        } else {
+            Ok(Some(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::FromIterator;
+
+    use super::*;
+
+    #[test]
+    fn scalars() {
+        assert_eq!(Value::from(5i32), Value::I32(5));
+        assert_eq!(Value::from("foo"), Value::String("foo".to_owned()));
+        assert_eq!(i64::try_from(Value::I64(-3)), Ok(-3));
+        assert_eq!(u32::try_from(Value::I32(1)), Err(Value::I32(1)));
+        assert_eq!(MapKey::from(true), MapKey::Bool(true));
+        assert_eq!(
+            String::try_from(MapKey::String("bar".to_owned())),
+            Ok("bar".to_owned())
+        );
+    }
+
+    #[test]
+    fn list() {
+        let value = Value::from(vec![1u64, 2, 3]);
+        assert_eq!(
+            value,
+            Value::List(vec![Value::U64(1), Value::U64(2), Value::U64(3)])
+        );
+        assert_eq!(Vec::<u64>::try_from(value), Ok(vec![1, 2, 3]));
+
+        let invalid = Value::List(vec![Value::U64(1), Value::I32(2), Value::U64(3)]);
+        assert_eq!(Vec::<u64>::try_from(invalid.clone()), Err(invalid));
+    }
+
+    #[test]
+    fn option() {
+        assert_eq!(Value::from(Some(5i32)), Value::I32(5));
+        assert_eq!(Value::from(None::<String>), Value::String(String::new()));
+        assert_eq!(
+            Value::from(Some(vec![1u32])),
+            Value::List(vec![Value::U32(1)])
+        );
+
+        assert_eq!(Option::<i32>::try_from(Value::I32(5)), Ok(Some(5)));
+        assert_eq!(Option::<i32>::try_from(Value::I32(0)), Ok(None));
+        assert_eq!(Option::<Vec<u32>>::try_from(Value::List(vec![])), Ok(None));
+        assert_eq!(Option::<bool>::try_from(Value::I32(1)), Err(Value::I32(1)));
+    }
+
+    #[test]
+    fn map() {
+        let value = Value::from(HashMap::<_, _>::from_iter([("a", 1.5f64), ("b", 2.5)]));
+        assert_eq!(
+            value,
+            Value::Map(HashMap::from_iter([
+                (MapKey::String("a".to_owned()), Value::F64(1.5)),
+                (MapKey::String("b".to_owned()), Value::F64(2.5)),
+            ]))
+        );
+        assert_eq!(
+            HashMap::<String, f64>::try_from(value),
+            Ok(HashMap::from_iter([
+                ("a".to_owned(), 1.5),
+                ("b".to_owned(), 2.5)
+            ]))
+        );
+
+        let invalid = Value::Map(HashMap::from_iter([
+            (MapKey::I32(1), Value::F64(1.5)),
+            (MapKey::I32(2), Value::F32(2.5)),
+            (MapKey::I32(3), Value::F64(3.5)),
+        ]));
+        assert_eq!(HashMap::<i32, f64>::try_from(invalid.clone()), Err(invalid));
+    }
+}
@@ -0,0 +1,663 @@
+use std::{borrow::Cow, collections::HashMap, error::Error, fmt, io};
+
+use base64::{display::Base64Display, prelude::BASE64_STANDARD, Engine};
+use prost::bytes::Bytes;
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value};
+
+/// The columns and values of one row, in column order.
+type Row = Vec<(String, String)>;
+
+/// Options to control how messages are flattened into CSV rows.
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    repeated_fields: RepeatedFieldPolicy,
+    join_separator: char,
+    delimiter: u8,
+}
+
+/// How repeated fields and map fields are flattened into columns.
+///
+/// Map fields are flattened like a repeated field of messages with `key` and `value` fields, with
+/// entries ordered by key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatedFieldPolicy {
+    /// Write a row for each element of the field, so that a message may produce several rows.
+    ///
+    /// If a message has several non-empty repeated fields, a row is written for each combination
+    /// of their elements. Empty repeated fields do not remove the message, but leave their
+    /// columns empty.
+    Explode,
+    /// Write all elements of the field to the same column, separated by the
+    /// [join separator](CsvOptions::join_separator).
+    ///
+    /// For a repeated message field, each of its columns contains the joined values of that field
+    /// in each element.
+    Join,
+    /// Write each element of the field to its own set of columns, with the index of the element
+    /// added to the path, as in `tags.0` and `tags.1`.
+    ///
+    /// The number of columns depends on the longest value of the field in the exported messages.
+    Index,
+}
+
+/// An error that occurred while converting messages to or from CSV.
+#[derive(Debug)]
+pub struct CsvError {
+    kind: CsvErrorKind,
+}
+
+#[derive(Debug)]
+enum CsvErrorKind {
+    Csv(csv1::Error),
+    RecursiveType { name: String },
+    MessageType { expected: String, actual: String },
+    UnknownColumn { column: String },
+    UnsupportedColumn { column: String },
+    InvalidValue { column: String, value: String },
+}
+
+/// A segment of the path of a column.
+enum Segment {
+    Field(FieldDescriptor),
+    Index(usize),
+}
+
+impl CsvOptions {
+    /// Creates a new instance of [`CsvOptions`], with the default options.
+    pub const fn new() -> Self {
+        CsvOptions {
+            repeated_fields: RepeatedFieldPolicy::Join,
+            join_separator: ';',
+            delimiter: b',',
+        }
+    }
+
+    /// How repeated fields and map fields are flattened into columns.
+    ///
+    /// The default value is [`RepeatedFieldPolicy::Join`].
+    pub const fn repeated_fields(mut self, policy: RepeatedFieldPolicy) -> Self {
+        self.repeated_fields = policy;
+        self
+    }
+
+    /// The character used to separate joined elements, when repeated fields are flattened using
+    /// [`RepeatedFieldPolicy::Join`].
+    ///
+    /// Elements which contain the separator cannot be read back correctly. The default value is
+    /// `;`.
+    pub const fn join_separator(mut self, separator: char) -> Self {
+        self.join_separator = separator;
+        self
+    }
+
+    /// The byte used to separate the cells of a row. For example, use `b'\t'` for tab-separated
+    /// values.
+    ///
+    /// The default value is `,`.
+    pub const fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the column headers of a CSV export of `messages`, which must be of type `desc`.
+///
+/// Each column is named by the dot-separated path of field names from `desc` to a scalar or enum
+/// field. The columns do not depend on `messages`, unless repeated fields are flattened using
+/// [`RepeatedFieldPolicy::Index`]. Returns an error if `desc` is recursive.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::DescriptorPool;
+/// use prost_reflect::csv::{self, CsvOptions};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let headers = csv::headers(&message_descriptor, &[], &CsvOptions::new()).unwrap();
+/// assert_eq!(headers, ["type_url", "value"]);
+/// ```
+pub fn headers(
+    desc: &MessageDescriptor,
+    messages: &[DynamicMessage],
+    options: &CsvOptions,
+) -> Result<Vec<String>, CsvError> {
+    check_recursion(desc, &mut vec![desc.clone()])?;
+    if let Some(message) = messages
+        .iter()
+        .find(|message| message.descriptor() != *desc)
+    {
+        return Err(CsvErrorKind::MessageType {
+            expected: desc.full_name().to_owned(),
+            actual: message.descriptor().full_name().to_owned(),
+        }
+        .into());
+    }
+
+    let messages: Vec<&DynamicMessage> = messages.iter().collect();
+    let mut headers = Vec::new();
+    message_headers(desc, "", &messages, options, &mut headers);
+    Ok(headers)
+}
+
+/// Writes `messages`, which must be of type `desc`, to `writer` as CSV.
+///
+/// The first row contains the column headers returned by [`headers`]. Each message is written as
+/// one row, or several rows if repeated fields are flattened using
+/// [`RepeatedFieldPolicy::Explode`]. Values are written using the same representation as the
+/// [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json), except that
+/// 64-bit integers are not quoted. Cells for unset fields which support presence are left
+/// empty, while other fields are written even if they have their default value.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+/// use prost_reflect::csv::{self, CsvOptions};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let mut message = DynamicMessage::new(message_descriptor.clone());
+/// message.set_field_by_name("type_url", Value::String("type.googleapis.com/Foo".to_owned()));
+///
+/// let mut buf = Vec::new();
+/// csv::to_writer(&mut buf, &message_descriptor, &[message], &CsvOptions::new()).unwrap();
+/// assert_eq!(buf, b"type_url,value\ntype.googleapis.com/Foo,\n");
+/// ```
+pub fn to_writer<W>(
+    writer: W,
+    desc: &MessageDescriptor,
+    messages: &[DynamicMessage],
+    options: &CsvOptions,
+) -> Result<(), CsvError>
+where
+    W: io::Write,
+{
+    let headers = headers(desc, messages, options)?;
+    let columns: HashMap<&str, usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.as_str(), index))
+        .collect();
+
+    let mut writer = csv1::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .from_writer(writer);
+    writer.write_record(&headers).map_err(CsvErrorKind::Csv)?;
+
+    let mut record = vec![String::new(); headers.len()];
+    for message in messages {
+        for row in flatten_message(message, "", options) {
+            record.iter_mut().for_each(String::clear);
+            for (column, value) in row {
+                record[columns[column.as_str()]] = value;
+            }
+            writer.write_record(&record).map_err(CsvErrorKind::Csv)?;
+        }
+    }
+
+    writer
+        .flush()
+        .map_err(|err| CsvErrorKind::Csv(err.into()))?;
+    Ok(())
+}
+
+/// Reads messages of type `desc` from CSV written by [`to_writer`], one for each row.
+///
+/// Each column header must be the path of a field, as described in [`headers`], and empty cells
+/// leave their field unset. Only simple schemas can be read back: map fields are not supported,
+/// and repeated fields are only supported when flattened using [`RepeatedFieldPolicy::Index`],
+/// or [`RepeatedFieldPolicy::Join`] for repeated fields which do not contain messages.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, Value};
+/// use prost_reflect::csv::{self, CsvOptions};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let data = "type_url,value\ntype.googleapis.com/Foo,AQI=\n";
+///
+/// let messages = csv::from_reader(data.as_bytes(), message_descriptor, &CsvOptions::new()).unwrap();
+/// assert_eq!(messages.len(), 1);
+/// assert_eq!(messages[0].get_field_by_name("value").unwrap().as_bytes().unwrap().as_ref(), b"\x01\x02");
+/// ```
+pub fn from_reader<R>(
+    reader: R,
+    desc: MessageDescriptor,
+    options: &CsvOptions,
+) -> Result<Vec<DynamicMessage>, CsvError>
+where
+    R: io::Read,
+{
+    let mut reader = csv1::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .from_reader(reader);
+
+    let headers = reader.headers().map_err(CsvErrorKind::Csv)?.clone();
+    let columns = headers
+        .iter()
+        .map(|column| resolve_column(&desc, column, options))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut messages = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(CsvErrorKind::Csv)?;
+
+        let mut message = DynamicMessage::new(desc.clone());
+        for ((column, path), cell) in headers.iter().zip(&columns).zip(record.iter()) {
+            if !cell.is_empty() {
+                set_path(&mut message, path, column, cell, options)?;
+            }
+        }
+        messages.push(message);
+    }
+    Ok(messages)
+}
+
+fn check_recursion(
+    desc: &MessageDescriptor,
+    stack: &mut Vec<MessageDescriptor>,
+) -> Result<(), CsvError> {
+    for field in desc.fields() {
+        if let Kind::Message(inner) = field.kind() {
+            if stack.contains(&inner) {
+                return Err(CsvErrorKind::RecursiveType {
+                    name: inner.full_name().to_owned(),
+                }
+                .into());
+            }
+
+            stack.push(inner.clone());
+            check_recursion(&inner, stack)?;
+            stack.pop();
+        }
+    }
+    Ok(())
+}
+
+fn message_headers(
+    desc: &MessageDescriptor,
+    prefix: &str,
+    messages: &[&DynamicMessage],
+    options: &CsvOptions,
+    headers: &mut Vec<String>,
+) {
+    for field in desc.fields() {
+        let path = join_path(prefix, field.name());
+        let is_repeated = field.is_list() || field.is_map();
+
+        if is_repeated && options.repeated_fields == RepeatedFieldPolicy::Index {
+            let elements: Vec<Vec<Cow<Value>>> = messages
+                .iter()
+                .map(|message| elements(message, &field))
+                .collect();
+            let len = elements.iter().map(Vec::len).max().unwrap_or(0);
+
+            for index in 0..len {
+                let path = format!("{}.{}", path, index);
+                match field.kind() {
+                    Kind::Message(inner) => {
+                        let inner_messages: Vec<&DynamicMessage> = elements
+                            .iter()
+                            .filter_map(|elements| elements.get(index))
+                            .filter_map(|value| value.as_message())
+                            .collect();
+                        message_headers(&inner, &path, &inner_messages, options, headers);
+                    }
+                    _ => headers.push(path),
+                }
+            }
+        } else if let Kind::Message(inner) = field.kind() {
+            let inner_messages: Vec<&DynamicMessage> = messages
+                .iter()
+                .filter_map(|message| get_message(message, &field))
+                .collect();
+            message_headers(&inner, &path, &inner_messages, options, headers);
+        } else {
+            headers.push(path);
+        }
+    }
+}
+
+fn flatten_message(message: &DynamicMessage, prefix: &str, options: &CsvOptions) -> Vec<Row> {
+    let mut rows = vec![Row::new()];
+    for field in message.descriptor().fields() {
+        let path = join_path(prefix, field.name());
+        let kind = field.kind();
+
+        if field.is_list() || field.is_map() {
+            let elements = elements(message, &field);
+            match options.repeated_fields {
+                RepeatedFieldPolicy::Explode => {
+                    let variants = elements
+                        .iter()
+                        .flat_map(|value| flatten_value(value, &kind, &path, options))
+                        .collect();
+                    rows = product(rows, variants);
+                }
+                RepeatedFieldPolicy::Join => {
+                    let mut joined = Row::new();
+                    for value in &elements {
+                        for (column, value) in flatten_value(value, &kind, &path, options)
+                            .into_iter()
+                            .flatten()
+                        {
+                            match joined.iter_mut().find(|(existing, _)| *existing == column) {
+                                Some((_, existing)) => {
+                                    existing.push(options.join_separator);
+                                    existing.push_str(&value);
+                                }
+                                None => joined.push((column, value)),
+                            }
+                        }
+                    }
+                    rows = product(rows, vec![joined]);
+                }
+                RepeatedFieldPolicy::Index => {
+                    for (index, value) in elements.iter().enumerate() {
+                        let path = format!("{}.{}", path, index);
+                        rows = product(rows, flatten_value(value, &kind, &path, options));
+                    }
+                }
+            }
+        } else if !field.supports_presence() || message.has_field(&field) {
+            let value = message.get_field(&field);
+            rows = product(rows, flatten_value(&value, &kind, &path, options));
+        }
+    }
+    rows
+}
+
+fn flatten_value(value: &Value, kind: &Kind, path: &str, options: &CsvOptions) -> Vec<Row> {
+    match value {
+        Value::Message(message) => flatten_message(message, path, options),
+        value => vec![vec![(path.to_owned(), format_scalar(value, kind))]],
+    }
+}
+
+/// Returns a row for each combination of a row from `rows` with a row from `variants`.
+fn product(rows: Vec<Row>, variants: Vec<Row>) -> Vec<Row> {
+    match variants.len() {
+        0 => rows,
+        1 => {
+            let variant = variants.into_iter().next().unwrap();
+            rows.into_iter()
+                .map(|mut row| {
+                    row.extend(variant.iter().cloned());
+                    row
+                })
+                .collect()
+        }
+        _ => rows
+            .iter()
+            .flat_map(|row| {
+                variants
+                    .iter()
+                    .map(move |variant| row.iter().chain(variant).cloned().collect())
+            })
+            .collect(),
+    }
+}
+
+/// Returns the elements of a list field, or the entries of a map field as messages ordered by key.
+fn elements<'a>(message: &'a DynamicMessage, field: &FieldDescriptor) -> Vec<Cow<'a, Value>> {
+    match message.get_field(field) {
+        Cow::Borrowed(Value::List(list)) => list.iter().map(Cow::Borrowed).collect(),
+        Cow::Borrowed(Value::Map(map)) => {
+            let entry = field.kind();
+            let entry = entry.as_message().expect("map entry should be a message");
+
+            let mut map: Vec<_> = map.iter().collect();
+            map.sort_by_key(|(key, _)| *key);
+
+            map.into_iter()
+                .map(|(key, value)| {
+                    let mut message = DynamicMessage::new(entry.clone());
+                    message.set_field(&entry.map_entry_key_field(), key.clone().into());
+                    message.set_field(&entry.map_entry_value_field(), value.clone());
+                    Cow::Owned(Value::Message(message))
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn get_message<'a>(
+    message: &'a DynamicMessage,
+    field: &FieldDescriptor,
+) -> Option<&'a DynamicMessage> {
+    match message.get_field(field) {
+        Cow::Borrowed(Value::Message(message)) => Some(message),
+        _ => None,
+    }
+}
+
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+fn format_scalar(value: &Value, kind: &Kind) -> String {
+    match value {
+        Value::Bool(value) => value.to_string(),
+        Value::I32(value) => value.to_string(),
+        Value::I64(value) => value.to_string(),
+        Value::U32(value) => value.to_string(),
+        Value::U64(value) => value.to_string(),
+        Value::F32(value) => format_float(f64::from(*value), value.to_string()),
+        Value::F64(value) => format_float(*value, value.to_string()),
+        Value::String(value) => value.clone(),
+        Value::Bytes(value) => Base64Display::new(value, &BASE64_STANDARD).to_string(),
+        Value::EnumNumber(number) => {
+            match kind.as_enum().and_then(|desc| desc.get_value(*number)) {
+                Some(value) => value.name().to_owned(),
+                None => number.to_string(),
+            }
+        }
+        Value::Message(_) | Value::List(_) | Value::Map(_) => {
+            unreachable!("expected scalar value")
+        }
+    }
+}
+
+fn format_float(value: f64, text: String) -> String {
+    if value.is_nan() {
+        "NaN".to_owned()
+    } else if value == f64::INFINITY {
+        "Infinity".to_owned()
+    } else if value == f64::NEG_INFINITY {
+        "-Infinity".to_owned()
+    } else {
+        text
+    }
+}
+
+fn resolve_column(
+    desc: &MessageDescriptor,
+    column: &str,
+    options: &CsvOptions,
+) -> Result<Vec<Segment>, CsvError> {
+    let unknown = || CsvErrorKind::UnknownColumn {
+        column: column.to_owned(),
+    };
+    let unsupported = || CsvErrorKind::UnsupportedColumn {
+        column: column.to_owned(),
+    };
+
+    let mut path = Vec::new();
+    let mut desc = desc.clone();
+    let mut segments = column.split('.').peekable();
+    while let Some(name) = segments.next() {
+        let field = desc.get_field_by_name(name).ok_or_else(unknown)?;
+        let kind = field.kind();
+        let is_list = field.is_list();
+        if field.is_map() {
+            return Err(unsupported().into());
+        }
+        path.push(Segment::Field(field));
+
+        if is_list {
+            match options.repeated_fields {
+                RepeatedFieldPolicy::Index => {
+                    let index = segments
+                        .next()
+                        .and_then(|index| index.parse().ok())
+                        .ok_or_else(unknown)?;
+                    path.push(Segment::Index(index));
+                }
+                RepeatedFieldPolicy::Join if kind.as_message().is_none() => (),
+                RepeatedFieldPolicy::Join | RepeatedFieldPolicy::Explode => {
+                    return Err(unsupported().into())
+                }
+            }
+        }
+
+        match kind {
+            Kind::Message(inner) if segments.peek().is_some() => desc = inner,
+            Kind::Message(_) => return Err(unknown().into()),
+            _ if segments.peek().is_some() => return Err(unknown().into()),
+            _ => (),
+        }
+    }
+    Ok(path)
+}
+
+fn set_path(
+    message: &mut DynamicMessage,
+    path: &[Segment],
+    column: &str,
+    cell: &str,
+    options: &CsvOptions,
+) -> Result<(), CsvError> {
+    match path {
+        [Segment::Field(field)] if field.is_list() => {
+            let values = cell
+                .split(options.join_separator)
+                .map(|text| parse_scalar(field, column, text))
+                .collect::<Result<_, _>>()?;
+            message.set_field(field, Value::List(values));
+        }
+        [Segment::Field(field)] => {
+            let value = parse_scalar(field, column, cell)?;
+            if field.supports_presence() || !value.is_default_for_field(field) {
+                message.set_field(field, value);
+            }
+        }
+        [Segment::Field(field), Segment::Index(index), rest @ ..] => {
+            let value = match rest {
+                [] => Some(parse_scalar(field, column, cell)?),
+                _ => None,
+            };
+
+            let list = message
+                .get_field_mut(field)
+                .as_list_mut()
+                .expect("expected list");
+            if list.len() <= *index {
+                list.resize(index + 1, Value::default_value(&field.kind()));
+            }
+
+            match value {
+                Some(value) => list[*index] = value,
+                None => {
+                    let inner = list[*index].as_message_mut().expect("expected message");
+                    set_path(inner, rest, column, cell, options)?;
+                }
+            }
+        }
+        [Segment::Field(field), rest @ ..] => {
+            let inner = message
+                .get_field_mut(field)
+                .as_message_mut()
+                .expect("expected message");
+            set_path(inner, rest, column, cell, options)?;
+        }
+        _ => unreachable!("invalid column path"),
+    }
+    Ok(())
+}
+
+fn parse_scalar(field: &FieldDescriptor, column: &str, text: &str) -> Result<Value, CsvError> {
+    let value = match field.kind() {
+        Kind::Double => text.parse().ok().map(Value::F64),
+        Kind::Float => text.parse().ok().map(Value::F32),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => text.parse().ok().map(Value::I32),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => text.parse().ok().map(Value::I64),
+        Kind::Uint32 | Kind::Fixed32 => text.parse().ok().map(Value::U32),
+        Kind::Uint64 | Kind::Fixed64 => text.parse().ok().map(Value::U64),
+        Kind::Bool => text.parse().ok().map(Value::Bool),
+        Kind::String => Some(Value::String(text.to_owned())),
+        Kind::Bytes => BASE64_STANDARD
+            .decode(text)
+            .ok()
+            .map(|bytes| Value::Bytes(Bytes::from(bytes))),
+        Kind::Enum(desc) => match desc.get_value_by_name(text) {
+            Some(value) => Some(Value::EnumNumber(value.number())),
+            None => text.parse().ok().map(Value::EnumNumber),
+        },
+        Kind::Message(_) => None,
+    };
+
+    value.ok_or_else(|| {
+        CsvErrorKind::InvalidValue {
+            column: column.to_owned(),
+            value: text.to_owned(),
+        }
+        .into()
+    })
+}
+
+impl From<CsvErrorKind> for CsvError {
+    fn from(kind: CsvErrorKind) -> Self {
+        CsvError { kind }
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            CsvErrorKind::Csv(err) => write!(f, "{}", err),
+            CsvErrorKind::RecursiveType { name } => write!(
+                f,
+                "message '{}' is recursive, so it cannot be flattened into columns",
+                name
+            ),
+            CsvErrorKind::MessageType { expected, actual } => write!(
+                f,
+                "expected messages of type '{}', but found '{}'",
+                expected, actual
+            ),
+            CsvErrorKind::UnknownColumn { column } => {
+                write!(f, "column '{}' does not match a field", column)
+            }
+            CsvErrorKind::UnsupportedColumn { column } => {
+                write!(f, "column '{}' cannot be read back into a message", column)
+            }
+            CsvErrorKind::InvalidValue { column, value } => {
+                write!(f, "invalid value '{}' in column '{}'", value, column)
+            }
+        }
+    }
+}
+
+impl Error for CsvError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            CsvErrorKind::Csv(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use prost::DecodeError;
+
+use crate::{DynamicMessage, ReflectMessage, Value};
+
+const TIMESTAMP_NAME: &str = "google.protobuf.Timestamp";
+const DURATION_NAME: &str = "google.protobuf.Duration";
+
+/// The number of seconds from the Unix epoch to `0001-01-01T00:00:00Z`, the smallest valid
+/// timestamp.
+const MIN_TIMESTAMP_SECONDS: i64 = -62_135_596_800;
+/// The number of seconds from the Unix epoch to `9999-12-31T23:59:59Z`, the largest valid
+/// timestamp.
+const MAX_TIMESTAMP_SECONDS: i64 = 253_402_300_799;
+/// The number of seconds in 10,000 years, the largest valid duration.
+const MAX_DURATION_SECONDS: i64 = 315_576_000_000;
+pub(super) const NANOS_PER_SECOND: i32 = 1_000_000_000;
+
+/// An error that occurred while converting a `google.protobuf.Timestamp` or
+/// `google.protobuf.Duration` message to or from a date or time type, or parsing or formatting a
+/// duration string.
+///
+/// [`TryFrom`] conversions are implemented between [`DynamicMessage`] or [`Value`] and the
+/// following types:
+///
+/// - [`SystemTime`] and [`std::time::Duration`].
+/// - `chrono::DateTime<Utc>` and `chrono::Duration`, if the `chrono` feature is enabled.
+/// - `time::OffsetDateTime` and `time::Duration`, if the `time` feature is enabled.
+///
+/// Values are checked against the ranges allowed by the protobuf definitions: timestamps must be
+/// between `0001-01-01T00:00:00Z` and `9999-12-31T23:59:59.999999999Z`, and durations may be at
+/// most 10,000 years, positive or negative. [`std::time::Duration`] cannot hold negative
+/// durations. Protobuf timestamps do not represent leap seconds, so a leap second from `chrono`
+/// is converted to the last nanosecond before it, `23:59:59.999999999`.
+///
+/// # Examples
+///
+/// ```
+/// # use std::{convert::TryFrom, time::{Duration, SystemTime, UNIX_EPOCH}};
+/// # use prost_reflect::{DynamicMessage, ReflectMessage};
+/// let time = UNIX_EPOCH + Duration::from_millis(1_500);
+/// let message = DynamicMessage::try_from(time).unwrap();
+/// assert_eq!(message, prost_types::Timestamp { seconds: 1, nanos: 500_000_000 }.transcode_to_dynamic());
+/// assert_eq!(SystemTime::try_from(&message).unwrap(), time);
+/// ```
+#[derive(Debug)]
+pub struct TimeError {
+    kind: TimeErrorKind,
+}
+
+#[derive(Debug)]
+pub(super) enum TimeErrorKind {
+    MessageType {
+        expected: &'static str,
+        actual: String,
+    },
+    NotMessage,
+    Decode(DecodeError),
+    TimestampOutOfRange,
+    DurationOutOfRange,
+    InvalidDuration {
+        value: String,
+    },
+}
+
+/// Converts a timestamp message to the number of seconds and nanoseconds since the Unix epoch,
+/// checking that it is within the range of valid timestamps.
+fn to_timestamp(message: &DynamicMessage) -> Result<(i64, u32), TimeError> {
+    if message.desc.full_name() != TIMESTAMP_NAME {
+        return Err(TimeErrorKind::MessageType {
+            expected: TIMESTAMP_NAME,
+            actual: message.desc.full_name().to_owned(),
+        }
+        .into());
+    }
+
+    let timestamp: prost_types::Timestamp =
+        message.transcode_to().map_err(TimeErrorKind::Decode)?;
+    check_timestamp(timestamp.seconds, timestamp.nanos)?;
+    Ok((timestamp.seconds, timestamp.nanos as u32))
+}
+
+/// Creates a timestamp message from the number of seconds and nanoseconds since the Unix epoch.
+///
+/// Nanoseconds of one second or more, which are used by some libraries to represent leap
+/// seconds, are clamped to the last nanosecond of the second, since protobuf timestamps do not
+/// support leap seconds.
+fn from_timestamp(seconds: i64, nanos: u32) -> Result<DynamicMessage, TimeError> {
+    let nanos = nanos.min(NANOS_PER_SECOND as u32 - 1) as i32;
+    check_timestamp(seconds, nanos)?;
+    Ok(prost_types::Timestamp { seconds, nanos }.transcode_to_dynamic())
+}
+
+fn check_timestamp(seconds: i64, nanos: i32) -> Result<(), TimeError> {
+    if (MIN_TIMESTAMP_SECONDS..=MAX_TIMESTAMP_SECONDS).contains(&seconds)
+        && (0..NANOS_PER_SECOND).contains(&nanos)
+    {
+        Ok(())
+    } else {
+        Err(TimeErrorKind::TimestampOutOfRange.into())
+    }
+}
+
+/// Converts a duration message to its number of seconds and nanoseconds, checking that it is
+/// within the range of valid durations. Both parts have the same sign.
+pub(super) fn to_duration(message: &DynamicMessage) -> Result<(i64, i32), TimeError> {
+    if message.desc.full_name() != DURATION_NAME {
+        return Err(TimeErrorKind::MessageType {
+            expected: DURATION_NAME,
+            actual: message.desc.full_name().to_owned(),
+        }
+        .into());
+    }
+
+    let duration: prost_types::Duration = message.transcode_to().map_err(TimeErrorKind::Decode)?;
+    check_duration(duration.seconds, duration.nanos)?;
+    Ok((duration.seconds, duration.nanos))
+}
+
+/// Creates a duration message from its number of seconds and nanoseconds, which must have the
+/// same sign.
+pub(super) fn from_duration(seconds: i64, nanos: i32) -> Result<DynamicMessage, TimeError> {
+    check_duration(seconds, nanos)?;
+    Ok(prost_types::Duration { seconds, nanos }.transcode_to_dynamic())
+}
+
+fn check_duration(seconds: i64, nanos: i32) -> Result<(), TimeError> {
+    if (-MAX_DURATION_SECONDS..=MAX_DURATION_SECONDS).contains(&seconds)
+        && (-NANOS_PER_SECOND + 1..NANOS_PER_SECOND).contains(&nanos)
+        && (seconds == 0 || nanos == 0 || (seconds < 0) == (nanos < 0))
+    {
+        Ok(())
+    } else {
+        Err(TimeErrorKind::DurationOutOfRange.into())
+    }
+}
+
+fn to_system_time(message: &DynamicMessage) -> Result<SystemTime, TimeError> {
+    let (seconds, nanos) = to_timestamp(message)?;
+    let time = if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::new(seconds as u64, nanos))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+            .and_then(|time| time.checked_add(Duration::from_nanos(nanos.into())))
+    };
+    time.ok_or_else(|| TimeErrorKind::TimestampOutOfRange.into())
+}
+
+fn from_system_time(time: SystemTime) -> Result<DynamicMessage, TimeError> {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => {
+            let seconds = i64::try_from(duration.as_secs())
+                .map_err(|_| TimeErrorKind::TimestampOutOfRange)?;
+            from_timestamp(seconds, duration.subsec_nanos())
+        }
+        Err(err) => {
+            let duration = err.duration();
+            let seconds = i64::try_from(duration.as_secs())
+                .map_err(|_| TimeErrorKind::TimestampOutOfRange)?;
+            match duration.subsec_nanos() {
+                0 => from_timestamp(-seconds, 0),
+                nanos => from_timestamp(-seconds - 1, NANOS_PER_SECOND as u32 - nanos),
+            }
+        }
+    }
+}
+
+fn to_std_duration(message: &DynamicMessage) -> Result<Duration, TimeError> {
+    let (seconds, nanos) = to_duration(message)?;
+    if seconds < 0 || nanos < 0 {
+        return Err(TimeErrorKind::DurationOutOfRange.into());
+    }
+    Ok(Duration::new(seconds as u64, nanos as u32))
+}
+
+fn from_std_duration(duration: Duration) -> Result<DynamicMessage, TimeError> {
+    let seconds =
+        i64::try_from(duration.as_secs()).map_err(|_| TimeErrorKind::DurationOutOfRange)?;
+    from_duration(seconds, duration.subsec_nanos() as i32)
+}
+
+#[cfg(feature = "chrono")]
+fn to_chrono_date_time(
+    message: &DynamicMessage,
+) -> Result<chrono1::DateTime<chrono1::Utc>, TimeError> {
+    use chrono1::TimeZone;
+
+    let (seconds, nanos) = to_timestamp(message)?;
+    chrono1::Utc
+        .timestamp_opt(seconds, nanos)
+        .single()
+        .ok_or_else(|| TimeErrorKind::TimestampOutOfRange.into())
+}
+
+#[cfg(feature = "chrono")]
+fn from_chrono_date_time(
+    time: chrono1::DateTime<chrono1::Utc>,
+) -> Result<DynamicMessage, TimeError> {
+    from_timestamp(time.timestamp(), time.timestamp_subsec_nanos())
+}
+
+#[cfg(feature = "chrono")]
+fn to_chrono_duration(message: &DynamicMessage) -> Result<chrono1::Duration, TimeError> {
+    let (seconds, nanos) = to_duration(message)?;
+    Ok(chrono1::Duration::seconds(seconds) + chrono1::Duration::nanoseconds(nanos.into()))
+}
+
+#[cfg(feature = "chrono")]
+fn from_chrono_duration(duration: chrono1::Duration) -> Result<DynamicMessage, TimeError> {
+    let seconds = duration.num_seconds();
+    let nanos = (duration - chrono1::Duration::seconds(seconds))
+        .num_nanoseconds()
+        .expect("the remainder should be less than one second");
+    from_duration(seconds, nanos as i32)
+}
+
+#[cfg(feature = "time")]
+fn to_offset_date_time(message: &DynamicMessage) -> Result<time1::OffsetDateTime, TimeError> {
+    let (seconds, nanos) = to_timestamp(message)?;
+    let nanos = i128::from(seconds) * i128::from(NANOS_PER_SECOND) + i128::from(nanos);
+    time1::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .map_err(|_| TimeErrorKind::TimestampOutOfRange.into())
+}
+
+#[cfg(feature = "time")]
+fn from_offset_date_time(time: time1::OffsetDateTime) -> Result<DynamicMessage, TimeError> {
+    from_timestamp(time.unix_timestamp(), time.nanosecond())
+}
+
+#[cfg(feature = "time")]
+fn to_time_duration(message: &DynamicMessage) -> Result<time1::Duration, TimeError> {
+    let (seconds, nanos) = to_duration(message)?;
+    Ok(time1::Duration::new(seconds, nanos))
+}
+
+#[cfg(feature = "time")]
+fn from_time_duration(duration: time1::Duration) -> Result<DynamicMessage, TimeError> {
+    from_duration(duration.whole_seconds(), duration.subsec_nanoseconds())
+}
+
+macro_rules! impl_time_conversions {
+    ($($(#[$attr:meta])* $ty:ty => $to:ident, $from:ident;)*) => {
+        $(
+            $(#[$attr])*
+            impl TryFrom<&DynamicMessage> for $ty {
+                type Error = TimeError;
+
+                fn try_from(message: &DynamicMessage) -> Result<Self, Self::Error> {
+                    $to(message)
+                }
+            }
+
+            $(#[$attr])*
+            impl TryFrom<$ty> for DynamicMessage {
+                type Error = TimeError;
+
+                fn try_from(value: $ty) -> Result<Self, Self::Error> {
+                    $from(value)
+                }
+            }
+
+            $(#[$attr])*
+            impl TryFrom<&Value> for $ty {
+                type Error = TimeError;
+
+                fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                    match value {
+                        Value::Message(message) => $to(message),
+                        _ => Err(TimeErrorKind::NotMessage.into()),
+                    }
+                }
+            }
+
+            $(#[$attr])*
+            impl TryFrom<$ty> for Value {
+                type Error = TimeError;
+
+                fn try_from(value: $ty) -> Result<Self, Self::Error> {
+                    $from(value).map(Value::Message)
+                }
+            }
+        )*
+    };
+}
+
+impl_time_conversions! {
+    SystemTime => to_system_time, from_system_time;
+    Duration => to_std_duration, from_std_duration;
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+    chrono1::DateTime<chrono1::Utc> => to_chrono_date_time, from_chrono_date_time;
+    #[cfg(feature = "chrono")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+    chrono1::Duration => to_chrono_duration, from_chrono_duration;
+    #[cfg(feature = "time")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+    time1::OffsetDateTime => to_offset_date_time, from_offset_date_time;
+    #[cfg(feature = "time")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "time")))]
+    time1::Duration => to_time_duration, from_time_duration;
+}
+
+impl From<TimeErrorKind> for TimeError {
+    fn from(kind: TimeErrorKind) -> Self {
+        TimeError { kind }
+    }
+}
+
+impl fmt::Display for TimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TimeErrorKind::MessageType { expected, actual } => write!(
+                f,
+                "expected a message of type '{}', but found '{}'",
+                expected, actual
+            ),
+            TimeErrorKind::NotMessage => write!(f, "expected a message value"),
+            TimeErrorKind::Decode(err) => write!(f, "failed to decode message: {}", err),
+            TimeErrorKind::TimestampOutOfRange => write!(f, "timestamp is out of range"),
+            TimeErrorKind::DurationOutOfRange => write!(f, "duration is out of range"),
+            TimeErrorKind::InvalidDuration { value } => write!(f, "invalid duration '{}'", value),
+        }
+    }
+}
+
+impl Error for TimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            TimeErrorKind::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,963 @@
+use std::{fmt::Write, mem, sync::Arc};
+
+use once_cell::sync::OnceCell;
+use prost::{
+    bytes::{Buf, Bytes},
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
+};
+
+use crate::{
+    descriptor::{MAP_ENTRY_KEY_NUMBER, MAP_ENTRY_VALUE_NUMBER},
+    DynamicMessage, EnumDescriptor, ExtensionDescriptor, FieldDescriptor, Kind, MessageDescriptor,
+    Syntax, Value,
+};
+
+use super::{
+    fields::{DynamicMessageFieldSet, FieldDescriptorLike},
+    unknown::UnknownField,
+    UsageObserver,
+};
+
+/// The default maximum nesting depth of messages, matching the limit used by [`prost`].
+const RECURSION_LIMIT: u32 = 100;
+
+/// Options to control decoding of messages from the protobuf binary format.
+///
+/// Used by [`DynamicMessage::decode_with_options()`] and [`DynamicMessage::merge_with_options()`].
+/// Options may also be attached to a [`DescriptorPool`][crate::DescriptorPool] with
+/// [`set_decode_options()`][crate::DescriptorPool::set_decode_options], so that they apply to all
+/// messages decoded using its descriptors.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    utf8_policy: Utf8Policy,
+    deny_duplicate_map_keys: bool,
+    closed_enums: bool,
+    max_message_bytes: Option<usize>,
+    max_depth: u32,
+    max_unknown_field_bytes: Option<usize>,
+    lazy_messages: bool,
+    usage_observer: Option<Arc<dyn UsageObserver>>,
+}
+
+/// How to handle string fields containing invalid UTF-8 when decoding.
+///
+/// Used by [`DecodeOptions::utf8_policy()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Fail to decode the message. This is the behaviour of [`DynamicMessage::decode`].
+    Strict,
+    /// Replace invalid sequences with `U+FFFD REPLACEMENT CHARACTER`, as
+    /// [`String::from_utf8_lossy`] does.
+    Lossy,
+    /// Preserve the raw bytes of invalid strings in fields defined in proto2 files.
+    ///
+    /// Since a [`Value::String`] must be valid UTF-8, the field is stored as an unknown field
+    /// instead, so it is not visible through [`DynamicMessage::get_field`], but is re-encoded
+    /// unchanged. Any earlier and later occurrences of the same field are also kept as unknown
+    /// fields, so their order is preserved.
+    ///
+    /// Invalid strings in fields defined in proto3 files are still an error, since proto3
+    /// requires strings to be valid UTF-8.
+    Permissive,
+}
+
+struct Decoder<'a> {
+    options: &'a DecodeOptions,
+    /// A shared copy of the options, to be stored with lazily decoded messages, if
+    /// [`DecodeOptions::lazy_messages`] is set.
+    lazy_options: Option<Arc<DecodeOptions>>,
+    depth: u32,
+    /// The total size of the unknown fields decoded so far.
+    unknown_field_bytes: usize,
+}
+
+/// The encoded fields of a nested message whose decoding was deferred by
+/// [`DecodeOptions::lazy_messages`].
+pub(super) struct LazyMessage {
+    desc: MessageDescriptor,
+    bytes: Bytes,
+    options: Arc<DecodeOptions>,
+    /// The nesting depth of the message, counted from the message originally decoded.
+    depth: u32,
+    decoded: OnceCell<Result<DynamicMessageFieldSet, DecodeError>>,
+}
+
+impl DynamicMessage {
+    /// Decodes an instance of the message type specified by the [`MessageDescriptor`] from the
+    /// buffer, using the given options.
+    ///
+    /// With the default options, this behaves identically to [`decode`][DynamicMessage::decode].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DecodeOptions, ReflectMessage, Utf8Policy};
+    /// // A google.protobuf.StringValue containing "café" encoded as latin-1.
+    /// let desc = String::new().descriptor();
+    /// let bytes = b"\x0a\x04caf\xe9";
+    /// assert!(DynamicMessage::decode(desc.clone(), bytes.as_ref()).is_err());
+    ///
+    /// let options = DecodeOptions::new().utf8_policy(Utf8Policy::Lossy);
+    /// let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+    /// assert_eq!(message.get_field_by_name("value").unwrap().as_str(), Some("caf\u{FFFD}"));
+    /// ```
+    pub fn decode_with_options<B>(
+        desc: MessageDescriptor,
+        buf: B,
+        options: &DecodeOptions,
+    ) -> Result<Self, DecodeError>
+    where
+        B: Buf,
+    {
+        let mut message = DynamicMessage::new(desc);
+        message.merge_with_options(buf, options)?;
+        Ok(message)
+    }
+
+    /// Decodes the fields in the buffer and merges them into this message, using the given
+    /// options.
+    ///
+    /// With the default options, this behaves identically to [`Message::merge`][prost::Message::merge].
+    pub fn merge_with_options<B>(
+        &mut self,
+        mut buf: B,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        options.check_message_len(buf.remaining() as u64)?;
+
+        let mut buf = buf.copy_to_bytes(buf.remaining());
+        Decoder {
+            options,
+            lazy_options: if options.lazy_messages {
+                Some(Arc::new(options.clone()))
+            } else {
+                None
+            },
+            depth: 0,
+            unknown_field_bytes: 0,
+        }
+        .merge_message(self, &mut buf)?;
+
+        if let Some(observer) = &options.usage_observer {
+            self.observe_usage(observer.as_ref());
+        }
+        Ok(())
+    }
+
+    /// Decodes all nested messages which were not decoded yet due to
+    /// [`DecodeOptions::lazy_messages`], returning the first error encountered.
+    ///
+    /// Once this method returns successfully, accessing any field of the message or its nested
+    /// messages does not require any further decoding. For a message decoded without
+    /// `lazy_messages`, this always succeeds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DecodeOptions, ReflectMessage};
+    /// // A google.protobuf.Struct with a truncated value for the key "a".
+    /// let desc = prost_types::Struct::default().descriptor();
+    /// let bytes = b"\x0a\x06\x0a\x01a\x12\x01\x80";
+    /// assert!(DynamicMessage::decode(desc.clone(), bytes.as_ref()).is_err());
+    ///
+    /// let options = DecodeOptions::new().lazy_messages(true);
+    /// let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+    /// assert!(message.resolve().is_err());
+    /// ```
+    pub fn resolve(&self) -> Result<(), DecodeError> {
+        let mut stack = vec![self];
+        while let Some(message) = stack.pop() {
+            message.fields.resolve()?;
+            for value in message.fields.values() {
+                match value {
+                    Value::Message(message) => stack.push(message),
+                    Value::List(values) => {
+                        stack.extend(values.iter().filter_map(Value::as_message))
+                    }
+                    Value::Map(values) => {
+                        stack.extend(values.values().filter_map(Value::as_message))
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DecodeOptions {
+    /// Creates a new instance of [`DecodeOptions`], with the default options chosen to match
+    /// [`DynamicMessage::decode`].
+    pub const fn new() -> Self {
+        DecodeOptions {
+            utf8_policy: Utf8Policy::Strict,
+            deny_duplicate_map_keys: false,
+            closed_enums: false,
+            max_message_bytes: None,
+            max_depth: RECURSION_LIMIT,
+            max_unknown_field_bytes: None,
+            lazy_messages: false,
+            usage_observer: None,
+        }
+    }
+
+    /// Sets how to handle string fields containing invalid UTF-8.
+    ///
+    /// The default value is [`Utf8Policy::Strict`].
+    pub const fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Whether to error during decoding when a map field contains the same key more than once.
+    ///
+    /// By default, the last entry with a given key is kept, as required by the protobuf
+    /// specification. If this option is set, a repeated key is instead reported as an error
+    /// containing the key and the path of the map field, which can be useful for detecting
+    /// malformed input. Keys already present in the message passed to
+    /// [`merge_with_options`][DynamicMessage::merge_with_options] also count as duplicates.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DecodeOptions, ReflectMessage};
+    /// // A google.protobuf.Struct with two entries for the key "a".
+    /// let desc = prost_types::Struct::default().descriptor();
+    /// let bytes = b"\x0a\x03\x0a\x01a\x0a\x03\x0a\x01a";
+    /// assert!(DynamicMessage::decode(desc.clone(), bytes.as_ref()).is_ok());
+    ///
+    /// let options = DecodeOptions::new().deny_duplicate_map_keys(true);
+    /// let err = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap_err();
+    /// assert!(err.to_string().contains("duplicate key \"a\" in map field 'fields'"));
+    /// ```
+    pub const fn deny_duplicate_map_keys(mut self, yes: bool) -> Self {
+        self.deny_duplicate_map_keys = yes;
+        self
+    }
+
+    /// Whether to honour the semantics of closed enums, as defined in proto2 files.
+    ///
+    /// By default, any number is accepted for an enum field and stored as a
+    /// [`Value::EnumNumber`]. If this option is set, a number which is not defined by a
+    /// [closed][EnumDescriptor::is_closed] enum is instead kept as an unknown field, as other
+    /// protobuf implementations do, so it is not visible through [`DynamicMessage::get_field`]
+    /// but is still re-encoded. A map entry with such a value is kept as an unknown field in its
+    /// entirety. Open enums, defined in proto3 files, are unaffected.
+    ///
+    /// The default value is `false`.
+    pub const fn closed_enums(mut self, yes: bool) -> Self {
+        self.closed_enums = yes;
+        self
+    }
+
+    /// Sets the maximum length of the encoded message, in bytes. Longer input is rejected before
+    /// any of it is decoded.
+    ///
+    /// By default, there is no limit.
+    pub const fn max_message_bytes(mut self, limit: usize) -> Self {
+        self.max_message_bytes = Some(limit);
+        self
+    }
+
+    /// Sets the maximum nesting depth of messages and groups.
+    ///
+    /// The default value is 100, which is the limit used by [`prost`]. Nested messages are
+    /// decoded without recursion, so higher limits do not risk overflowing the stack.
+    pub const fn max_depth(mut self, limit: u32) -> Self {
+        self.max_depth = limit;
+        self
+    }
+
+    /// Sets the maximum total size, in bytes, of the unknown fields in the message and any nested
+    /// messages, including their tags. Unknown fields are those with a number not defined in the
+    /// message type, and are otherwise preserved without limit.
+    ///
+    /// By default, there is no limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DecodeOptions, ReflectMessage};
+    /// // A google.protobuf.Empty with an unknown field containing 100 bytes.
+    /// let desc = ().descriptor();
+    /// let mut bytes = vec![0x0a, 100];
+    /// bytes.extend_from_slice(&[0; 100]);
+    ///
+    /// let options = DecodeOptions::new().max_unknown_field_bytes(64);
+    /// let err = DynamicMessage::decode_with_options(desc, bytes.as_slice(), &options).unwrap_err();
+    /// assert!(err.to_string().contains("unknown fields exceed the limit of 64 bytes"));
+    /// ```
+    pub const fn max_unknown_field_bytes(mut self, limit: usize) -> Self {
+        self.max_unknown_field_bytes = Some(limit);
+        self
+    }
+
+    /// Whether to defer decoding nested messages until their fields are first accessed.
+    ///
+    /// If this option is set, the fields of each nested message are kept in their encoded form
+    /// when the message containing it is decoded, and are only decoded, one level at a time, when
+    /// one of them is accessed. This makes decoding much cheaper when only a few fields of a large
+    /// or deeply nested message are used. A nested message which has not been modified is encoded
+    /// by copying its original bytes.
+    ///
+    /// Since lazily decoded messages are not checked until they are accessed, errors in nested
+    /// messages are not reported when the outer message is decoded. Use
+    /// [`DynamicMessage::resolve`] to decode all nested messages and report any errors. A nested
+    /// message which fails to decode appears to have no fields set, but its original bytes are
+    /// never discarded unless it is cleared: it is encoded as those bytes, followed by any fields
+    /// which are set on it afterwards, and `resolve` continues to report the error.
+    ///
+    /// Other options, such as [`max_depth`][Self::max_depth], are applied when
+    /// each nested message is decoded, except that the
+    /// [`max_unknown_field_bytes`][Self::max_unknown_field_bytes] limit applies separately to each
+    /// lazily decoded message. Setting a [`usage_observer`][Self::usage_observer] causes all
+    /// nested messages to be decoded immediately.
+    ///
+    /// Groups and map entries are always decoded immediately, although message values in maps
+    /// are decoded lazily.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DecodeOptions, ReflectMessage, Value};
+    /// // A google.protobuf.Struct containing {"a": {"b": true}}.
+    /// let desc = prost_types::Struct::default().descriptor();
+    /// let bytes = b"\x0a\x0e\x0a\x01a\x12\x09\x2a\x07\x0a\x05\x0a\x01b\x12\x00";
+    ///
+    /// let options = DecodeOptions::new().lazy_messages(true);
+    /// let message = DynamicMessage::decode_with_options(desc, bytes.as_ref(), &options).unwrap();
+    /// assert_eq!(message.encode_to_vec(), bytes);
+    ///
+    /// let fields = message.get_field_by_name("fields").unwrap();
+    /// let value = fields.as_map().unwrap()[&"a".into()].as_message().unwrap();
+    /// assert!(value.has_field_by_name("struct_value"));
+    /// ```
+    pub const fn lazy_messages(mut self, yes: bool) -> Self {
+        self.lazy_messages = yes;
+        self
+    }
+
+    /// Sets an observer to report the deprecated fields and enum values set in each decoded
+    /// message.
+    ///
+    /// The observer is called once decoding has finished successfully, for all fields in the
+    /// message. See [`UsageObserver`] for details.
+    ///
+    /// By default, no observer is set.
+    pub fn usage_observer<O>(mut self, observer: O) -> Self
+    where
+        O: UsageObserver + 'static,
+    {
+        self.usage_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Returns the limit set with [`max_message_bytes()`][DecodeOptions::max_message_bytes].
+    pub(crate) fn message_bytes_limit(&self) -> Option<usize> {
+        self.max_message_bytes
+    }
+
+    /// Returns an error if a message of `len` bytes exceeds the limit set with
+    /// [`max_message_bytes()`][DecodeOptions::max_message_bytes].
+    pub(crate) fn check_message_len(&self, len: u64) -> Result<(), DecodeError> {
+        match self.max_message_bytes {
+            Some(limit) if len > limit as u64 => Err(DecodeError::new(format!(
+                "message of {} bytes exceeds the limit of {} bytes",
+                len, limit
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions::new()
+    }
+}
+
+/// A nested message which is being decoded.
+struct Frame {
+    message: DynamicMessage,
+    buf: Bytes,
+    /// The field number of the group, if the message is encoded as a group.
+    group: Option<u32>,
+    /// Where to store the message once it has been decoded.
+    target: Target,
+}
+
+/// Where a decoded nested message is stored in the message containing it.
+enum Target {
+    /// A singular message field, whose existing value is being merged into.
+    Field(NestedField),
+    /// A new element of a repeated message field, with its index.
+    ListElement(NestedField, usize),
+    /// A new entry of a map field, with its encoded bytes.
+    MapEntry(NestedField, Bytes),
+}
+
+/// A field or extension whose value contains nested messages.
+enum NestedField {
+    Field(FieldDescriptor),
+    Extension(ExtensionDescriptor),
+}
+
+impl Decoder<'_> {
+    /// Merges fields into `message` until the end of the buffer.
+    ///
+    /// Nested messages are decoded using an explicit stack rather than by recursion, so that
+    /// deeply nested input cannot overflow the thread's stack, whatever the value of
+    /// [`DecodeOptions::max_depth`].
+    fn merge_message(
+        &mut self,
+        message: &mut DynamicMessage,
+        buf: &mut Bytes,
+    ) -> Result<(), DecodeError> {
+        let mut stack = Vec::new();
+        let result = self.merge_frames(message, buf, &mut stack);
+        if result.is_err() {
+            // Put back the partially decoded messages taken out of singular fields.
+            while let Some(frame) = stack.pop() {
+                if let Target::Field(field) = frame.target {
+                    let parent = match stack.last_mut() {
+                        Some(parent) => &mut parent.message,
+                        None => &mut *message,
+                    };
+                    *field.value_mut(parent) = Value::Message(frame.message);
+                }
+            }
+        }
+        result
+    }
+
+    fn merge_frames(
+        &mut self,
+        message: &mut DynamicMessage,
+        buf: &mut Bytes,
+        stack: &mut Vec<Frame>,
+    ) -> Result<(), DecodeError> {
+        loop {
+            let (current, current_buf, group) = match stack.last_mut() {
+                Some(frame) => (&mut frame.message, &mut frame.buf, frame.group),
+                None => (&mut *message, &mut *buf, None),
+            };
+
+            if !current_buf.has_remaining() {
+                if group.is_some() {
+                    return Err(DecodeError::new("unexpected end of group"));
+                }
+                match stack.pop() {
+                    Some(frame) => {
+                        self.finish_nested(message, buf, stack, frame)?;
+                        continue;
+                    }
+                    None => return Ok(()),
+                }
+            }
+
+            let field_start = current_buf.remaining();
+            let (number, wire_type) = encoding::decode_key(current_buf)?;
+            if wire_type == WireType::EndGroup {
+                if group != Some(number) {
+                    return Err(DecodeError::new("unexpected end group tag"));
+                }
+                let frame = stack.pop().expect("expected group frame");
+                self.finish_nested(message, buf, stack, frame)?;
+                continue;
+            }
+
+            let nested = if let Some(field_desc) = current.desc.get_field(number) {
+                let syntax = field_desc.parent_file().syntax();
+                self.merge_field(current, &field_desc, syntax, wire_type, current_buf)?
+            } else if let Some(extension_desc) = current.desc.get_extension(number) {
+                let syntax = extension_desc.parent_file().syntax();
+                self.merge_field(current, &extension_desc, syntax, wire_type, current_buf)?
+            } else {
+                let field =
+                    UnknownField::decode(number, wire_type, current_buf, DecodeContext::default())?;
+                self.unknown_field_bytes += field_start - current_buf.remaining();
+                if let Some(max_unknown_field_bytes) = self.options.max_unknown_field_bytes {
+                    if self.unknown_field_bytes > max_unknown_field_bytes {
+                        return Err(DecodeError::new(format!(
+                            "unknown fields exceed the limit of {} bytes",
+                            max_unknown_field_bytes
+                        )));
+                    }
+                }
+                current.fields.add_unknown(number, field);
+                None
+            };
+
+            if let Some(frame) = nested {
+                stack.push(frame);
+                if self.depth >= self.options.max_depth {
+                    return Err(DecodeError::new("recursion limit reached"));
+                }
+                self.depth += 1;
+            }
+        }
+    }
+
+    /// Stores a nested message which has been fully decoded in the message containing it.
+    fn finish_nested(
+        &mut self,
+        message: &mut DynamicMessage,
+        buf: &mut Bytes,
+        stack: &mut [Frame],
+        frame: Frame,
+    ) -> Result<(), DecodeError> {
+        self.depth -= 1;
+
+        let field_path = match frame.target {
+            Target::MapEntry(..) if self.options.deny_duplicate_map_keys => {
+                Some(path(stack.iter().chain(Some(&frame))))
+            }
+            _ => None,
+        };
+
+        let (parent, parent_buf) = match stack.last_mut() {
+            Some(parent) => (&mut parent.message, &mut parent.buf),
+            None => (message, buf),
+        };
+        if frame.group.is_some() {
+            // Groups are decoded directly from the buffer of their parent.
+            *parent_buf = frame.buf;
+        }
+
+        match frame.target {
+            Target::Field(field) => *field.value_mut(parent) = Value::Message(frame.message),
+            Target::ListElement(field, _) => match field.value_mut(parent) {
+                Value::List(values) => values.push(Value::Message(frame.message)),
+                value => panic!(
+                    "mismatch between DynamicMessage value {:?} and type {:?}",
+                    value,
+                    field.kind()
+                ),
+            },
+            Target::MapEntry(NestedField::Field(field_desc), bytes) => {
+                self.merge_map_entry(parent, &field_desc, frame.message, bytes, field_path)?
+            }
+            Target::MapEntry(NestedField::Extension(extension_desc), bytes) => {
+                self.merge_map_entry(parent, &extension_desc, frame.message, bytes, field_path)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges a single field into `message`, returning a frame to decode if the field is a
+    /// nested message.
+    fn merge_field<D>(
+        &mut self,
+        message: &mut DynamicMessage,
+        field_desc: &D,
+        syntax: Syntax,
+        wire_type: WireType,
+        buf: &mut Bytes,
+    ) -> Result<Option<Frame>, DecodeError>
+    where
+        D: FieldDescriptorLike + Clone + Into<NestedField>,
+    {
+        let number = field_desc.number();
+        if message.fields.is_unknown(number) {
+            let field = UnknownField::decode(number, wire_type, buf, DecodeContext::default())?;
+            message.fields.add_unknown(number, field);
+            return Ok(None);
+        }
+
+        match field_desc.kind() {
+            Kind::String if wire_type == WireType::LengthDelimited => {
+                let bytes = decode_length_delimited(buf)?;
+                let value = match std::str::from_utf8(&bytes) {
+                    Ok(value) => value.to_owned(),
+                    Err(_) => match self.options.utf8_policy {
+                        Utf8Policy::Lossy => String::from_utf8_lossy(&bytes).into_owned(),
+                        Utf8Policy::Permissive if syntax == Syntax::Proto2 => {
+                            message.fields.convert_to_unknown(field_desc);
+                            message
+                                .fields
+                                .add_unknown(number, UnknownField::LengthDelimited(bytes));
+                            return Ok(None);
+                        }
+                        _ => {
+                            return Err(DecodeError::new(
+                                "invalid string value: data is not UTF-8 encoded",
+                            ))
+                        }
+                    },
+                };
+
+                match message.fields.get_mut(field_desc) {
+                    Value::String(string) => *string = value,
+                    Value::List(values) => values.push(Value::String(value)),
+                    value => panic!(
+                        "mismatch between DynamicMessage value {:?} and type {:?}",
+                        value,
+                        field_desc.kind()
+                    ),
+                }
+                Ok(None)
+            }
+            Kind::Message(entry_desc)
+                if field_desc.is_map() && wire_type == WireType::LengthDelimited =>
+            {
+                let bytes = decode_length_delimited(buf)?;
+                Ok(Some(Frame {
+                    message: DynamicMessage::new(entry_desc),
+                    buf: bytes.clone(),
+                    group: None,
+                    target: Target::MapEntry(field_desc.clone().into(), bytes),
+                }))
+            }
+            Kind::Enum(enum_desc)
+                if self.options.closed_enums
+                    && enum_desc.is_closed()
+                    && (wire_type == WireType::Varint
+                        || (field_desc.is_list() && wire_type == WireType::LengthDelimited)) =>
+            {
+                merge_closed_enum(message, field_desc, &enum_desc, wire_type, buf).map(|()| None)
+            }
+            Kind::Message(nested_desc)
+                if field_desc.is_group() && wire_type == WireType::StartGroup =>
+            {
+                let buf = mem::take(buf);
+                Ok(Some(nested_frame(
+                    message,
+                    field_desc,
+                    nested_desc,
+                    buf,
+                    Some(number),
+                )))
+            }
+            Kind::Message(nested_desc)
+                if !field_desc.is_group()
+                    && !field_desc.is_map()
+                    && wire_type == WireType::LengthDelimited =>
+            {
+                let bytes = decode_length_delimited(buf)?;
+                match self.lazy_options.clone() {
+                    Some(options) => {
+                        self.merge_lazy_field(message, field_desc, nested_desc, bytes, options)
+                    }
+                    None => Ok(Some(nested_frame(
+                        message,
+                        field_desc,
+                        nested_desc,
+                        bytes,
+                        None,
+                    ))),
+                }
+            }
+            _ => message
+                .fields
+                .merge_field(field_desc, wire_type, buf, DecodeContext::default())
+                .map(|()| None),
+        }
+    }
+
+    /// Stores a decoded map entry in its map field.
+    fn merge_map_entry(
+        &self,
+        message: &mut DynamicMessage,
+        field_desc: &impl FieldDescriptorLike,
+        entry: DynamicMessage,
+        bytes: Bytes,
+        field_path: Option<String>,
+    ) -> Result<(), DecodeError> {
+        let number = field_desc.number();
+        if entry.fields.has_unknown_enum_values() {
+            message
+                .fields
+                .add_unknown_enum_value(number, UnknownField::LengthDelimited(bytes));
+            return Ok(());
+        }
+
+        if entry.fields.is_unknown(MAP_ENTRY_KEY_NUMBER)
+            || entry.fields.is_unknown(MAP_ENTRY_VALUE_NUMBER)
+        {
+            message.fields.convert_to_unknown(field_desc);
+            message
+                .fields
+                .add_unknown(number, UnknownField::LengthDelimited(bytes));
+            return Ok(());
+        }
+
+        let key_desc = entry.desc.get_field(MAP_ENTRY_KEY_NUMBER).unwrap();
+        let value_desc = entry.desc.get_field(MAP_ENTRY_VALUE_NUMBER).unwrap();
+        let key = entry
+            .get_field(&key_desc)
+            .into_owned()
+            .into_map_key()
+            .expect("invalid map key");
+        let value = entry.get_field(&value_desc).into_owned();
+        match message.fields.get_mut(field_desc) {
+            Value::Map(values) => {
+                if self.options.deny_duplicate_map_keys && values.contains_key(&key) {
+                    return Err(DecodeError::new(format!(
+                        "duplicate key {} in map field '{}'",
+                        key,
+                        field_path.unwrap_or_default()
+                    )));
+                }
+                values.insert(key, value);
+            }
+            value => panic!(
+                "mismatch between DynamicMessage value {:?} and type {:?}",
+                value,
+                field_desc.kind()
+            ),
+        };
+        Ok(())
+    }
+
+    /// Merges a nested message field without decoding it, by storing its encoded fields to be
+    /// decoded on first access.
+    fn merge_lazy_field<D>(
+        &mut self,
+        message: &mut DynamicMessage,
+        field_desc: &D,
+        nested_desc: MessageDescriptor,
+        bytes: Bytes,
+        options: Arc<DecodeOptions>,
+    ) -> Result<Option<Frame>, DecodeError>
+    where
+        D: FieldDescriptorLike + Clone + Into<NestedField>,
+    {
+        if self.depth >= self.options.max_depth {
+            return Err(DecodeError::new("recursion limit reached"));
+        }
+
+        let depth = self.depth + 1;
+        let lazy = |desc: MessageDescriptor, bytes: Bytes| {
+            DynamicMessageFieldSet::lazy(LazyMessage {
+                desc,
+                bytes,
+                options,
+                depth,
+                decoded: OnceCell::new(),
+            })
+        };
+
+        match message.fields.get_mut(field_desc) {
+            Value::Message(nested) => {
+                let bytes = if nested.fields.is_empty() {
+                    bytes
+                } else if let Some(encoded) = nested.fields.encoded() {
+                    // Merging two encoded messages is equivalent to concatenating them.
+                    Bytes::from([encoded, &bytes].concat())
+                } else {
+                    return Ok(Some(nested_frame(
+                        message,
+                        field_desc,
+                        nested_desc,
+                        bytes,
+                        None,
+                    )));
+                };
+                nested.fields = lazy(nested_desc, bytes);
+                Ok(None)
+            }
+            Value::List(values) => {
+                values.push(Value::Message(DynamicMessage {
+                    fields: lazy(nested_desc.clone(), bytes),
+                    desc: nested_desc,
+                }));
+                Ok(None)
+            }
+            value => panic!(
+                "mismatch between DynamicMessage value {:?} and type {:?}",
+                value,
+                field_desc.kind()
+            ),
+        }
+    }
+}
+
+/// Creates a frame to decode a nested message field. The existing value of a singular field is
+/// taken out of `message` to be merged into, and put back once decoding finishes.
+fn nested_frame<D>(
+    message: &mut DynamicMessage,
+    field_desc: &D,
+    nested_desc: MessageDescriptor,
+    buf: Bytes,
+    group: Option<u32>,
+) -> Frame
+where
+    D: FieldDescriptorLike + Clone + Into<NestedField>,
+{
+    let field = field_desc.clone().into();
+    let (nested, target) = match message.fields.get_mut(field_desc) {
+        Value::Message(nested) => (
+            mem::replace(nested, DynamicMessage::new(nested_desc)),
+            Target::Field(field),
+        ),
+        Value::List(values) => (
+            DynamicMessage::new(nested_desc),
+            Target::ListElement(field, values.len()),
+        ),
+        value => panic!(
+            "mismatch between DynamicMessage value {:?} and type {:?}",
+            value,
+            field_desc.kind()
+        ),
+    };
+    Frame {
+        message: nested,
+        buf,
+        group,
+        target,
+    }
+}
+
+/// Gets the path of the message being decoded, for use in error messages.
+fn path<'a>(frames: impl IntoIterator<Item = &'a Frame>) -> String {
+    let mut path = String::new();
+    let mut in_map_entry = false;
+    for frame in frames {
+        let (field, index) = match &frame.target {
+            // The fields of map entries are not included in the path.
+            Target::Field(_) if in_map_entry => {
+                in_map_entry = false;
+                continue;
+            }
+            Target::Field(field) => (field, None),
+            Target::ListElement(field, index) => (field, Some(*index)),
+            Target::MapEntry(field, _) => (field, None),
+        };
+        in_map_entry = matches!(frame.target, Target::MapEntry(..));
+
+        if !path.is_empty() {
+            path.push('.');
+        }
+        path.push_str(field.text_name());
+        if let Some(index) = index {
+            let _ = write!(path, "[{}]", index);
+        }
+    }
+    path
+}
+
+impl NestedField {
+    fn value_mut<'a>(&self, message: &'a mut DynamicMessage) -> &'a mut Value {
+        match self {
+            NestedField::Field(field_desc) => message.fields.get_mut(field_desc),
+            NestedField::Extension(extension_desc) => message.fields.get_mut(extension_desc),
+        }
+    }
+
+    fn kind(&self) -> Kind {
+        match self {
+            NestedField::Field(field_desc) => field_desc.kind(),
+            NestedField::Extension(extension_desc) => extension_desc.kind(),
+        }
+    }
+
+    fn text_name(&self) -> &str {
+        match self {
+            NestedField::Field(field_desc) => field_desc.text_name(),
+            NestedField::Extension(extension_desc) => extension_desc.text_name(),
+        }
+    }
+}
+
+impl From<FieldDescriptor> for NestedField {
+    fn from(field_desc: FieldDescriptor) -> Self {
+        NestedField::Field(field_desc)
+    }
+}
+
+impl From<ExtensionDescriptor> for NestedField {
+    fn from(extension_desc: ExtensionDescriptor) -> Self {
+        NestedField::Extension(extension_desc)
+    }
+}
+
+impl LazyMessage {
+    pub(super) fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Gets the decoded fields of the message, if it has been decoded successfully.
+    pub(super) fn decoded_mut(&mut self) -> Option<&mut DynamicMessageFieldSet> {
+        self.decoded
+            .get_mut()
+            .and_then(|decoded| decoded.as_mut().ok())
+    }
+
+    /// Decodes the fields of the message, if they have not been decoded already.
+    pub(super) fn decode(&self) -> &Result<DynamicMessageFieldSet, DecodeError> {
+        self.decoded.get_or_init(|| {
+            let mut message = DynamicMessage::new(self.desc.clone());
+            Decoder {
+                options: &self.options,
+                lazy_options: Some(self.options.clone()),
+                depth: self.depth,
+                unknown_field_bytes: 0,
+            }
+            .merge_message(&mut message, &mut self.bytes.clone())?;
+            Ok(message.fields)
+        })
+    }
+}
+
+/// Merges a value, or packed list of values, of a closed enum field, keeping any numbers not
+/// defined by the enum as unknown fields.
+fn merge_closed_enum(
+    message: &mut DynamicMessage,
+    field_desc: &impl FieldDescriptorLike,
+    enum_desc: &EnumDescriptor,
+    wire_type: WireType,
+    buf: &mut Bytes,
+) -> Result<(), DecodeError> {
+    let mut numbers = Vec::new();
+    if wire_type == WireType::LengthDelimited {
+        let mut bytes = decode_length_delimited(buf)?;
+        while bytes.has_remaining() {
+            numbers.push(encoding::decode_varint(&mut bytes)?);
+        }
+    } else {
+        numbers.push(encoding::decode_varint(buf)?);
+    }
+
+    for raw in numbers {
+        let number = raw as i32;
+        if enum_desc.get_value(number).is_none() {
+            message
+                .fields
+                .add_unknown_enum_value(field_desc.number(), UnknownField::Varint(raw));
+            continue;
+        }
+
+        if field_desc.is_list() {
+            message.fields.push(field_desc, Value::EnumNumber(number));
+            continue;
+        }
+
+        match message.fields.get_mut(field_desc) {
+            Value::EnumNumber(value) => *value = number,
+            value => panic!(
+                "mismatch between DynamicMessage value {:?} and type {:?}",
+                value,
+                field_desc.kind()
+            ),
+        }
+    }
+    Ok(())
+}
+
+fn decode_length_delimited(buf: &mut Bytes) -> Result<Bytes, DecodeError> {
+    let len = encoding::decode_varint(buf)?;
+    if len > buf.remaining() as u64 {
+        return Err(DecodeError::new("buffer underflow"));
+    }
+    Ok(buf.split_to(len as usize))
+}
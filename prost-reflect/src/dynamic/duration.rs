@@ -0,0 +1,204 @@
+use std::fmt::Write;
+
+use crate::{
+    dynamic::datetime::{from_duration, to_duration, TimeErrorKind, NANOS_PER_SECOND},
+    DynamicMessage, TimeError, Value,
+};
+
+const NANOS_PER_MICROSECOND: i128 = 1_000;
+const NANOS_PER_MILLISECOND: i128 = 1_000_000;
+const NANOS_PER_MINUTE: i128 = 60 * NANOS_PER_SECOND as i128;
+const NANOS_PER_HOUR: i128 = 60 * NANOS_PER_MINUTE;
+
+/// Parses a duration string into a `google.protobuf.Duration` message.
+///
+/// The canonical form used by the JSON mapping, a number of seconds with up to nine fractional
+/// digits followed by `s`, such as `"3.5s"`, is accepted. So is a sequence of numbers with the
+/// units `h`, `m`, `s`, `ms`, `us` (or `µs`) and `ns`, such as `"1h30m"` or `"250ms"`, as produced
+/// by [`format_humanized`]. The whole duration may be preceded by a sign. Digits beyond
+/// nanosecond precision are truncated.
+///
+/// An error is returned if the string is invalid, or the duration is longer than 10,000 years.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{duration, ReflectMessage};
+/// let message = duration::parse("3.5s").unwrap();
+/// assert_eq!(message, prost_types::Duration { seconds: 3, nanos: 500_000_000 }.transcode_to_dynamic());
+/// assert_eq!(duration::parse("-1h30m").unwrap(), duration::parse("-5400s").unwrap());
+///
+/// assert_eq!(duration::format(&message).unwrap(), "3.500s");
+/// assert_eq!(duration::format_humanized(&message).unwrap(), "3.5s");
+/// ```
+pub fn parse(s: &str) -> Result<DynamicMessage, TimeError> {
+    let nanos = parse_nanos(s).ok_or_else(|| TimeErrorKind::InvalidDuration {
+        value: s.to_owned(),
+    })?;
+
+    let seconds = i64::try_from(nanos / i128::from(NANOS_PER_SECOND))
+        .map_err(|_| TimeErrorKind::DurationOutOfRange)?;
+    from_duration(seconds, (nanos % i128::from(NANOS_PER_SECOND)) as i32)
+}
+
+/// Formats a `google.protobuf.Duration` message in the canonical form used by the JSON mapping,
+/// such as `"3.500s"`.
+///
+/// An error is returned if the message is not a duration, or is out of range.
+pub fn format(message: &DynamicMessage) -> Result<String, TimeError> {
+    let (seconds, nanos) = to_duration(message)?;
+    Ok(prost_types::Duration { seconds, nanos }.to_string())
+}
+
+/// Formats a `google.protobuf.Duration` message in a human-readable form, such as `"1h30m"`,
+/// `"2.5s"` or `"250ms"`.
+///
+/// Durations of a second or more are written in hours, minutes and seconds, omitting zero
+/// components, and shorter durations are written in the largest of `ms`, `µs` or `ns` which is
+/// at most the duration. The result can be read back using [`parse`].
+///
+/// An error is returned if the message is not a duration, or is out of range.
+pub fn format_humanized(message: &DynamicMessage) -> Result<String, TimeError> {
+    let (seconds, nanos) = to_duration(message)?;
+    let total = i128::from(seconds) * i128::from(NANOS_PER_SECOND) + i128::from(nanos);
+
+    let mut result = String::new();
+    if total < 0 {
+        result.push('-');
+    }
+    let total = total.abs();
+
+    if total == 0 {
+        result.push_str("0s");
+    } else if total < NANOS_PER_MICROSECOND {
+        write!(result, "{}ns", total).unwrap();
+    } else if total < NANOS_PER_MILLISECOND {
+        write_decimal(&mut result, total, NANOS_PER_MICROSECOND);
+        result.push_str("µs");
+    } else if total < i128::from(NANOS_PER_SECOND) {
+        write_decimal(&mut result, total, NANOS_PER_MILLISECOND);
+        result.push_str("ms");
+    } else {
+        let hours = total / NANOS_PER_HOUR;
+        let minutes = total % NANOS_PER_HOUR / NANOS_PER_MINUTE;
+        let seconds = total % NANOS_PER_MINUTE;
+        if hours != 0 {
+            write!(result, "{}h", hours).unwrap();
+        }
+        if minutes != 0 {
+            write!(result, "{}m", minutes).unwrap();
+        }
+        if seconds != 0 {
+            write_decimal(&mut result, seconds, i128::from(NANOS_PER_SECOND));
+            result.push('s');
+        }
+    }
+    Ok(result)
+}
+
+impl Value {
+    /// Parses a duration string into a `google.protobuf.Duration` message value.
+    ///
+    /// See [`duration::parse`][crate::duration::parse] for the accepted formats.
+    pub fn parse_duration(s: &str) -> Result<Value, TimeError> {
+        parse(s).map(Value::Message)
+    }
+
+    /// Formats a `google.protobuf.Duration` message value in the canonical form used by the JSON
+    /// mapping, such as `"3.500s"`.
+    ///
+    /// See [`duration::format`][crate::duration::format] for more details.
+    pub fn format_duration(&self) -> Result<String, TimeError> {
+        match self {
+            Value::Message(message) => format(message),
+            _ => Err(TimeErrorKind::NotMessage.into()),
+        }
+    }
+
+    /// Formats a `google.protobuf.Duration` message value in a human-readable form, such as
+    /// `"1h30m"`.
+    ///
+    /// See [`duration::format_humanized`][crate::duration::format_humanized] for more details.
+    pub fn format_duration_humanized(&self) -> Result<String, TimeError> {
+        match self {
+            Value::Message(message) => format_humanized(message),
+            _ => Err(TimeErrorKind::NotMessage.into()),
+        }
+    }
+}
+
+/// Parses a duration string into a number of nanoseconds.
+fn parse_nanos(s: &str) -> Option<i128> {
+    let (negative, mut rest) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if rest == "0" {
+        return Some(0);
+    }
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total: i128 = 0;
+    while !rest.is_empty() {
+        let (integer, tail) = split_digits(rest);
+        let (fraction, tail) = match tail.strip_prefix('.') {
+            Some(tail) => split_digits(tail),
+            None => ("", tail),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return None;
+        }
+
+        let unit_len = tail
+            .find(|ch: char| ch.is_ascii_digit() || ch == '.')
+            .unwrap_or(tail.len());
+        let unit = match &tail[..unit_len] {
+            "h" => NANOS_PER_HOUR,
+            "m" => NANOS_PER_MINUTE,
+            "s" => i128::from(NANOS_PER_SECOND),
+            "ms" => NANOS_PER_MILLISECOND,
+            "us" | "µs" => NANOS_PER_MICROSECOND,
+            "ns" => 1,
+            _ => return None,
+        };
+        rest = &tail[unit_len..];
+
+        if integer.len() > 20 {
+            return None;
+        }
+        let integer: i128 = if integer.is_empty() {
+            0
+        } else {
+            integer.parse().ok()?
+        };
+        // Digits beyond nanosecond precision cannot affect the result.
+        let fraction = &fraction[..fraction.len().min(18)];
+        let fraction = if fraction.is_empty() {
+            0
+        } else {
+            fraction.parse::<i128>().ok()? * unit / 10i128.pow(fraction.len() as u32)
+        };
+
+        total = total.checked_add(integer.checked_mul(unit)?.checked_add(fraction)?)?;
+    }
+
+    Some(if negative { -total } else { total })
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    s.split_at(s.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(s.len()))
+}
+
+/// Writes `value / unit` as a decimal number, omitting trailing zeros of the fractional part.
+fn write_decimal(result: &mut String, value: i128, unit: i128) {
+    write!(result, "{}", value / unit).unwrap();
+    let fraction = value % unit;
+    if fraction != 0 {
+        let width = unit.to_string().len() - 1;
+        let digits = format!("{:0width$}", fraction, width = width);
+        result.push('.');
+        result.push_str(digits.trim_end_matches('0'));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{error::Error, fmt};
+
+use prost::{bytes::BufMut, Message};
+
+use crate::{Cardinality, DynamicMessage, Value};
+
+use super::fields::ValueAndDescriptor;
+
+/// Options to control encoding of messages to the protobuf binary format.
+///
+/// Used by [`DynamicMessage::encode_with_options()`] and
+/// [`DynamicMessage::encode_to_vec_with_options()`].
+#[derive(Debug, Clone)]
+pub struct EncodeOptions {
+    check_required_fields: bool,
+}
+
+/// An error that may occur while encoding a message with [`EncodeOptions`].
+#[derive(Debug)]
+pub struct EncodeError {
+    kind: EncodeErrorKind,
+}
+
+#[derive(Debug)]
+enum EncodeErrorKind {
+    MissingRequiredFields { paths: Vec<String> },
+    InsufficientCapacity(prost::EncodeError),
+}
+
+impl DynamicMessage {
+    /// Returns `true` if all required fields of this message are set, including in nested
+    /// messages.
+    ///
+    /// Required fields only exist in proto2 files, so this always returns `true` for messages
+    /// defined in proto3 files which do not contain any proto2 messages.
+    pub fn is_initialized(&self) -> bool {
+        let mut paths = Vec::new();
+        self.missing_required_fields_inner("", &mut paths);
+        paths.is_empty()
+    }
+
+    /// Returns the paths of all required fields which are not set in this message, including in
+    /// nested messages.
+    ///
+    /// Fields are identified by their name, with nested fields separated by `.`, and list
+    /// elements and map values identified by their index or key in brackets, for example
+    /// `items[0].name` or `labels["key"].value`. Extension fields are identified by their full
+    /// name in brackets, as in the text format.
+    pub fn missing_required_fields(&self) -> Vec<String> {
+        let mut paths = Vec::new();
+        self.missing_required_fields_inner("", &mut paths);
+        paths
+    }
+
+    /// Encodes this message to the buffer, using the given options.
+    ///
+    /// With the default options, this behaves identically to [`Message::encode`].
+    pub fn encode_with_options<B>(
+        &self,
+        buf: &mut B,
+        options: &EncodeOptions,
+    ) -> Result<(), EncodeError>
+    where
+        B: BufMut,
+    {
+        self.check(options)?;
+        self.encode(buf)
+            .map_err(|err| EncodeErrorKind::InsufficientCapacity(err).into())
+    }
+
+    /// Encodes this message into a newly allocated buffer, using the given options.
+    ///
+    /// With the default options, this behaves identically to [`Message::encode_to_vec`], and
+    /// never fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, EncodeOptions, ReflectMessage, Value};
+    /// # use prost_types::{uninterpreted_option::NamePart, UninterpretedOption};
+    /// let mut option = DynamicMessage::new(UninterpretedOption::default().descriptor());
+    /// let name_part = DynamicMessage::new(NamePart::default().descriptor());
+    /// option.set_field_by_name("name", Value::List(vec![Value::Message(name_part)]));
+    /// assert!(!option.is_initialized());
+    ///
+    /// let options = EncodeOptions::new().check_required_fields(true);
+    /// let err = option.encode_to_vec_with_options(&options).unwrap_err();
+    /// assert_eq!(err.missing_required_fields(), ["name[0].name_part", "name[0].is_extension"]);
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "message is missing required fields: name[0].name_part, name[0].is_extension",
+    /// );
+    /// ```
+    pub fn encode_to_vec_with_options(
+        &self,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, EncodeError> {
+        self.check(options)?;
+        Ok(self.encode_to_vec())
+    }
+
+    fn check(&self, options: &EncodeOptions) -> Result<(), EncodeError> {
+        if options.check_required_fields {
+            let paths = self.missing_required_fields();
+            if !paths.is_empty() {
+                return Err(EncodeErrorKind::MissingRequiredFields { paths }.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn missing_required_fields_inner(&self, path: &str, paths: &mut Vec<String>) {
+        for field_desc in self.desc.fields() {
+            if field_desc.cardinality() == Cardinality::Required && !self.has_field(&field_desc) {
+                paths.push(join_path(path, field_desc.name()));
+            }
+        }
+
+        for field in self.fields.iter(&self.desc) {
+            let (value, field_path) = match field {
+                ValueAndDescriptor::Field(value, field_desc) => {
+                    (value, join_path(path, field_desc.name()))
+                }
+                ValueAndDescriptor::Extension(value, extension_desc) => (
+                    value,
+                    join_path(path, &format!("[{}]", extension_desc.full_name())),
+                ),
+                ValueAndDescriptor::Unknown(..) => continue,
+            };
+
+            match value.as_ref() {
+                Value::Message(message) => {
+                    message.missing_required_fields_inner(&field_path, paths);
+                }
+                Value::List(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        if let Value::Message(message) = value {
+                            let element_path = format!("{}[{}]", field_path, index);
+                            message.missing_required_fields_inner(&element_path, paths);
+                        }
+                    }
+                }
+                Value::Map(values) => {
+                    let mut entries: Vec<_> = values.iter().collect();
+                    entries.sort_by_key(|(key, _)| *key);
+                    for (key, value) in entries {
+                        if let Value::Message(message) = value {
+                            let entry_path = format!("{}[{}]", field_path, key);
+                            message.missing_required_fields_inner(&entry_path, paths);
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// Creates a new instance of [`EncodeOptions`], with the default options chosen to match
+    /// [`Message::encode`].
+    pub const fn new() -> Self {
+        EncodeOptions {
+            check_required_fields: false,
+        }
+    }
+
+    /// Whether to fail with an error if any required fields are not set, instead of encoding a
+    /// message which other implementations will refuse to decode.
+    ///
+    /// The paths of the missing fields are available from
+    /// [`EncodeError::missing_required_fields`]. See also [`DynamicMessage::is_initialized`].
+    ///
+    /// The default value is `false`.
+    pub const fn check_required_fields(mut self, yes: bool) -> Self {
+        self.check_required_fields = yes;
+        self
+    }
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        EncodeOptions::new()
+    }
+}
+
+impl EncodeError {
+    /// Returns the paths of the required fields which were not set, if encoding failed because
+    /// the message was not initialized.
+    ///
+    /// See [`DynamicMessage::missing_required_fields`] for the format of the paths.
+    pub fn missing_required_fields(&self) -> &[String] {
+        match &self.kind {
+            EncodeErrorKind::MissingRequiredFields { paths } => paths,
+            EncodeErrorKind::InsufficientCapacity(_) => &[],
+        }
+    }
+}
+
+impl From<EncodeErrorKind> for EncodeError {
+    fn from(kind: EncodeErrorKind) -> Self {
+        EncodeError { kind }
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            EncodeErrorKind::MissingRequiredFields { paths } => {
+                write!(
+                    f,
+                    "message is missing required fields: {}",
+                    paths.join(", ")
+                )
+            }
+            EncodeErrorKind::InsufficientCapacity(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for EncodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            EncodeErrorKind::MissingRequiredFields { .. } => None,
+            EncodeErrorKind::InsufficientCapacity(err) => Some(err),
+        }
+    }
+}
+
+pub(super) fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
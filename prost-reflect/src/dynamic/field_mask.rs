@@ -0,0 +1,383 @@
+use std::{error::Error, fmt, mem};
+
+use prost_types::FieldMask;
+
+use crate::{
+    dynamic::case::{camel_case_to_snake_case, snake_case_to_camel_case},
+    DynamicMessage, Kind, MessageDescriptor, ReflectMessage,
+};
+
+/// An error that occurred while converting or validating a [`FieldMask`].
+#[derive(Debug)]
+pub struct FieldMaskError {
+    kind: FieldMaskErrorKind,
+}
+
+#[derive(Debug)]
+enum FieldMaskErrorKind {
+    InvalidJson { path: String },
+    NotCamelCase { path: String },
+    UnknownField { path: String, message: String },
+}
+
+/// Computes the smallest [`FieldMask`] covering all fields which differ between `a` and `b`.
+///
+/// Singular message fields which are set in both messages are compared recursively, so the mask
+/// contains a path to the most deeply nested field that differs. Repeated and map fields are
+/// compared as a whole. A field is also considered to differ if it is present in one message but
+/// not the other, even if its value is the default.
+///
+/// Paths are built from field names, as required by the JSON mapping of `FieldMask`. Extension
+/// fields and unknown fields are not compared.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` are not of the same message type.
+///
+/// # Examples
+///
+/// ```
+/// # use prost::Message;
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::field_mask;
+///
+/// let a = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x01\x1a\x02\x10\x02".as_ref()).unwrap();
+/// let b = DynamicMessage::decode(message_descriptor, b"\x08\x01\x1a\x02\x10\x03".as_ref()).unwrap();
+/// assert_eq!(field_mask::diff_mask(&a, &b).paths, vec!["nested.bar".to_owned()]);
+/// ```
+pub fn diff_mask(a: &DynamicMessage, b: &DynamicMessage) -> FieldMask {
+    assert_eq!(
+        a.descriptor(),
+        b.descriptor(),
+        "cannot compare messages of different types"
+    );
+
+    let mut paths = Vec::new();
+    diff_fields(a, b, "", &mut paths);
+    FieldMask { paths }
+}
+
+fn diff_fields(a: &DynamicMessage, b: &DynamicMessage, prefix: &str, paths: &mut Vec<String>) {
+    for field in a.descriptor().fields() {
+        let has_a = a.has_field(&field);
+        let has_b = b.has_field(&field);
+        if !has_a && !has_b {
+            continue;
+        }
+
+        let value_a = a.get_field(&field);
+        let value_b = b.get_field(&field);
+        if has_a == has_b && value_a == value_b {
+            continue;
+        }
+
+        let path = format!("{}{}", prefix, field.name());
+        match (value_a.as_message(), value_b.as_message()) {
+            (Some(message_a), Some(message_b))
+                if has_a && has_b && !field.is_list() && !field.is_map() =>
+            {
+                debug_assert!(matches!(field.kind(), Kind::Message(_)));
+                let len = paths.len();
+                diff_fields(message_a, message_b, &format!("{}.", path), paths);
+                if paths.len() == len {
+                    // The messages only differ in fields not covered by the mask, such as unknown
+                    // fields, so replace the message as a whole.
+                    paths.push(path);
+                }
+            }
+            _ => paths.push(path),
+        }
+    }
+}
+
+/// Parses a [`FieldMask`] from its [JSON representation](https://developers.google.com/protocol-buffers/docs/proto3#json),
+/// a comma-separated list of paths with each field name in lower camel case, such as
+/// `"user.displayName,photo"`.
+///
+/// Each field name is converted to snake case. An error is returned if a path contains an
+/// underscore, since it could not have been produced from a snake case field name.
+///
+/// # Examples
+///
+/// ```
+/// use prost_reflect::field_mask;
+///
+/// let mask = field_mask::from_json_string("user.displayName,photo").unwrap();
+/// assert_eq!(mask.paths, vec!["user.display_name".to_owned(), "photo".to_owned()]);
+/// assert_eq!(field_mask::to_json_string(&mask).unwrap(), "user.displayName,photo");
+/// ```
+pub fn from_json_string(s: &str) -> Result<FieldMask, FieldMaskError> {
+    let paths = s
+        .split(',')
+        .filter(|path| !path.is_empty())
+        .map(|path| {
+            let mut result = String::with_capacity(path.len());
+            for (index, part) in path.split('.').enumerate() {
+                if index != 0 {
+                    result.push('.');
+                }
+                camel_case_to_snake_case(&mut result, part).map_err(|()| {
+                    FieldMaskErrorKind::InvalidJson {
+                        path: path.to_owned(),
+                    }
+                })?;
+            }
+            Ok(result)
+        })
+        .collect::<Result<_, FieldMaskError>>()?;
+    Ok(FieldMask { paths })
+}
+
+/// Formats a [`FieldMask`] in its [JSON representation](https://developers.google.com/protocol-buffers/docs/proto3#json),
+/// a comma-separated list of paths with each field name converted to lower camel case.
+///
+/// An error is returned if a field name could not be parsed back from its camel case form, for
+/// example if it contains uppercase letters or consecutive underscores.
+pub fn to_json_string(mask: &FieldMask) -> Result<String, FieldMaskError> {
+    let mut result = String::new();
+    for path in &mask.paths {
+        if !result.is_empty() {
+            result.push(',');
+        }
+        for (index, part) in path.split('.').enumerate() {
+            if index != 0 {
+                result.push('.');
+            }
+            snake_case_to_camel_case(&mut result, part).map_err(|()| {
+                FieldMaskErrorKind::NotCamelCase {
+                    path: path.to_owned(),
+                }
+            })?;
+        }
+    }
+    Ok(result)
+}
+
+/// Parses a [`FieldMask`] from a comma-separated list of paths, keeping field names unchanged,
+/// such as the `update_mask` query parameter `"user.display_name,photo"`.
+///
+/// Whitespace around each path is ignored, as are empty paths.
+pub fn split_paths(s: &str) -> FieldMask {
+    FieldMask {
+        paths: s
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+    }
+}
+
+/// Formats a [`FieldMask`] as a comma-separated list of paths, keeping field names unchanged.
+///
+/// This is the inverse of [`split_paths`].
+pub fn join_paths(mask: &FieldMask) -> String {
+    mask.paths.join(",")
+}
+
+/// Converts a [`FieldMask`] to its canonical form.
+///
+/// The paths are sorted, duplicates are removed, and any path which is covered by another path
+/// is removed. For example, `b,a.c,a` is normalized to `a,b`, since `a` covers `a.c`.
+///
+/// # Examples
+///
+/// ```
+/// use prost_reflect::field_mask;
+///
+/// let mask = field_mask::normalize(&field_mask::split_paths("b,a.c,a,b"));
+/// assert_eq!(field_mask::join_paths(&mask), "a,b");
+/// ```
+pub fn normalize(mask: &FieldMask) -> FieldMask {
+    normalize_paths(mask.paths.clone())
+}
+
+/// Returns the union of two [`FieldMask`]s in canonical form, covering every field covered by
+/// either mask.
+pub fn union(a: &FieldMask, b: &FieldMask) -> FieldMask {
+    normalize_paths(a.paths.iter().chain(&b.paths).cloned().collect())
+}
+
+/// Returns the intersection of two [`FieldMask`]s in canonical form, covering the fields covered
+/// by both masks.
+///
+/// For example, the intersection of `a` and `a.b,c` is `a.b`.
+pub fn intersection(a: &FieldMask, b: &FieldMask) -> FieldMask {
+    let mut paths = Vec::new();
+    for path_a in &a.paths {
+        for path_b in &b.paths {
+            if covers(path_a, path_b) {
+                paths.push(path_b.clone());
+            } else if covers(path_b, path_a) {
+                paths.push(path_a.clone());
+            }
+        }
+    }
+    normalize_paths(paths)
+}
+
+/// Returns the paths of `a` which are not covered by `b`, in canonical form.
+///
+/// Since a mask cannot exclude fields, a path of `a` is kept if `b` covers only some of the
+/// fields within it. For example, subtracting `a.b` from `a,c` gives `a,c`, and subtracting `a`
+/// from `a.b,c` gives `c`.
+pub fn subtract(a: &FieldMask, b: &FieldMask) -> FieldMask {
+    let mut result = normalize(a);
+    result
+        .paths
+        .retain(|path_a| !b.paths.iter().any(|path_b| covers(path_b, path_a)));
+    result
+}
+
+/// Checks that every path of a [`FieldMask`] refers to a field of the message type `desc`.
+///
+/// Each segment of a path must name a field of the message type selected by the preceding
+/// segments, and only the last segment may name a field which is not a message. A map field may
+/// be followed by a key, and a repeated field may be followed by a `*` segment, before the fields
+/// of its values. A `*` segment at the end of a path selects every field. Segments containing
+/// `.`, such as map keys, may be quoted with backticks.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::DescriptorPool;
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::field_mask;
+///
+/// assert!(field_mask::validate(&field_mask::split_paths("foo,nested.bar"), &message_descriptor).is_ok());
+/// assert_eq!(
+///     field_mask::validate(&field_mask::split_paths("foo.bar"), &message_descriptor).unwrap_err().to_string(),
+///     "field mask path 'foo.bar' is not valid for message 'package.MyMessage'",
+/// );
+/// ```
+pub fn validate(mask: &FieldMask, desc: &MessageDescriptor) -> Result<(), FieldMaskError> {
+    for path in &mask.paths {
+        if !is_valid_path(&parse_path(path), desc) {
+            return Err(FieldMaskErrorKind::UnknownField {
+                path: path.clone(),
+                message: desc.full_name().to_owned(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn is_valid_path(segments: &[String], desc: &MessageDescriptor) -> bool {
+    let mut desc = desc.clone();
+    let mut segments = segments.iter().map(String::as_str).peekable();
+    while let Some(segment) = segments.next() {
+        if segment == "*" && segments.peek().is_none() {
+            return true;
+        }
+
+        let field = match desc.get_field_by_name(segment) {
+            Some(field) => field,
+            None => return false,
+        };
+        let kind = if field.is_map() {
+            // Skip the map key.
+            if segments.next().is_none() {
+                return true;
+            }
+            match field.kind() {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                _ => unreachable!("map fields should have a message type"),
+            }
+        } else {
+            if field.is_list() && segments.peek() == Some(&"*") {
+                segments.next();
+            }
+            field.kind()
+        };
+
+        if segments.peek().is_none() {
+            return true;
+        }
+        match kind {
+            Kind::Message(message) => desc = message,
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Returns `true` if `path` covers `other`, because they are equal or `other` selects a field
+/// within `path`.
+fn covers(path: &str, other: &str) -> bool {
+    match other.strip_prefix(path) {
+        Some(rest) => rest.is_empty() || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+fn normalize_paths(mut paths: Vec<String>) -> FieldMask {
+    paths.sort();
+    paths.dedup();
+
+    // Sorting places each path directly before the paths it covers.
+    let mut result: Vec<String> = Vec::with_capacity(paths.len());
+    for path in paths {
+        match result.last() {
+            Some(last) if covers(last, &path) => (),
+            _ => result.push(path),
+        }
+    }
+    FieldMask { paths: result }
+}
+
+/// Splits a path into segments separated by `.`. Segments may be quoted with backticks to
+/// include `.` characters, for example in map keys, and a literal backtick within a quoted
+/// segment is written as two backticks.
+pub(crate) fn parse_path(path: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut quoted = false;
+
+    let mut chars = path.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '`' if quoted && chars.peek() == Some(&'`') => {
+                chars.next();
+                segment.push('`');
+            }
+            '`' => quoted = !quoted,
+            '.' if !quoted => segments.push(mem::take(&mut segment)),
+            _ => segment.push(ch),
+        }
+    }
+    segments.push(segment);
+
+    segments
+}
+
+impl From<FieldMaskErrorKind> for FieldMaskError {
+    fn from(kind: FieldMaskErrorKind) -> Self {
+        FieldMaskError { kind }
+    }
+}
+
+impl fmt::Display for FieldMaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FieldMaskErrorKind::InvalidJson { path } => {
+                write!(f, "invalid field mask path '{}'", path)
+            }
+            FieldMaskErrorKind::NotCamelCase { path } => write!(
+                f,
+                "field mask path '{}' cannot be written in camel case",
+                path
+            ),
+            FieldMaskErrorKind::UnknownField { path, message } => write!(
+                f,
+                "field mask path '{}' is not valid for message '{}'",
+                path, message
+            ),
+        }
+    }
+}
+
+impl Error for FieldMaskError {}
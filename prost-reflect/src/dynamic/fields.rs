@@ -1,15 +1,22 @@
 use std::{
     borrow::Cow,
     collections::btree_map::{self, BTreeMap},
-    fmt,
+    fmt, mem,
+    sync::Arc,
+};
+
+use prost::{
+    bytes::Buf,
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
 };
 
 use crate::{
-    Cardinality, ExtensionDescriptor, FieldDescriptor, Kind, MessageDescriptor, OneofDescriptor,
+    DynamicMessage, ExtensionDescriptor, FieldDescriptor, Kind, MessageDescriptor, OneofDescriptor,
     Value,
 };
 
-use super::unknown::UnknownField;
+use super::{decode::LazyMessage, scalar_list::ScalarList, unknown::UnknownField};
 
 pub(crate) trait FieldDescriptorLike: fmt::Debug {
     fn text_name(&self) -> &str;
@@ -21,7 +28,6 @@ pub(crate) trait FieldDescriptorLike: fmt::Debug {
     fn supports_presence(&self) -> bool;
     fn kind(&self) -> Kind;
     fn is_group(&self) -> bool;
-    fn cardinality(&self) -> Cardinality;
     fn is_list(&self) -> bool;
     fn is_map(&self) -> bool;
     fn is_packed(&self) -> bool;
@@ -32,14 +38,37 @@ pub(crate) trait FieldDescriptorLike: fmt::Debug {
 }
 
 /// A set of extension fields in a protobuf message.
-#[derive(Default, Debug, Clone, PartialEq)]
+///
+/// The fields are reference counted, so cloning a message is cheap, and the fields are only
+/// copied when one of the clones is modified. Nested messages are shared in the same way, so
+/// only the messages on the path to the modified field are copied.
+#[derive(Default, Clone)]
 pub(super) struct DynamicMessageFieldSet {
-    fields: BTreeMap<u32, ValueOrUnknown>,
+    /// The fields of the message, or `None` if no fields have been set, to avoid allocating
+    /// for empty messages.
+    ///
+    /// Nested messages are dropped iteratively rather than recursively, so that dropping deeply
+    /// nested messages cannot overflow the thread's stack.
+    fields: Option<Arc<BTreeMap<u32, ValueOrUnknown>>>,
+    /// Unrecognized values of closed enum fields, which are preserved as unknown fields
+    /// alongside any known values of the same field. This is boxed, so that messages which do not
+    /// use it only pay for a pointer rather than an empty map.
+    #[allow(clippy::box_collection)]
+    unknown_enum_values: Option<Box<BTreeMap<u32, Vec<UnknownField>>>>,
+    /// The encoded fields of a message decoded with
+    /// [`lazy_messages`][crate::DecodeOptions::lazy_messages], if they have not been modified
+    /// yet. They are decoded on first access, and moved into `fields` on first modification.
+    ///
+    /// If they fail to decode, they are kept until the message is cleared, and any fields set
+    /// afterwards are stored in `fields` and encoded after the original bytes.
+    lazy: Option<Arc<LazyMessage>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub(super) enum ValueOrUnknown {
     Value(Value),
+    /// A decoded repeated scalar field, which is stored compactly until it is modified.
+    ScalarList(ScalarList),
     Unknown(Vec<UnknownField>),
 }
 
@@ -50,36 +79,156 @@ pub(super) enum ValueAndDescriptor<'a> {
 }
 
 impl DynamicMessageFieldSet {
-    fn get_value(&self, number: u32) -> Option<&Value> {
-        match self.fields.get(&number) {
-            Some(ValueOrUnknown::Value(value)) => Some(value),
+    pub(super) fn lazy(message: LazyMessage) -> Self {
+        DynamicMessageFieldSet {
+            fields: None,
+            unknown_enum_values: None,
+            lazy: Some(Arc::new(message)),
+        }
+    }
+
+    /// Returns the encoded fields of a lazily decoded message which has not been modified.
+    pub(super) fn encoded(&self) -> Option<&[u8]> {
+        match &self.lazy {
+            Some(lazy) if !self.has_local_fields() => Some(lazy.bytes()),
+            _ => None,
+        }
+    }
+
+    /// Returns the original bytes of a lazily decoded message which failed to decode, and has
+    /// been modified since. They are encoded before the fields which have been set.
+    pub(super) fn undecoded(&self) -> Option<&[u8]> {
+        match &self.lazy {
+            Some(lazy) if self.has_local_fields() => Some(lazy.bytes()),
+            _ => None,
+        }
+    }
+
+    /// Returns the original bytes of a lazily decoded message which failed to decode.
+    fn undecodable(&self) -> Option<&[u8]> {
+        match &self.lazy {
+            Some(lazy) if lazy.decode().is_err() => Some(lazy.bytes()),
+            _ => None,
+        }
+    }
+
+    fn has_local_fields(&self) -> bool {
+        self.fields.is_some() || self.unknown_enum_values.is_some()
+    }
+
+    /// Returns `true` if no fields have been set or decoded.
+    pub(super) fn is_empty(&self) -> bool {
+        self.fields.is_none() && self.unknown_enum_values.is_none() && self.lazy.is_none()
+    }
+
+    /// Decodes the fields of a lazily decoded message, returning any error.
+    pub(super) fn resolve(&self) -> Result<(), DecodeError> {
+        match &self.lazy {
+            Some(lazy) => match lazy.decode() {
+                Ok(_) => Ok(()),
+                Err(err) => Err(err.clone()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the field set containing the decoded fields, decoding them first if necessary.
+    ///
+    /// If the fields fail to decode, this contains only the fields set since.
+    fn decoded(&self) -> &DynamicMessageFieldSet {
+        match self.lazy.as_deref().map(LazyMessage::decode) {
+            Some(Ok(decoded)) => decoded,
+            Some(Err(_)) | None => self,
+        }
+    }
+
+    /// Replaces lazily decoded fields with their decoded values, so they can be modified.
+    ///
+    /// If the fields fail to decode, the original bytes are kept, so that they are not lost.
+    fn materialize(&mut self) {
+        if let Some(lazy) = &self.lazy {
+            if let Ok(decoded) = lazy.decode() {
+                self.fields = decoded.fields.clone();
+                self.unknown_enum_values = decoded.unknown_enum_values.clone();
+                self.lazy = None;
+            }
+        }
+    }
+
+    /// Iterates over the values of all known fields which are set, including extensions.
+    ///
+    /// Repeated scalar fields stored as a [`ScalarList`] are skipped, since they cannot contain
+    /// messages.
+    pub(super) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.fields().filter_map(|(_, field)| match field {
+            ValueOrUnknown::Value(value) => Some(value),
+            ValueOrUnknown::ScalarList(_) | ValueOrUnknown::Unknown(_) => None,
+        })
+    }
+
+    fn fields(&self) -> impl Iterator<Item = (&u32, &ValueOrUnknown)> {
+        self.decoded()
+            .fields
+            .iter()
+            .flat_map(|fields| fields.iter())
+    }
+
+    fn get_field(&self, number: u32) -> Option<&ValueOrUnknown> {
+        self.decoded().fields.as_ref()?.get(&number)
+    }
+
+    /// Returns the fields for modification, copying them first if they are shared with another
+    /// message.
+    fn fields_mut(&mut self) -> &mut BTreeMap<u32, ValueOrUnknown> {
+        self.materialize();
+        Arc::make_mut(self.fields.get_or_insert_with(Default::default))
+    }
+
+    fn remove(&mut self, number: u32) {
+        if self.get_field(number).is_some() {
+            self.fields_mut().remove(&number);
+        }
+    }
+
+    fn get_value(&self, number: u32) -> Option<Cow<'_, Value>> {
+        match self.get_field(number) {
+            Some(ValueOrUnknown::Value(value)) => Some(Cow::Borrowed(value)),
+            Some(ValueOrUnknown::ScalarList(list)) => Some(Cow::Owned(list.to_value())),
             Some(ValueOrUnknown::Unknown(_)) | None => None,
         }
     }
 
     pub(super) fn has(&self, desc: &impl FieldDescriptorLike) -> bool {
-        self.get_value(desc.number())
-            .map(|value| desc.has(value))
-            .unwrap_or(false)
+        match self.get_field(desc.number()) {
+            Some(ValueOrUnknown::Value(value)) => desc.has(value),
+            Some(ValueOrUnknown::ScalarList(list)) => !list.is_empty(),
+            Some(ValueOrUnknown::Unknown(_)) | None => false,
+        }
     }
 
     pub(super) fn get(&self, desc: &impl FieldDescriptorLike) -> Cow<'_, Value> {
         match self.get_value(desc.number()) {
-            Some(value) => Cow::Borrowed(value),
+            Some(value) => value,
             None => Cow::Owned(desc.default_value()),
         }
     }
 
     pub(super) fn get_mut(&mut self, desc: &impl FieldDescriptorLike) -> &mut Value {
         self.clear_oneof_fields(desc);
-        match self.fields.entry(desc.number()) {
-            btree_map::Entry::Occupied(entry) => match entry.into_mut() {
-                ValueOrUnknown::Value(value) => value,
-                value @ ValueOrUnknown::Unknown(_) => {
-                    *value = ValueOrUnknown::Value(desc.default_value());
-                    value.unwrap_value_mut()
+        match self.fields_mut().entry(desc.number()) {
+            btree_map::Entry::Occupied(entry) => {
+                let value = entry.into_mut();
+                match value {
+                    ValueOrUnknown::Value(_) => (),
+                    ValueOrUnknown::ScalarList(list) => {
+                        *value = ValueOrUnknown::Value(list.to_value())
+                    }
+                    ValueOrUnknown::Unknown(_) => {
+                        *value = ValueOrUnknown::Value(desc.default_value())
+                    }
                 }
-            },
+                value.unwrap_value_mut()
+            }
             btree_map::Entry::Vacant(entry) => entry
                 .insert(ValueOrUnknown::Value(desc.default_value()))
                 .unwrap_value_mut(),
@@ -95,10 +244,75 @@ impl DynamicMessageFieldSet {
         );
 
         self.clear_oneof_fields(desc);
-        self.fields
+        self.fields_mut()
             .insert(desc.number(), ValueOrUnknown::Value(value));
     }
 
+    /// Merges an encoded value into a field.
+    ///
+    /// Repeated scalar fields are decoded into a [`ScalarList`], unless they already hold a
+    /// [`Value::List`], for example because they were modified after being decoded.
+    pub(super) fn merge_field<B>(
+        &mut self,
+        desc: &impl FieldDescriptorLike,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        let kind = desc.kind();
+        if let Some(list) = self.scalar_list_mut(desc, &kind) {
+            return match list {
+                ValueOrUnknown::ScalarList(list) => list.merge(&kind, wire_type, buf, ctx),
+                value => value
+                    .unwrap_value_mut()
+                    .merge_field(desc, wire_type, buf, ctx),
+            };
+        }
+
+        self.get_mut(desc).merge_field(desc, wire_type, buf, ctx)
+    }
+
+    /// Appends a value to a repeated field.
+    pub(super) fn push(&mut self, desc: &impl FieldDescriptorLike, value: Value) {
+        let kind = desc.kind();
+        let list = match self.scalar_list_mut(desc, &kind) {
+            Some(ValueOrUnknown::ScalarList(list)) => return list.push(value),
+            Some(list) => list.unwrap_value_mut(),
+            None => self.get_mut(desc),
+        };
+        match list {
+            Value::List(values) => values.push(value),
+            list => panic!(
+                "mismatch between DynamicMessage value {:?} and type {:?}",
+                list, kind
+            ),
+        }
+    }
+
+    /// Returns the entry of a repeated scalar field, inserting an empty [`ScalarList`] if it is
+    /// not set, or `None` if the field is not a repeated scalar field.
+    fn scalar_list_mut(
+        &mut self,
+        desc: &impl FieldDescriptorLike,
+        kind: &Kind,
+    ) -> Option<&mut ValueOrUnknown> {
+        if !desc.is_list() {
+            return None;
+        }
+        let list = ScalarList::new(kind)?;
+        let value = self
+            .fields_mut()
+            .entry(desc.number())
+            .or_insert_with(|| ValueOrUnknown::Unknown(Vec::new()));
+        if let ValueOrUnknown::Unknown(_) = value {
+            *value = ValueOrUnknown::ScalarList(list);
+        }
+        Some(value)
+    }
+
     fn clear_oneof_fields(&mut self, desc: &impl FieldDescriptorLike) {
         if let Some(oneof_desc) = desc.containing_oneof() {
             for oneof_field in oneof_desc.fields() {
@@ -110,9 +324,9 @@ impl DynamicMessageFieldSet {
     }
 
     pub(crate) fn add_unknown(&mut self, number: u32, unknown: UnknownField) {
-        match self.fields.entry(number) {
+        match self.fields_mut().entry(number) {
             btree_map::Entry::Occupied(mut entry) => match entry.get_mut() {
-                ValueOrUnknown::Value(_) => {
+                ValueOrUnknown::Value(_) | ValueOrUnknown::ScalarList(_) => {
                     panic!("expected no field to be found with number {}", number)
                 }
                 ValueOrUnknown::Unknown(unknowns) => unknowns.push(unknown),
@@ -123,79 +337,234 @@ impl DynamicMessageFieldSet {
         }
     }
 
+    pub(super) fn add_unknown_enum_value(&mut self, number: u32, unknown: UnknownField) {
+        self.materialize();
+        self.unknown_enum_values
+            .get_or_insert_with(Default::default)
+            .entry(number)
+            .or_default()
+            .push(unknown);
+    }
+
+    pub(super) fn has_unknown_enum_values(&self) -> bool {
+        self.decoded().unknown_enum_values.is_some()
+    }
+
+    pub(super) fn is_unknown(&self, number: u32) -> bool {
+        matches!(self.get_field(number), Some(ValueOrUnknown::Unknown(_)))
+    }
+
+    /// Replaces the value of a field with the unknown fields it would be encoded as, so that
+    /// further occurrences of the field can be preserved in order.
+    pub(super) fn convert_to_unknown(&mut self, desc: &impl FieldDescriptorLike) {
+        if let Some(value) = self.get_value(desc.number()) {
+            let mut buf = Vec::new();
+            value.encode_field(desc, &mut buf);
+            self.remove(desc.number());
+
+            let mut buf = buf.as_slice();
+            while buf.has_remaining() {
+                let (number, wire_type) =
+                    encoding::decode_key(&mut buf).expect("failed to decode encoded field");
+                let field = UnknownField::decode(number, wire_type, &mut buf, Default::default())
+                    .expect("failed to decode encoded field");
+                self.add_unknown(number, field);
+            }
+        }
+    }
+
     pub(super) fn clear(&mut self, desc: &impl FieldDescriptorLike) {
-        self.fields.remove(&desc.number());
+        self.materialize();
+        self.remove(desc.number());
+        if let Some(unknown_enum_values) = &mut self.unknown_enum_values {
+            unknown_enum_values.remove(&desc.number());
+            if unknown_enum_values.is_empty() {
+                self.unknown_enum_values = None;
+            }
+        }
     }
 
     pub(crate) fn iter<'a>(
         &'a self,
         message: &'a MessageDescriptor,
-    ) -> impl Iterator<Item = ValueAndDescriptor> + 'a {
-        self.fields
-            .iter()
-            .filter_map(move |(&number, value)| match value {
-                ValueOrUnknown::Value(value) => {
-                    if let Some(field) = message.get_field(number) {
-                        if field.has(value) {
-                            Some(ValueAndDescriptor::Field(Cow::Borrowed(value), field))
-                        } else {
-                            None
-                        }
-                    } else if let Some(extension) = message.get_extension(number) {
-                        if extension.has(value) {
-                            Some(ValueAndDescriptor::Extension(
-                                Cow::Borrowed(value),
-                                extension,
-                            ))
-                        } else {
-                            None
-                        }
+    ) -> impl Iterator<Item = ValueAndDescriptor<'a>> + 'a {
+        self.fields()
+            .filter_map(move |(&number, value)| {
+                let value = match value {
+                    ValueOrUnknown::Value(value) => Cow::Borrowed(value),
+                    ValueOrUnknown::ScalarList(list) if list.is_empty() => return None,
+                    ValueOrUnknown::ScalarList(list) => Cow::Owned(list.to_value()),
+                    ValueOrUnknown::Unknown(unknown) => {
+                        return Some(ValueAndDescriptor::Unknown(number, unknown.as_slice()))
+                    }
+                };
+                if let Some(field) = message.get_field(number) {
+                    if field.has(&value) {
+                        Some(ValueAndDescriptor::Field(value, field))
                     } else {
-                        panic!("no field found with number {}", number)
+                        None
                     }
-                }
-                ValueOrUnknown::Unknown(unknown) => {
-                    Some(ValueAndDescriptor::Unknown(number, unknown.as_slice()))
+                } else if let Some(extension) = message.get_extension(number) {
+                    if extension.has(&value) {
+                        Some(ValueAndDescriptor::Extension(value, extension))
+                    } else {
+                        None
+                    }
+                } else {
+                    panic!("no field found with number {}", number)
                 }
             })
+            .chain(self.iter_unknown_enum_values())
     }
 
     #[cfg(feature = "serde")]
     pub(crate) fn iter_include_default<'a>(
         &'a self,
         message: &'a MessageDescriptor,
-    ) -> impl Iterator<Item = ValueAndDescriptor> + 'a {
+    ) -> impl Iterator<Item = ValueAndDescriptor<'a>> + 'a {
         let fields = message
             .fields()
             .filter(move |f| !f.supports_presence() || self.has(f))
             .map(move |f| ValueAndDescriptor::Field(self.get(&f), f));
-        let others = self
-            .fields
+        let others = self.fields().filter_map(move |(&number, value)| {
+            let value = match value {
+                ValueOrUnknown::Value(value) => Cow::Borrowed(value),
+                ValueOrUnknown::ScalarList(list) if list.is_empty() => return None,
+                ValueOrUnknown::ScalarList(list) => Cow::Owned(list.to_value()),
+                ValueOrUnknown::Unknown(unknown) => {
+                    return Some(ValueAndDescriptor::Unknown(number, unknown.as_slice()))
+                }
+            };
+            match message.get_extension(number) {
+                Some(extension) if extension.has(&value) => {
+                    Some(ValueAndDescriptor::Extension(value, extension))
+                }
+                _ => None,
+            }
+        });
+        fields.chain(others).chain(self.iter_unknown_enum_values())
+    }
+
+    fn iter_unknown_enum_values(&self) -> impl Iterator<Item = ValueAndDescriptor<'_>> {
+        self.decoded()
+            .unknown_enum_values
             .iter()
-            .filter_map(move |(&number, value)| match value {
-                ValueOrUnknown::Value(value) => {
-                    if let Some(extension) = message.get_extension(number) {
-                        if extension.has(value) {
-                            Some(ValueAndDescriptor::Extension(
-                                Cow::Borrowed(value),
-                                extension,
-                            ))
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
+            .flat_map(|unknown_enum_values| unknown_enum_values.iter())
+            .map(|(&number, unknown)| ValueAndDescriptor::Unknown(number, unknown.as_slice()))
+    }
+
+    pub(super) fn clear_all(&mut self) {
+        self.fields = None;
+        self.unknown_enum_values = None;
+        self.lazy = None;
+    }
+
+    /// Pushes the nested messages in the values of all known fields onto `stack`, including
+    /// list elements and map values, so that they can be modified.
+    ///
+    /// The fields are only copied if they contain a nested message and are shared with another
+    /// message.
+    pub(super) fn push_nested_mut<'a>(&'a mut self, stack: &mut Vec<&'a mut DynamicMessage>) {
+        let has_nested = self.values().any(|value| match value {
+            Value::Message(_) => true,
+            Value::List(values) => values.iter().any(|value| value.as_message().is_some()),
+            Value::Map(values) => values.values().any(|value| value.as_message().is_some()),
+            _ => false,
+        });
+        if !has_nested {
+            return;
+        }
+
+        for field in self.fields_mut().values_mut() {
+            match field {
+                ValueOrUnknown::Value(Value::Message(message)) => stack.push(message),
+                ValueOrUnknown::Value(Value::List(values)) => {
+                    stack.extend(values.iter_mut().filter_map(Value::as_message_mut))
+                }
+                ValueOrUnknown::Value(Value::Map(values)) => {
+                    stack.extend(values.values_mut().filter_map(Value::as_message_mut))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Moves the field sets of nested messages which are not shared with any other message into
+    /// `stack`, leaving them empty.
+    fn take_nested(&mut self, stack: &mut Vec<DynamicMessageFieldSet>) {
+        if let Some(lazy) = self.lazy.as_mut().and_then(Arc::get_mut) {
+            if let Some(decoded) = lazy.decoded_mut() {
+                stack.push(mem::take(decoded));
+            }
+        }
+
+        if let Some(fields) = self.fields.as_mut().and_then(Arc::get_mut) {
+            let mut take = |value: &mut Value| {
+                if let Value::Message(message) = value {
+                    if !message.fields.is_empty() {
+                        stack.push(mem::take(&mut message.fields));
                     }
                 }
-                ValueOrUnknown::Unknown(unknown) => {
-                    Some(ValueAndDescriptor::Unknown(number, unknown.as_slice()))
+            };
+
+            for field in fields.values_mut() {
+                match field {
+                    ValueOrUnknown::Value(Value::List(values)) => {
+                        values.iter_mut().for_each(&mut take)
+                    }
+                    ValueOrUnknown::Value(Value::Map(values)) => {
+                        values.values_mut().for_each(&mut take)
+                    }
+                    ValueOrUnknown::Value(value) => take(value),
+                    ValueOrUnknown::ScalarList(_) | ValueOrUnknown::Unknown(_) => (),
                 }
-            });
-        fields.chain(others)
+            }
+        }
     }
+}
 
-    pub(super) fn clear_all(&mut self) {
-        self.fields.clear();
+impl Drop for DynamicMessageFieldSet {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+        self.take_nested(&mut stack);
+        while let Some(mut fields) = stack.pop() {
+            fields.take_nested(&mut stack);
+        }
+    }
+}
+
+impl PartialEq for DynamicMessageFieldSet {
+    fn eq(&self, other: &Self) -> bool {
+        if let (Some(lazy), Some(other_lazy)) = (&self.lazy, &other.lazy) {
+            if Arc::ptr_eq(lazy, other_lazy)
+                && !self.has_local_fields()
+                && !other.has_local_fields()
+            {
+                return true;
+            }
+        }
+
+        let (this, other) = (self.decoded(), other.decoded());
+        let fields_eq = match (&this.fields, &other.fields) {
+            (Some(fields), Some(other_fields)) if Arc::ptr_eq(fields, other_fields) => true,
+            _ => this.fields().eq(other.fields()),
+        };
+        fields_eq
+            && this.unknown_enum_values == other.unknown_enum_values
+            && self.undecodable() == other.undecodable()
+    }
+}
+
+impl fmt::Debug for DynamicMessageFieldSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decoded = self.decoded();
+        f.debug_struct("DynamicMessageFieldSet")
+            .field(
+                "fields",
+                decoded.fields.as_deref().unwrap_or(&BTreeMap::new()),
+            )
+            .field("unknown_enum_values", &decoded.unknown_enum_values)
+            .finish()
     }
 }
 
@@ -203,7 +572,22 @@ impl ValueOrUnknown {
     fn unwrap_value_mut(&mut self) -> &mut Value {
         match self {
             ValueOrUnknown::Value(value) => value,
-            ValueOrUnknown::Unknown(_) => unreachable!(),
+            ValueOrUnknown::ScalarList(_) | ValueOrUnknown::Unknown(_) => unreachable!(),
+        }
+    }
+}
+
+impl PartialEq for ValueOrUnknown {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ValueOrUnknown::Value(value), ValueOrUnknown::Value(other)) => value == other,
+            (ValueOrUnknown::ScalarList(list), ValueOrUnknown::ScalarList(other)) => list == other,
+            (ValueOrUnknown::ScalarList(list), ValueOrUnknown::Value(Value::List(values)))
+            | (ValueOrUnknown::Value(Value::List(values)), ValueOrUnknown::ScalarList(list)) => {
+                list.eq_values(values)
+            }
+            (ValueOrUnknown::Unknown(unknown), ValueOrUnknown::Unknown(other)) => unknown == other,
+            _ => false,
         }
     }
 }
@@ -245,10 +629,6 @@ impl FieldDescriptorLike for FieldDescriptor {
         self.is_group()
     }
 
-    fn cardinality(&self) -> Cardinality {
-        self.cardinality()
-    }
-
     fn is_list(&self) -> bool {
         self.is_list()
     }
@@ -303,10 +683,6 @@ impl FieldDescriptorLike for ExtensionDescriptor {
         self.is_group()
     }
 
-    fn cardinality(&self) -> Cardinality {
-        self.cardinality()
-    }
-
     fn is_list(&self) -> bool {
         self.is_list()
     }
@@ -323,3 +699,64 @@ impl FieldDescriptorLike for ExtensionDescriptor {
         self.is_packable()
     }
 }
+
+#[test]
+fn scalar_list_sizes() {
+    assert_eq!(
+        std::mem::size_of::<ValueOrUnknown>(),
+        std::mem::size_of::<Value>()
+    );
+}
+
+#[test]
+fn scalar_lists_are_stored_inline() {
+    use prost::Message;
+    use prost_types::source_code_info::Location;
+
+    use crate::ReflectMessage;
+
+    let location = Location {
+        path: vec![4, 0, 2],
+        span: (0..100).collect(),
+        ..Default::default()
+    };
+    let mut message =
+        DynamicMessage::decode(location.descriptor(), location.encode_to_vec().as_slice()).unwrap();
+
+    let path = message.descriptor().get_field_by_name("path").unwrap();
+    let span = message.descriptor().get_field_by_name("span").unwrap();
+    match message.fields.get_field(path.number()) {
+        Some(ValueOrUnknown::ScalarList(list)) => assert!(list.is_inline()),
+        field => panic!("expected a scalar list, found {:?}", field),
+    }
+    match message.fields.get_field(span.number()) {
+        Some(ValueOrUnknown::ScalarList(list)) => assert!(!list.is_inline()),
+        field => panic!("expected a scalar list, found {:?}", field),
+    }
+
+    let values = |values: &[i32]| Value::List(values.iter().map(|&v| Value::I32(v)).collect());
+    assert_eq!(*message.get_field(&path), values(&[4, 0, 2]));
+    assert_eq!(message.encode_to_vec(), location.encode_to_vec());
+    assert_eq!(message, location.transcode_to_dynamic());
+
+    message
+        .get_field_mut(&path)
+        .as_list_mut()
+        .unwrap()
+        .push(Value::I32(1));
+    assert!(matches!(
+        message.fields.get_field(path.number()),
+        Some(ValueOrUnknown::Value(_))
+    ));
+    message
+        .merge(
+            Location {
+                path: vec![5],
+                ..Default::default()
+            }
+            .encode_to_vec()
+            .as_slice(),
+        )
+        .unwrap();
+    assert_eq!(*message.get_field(&path), values(&[4, 0, 2, 1, 5]));
+}
@@ -0,0 +1,419 @@
+#[cfg(feature = "grpc-web")]
+#[cfg_attr(docsrs, doc(cfg(feature = "grpc-web")))]
+pub mod web;
+
+use std::{error::Error, fmt};
+
+use prost::{
+    bytes::{Buf, BufMut, Bytes, BytesMut},
+    DecodeError, Message,
+};
+
+use crate::{DynamicMessage, MessageDescriptor};
+
+/// The length of the prefix which precedes each message: a 1-byte compressed flag followed by the
+/// length of the message as a 4-byte big-endian integer.
+pub const HEADER_LEN: usize = 5;
+
+/// The default limit on the size of decoded messages, matching the default used by gRPC
+/// implementations.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+/// The compression applied to messages, as named by the `grpc-encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Messages are not compressed.
+    Identity,
+    /// Messages are compressed using gzip.
+    #[cfg(feature = "gzip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+    Gzip,
+    /// Messages are compressed using the zlib format.
+    #[cfg(feature = "deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "deflate")))]
+    Deflate,
+}
+
+/// An incremental decoder for a stream of framed messages, such as the body of a gRPC request or
+/// response.
+///
+/// The contents of each HTTP/2 DATA frame are added with [`push`][FrameDecoder::push], which may
+/// split messages at any point, and complete messages are taken from the decoder with
+/// [`decode_next`][FrameDecoder::decode_next].
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// use prost_reflect::framing::{self, Compression, FrameDecoder};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+/// let desc = pool.get_message_by_name("package.MyMessage").unwrap();
+/// let mut message = DynamicMessage::new(desc.clone());
+/// message.set_field_by_name("foo", Value::I32(150));
+///
+/// let mut bytes = framing::encode_to_vec(&message, Compression::Identity);
+/// bytes.extend(framing::encode_to_vec(&message, Compression::Identity));
+///
+/// let mut decoder = FrameDecoder::new(desc, Compression::Identity);
+/// decoder.push(&bytes[..4]);
+/// assert_eq!(decoder.decode_next().unwrap(), None);
+/// decoder.push(&bytes[4..]);
+/// assert_eq!(decoder.decode_next().unwrap(), Some(message.clone()));
+/// assert_eq!(decoder.decode_next().unwrap(), Some(message));
+/// assert_eq!(decoder.decode_next().unwrap(), None);
+/// decoder.finish().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    desc: MessageDescriptor,
+    compression: Compression,
+    max_message_size: usize,
+    buf: BytesMut,
+}
+
+/// An error that occurred while decoding a framed message.
+#[derive(Debug)]
+pub struct FramingError {
+    kind: FramingErrorKind,
+}
+
+#[derive(Debug)]
+enum FramingErrorKind {
+    InvalidFlag(u8),
+    UnexpectedCompression,
+    MessageTooLarge {
+        len: usize,
+        limit: usize,
+    },
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    DecompressedTooLarge {
+        limit: usize,
+    },
+    Incomplete {
+        remaining: usize,
+    },
+    TrailingData {
+        remaining: usize,
+    },
+    #[cfg(any(feature = "gzip", feature = "deflate"))]
+    Decompress(std::io::Error),
+    Decode(DecodeError),
+    #[cfg(feature = "grpc-web")]
+    InvalidBase64(base64::DecodeError),
+    #[cfg(feature = "grpc-web")]
+    InvalidTrailers,
+}
+
+impl Compression {
+    /// Gets the compression named by a value of the `grpc-encoding` header, or `None` if it is
+    /// not supported.
+    pub fn from_encoding(encoding: &str) -> Option<Self> {
+        match encoding {
+            "identity" => Some(Compression::Identity),
+            #[cfg(feature = "gzip")]
+            "gzip" => Some(Compression::Gzip),
+            #[cfg(feature = "deflate")]
+            "deflate" => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+
+    /// Gets the name of this compression, as used in the `grpc-encoding` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::Identity => "identity",
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => "deflate",
+        }
+    }
+
+    fn compress(&self, data: Vec<u8>) -> Vec<u8> {
+        #[cfg(any(feature = "gzip", feature = "deflate"))]
+        use std::io::Write;
+
+        match self {
+            Compression::Identity => data,
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(&data)
+                    .expect("writing to a vector cannot fail");
+                encoder.finish().expect("writing to a vector cannot fail")
+            }
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(&data)
+                    .expect("writing to a vector cannot fail");
+                encoder.finish().expect("writing to a vector cannot fail")
+            }
+        }
+    }
+
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "deflate")),
+        allow(unused_variables)
+    )]
+    fn decompress(&self, data: Bytes, limit: usize) -> Result<Bytes, FramingError> {
+        #[cfg(any(feature = "gzip", feature = "deflate"))]
+        fn read_limited<R: std::io::Read>(reader: R, limit: usize) -> Result<Bytes, FramingError> {
+            let mut result = Vec::new();
+            // Read one byte past the limit to detect messages which are too large without
+            // decompressing all of them.
+            std::io::Read::read_to_end(&mut reader.take(limit as u64 + 1), &mut result)
+                .map_err(FramingErrorKind::Decompress)?;
+            if result.len() > limit {
+                return Err(FramingErrorKind::DecompressedTooLarge { limit }.into());
+            }
+            Ok(result.into())
+        }
+
+        match self {
+            Compression::Identity => Err(FramingErrorKind::UnexpectedCompression.into()),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => read_limited(flate2::read::GzDecoder::new(&data[..]), limit),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => read_limited(flate2::read::ZlibDecoder::new(&data[..]), limit),
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Identity
+    }
+}
+
+/// Encodes a message to the buffer, preceded by its compressed flag and length.
+///
+/// The message is compressed unless `compression` is [`Identity`][Compression::Identity].
+pub fn encode<M, B>(message: &M, compression: Compression, buf: &mut B)
+where
+    M: Message,
+    B: BufMut,
+{
+    let data = compression.compress(message.encode_to_vec());
+    buf.put_u8((compression != Compression::Identity) as u8);
+    buf.put_u32(u32::try_from(data.len()).expect("message is too large to be framed"));
+    buf.put_slice(&data);
+}
+
+/// Encodes a message to a vector, preceded by its compressed flag and length.
+///
+/// See [`encode`] for details.
+pub fn encode_to_vec<M>(message: &M, compression: Compression) -> Vec<u8>
+where
+    M: Message,
+{
+    let mut buf = Vec::with_capacity(HEADER_LEN + message.encoded_len());
+    encode(message, compression, &mut buf);
+    buf
+}
+
+/// Decodes a single framed message of the given type.
+///
+/// The buffer must contain exactly one message. Compressed messages are decompressed using
+/// `compression`, and messages larger than [`DEFAULT_MAX_MESSAGE_SIZE`] are rejected.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// use prost_reflect::framing::{self, Compression};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+/// let desc = pool.get_message_by_name("package.MyMessage").unwrap();
+/// let message = framing::decode(desc, Compression::Identity, b"\x00\x00\x00\x00\x03\x08\x96\x01".as_ref()).unwrap();
+/// assert_eq!(message.get_field_by_name("foo").unwrap().as_i32(), Some(150));
+/// ```
+pub fn decode<B>(
+    desc: MessageDescriptor,
+    compression: Compression,
+    mut buf: B,
+) -> Result<DynamicMessage, FramingError>
+where
+    B: Buf,
+{
+    let mut decoder = FrameDecoder::new(desc, compression);
+    decoder.push(buf.copy_to_bytes(buf.remaining()));
+    match decoder.decode_next()? {
+        Some(message) if decoder.buf.is_empty() => Ok(message),
+        Some(_) => Err(FramingErrorKind::TrailingData {
+            remaining: decoder.buf.len(),
+        }
+        .into()),
+        None => Err(FramingErrorKind::Incomplete {
+            remaining: decoder.buf.len(),
+        }
+        .into()),
+    }
+}
+
+impl FrameDecoder {
+    /// Creates a decoder for messages of the given type, using `compression` to decompress
+    /// messages whose compressed flag is set.
+    pub fn new(desc: MessageDescriptor, compression: Compression) -> Self {
+        FrameDecoder {
+            desc,
+            compression,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// Sets the maximum size of a message, after decompression.
+    ///
+    /// The default value is [`DEFAULT_MAX_MESSAGE_SIZE`].
+    pub fn max_message_size(mut self, limit: usize) -> Self {
+        self.max_message_size = limit;
+        self
+    }
+
+    /// Adds data to the end of the buffered input.
+    pub fn push(&mut self, data: impl AsRef<[u8]>) {
+        self.buf.extend_from_slice(data.as_ref());
+    }
+
+    /// Returns the number of bytes of input which have not yet been decoded.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Decodes the next message from the buffered input, or returns `None` if a complete message
+    /// has not been received yet.
+    ///
+    /// After an error, the decoder is left in an unspecified state and should not be used
+    /// further.
+    pub fn decode_next(&mut self) -> Result<Option<DynamicMessage>, FramingError> {
+        match self.next_frame(false)? {
+            Some((flag, data)) => self.decode_message(flag, data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Takes the next complete frame from the buffered input, returning its flag and contents.
+    ///
+    /// If `trailers` is true, the flag may also have the gRPC-Web trailers bit set.
+    fn next_frame(&mut self, trailers: bool) -> Result<Option<(u8, Bytes)>, FramingError> {
+        if self.buf.len() < HEADER_LEN {
+            return Ok(None);
+        }
+
+        let flag = self.buf[0];
+        match flag {
+            0 | 1 => (),
+            0x80 | 0x81 if trailers => (),
+            flag => return Err(FramingErrorKind::InvalidFlag(flag).into()),
+        }
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+        if len > self.max_message_size {
+            return Err(FramingErrorKind::MessageTooLarge {
+                len,
+                limit: self.max_message_size,
+            }
+            .into());
+        }
+        if self.buf.len() < HEADER_LEN + len {
+            return Ok(None);
+        }
+
+        self.buf.advance(HEADER_LEN);
+        Ok(Some((flag, self.buf.split_to(len).freeze())))
+    }
+
+    /// Decompresses the contents of a frame if its compressed flag is set.
+    fn decompress(&self, flag: u8, data: Bytes) -> Result<Bytes, FramingError> {
+        if flag & 1 != 0 {
+            self.compression.decompress(data, self.max_message_size)
+        } else {
+            Ok(data)
+        }
+    }
+
+    fn decode_message(&self, flag: u8, data: Bytes) -> Result<DynamicMessage, FramingError> {
+        let data = self.decompress(flag, data)?;
+        DynamicMessage::decode(self.desc.clone(), data)
+            .map_err(|err| FramingErrorKind::Decode(err).into())
+    }
+
+    /// Checks that no partial message remains in the buffered input, for use once the stream has
+    /// ended.
+    pub fn finish(&self) -> Result<(), FramingError> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(FramingErrorKind::Incomplete {
+                remaining: self.buf.len(),
+            }
+            .into())
+        }
+    }
+}
+
+impl From<FramingErrorKind> for FramingError {
+    fn from(kind: FramingErrorKind) -> Self {
+        FramingError { kind }
+    }
+}
+
+impl fmt::Display for FramingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FramingErrorKind::InvalidFlag(flag) => {
+                write!(f, "invalid compressed flag {}", flag)
+            }
+            FramingErrorKind::UnexpectedCompression => write!(
+                f,
+                "received a compressed message, but no compression is configured"
+            ),
+            FramingErrorKind::MessageTooLarge { len, limit } => write!(
+                f,
+                "message of {} bytes exceeds the limit of {} bytes",
+                len, limit
+            ),
+            #[cfg(any(feature = "gzip", feature = "deflate"))]
+            FramingErrorKind::DecompressedTooLarge { limit } => write!(
+                f,
+                "decompressed message exceeds the limit of {} bytes",
+                limit
+            ),
+            FramingErrorKind::Incomplete { remaining } => write!(
+                f,
+                "unexpected end of input with {} bytes of an incomplete message",
+                remaining
+            ),
+            FramingErrorKind::TrailingData { remaining } => {
+                write!(f, "found {} bytes of data after the message", remaining)
+            }
+            #[cfg(any(feature = "gzip", feature = "deflate"))]
+            FramingErrorKind::Decompress(err) => {
+                write!(f, "failed to decompress message: {}", err)
+            }
+            FramingErrorKind::Decode(err) => write!(f, "failed to decode message: {}", err),
+            #[cfg(feature = "grpc-web")]
+            FramingErrorKind::InvalidBase64(err) => write!(f, "invalid base64 data: {}", err),
+            #[cfg(feature = "grpc-web")]
+            FramingErrorKind::InvalidTrailers => write!(f, "invalid trailers frame"),
+        }
+    }
+}
+
+impl Error for FramingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            #[cfg(any(feature = "gzip", feature = "deflate"))]
+            FramingErrorKind::Decompress(err) => Some(err),
+            FramingErrorKind::Decode(err) => Some(err),
+            #[cfg(feature = "grpc-web")]
+            FramingErrorKind::InvalidBase64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
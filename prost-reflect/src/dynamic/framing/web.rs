@@ -0,0 +1,311 @@
+//! Encoding and decoding of the [gRPC-Web](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md)
+//! wire format, which lets browsers call gRPC services over HTTP/1.1.
+//!
+//! gRPC-Web uses the same length-prefixed framing as gRPC, but sends the trailers of a response
+//! at the end of the body in a frame with the [`TRAILERS_FLAG`] bit set. In the
+//! [`Text`][Mode::Text] mode, used by clients which cannot handle binary response bodies, the
+//! whole body is additionally encoded as base64.
+//!
+//! # Examples
+//!
+//! ```
+//! # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+//! use prost_reflect::framing::{Compression, web::{self, Frame, FrameDecoder, Mode, Trailers}};
+//!
+//! # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+//! let desc = pool.get_message_by_name("package.MyMessage").unwrap();
+//! let mut message = DynamicMessage::new(desc.clone());
+//! message.set_field_by_name("foo", Value::I32(150));
+//!
+//! let mut trailers = Trailers::new();
+//! trailers.append("grpc-status", "0");
+//!
+//! let mut body = Vec::new();
+//! web::encode(&message, Compression::Identity, Mode::Text, &mut body);
+//! web::encode_trailers(&trailers, Mode::Text, &mut body);
+//!
+//! let mut decoder = FrameDecoder::new(desc, Compression::Identity, Mode::Text);
+//! decoder.push(&body);
+//! assert_eq!(decoder.decode_next().unwrap(), Some(Frame::Message(message)));
+//! assert_eq!(decoder.decode_next().unwrap(), Some(Frame::Trailers(trailers)));
+//! assert_eq!(decoder.decode_next().unwrap(), None);
+//! decoder.finish().unwrap();
+//! ```
+
+use std::str;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use prost::{
+    bytes::{BufMut, BytesMut},
+    Message,
+};
+
+use super::{Compression, FramingError, FramingErrorKind, HEADER_LEN};
+use crate::{DynamicMessage, MessageDescriptor};
+
+/// The bit set in the flag of a frame which contains trailers rather than a message.
+pub const TRAILERS_FLAG: u8 = 0x80;
+
+/// The encoding of a gRPC-Web request or response body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Frames are sent as binary data, with the content type `application/grpc-web`.
+    Binary,
+    /// Frames are encoded as base64, with the content type `application/grpc-web-text`.
+    Text,
+}
+
+/// The trailers of a gRPC-Web response, such as `grpc-status` and `grpc-message`.
+///
+/// Names are case-insensitive, and are stored in lowercase. Values are stored as they are sent,
+/// so the `grpc-message` trailer is still percent-encoded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trailers {
+    entries: Vec<(String, String)>,
+}
+
+/// A frame decoded from a gRPC-Web body.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    /// A message frame.
+    Message(DynamicMessage),
+    /// A trailers frame, which ends a response.
+    Trailers(Trailers),
+}
+
+/// An incremental decoder for the body of a gRPC-Web request or response.
+///
+/// This behaves like [`framing::FrameDecoder`][super::FrameDecoder], but also decodes trailers
+/// frames, and in the [`Text`][Mode::Text] mode decodes base64 input which may be split at any
+/// point. Trailers must be the last frame of the body.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    frames: super::FrameDecoder,
+    mode: Mode,
+    text: BytesMut,
+    trailers_received: bool,
+}
+
+impl Mode {
+    /// Gets the mode for a value of the `content-type` header, or `None` if it is not a gRPC-Web
+    /// content type using protobuf messages.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type {
+            "application/grpc-web" | "application/grpc-web+proto" => Some(Mode::Binary),
+            "application/grpc-web-text" | "application/grpc-web-text+proto" => Some(Mode::Text),
+            _ => None,
+        }
+    }
+
+    /// Gets the content type for bodies encoded in this mode.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Mode::Binary => "application/grpc-web+proto",
+            Mode::Text => "application/grpc-web-text+proto",
+        }
+    }
+
+    fn put(&self, frame: &[u8], buf: &mut impl BufMut) {
+        match self {
+            Mode::Binary => buf.put_slice(frame),
+            Mode::Text => buf.put_slice(BASE64_STANDARD.encode(frame).as_bytes()),
+        }
+    }
+}
+
+impl Trailers {
+    /// Creates an empty set of trailers.
+    pub fn new() -> Self {
+        Trailers::default()
+    }
+
+    /// Adds a trailer. Existing trailers with the same name are kept.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let mut name = name.into();
+        name.make_ascii_lowercase();
+        self.entries.push((name, value.into()));
+    }
+
+    /// Gets the value of the first trailer with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets an iterator over the names and values of the trailers, in the order they were added.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (&str, &str)> + '_ {
+        self.entries
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Gets the status code from the `grpc-status` trailer, if it is present and valid.
+    pub fn grpc_status(&self) -> Option<i32> {
+        self.get("grpc-status")?.trim().parse().ok()
+    }
+
+    /// Gets the value of the `grpc-message` trailer.
+    pub fn grpc_message(&self) -> Option<&str> {
+        self.get("grpc-message")
+    }
+
+    fn encode_block(&self) -> Vec<u8> {
+        let mut block = Vec::new();
+        for (name, value) in &self.entries {
+            block.extend_from_slice(name.as_bytes());
+            block.extend_from_slice(b": ");
+            block.extend_from_slice(value.as_bytes());
+            block.extend_from_slice(b"\r\n");
+        }
+        block
+    }
+
+    fn parse(block: &[u8]) -> Result<Self, FramingError> {
+        let block = str::from_utf8(block).map_err(|_| FramingErrorKind::InvalidTrailers)?;
+
+        let mut trailers = Trailers::new();
+        for line in block.split("\r\n").flat_map(|line| line.split('\n')) {
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or(FramingErrorKind::InvalidTrailers)?;
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(FramingErrorKind::InvalidTrailers.into());
+            }
+            trailers.append(name, value.trim());
+        }
+        Ok(trailers)
+    }
+}
+
+/// Encodes a message frame to the buffer.
+///
+/// This is the same as [`framing::encode`][super::encode], except that in the
+/// [`Text`][Mode::Text] mode the frame is encoded as base64. Each call produces a complete
+/// base64 string, which may be concatenated with others in the same body.
+pub fn encode<M, B>(message: &M, compression: Compression, mode: Mode, buf: &mut B)
+where
+    M: Message,
+    B: BufMut,
+{
+    let frame = super::encode_to_vec(message, compression);
+    mode.put(&frame, buf);
+}
+
+/// Encodes a trailers frame to the buffer.
+///
+/// Trailers are sent as lines of the form `name: value`, in the same way as HTTP/1.1 headers, and
+/// are never compressed.
+pub fn encode_trailers<B>(trailers: &Trailers, mode: Mode, buf: &mut B)
+where
+    B: BufMut,
+{
+    let block = trailers.encode_block();
+    let mut frame = Vec::with_capacity(HEADER_LEN + block.len());
+    frame.put_u8(TRAILERS_FLAG);
+    frame.put_u32(u32::try_from(block.len()).expect("trailers are too large to be framed"));
+    frame.put_slice(&block);
+    mode.put(&frame, buf);
+}
+
+impl FrameDecoder {
+    /// Creates a decoder for a body in the given mode, containing messages of the given type.
+    pub fn new(desc: MessageDescriptor, compression: Compression, mode: Mode) -> Self {
+        FrameDecoder {
+            frames: super::FrameDecoder::new(desc, compression),
+            mode,
+            text: BytesMut::new(),
+            trailers_received: false,
+        }
+    }
+
+    /// Sets the maximum size of a message or trailers frame, after decompression.
+    ///
+    /// The default value is [`DEFAULT_MAX_MESSAGE_SIZE`][super::DEFAULT_MAX_MESSAGE_SIZE].
+    pub fn max_message_size(mut self, limit: usize) -> Self {
+        self.frames = self.frames.max_message_size(limit);
+        self
+    }
+
+    /// Adds data to the end of the buffered input.
+    pub fn push(&mut self, data: impl AsRef<[u8]>) {
+        match self.mode {
+            Mode::Binary => self.frames.push(data),
+            Mode::Text => self.text.extend_from_slice(data.as_ref()),
+        }
+    }
+
+    /// Returns the number of bytes of input which have not yet been decoded.
+    pub fn buffered_len(&self) -> usize {
+        self.frames.buffered_len() + self.text.len()
+    }
+
+    /// Decodes the next frame from the buffered input, or returns `None` if a complete frame has
+    /// not been received yet.
+    ///
+    /// After an error, the decoder is left in an unspecified state and should not be used
+    /// further.
+    pub fn decode_next(&mut self) -> Result<Option<Frame>, FramingError> {
+        self.decode_text()?;
+
+        if self.trailers_received {
+            return match self.buffered_len() {
+                0 => Ok(None),
+                remaining => Err(FramingErrorKind::TrailingData { remaining }.into()),
+            };
+        }
+
+        let (flag, data) = match self.frames.next_frame(true)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        if flag & TRAILERS_FLAG != 0 {
+            self.trailers_received = true;
+            let block = self.frames.decompress(flag, data)?;
+            Ok(Some(Frame::Trailers(Trailers::parse(&block)?)))
+        } else {
+            self.frames
+                .decode_message(flag, data)
+                .map(|m| Some(Frame::Message(m)))
+        }
+    }
+
+    /// Checks that no partial frame remains in the buffered input, for use once the body has
+    /// ended.
+    pub fn finish(&self) -> Result<(), FramingError> {
+        match self.buffered_len() {
+            0 => Ok(()),
+            remaining => Err(FramingErrorKind::Incomplete { remaining }.into()),
+        }
+    }
+
+    /// Decodes all complete groups of four base64 characters from the text input.
+    fn decode_text(&mut self) -> Result<(), FramingError> {
+        let len = self.text.len() - self.text.len() % 4;
+        if len == 0 {
+            return Ok(());
+        }
+
+        // Senders may encode each frame separately, so padding can appear at the end of any
+        // group, and each padded section must be decoded on its own.
+        let text = self.text.split_to(len);
+        let mut rest = &text[..];
+        while !rest.is_empty() {
+            let end = rest
+                .chunks(4)
+                .position(|group| group[3] == b'=')
+                .map_or(rest.len(), |index| (index + 1) * 4);
+            let data = BASE64_STANDARD
+                .decode(&rest[..end])
+                .map_err(FramingErrorKind::InvalidBase64)?;
+            self.frames.push(data);
+            rest = &rest[end..];
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+use std::{error::Error, fmt};
+
+use crate::{DynamicMessage, MessageDescriptor, Value};
+
+const LAT_LNG_NAME: &str = "google.type.LatLng";
+#[cfg(feature = "chrono")]
+const DATE_NAME: &str = "google.type.Date";
+#[cfg(feature = "chrono")]
+const TIME_OF_DAY_NAME: &str = "google.type.TimeOfDay";
+#[cfg(feature = "rust-decimal")]
+const MONEY_NAME: &str = "google.type.Money";
+
+#[cfg(any(feature = "chrono", feature = "rust-decimal"))]
+const NANOS_PER_SECOND: i32 = 1_000_000_000;
+
+/// An error that occurred while converting a `google.type` message to or from a native type.
+#[derive(Debug)]
+pub struct GoogleTypeError {
+    kind: GoogleTypeErrorKind,
+}
+
+#[derive(Debug)]
+enum GoogleTypeErrorKind {
+    MessageType {
+        expected: &'static str,
+        actual: String,
+    },
+    InvalidField {
+        name: &'static str,
+        number: u32,
+    },
+    OutOfRange {
+        name: &'static str,
+    },
+    #[cfg(feature = "chrono")]
+    PartialDate,
+}
+
+/// Converts a `google.type.LatLng` message to a pair of latitude and longitude, in degrees.
+///
+/// An error is returned if the latitude is not in the range `[-90.0, +90.0]`, or the longitude
+/// is not in the range `[-180.0, +180.0]`.
+pub fn to_lat_lng(message: &DynamicMessage) -> Result<(f64, f64), GoogleTypeError> {
+    check_type(message, LAT_LNG_NAME)?;
+    let latitude = get_field(message, LAT_LNG_NAME, 1, Value::as_f64)?;
+    let longitude = get_field(message, LAT_LNG_NAME, 2, Value::as_f64)?;
+    check_lat_lng(latitude, longitude)?;
+    Ok((latitude, longitude))
+}
+
+/// Creates a `google.type.LatLng` message of type `desc` from a pair of latitude and longitude,
+/// in degrees.
+///
+/// An error is returned if the latitude is not in the range `[-90.0, +90.0]`, or the longitude
+/// is not in the range `[-180.0, +180.0]`.
+pub fn from_lat_lng(
+    desc: MessageDescriptor,
+    (latitude, longitude): (f64, f64),
+) -> Result<DynamicMessage, GoogleTypeError> {
+    check_desc(&desc, LAT_LNG_NAME)?;
+    check_lat_lng(latitude, longitude)?;
+    let mut message = DynamicMessage::new(desc);
+    set_field(&mut message, LAT_LNG_NAME, 1, Value::F64(latitude))?;
+    set_field(&mut message, LAT_LNG_NAME, 2, Value::F64(longitude))?;
+    Ok(message)
+}
+
+fn check_lat_lng(latitude: f64, longitude: f64) -> Result<(), GoogleTypeError> {
+    if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) {
+        Ok(())
+    } else {
+        Err(GoogleTypeErrorKind::OutOfRange { name: LAT_LNG_NAME }.into())
+    }
+}
+
+/// Converts a `google.type.Date` message to a [`NaiveDate`][chrono1::NaiveDate].
+///
+/// An error is returned if the date is not a valid calendar date, or if it is a partial date
+/// with a zero year, month or day.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+pub fn to_naive_date(message: &DynamicMessage) -> Result<chrono1::NaiveDate, GoogleTypeError> {
+    check_type(message, DATE_NAME)?;
+    let year = get_field(message, DATE_NAME, 1, Value::as_i32)?;
+    let month = get_field(message, DATE_NAME, 2, Value::as_i32)?;
+    let day = get_field(message, DATE_NAME, 3, Value::as_i32)?;
+    if year == 0 || month == 0 || day == 0 {
+        return Err(GoogleTypeErrorKind::PartialDate.into());
+    }
+
+    if !(1..=9999).contains(&year) {
+        return Err(GoogleTypeErrorKind::OutOfRange { name: DATE_NAME }.into());
+    }
+    u32::try_from(month)
+        .ok()
+        .zip(u32::try_from(day).ok())
+        .and_then(|(month, day)| chrono1::NaiveDate::from_ymd_opt(year, month, day))
+        .ok_or_else(|| GoogleTypeErrorKind::OutOfRange { name: DATE_NAME }.into())
+}
+
+/// Creates a `google.type.Date` message of type `desc` from a [`NaiveDate`][chrono1::NaiveDate].
+///
+/// An error is returned if the year is not between 1 and 9999.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+pub fn from_naive_date(
+    desc: MessageDescriptor,
+    date: chrono1::NaiveDate,
+) -> Result<DynamicMessage, GoogleTypeError> {
+    use chrono1::Datelike;
+
+    check_desc(&desc, DATE_NAME)?;
+    if !(1..=9999).contains(&date.year()) {
+        return Err(GoogleTypeErrorKind::OutOfRange { name: DATE_NAME }.into());
+    }
+    let mut message = DynamicMessage::new(desc);
+    set_field(&mut message, DATE_NAME, 1, Value::I32(date.year()))?;
+    set_field(&mut message, DATE_NAME, 2, Value::I32(date.month() as i32))?;
+    set_field(&mut message, DATE_NAME, 3, Value::I32(date.day() as i32))?;
+    Ok(message)
+}
+
+/// Converts a `google.type.TimeOfDay` message to a [`NaiveTime`][chrono1::NaiveTime].
+///
+/// A value of 60 for the seconds is converted to a leap second. An error is returned if any
+/// field is out of range, including the value 24 for the hours, which some APIs allow to
+/// represent the end of a day.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+pub fn to_naive_time(message: &DynamicMessage) -> Result<chrono1::NaiveTime, GoogleTypeError> {
+    check_type(message, TIME_OF_DAY_NAME)?;
+    let hours = get_field(message, TIME_OF_DAY_NAME, 1, Value::as_i32)?;
+    let minutes = get_field(message, TIME_OF_DAY_NAME, 2, Value::as_i32)?;
+    let seconds = get_field(message, TIME_OF_DAY_NAME, 3, Value::as_i32)?;
+    let nanos = get_field(message, TIME_OF_DAY_NAME, 4, Value::as_i32)?;
+
+    let out_of_range = || {
+        GoogleTypeError::from(GoogleTypeErrorKind::OutOfRange {
+            name: TIME_OF_DAY_NAME,
+        })
+    };
+    if !(0..NANOS_PER_SECOND).contains(&nanos) || !(0..=60).contains(&seconds) {
+        return Err(out_of_range());
+    }
+    // Leap seconds are represented in chrono by a nanosecond value of one second or more.
+    let (seconds, nanos) = if seconds == 60 {
+        (59, nanos + NANOS_PER_SECOND)
+    } else {
+        (seconds, nanos)
+    };
+    u32::try_from(hours)
+        .ok()
+        .zip(u32::try_from(minutes).ok())
+        .and_then(|(hours, minutes)| {
+            chrono1::NaiveTime::from_hms_nano_opt(hours, minutes, seconds as u32, nanos as u32)
+        })
+        .ok_or_else(out_of_range)
+}
+
+/// Creates a `google.type.TimeOfDay` message of type `desc` from a
+/// [`NaiveTime`][chrono1::NaiveTime].
+///
+/// A leap second is converted to a value of 60 for the seconds.
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+pub fn from_naive_time(
+    desc: MessageDescriptor,
+    time: chrono1::NaiveTime,
+) -> Result<DynamicMessage, GoogleTypeError> {
+    use chrono1::Timelike;
+
+    check_desc(&desc, TIME_OF_DAY_NAME)?;
+    let (seconds, nanos) = if time.nanosecond() >= NANOS_PER_SECOND as u32 {
+        (60, time.nanosecond() - NANOS_PER_SECOND as u32)
+    } else {
+        (time.second(), time.nanosecond())
+    };
+    let mut message = DynamicMessage::new(desc);
+    set_field(
+        &mut message,
+        TIME_OF_DAY_NAME,
+        1,
+        Value::I32(time.hour() as i32),
+    )?;
+    set_field(
+        &mut message,
+        TIME_OF_DAY_NAME,
+        2,
+        Value::I32(time.minute() as i32),
+    )?;
+    set_field(
+        &mut message,
+        TIME_OF_DAY_NAME,
+        3,
+        Value::I32(seconds as i32),
+    )?;
+    set_field(&mut message, TIME_OF_DAY_NAME, 4, Value::I32(nanos as i32))?;
+    Ok(message)
+}
+
+/// Converts a `google.type.Money` message to its currency code and a
+/// [`Decimal`][rust_decimal::Decimal] amount.
+///
+/// An error is returned if the nanos are out of range, or have a different sign to the units.
+#[cfg(feature = "rust-decimal")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rust-decimal")))]
+pub fn to_decimal(
+    message: &DynamicMessage,
+) -> Result<(String, rust_decimal::Decimal), GoogleTypeError> {
+    check_type(message, MONEY_NAME)?;
+    let currency_code = get_field(message, MONEY_NAME, 1, |value| {
+        value.as_str().map(ToOwned::to_owned)
+    })?;
+    let units = get_field(message, MONEY_NAME, 2, Value::as_i64)?;
+    let nanos = get_field(message, MONEY_NAME, 3, Value::as_i32)?;
+    check_money(units, nanos)?;
+
+    let amount = rust_decimal::Decimal::from(units) + rust_decimal::Decimal::new(nanos.into(), 9);
+    Ok((currency_code, amount.normalize()))
+}
+
+/// Creates a `google.type.Money` message of type `desc` from a currency code and a
+/// [`Decimal`][rust_decimal::Decimal] amount.
+///
+/// An error is returned if the amount has more than nine decimal places, or its whole units do
+/// not fit in an `int64`.
+#[cfg(feature = "rust-decimal")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rust-decimal")))]
+pub fn from_decimal(
+    desc: MessageDescriptor,
+    currency_code: &str,
+    amount: rust_decimal::Decimal,
+) -> Result<DynamicMessage, GoogleTypeError> {
+    use rust_decimal::prelude::ToPrimitive;
+
+    check_desc(&desc, MONEY_NAME)?;
+    let out_of_range =
+        || GoogleTypeError::from(GoogleTypeErrorKind::OutOfRange { name: MONEY_NAME });
+    let units = amount.trunc().to_i64().ok_or_else(out_of_range)?;
+    let nanos = amount.fract() * rust_decimal::Decimal::from(NANOS_PER_SECOND);
+    if !nanos.fract().is_zero() {
+        return Err(out_of_range());
+    }
+    let nanos = nanos.to_i32().ok_or_else(out_of_range)?;
+
+    let mut message = DynamicMessage::new(desc);
+    set_field(
+        &mut message,
+        MONEY_NAME,
+        1,
+        Value::String(currency_code.to_owned()),
+    )?;
+    set_field(&mut message, MONEY_NAME, 2, Value::I64(units))?;
+    set_field(&mut message, MONEY_NAME, 3, Value::I32(nanos))?;
+    Ok(message)
+}
+
+#[cfg(feature = "rust-decimal")]
+fn check_money(units: i64, nanos: i32) -> Result<(), GoogleTypeError> {
+    if (-NANOS_PER_SECOND + 1..NANOS_PER_SECOND).contains(&nanos)
+        && (units == 0 || nanos == 0 || (units < 0) == (nanos < 0))
+    {
+        Ok(())
+    } else {
+        Err(GoogleTypeErrorKind::OutOfRange { name: MONEY_NAME }.into())
+    }
+}
+
+fn check_type(message: &DynamicMessage, name: &'static str) -> Result<(), GoogleTypeError> {
+    check_desc(&message.desc, name)
+}
+
+fn check_desc(desc: &MessageDescriptor, name: &'static str) -> Result<(), GoogleTypeError> {
+    if desc.full_name() == name {
+        Ok(())
+    } else {
+        Err(GoogleTypeErrorKind::MessageType {
+            expected: name,
+            actual: desc.full_name().to_owned(),
+        }
+        .into())
+    }
+}
+
+fn get_field<T>(
+    message: &DynamicMessage,
+    name: &'static str,
+    number: u32,
+    get: impl FnOnce(&Value) -> Option<T>,
+) -> Result<T, GoogleTypeError> {
+    message
+        .get_field_by_number(number)
+        .and_then(|value| get(&value))
+        .ok_or_else(|| GoogleTypeErrorKind::InvalidField { name, number }.into())
+}
+
+fn set_field(
+    message: &mut DynamicMessage,
+    name: &'static str,
+    number: u32,
+    value: Value,
+) -> Result<(), GoogleTypeError> {
+    message
+        .try_set_field_by_number(number, value)
+        .map_err(|_| GoogleTypeErrorKind::InvalidField { name, number }.into())
+}
+
+impl From<GoogleTypeErrorKind> for GoogleTypeError {
+    fn from(kind: GoogleTypeErrorKind) -> Self {
+        GoogleTypeError { kind }
+    }
+}
+
+impl fmt::Display for GoogleTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            GoogleTypeErrorKind::MessageType { expected, actual } => write!(
+                f,
+                "expected a message of type '{}', but found '{}'",
+                expected, actual
+            ),
+            GoogleTypeErrorKind::InvalidField { name, number } => write!(
+                f,
+                "field {} of message '{}' is missing or has an invalid type",
+                number, name
+            ),
+            GoogleTypeErrorKind::OutOfRange { name } => {
+                write!(f, "value is out of range for message '{}'", name)
+            }
+            #[cfg(feature = "chrono")]
+            GoogleTypeErrorKind::PartialDate => {
+                write!(f, "cannot convert a date with a zero year, month or day")
+            }
+        }
+    }
+}
+
+impl Error for GoogleTypeError {}
@@ -0,0 +1,271 @@
+mod server;
+
+use prost::{bytes::Bytes, Message};
+use tonic1::{
+    body::BoxBody,
+    client::{Grpc, GrpcService},
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::{futures_core::Stream, http::uri::PathAndQuery, Body, StdError},
+    Code, Request, Response, Status, Streaming,
+};
+
+pub use self::server::{DynamicService, DynamicStream};
+use crate::{DynamicMessage, MessageDescriptor, MethodDescriptor};
+
+/// A [`Codec`] which encodes and decodes [`DynamicMessage`]s.
+///
+/// Outgoing messages are encoded as they are, and incoming messages are decoded as the message
+/// type given when creating the codec. Decoding errors are mapped to a status with the code
+/// [`Internal`][Code::Internal], as with tonic's own codec.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct DynamicCodec {
+    desc: MessageDescriptor,
+}
+
+/// The [`Encoder`] of a [`DynamicCodec`].
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct DynamicEncoder {
+    _private: (),
+}
+
+/// The [`Decoder`] of a [`DynamicCodec`], which decodes messages of a single type.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct DynamicDecoder {
+    desc: MessageDescriptor,
+}
+
+/// A gRPC client which invokes methods described by a [`MethodDescriptor`], using
+/// [`DynamicMessage`]s for the requests and responses.
+///
+/// The client can be created from any tonic transport, such as a `tonic::transport::Channel`.
+/// Requests are sent to the path `/<service>/<method>`, where `<service>` is the full name of the
+/// method's service, and responses are decoded as the method's output type. Request metadata is
+/// sent and response metadata is returned through tonic's [`Request`] and [`Response`] types.
+///
+/// The method used to invoke a method should match its streaming type, for example
+/// [`server_streaming`][Self::server_streaming] for a method which returns a stream of messages.
+/// For methods which take a single request, an error with the code
+/// [`InvalidArgument`][Code::InvalidArgument] is returned without contacting the server if the
+/// request message is not of the method's input type. Streamed request messages are not checked.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::{DescriptorPool, DynamicMessage};
+/// use prost_reflect::{grpc::DynamicClient, tonic};
+///
+/// # async fn example<T>(channel: T, pool: DescriptorPool) -> Result<(), tonic::Status>
+/// # where
+/// #     T: tonic::client::GrpcService<tonic::body::BoxBody>,
+/// #     T::Error: Into<tonic::codegen::StdError>,
+/// #     T::ResponseBody: tonic::codegen::Body<Data = prost_reflect::bytes::Bytes> + Send + 'static,
+/// #     <T::ResponseBody as tonic::codegen::Body>::Error: Into<tonic::codegen::StdError> + Send,
+/// # {
+/// let method = pool
+///     .get_service_by_name("package.MyService")
+///     .unwrap()
+///     .methods()
+///     .find(|method| method.name() == "MyMethod")
+///     .unwrap();
+///
+/// let mut client = DynamicClient::new(channel);
+/// let request = DynamicMessage::new(method.input());
+/// let response = client.unary(&method, tonic::Request::new(request)).await?;
+/// println!("{:?}", response.into_inner());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct DynamicClient<T> {
+    inner: Grpc<T>,
+}
+
+impl DynamicCodec {
+    /// Creates a codec which decodes incoming messages as messages of type `desc`.
+    ///
+    /// For a client, this is the output type of the method being called. For a server, it is the
+    /// input type of the method.
+    pub fn new(desc: MessageDescriptor) -> Self {
+        DynamicCodec { desc }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder::default()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder {
+            desc: self.desc.clone(),
+        }
+    }
+}
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .expect("encode buffers have unlimited capacity");
+        Ok(())
+    }
+}
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        DynamicMessage::decode(self.desc.clone(), src)
+            .map(Some)
+            .map_err(|err| Status::new(Code::Internal, err.to_string()))
+    }
+}
+
+impl<T> DynamicClient<T> {
+    /// Creates a client which sends requests using the given transport.
+    pub fn new(inner: T) -> Self {
+        DynamicClient {
+            inner: Grpc::new(inner),
+        }
+    }
+
+    /// Limits the size of response messages accepted by the client.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_decoding_message_size(limit);
+        self
+    }
+
+    /// Limits the size of request messages sent by the client.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.inner = self.inner.max_encoding_message_size(limit);
+        self
+    }
+}
+
+// Errors are returned as a `Status`, for consistency with tonic's generated clients.
+#[allow(clippy::result_large_err)]
+impl<T> DynamicClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Invokes a method which takes a single request message and returns a single response.
+    pub async fn unary(
+        &mut self,
+        method: &MethodDescriptor,
+        request: Request<DynamicMessage>,
+    ) -> Result<Response<DynamicMessage>, Status> {
+        check_input(method, request.get_ref())?;
+        self.ready().await?;
+        self.inner
+            .unary(
+                request,
+                method_path(method),
+                DynamicCodec::new(method.output()),
+            )
+            .await
+    }
+
+    /// Invokes a method which takes a single request message and returns a stream of responses.
+    pub async fn server_streaming(
+        &mut self,
+        method: &MethodDescriptor,
+        request: Request<DynamicMessage>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status> {
+        check_input(method, request.get_ref())?;
+        self.ready().await?;
+        self.inner
+            .server_streaming(
+                request,
+                method_path(method),
+                DynamicCodec::new(method.output()),
+            )
+            .await
+    }
+
+    /// Invokes a method which takes a stream of request messages and returns a single response.
+    pub async fn client_streaming<S>(
+        &mut self,
+        method: &MethodDescriptor,
+        request: Request<S>,
+    ) -> Result<Response<DynamicMessage>, Status>
+    where
+        S: Stream<Item = DynamicMessage> + Send + 'static,
+    {
+        self.ready().await?;
+        self.inner
+            .client_streaming(
+                request,
+                method_path(method),
+                DynamicCodec::new(method.output()),
+            )
+            .await
+    }
+
+    /// Invokes a method which takes a stream of request messages and returns a stream of
+    /// responses.
+    pub async fn streaming<S>(
+        &mut self,
+        method: &MethodDescriptor,
+        request: Request<S>,
+    ) -> Result<Response<Streaming<DynamicMessage>>, Status>
+    where
+        S: Stream<Item = DynamicMessage> + Send + 'static,
+    {
+        self.ready().await?;
+        self.inner
+            .streaming(
+                request,
+                method_path(method),
+                DynamicCodec::new(method.output()),
+            )
+            .await
+    }
+
+    async fn ready(&mut self) -> Result<(), Status> {
+        self.inner.ready().await.map_err(|err| {
+            Status::new(
+                Code::Unknown,
+                format!("service was not ready: {}", err.into()),
+            )
+        })
+    }
+}
+
+fn method_path(method: &MethodDescriptor) -> PathAndQuery {
+    format!("/{}/{}", method.parent_service().full_name(), method.name())
+        .parse()
+        .expect("method names should be valid paths")
+}
+
+#[allow(clippy::result_large_err)]
+fn check_input(method: &MethodDescriptor, message: &DynamicMessage) -> Result<(), Status> {
+    if message.desc == method.input() {
+        Ok(())
+    } else {
+        Err(Status::new(
+            Code::InvalidArgument,
+            format!(
+                "expected a request of type '{}' for method '{}', but found '{}'",
+                method.input().full_name(),
+                method.full_name(),
+                message.desc.full_name()
+            ),
+        ))
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tonic1::{
+    body::BoxBody,
+    codegen::{empty_body, futures_core::Stream, http, Body, BoxFuture, Service, StdError},
+    server::{
+        ClientStreamingService, Grpc, ServerStreamingService, StreamingService, UnaryService,
+    },
+    Code, Request, Response, Status, Streaming,
+};
+
+use super::DynamicCodec;
+use crate::{DynamicMessage, MethodDescriptor, ServiceDescriptor};
+
+/// A boxed stream of response messages, returned by the handlers of streaming methods registered
+/// with a [`DynamicService`].
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub type DynamicStream =
+    Pin<Box<dyn Stream<Item = Result<DynamicMessage, Status>> + Send + 'static>>;
+
+/// A tonic service which dispatches the methods of a [`ServiceDescriptor`] to handlers using
+/// [`DynamicMessage`]s, without generated code.
+///
+/// A handler is registered for each method using the function matching its streaming type, such
+/// as [`unary`][Self::unary] or [`server_streaming`][Self::server_streaming]. Request messages are
+/// decoded as the method's input type, and responses which are not of the method's output type
+/// are replaced with an error with the code [`Internal`][Code::Internal]. Requests for methods
+/// without a registered handler, or which are not part of the service, are rejected with the code
+/// [`Unimplemented`][Code::Unimplemented].
+///
+/// The name of the service is only known at runtime, so this type does not implement tonic's
+/// `NamedService` trait. It can be served by routing requests with the path prefix
+/// `/<service>/` to it, where `<service>` is the full name of the service.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::{DescriptorPool, DynamicMessage};
+/// use prost_reflect::{grpc::DynamicService, tonic};
+///
+/// # fn example(pool: DescriptorPool) {
+/// let service_desc = pool.get_service_by_name("package.MyService").unwrap();
+/// let method = service_desc
+///     .methods()
+///     .find(|method| method.name() == "MyMethod")
+///     .unwrap();
+///
+/// let output = method.output();
+/// let service = DynamicService::new(service_desc).unary(&method, move |request| {
+///     let output = output.clone();
+///     async move {
+///         println!("received {:?}", request.get_ref());
+///         Ok(tonic::Response::new(DynamicMessage::new(output)))
+///     }
+/// });
+/// # }
+/// ```
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct DynamicService {
+    service: ServiceDescriptor,
+    handlers: HashMap<String, Handler>,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+}
+
+type UnaryHandler = Arc<
+    dyn Fn(Request<DynamicMessage>) -> BoxFuture<Response<DynamicMessage>, Status>
+        + Send
+        + Sync
+        + 'static,
+>;
+type ServerStreamingHandler = Arc<
+    dyn Fn(Request<DynamicMessage>) -> BoxFuture<Response<DynamicStream>, Status>
+        + Send
+        + Sync
+        + 'static,
+>;
+type ClientStreamingHandler = Arc<
+    dyn Fn(Request<Streaming<DynamicMessage>>) -> BoxFuture<Response<DynamicMessage>, Status>
+        + Send
+        + Sync
+        + 'static,
+>;
+type StreamingHandler = Arc<
+    dyn Fn(Request<Streaming<DynamicMessage>>) -> BoxFuture<Response<DynamicStream>, Status>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+#[derive(Clone)]
+enum Handler {
+    Unary(UnaryHandler),
+    ServerStreaming(ServerStreamingHandler),
+    ClientStreaming(ClientStreamingHandler),
+    Streaming(StreamingHandler),
+}
+
+struct UnaryMethod {
+    method: MethodDescriptor,
+    handler: UnaryHandler,
+}
+
+struct ServerStreamingMethod {
+    method: MethodDescriptor,
+    handler: ServerStreamingHandler,
+}
+
+struct ClientStreamingMethod {
+    method: MethodDescriptor,
+    handler: ClientStreamingHandler,
+}
+
+struct StreamingMethod {
+    method: MethodDescriptor,
+    handler: StreamingHandler,
+}
+
+/// Checks that each message in a response stream is of the method's output type.
+struct CheckedStream {
+    method: MethodDescriptor,
+    inner: DynamicStream,
+}
+
+impl DynamicService {
+    /// Creates a service for the methods of `service`, with no handlers registered.
+    pub fn new(service: ServiceDescriptor) -> Self {
+        DynamicService {
+            service,
+            handlers: HashMap::new(),
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+        }
+    }
+
+    /// Gets the descriptor of the service.
+    pub fn service(&self) -> &ServiceDescriptor {
+        &self.service
+    }
+
+    /// Registers the handler for a method which takes a single request message and returns a
+    /// single response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is not part of this service, or is a streaming method.
+    pub fn unary<F, Fut>(mut self, method: &MethodDescriptor, handler: F) -> Self
+    where
+        F: Fn(Request<DynamicMessage>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<DynamicMessage>, Status>> + Send + 'static,
+    {
+        self.check_method(method, false, false);
+        self.handlers.insert(
+            method.name().to_owned(),
+            Handler::Unary(Arc::new(move |request| Box::pin(handler(request)))),
+        );
+        self
+    }
+
+    /// Registers the handler for a method which takes a single request message and returns a
+    /// stream of responses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is not part of this service, or does not have a streaming response
+    /// and a single request.
+    pub fn server_streaming<F, Fut, S>(mut self, method: &MethodDescriptor, handler: F) -> Self
+    where
+        F: Fn(Request<DynamicMessage>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<S>, Status>> + Send + 'static,
+        S: Stream<Item = Result<DynamicMessage, Status>> + Send + 'static,
+    {
+        self.check_method(method, false, true);
+        self.handlers.insert(
+            method.name().to_owned(),
+            Handler::ServerStreaming(Arc::new(move |request| {
+                let future = handler(request);
+                Box::pin(async move { Ok(future.await?.map(|stream| Box::pin(stream) as _)) })
+            })),
+        );
+        self
+    }
+
+    /// Registers the handler for a method which takes a stream of request messages and returns a
+    /// single response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is not part of this service, or does not have a streaming request
+    /// and a single response.
+    pub fn client_streaming<F, Fut>(mut self, method: &MethodDescriptor, handler: F) -> Self
+    where
+        F: Fn(Request<Streaming<DynamicMessage>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<DynamicMessage>, Status>> + Send + 'static,
+    {
+        self.check_method(method, true, false);
+        self.handlers.insert(
+            method.name().to_owned(),
+            Handler::ClientStreaming(Arc::new(move |request| Box::pin(handler(request)))),
+        );
+        self
+    }
+
+    /// Registers the handler for a method which takes a stream of request messages and returns a
+    /// stream of responses.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the method is not part of this service, or is not a bidirectional streaming
+    /// method.
+    pub fn streaming<F, Fut, S>(mut self, method: &MethodDescriptor, handler: F) -> Self
+    where
+        F: Fn(Request<Streaming<DynamicMessage>>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Response<S>, Status>> + Send + 'static,
+        S: Stream<Item = Result<DynamicMessage, Status>> + Send + 'static,
+    {
+        self.check_method(method, true, true);
+        self.handlers.insert(
+            method.name().to_owned(),
+            Handler::Streaming(Arc::new(move |request| {
+                let future = handler(request);
+                Box::pin(async move { Ok(future.await?.map(|stream| Box::pin(stream) as _)) })
+            })),
+        );
+        self
+    }
+
+    /// Limits the size of request messages accepted by the service.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the size of response messages sent by the service.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    fn check_method(
+        &self,
+        method: &MethodDescriptor,
+        client_streaming: bool,
+        server_streaming: bool,
+    ) {
+        assert_eq!(
+            method.parent_service(),
+            &self.service,
+            "method '{}' is not part of service '{}'",
+            method.full_name(),
+            self.service.full_name()
+        );
+        assert!(
+            method.is_client_streaming() == client_streaming
+                && method.is_server_streaming() == server_streaming,
+            "the handler for method '{}' does not match its streaming type",
+            method.full_name()
+        );
+    }
+}
+
+impl fmt::Debug for DynamicService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut methods: Vec<_> = self.handlers.keys().collect();
+        methods.sort();
+        f.debug_struct("DynamicService")
+            .field("service", &self.service.full_name())
+            .field("methods", &methods)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B> Service<http::Request<B>> for DynamicService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let method = req
+            .uri()
+            .path()
+            .strip_prefix('/')
+            .and_then(|path| path.strip_prefix(self.service.full_name()))
+            .and_then(|path| path.strip_prefix('/'))
+            .and_then(|name| {
+                let handler = self.handlers.get(name)?;
+                let method = self
+                    .service
+                    .methods()
+                    .find(|method| method.name() == name)?;
+                Some((method, handler.clone()))
+            });
+        let (method, handler) = match method {
+            Some(method) => method,
+            None => {
+                return Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", (Code::Unimplemented as i32).to_string())
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                })
+            }
+        };
+
+        let mut grpc = Grpc::new(DynamicCodec::new(method.input())).apply_max_message_size_config(
+            self.max_decoding_message_size,
+            self.max_encoding_message_size,
+        );
+        Box::pin(async move {
+            Ok(match handler {
+                Handler::Unary(handler) => grpc.unary(UnaryMethod { method, handler }, req).await,
+                Handler::ServerStreaming(handler) => {
+                    grpc.server_streaming(ServerStreamingMethod { method, handler }, req)
+                        .await
+                }
+                Handler::ClientStreaming(handler) => {
+                    grpc.client_streaming(ClientStreamingMethod { method, handler }, req)
+                        .await
+                }
+                Handler::Streaming(handler) => {
+                    grpc.streaming(StreamingMethod { method, handler }, req)
+                        .await
+                }
+            })
+        })
+    }
+}
+
+impl UnaryService<DynamicMessage> for UnaryMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let method = self.method.clone();
+        let future = (self.handler)(request);
+        Box::pin(async move {
+            let response = future.await?;
+            check_output(&method, response.get_ref())?;
+            Ok(response)
+        })
+    }
+}
+
+impl ServerStreamingService<DynamicMessage> for ServerStreamingMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = CheckedStream;
+    type Future = BoxFuture<Response<CheckedStream>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let method = self.method.clone();
+        let future = (self.handler)(request);
+        Box::pin(async move { Ok(future.await?.map(|inner| CheckedStream { method, inner })) })
+    }
+}
+
+impl ClientStreamingService<DynamicMessage> for ClientStreamingMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.method.clone();
+        let future = (self.handler)(request);
+        Box::pin(async move {
+            let response = future.await?;
+            check_output(&method, response.get_ref())?;
+            Ok(response)
+        })
+    }
+}
+
+impl StreamingService<DynamicMessage> for StreamingMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = CheckedStream;
+    type Future = BoxFuture<Response<CheckedStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.method.clone();
+        let future = (self.handler)(request);
+        Box::pin(async move { Ok(future.await?.map(|inner| CheckedStream { method, inner })) })
+    }
+}
+
+impl Stream for CheckedStream {
+    type Item = Result<DynamicMessage, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.inner.as_mut().poll_next(cx) {
+            Poll::Ready(Some(Ok(message))) => {
+                Poll::Ready(Some(check_output(&self.method, &message).map(|()| message)))
+            }
+            Poll::Ready(Some(Err(status))) => Poll::Ready(Some(Err(status))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn check_output(method: &MethodDescriptor, message: &DynamicMessage) -> Result<(), Status> {
+    if message.desc == method.output() {
+        Ok(())
+    } else {
+        Err(Status::new(
+            Code::Internal,
+            format!(
+                "expected a response of type '{}' for method '{}', but found '{}'",
+                method.output().full_name(),
+                method.full_name(),
+                message.desc.full_name()
+            ),
+        ))
+    }
+}
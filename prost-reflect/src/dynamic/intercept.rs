@@ -0,0 +1,117 @@
+use std::error::Error;
+
+use prost::Message;
+
+use crate::DynamicMessage;
+
+/// A hook which can inspect or modify a message immediately before it is encoded.
+///
+/// Interceptors can be registered for all messages in a pool with
+/// [`DescriptorPool::add_encode_interceptor`][crate::DescriptorPool::add_encode_interceptor], or
+/// passed to a single call of [`DynamicMessage::encode_to_vec_with_interceptor`]. They can be used
+/// to keep cross-cutting concerns such as validation or stamping trace identifiers into a
+/// well-known field out of the code constructing the message.
+///
+/// Interceptors are invoked for the message being encoded and for each message nested in it,
+/// including list elements and map values. A message is passed to the interceptors before the
+/// messages nested in it, so those include any nested messages added by the interceptors.
+/// Interceptors which only apply to some message types should check
+/// [`descriptor()`][crate::ReflectMessage::descriptor] first.
+///
+/// Interceptors are not run by [`Message::encode`] or [`Message::encode_to_vec`].
+///
+/// This trait is implemented for any function or closure with the signature
+/// `Fn(&mut DynamicMessage) -> Result<(), E>`, where `E` can be converted into a boxed error.
+pub trait EncodeInterceptor {
+    /// Inspects or modifies `message` before it is encoded.
+    ///
+    /// If this returns an error, encoding is aborted and the error is returned to the caller.
+    fn before_encode(
+        &self,
+        message: &mut DynamicMessage,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+impl<F, E> EncodeInterceptor for F
+where
+    F: Fn(&mut DynamicMessage) -> Result<(), E>,
+    E: Into<Box<dyn Error + Send + Sync>>,
+{
+    fn before_encode(
+        &self,
+        message: &mut DynamicMessage,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self(message).map_err(Into::into)
+    }
+}
+
+impl DynamicMessage {
+    /// Encodes this message into a newly allocated buffer, after passing it to the interceptors
+    /// registered on its pool with
+    /// [`DescriptorPool::add_encode_interceptor`][crate::DescriptorPool::add_encode_interceptor].
+    ///
+    /// The interceptors are invoked for this message and each message nested in it, as described
+    /// in [`EncodeInterceptor`]. They modify the messages in place, so any changes they make are
+    /// kept after encoding. Clone the message first to discard them: clones share their fields
+    /// until modified, so only the fields changed by the interceptors are copied.
+    pub fn encode_to_vec_intercepted(&mut self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        self.intercept::<dyn EncodeInterceptor>(None)?;
+        Ok(self.encode_to_vec())
+    }
+
+    /// Encodes this message into a newly allocated buffer, after passing it to the interceptors
+    /// registered on its pool and then to `interceptor`.
+    ///
+    /// See [`encode_to_vec_intercepted`][DynamicMessage::encode_to_vec_intercepted] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// let reject = |_: &mut DynamicMessage| Err("rejected");
+    /// let err = dynamic_message.encode_to_vec_with_interceptor(&reject).unwrap_err();
+    /// assert_eq!(err.to_string(), "rejected");
+    ///
+    /// let stamp = |message: &mut DynamicMessage| message.try_set_field_by_name("foo", Value::I32(150));
+    /// assert_eq!(dynamic_message.encode_to_vec_with_interceptor(&stamp).unwrap(), b"\x08\x96\x01");
+    /// assert_eq!(dynamic_message.encode_to_vec(), b"\x08\x96\x01");
+    /// ```
+    pub fn encode_to_vec_with_interceptor<I>(
+        &mut self,
+        interceptor: &I,
+    ) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>>
+    where
+        I: EncodeInterceptor + ?Sized,
+    {
+        self.intercept(Some(interceptor))?;
+        Ok(self.encode_to_vec())
+    }
+
+    /// Passes this message and each message nested in it to the interceptors registered on the
+    /// pool, then to `interceptor`.
+    fn intercept<I>(&mut self, interceptor: Option<&I>) -> Result<(), Box<dyn Error + Send + Sync>>
+    where
+        I: EncodeInterceptor + ?Sized,
+    {
+        let pool = self.desc.parent_pool().clone();
+        let mut stack = vec![self];
+        while let Some(message) = stack.pop() {
+            for pool_interceptor in pool.encode_interceptors() {
+                pool_interceptor.before_encode(message)?;
+            }
+            if let Some(interceptor) = interceptor {
+                interceptor.before_encode(message)?;
+            }
+
+            // Visit nested messages in field order.
+            let start = stack.len();
+            message.fields.push_nested_mut(&mut stack);
+            stack[start..].reverse();
+        }
+        Ok(())
+    }
+}
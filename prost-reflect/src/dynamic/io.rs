@@ -0,0 +1,270 @@
+use std::io::{self, Read, Write};
+
+use prost::{
+    encoding::{encode_varint, encoded_len_varint},
+    DecodeError, Message,
+};
+
+use crate::{DynamicMessage, MessageDescriptor};
+
+/// The largest buffer allocated up front for a length-delimited message, so that a corrupt
+/// length prefix cannot cause a large allocation before any of the message has been read.
+const MAX_PREALLOCATE: usize = 64 * 1024;
+
+/// How the length of each message in a stream is written before it.
+///
+/// Used by [`DynamicMessage::decode_length_delimited_from_reader`] and
+/// [`DynamicMessage::encode_length_delimited_to_writer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SizePrefix {
+    /// The length is written as a varint. This is the format used by
+    /// [`Message::encode_length_delimited`], and by `writeDelimitedTo` in the Java protobuf
+    /// library.
+    Varint,
+    /// The length is written as a 4-byte big-endian integer.
+    Fixed32,
+}
+
+impl DynamicMessage {
+    /// Decodes an instance of the message type specified by the [`MessageDescriptor`] from all
+    /// the bytes remaining in `reader`.
+    ///
+    /// The input is read directly into a buffer which is decoded once the end of the stream is
+    /// reached, so the whole encoded message is held in memory. As for
+    /// [`decode`][Message::decode], any options set with
+    /// [`DescriptorPool::set_decode_options`][crate::DescriptorPool::set_decode_options] are
+    /// applied. If the message cannot be decoded, an error of kind
+    /// [`InvalidData`][io::ErrorKind::InvalidData] is returned, wrapping the [`DecodeError`].
+    ///
+    /// If a limit is set with [`DecodeOptions::max_message_bytes`][crate::DecodeOptions::max_message_bytes],
+    /// at most one byte more than the limit is read, so a longer stream is rejected without
+    /// buffering all of it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let reader = std::io::Cursor::new(b"\x08\x96\x01");
+    /// let message = DynamicMessage::decode_from_reader(message_descriptor, reader).unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    pub fn decode_from_reader<R>(desc: MessageDescriptor, mut reader: R) -> io::Result<Self>
+    where
+        R: Read,
+    {
+        let mut buf = Vec::new();
+        match message_bytes_limit(&desc) {
+            Some(limit) => {
+                reader.take(limit as u64 + 1).read_to_end(&mut buf)?;
+                if buf.len() > limit {
+                    return Err(invalid_data(DecodeError::new(format!(
+                        "message exceeds the limit of {} bytes",
+                        limit
+                    ))));
+                }
+            }
+            None => {
+                reader.read_to_end(&mut buf)?;
+            }
+        }
+        DynamicMessage::decode(desc, buf.as_slice()).map_err(invalid_data)
+    }
+
+    /// Decodes a single length-delimited instance of the message type specified by the
+    /// [`MessageDescriptor`] from `reader`, returning `None` if the reader is already at the end
+    /// of the stream.
+    ///
+    /// Only the bytes of the length prefix and the message are read, so this can be called
+    /// repeatedly to read a stream of messages. Since the length prefix is read one byte at a time,
+    /// unbuffered readers such as files and sockets should be wrapped in a
+    /// [`BufReader`][io::BufReader].
+    ///
+    /// If a limit is set with [`DecodeOptions::max_message_bytes`][crate::DecodeOptions::max_message_bytes],
+    /// it is checked against the length prefix, so an oversized message is rejected with an error
+    /// of kind [`InvalidData`][io::ErrorKind::InvalidData] before any of it is read.
+    ///
+    /// If the stream ends partway through a message, an error of kind
+    /// [`UnexpectedEof`][io::ErrorKind::UnexpectedEof] is returned. If the message cannot be
+    /// decoded, an error of kind [`InvalidData`][io::ErrorKind::InvalidData] is returned,
+    /// wrapping the [`DecodeError`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SizePrefix, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut reader = std::io::Cursor::new(b"\x03\x08\x96\x01\x02\x08\x01");
+    /// let message = DynamicMessage::decode_length_delimited_from_reader(message_descriptor.clone(), &mut reader, SizePrefix::Varint).unwrap().unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// let message = DynamicMessage::decode_length_delimited_from_reader(message_descriptor.clone(), &mut reader, SizePrefix::Varint).unwrap().unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(1));
+    /// assert!(DynamicMessage::decode_length_delimited_from_reader(message_descriptor, &mut reader, SizePrefix::Varint).unwrap().is_none());
+    /// ```
+    pub fn decode_length_delimited_from_reader<R>(
+        desc: MessageDescriptor,
+        mut reader: R,
+        prefix: SizePrefix,
+    ) -> io::Result<Option<Self>>
+    where
+        R: Read,
+    {
+        let len = match read_size_prefix(&mut reader, prefix)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+
+        if let Some(options) = desc.parent_pool().decode_options() {
+            options.check_message_len(len).map_err(invalid_data)?;
+        }
+
+        let mut buf = Vec::with_capacity(len.min(MAX_PREALLOCATE as u64) as usize);
+        reader.take(len).read_to_end(&mut buf)?;
+        if (buf.len() as u64) < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        DynamicMessage::decode(desc, buf.as_slice())
+            .map(Some)
+            .map_err(invalid_data)
+    }
+
+    /// Encodes this message to `writer`.
+    ///
+    /// The message is encoded into a buffer of exactly the required size, which is written with a
+    /// single call to [`write_all`][Write::write_all], so the writer does not need to be
+    /// buffered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut message = DynamicMessage::new(message_descriptor);
+    /// message.set_field_by_name("foo", Value::I32(150));
+    ///
+    /// let mut output = Vec::new();
+    /// message.encode_to_writer(&mut output).unwrap();
+    /// assert_eq!(output, b"\x08\x96\x01");
+    /// ```
+    pub fn encode_to_writer<W>(&self, mut writer: W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        writer.write_all(&self.encode_to_vec())
+    }
+
+    /// Encodes this message to `writer`, preceded by its length.
+    ///
+    /// The length prefix and the message are encoded into a buffer of exactly the required size,
+    /// which is written with a single call to [`write_all`][Write::write_all]. The messages can
+    /// be read back using [`decode_length_delimited_from_reader`][DynamicMessage::decode_length_delimited_from_reader]
+    /// with the same [`SizePrefix`].
+    ///
+    /// If the prefix is [`SizePrefix::Fixed32`] and the encoded message is longer than
+    /// [`u32::MAX`] bytes, an error of kind [`InvalidInput`][io::ErrorKind::InvalidInput] is
+    /// returned and nothing is written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SizePrefix, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut message = DynamicMessage::new(message_descriptor);
+    /// message.set_field_by_name("foo", Value::I32(150));
+    ///
+    /// let mut output = Vec::new();
+    /// message.encode_length_delimited_to_writer(&mut output, SizePrefix::Varint).unwrap();
+    /// message.encode_length_delimited_to_writer(&mut output, SizePrefix::Fixed32).unwrap();
+    /// assert_eq!(output, b"\x03\x08\x96\x01\x00\x00\x00\x03\x08\x96\x01");
+    /// ```
+    pub fn encode_length_delimited_to_writer<W>(
+        &self,
+        mut writer: W,
+        prefix: SizePrefix,
+    ) -> io::Result<()>
+    where
+        W: Write,
+    {
+        let len = self.encoded_len();
+        let mut buf = match prefix {
+            SizePrefix::Varint => {
+                let mut buf = Vec::with_capacity(encoded_len_varint(len as u64) + len);
+                encode_varint(len as u64, &mut buf);
+                buf
+            }
+            SizePrefix::Fixed32 => {
+                let len = u32::try_from(len).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "message is too long for a 4-byte length prefix",
+                    )
+                })?;
+                let mut buf = Vec::with_capacity(4 + len as usize);
+                buf.extend_from_slice(&len.to_be_bytes());
+                buf
+            }
+        };
+        self.encode_raw(&mut buf);
+        writer.write_all(&buf)
+    }
+}
+
+/// Reads a length prefix, or returns `None` if the reader is at the end of the stream.
+fn read_size_prefix<R>(reader: &mut R, prefix: SizePrefix) -> io::Result<Option<u64>>
+where
+    R: Read,
+{
+    match prefix {
+        SizePrefix::Varint => {
+            let mut value = 0u64;
+            for i in 0..10 {
+                let mut byte = [0];
+                if !read_exact_or_eof(reader, &mut byte, i == 0)? {
+                    return Ok(None);
+                }
+
+                value |= u64::from(byte[0] & 0x7f) << (i * 7);
+                if byte[0] < 0x80 {
+                    return Ok(Some(value));
+                }
+            }
+            Err(invalid_data(DecodeError::new("invalid varint")))
+        }
+        SizePrefix::Fixed32 => {
+            let mut bytes = [0; 4];
+            if !read_exact_or_eof(reader, &mut bytes[..1], true)? {
+                return Ok(None);
+            }
+            reader.read_exact(&mut bytes[1..])?;
+            Ok(Some(u32::from_be_bytes(bytes).into()))
+        }
+    }
+}
+
+/// Fills `buf` from the reader, returning `false` if `allow_eof` is set and the reader is at the
+/// end of the stream.
+fn read_exact_or_eof<R>(reader: &mut R, buf: &mut [u8], allow_eof: bool) -> io::Result<bool>
+where
+    R: Read,
+{
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(err) if allow_eof && err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+fn message_bytes_limit(desc: &MessageDescriptor) -> Option<usize> {
+    desc.parent_pool()
+        .decode_options()
+        .and_then(|options| options.message_bytes_limit())
+}
+
+fn invalid_data(err: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
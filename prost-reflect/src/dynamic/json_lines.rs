@@ -0,0 +1,507 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, Read, Write},
+};
+
+use prost::{DecodeError, Message};
+
+use crate::{
+    dynamic::{deserialize_json, JsonParseError},
+    DeserializeOptions, DynamicMessage, MessageDescriptor, SerializeOptions,
+};
+
+/// The progress of a streaming conversion, passed to the callback after each message.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct Progress {
+    messages: u64,
+    bytes_read: u64,
+}
+
+/// An error that occurred while converting a stream of messages.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct ConvertError {
+    index: u64,
+    kind: ConvertErrorKind,
+}
+
+#[derive(Debug)]
+enum ConvertErrorKind {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Parse(JsonParseError),
+    Decode(DecodeError),
+}
+
+/// An iterator over the messages in a stream of JSON messages, created by [`read_json_lines`].
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct JsonLinesReader<R> {
+    desc: MessageDescriptor,
+    reader: R,
+    options: DeserializeOptions,
+    format: Format,
+    buf: Vec<u8>,
+    index: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Unknown,
+    Lines,
+    Array { first: bool },
+    Done,
+}
+
+/// Returns an iterator which reads messages of type `desc` from a stream of JSON.
+///
+/// The input may either be newline-delimited JSON, with one message on each line, or a single
+/// top-level JSON array of messages. The format is detected from the first non-whitespace
+/// character of the input. Blank lines in newline-delimited input are skipped.
+///
+/// Messages are read one at a time, so memory use is bounded by the size of the largest message
+/// rather than the size of the input. Each message is deserialized separately, so if a message is
+/// invalid, an error is returned for it and iteration continues with the next message. Errors
+/// include the zero-based [index][ConvertError::index] of the message in the stream. The iterator
+/// ends after an I/O error, or if the input is an array which is not well-formed.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::json_lines;
+///
+/// let input = b"{\"foo\":150}\n{\"foo\":\"bar\"}\n{\"foo\":1}\n";
+/// let messages: Vec<_> = json_lines::read_json_lines(&message_descriptor, input.as_ref(), &DeserializeOptions::new()).collect();
+/// assert_eq!(messages.len(), 3);
+/// assert_eq!(messages[0].as_ref().unwrap().get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+/// assert_eq!(messages[1].as_ref().unwrap_err().index(), 1);
+/// assert_eq!(messages[2].as_ref().unwrap().get_field_by_name("foo").unwrap().as_ref(), &Value::I32(1));
+///
+/// let input = b"[{\"foo\":150}, {\"foo\":1}]";
+/// let messages: Vec<_> = json_lines::read_json_lines(&message_descriptor, input.as_ref(), &DeserializeOptions::new())
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(messages.len(), 2);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub fn read_json_lines<R>(
+    desc: &MessageDescriptor,
+    reader: R,
+    options: &DeserializeOptions,
+) -> JsonLinesReader<R>
+where
+    R: BufRead,
+{
+    JsonLinesReader {
+        desc: desc.clone(),
+        reader,
+        options: options.clone(),
+        format: Format::Unknown,
+        buf: Vec::new(),
+        index: 0,
+    }
+}
+
+/// Converts a stream of newline-delimited JSON messages of type `desc` into a stream of
+/// length-delimited protobuf messages.
+///
+/// Messages are read and written one at a time, so memory use is bounded by the size of the largest
+/// message rather than the size of the input. Blank lines in the input are skipped. The `progress`
+/// callback is invoked after each message is written.
+///
+/// Returns the total number of messages converted.
+///
+/// # Examples
+///
+/// ```
+/// # use prost::Message;
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::json_lines;
+///
+/// let input = b"{\"foo\":150}\n{\"foo\":1}\n";
+/// let mut output = Vec::new();
+/// let count = json_lines::json_lines_to_length_delimited(
+///     &message_descriptor,
+///     input.as_ref(),
+///     &mut output,
+///     &DeserializeOptions::new(),
+///     |_| {},
+/// ).unwrap();
+/// assert_eq!(count, 2);
+/// assert_eq!(output, b"\x03\x08\x96\x01\x02\x08\x01");
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub fn json_lines_to_length_delimited<R, W, F>(
+    desc: &MessageDescriptor,
+    mut reader: R,
+    mut writer: W,
+    options: &DeserializeOptions,
+    mut progress: F,
+) -> Result<u64, ConvertError>
+where
+    R: BufRead,
+    W: Write,
+    F: FnMut(Progress),
+{
+    let mut state = Progress::default();
+    let mut line = Vec::new();
+    let mut buf = Vec::new();
+
+    loop {
+        line.clear();
+        let len = reader
+            .read_until(b'\n', &mut line)
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+        if len == 0 {
+            break;
+        }
+        state.bytes_read += len as u64;
+
+        if line.iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+
+        let message = deserialize_json(desc.clone(), &mut line, options)
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Parse(err)))?;
+
+        buf.clear();
+        message
+            .encode_length_delimited(&mut buf)
+            .expect("vec should have sufficient capacity");
+        writer
+            .write_all(&buf)
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+
+        state.messages += 1;
+        progress(state);
+    }
+
+    writer
+        .flush()
+        .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+    Ok(state.messages)
+}
+
+/// Converts a stream of length-delimited protobuf messages of type `desc` into a stream of
+/// newline-delimited JSON messages.
+///
+/// Messages are read and written one at a time, so memory use is bounded by the size of the largest
+/// message rather than the size of the input. The `progress` callback is invoked after each message
+/// is written.
+///
+/// Returns the total number of messages converted.
+///
+/// # Examples
+///
+/// ```
+/// # use prost::Message;
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::json_lines;
+///
+/// let input = b"\x03\x08\x96\x01\x02\x08\x01";
+/// let mut output = Vec::new();
+/// let count = json_lines::length_delimited_to_json_lines(
+///     &message_descriptor,
+///     input.as_ref(),
+///     &mut output,
+///     &SerializeOptions::new(),
+///     |_| {},
+/// ).unwrap();
+/// assert_eq!(count, 2);
+/// assert_eq!(output, b"{\"foo\":150}\n{\"foo\":1}\n");
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub fn length_delimited_to_json_lines<R, W, F>(
+    desc: &MessageDescriptor,
+    mut reader: R,
+    mut writer: W,
+    options: &SerializeOptions,
+    mut progress: F,
+) -> Result<u64, ConvertError>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Progress),
+{
+    let mut state = Progress::default();
+    let mut buf = Vec::new();
+
+    while let Some((len, len_size)) =
+        read_length_delimiter(&mut reader).map_err(|err| ConvertError::new(state.messages, err))?
+    {
+        buf.clear();
+        (&mut reader)
+            .take(len)
+            .read_to_end(&mut buf)
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+        if (buf.len() as u64) < len {
+            return Err(ConvertError::new(
+                state.messages,
+                ConvertErrorKind::Io(io::ErrorKind::UnexpectedEof.into()),
+            ));
+        }
+        state.bytes_read += len_size + len;
+
+        let message = DynamicMessage::decode(desc.clone(), buf.as_slice())
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Decode(err)))?;
+
+        let mut serializer = serde_json::Serializer::new(&mut writer);
+        message
+            .serialize_with_options(&mut serializer, options)
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Json(err)))?;
+        writer
+            .write_all(b"\n")
+            .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+
+        state.messages += 1;
+        progress(state);
+    }
+
+    writer
+        .flush()
+        .map_err(|err| ConvertError::new(state.messages, ConvertErrorKind::Io(err)))?;
+    Ok(state.messages)
+}
+
+/// Reads a varint length prefix, returning the length and the number of bytes it occupied, or
+/// `None` if the reader is at the end of the stream.
+fn read_length_delimiter<R>(reader: &mut R) -> Result<Option<(u64, u64)>, ConvertErrorKind>
+where
+    R: Read,
+{
+    let mut value = 0u64;
+    for i in 0..10 {
+        let mut byte = [0];
+        if let Err(err) = reader.read_exact(&mut byte) {
+            return if i == 0 && err.kind() == io::ErrorKind::UnexpectedEof {
+                Ok(None)
+            } else {
+                Err(ConvertErrorKind::Io(err))
+            };
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << (i * 7);
+        if byte[0] < 0x80 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+
+    Err(ConvertErrorKind::Decode(DecodeError::new("invalid varint")))
+}
+
+impl<R> JsonLinesReader<R>
+where
+    R: BufRead,
+{
+    /// Reads the next message into `buf`, returning `false` at the end of the input.
+    fn read_message(&mut self) -> Result<bool, ConvertErrorKind> {
+        self.buf.clear();
+        loop {
+            match self.format {
+                Format::Unknown => match self.skip_whitespace()? {
+                    Some(b'[') => {
+                        self.reader.consume(1);
+                        self.format = Format::Array { first: true };
+                    }
+                    Some(_) => self.format = Format::Lines,
+                    None => self.format = Format::Done,
+                },
+                Format::Lines => {
+                    if self
+                        .reader
+                        .read_until(b'\n', &mut self.buf)
+                        .map_err(ConvertErrorKind::Io)?
+                        == 0
+                    {
+                        self.format = Format::Done;
+                    } else if self.buf.iter().all(u8::is_ascii_whitespace) {
+                        self.buf.clear();
+                    } else {
+                        return Ok(true);
+                    }
+                }
+                Format::Array { first } => {
+                    match self.skip_whitespace()? {
+                        Some(b']') => {
+                            self.reader.consume(1);
+                            self.format = Format::Done;
+                            continue;
+                        }
+                        Some(b',') if !first => {
+                            self.reader.consume(1);
+                            self.skip_whitespace()?;
+                        }
+                        Some(_) if first => (),
+                        Some(_) => return Err(invalid_array("expected `,` or `]`")),
+                        None => return Err(invalid_array("unterminated array")),
+                    }
+
+                    self.format = Format::Array { first: false };
+                    self.read_array_element()?;
+                    return Ok(true);
+                }
+                Format::Done => return Ok(false),
+            }
+        }
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it.
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, ConvertErrorKind> {
+        loop {
+            let chunk = self.reader.fill_buf().map_err(ConvertErrorKind::Io)?;
+            match chunk.iter().position(|byte| !byte.is_ascii_whitespace()) {
+                Some(pos) => {
+                    let byte = chunk[pos];
+                    self.reader.consume(pos);
+                    return Ok(Some(byte));
+                }
+                None if chunk.is_empty() => return Ok(None),
+                None => {
+                    let len = chunk.len();
+                    self.reader.consume(len);
+                }
+            }
+        }
+    }
+
+    /// Reads a single JSON value from an array into `buf`, tracking nesting to find its end
+    /// without parsing it.
+    fn read_array_element(&mut self) -> Result<(), ConvertErrorKind> {
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let chunk = self.reader.fill_buf().map_err(ConvertErrorKind::Io)?;
+            if chunk.is_empty() {
+                return Err(invalid_array("unterminated array"));
+            }
+
+            let mut len = 0;
+            let mut done = false;
+            for &byte in chunk {
+                len += 1;
+                if in_string {
+                    if escaped {
+                        escaped = false;
+                    } else if byte == b'\\' {
+                        escaped = true;
+                    } else if byte == b'"' {
+                        in_string = false;
+                        done = depth == 0;
+                    }
+                } else {
+                    match byte {
+                        b'"' => in_string = true,
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' if depth > 0 => {
+                            depth -= 1;
+                            done = depth == 0;
+                        }
+                        b',' | b'}' | b']' if depth == 0 => {
+                            // The end of a scalar value, which belongs to the enclosing array.
+                            len -= 1;
+                            done = true;
+                        }
+                        _ if byte.is_ascii_whitespace() && depth == 0 => done = true,
+                        _ => (),
+                    }
+                }
+
+                if done {
+                    break;
+                }
+            }
+
+            self.buf.extend_from_slice(&chunk[..len]);
+            self.reader.consume(len);
+            if done {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<R> Iterator for JsonLinesReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<DynamicMessage, ConvertError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.read_message() {
+            Ok(true) => deserialize_json(self.desc.clone(), &mut self.buf, &self.options)
+                .map_err(ConvertErrorKind::Parse),
+            Ok(false) => return None,
+            Err(err) => {
+                self.format = Format::Done;
+                Err(err)
+            }
+        };
+
+        let index = self.index;
+        self.index += 1;
+        Some(result.map_err(|err| ConvertError::new(index, err)))
+    }
+}
+
+fn invalid_array(msg: &str) -> ConvertErrorKind {
+    ConvertErrorKind::Parse(serde::de::Error::custom(msg))
+}
+
+impl Progress {
+    /// Returns the number of messages converted so far.
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Returns the number of bytes consumed from the input so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+}
+
+impl ConvertError {
+    fn new(index: u64, kind: ConvertErrorKind) -> Self {
+        ConvertError { index, kind }
+    }
+
+    /// Returns the zero-based index of the message in the stream which caused this error.
+    pub fn index(&self) -> u64 {
+        self.index
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConvertErrorKind::Io(_) => write!(f, "i/o error at message {}", self.index),
+            ConvertErrorKind::Json(_) | ConvertErrorKind::Parse(_) => {
+                write!(f, "invalid JSON for message {}", self.index)
+            }
+            ConvertErrorKind::Decode(_) => {
+                write!(f, "failed to decode message {}", self.index)
+            }
+        }
+    }
+}
+
+impl Error for ConvertError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ConvertErrorKind::Io(err) => Some(err),
+            ConvertErrorKind::Json(err) => Some(err),
+            ConvertErrorKind::Parse(err) => Some(err),
+            ConvertErrorKind::Decode(err) => Some(err),
+        }
+    }
+}
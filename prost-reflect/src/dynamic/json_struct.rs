@@ -0,0 +1,275 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use serde_json::{Map, Number};
+
+use crate::{DynamicMessage, MapKey, MessageDescriptor, Value};
+
+const STRUCT_NAME: &str = "google.protobuf.Struct";
+const VALUE_NAME: &str = "google.protobuf.Value";
+const LIST_VALUE_NAME: &str = "google.protobuf.ListValue";
+
+const STRUCT_FIELDS_NUMBER: u32 = 1;
+const LIST_VALUE_VALUES_NUMBER: u32 = 1;
+const NULL_VALUE_NUMBER: u32 = 1;
+const NUMBER_VALUE_NUMBER: u32 = 2;
+const STRING_VALUE_NUMBER: u32 = 3;
+const BOOL_VALUE_NUMBER: u32 = 4;
+const STRUCT_VALUE_NUMBER: u32 = 5;
+const LIST_VALUE_NUMBER: u32 = 6;
+
+/// An error that occurred while converting a `google.protobuf.Struct`, `google.protobuf.Value` or
+/// `google.protobuf.ListValue` message to or from a [`serde_json::Value`].
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct JsonValueError {
+    kind: JsonValueErrorKind,
+}
+
+#[derive(Debug)]
+enum JsonValueErrorKind {
+    MessageType { name: String },
+    InvalidJsonType { name: &'static str },
+    NonFiniteNumber { value: f64 },
+}
+
+impl DynamicMessage {
+    /// Converts a `google.protobuf.Struct`, `google.protobuf.Value` or `google.protobuf.ListValue`
+    /// message to the equivalent [`serde_json::Value`].
+    ///
+    /// This is equivalent to serializing the message using the
+    /// [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json) and parsing
+    /// the result, but the values are converted directly. A `google.protobuf.Value` with no kind set
+    /// is converted to `null`. An error is returned for any other message type, or if a number is
+    /// infinite or NaN, since JSON cannot represent it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, ReflectMessage};
+    /// let json = serde_json::json!({ "name": "prost", "tags": [1.0, true, null] });
+    /// let message = DynamicMessage::struct_from_json_value(prost_types::Struct::default().descriptor(), json.clone()).unwrap();
+    /// assert_eq!(message.struct_to_json_value().unwrap(), json);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+    pub fn struct_to_json_value(&self) -> Result<serde_json::Value, JsonValueError> {
+        match self.desc.full_name() {
+            STRUCT_NAME => struct_to_json(self).map(serde_json::Value::Object),
+            VALUE_NAME => value_to_json(self),
+            LIST_VALUE_NAME => list_to_json(self).map(serde_json::Value::Array),
+            name => Err(JsonValueErrorKind::MessageType {
+                name: name.to_owned(),
+            }
+            .into()),
+        }
+    }
+
+    /// Converts a [`serde_json::Value`] to a message of type `desc`, which must be one of
+    /// `google.protobuf.Struct`, `google.protobuf.Value` or `google.protobuf.ListValue`.
+    ///
+    /// A `google.protobuf.Struct` requires a JSON object, and a `google.protobuf.ListValue`
+    /// requires a JSON array. Any JSON value can be converted to a `google.protobuf.Value`. Numbers
+    /// are converted to `double`, so integers larger than 2<sup>53</sup> may lose precision.
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+    pub fn struct_from_json_value(
+        desc: MessageDescriptor,
+        value: serde_json::Value,
+    ) -> Result<Self, JsonValueError> {
+        match (desc.full_name(), value) {
+            (STRUCT_NAME, serde_json::Value::Object(object)) => {
+                Ok(StructTypes::new(&desc).struct_from_json(object))
+            }
+            (VALUE_NAME, value) => Ok(StructTypes::new(&desc).value_from_json(value)),
+            (LIST_VALUE_NAME, serde_json::Value::Array(array)) => {
+                Ok(StructTypes::new(&desc).list_from_json(array))
+            }
+            (STRUCT_NAME, _) => {
+                Err(JsonValueErrorKind::InvalidJsonType { name: STRUCT_NAME }.into())
+            }
+            (LIST_VALUE_NAME, _) => Err(JsonValueErrorKind::InvalidJsonType {
+                name: LIST_VALUE_NAME,
+            }
+            .into()),
+            (name, _) => Err(JsonValueErrorKind::MessageType {
+                name: name.to_owned(),
+            }
+            .into()),
+        }
+    }
+}
+
+fn struct_to_json(
+    message: &DynamicMessage,
+) -> Result<Map<String, serde_json::Value>, JsonValueError> {
+    let mut object = Map::new();
+    if let Some(fields) = message
+        .get_field_by_number(STRUCT_FIELDS_NUMBER)
+        .as_deref()
+        .and_then(Value::as_map)
+    {
+        let mut fields: Vec<_> = fields.iter().collect();
+        fields.sort_by_key(|&(key, _)| key);
+        for (key, value) in fields {
+            if let (MapKey::String(key), Value::Message(value)) = (key, value) {
+                object.insert(key.clone(), value_to_json(value)?);
+            }
+        }
+    }
+    Ok(object)
+}
+
+fn list_to_json(message: &DynamicMessage) -> Result<Vec<serde_json::Value>, JsonValueError> {
+    match message
+        .get_field_by_number(LIST_VALUE_VALUES_NUMBER)
+        .as_deref()
+        .and_then(Value::as_list)
+    {
+        Some(values) => values
+            .iter()
+            .filter_map(Value::as_message)
+            .map(value_to_json)
+            .collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn value_to_json(message: &DynamicMessage) -> Result<serde_json::Value, JsonValueError> {
+    let number = [
+        NUMBER_VALUE_NUMBER,
+        STRING_VALUE_NUMBER,
+        BOOL_VALUE_NUMBER,
+        STRUCT_VALUE_NUMBER,
+        LIST_VALUE_NUMBER,
+    ]
+    .into_iter()
+    .find(|&number| message.has_field_by_number(number));
+    let (number, value) = match number {
+        Some(number) => (number, message.get_field_by_number(number)),
+        None => return Ok(serde_json::Value::Null),
+    };
+
+    match (number, value.as_deref()) {
+        (NUMBER_VALUE_NUMBER, Some(&Value::F64(value))) => match Number::from_f64(value) {
+            Some(number) => Ok(serde_json::Value::Number(number)),
+            None => Err(JsonValueErrorKind::NonFiniteNumber { value }.into()),
+        },
+        (STRING_VALUE_NUMBER, Some(Value::String(value))) => {
+            Ok(serde_json::Value::String(value.clone()))
+        }
+        (BOOL_VALUE_NUMBER, Some(&Value::Bool(value))) => Ok(serde_json::Value::Bool(value)),
+        (STRUCT_VALUE_NUMBER, Some(Value::Message(value))) => {
+            struct_to_json(value).map(serde_json::Value::Object)
+        }
+        (LIST_VALUE_NUMBER, Some(Value::Message(value))) => {
+            list_to_json(value).map(serde_json::Value::Array)
+        }
+        _ => Ok(serde_json::Value::Null),
+    }
+}
+
+/// The descriptors of the `Struct`, `Value` and `ListValue` messages in a pool.
+struct StructTypes {
+    struct_desc: MessageDescriptor,
+    value_desc: MessageDescriptor,
+    list_desc: MessageDescriptor,
+}
+
+impl StructTypes {
+    fn new(desc: &MessageDescriptor) -> Self {
+        // All three types are defined in `google/protobuf/struct.proto`, so they are present in
+        // the pool of any of them.
+        let pool = desc.parent_pool();
+        let get = |name: &str| {
+            pool.get_message_by_name(name)
+                .expect("struct types should be defined in the same file")
+        };
+        StructTypes {
+            struct_desc: get(STRUCT_NAME),
+            value_desc: get(VALUE_NAME),
+            list_desc: get(LIST_VALUE_NAME),
+        }
+    }
+
+    fn struct_from_json(&self, object: Map<String, serde_json::Value>) -> DynamicMessage {
+        let fields: HashMap<MapKey, Value> = object
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    MapKey::String(key),
+                    Value::Message(self.value_from_json(value)),
+                )
+            })
+            .collect();
+
+        let mut message = DynamicMessage::new(self.struct_desc.clone());
+        message.set_field_by_number(STRUCT_FIELDS_NUMBER, Value::Map(fields));
+        message
+    }
+
+    fn list_from_json(&self, array: Vec<serde_json::Value>) -> DynamicMessage {
+        let values = array
+            .into_iter()
+            .map(|value| Value::Message(self.value_from_json(value)))
+            .collect();
+
+        let mut message = DynamicMessage::new(self.list_desc.clone());
+        message.set_field_by_number(LIST_VALUE_VALUES_NUMBER, Value::List(values));
+        message
+    }
+
+    fn value_from_json(&self, value: serde_json::Value) -> DynamicMessage {
+        let (number, value) = match value {
+            serde_json::Value::Null => (NULL_VALUE_NUMBER, Value::EnumNumber(0)),
+            serde_json::Value::Bool(value) => (BOOL_VALUE_NUMBER, Value::Bool(value)),
+            serde_json::Value::Number(number) => (
+                NUMBER_VALUE_NUMBER,
+                Value::F64(number.as_f64().unwrap_or_default()),
+            ),
+            serde_json::Value::String(value) => (STRING_VALUE_NUMBER, Value::String(value)),
+            serde_json::Value::Array(array) => (
+                LIST_VALUE_NUMBER,
+                Value::Message(self.list_from_json(array)),
+            ),
+            serde_json::Value::Object(object) => (
+                STRUCT_VALUE_NUMBER,
+                Value::Message(self.struct_from_json(object)),
+            ),
+        };
+
+        let mut message = DynamicMessage::new(self.value_desc.clone());
+        message.set_field_by_number(number, value);
+        message
+    }
+}
+
+impl From<JsonValueErrorKind> for JsonValueError {
+    fn from(kind: JsonValueErrorKind) -> Self {
+        JsonValueError { kind }
+    }
+}
+
+impl fmt::Display for JsonValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            JsonValueErrorKind::MessageType { name } => write!(
+                f,
+                "expected a message of type '{}', '{}' or '{}', but found '{}'",
+                STRUCT_NAME, VALUE_NAME, LIST_VALUE_NAME, name
+            ),
+            JsonValueErrorKind::InvalidJsonType { name } => {
+                let expected = if *name == STRUCT_NAME {
+                    "an object"
+                } else {
+                    "an array"
+                };
+                write!(f, "expected {} for message '{}'", expected, name)
+            }
+            JsonValueErrorKind::NonFiniteNumber { value } => write!(
+                f,
+                "cannot convert non-finite number {} in '{}' to JSON",
+                value, VALUE_NAME
+            ),
+        }
+    }
+}
+
+impl Error for JsonValueError {}
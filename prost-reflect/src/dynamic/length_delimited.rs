@@ -0,0 +1,339 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_core::{ready, Stream};
+use futures_sink::Sink;
+use prost::{
+    bytes::{Buf, BytesMut},
+    encoding::{encode_varint, encoded_len_varint},
+    DecodeError, Message,
+};
+use tokio1::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{DynamicMessage, MessageDescriptor};
+
+/// The default limit on the length of a message, matching the default used by gRPC
+/// implementations.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 4 * 1024 * 1024;
+
+/// The number of bytes requested from the reader at a time.
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// The number of encoded bytes buffered by a [`MessageSink`] before they are written out.
+const WRITE_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A [`Stream`] of varint length-delimited messages read from an [`AsyncRead`].
+///
+/// Input is only read when the stream is polled, so a slow consumer applies backpressure to the
+/// reader. Messages longer than the [maximum frame size](MessageStream::max_frame_size) are
+/// rejected before they are buffered.
+///
+/// If a message cannot be decoded, an error of kind [`InvalidData`][io::ErrorKind::InvalidData]
+/// is returned, and the stream continues with the next message. The stream ends after any other
+/// error, such as an I/O error, an oversized message, or input which ends partway through a
+/// message.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use prost_reflect::length_delimited::MessageStream;
+/// use tokio_stream::StreamExt;
+///
+/// # pollster::block_on(async {
+/// let input: &[u8] = b"\x03\x08\x96\x01\x02\x08\x01";
+/// let messages: Vec<DynamicMessage> = MessageStream::new(message_descriptor, input)
+///     .collect::<Result<_, _>>()
+///     .await
+///     .unwrap();
+/// assert_eq!(messages.len(), 2);
+/// assert_eq!(messages[0].get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+/// assert_eq!(messages[1].get_field_by_name("foo").unwrap().as_ref(), &Value::I32(1));
+/// # });
+/// ```
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub struct MessageStream<R> {
+    desc: MessageDescriptor,
+    reader: R,
+    max_frame_size: usize,
+    buf: BytesMut,
+    eof: bool,
+    done: bool,
+}
+
+/// A [`Sink`] which writes varint length-delimited messages to an [`AsyncWrite`].
+///
+/// Encoded messages are collected in a small buffer, which is written out once it fills up, so
+/// the sink only becomes ready for more messages once the writer has caught up. Flushing or
+/// closing the sink writes any buffered messages. Messages longer than the
+/// [maximum frame size](MessageSink::max_frame_size) are rejected with an error of kind
+/// [`InvalidInput`][io::ErrorKind::InvalidInput], and are not written.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// use futures_util::SinkExt;
+/// use prost_reflect::length_delimited::MessageSink;
+///
+/// let mut message = DynamicMessage::new(message_descriptor);
+/// message.set_field_by_name("foo", Value::I32(150));
+///
+/// # pollster::block_on(async {
+/// let mut sink = MessageSink::new(Vec::new());
+/// sink.feed(message.clone()).await.unwrap();
+/// sink.send(message).await.unwrap();
+/// assert_eq!(sink.into_inner(), b"\x03\x08\x96\x01\x03\x08\x96\x01");
+/// # });
+/// ```
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub struct MessageSink<W> {
+    writer: W,
+    max_frame_size: usize,
+    buf: BytesMut,
+}
+
+impl<R> MessageStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Creates a stream which reads messages of type `desc` from `reader`.
+    pub fn new(desc: MessageDescriptor, reader: R) -> Self {
+        MessageStream {
+            desc,
+            reader,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            buf: BytesMut::new(),
+            eof: false,
+            done: false,
+        }
+    }
+
+    /// Sets the maximum length of a message, in bytes, not including its length prefix.
+    ///
+    /// The default value is [`DEFAULT_MAX_FRAME_SIZE`].
+    pub fn max_frame_size(mut self, limit: usize) -> Self {
+        self.max_frame_size = limit;
+        self
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Consumes the stream, returning the underlying reader.
+    ///
+    /// Any input which has been read but not yet decoded is discarded.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decodes a message from the start of the buffer, or returns `None` if the buffer does not
+    /// contain a complete message yet. The outer error is returned if the stream cannot
+    /// continue.
+    fn decode_frame(&mut self) -> io::Result<Option<Result<DynamicMessage, DecodeError>>> {
+        let (len, prefix_len) = match decode_prefix(&self.buf)? {
+            Some(prefix) => prefix,
+            None => return Ok(None),
+        };
+        if len > self.max_frame_size as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "message of {} bytes exceeds the maximum frame size of {} bytes",
+                    len, self.max_frame_size
+                ),
+            ));
+        }
+
+        let frame_len = prefix_len + len as usize;
+        if self.buf.len() < frame_len {
+            self.buf.reserve(frame_len - self.buf.len());
+            return Ok(None);
+        }
+
+        self.buf.advance(prefix_len);
+        let frame = self.buf.split_to(len as usize).freeze();
+        Ok(Some(DynamicMessage::decode(self.desc.clone(), frame)))
+    }
+
+    /// Reads more input into the buffer, setting `eof` if the reader is at the end of the stream.
+    fn poll_fill_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let start = self.buf.len();
+        self.buf.resize(start + READ_CHUNK_SIZE, 0);
+
+        let mut read_buf = ReadBuf::new(&mut self.buf[start..]);
+        let result = Pin::new(&mut self.reader).poll_read(cx, &mut read_buf);
+        let filled = read_buf.filled().len();
+        self.buf.truncate(start + filled);
+
+        if let Poll::Ready(Ok(())) = result {
+            self.eof = filled == 0;
+        }
+        result
+    }
+}
+
+impl<R> Stream for MessageStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    type Item = io::Result<DynamicMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            match this.decode_frame() {
+                Ok(Some(Ok(message))) => return Poll::Ready(Some(Ok(message))),
+                // The invalid message has been consumed, so the stream can continue.
+                Ok(Some(Err(err))) => {
+                    return Poll::Ready(Some(Err(io::Error::new(io::ErrorKind::InvalidData, err))))
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+
+            if this.eof {
+                this.done = true;
+                return if this.buf.is_empty() {
+                    Poll::Ready(None)
+                } else {
+                    Poll::Ready(Some(Err(io::ErrorKind::UnexpectedEof.into())))
+                };
+            }
+
+            if let Err(err) = ready!(this.poll_fill_buf(cx)) {
+                this.done = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+    }
+}
+
+impl<W> MessageSink<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Creates a sink which writes messages to `writer`.
+    pub fn new(writer: W) -> Self {
+        MessageSink {
+            writer,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// Sets the maximum length of a message, in bytes, not including its length prefix.
+    ///
+    /// The default value is [`DEFAULT_MAX_FRAME_SIZE`].
+    pub fn max_frame_size(mut self, limit: usize) -> Self {
+        self.max_frame_size = limit;
+        self
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the sink, returning the underlying writer.
+    ///
+    /// Any buffered messages which have not been written are discarded, so the sink should
+    /// usually be flushed first.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes out the buffered messages.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.buf.is_empty() {
+            let written = ready!(Pin::new(&mut self.writer).poll_write(cx, &self.buf))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.buf.advance(written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W> Sink<DynamicMessage> for MessageSink<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.buf.len() >= WRITE_BUFFER_SIZE {
+            ready!(this.poll_write_buf(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, message: DynamicMessage) -> io::Result<()> {
+        let this = self.get_mut();
+        let len = message.encoded_len();
+        if len > this.max_frame_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "message of {} bytes exceeds the maximum frame size of {} bytes",
+                    len, this.max_frame_size
+                ),
+            ));
+        }
+
+        this.buf.reserve(encoded_len_varint(len as u64) + len);
+        encode_varint(len as u64, &mut this.buf);
+        message.encode_raw(&mut this.buf);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+/// Decodes a varint length prefix from the start of `buf`, returning the length and the size of
+/// the prefix, or `None` if `buf` ends before the end of the prefix.
+fn decode_prefix(buf: &[u8]) -> io::Result<Option<(u64, usize)>> {
+    let mut value = 0u64;
+    for (i, &byte) in buf.iter().take(10).enumerate() {
+        value |= u64::from(byte & 0x7f) << (i * 7);
+        if byte < 0x80 {
+            return Ok(Some((value, i + 1)));
+        }
+    }
+
+    if buf.len() >= 10 {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "invalid varint"))
+    } else {
+        Ok(None)
+    }
+}
@@ -1,6 +1,8 @@
+use std::{borrow::Cow, collections::hash_map, slice};
+
 use prost::{
     bytes::{Buf, BufMut},
-    encoding::{DecodeContext, WireType},
+    encoding::{self, DecodeContext, WireType},
     DecodeError, Message,
 };
 
@@ -20,21 +22,64 @@ impl Message for DynamicMessage {
         B: BufMut,
         Self: Sized,
     {
-        for field in self.fields.iter(&self.desc) {
-            match field {
-                ValueAndDescriptor::Field(value, field_desc) => {
-                    value.encode_field(&field_desc, buf)
-                }
-                ValueAndDescriptor::Extension(value, extension_desc) => {
-                    value.encode_field(&extension_desc, buf)
-                }
-                ValueAndDescriptor::Unknown(number, unknowns) => {
-                    for unknown in unknowns {
-                        unknown.encode_field(number, buf);
-                    }
-                }
+        if let Some(encoded) = self.fields.encoded() {
+            buf.put_slice(encoded);
+            return;
+        }
+
+        // The length of each nested message is written before its fields, so the lengths are all
+        // computed up front, rather than once for each level of nesting.
+        let mut lens = EncodedLen {
+            nested_only: true,
+            ..Default::default()
+        };
+        walk_message(self, &mut lens);
+        walk_message(
+            self,
+            &mut Encoder {
+                buf,
+                nested_lens: lens.nested.into_iter(),
+            },
+        );
+    }
+
+    fn merge<B>(&mut self, mut buf: B) -> Result<(), DecodeError>
+    where
+        B: Buf,
+        Self: Sized,
+    {
+        if let Some(options) = self.desc.parent_pool().decode_options() {
+            let options = options.clone();
+            return self.merge_with_options(buf, &options);
+        }
+
+        let ctx = DecodeContext::default();
+        while buf.has_remaining() {
+            let (number, wire_type) = encoding::decode_key(&mut buf)?;
+            self.merge_field(number, wire_type, &mut buf, ctx.clone())?;
+        }
+        Ok(())
+    }
+
+    fn merge_length_delimited<B>(&mut self, mut buf: B) -> Result<(), DecodeError>
+    where
+        B: Buf,
+        Self: Sized,
+    {
+        if self.desc.parent_pool().decode_options().is_some() {
+            let len = encoding::decode_varint(&mut buf)?;
+            if len > buf.remaining() as u64 {
+                return Err(DecodeError::new("buffer underflow"));
             }
+            return self.merge(buf.take(len as usize));
         }
+
+        encoding::message::merge(
+            WireType::LengthDelimited,
+            self,
+            &mut buf,
+            DecodeContext::default(),
+        )
     }
 
     fn merge_field<B>(
@@ -49,15 +94,10 @@ impl Message for DynamicMessage {
         Self: Sized,
     {
         if let Some(field_desc) = self.desc.get_field(number) {
-            self.get_field_mut(&field_desc)
-                .merge_field(&field_desc, wire_type, buf, ctx)
+            self.fields.merge_field(&field_desc, wire_type, buf, ctx)
         } else if let Some(extension_desc) = self.desc.get_extension(number) {
-            self.get_extension_mut(&extension_desc).merge_field(
-                &extension_desc,
-                wire_type,
-                buf,
-                ctx,
-            )
+            self.fields
+                .merge_field(&extension_desc, wire_type, buf, ctx)
         } else {
             let field = UnknownField::decode(number, wire_type, buf, ctx)?;
             self.fields.add_unknown(number, field);
@@ -66,27 +106,312 @@ impl Message for DynamicMessage {
     }
 
     fn encoded_len(&self) -> usize {
-        let mut len = 0;
-        for field in self.fields.iter(&self.desc) {
-            match field {
-                ValueAndDescriptor::Field(value, field_desc) => {
-                    len += value.encoded_len(&field_desc);
+        if let Some(encoded) = self.fields.encoded() {
+            return encoded.len();
+        }
+
+        let mut lens = EncodedLen::default();
+        walk_message(self, &mut lens);
+        lens.len
+    }
+
+    fn clear(&mut self) {
+        self.fields.clear_all();
+    }
+}
+
+/// Receives the parts of a message, in the order they are encoded, from [`walk_message`].
+trait Visitor {
+    /// Visits a field which does not contain any messages.
+    fn field(&mut self, value: &Value, field_desc: &impl FieldDescriptorLike);
+    fn map_key(&mut self, key: &MapKey, key_desc: &FieldDescriptor);
+    fn unknown(&mut self, number: u32, unknowns: &[UnknownField]);
+    /// Visits the original bytes of a lazily decoded message which has not been modified, or
+    /// which failed to decode.
+    fn encoded(&mut self, bytes: &[u8]);
+    fn start_message(&mut self, number: u32, group: bool);
+    fn end_message(&mut self, number: u32, group: bool);
+}
+
+/// A message field, repeated message field or map field with message values, whose messages
+/// are visited before the remaining fields of the message containing it.
+enum Nested<'a> {
+    Message(&'a DynamicMessage, u32, bool),
+    List(slice::Iter<'a, Value>, u32, bool),
+    Map(hash_map::Iter<'a, MapKey, Value>, u32, FieldDescriptor),
+}
+
+enum Frame<'a, I> {
+    /// The remaining fields of a nested message.
+    Message(I, u32, bool),
+    /// The remaining elements of a repeated message field.
+    List(slice::Iter<'a, Value>, u32, bool),
+    /// The remaining entries of a map field with message values.
+    Map(hash_map::Iter<'a, MapKey, Value>, u32, FieldDescriptor),
+    /// The end of a map entry, once its value has been visited.
+    MapEntryEnd(u32),
+}
+
+/// Visits the fields of `message` and its nested messages, in the order they are encoded.
+///
+/// Nested messages are tracked using an explicit stack rather than by recursion, so that
+/// encoding deeply nested messages cannot overflow the thread's stack.
+fn walk_message(message: &DynamicMessage, visitor: &mut impl Visitor) {
+    if let Some(undecoded) = message.fields.undecoded() {
+        visitor.encoded(undecoded);
+    }
+    let mut fields = message.fields.iter(&message.desc);
+    let mut stack = frame_stack(&fields);
+    loop {
+        let nested = match stack.last_mut() {
+            None => match fields.next() {
+                Some(field) => visit_field(field, visitor),
+                None => return,
+            },
+            Some(Frame::Message(fields, number, group)) => match fields.next() {
+                Some(field) => visit_field(field, visitor),
+                None => {
+                    visitor.end_message(*number, *group);
+                    stack.pop();
+                    continue;
                 }
-                ValueAndDescriptor::Extension(value, extension_desc) => {
-                    len += value.encoded_len(&extension_desc);
+            },
+            Some(Frame::List(values, number, group)) => match values.next() {
+                Some(Value::Message(message)) => Some(Nested::Message(message, *number, *group)),
+                Some(value) => panic!(
+                    "mismatch between DynamicMessage value {:?} and message type",
+                    value
+                ),
+                None => {
+                    stack.pop();
+                    continue;
                 }
-                ValueAndDescriptor::Unknown(number, unknowns) => {
-                    for unknown in unknowns {
-                        len += unknown.encoded_len(number)
+            },
+            Some(Frame::Map(entries, number, key_desc)) => match entries.next() {
+                Some((key, Value::Message(message))) => {
+                    let number = *number;
+                    visitor.start_message(number, false);
+                    visitor.map_key(key, key_desc);
+                    stack.push(Frame::MapEntryEnd(number));
+                    Some(Nested::Message(message, MAP_ENTRY_VALUE_NUMBER, false))
+                }
+                Some((_, value)) => panic!(
+                    "mismatch between DynamicMessage value {:?} and message type",
+                    value
+                ),
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            },
+            Some(Frame::MapEntryEnd(number)) => {
+                visitor.end_message(*number, false);
+                stack.pop();
+                continue;
+            }
+        };
+
+        match nested {
+            Some(Nested::Message(message, number, group)) => {
+                visitor.start_message(number, group);
+                if let Some(encoded) = message.fields.encoded() {
+                    visitor.encoded(encoded);
+                    visitor.end_message(number, group);
+                } else {
+                    if let Some(undecoded) = message.fields.undecoded() {
+                        visitor.encoded(undecoded);
                     }
+                    stack.push(Frame::Message(
+                        message.fields.iter(&message.desc),
+                        number,
+                        group,
+                    ));
                 }
             }
+            Some(Nested::List(values, number, group)) => {
+                stack.push(Frame::List(values, number, group))
+            }
+            Some(Nested::Map(entries, number, key_desc)) => {
+                stack.push(Frame::Map(entries, number, key_desc))
+            }
+            None => (),
         }
-        len
     }
+}
 
-    fn clear(&mut self) {
-        self.fields.clear_all();
+/// Creates an empty stack of frames whose message fields are iterated by the same type as `_fields`.
+fn frame_stack<'a, I>(_fields: &I) -> Vec<Frame<'a, I>> {
+    Vec::new()
+}
+
+/// Visits a field if it does not contain any messages, or otherwise returns its messages to be
+/// visited.
+fn visit_field<'a>(
+    field: ValueAndDescriptor<'a>,
+    visitor: &mut impl Visitor,
+) -> Option<Nested<'a>> {
+    match field {
+        ValueAndDescriptor::Field(value, field_desc) => {
+            visit_value(value, &field_desc, field_desc.is_group(), visitor)
+        }
+        ValueAndDescriptor::Extension(value, extension_desc) => {
+            visit_value(value, &extension_desc, extension_desc.is_group(), visitor)
+        }
+        ValueAndDescriptor::Unknown(number, unknowns) => {
+            visitor.unknown(number, unknowns);
+            None
+        }
+    }
+}
+
+fn visit_value<'a>(
+    value: Cow<'a, Value>,
+    field_desc: &impl FieldDescriptorLike,
+    group: bool,
+    visitor: &mut impl Visitor,
+) -> Option<Nested<'a>> {
+    let value = match value {
+        Cow::Borrowed(value) => value,
+        Cow::Owned(value) => {
+            visitor.field(&value, field_desc);
+            return None;
+        }
+    };
+
+    let number = field_desc.number();
+    match (value, field_desc.kind()) {
+        (Value::Message(message), _) => Some(Nested::Message(message, number, group)),
+        (Value::List(values), Kind::Message(_)) => Some(Nested::List(values.iter(), number, group)),
+        (Value::Map(entries), Kind::Message(map_entry))
+            if map_entry
+                .map_entry_value_field()
+                .kind()
+                .as_message()
+                .is_some() =>
+        {
+            Some(Nested::Map(
+                entries.iter(),
+                number,
+                map_entry.map_entry_key_field(),
+            ))
+        }
+        _ => {
+            visitor.field(value, field_desc);
+            None
+        }
+    }
+}
+
+/// Computes the encoded length of a message, and of each of its nested messages.
+#[derive(Default)]
+struct EncodedLen {
+    /// The encoded length of the message currently being visited.
+    len: usize,
+    /// The encoded lengths of the messages containing the current message, and the index of the
+    /// current message in `nested`.
+    stack: Vec<(usize, usize)>,
+    /// The encoded lengths of the nested messages, in the order they are started.
+    nested: Vec<usize>,
+    /// Whether to skip the fields of the outermost message, if only the lengths of nested
+    /// messages are needed.
+    nested_only: bool,
+}
+
+impl EncodedLen {
+    fn add(&mut self, len: impl FnOnce() -> usize) {
+        if !self.nested_only || !self.stack.is_empty() {
+            self.len += len();
+        }
+    }
+}
+
+impl Visitor for EncodedLen {
+    fn field(&mut self, value: &Value, field_desc: &impl FieldDescriptorLike) {
+        self.add(|| value.encoded_len(field_desc))
+    }
+
+    fn map_key(&mut self, key: &MapKey, key_desc: &FieldDescriptor) {
+        self.add(|| key.encoded_len(key_desc))
+    }
+
+    fn unknown(&mut self, number: u32, unknowns: &[UnknownField]) {
+        self.add(|| {
+            unknowns
+                .iter()
+                .map(|unknown| unknown.encoded_len(number))
+                .sum()
+        })
+    }
+
+    fn encoded(&mut self, bytes: &[u8]) {
+        self.len += bytes.len();
+    }
+
+    fn start_message(&mut self, _: u32, _: bool) {
+        self.stack.push((self.len, self.nested.len()));
+        self.nested.push(0);
+        self.len = 0;
+    }
+
+    fn end_message(&mut self, number: u32, group: bool) {
+        let (parent_len, index) = self.stack.pop().expect("unbalanced nested message");
+        let len = self.len;
+        self.nested[index] = len;
+        self.len = parent_len
+            + len
+            + if group {
+                2 * encoding::key_len(number)
+            } else {
+                encoding::key_len(number) + encoding::encoded_len_varint(len as u64)
+            };
+    }
+}
+
+/// Encodes a message, given the lengths of its nested messages computed by [`EncodedLen`].
+struct Encoder<'b, B> {
+    buf: &'b mut B,
+    nested_lens: std::vec::IntoIter<usize>,
+}
+
+impl<B> Visitor for Encoder<'_, B>
+where
+    B: BufMut,
+{
+    fn field(&mut self, value: &Value, field_desc: &impl FieldDescriptorLike) {
+        value.encode_field(field_desc, self.buf)
+    }
+
+    fn map_key(&mut self, key: &MapKey, key_desc: &FieldDescriptor) {
+        key.encode_field(key_desc, self.buf)
+    }
+
+    fn unknown(&mut self, number: u32, unknowns: &[UnknownField]) {
+        for unknown in unknowns {
+            unknown.encode_field(number, self.buf);
+        }
+    }
+
+    fn encoded(&mut self, bytes: &[u8]) {
+        self.buf.put_slice(bytes);
+    }
+
+    fn start_message(&mut self, number: u32, group: bool) {
+        let len = self
+            .nested_lens
+            .next()
+            .expect("missing nested message length");
+        if group {
+            encoding::encode_key(number, WireType::StartGroup, self.buf);
+        } else {
+            encoding::encode_key(number, WireType::LengthDelimited, self.buf);
+            encoding::encode_varint(len as u64, self.buf);
+        }
+    }
+
+    fn end_message(&mut self, number: u32, group: bool) {
+        if group {
+            encoding::encode_key(number, WireType::EndGroup, self.buf);
+        }
     }
 }
 
@@ -348,6 +673,12 @@ impl Value {
             }
             (Value::List(values), field_kind) if field_desc.is_list() => {
                 if wire_type == WireType::LengthDelimited && field_desc.is_packable() {
+                    let len = packed_list_len(&field_kind, buf.chunk());
+                    if values.is_empty() {
+                        values.reserve_exact(len);
+                    } else {
+                        values.reserve(len);
+                    }
                     prost::encoding::merge_loop(values, buf, ctx, |values, buf, ctx| {
                         let mut value = Value::default_value(&field_kind);
                         value.merge_field(field_desc, field_kind.wire_type(), buf, ctx)?;
@@ -725,6 +1056,23 @@ where
     prost::encoding::key_len(number) + prost::encoding::encoded_len_varint(len as u64) + len
 }
 
+/// Returns the number of values in the packed list at the start of `buf`, so the list can be
+/// allocated with the right capacity up front. Returns 0 if the list is malformed or not
+/// contained in `buf`, in which case the list grows as it is decoded.
+pub(super) fn packed_list_len(kind: &Kind, mut buf: &[u8]) -> usize {
+    let len = match prost::encoding::decode_varint(&mut buf) {
+        Ok(len) if len <= buf.len() as u64 => len as usize,
+        _ => return 0,
+    };
+
+    match kind {
+        Kind::Double | Kind::Fixed64 | Kind::Sfixed64 => len / 8,
+        Kind::Float | Kind::Fixed32 | Kind::Sfixed32 => len / 4,
+        // Each varint ends with a byte which does not have the continuation bit set.
+        _ => buf[..len].iter().filter(|&&byte| byte < 0x80).count(),
+    }
+}
+
 fn from_sint32(value: i32) -> u32 {
     ((value << 1) ^ (value >> 31)) as u32
 }
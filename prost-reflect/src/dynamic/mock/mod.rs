@@ -0,0 +1,266 @@
+#[cfg(feature = "tonic")]
+mod service;
+
+use std::collections::HashMap;
+
+use prost::bytes::Bytes;
+
+#[cfg(feature = "tonic")]
+pub use self::service::MockService;
+use crate::{DynamicMessage, Kind, MessageDescriptor, MethodDescriptor, Value};
+
+/// The maximum depth of nested messages populated by [`MockStrategy::Random`]. Message fields
+/// below this depth are left unset, so recursive message types produce finite messages.
+const MAX_DEPTH: usize = 4;
+
+/// The maximum number of elements generated for list and map fields by [`MockStrategy::Random`].
+const MAX_LEN: u64 = 3;
+
+/// How [`MockResponses`] populates the messages it generates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockStrategy {
+    /// Generate the default instance of each message, with no fields set.
+    Default,
+    /// Generate messages with random values for every field, deterministically derived from the
+    /// seed.
+    ///
+    /// Lists and maps have up to three elements, one field of each oneof is set, and nested
+    /// messages are populated up to a fixed depth. Enum fields are set to one of their declared
+    /// values, and well-known types such as `google.protobuf.Timestamp` are given valid values,
+    /// so generated messages can be serialized using the JSON mapping.
+    Random {
+        /// The seed for the random number generator.
+        seed: u64,
+    },
+}
+
+/// A generator of mock response messages for methods, driven only by their descriptors.
+///
+/// Messages are generated according to a [`MockStrategy`], and fields set in a template message
+/// registered with [`with_template`][MockResponses::with_template] override the generated values
+/// for responses to that method.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+/// use prost_reflect::mock::{MockResponses, MockStrategy};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_desc = pool.get_message_by_name("package.MyMessage").unwrap();
+///
+/// let mut mock = MockResponses::new(MockStrategy::Random { seed: 42 });
+/// let message = mock.message(&message_desc);
+/// assert!(message.has_field_by_name("foo"));
+///
+/// // The same seed always generates the same messages.
+/// let mut other = MockResponses::new(MockStrategy::Random { seed: 42 });
+/// assert_eq!(other.message(&message_desc), message);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockResponses {
+    strategy: MockStrategy,
+    templates: HashMap<String, DynamicMessage>,
+    rng: Rng,
+}
+
+/// A small deterministic pseudo-random number generator, using the SplitMix64 algorithm.
+#[derive(Debug, Clone)]
+struct Rng {
+    state: u64,
+}
+
+impl Default for MockStrategy {
+    fn default() -> Self {
+        MockStrategy::Default
+    }
+}
+
+impl MockResponses {
+    /// Creates a generator which populates messages according to `strategy`.
+    pub fn new(strategy: MockStrategy) -> Self {
+        let seed = match strategy {
+            MockStrategy::Default => 0,
+            MockStrategy::Random { seed } => seed,
+        };
+        MockResponses {
+            strategy,
+            templates: HashMap::new(),
+            rng: Rng { state: seed },
+        }
+    }
+
+    /// Sets a template for the responses to a method.
+    ///
+    /// Each field which is set in `template` replaces the generated value of that field in
+    /// responses to `method`, while other fields are generated as usual. Fields without presence
+    /// which are set to their default value in the template do not override generated values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `template` is not of the output type of `method`.
+    pub fn with_template(mut self, method: &MethodDescriptor, template: DynamicMessage) -> Self {
+        assert_eq!(
+            &template.desc,
+            &method.output(),
+            "template does not match the output type of '{}'",
+            method.full_name()
+        );
+        self.templates
+            .insert(method.full_name().to_owned(), template);
+        self
+    }
+
+    /// Generates a response message for a method.
+    pub fn response(&mut self, method: &MethodDescriptor) -> DynamicMessage {
+        let mut message = self.message(&method.output());
+        if let Some(template) = self.templates.get(method.full_name()) {
+            for field in template.desc.fields() {
+                if template.has_field(&field) {
+                    message.set_field(&field, template.get_field(&field).into_owned());
+                }
+            }
+        }
+        message
+    }
+
+    /// Generates a message of the given type, without applying any template.
+    pub fn message(&mut self, desc: &MessageDescriptor) -> DynamicMessage {
+        match self.strategy {
+            MockStrategy::Default => DynamicMessage::new(desc.clone()),
+            MockStrategy::Random { .. } => self.random_message(desc, 0),
+        }
+    }
+
+    fn random_message(&mut self, desc: &MessageDescriptor, depth: usize) -> DynamicMessage {
+        let mut message = DynamicMessage::new(desc.clone());
+        match desc.full_name() {
+            "google.protobuf.Timestamp" => {
+                // Timestamps between 1970 and 2100.
+                message.set_field_by_number(1, Value::I64(self.rng.below(4_102_444_800) as i64));
+                message.set_field_by_number(2, Value::I32(self.rng.below(1_000_000_000) as i32));
+                return message;
+            }
+            "google.protobuf.Duration" => {
+                message.set_field_by_number(1, Value::I64(self.rng.below(1_000_000) as i64));
+                message.set_field_by_number(2, Value::I32(self.rng.below(1_000_000_000) as i32));
+                return message;
+            }
+            // The value of an `Any` must be a message of a type which can be resolved from its
+            // type URL, so it is left empty.
+            "google.protobuf.Any" => return message,
+            _ => (),
+        }
+
+        for oneof in desc.oneofs() {
+            let index = self.rng.below(oneof.fields().len() as u64) as usize;
+            let field = oneof.fields().nth(index).expect("index is in range");
+            if let Some(value) = self.random_field_value(&field.kind(), depth) {
+                message.set_field(&field, value);
+            }
+        }
+
+        for field in desc.fields() {
+            if field.containing_oneof().is_some() {
+                continue;
+            }
+
+            let value = if field.is_map() {
+                let kind = field.kind();
+                let entry = kind.as_message().expect("map fields have an entry message");
+                let key_kind = entry.map_entry_key_field().kind();
+                let value_kind = entry.map_entry_value_field().kind();
+                let len = self.rng.below(MAX_LEN + 1);
+                let mut map = HashMap::new();
+                for _ in 0..len {
+                    let key = self
+                        .random_field_value(&key_kind, depth)
+                        .and_then(Value::into_map_key)
+                        .expect("map keys are scalars");
+                    if let Some(value) = self.random_field_value(&value_kind, depth) {
+                        map.insert(key, value);
+                    }
+                }
+                Value::Map(map)
+            } else if field.is_list() {
+                let len = self.rng.below(MAX_LEN + 1);
+                Value::List(
+                    (0..len)
+                        .filter_map(|_| self.random_field_value(&field.kind(), depth))
+                        .collect(),
+                )
+            } else {
+                match self.random_field_value(&field.kind(), depth) {
+                    Some(value) => value,
+                    None => continue,
+                }
+            };
+            message.set_field(&field, value);
+        }
+        message
+    }
+
+    /// Generates a random value of the given kind, or returns `None` for messages nested too
+    /// deeply to be populated.
+    fn random_field_value(&mut self, kind: &Kind, depth: usize) -> Option<Value> {
+        Some(match kind {
+            Kind::Double => Value::F64(self.rng.float()),
+            Kind::Float => Value::F32(self.rng.float() as f32),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(self.rng.next() as i32),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(self.rng.next() as i64),
+            Kind::Uint32 | Kind::Fixed32 => Value::U32(self.rng.next() as u32),
+            Kind::Uint64 | Kind::Fixed64 => Value::U64(self.rng.next()),
+            Kind::Bool => Value::Bool(self.rng.next() & 1 == 1),
+            Kind::String => Value::String(self.rng.string()),
+            Kind::Bytes => {
+                let len = self.rng.below(9);
+                let bytes: Vec<u8> = (0..len).map(|_| self.rng.next() as u8).collect();
+                Value::Bytes(Bytes::from(bytes))
+            }
+            Kind::Enum(enum_) => {
+                let index = self.rng.below(enum_.values().len() as u64) as usize;
+                let value = enum_.values().nth(index).expect("index is in range");
+                Value::EnumNumber(value.number())
+            }
+            Kind::Message(message) => {
+                if depth >= MAX_DEPTH {
+                    return None;
+                }
+                Value::Message(self.random_message(message, depth + 1))
+            }
+        })
+    }
+}
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in the range `0..bound`, or `0` if `bound` is zero.
+    fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            0
+        } else {
+            self.next() % bound
+        }
+    }
+
+    /// Returns a finite value in the range `-1000.0..1000.0`.
+    fn float(&mut self) -> f64 {
+        let unit = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        unit * 2000.0 - 1000.0
+    }
+
+    /// Returns a string of between one and ten lowercase letters.
+    fn string(&mut self) -> String {
+        let len = 1 + self.below(10);
+        (0..len)
+            .map(|_| char::from(b'a' + self.below(26) as u8))
+            .collect()
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    convert::Infallible,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    vec,
+};
+
+use tonic1::{
+    body::BoxBody,
+    codegen::{empty_body, futures_core::Stream, http, Body, BoxFuture, Service, StdError},
+    server::{
+        ClientStreamingService, Grpc, ServerStreamingService, StreamingService, UnaryService,
+    },
+    Code, Request, Response, Status, Streaming,
+};
+
+use super::MockResponses;
+use crate::{grpc::DynamicCodec, DynamicMessage, MethodDescriptor, ServiceDescriptor};
+
+/// A tonic service which mocks every method of a [`ServiceDescriptor`], responding with messages
+/// generated by [`MockResponses`].
+///
+/// Unary and client streaming methods respond with a single message, after reading all request
+/// messages. Server streaming methods respond with a fixed number of messages, set by
+/// [`streaming_responses`][Self::streaming_responses], and bidirectional streaming methods respond
+/// to each request message with one response message. Requests for methods which are not part of
+/// the service are rejected with the code [`Unimplemented`][Code::Unimplemented].
+///
+/// The name of the service is only known at runtime, so this type does not implement tonic's
+/// `NamedService` trait. It can be served by routing requests with the path prefix
+/// `/<service>/` to it, where `<service>` is the full name of the service.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::DescriptorPool;
+/// use prost_reflect::mock::{MockResponses, MockService, MockStrategy};
+///
+/// # fn example(pool: DescriptorPool) {
+/// let service_desc = pool.get_service_by_name("package.MyService").unwrap();
+/// let service = MockService::new(service_desc, MockResponses::new(MockStrategy::Random { seed: 1 }))
+///     .streaming_responses(5);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct MockService {
+    service: ServiceDescriptor,
+    responses: Arc<Mutex<MockResponses>>,
+    streaming_responses: usize,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+}
+
+#[derive(Clone)]
+struct MockMethod {
+    method: MethodDescriptor,
+    responses: Arc<Mutex<MockResponses>>,
+    streaming_responses: usize,
+}
+
+struct ResponseStream {
+    responses: vec::IntoIter<DynamicMessage>,
+}
+
+struct ReplyStream {
+    method: MockMethod,
+    requests: Streaming<DynamicMessage>,
+}
+
+impl MockService {
+    /// Creates a service which mocks the methods of `service` using `responses`.
+    pub fn new(service: ServiceDescriptor, responses: MockResponses) -> Self {
+        MockService {
+            service,
+            responses: Arc::new(Mutex::new(responses)),
+            streaming_responses: 1,
+            max_decoding_message_size: None,
+            max_encoding_message_size: None,
+        }
+    }
+
+    /// Sets the number of messages sent in response to server streaming methods.
+    ///
+    /// The default value is `1`.
+    pub fn streaming_responses(mut self, count: usize) -> Self {
+        self.streaming_responses = count;
+        self
+    }
+
+    /// Limits the size of request messages accepted by the service.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the size of response messages sent by the service.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.max_encoding_message_size = Some(limit);
+        self
+    }
+}
+
+impl<B> Service<http::Request<B>> for MockService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let method_name = req
+            .uri()
+            .path()
+            .strip_prefix('/')
+            .and_then(|path| path.strip_prefix(self.service.full_name()))
+            .and_then(|path| path.strip_prefix('/'));
+        let method = match method_name
+            .and_then(|name| self.service.methods().find(|method| method.name() == name))
+        {
+            Some(method) => method,
+            None => {
+                return Box::pin(async move {
+                    Ok(http::Response::builder()
+                        .status(200)
+                        .header("grpc-status", (Code::Unimplemented as i32).to_string())
+                        .header("content-type", "application/grpc")
+                        .body(empty_body())
+                        .unwrap())
+                })
+            }
+        };
+
+        let mut grpc = Grpc::new(DynamicCodec::new(method.input())).apply_max_message_size_config(
+            self.max_decoding_message_size,
+            self.max_encoding_message_size,
+        );
+        let service = MockMethod {
+            method,
+            responses: self.responses.clone(),
+            streaming_responses: self.streaming_responses,
+        };
+        Box::pin(async move {
+            Ok(
+                match (
+                    service.method.is_client_streaming(),
+                    service.method.is_server_streaming(),
+                ) {
+                    (false, false) => grpc.unary(service, req).await,
+                    (false, true) => grpc.server_streaming(service, req).await,
+                    (true, false) => grpc.client_streaming(service, req).await,
+                    (true, true) => grpc.streaming(service, req).await,
+                },
+            )
+        })
+    }
+}
+
+impl MockMethod {
+    fn response(&self) -> DynamicMessage {
+        self.responses
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .response(&self.method)
+    }
+}
+
+impl UnaryService<DynamicMessage> for MockMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, _: Request<DynamicMessage>) -> Self::Future {
+        let response = self.response();
+        Box::pin(async move { Ok(Response::new(response)) })
+    }
+}
+
+impl ServerStreamingService<DynamicMessage> for MockMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = ResponseStream;
+    type Future = BoxFuture<Response<ResponseStream>, Status>;
+
+    fn call(&mut self, _: Request<DynamicMessage>) -> Self::Future {
+        let responses: Vec<_> = (0..self.streaming_responses)
+            .map(|_| self.response())
+            .collect();
+        Box::pin(async move {
+            Ok(Response::new(ResponseStream {
+                responses: responses.into_iter(),
+            }))
+        })
+    }
+}
+
+impl ClientStreamingService<DynamicMessage> for MockMethod {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<DynamicMessage>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let method = self.clone();
+        Box::pin(async move {
+            let mut requests = request.into_inner();
+            while requests.message().await?.is_some() {}
+            Ok(Response::new(method.response()))
+        })
+    }
+}
+
+impl StreamingService<DynamicMessage> for MockMethod {
+    type Response = DynamicMessage;
+    type ResponseStream = ReplyStream;
+    type Future = BoxFuture<Response<ReplyStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<DynamicMessage>>) -> Self::Future {
+        let stream = ReplyStream {
+            method: self.clone(),
+            requests: request.into_inner(),
+        };
+        Box::pin(async move { Ok(Response::new(stream)) })
+    }
+}
+
+impl Stream for ResponseStream {
+    type Item = Result<DynamicMessage, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.responses.next().map(Ok))
+    }
+}
+
+impl Stream for ReplyStream {
+    type Item = Result<DynamicMessage, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.requests).poll_next(cx) {
+            Poll::Ready(Some(Ok(_))) => Poll::Ready(Some(Ok(self.method.response()))),
+            Poll::Ready(Some(Err(status))) => Poll::Ready(Some(Err(status))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -6,18 +6,295 @@
 #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
 pub mod text_format;
 
+/// Utilities for working with [`FieldMask`][prost_types::FieldMask] values and dynamic messages.
+pub mod field_mask;
+
+/// Decoding and inspection of the protobuf wire format without a message descriptor.
+///
+/// This is useful for debugging when the descriptor for an encoded message is missing or does not
+/// match the data.
+pub mod wire;
+
+/// Encoding and decoding of the [gRPC message framing](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md#requests),
+/// for proxies and tools which handle the bodies of gRPC requests and responses directly.
+///
+/// Each message is preceded by a 1-byte flag indicating whether it is compressed, and its length
+/// as a 4-byte big-endian integer. Compressed messages are supported using gzip with the `gzip`
+/// feature, and using the zlib format with the `deflate` feature. The [gRPC-Web](https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-WEB.md)
+/// variant of the framing is supported with the `grpc-web` feature.
+pub mod framing;
+
+/// Framing for the [Confluent Schema Registry](https://docs.confluent.io/platform/current/schema-registry/fundamentals/serdes-develop/index.html#wire-format)
+/// wire format, used by Kafka producers and consumers of protobuf topics.
+///
+/// Each framed message starts with a magic byte of `0`, followed by the schema ID as a 4-byte
+/// big-endian integer and the message index path identifying the message type within the schema,
+/// followed by the encoded message. Schemas are looked up using a [`SchemaResolver`][confluent::SchemaResolver],
+/// which can be implemented to plug in a registry client.
+pub mod confluent;
+
+/// Conversions between messages of the common [`google.type`](https://github.com/googleapis/googleapis/tree/master/google/type)
+/// types and native Rust types.
+///
+/// These types are not part of the protobuf library, so the conversions operate on dynamic
+/// messages whose descriptors are named after them, such as `google.type.LatLng`, using the field
+/// numbers of the official definitions:
+///
+/// - `google.type.LatLng` to and from a `(latitude, longitude)` pair of degrees.
+/// - `google.type.Date` to and from `chrono::NaiveDate`, if the `chrono` feature is enabled.
+/// - `google.type.TimeOfDay` to and from `chrono::NaiveTime`, if the `chrono` feature is enabled.
+/// - `google.type.Money` to and from a currency code and a `rust_decimal::Decimal` amount, if the
+///   `rust-decimal` feature is enabled.
+pub mod google_type;
+
+/// Parsing and formatting of `google.protobuf.Duration` values as strings, such as `"3.5s"` or
+/// `"1h30m"`.
+pub mod duration;
+
+/// Streaming conversion between [JSON Lines](https://jsonlines.org/) and length-delimited protobuf
+/// messages.
+#[cfg(feature = "serde-json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub mod json_lines;
+
+/// Conversion between dynamic messages and [Apache Arrow](https://arrow.apache.org/) record
+/// batches.
+///
+/// Each message is converted to one row of a [`RecordBatch`][arrow_array::RecordBatch], with a
+/// column for each field of the message, named after the field. Types are mapped as follows:
+///
+/// | Protobuf type | Arrow type |
+/// |---|---|
+/// | `double`, `float` | `Float64`, `Float32` |
+/// | `int32`, `sint32`, `sfixed32` | `Int32` |
+/// | `int64`, `sint64`, `sfixed64` | `Int64` |
+/// | `uint32`, `fixed32` | `UInt32` |
+/// | `uint64`, `fixed64` | `UInt64` |
+/// | `bool`, `string`, `bytes` | `Boolean`, `Utf8`, `Binary` |
+/// | enum | `Int32`, containing the enum value number |
+/// | message | `Struct`, with a child for each field |
+/// | `repeated T` | `List<T>` |
+/// | `map<K, V>` | `Map<K, V>`, with entries sorted by key |
+/// | `google.protobuf.Timestamp` | `Timestamp(Nanosecond, "UTC")` |
+/// | `google.protobuf.Duration` | `Duration(Nanosecond)` |
+/// | wrapper types, such as `google.protobuf.Int32Value` | the type of the wrapped value |
+/// | `google.protobuf.Struct`, `Value` and `ListValue` | `Utf8`, containing the JSON representation |
+///
+/// Fields which support presence, such as message fields and members of a oneof, are nullable,
+/// and are null when unset. Other fields are not nullable, and hold their default value when
+/// unset. List items and map entries are never null. Extension fields and unknown fields are not
+/// converted. Recursive message types cannot be represented, and produce an error.
+#[cfg(feature = "arrow")]
+#[cfg_attr(docsrs, doc(cfg(feature = "arrow")))]
+pub mod arrow;
+
+/// Conversion between dynamic messages and [Apache Avro](https://avro.apache.org/) data.
+///
+/// Each message type is mapped to an Avro record with a field for each field of the message, in
+/// declaration order. Records and enums keep their protobuf names, with the package or enclosing
+/// message as their namespace, and a type which occurs more than once is referred to by its full
+/// name after its first definition, so recursive messages are supported. Types are mapped as
+/// follows:
+///
+/// | Protobuf type | Avro type |
+/// |---|---|
+/// | `double`, `float` | `double`, `float` |
+/// | `int32`, `sint32`, `sfixed32` | `int` |
+/// | `int64`, `sint64`, `sfixed64`, `uint32`, `fixed32` | `long` |
+/// | `uint64`, `fixed64` | `long`, with values above `i64::MAX` stored as negative numbers |
+/// | `bool`, `string`, `bytes` | `boolean`, `string`, `bytes` |
+/// | enum | `enum`, with a symbol for each value name, ordered by number |
+/// | message | `record` |
+/// | `repeated T` | `array` |
+/// | `map<K, V>` | `map`, with keys converted to strings as in the JSON mapping |
+///
+/// Fields which support presence, such as message fields and members of a oneof, are written as
+/// a union of `null` and their type, and are null when unset. Other fields have a default value
+/// in the schema, so that readers can add them when resolving an older schema. Well-known types
+/// are mapped to records like any other message. Extension fields and unknown fields are not
+/// converted, and enum values which are not defined by their enum type cannot be encoded.
+///
+/// Avro names must match `[A-Za-z_][A-Za-z0-9_]*`, and records and enums may not be named after
+/// a primitive type such as `string`. Deriving a schema for a message which uses such a name
+/// produces an error.
+#[cfg(feature = "avro")]
+#[cfg_attr(docsrs, doc(cfg(feature = "avro")))]
+pub mod avro;
+
+/// Integration with the [axum](https://docs.rs/axum) web framework, for accepting and returning
+/// dynamic messages in HTTP handlers.
+#[cfg(feature = "axum")]
+#[cfg_attr(docsrs, doc(cfg(feature = "axum")))]
+pub mod axum;
+
+/// Flattening dynamic messages into CSV rows, for tabular exports.
+///
+/// Each column is named by the dot-separated path of fields leading to a scalar value, such as
+/// `nested.int32`, and nested messages are flattened into the columns of their fields. Scalar
+/// values are written using their representation in the
+/// [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json). The
+/// [`RepeatedFieldPolicy`][csv::RepeatedFieldPolicy] set in [`CsvOptions`][csv::CsvOptions]
+/// chooses whether repeated fields are exploded into several rows, joined into a single cell, or
+/// spread over indexed columns. Recursive message types cannot be flattened.
+#[cfg(feature = "csv")]
+#[cfg_attr(docsrs, doc(cfg(feature = "csv")))]
+pub mod csv;
+
+/// Writing dynamic messages to [Apache Parquet](https://parquet.apache.org/) files.
+///
+/// Messages are converted to Arrow record batches as described in the [`arrow`](crate::arrow)
+/// module, and written with the [`parquet`](https://docs.rs/parquet) crate. The
+/// [`ParquetOptions`][parquet::ParquetOptions] type controls how maps, the repeated groups of
+/// lists and maps, and 64-bit integers are represented.
+#[cfg(feature = "parquet")]
+#[cfg_attr(docsrs, doc(cfg(feature = "parquet")))]
+pub mod parquet;
+
+/// Mock responses for gRPC methods, generated from their descriptors.
+///
+/// [`MockResponses`][mock::MockResponses] generates response messages for a method, either as
+/// default instances or populated with seeded random data, optionally overridden by a template
+/// message. With the `tonic` feature, [`MockService`][mock::MockService] serves generated responses
+/// for every method of a service, for use in contract tests.
+#[cfg(feature = "services")]
+#[cfg_attr(docsrs, doc(cfg(feature = "services")))]
+pub mod mock;
+
+/// Reading and writing streams of varint length-delimited messages over asynchronous I/O, using
+/// [tokio](https://docs.rs/tokio).
+///
+/// [`MessageStream`][length_delimited::MessageStream] is a [`Stream`][futures_core::Stream] of
+/// messages read from an [`AsyncRead`][tokio1::io::AsyncRead], and
+/// [`MessageSink`][length_delimited::MessageSink] is a [`Sink`][futures_sink::Sink] of messages
+/// written to an [`AsyncWrite`][tokio1::io::AsyncWrite]. Each message is preceded by its length
+/// as a varint, as written by [`Message::encode_length_delimited`][prost::Message::encode_length_delimited].
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod length_delimited;
+
+/// Invoking and serving gRPC methods with dynamic messages, using [tonic](https://docs.rs/tonic).
+#[cfg(feature = "tonic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub mod grpc;
+
+/// An implementation of the [gRPC server reflection](https://github.com/grpc/grpc/blob/master/doc/server-reflection.md)
+/// service for [tonic](https://docs.rs/tonic), backed by a [`DescriptorPool`][crate::DescriptorPool],
+/// and a client which builds a `DescriptorPool` from a remote server.
+#[cfg(feature = "tonic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub mod reflection;
+
+/// Serving gRPC methods as HTTP/JSON APIs, using the
+/// [`google.api.http`](https://github.com/googleapis/googleapis/blob/master/google/api/http.proto)
+/// annotations.
+///
+/// A [`Transcoder`][transcoding::Transcoder] matches the method and path of an HTTP request against
+/// the rules of each method, builds the request message from the path variables, query parameters
+/// and body, and serializes the response using the JSON mapping. Errors are returned as JSON
+/// `google.rpc.Status` messages, with the HTTP status code corresponding to the gRPC status. The
+/// [`openapi`][transcoding::openapi] module describes the resulting HTTP APIs as OpenAPI documents.
+#[cfg(feature = "transcoding")]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub mod transcoding;
+
+/// Evaluation of [CEL](https://github.com/google/cel-spec) expressions against dynamic messages.
+///
+/// A [`Program`][cel::Program] is compiled from the source of an expression, and evaluated with an
+/// [`Activation`][cel::Activation] providing its variables. Messages are accessed using their
+/// descriptors, so an expression such as `request.user.age >= 18` can be evaluated against any
+/// message with the corresponding fields.
+#[cfg(feature = "cel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cel")))]
+pub mod cel;
+
+/// Validation of messages using the [protovalidate](https://github.com/bufbuild/protovalidate)
+/// constraints declared in their `buf.validate` options.
+///
+/// A [`Validator`][protovalidate::Validator] checks a message against the standard constraints of
+/// its fields, oneofs and nested messages, and reports each unsatisfied constraint as a
+/// [`Violation`][protovalidate::Violation] with the path of the field and the id of the
+/// constraint.
+#[cfg(feature = "protovalidate")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protovalidate")))]
+pub mod protovalidate;
+
+mod any;
+#[cfg(feature = "bson")]
+mod bson;
+mod case;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod convert;
+mod datetime;
+mod decode;
+mod encode;
 mod fields;
+mod intercept;
+mod io;
+#[cfg(feature = "serde-json")]
+mod json_struct;
 mod message;
+mod null;
+mod registry;
+mod report;
+mod resolver;
+mod scalar_list;
+mod scan;
 #[cfg(feature = "serde")]
 mod serde;
+#[cfg(feature = "simd-json")]
+mod simd_json;
+mod sort;
 #[cfg(not(feature = "text-format"))]
 mod text_format;
 mod unknown;
-
-use std::{borrow::Cow, collections::HashMap, error::Error, fmt};
-
+mod usage;
+mod validate;
+mod verify;
+mod wrapper;
+#[cfg(feature = "xml")]
+mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+use std::{borrow::Cow, cmp::Ordering, collections::HashMap, error::Error, fmt};
+
+#[cfg(feature = "bson")]
+pub use self::bson::BsonError;
+#[cfg(feature = "cbor")]
+pub use self::cbor::CborError;
+#[cfg(feature = "serde-json")]
+pub use self::json_struct::JsonValueError;
+#[cfg(feature = "serde-json")]
+pub(crate) use self::serde::{deserialize_json, JsonParseError};
 #[cfg(feature = "serde")]
-pub use self::serde::{DeserializeOptions, SerializeOptions};
+pub use self::serde::{
+    Base64Encoding, DeserializeError, DeserializeOptions, FieldNameStrategy, OutOfRangePolicy,
+    SerializeOptions, TimestampPrecision, UnknownFieldCollector, UnknownJsonField,
+};
+#[cfg(feature = "serde-json")]
+pub use self::serde::{CanonicalJsonFormatter, FloatFormatter};
+#[cfg(feature = "xml")]
+pub use self::xml::{XmlError, XmlOptions};
+#[cfg(feature = "yaml")]
+pub use self::yaml::YamlError;
+
+pub use self::any::AnyError;
+pub use self::datetime::TimeError;
+pub use self::decode::{DecodeOptions, Utf8Policy};
+pub use self::encode::{EncodeError, EncodeOptions};
+pub use self::intercept::EncodeInterceptor;
+pub use self::io::SizePrefix;
+pub use self::registry::AnyRegistry;
+pub use self::report::{DecodeReport, ListEncoding};
+pub use self::resolver::TypeResolver;
+pub use self::scan::scan_field_numbers;
+pub use self::sort::SortError;
+pub use self::usage::{DeprecatedUsageCounter, UsageObserver};
+pub use self::validate::{InvalidMessageError, InvalidValue};
+pub use self::verify::{verify, VerifyError, VerifyOptions, WireStats};
+
+pub(crate) use self::resolver::resolve_any_type;
 
 pub(crate) use self::fields::FieldDescriptorLike;
 
@@ -26,7 +303,7 @@ use prost::{
     DecodeError, Message,
 };
 
-use self::fields::DynamicMessageFieldSet;
+use self::fields::{DynamicMessageFieldSet, ValueAndDescriptor};
 use crate::{
     descriptor::Kind, ExtensionDescriptor, FieldDescriptor, MessageDescriptor, ReflectMessage,
 };
@@ -35,6 +312,9 @@ use crate::{
 ///
 /// It wraps a [`MessageDescriptor`] and the [`Value`] for each field of the message, and implements
 /// [`Message`][`prost::Message`].
+///
+/// Cloning a `DynamicMessage` is cheap: the clone shares its fields with the original, and they
+/// are only copied when either message is modified.
 #[derive(Debug, Clone, PartialEq)]
 pub struct DynamicMessage {
     desc: MessageDescriptor,
@@ -108,6 +388,11 @@ pub enum SetFieldError {
 
 impl DynamicMessage {
     /// Creates a new, empty instance of [`DynamicMessage`] for the message type specified by the [`MessageDescriptor`].
+    ///
+    /// This does not allocate: an empty message only holds a reference to its descriptor, and
+    /// storage for fields is created when the first one is set. Default values of unset message
+    /// fields are created this way, so they are cheap to return. To check whether a message is
+    /// equivalent to an empty one, use [`is_default`][DynamicMessage::is_default].
     pub fn new(desc: MessageDescriptor) -> Self {
         DynamicMessage {
             fields: DynamicMessageFieldSet::default(),
@@ -118,6 +403,13 @@ impl DynamicMessage {
     /// Decodes an instance of the message type specified by the [`MessageDescriptor`] from the buffer and merges it into a
     /// new instance of [`DynamicMessage`].
     ///
+    /// Repeated scalar fields are accepted in both packed and unpacked encodings, regardless of
+    /// the value of [`FieldDescriptor::is_packed`]. See [`decode_with_report`][Self::decode_with_report]
+    /// to find out which encoding was used.
+    ///
+    /// If the descriptor's pool has [decode options][crate::DescriptorPool::set_decode_options] set,
+    /// they are applied as if by [`decode_with_options`][Self::decode_with_options].
+    ///
     /// # Examples
     ///
     /// ```
@@ -455,6 +747,57 @@ impl DynamicMessage {
         self.fields.clear(extension_desc)
     }
 
+    /// Returns `true` if every field of this message is unset or holds its default value.
+    ///
+    /// Unlike comparing with [`DynamicMessage::new`], fields which are set to their default value,
+    /// such as a proto2 `optional` field set to zero or a message field set to an empty message, are
+    /// treated as default. Non-empty lists and maps are never default, even if their elements are.
+    /// Unknown fields are ignored; use [`is_default_including_unknown`][Self::is_default_including_unknown]
+    /// to also require that there are none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// assert!(dynamic_message.is_default());
+    ///
+    /// dynamic_message.set_field_by_name("foo", Value::I32(0));
+    /// assert!(dynamic_message.is_default());
+    ///
+    /// dynamic_message.set_field_by_name("foo", Value::I32(150));
+    /// assert!(!dynamic_message.is_default());
+    /// ```
+    pub fn is_default(&self) -> bool {
+        self.is_default_inner(false)
+    }
+
+    /// Returns `true` if every field of this message is unset or holds its default value, and it
+    /// has no unknown fields, including in nested messages.
+    ///
+    /// See [`is_default`][Self::is_default] for more details.
+    pub fn is_default_including_unknown(&self) -> bool {
+        self.is_default_inner(true)
+    }
+
+    fn is_default_inner(&self, include_unknown: bool) -> bool {
+        self.fields.iter(&self.desc).all(|field| match field {
+            ValueAndDescriptor::Field(value, field_desc) => value.is_default_inner(
+                &field_desc.kind(),
+                field_desc.default_value(),
+                include_unknown,
+            ),
+            ValueAndDescriptor::Extension(value, extension_desc) => value.is_default_inner(
+                &extension_desc.kind(),
+                extension_desc.default_value(),
+                include_unknown,
+            ),
+            ValueAndDescriptor::Unknown(..) => !include_unknown,
+        })
+    }
+
     /// Merge a strongly-typed message into this one.
     ///
     /// The message should be compatible with the type specified by
@@ -533,7 +876,23 @@ impl Value {
     /// Returns the default value for the given protobuf type `kind`.
     ///
     /// Unlike [`default_value_for_field`](Value::default_value_for_field), this method does not
-    /// look at field cardinality, so it will never return a list or map.
+    /// look at field cardinality, so it will never return a list or map. For message types, an
+    /// empty message is returned. To get the default for a repeated or map field, use
+    /// [`default_value_for_field`](Value::default_value_for_field), which returns an empty list or map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DescriptorPool, DynamicMessage, Kind, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// assert_eq!(Value::default_value(&Kind::Int32), Value::I32(0));
+    /// assert_eq!(Value::default_value(&Kind::String), Value::String(String::new()));
+    /// assert_eq!(
+    ///     Value::default_value(&Kind::Message(message_descriptor.clone())),
+    ///     Value::Message(DynamicMessage::new(message_descriptor)),
+    /// );
+    /// ```
     pub fn default_value(kind: &Kind) -> Self {
         match kind {
             Kind::Message(desc) => Value::Message(DynamicMessage::new(desc.clone())),
@@ -551,18 +910,65 @@ impl Value {
     }
 
     /// Returns `true` if this is the default value for the given protobuf field.
+    ///
+    /// See [`is_default`][Value::is_default] for how messages, lists and maps are handled.
     pub fn is_default_for_field(&self, field_desc: &FieldDescriptor) -> bool {
-        *self == Value::default_value_for_field(field_desc)
+        if field_desc.is_list() || field_desc.is_map() {
+            self.is_empty_collection()
+        } else {
+            self.is_default_inner(&field_desc.kind(), field_desc.default_value(), false)
+        }
     }
 
     /// Returns `true` if this is the default value for the given protobuf extension field.
+    ///
+    /// See [`is_default`][Value::is_default] for how messages, lists and maps are handled.
     pub fn is_default_for_extension(&self, extension_desc: &ExtensionDescriptor) -> bool {
-        *self == Value::default_value_for_extension(extension_desc)
+        if extension_desc.is_list() || extension_desc.is_map() {
+            self.is_empty_collection()
+        } else {
+            self.is_default_inner(
+                &extension_desc.kind(),
+                extension_desc.default_value(),
+                false,
+            )
+        }
     }
 
     /// Returns `true` if this is the default value for the given protobuf type `kind`.
+    ///
+    /// A message value is default if it has the message type of `kind`, and
+    /// [`DynamicMessage::is_default`] returns `true` for it. Empty lists and maps are also treated
+    /// as default.
     pub fn is_default(&self, kind: &Kind) -> bool {
-        *self == Value::default_value(kind)
+        self.is_default_inner(kind, None, false)
+    }
+
+    fn is_empty_collection(&self) -> bool {
+        match self {
+            Value::List(list) => list.is_empty(),
+            Value::Map(map) => map.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this is the default value for `kind`, or `default` if it is set.
+    fn is_default_inner(
+        &self,
+        kind: &Kind,
+        default: Option<&Value>,
+        include_unknown: bool,
+    ) -> bool {
+        match (self, kind) {
+            (Value::Message(message), Kind::Message(desc)) => {
+                message.desc == *desc && message.is_default_inner(include_unknown)
+            }
+            (Value::List(_) | Value::Map(_), _) => self.is_empty_collection(),
+            _ => match default {
+                Some(default) => self == default,
+                None => *self == Value::default_value(kind),
+            },
+        }
     }
 
     /// Returns `true` if this value can be set for a given field.
@@ -608,6 +1014,15 @@ impl Value {
     ///
     /// Unlike [`is_valid_for_field`](Value::is_valid_for_field), this method does not
     /// look at field cardinality, so it will never return `true` for lists or maps.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{Kind, Value};
+    /// assert!(Value::I32(5).is_valid(&Kind::Sint32));
+    /// assert!(!Value::I32(5).is_valid(&Kind::Int64));
+    /// assert!(!Value::List(vec![Value::I32(5)]).is_valid(&Kind::Int32));
+    /// ```
     pub fn is_valid(&self, kind: &Kind) -> bool {
         matches!(
             (self, kind),
@@ -625,6 +1040,72 @@ impl Value {
         )
     }
 
+    /// Compares this value with `other`, interpreting both as values of the protobuf type `kind`.
+    ///
+    /// Numeric values are promoted before comparing, so for example a `Value::I32` and a
+    /// `Value::I64` can be compared as `int64` values. Strings and bytes are compared bytewise,
+    /// and enums are compared by number.
+    ///
+    /// Returns `None` if the values are not comparable: if either value is not a scalar of a type
+    /// compatible with `kind`, if `kind` is a message type, or if a floating point value is `NaN`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::cmp::Ordering;
+    /// # use prost_reflect::{Kind, Value};
+    /// assert_eq!(Value::I32(-1).compare(&Value::I64(5), &Kind::Int64), Some(Ordering::Less));
+    /// assert_eq!(Value::U64(u64::MAX).compare(&Value::I64(-1), &Kind::Uint64), Some(Ordering::Greater));
+    /// assert_eq!(Value::String("b".to_owned()).compare(&Value::String("a".to_owned()), &Kind::String), Some(Ordering::Greater));
+    /// assert_eq!(Value::F64(f64::NAN).compare(&Value::F64(1.0), &Kind::Double), None);
+    /// ```
+    pub fn compare(&self, other: &Value, kind: &Kind) -> Option<Ordering> {
+        match kind {
+            Kind::Double | Kind::Float => {
+                let lhs = self.as_promoted_float()?;
+                let rhs = other.as_promoted_float()?;
+                lhs.partial_cmp(&rhs)
+            }
+            Kind::Int32
+            | Kind::Int64
+            | Kind::Uint32
+            | Kind::Uint64
+            | Kind::Sint32
+            | Kind::Sint64
+            | Kind::Fixed32
+            | Kind::Fixed64
+            | Kind::Sfixed32
+            | Kind::Sfixed64 => {
+                let lhs = self.as_promoted_integer()?;
+                let rhs = other.as_promoted_integer()?;
+                Some(lhs.cmp(&rhs))
+            }
+            Kind::Bool => Some(self.as_bool()?.cmp(&other.as_bool()?)),
+            Kind::String => Some(self.as_str()?.as_bytes().cmp(other.as_str()?.as_bytes())),
+            Kind::Bytes => Some(self.as_bytes()?.cmp(other.as_bytes()?)),
+            Kind::Enum(_) => Some(self.as_enum_number()?.cmp(&other.as_enum_number()?)),
+            Kind::Message(_) => None,
+        }
+    }
+
+    fn as_promoted_integer(&self) -> Option<i128> {
+        match *self {
+            Value::I32(value) => Some(value.into()),
+            Value::I64(value) => Some(value.into()),
+            Value::U32(value) => Some(value.into()),
+            Value::U64(value) => Some(value.into()),
+            _ => None,
+        }
+    }
+
+    fn as_promoted_float(&self) -> Option<f64> {
+        match *self {
+            Value::F32(value) => Some(value.into()),
+            Value::F64(value) => Some(value),
+            _ => None,
+        }
+    }
+
     /// Returns the value if it is a `Value::Bool`, or `None` if it is any other type.
     pub fn as_bool(&self) -> Option<bool> {
         match *self {
@@ -854,6 +1335,25 @@ impl Value {
             _ => None,
         }
     }
+
+    /// Returns an object which formats this value using the protobuf text format, interpreting
+    /// it as a value of type `kind`.
+    ///
+    /// Unlike the [`Display`][fmt::Display] implementation for `Value`, this prints the names of
+    /// enum values, including those nested in lists and maps, instead of their numbers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DescriptorPool, Kind, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// let kind = Kind::Enum(pool.get_enum_by_name("package.MyEnum").unwrap());
+    /// assert_eq!(Value::EnumNumber(1).display_with_kind(&kind).to_string(), "FOO");
+    /// assert_eq!(Value::List(vec![Value::EnumNumber(2), Value::EnumNumber(5)]).display_with_kind(&kind).to_string(), "[BAR,5]");
+    /// ```
+    pub fn display_with_kind<'a>(&'a self, kind: &'a Kind) -> impl fmt::Display + 'a {
+        DisplayWithKind { value: self, kind }
+    }
 }
 
 impl MapKey {
@@ -1095,8 +1595,145 @@ impl fmt::Display for Value {
     }
 }
 
+impl fmt::Display for MapKey {
+    /// Formats this map key using the protobuf text format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::MapKey;
+    /// assert_eq!(format!("{}", MapKey::I64(-5)), "-5");
+    /// assert_eq!(format!("{}", MapKey::String("hello\n".to_owned())), "\"hello\\n\"");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        text_format::Writer::new(text_format::FormatOptions::new(), f).fmt_map_key(self)
+    }
+}
+
+struct DisplayWithKind<'a> {
+    value: &'a Value,
+    kind: &'a Kind,
+}
+
+impl fmt::Display for DisplayWithKind<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        text_format::Writer::new(text_format::FormatOptions::new().pretty(f.alternate()), f)
+            .fmt_value(self.value, Some(self.kind))
+    }
+}
+
 #[test]
 fn type_sizes() {
     assert_eq!(std::mem::size_of::<DynamicMessage>(), 40);
     assert_eq!(std::mem::size_of::<Value>(), 56);
 }
+
+#[test]
+fn clone_on_write() {
+    use prost_types::{FileDescriptorProto, FileOptions};
+
+    let message = FileDescriptorProto {
+        name: Some("a.proto".to_owned()),
+        options: Some(FileOptions {
+            java_package: Some("a".to_owned()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+    .transcode_to_dynamic();
+
+    let mut clone = message.clone();
+    assert_eq!(clone, message);
+
+    clone
+        .get_field_by_name_mut("options")
+        .unwrap()
+        .as_message_mut()
+        .unwrap()
+        .set_field_by_name("java_package", Value::String("b".to_owned()));
+    assert_ne!(clone, message);
+
+    let java_package = |message: &DynamicMessage| {
+        message
+            .get_field_by_name("options")
+            .unwrap()
+            .as_message()
+            .unwrap()
+            .get_field_by_name("java_package")
+            .unwrap()
+            .into_owned()
+    };
+    assert_eq!(java_package(&message), Value::String("a".to_owned()));
+    assert_eq!(java_package(&clone), Value::String("b".to_owned()));
+
+    clone.clear_field_by_name("name");
+    assert_eq!(
+        message.get_field_by_name("name").unwrap().as_str(),
+        Some("a.proto")
+    );
+}
+
+#[test]
+fn compare_values() {
+    use prost::bytes::Bytes;
+
+    assert_eq!(
+        Value::U32(u32::MAX).compare(&Value::I32(-1), &Kind::Int64),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Value::F32(1.5).compare(&Value::F64(1.5), &Kind::Double),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(
+        Value::Bytes(Bytes::from_static(b"\x00\xff"))
+            .compare(&Value::Bytes(Bytes::from_static(b"\x01")), &Kind::Bytes),
+        Some(Ordering::Less)
+    );
+    assert_eq!(
+        Value::String("é".to_owned()).compare(&Value::String("z".to_owned()), &Kind::String),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(
+        Value::Bool(true).compare(&Value::Bool(false), &Kind::Bool),
+        Some(Ordering::Greater)
+    );
+    assert_eq!(Value::I32(1).compare(&Value::F64(1.0), &Kind::Int32), None);
+    assert_eq!(
+        Value::String("1".to_owned()).compare(&Value::I32(1), &Kind::Int32),
+        None
+    );
+}
+
+#[test]
+fn deeply_nested() {
+    use prost::encoding::encode_varint;
+
+    const DEPTH: usize = 100_000;
+
+    // A google.protobuf.Value containing DEPTH nested lists, built back to front.
+    let mut bytes = Vec::new();
+    for _ in 0..DEPTH {
+        for tag in [0x0a, 0x32] {
+            let mut len = Vec::new();
+            encode_varint(bytes.len() as u64, &mut len);
+            bytes.extend(len.into_iter().rev());
+            bytes.push(tag);
+        }
+    }
+    bytes.reverse();
+    let bytes = Bytes::from(bytes);
+
+    let desc = prost_types::Value::default().descriptor();
+    for options in [
+        DecodeOptions::new().max_depth(u32::MAX),
+        DecodeOptions::new().max_depth(u32::MAX).lazy_messages(true),
+    ] {
+        let message =
+            DynamicMessage::decode_with_options(desc.clone(), bytes.clone(), &options).unwrap();
+        message.resolve().unwrap();
+        assert_eq!(message.encoded_len(), bytes.len());
+        assert_eq!(message.clone().encode_to_vec(), bytes);
+        drop(message);
+    }
+}
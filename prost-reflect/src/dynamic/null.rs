@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, Value};
+
+const VALUE_NAME: &str = "google.protobuf.Value";
+const NULL_VALUE_NAME: &str = "google.protobuf.NullValue";
+const NULL_VALUE_NUMBER: u32 = 1;
+const LIST_VALUE_NUMBER: u32 = 6;
+
+impl Value {
+    /// Returns `true` if this value is a `google.protobuf.Value` message representing JSON `null`.
+    ///
+    /// This is the case if its `null_value` field is set, or if none of its fields are set, since
+    /// both are serialized as `null` in JSON. To also check for values of the
+    /// `google.protobuf.NullValue` enum, use [`is_null_for_field`][Value::is_null_for_field].
+    pub fn is_null(&self) -> bool {
+        match self {
+            Value::Message(message) => {
+                message.desc.full_name() == VALUE_NAME
+                    && !(NULL_VALUE_NUMBER + 1..=LIST_VALUE_NUMBER)
+                        .any(|number| message.has_field_by_number(number))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this value represents JSON `null` for the given field.
+    ///
+    /// This is the case if [`is_null`][Value::is_null] returns `true`, or if the field has type
+    /// `google.protobuf.NullValue`, which has `NULL_VALUE` as its only value.
+    pub fn is_null_for_field(&self, field_desc: &FieldDescriptor) -> bool {
+        self.is_null() || (!field_desc.is_list() && is_null_value_enum(&field_desc.kind()))
+    }
+
+    /// Returns the value representing JSON `null` for the given field, or `None` if the field
+    /// can only represent `null` by being cleared.
+    ///
+    /// For a singular field of type `google.protobuf.Value`, this is a message with its
+    /// `null_value` field set, and for a singular field of type `google.protobuf.NullValue`, it is
+    /// `NULL_VALUE`.
+    pub fn null_for_field(field_desc: &FieldDescriptor) -> Option<Value> {
+        if field_desc.is_list() || field_desc.is_map() {
+            return None;
+        }
+
+        match field_desc.kind() {
+            Kind::Message(desc) if desc.full_name() == VALUE_NAME => {
+                let mut message = DynamicMessage::new(desc);
+                message.set_field_by_number(NULL_VALUE_NUMBER, Value::EnumNumber(0));
+                Some(Value::Message(message))
+            }
+            kind if is_null_value_enum(&kind) => Some(Value::EnumNumber(0)),
+            _ => None,
+        }
+    }
+}
+
+impl DynamicMessage {
+    /// Gets the value of the given field, or `None` if it represents JSON `null`.
+    ///
+    /// `None` is returned if the field supports presence and is not set, or if its value is
+    /// `null` according to [`Value::is_null_for_field`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+    /// let mut message = DynamicMessage::new(prost_types::Struct::default().descriptor());
+    /// let value_desc = message.descriptor().parent_pool().get_message_by_name("google.protobuf.Value").unwrap();
+    /// let mut value = DynamicMessage::new(value_desc.clone());
+    /// let bool_value = value_desc.get_field_by_name("bool_value").unwrap();
+    ///
+    /// value.set_field_nullable(&bool_value, Some(Value::Bool(true)));
+    /// assert_eq!(value.get_field_nullable(&bool_value).as_deref(), Some(&Value::Bool(true)));
+    /// assert!(!Value::Message(value.clone()).is_null());
+    ///
+    /// value.set_field_nullable(&bool_value, None);
+    /// assert_eq!(value.get_field_nullable(&bool_value), None);
+    /// assert!(Value::Message(value).is_null());
+    /// ```
+    pub fn get_field_nullable(&self, field_desc: &FieldDescriptor) -> Option<Cow<'_, Value>> {
+        if field_desc.supports_presence() && !self.has_field(field_desc) {
+            return None;
+        }
+
+        let value = self.get_field(field_desc);
+        if value.is_null_for_field(field_desc) {
+            None
+        } else {
+            Some(value)
+        }
+    }
+
+    /// Sets the value of the given field, or sets it to JSON `null` if `value` is `None`.
+    ///
+    /// If `value` is `None` and [`Value::null_for_field`] returns a `null` value for the field,
+    /// the field is set to it, otherwise the field is cleared.
+    ///
+    /// # Panics
+    ///
+    /// This method may panic if the value type is not compatible with the field type, as defined
+    /// by [`Value::is_valid_for_field`].
+    pub fn set_field_nullable(&mut self, field_desc: &FieldDescriptor, value: Option<Value>) {
+        match value.or_else(|| Value::null_for_field(field_desc)) {
+            Some(value) => self.set_field(field_desc, value),
+            None => self.clear_field(field_desc),
+        }
+    }
+
+    /// Gets the value of the field with the given name, or `None` if it represents JSON `null`.
+    ///
+    /// If the message has no field with the given name, `None` is returned.
+    ///
+    /// See [`get_field_nullable`][Self::get_field_nullable] for more details.
+    pub fn get_field_by_name_nullable(&self, name: &str) -> Option<Cow<'_, Value>> {
+        self.desc
+            .get_field_by_name(name)
+            .and_then(|field_desc| self.get_field_nullable(&field_desc))
+    }
+
+    /// Sets the value of the field with the given name, or sets it to JSON `null` if `value` is
+    /// `None`.
+    ///
+    /// If no field with the given name exists, this method does nothing.
+    ///
+    /// See [`set_field_nullable`][Self::set_field_nullable] for more details.
+    pub fn set_field_by_name_nullable(&mut self, name: &str, value: Option<Value>) {
+        if let Some(field_desc) = self.desc.get_field_by_name(name) {
+            self.set_field_nullable(&field_desc, value)
+        }
+    }
+}
+
+fn is_null_value_enum(kind: &Kind) -> bool {
+    matches!(kind, Kind::Enum(desc) if desc.full_name() == NULL_VALUE_NAME)
+}
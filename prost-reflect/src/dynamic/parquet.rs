@@ -0,0 +1,400 @@
+use std::{fmt, io::Write, mem, sync::Arc};
+
+use arrow_array::{
+    cast::AsArray,
+    types::{DurationNanosecondType, Int64Type, UInt64Type},
+    Array, ArrayRef, Int64Array, ListArray, MapArray, RecordBatch, RecordBatchOptions, StringArray,
+    StructArray,
+};
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Fields, Schema, SchemaRef};
+use parquet1::{arrow::ArrowWriter, errors::ParquetError, file::properties::WriterProperties};
+
+use crate::{arrow, DynamicMessage, MessageDescriptor, ReflectMessage};
+
+/// Options to control how messages are mapped to Parquet columns.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "parquet")))]
+pub struct ParquetOptions {
+    maps_as_lists: bool,
+    compliant_nested_types: bool,
+    stringify_64_bit_integers: bool,
+    batch_size: usize,
+}
+
+/// Writes messages of a single type to a Parquet file.
+///
+/// Messages are buffered, and converted to an Arrow record batch once
+/// [`batch_size`][ParquetOptions::batch_size] messages have been written. The file is not valid
+/// until [`close`][ParquetWriter::close] is called to write the footer.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+/// use prost_reflect::parquet::{ParquetOptions, ParquetWriter};
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let mut message = DynamicMessage::new(message_descriptor.clone());
+/// message.set_field_by_name("type_url", Value::String("foo".to_owned()));
+///
+/// let mut writer =
+///     ParquetWriter::try_new(Vec::new(), message_descriptor, &ParquetOptions::new(), None).unwrap();
+/// writer.write(&message).unwrap();
+/// let file = writer.close().unwrap();
+/// assert!(file.starts_with(b"PAR1"));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "parquet")))]
+pub struct ParquetWriter<W: Write + Send> {
+    desc: MessageDescriptor,
+    options: ParquetOptions,
+    schema: SchemaRef,
+    writer: ArrowWriter<W>,
+    buffer: Vec<DynamicMessage>,
+}
+
+/// Returns the schema of a Parquet file containing messages of type `desc`, as an Arrow schema.
+///
+/// The schema is derived from the one returned by [`arrow::schema`], with the following changes:
+///
+/// * Fields of type `google.protobuf.Duration` are stored as an `Int64` count of nanoseconds,
+///   since Parquet has no duration type.
+/// * Fields containing messages with no fields, such as `google.protobuf.Empty`, are omitted,
+///   since Parquet cannot represent empty groups.
+/// * Maps, list items and 64-bit integers are represented as configured by `options`.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::DescriptorPool;
+/// use arrow_schema::DataType;
+/// use prost_reflect::parquet::ParquetOptions;
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let schema = prost_reflect::parquet::schema(&message_descriptor, &ParquetOptions::new()).unwrap();
+/// assert_eq!(schema.field(0).name(), "type_url");
+/// assert_eq!(schema.field(1).data_type(), &DataType::Binary);
+///
+/// let empty = pool.get_message_by_name("google.protobuf.Empty").unwrap();
+/// assert!(prost_reflect::parquet::schema(&empty, &ParquetOptions::new()).unwrap().fields().is_empty());
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "parquet")))]
+pub fn schema(desc: &MessageDescriptor, options: &ParquetOptions) -> Result<Schema, ArrowError> {
+    let schema = arrow::schema(desc)?;
+    Ok(Schema::new(convert_fields(schema.fields(), options)))
+}
+
+impl ParquetOptions {
+    /// Creates a new instance of [`ParquetOptions`], with the default options chosen to conform to
+    /// the Parquet specification.
+    pub const fn new() -> Self {
+        ParquetOptions {
+            maps_as_lists: false,
+            compliant_nested_types: true,
+            stringify_64_bit_integers: false,
+            batch_size: 1024,
+        }
+    }
+
+    /// Whether to write map fields as a list of key-value structs, rather than using the Parquet
+    /// `MAP` type.
+    ///
+    /// This is useful for readers which do not support maps. The default value is `false`.
+    pub const fn maps_as_lists(mut self, yes: bool) -> Self {
+        self.maps_as_lists = yes;
+        self
+    }
+
+    /// Whether to name the repeated groups of lists and maps as required by the Parquet
+    /// specification.
+    ///
+    /// If `true`, the repeated group of a list contains a field named `element`, and the repeated
+    /// group of a map is named `key_value`. If `false`, the names used by Arrow (`item` and
+    /// `entries`) are kept. The default value is `true`.
+    pub const fn compliant_nested_types(mut self, yes: bool) -> Self {
+        self.compliant_nested_types = yes;
+        self
+    }
+
+    /// Whether to store 64-bit integer fields as strings, rather than `INT64` columns.
+    ///
+    /// This is useful for readers which do not support the unsigned `UINT_64` annotation used
+    /// for `uint64` and `fixed64` fields. The default value is `false`.
+    pub const fn stringify_64_bit_integers(mut self, yes: bool) -> Self {
+        self.stringify_64_bit_integers = yes;
+        self
+    }
+
+    /// The number of messages to buffer in a [`ParquetWriter`] before converting them to an Arrow
+    /// record batch.
+    ///
+    /// The default value is `1024`.
+    pub const fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size;
+        self
+    }
+
+    fn list_item_name(&self) -> &'static str {
+        if self.compliant_nested_types {
+            "element"
+        } else {
+            "item"
+        }
+    }
+
+    fn map_entries_name(&self) -> &'static str {
+        if self.compliant_nested_types {
+            "key_value"
+        } else {
+            "entries"
+        }
+    }
+}
+
+impl Default for ParquetOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Write + Send> ParquetWriter<W> {
+    /// Creates a new writer for messages of type `desc`, writing to `writer`.
+    ///
+    /// The Parquet file is written with the given [`WriterProperties`], or the defaults if `None`
+    /// is passed. Returns an error if `desc` cannot be represented in a Parquet schema.
+    pub fn try_new(
+        writer: W,
+        desc: MessageDescriptor,
+        options: &ParquetOptions,
+        properties: Option<WriterProperties>,
+    ) -> Result<Self, ParquetError> {
+        let schema = Arc::new(schema(&desc, options)?);
+        let writer = ArrowWriter::try_new(writer, schema.clone(), properties)?;
+        Ok(ParquetWriter {
+            desc,
+            options: options.clone(),
+            schema,
+            writer,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Returns the Arrow schema of the file being written.
+    pub fn schema(&self) -> &SchemaRef {
+        &self.schema
+    }
+
+    /// Appends a message to the file.
+    ///
+    /// Returns an error if the message is not of the type given to
+    /// [`try_new`][ParquetWriter::try_new].
+    pub fn write(&mut self, message: &DynamicMessage) -> Result<(), ParquetError> {
+        if message.descriptor() != self.desc {
+            return Err(ParquetError::General(format!(
+                "expected messages of type '{}', but found '{}'",
+                self.desc.full_name(),
+                message.descriptor().full_name()
+            )));
+        }
+
+        self.buffer.push(message.clone());
+        if self.buffer.len() >= self.options.batch_size {
+            self.write_buffer()?;
+        }
+        Ok(())
+    }
+
+    /// Appends all messages from an iterator to the file.
+    pub fn write_all<'a>(
+        &mut self,
+        messages: impl IntoIterator<Item = &'a DynamicMessage>,
+    ) -> Result<(), ParquetError> {
+        for message in messages {
+            self.write(message)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all buffered messages to a new row group.
+    pub fn flush(&mut self) -> Result<(), ParquetError> {
+        self.write_buffer()?;
+        self.writer.flush()
+    }
+
+    /// Writes all buffered messages and the file footer, and returns the underlying writer.
+    pub fn close(mut self) -> Result<W, ParquetError> {
+        self.write_buffer()?;
+        self.writer.into_inner()
+    }
+
+    fn write_buffer(&mut self) -> Result<(), ParquetError> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let messages = mem::take(&mut self.buffer);
+        let batch = arrow::to_record_batch(&self.desc, &messages)?;
+        let columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .zip(batch.columns())
+            .filter_map(|(field, column)| {
+                self.schema
+                    .field_with_name(field.name())
+                    .ok()
+                    .map(|target| convert_array(column, field.data_type(), target.data_type()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let batch = RecordBatch::try_new_with_options(
+            self.schema.clone(),
+            columns,
+            &RecordBatchOptions::new().with_row_count(Some(messages.len())),
+        )?;
+        self.writer.write(&batch)
+    }
+}
+
+impl<W: Write + Send> fmt::Debug for ParquetWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ParquetWriter")
+            .field("desc", &self.desc)
+            .field("options", &self.options)
+            .field("writer", &self.writer)
+            .field("buffered", &self.buffer.len())
+            .finish()
+    }
+}
+
+fn convert_fields(fields: &Fields, options: &ParquetOptions) -> Vec<Field> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            convert_data_type(field.data_type(), options)
+                .map(|data_type| Field::new(field.name(), data_type, field.is_nullable()))
+        })
+        .collect()
+}
+
+/// Returns the Parquet representation of an Arrow data type, or `None` if it is an empty struct.
+fn convert_data_type(data_type: &DataType, options: &ParquetOptions) -> Option<DataType> {
+    Some(match data_type {
+        DataType::Int64 | DataType::UInt64 if options.stringify_64_bit_integers => DataType::Utf8,
+        DataType::Duration(_) => DataType::Int64,
+        DataType::Struct(fields) => {
+            let fields = convert_fields(fields, options);
+            if fields.is_empty() {
+                return None;
+            }
+            DataType::Struct(fields.into())
+        }
+        DataType::List(item) => {
+            let item_type = convert_data_type(item.data_type(), options)?;
+            DataType::List(Arc::new(Field::new(
+                options.list_item_name(),
+                item_type,
+                item.is_nullable(),
+            )))
+        }
+        DataType::Map(entries, sorted) => {
+            let entries_type = convert_data_type(entries.data_type(), options)?;
+            if options.maps_as_lists {
+                DataType::List(Arc::new(Field::new(
+                    options.list_item_name(),
+                    entries_type,
+                    false,
+                )))
+            } else {
+                DataType::Map(
+                    Arc::new(Field::new(options.map_entries_name(), entries_type, false)),
+                    *sorted,
+                )
+            }
+        }
+        data_type => data_type.clone(),
+    })
+}
+
+/// Converts an array produced by [`arrow::to_record_batch`] to its Parquet representation.
+fn convert_array(array: &ArrayRef, from: &DataType, to: &DataType) -> Result<ArrayRef, ArrowError> {
+    Ok(match (from, to) {
+        (DataType::Int64, DataType::Utf8) => Arc::new(
+            array
+                .as_primitive::<Int64Type>()
+                .iter()
+                .map(|value| value.map(|value| value.to_string()))
+                .collect::<StringArray>(),
+        ),
+        (DataType::UInt64, DataType::Utf8) => Arc::new(
+            array
+                .as_primitive::<UInt64Type>()
+                .iter()
+                .map(|value| value.map(|value| value.to_string()))
+                .collect::<StringArray>(),
+        ),
+        (DataType::Duration(_), DataType::Int64) => Arc::new(Int64Array::new(
+            array
+                .as_primitive::<DurationNanosecondType>()
+                .values()
+                .clone(),
+            array.nulls().cloned(),
+        )),
+        (DataType::Struct(_), DataType::Struct(to_fields)) => {
+            Arc::new(convert_struct(array.as_struct(), to_fields)?)
+        }
+        (DataType::List(from_item), DataType::List(to_item)) => {
+            let list = array.as_list::<i32>();
+            let values = convert_array(list.values(), from_item.data_type(), to_item.data_type())?;
+            Arc::new(ListArray::try_new(
+                to_item.clone(),
+                list.offsets().clone(),
+                values,
+                list.nulls().cloned(),
+            )?)
+        }
+        (DataType::Map(..), DataType::List(to_item)) => {
+            let map = array.as_map();
+            let entries = convert_struct(map.entries(), struct_fields(to_item))?;
+            Arc::new(ListArray::try_new(
+                to_item.clone(),
+                map.offsets().clone(),
+                Arc::new(entries),
+                map.nulls().cloned(),
+            )?)
+        }
+        (DataType::Map(..), DataType::Map(to_entries, sorted)) => {
+            let map = array.as_map();
+            let entries = convert_struct(map.entries(), struct_fields(to_entries))?;
+            Arc::new(MapArray::try_new(
+                to_entries.clone(),
+                map.offsets().clone(),
+                entries,
+                map.nulls().cloned(),
+                *sorted,
+            )?)
+        }
+        _ => array.clone(),
+    })
+}
+
+fn convert_struct(array: &StructArray, to_fields: &Fields) -> Result<StructArray, ArrowError> {
+    let columns = array
+        .fields()
+        .iter()
+        .zip(array.columns())
+        .filter_map(|(field, column)| {
+            to_fields
+                .find(field.name())
+                .map(|(_, target)| convert_array(column, field.data_type(), target.data_type()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    StructArray::try_new(to_fields.clone(), columns, array.nulls().cloned())
+}
+
+fn struct_fields(field: &FieldRef) -> &Fields {
+    match field.data_type() {
+        DataType::Struct(fields) => fields,
+        _ => unreachable!("map entries should be a struct"),
+    }
+}
@@ -0,0 +1,104 @@
+//! The variables and extension functions available to the custom constraints of
+//! `buf.validate`, which are written as CEL expressions.
+//!
+//! These follow the
+//! [protovalidate CEL library](https://github.com/bufbuild/protovalidate/blob/main/docs/cel.md):
+//! `this` refers to the value being validated, `now` to the current time, and functions such as
+//! `isEmail()` check the same formats as the standard string rules.
+
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+
+use super::strings;
+use crate::cel::{Activation, CelValue};
+
+static EXTENSIONS: Lazy<Activation> = Lazy::new(|| {
+    let mut activation = Activation::new();
+    add_string_check(&mut activation, "isEmail", strings::is_email);
+    add_string_check(&mut activation, "isHostname", strings::is_hostname);
+    add_string_check(&mut activation, "isUri", strings::is_uri);
+    add_string_check(&mut activation, "isUriRef", strings::is_uri_ref);
+    activation.add_function("isIp", |args| match args {
+        [CelValue::String(s)] => Ok(CelValue::Bool(strings::is_ip(s, None))),
+        [CelValue::String(s), CelValue::Int(version)] => Ok(CelValue::Bool(
+            ip_version(*version).map_or(false, |version| strings::is_ip(s, version)),
+        )),
+        _ => Err(no_such_overload("isIp")),
+    });
+    activation.add_function("isIpPrefix", |args| {
+        let (s, version, strict) = match args {
+            [CelValue::String(s)] => (s, 0, false),
+            [CelValue::String(s), CelValue::Int(version)] => (s, *version, false),
+            [CelValue::String(s), CelValue::Bool(strict)] => (s, 0, *strict),
+            [CelValue::String(s), CelValue::Int(version), CelValue::Bool(strict)] => {
+                (s, *version, *strict)
+            }
+            _ => return Err(no_such_overload("isIpPrefix")),
+        };
+        Ok(CelValue::Bool(
+            ip_version(version).map_or(false, |version| {
+                strings::is_ip_with_prefix_len(s, version, strict)
+            }),
+        ))
+    });
+    activation.add_function("isHostAndPort", |args| match args {
+        [CelValue::String(s), CelValue::Bool(port_required)] => Ok(CelValue::Bool(
+            strings::is_host_and_port(s)
+                || (!*port_required && (strings::is_hostname(s) || strings::is_ip(s, None))),
+        )),
+        _ => Err(no_such_overload("isHostAndPort")),
+    });
+    activation.add_function("unique", |args| match args {
+        [CelValue::List(items)] => Ok(CelValue::Bool(
+            !items
+                .iter()
+                .enumerate()
+                .any(|(i, item)| items[..i].contains(item)),
+        )),
+        _ => Err(no_such_overload("unique")),
+    });
+    activation.add_function("isNan", |args| match args {
+        [CelValue::Double(value)] => Ok(CelValue::Bool(value.is_nan())),
+        _ => Err(no_such_overload("isNan")),
+    });
+    activation.add_function("isInf", |args| match args {
+        [CelValue::Double(value)] => Ok(CelValue::Bool(value.is_infinite())),
+        [CelValue::Double(value), CelValue::Int(sign)] => Ok(CelValue::Bool(match sign {
+            0 => value.is_infinite(),
+            sign if *sign > 0 => *value == f64::INFINITY,
+            _ => *value == f64::NEG_INFINITY,
+        })),
+        _ => Err(no_such_overload("isInf")),
+    });
+    activation
+});
+
+/// Creates the activation for evaluating the constraints of `this`.
+pub(super) fn activation(this: CelValue) -> Activation {
+    let mut activation = EXTENSIONS.clone();
+    activation.add_variable("this", this);
+    activation.add_variable("now", prost_types::Timestamp::from(SystemTime::now()));
+    activation
+}
+
+fn add_string_check(activation: &mut Activation, name: &'static str, check: fn(&str) -> bool) {
+    activation.add_function(name, move |args| match args {
+        [CelValue::String(s)] => Ok(CelValue::Bool(check(s))),
+        _ => Err(no_such_overload(name)),
+    });
+}
+
+/// Converts the IP version argument of `isIp` and `isIpPrefix`, where zero means either version.
+fn ip_version(version: i64) -> Option<Option<u8>> {
+    match version {
+        0 => Some(None),
+        4 => Some(Some(4)),
+        6 => Some(Some(6)),
+        _ => None,
+    }
+}
+
+fn no_such_overload(function: &str) -> String {
+    format!("no matching overload for '{}'", function)
+}
@@ -0,0 +1,1539 @@
+mod expressions;
+mod rules;
+mod strings;
+
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::HashMap,
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use prost::Message;
+use regex::Regex;
+
+use self::rules::{
+    AnyRules, BoolRules, BytesRules, Constraint, EnumRules, FieldConstraints, FieldOptions, Ignore,
+    KnownRegex, MapRules, MessageOptions, OneofOptions, RepeatedRules, Rules, StringRules,
+    WellKnownString,
+};
+use crate::{
+    cel::{CelValue, Program},
+    DynamicMessage, FieldDescriptor, Kind, MapKey, Value,
+};
+
+/// Evaluates the [protovalidate](https://github.com/bufbuild/protovalidate) constraints of a
+/// message.
+///
+/// Constraints are read from the `buf.validate.message`, `buf.validate.oneof` and
+/// `buf.validate.field` options of each descriptor. The options are decoded directly from the
+/// descriptors, so `buf/validate/validate.proto` does not need to be added to the
+/// [`DescriptorPool`][crate::DescriptorPool]. Nested messages, including the elements of lists
+/// and the values of maps, are validated recursively.
+///
+/// All standard constraints are supported, as are custom constraints written as
+/// [CEL](crate::cel) expressions in the `cel` fields of the options. Expressions may use the
+/// `this` and `now` variables and the extension functions of protovalidate, such as `isEmail()`
+/// and `unique()`. An expression fails if it evaluates to `false` or a non-empty string, in which
+/// case the string is used as the message of the violation.
+///
+/// The validator caches compiled regular expressions and CEL expressions, so it is cheaper to
+/// reuse a validator than to create one for each message.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::DynamicMessage;
+/// use prost_reflect::protovalidate::Validator;
+///
+/// # fn example(message: DynamicMessage) {
+/// let validator = Validator::new();
+/// if let Err(err) = validator.validate(&message) {
+///     for violation in err.violations() {
+///         // e.g. "user.email: value must be a valid email address [string.email]"
+///         println!("{}", violation);
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct Validator {
+    fail_fast: bool,
+    regexes: Mutex<HashMap<String, Regex>>,
+    programs: Mutex<HashMap<String, Arc<Program>>>,
+}
+
+/// A constraint which was not satisfied by a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    field_path: String,
+    constraint_id: String,
+    message: String,
+    for_key: bool,
+}
+
+/// An error returned when validating a message.
+#[derive(Debug)]
+pub struct ValidationError {
+    kind: ValidationErrorKind,
+}
+
+#[derive(Debug)]
+enum ValidationErrorKind {
+    Violations(Vec<Violation>),
+    InvalidConstraint { name: String, message: String },
+}
+
+/// Validates a message using a new [`Validator`] with the default settings.
+pub fn validate(message: &DynamicMessage) -> Result<(), ValidationError> {
+    Validator::new().validate(message)
+}
+
+impl Validator {
+    /// Creates a new validator with the default settings.
+    pub fn new() -> Self {
+        Validator::default()
+    }
+
+    /// Whether to stop validating at the first violation.
+    ///
+    /// By default, all violations are reported.
+    pub fn fail_fast(mut self, yes: bool) -> Self {
+        self.fail_fast = yes;
+        self
+    }
+
+    /// Validates a message against the constraints of its type and the types of any nested
+    /// messages.
+    ///
+    /// Returns an error listing the violations if any constraint is not satisfied, or an error
+    /// with no violations if the constraints themselves are invalid, for example if a rule for
+    /// strings is applied to an integer field.
+    pub fn validate(&self, message: &DynamicMessage) -> Result<(), ValidationError> {
+        let mut evaluation = Evaluation {
+            validator: self,
+            violations: Vec::new(),
+        };
+        evaluation.validate_message(message, "")?;
+        if evaluation.violations.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrorKind::Violations(evaluation.violations).into())
+        }
+    }
+
+    fn is_match(&self, pattern: &str, value: &str, name: &str) -> Result<bool, ValidationError> {
+        let mut regexes = self.regexes.lock().unwrap_or_else(|err| err.into_inner());
+        if !regexes.contains_key(pattern) {
+            let regex =
+                Regex::new(pattern).map_err(|err| ValidationErrorKind::InvalidConstraint {
+                    name: name.to_owned(),
+                    message: format!("invalid regex pattern: {}", err),
+                })?;
+            regexes.insert(pattern.to_owned(), regex);
+        }
+        Ok(regexes[pattern].is_match(value))
+    }
+
+    fn program(&self, expression: &str, name: &str) -> Result<Arc<Program>, ValidationError> {
+        let mut programs = self.programs.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(program) = programs.get(expression) {
+            return Ok(program.clone());
+        }
+        let program =
+            Program::compile(expression).map_err(|err| ValidationErrorKind::InvalidConstraint {
+                name: name.to_owned(),
+                message: format!("invalid expression '{}': {}", expression, err),
+            })?;
+        let program = Arc::new(program);
+        programs.insert(expression.to_owned(), program.clone());
+        Ok(program)
+    }
+}
+
+impl Violation {
+    /// Gets the path to the field which failed validation, such as `items[0].name` or
+    /// `labels["key"]`. The path is empty for constraints on the message itself.
+    pub fn field_path(&self) -> &str {
+        &self.field_path
+    }
+
+    /// Gets the id of the constraint which was not satisfied, such as `string.min_len`.
+    pub fn constraint_id(&self) -> &str {
+        &self.constraint_id
+    }
+
+    /// Gets a description of the violation.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns `true` if the violation applies to the key of a map entry, rather than its value.
+    pub fn for_key(&self) -> bool {
+        self.for_key
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.field_path.is_empty() {
+            write!(f, "{}: ", self.field_path)?;
+        }
+        write!(f, "{} [{}]", self.message, self.constraint_id)
+    }
+}
+
+impl ValidationError {
+    /// Gets the violations found in the message.
+    ///
+    /// Returns an empty slice if validation failed because the constraints are invalid.
+    pub fn violations(&self) -> &[Violation] {
+        match &self.kind {
+            ValidationErrorKind::Violations(violations) => violations,
+            ValidationErrorKind::InvalidConstraint { .. } => &[],
+        }
+    }
+}
+
+impl From<ValidationErrorKind> for ValidationError {
+    fn from(kind: ValidationErrorKind) -> Self {
+        ValidationError { kind }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ValidationErrorKind::Violations(violations) => {
+                write!(f, "validation failed: ")?;
+                for (i, violation) in violations.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", violation)?;
+                }
+                Ok(())
+            }
+            ValidationErrorKind::InvalidConstraint { name, message } => {
+                write!(f, "invalid constraint for '{}': {}", name, message)
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// A timestamp or duration, compared by its seconds and then its nanoseconds.
+struct Time<T> {
+    seconds: i64,
+    nanos: i32,
+    _kind: PhantomData<T>,
+}
+
+impl<T> Clone for Time<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Time<T> {}
+
+impl<T> PartialEq for Time<T> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.seconds, self.nanos) == (other.seconds, other.nanos)
+    }
+}
+
+impl<T> PartialOrd for Time<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.seconds, self.nanos).partial_cmp(&(other.seconds, other.nanos))
+    }
+}
+
+impl<T> Time<T> {
+    fn new(seconds: i64, nanos: i32) -> Self {
+        Time {
+            seconds,
+            nanos,
+            _kind: PhantomData,
+        }
+    }
+
+    fn from_message(message: &DynamicMessage) -> Self {
+        let seconds = message
+            .get_field_by_number(1)
+            .and_then(|v| v.as_i64())
+            .unwrap_or_default();
+        let nanos = message
+            .get_field_by_number(2)
+            .and_then(|v| v.as_i32())
+            .unwrap_or_default();
+        Time::new(seconds, nanos)
+    }
+}
+
+impl fmt::Display for Time<prost_types::Duration> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = prost_types::Duration {
+            seconds: self.seconds,
+            nanos: self.nanos,
+        };
+        write!(f, "{}", duration)
+    }
+}
+
+impl fmt::Display for Time<prost_types::Timestamp> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let timestamp = prost_types::Timestamp {
+            seconds: self.seconds,
+            nanos: self.nanos,
+        };
+        write!(f, "{}", timestamp)
+    }
+}
+
+impl From<prost_types::Duration> for Time<prost_types::Duration> {
+    fn from(duration: prost_types::Duration) -> Self {
+        Time::new(duration.seconds, duration.nanos)
+    }
+}
+
+impl From<prost_types::Timestamp> for Time<prost_types::Timestamp> {
+    fn from(timestamp: prost_types::Timestamp) -> Self {
+        Time::new(timestamp.seconds, timestamp.nanos)
+    }
+}
+
+/// The bounds and allowed values of a numeric field.
+struct Range<T> {
+    r#const: Option<T>,
+    lt: Option<T>,
+    lte: Option<T>,
+    gt: Option<T>,
+    gte: Option<T>,
+    r#in: Vec<T>,
+    not_in: Vec<T>,
+}
+
+macro_rules! range {
+    ($rules:expr) => {
+        Range {
+            r#const: $rules.r#const,
+            lt: $rules.lt,
+            lte: $rules.lte,
+            gt: $rules.gt,
+            gte: $rules.gte,
+            r#in: $rules.r#in.clone(),
+            not_in: $rules.not_in.clone(),
+        }
+    };
+}
+
+macro_rules! time_range {
+    ($rules:expr) => {
+        Range {
+            r#const: $rules.r#const.clone().map(Time::from),
+            lt: $rules.lt.clone().map(Time::from),
+            lte: $rules.lte.clone().map(Time::from),
+            gt: $rules.gt.clone().map(Time::from),
+            gte: $rules.gte.clone().map(Time::from),
+            r#in: Vec::new(),
+            not_in: Vec::new(),
+        }
+    };
+}
+
+/// The location of a value being validated, used to report violations.
+#[derive(Clone, Copy)]
+struct Location<'a> {
+    field: &'a FieldDescriptor,
+    path: &'a str,
+    for_key: bool,
+}
+
+struct Evaluation<'a> {
+    validator: &'a Validator,
+    violations: Vec<Violation>,
+}
+
+impl Evaluation<'_> {
+    fn violation(
+        &mut self,
+        location: Location<'_>,
+        constraint_id: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Result<(), ValidationError> {
+        self.push(Violation {
+            field_path: location.path.to_owned(),
+            constraint_id: constraint_id.into(),
+            message: message.into(),
+            for_key: location.for_key,
+        })
+    }
+
+    fn push(&mut self, violation: Violation) -> Result<(), ValidationError> {
+        self.violations.push(violation);
+        if self.validator.fail_fast {
+            Err(ValidationErrorKind::Violations(std::mem::take(&mut self.violations)).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn validate_message(
+        &mut self,
+        message: &DynamicMessage,
+        path: &str,
+    ) -> Result<(), ValidationError> {
+        let desc = &message.desc;
+        let options: MessageOptions = decode_options(desc.full_name(), &desc.options())?;
+        let constraints = options.message.unwrap_or_default();
+        if constraints.disabled == Some(true) {
+            return Ok(());
+        }
+        self.check_expressions(desc.full_name(), path, false, &constraints.cel, || {
+            CelValue::Message(message.clone())
+        })?;
+
+        for oneof in desc.oneofs() {
+            let options: OneofOptions = decode_options(oneof.full_name(), &oneof.options())?;
+            let required = options
+                .oneof
+                .map_or(false, |constraints| constraints.required == Some(true));
+            if required && !oneof.fields().any(|field| message.has_field(&field)) {
+                self.push(Violation {
+                    field_path: join_path(path, oneof.name()),
+                    constraint_id: "required".to_owned(),
+                    message: "exactly one field is required in oneof".to_owned(),
+                    for_key: false,
+                })?;
+            }
+        }
+
+        for field in desc.fields() {
+            let options: FieldOptions = decode_options(field.full_name(), &field.options())?;
+            let constraints = options.field.unwrap_or_default();
+            let path = join_path(path, field.name());
+            self.validate_field(message, &field, &constraints, &path)?;
+        }
+        Ok(())
+    }
+
+    fn validate_field(
+        &mut self,
+        message: &DynamicMessage,
+        field: &FieldDescriptor,
+        constraints: &FieldConstraints,
+        path: &str,
+    ) -> Result<(), ValidationError> {
+        let location = Location {
+            field,
+            path,
+            for_key: false,
+        };
+        let ignore = constraints.ignore();
+        if ignore == Ignore::Always {
+            return Ok(());
+        }
+
+        let populated = message.has_field(field);
+        if !populated {
+            if constraints.required == Some(true) {
+                return self.violation(location, "required", "value is required");
+            }
+            if field.supports_presence() || ignore != Ignore::Unspecified {
+                return Ok(());
+            }
+        }
+
+        let value = message.get_field(field);
+        if ignore == Ignore::IfDefaultValue && value.is_default_for_field(field) {
+            return Ok(());
+        }
+
+        if field.is_map() || field.is_list() {
+            self.check_expressions(field.full_name(), path, false, &constraints.cel, || {
+                CelValue::from(value.clone().into_owned())
+            })?;
+        }
+
+        if field.is_map() {
+            self.validate_map(location, constraints, value.as_map().expect("map field"))
+        } else if field.is_list() {
+            self.validate_list(location, constraints, value.as_list().expect("list field"))
+        } else {
+            self.validate_value(location, constraints, &value)
+        }
+    }
+
+    fn validate_list(
+        &mut self,
+        location: Location<'_>,
+        constraints: &FieldConstraints,
+        values: &[Value],
+    ) -> Result<(), ValidationError> {
+        let default_rules = RepeatedRules::default();
+        let rules = match &constraints.rules {
+            None => &default_rules,
+            Some(Rules::Repeated(rules)) => rules,
+            Some(rules) => return Err(mismatched_rules(location.field, rules)),
+        };
+
+        if let Some(min) = rules.min_items {
+            if (values.len() as u64) < min {
+                self.violation(
+                    location,
+                    "repeated.min_items",
+                    format!("value must contain at least {} item(s)", min),
+                )?;
+            }
+        }
+        if let Some(max) = rules.max_items {
+            if values.len() as u64 > max {
+                self.violation(
+                    location,
+                    "repeated.max_items",
+                    format!("value must contain no more than {} item(s)", max),
+                )?;
+            }
+        }
+        if rules.unique == Some(true) {
+            if matches!(location.field.kind(), Kind::Message(_)) {
+                return Err(invalid_constraint(
+                    location.field,
+                    "the 'repeated.unique' rule cannot be applied to message fields",
+                ));
+            }
+            let has_duplicate = values
+                .iter()
+                .enumerate()
+                .any(|(i, value)| values[..i].contains(value));
+            if has_duplicate {
+                self.violation(
+                    location,
+                    "repeated.unique",
+                    "repeated value must contain unique items",
+                )?;
+            }
+        }
+
+        let default_items = FieldConstraints::default();
+        let items = rules.items.as_ref().unwrap_or(&default_items);
+        for (index, value) in values.iter().enumerate() {
+            let path = format!("{}[{}]", location.path, index);
+            let location = Location {
+                path: &path,
+                ..location
+            };
+            if !skip_element(items, value, location.field) {
+                self.validate_value(location, items, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_map(
+        &mut self,
+        location: Location<'_>,
+        constraints: &FieldConstraints,
+        map: &HashMap<MapKey, Value>,
+    ) -> Result<(), ValidationError> {
+        let default_rules = MapRules::default();
+        let rules = match &constraints.rules {
+            None => &default_rules,
+            Some(Rules::Map(rules)) => rules,
+            Some(rules) => return Err(mismatched_rules(location.field, rules)),
+        };
+
+        if let Some(min) = rules.min_pairs {
+            if (map.len() as u64) < min {
+                self.violation(
+                    location,
+                    "map.min_pairs",
+                    format!("map must be at least {} entries", min),
+                )?;
+            }
+        }
+        if let Some(max) = rules.max_pairs {
+            if map.len() as u64 > max {
+                self.violation(
+                    location,
+                    "map.max_pairs",
+                    format!("map must be at most {} entries", max),
+                )?;
+            }
+        }
+
+        let kind = location.field.kind();
+        let entry = kind.as_message().expect("map fields have an entry message");
+        let key_field = entry.map_entry_key_field();
+        let value_field = entry.map_entry_value_field();
+        let default_constraints = FieldConstraints::default();
+        let keys = rules.keys.as_ref().unwrap_or(&default_constraints);
+        let values = rules.values.as_ref().unwrap_or(&default_constraints);
+
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
+        for (key, value) in entries {
+            let path = format!("{}[{}]", location.path, key);
+            let key_value = Value::from(key.clone());
+            if !skip_element(keys, &key_value, &key_field) {
+                let location = Location {
+                    field: &key_field,
+                    path: &path,
+                    for_key: true,
+                };
+                self.validate_value(location, keys, &key_value)?;
+            }
+            if !skip_element(values, value, &value_field) {
+                let location = Location {
+                    field: &value_field,
+                    path: &path,
+                    for_key: false,
+                };
+                self.validate_value(location, values, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates a singular value, or an element of a list or map, and any nested message.
+    fn validate_value(
+        &mut self,
+        location: Location<'_>,
+        constraints: &FieldConstraints,
+        value: &Value,
+    ) -> Result<(), ValidationError> {
+        let kind = location.field.kind();
+        if let Some(rules) = &constraints.rules {
+            let expected = rules_name(&kind);
+            if expected != Some(rules.name()) {
+                return Err(mismatched_rules(location.field, rules));
+            }
+            self.check_rules(location, rules, &unwrap_wrapper(rules, value))?;
+        }
+        self.check_expressions(
+            location.field.full_name(),
+            location.path,
+            location.for_key,
+            &constraints.cel,
+            || CelValue::from(value.clone()),
+        )?;
+
+        if let Value::Message(message) = value {
+            if !is_well_known_value(&kind) {
+                self.validate_message(message, location.path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_rules(
+        &mut self,
+        location: Location<'_>,
+        rules: &Rules,
+        value: &Value,
+    ) -> Result<(), ValidationError> {
+        match (rules, value) {
+            (Rules::Float(rules), &Value::F32(value)) => {
+                self.check_finite(location, "float", rules.finite, value.is_finite())?;
+                self.check_range(location, "float", &range!(rules), value)
+            }
+            (Rules::Double(rules), &Value::F64(value)) => {
+                self.check_finite(location, "double", rules.finite, value.is_finite())?;
+                self.check_range(location, "double", &range!(rules), value)
+            }
+            (Rules::Int32(rules), &Value::I32(value)) => {
+                self.check_range(location, "int32", &range!(rules), value)
+            }
+            (Rules::Int64(rules), &Value::I64(value)) => {
+                self.check_range(location, "int64", &range!(rules), value)
+            }
+            (Rules::Uint32(rules), &Value::U32(value)) => {
+                self.check_range(location, "uint32", &range!(rules), value)
+            }
+            (Rules::Uint64(rules), &Value::U64(value)) => {
+                self.check_range(location, "uint64", &range!(rules), value)
+            }
+            (Rules::Sint32(rules), &Value::I32(value)) => {
+                self.check_range(location, "sint32", &range!(rules), value)
+            }
+            (Rules::Sint64(rules), &Value::I64(value)) => {
+                self.check_range(location, "sint64", &range!(rules), value)
+            }
+            (Rules::Fixed32(rules), &Value::U32(value)) => {
+                self.check_range(location, "fixed32", &range!(rules), value)
+            }
+            (Rules::Fixed64(rules), &Value::U64(value)) => {
+                self.check_range(location, "fixed64", &range!(rules), value)
+            }
+            (Rules::Sfixed32(rules), &Value::I32(value)) => {
+                self.check_range(location, "sfixed32", &range!(rules), value)
+            }
+            (Rules::Sfixed64(rules), &Value::I64(value)) => {
+                self.check_range(location, "sfixed64", &range!(rules), value)
+            }
+            (Rules::Bool(rules), &Value::Bool(value)) => self.check_bool(location, rules, value),
+            (Rules::String(rules), Value::String(value)) => {
+                self.check_string(location, rules, value)
+            }
+            (Rules::Bytes(rules), Value::Bytes(value)) => self.check_bytes(location, rules, value),
+            (Rules::Enum(rules), &Value::EnumNumber(value)) => {
+                self.check_enum(location, rules, value)
+            }
+            (Rules::Any(rules), Value::Message(value)) => self.check_any(location, rules, value),
+            (Rules::Duration(rules), Value::Message(value)) => {
+                let value = Time::<prost_types::Duration>::from_message(value);
+                self.check_range(location, "duration", &time_range!(rules), value)?;
+                let r#in: Vec<_> = rules.r#in.iter().cloned().map(Time::from).collect();
+                let not_in: Vec<_> = rules.not_in.iter().cloned().map(Time::from).collect();
+                self.check_in(location, "duration", &r#in, &not_in, value)
+            }
+            (Rules::Timestamp(rules), Value::Message(value)) => {
+                let value = Time::<prost_types::Timestamp>::from_message(value);
+                self.check_range(location, "timestamp", &time_range!(rules), value)?;
+                self.check_now(location, rules, value)
+            }
+            // Unset wrapper fields and nested rules are handled elsewhere.
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the custom constraints of a value, where `this` is only computed if there are
+    /// any constraints.
+    fn check_expressions(
+        &mut self,
+        name: &str,
+        path: &str,
+        for_key: bool,
+        constraints: &[Constraint],
+        this: impl FnOnce() -> CelValue,
+    ) -> Result<(), ValidationError> {
+        if constraints.is_empty() {
+            return Ok(());
+        }
+
+        let activation = expressions::activation(this());
+        for constraint in constraints {
+            let expression = constraint.expression.as_deref().unwrap_or_default();
+            let program = self.validator.program(expression, name)?;
+            let message = match program.evaluate(&activation) {
+                Ok(CelValue::Bool(true)) => continue,
+                Ok(CelValue::Bool(false)) => match &constraint.message {
+                    Some(message) if !message.is_empty() => message.clone(),
+                    _ => format!("\"{}\" returned false", expression),
+                },
+                Ok(CelValue::String(message)) if message.is_empty() => continue,
+                Ok(CelValue::String(message)) => message,
+                Ok(value) => {
+                    return Err(ValidationErrorKind::InvalidConstraint {
+                        name: name.to_owned(),
+                        message: format!(
+                        "expression '{}' must evaluate to a bool or string, but evaluated to '{}'",
+                        expression,
+                        value.type_name()
+                    ),
+                    }
+                    .into())
+                }
+                Err(err) => {
+                    return Err(ValidationErrorKind::InvalidConstraint {
+                        name: name.to_owned(),
+                        message: format!("failed to evaluate expression '{}': {}", expression, err),
+                    }
+                    .into())
+                }
+            };
+            self.push(Violation {
+                field_path: path.to_owned(),
+                constraint_id: constraint.id.clone().unwrap_or_default(),
+                message,
+                for_key,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn check_finite(
+        &mut self,
+        location: Location<'_>,
+        name: &str,
+        finite: Option<bool>,
+        is_finite: bool,
+    ) -> Result<(), ValidationError> {
+        if finite == Some(true) && !is_finite {
+            self.violation(location, format!("{}.finite", name), "value must be finite")?;
+        }
+        Ok(())
+    }
+
+    fn check_range<T>(
+        &mut self,
+        location: Location<'_>,
+        name: &str,
+        range: &Range<T>,
+        value: T,
+    ) -> Result<(), ValidationError>
+    where
+        T: Copy + PartialOrd + fmt::Display,
+    {
+        if let Some(expected) = range.r#const {
+            if value != expected {
+                self.violation(
+                    location,
+                    format!("{}.const", name),
+                    format!("value must equal {}", expected),
+                )?;
+            }
+        }
+
+        let lower = match (range.gt, range.gte) {
+            (Some(gt), _) => Some((gt, false)),
+            (None, Some(gte)) => Some((gte, true)),
+            (None, None) => None,
+        };
+        let upper = match (range.lt, range.lte) {
+            (Some(lt), _) => Some((lt, false)),
+            (None, Some(lte)) => Some((lte, true)),
+            (None, None) => None,
+        };
+        let above = |(bound, inclusive): (T, bool)| {
+            if inclusive {
+                value >= bound
+            } else {
+                value > bound
+            }
+        };
+        let below = |(bound, inclusive): (T, bool)| {
+            if inclusive {
+                value <= bound
+            } else {
+                value < bound
+            }
+        };
+        let lower_name = |inclusive| if inclusive { "gte" } else { "gt" };
+        let lower_message = |inclusive| {
+            if inclusive {
+                "greater than or equal to"
+            } else {
+                "greater than"
+            }
+        };
+        let upper_name = |inclusive| if inclusive { "lte" } else { "lt" };
+        let upper_message = |inclusive| {
+            if inclusive {
+                "less than or equal to"
+            } else {
+                "less than"
+            }
+        };
+
+        match (lower, upper) {
+            (Some(lower), None) if !above(lower) => self.violation(
+                location,
+                format!("{}.{}", name, lower_name(lower.1)),
+                format!("value must be {} {}", lower_message(lower.1), lower.0),
+            )?,
+            (None, Some(upper)) if !below(upper) => self.violation(
+                location,
+                format!("{}.{}", name, upper_name(upper.1)),
+                format!("value must be {} {}", upper_message(upper.1), upper.0),
+            )?,
+            (Some(lower), Some(upper)) => {
+                let id = format!("{}.{}_{}", name, lower_name(lower.1), upper_name(upper.1));
+                let ordered = if lower.1 && upper.1 {
+                    upper.0 >= lower.0
+                } else {
+                    upper.0 > lower.0
+                };
+                // If the upper bound is below the lower bound, the range is exclusive, and the
+                // value must be outside of it.
+                if ordered && !(above(lower) && below(upper)) {
+                    self.violation(
+                        location,
+                        id,
+                        format!(
+                            "value must be {} {} and {} {}",
+                            lower_message(lower.1),
+                            lower.0,
+                            upper_message(upper.1),
+                            upper.0
+                        ),
+                    )?;
+                } else if !ordered && !(above(lower) || below(upper)) {
+                    self.violation(
+                        location,
+                        format!("{}_exclusive", id),
+                        format!(
+                            "value must be {} {} or {} {}",
+                            lower_message(lower.1),
+                            lower.0,
+                            upper_message(upper.1),
+                            upper.0
+                        ),
+                    )?;
+                }
+            }
+            _ => (),
+        }
+
+        self.check_in(location, name, &range.r#in, &range.not_in, value)
+    }
+
+    fn check_in<T>(
+        &mut self,
+        location: Location<'_>,
+        name: &str,
+        r#in: &[T],
+        not_in: &[T],
+        value: T,
+    ) -> Result<(), ValidationError>
+    where
+        T: PartialEq + fmt::Display,
+    {
+        if !r#in.is_empty() && !r#in.contains(&value) {
+            self.violation(
+                location,
+                format!("{}.in", name),
+                format!("value must be in list {}", fmt_list(r#in)),
+            )?;
+        }
+        if not_in.contains(&value) {
+            self.violation(
+                location,
+                format!("{}.not_in", name),
+                format!("value must not be in list {}", fmt_list(not_in)),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check_bool(
+        &mut self,
+        location: Location<'_>,
+        rules: &BoolRules,
+        value: bool,
+    ) -> Result<(), ValidationError> {
+        if let Some(expected) = rules.r#const {
+            if value != expected {
+                self.violation(
+                    location,
+                    "bool.const",
+                    format!("value must equal {}", expected),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_string(
+        &mut self,
+        location: Location<'_>,
+        rules: &StringRules,
+        value: &str,
+    ) -> Result<(), ValidationError> {
+        if let Some(expected) = &rules.r#const {
+            if value != expected {
+                self.violation(
+                    location,
+                    "string.const",
+                    format!("value must equal `{}`", expected),
+                )?;
+            }
+        }
+
+        let chars = value.chars().count() as u64;
+        if let Some(len) = rules.len {
+            if chars != len {
+                self.violation(
+                    location,
+                    "string.len",
+                    format!("value length must be {} characters", len),
+                )?;
+            }
+        }
+        if let Some(min) = rules.min_len {
+            if chars < min {
+                self.violation(
+                    location,
+                    "string.min_len",
+                    format!("value length must be at least {} characters", min),
+                )?;
+            }
+        }
+        if let Some(max) = rules.max_len {
+            if chars > max {
+                self.violation(
+                    location,
+                    "string.max_len",
+                    format!("value length must be at most {} characters", max),
+                )?;
+            }
+        }
+
+        let bytes = value.len() as u64;
+        if let Some(len) = rules.len_bytes {
+            if bytes != len {
+                self.violation(
+                    location,
+                    "string.len_bytes",
+                    format!("value length must be {} bytes", len),
+                )?;
+            }
+        }
+        if let Some(min) = rules.min_bytes {
+            if bytes < min {
+                self.violation(
+                    location,
+                    "string.min_bytes",
+                    format!("value length must be at least {} bytes", min),
+                )?;
+            }
+        }
+        if let Some(max) = rules.max_bytes {
+            if bytes > max {
+                self.violation(
+                    location,
+                    "string.max_bytes",
+                    format!("value length must be at most {} bytes", max),
+                )?;
+            }
+        }
+
+        if let Some(pattern) = &rules.pattern {
+            if !self
+                .validator
+                .is_match(pattern, value, location.field.full_name())?
+            {
+                self.violation(
+                    location,
+                    "string.pattern",
+                    format!("value does not match regex pattern `{}`", pattern),
+                )?;
+            }
+        }
+        if let Some(prefix) = &rules.prefix {
+            if !value.starts_with(prefix.as_str()) {
+                self.violation(
+                    location,
+                    "string.prefix",
+                    format!("value does not have prefix `{}`", prefix),
+                )?;
+            }
+        }
+        if let Some(suffix) = &rules.suffix {
+            if !value.ends_with(suffix.as_str()) {
+                self.violation(
+                    location,
+                    "string.suffix",
+                    format!("value does not have suffix `{}`", suffix),
+                )?;
+            }
+        }
+        if let Some(contains) = &rules.contains {
+            if !value.contains(contains.as_str()) {
+                self.violation(
+                    location,
+                    "string.contains",
+                    format!("value does not contain substring `{}`", contains),
+                )?;
+            }
+        }
+        if let Some(not_contains) = &rules.not_contains {
+            if value.contains(not_contains.as_str()) {
+                self.violation(
+                    location,
+                    "string.not_contains",
+                    format!("value contains substring `{}`", not_contains),
+                )?;
+            }
+        }
+
+        let quoted = |list: &[String]| list.iter().map(|s| format!("{:?}", s)).collect::<Vec<_>>();
+        if !rules.r#in.is_empty() && !rules.r#in.iter().any(|s| s == value) {
+            self.violation(
+                location,
+                "string.in",
+                format!("value must be in list {}", fmt_list(&quoted(&rules.r#in))),
+            )?;
+        }
+        if rules.not_in.iter().any(|s| s == value) {
+            self.violation(
+                location,
+                "string.not_in",
+                format!(
+                    "value must not be in list {}",
+                    fmt_list(&quoted(&rules.not_in))
+                ),
+            )?;
+        }
+
+        if let Some(well_known) = rules.well_known {
+            self.check_well_known_string(location, well_known, rules.strict != Some(false), value)?;
+        }
+        Ok(())
+    }
+
+    fn check_well_known_string(
+        &mut self,
+        location: Location<'_>,
+        well_known: WellKnownString,
+        strict: bool,
+        value: &str,
+    ) -> Result<(), ValidationError> {
+        let (name, description, valid): (&str, &str, fn(&str, bool) -> bool) = match well_known {
+            WellKnownString::Email(true) => ("email", "email address", |s, _| strings::is_email(s)),
+            WellKnownString::Hostname(true) => {
+                ("hostname", "hostname", |s, _| strings::is_hostname(s))
+            }
+            WellKnownString::Ip(true) => ("ip", "IP address", |s, _| strings::is_ip(s, None)),
+            WellKnownString::Ipv4(true) => {
+                ("ipv4", "IPv4 address", |s, _| strings::is_ip(s, Some(4)))
+            }
+            WellKnownString::Ipv6(true) => {
+                ("ipv6", "IPv6 address", |s, _| strings::is_ip(s, Some(6)))
+            }
+            WellKnownString::Uri(true) => ("uri", "URI", |s, _| strings::is_uri(s)),
+            WellKnownString::UriRef(true) => {
+                if !strings::is_uri_ref(value) {
+                    self.violation(
+                        location,
+                        "string.uri_ref",
+                        "value must be a valid URI Reference",
+                    )?;
+                }
+                return Ok(());
+            }
+            WellKnownString::Address(true) => ("address", "hostname, or ip address", |s, _| {
+                strings::is_hostname(s) || strings::is_ip(s, None)
+            }),
+            WellKnownString::Uuid(true) => ("uuid", "UUID", |s, _| strings::is_uuid(s)),
+            WellKnownString::Tuuid(true) => {
+                ("tuuid", "trimmed UUID", |s, _| strings::is_trimmed_uuid(s))
+            }
+            WellKnownString::IpWithPrefixlen(true) => (
+                "ip_with_prefixlen",
+                "IP address with prefix length",
+                |s, _| strings::is_ip_with_prefix_len(s, None, false),
+            ),
+            WellKnownString::Ipv4WithPrefixlen(true) => (
+                "ipv4_with_prefixlen",
+                "IPv4 address with prefix length",
+                |s, _| strings::is_ip_with_prefix_len(s, Some(4), false),
+            ),
+            WellKnownString::Ipv6WithPrefixlen(true) => (
+                "ipv6_with_prefixlen",
+                "IPv6 address with prefix length",
+                |s, _| strings::is_ip_with_prefix_len(s, Some(6), false),
+            ),
+            WellKnownString::IpPrefix(true) => ("ip_prefix", "IP prefix", |s, _| {
+                strings::is_ip_with_prefix_len(s, None, true)
+            }),
+            WellKnownString::Ipv4Prefix(true) => ("ipv4_prefix", "IPv4 prefix", |s, _| {
+                strings::is_ip_with_prefix_len(s, Some(4), true)
+            }),
+            WellKnownString::Ipv6Prefix(true) => ("ipv6_prefix", "IPv6 prefix", |s, _| {
+                strings::is_ip_with_prefix_len(s, Some(6), true)
+            }),
+            WellKnownString::HostAndPort(true) => (
+                "host_and_port",
+                "host (hostname or IP address) and port pair",
+                |s, _| strings::is_host_and_port(s),
+            ),
+            WellKnownString::WellKnownRegex(regex) => match KnownRegex::from_i32(regex) {
+                Some(KnownRegex::HttpHeaderName) => (
+                    "well_known_regex.header_name",
+                    "HTTP header name",
+                    strings::is_header_name,
+                ),
+                Some(KnownRegex::HttpHeaderValue) => {
+                    if !strings::is_header_value(value, strict) {
+                        self.violation(
+                            location,
+                            "string.well_known_regex.header_value",
+                            "value must be a valid HTTP header value",
+                        )?;
+                    }
+                    return Ok(());
+                }
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        if value.is_empty() {
+            self.violation(
+                location,
+                format!("string.{}_empty", name),
+                format!("value is empty, which is not a valid {}", description),
+            )
+        } else if !valid(value, strict) {
+            self.violation(
+                location,
+                format!("string.{}", name),
+                format!("value must be a valid {}", description),
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_bytes(
+        &mut self,
+        location: Location<'_>,
+        rules: &BytesRules,
+        value: &[u8],
+    ) -> Result<(), ValidationError> {
+        if let Some(expected) = &rules.r#const {
+            if value != expected.as_ref() {
+                self.violation(
+                    location,
+                    "bytes.const",
+                    format!("value must be {}", hex(expected)),
+                )?;
+            }
+        }
+
+        let len = value.len() as u64;
+        if let Some(expected) = rules.len {
+            if len != expected {
+                self.violation(
+                    location,
+                    "bytes.len",
+                    format!("value length must be {} bytes", expected),
+                )?;
+            }
+        }
+        if let Some(min) = rules.min_len {
+            if len < min {
+                self.violation(
+                    location,
+                    "bytes.min_len",
+                    format!("value length must be at least {} bytes", min),
+                )?;
+            }
+        }
+        if let Some(max) = rules.max_len {
+            if len > max {
+                self.violation(
+                    location,
+                    "bytes.max_len",
+                    format!("value must be at most {} bytes", max),
+                )?;
+            }
+        }
+
+        if let Some(pattern) = &rules.pattern {
+            let matches = match std::str::from_utf8(value) {
+                Ok(value) => self
+                    .validator
+                    .is_match(pattern, value, location.field.full_name())?,
+                Err(_) => false,
+            };
+            if !matches {
+                self.violation(
+                    location,
+                    "bytes.pattern",
+                    format!("value must match regex pattern `{}`", pattern),
+                )?;
+            }
+        }
+        if let Some(prefix) = &rules.prefix {
+            if !value.starts_with(prefix) {
+                self.violation(
+                    location,
+                    "bytes.prefix",
+                    format!("value does not have prefix {}", hex(prefix)),
+                )?;
+            }
+        }
+        if let Some(suffix) = &rules.suffix {
+            if !value.ends_with(suffix) {
+                self.violation(
+                    location,
+                    "bytes.suffix",
+                    format!("value does not have suffix {}", hex(suffix)),
+                )?;
+            }
+        }
+        if let Some(contains) = &rules.contains {
+            let found = contains.is_empty()
+                || value
+                    .windows(contains.len())
+                    .any(|window| window == contains.as_ref());
+            if !found {
+                self.violation(
+                    location,
+                    "bytes.contains",
+                    format!("value does not contain {}", hex(contains)),
+                )?;
+            }
+        }
+
+        let hex_list =
+            |list: &[prost::bytes::Bytes]| list.iter().map(|b| hex(b)).collect::<Vec<_>>();
+        if !rules.r#in.is_empty() && !rules.r#in.iter().any(|b| b.as_ref() == value) {
+            self.violation(
+                location,
+                "bytes.in",
+                format!("value must be in list {}", fmt_list(&hex_list(&rules.r#in))),
+            )?;
+        }
+        if rules.not_in.iter().any(|b| b.as_ref() == value) {
+            self.violation(
+                location,
+                "bytes.not_in",
+                format!(
+                    "value must not be in list {}",
+                    fmt_list(&hex_list(&rules.not_in))
+                ),
+            )?;
+        }
+
+        let ip = [
+            (
+                rules.ip,
+                "ip",
+                "IP address",
+                value.len() == 4 || value.len() == 16,
+            ),
+            (rules.ipv4, "ipv4", "IPv4 address", value.len() == 4),
+            (rules.ipv6, "ipv6", "IPv6 address", value.len() == 16),
+        ];
+        for (enabled, name, description, valid) in ip {
+            if enabled != Some(true) {
+                continue;
+            }
+            if value.is_empty() {
+                self.violation(
+                    location,
+                    format!("bytes.{}_empty", name),
+                    format!("value is empty, which is not a valid {}", description),
+                )?;
+            } else if !valid {
+                self.violation(
+                    location,
+                    format!("bytes.{}", name),
+                    format!("value must be a valid {}", description),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_enum(
+        &mut self,
+        location: Location<'_>,
+        rules: &EnumRules,
+        value: i32,
+    ) -> Result<(), ValidationError> {
+        if let Some(expected) = rules.r#const {
+            if value != expected {
+                self.violation(
+                    location,
+                    "enum.const",
+                    format!("value must equal {}", expected),
+                )?;
+            }
+        }
+        if rules.defined_only == Some(true) {
+            let kind = location.field.kind();
+            let enum_desc = kind.as_enum().expect("enum rules apply to enum fields");
+            if enum_desc.get_value(value).is_none() {
+                self.violation(
+                    location,
+                    "enum.defined_only",
+                    "value must be one of the defined enum values",
+                )?;
+            }
+        }
+        self.check_in(location, "enum", &rules.r#in, &rules.not_in, value)
+    }
+
+    fn check_any(
+        &mut self,
+        location: Location<'_>,
+        rules: &AnyRules,
+        value: &DynamicMessage,
+    ) -> Result<(), ValidationError> {
+        let type_url = value.get_field_by_number(1);
+        let type_url = type_url
+            .as_deref()
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !rules.r#in.is_empty() && !rules.r#in.iter().any(|url| url == type_url) {
+            self.violation(location, "any.in", "type URL must be in the allow list")?;
+        }
+        if rules.not_in.iter().any(|url| url == type_url) {
+            self.violation(
+                location,
+                "any.not_in",
+                "type URL must not be in the block list",
+            )?;
+        }
+        Ok(())
+    }
+
+    fn check_now(
+        &mut self,
+        location: Location<'_>,
+        rules: &rules::TimestampRules,
+        value: Time<prost_types::Timestamp>,
+    ) -> Result<(), ValidationError> {
+        if rules.lt_now != Some(true) && rules.gt_now != Some(true) && rules.within.is_none() {
+            return Ok(());
+        }
+
+        let now = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(now) => {
+                Time::<prost_types::Timestamp>::new(now.as_secs() as i64, now.subsec_nanos() as i32)
+            }
+            Err(_) => Time::new(0, 0),
+        };
+        if rules.lt_now == Some(true) && value >= now {
+            self.violation(location, "timestamp.lt_now", "value must be less than now")?;
+        }
+        if rules.gt_now == Some(true) && value <= now {
+            self.violation(
+                location,
+                "timestamp.gt_now",
+                "value must be greater than now",
+            )?;
+        }
+        if let Some(within) = &rules.within {
+            let to_nanos =
+                |seconds: i64, nanos: i32| i128::from(seconds) * 1_000_000_000 + i128::from(nanos);
+            let distance =
+                (to_nanos(value.seconds, value.nanos) - to_nanos(now.seconds, now.nanos)).abs();
+            if distance > to_nanos(within.seconds, within.nanos) {
+                self.violation(
+                    location,
+                    "timestamp.within",
+                    format!(
+                        "value must be within {} of now",
+                        Time::<prost_types::Duration>::from(within.clone())
+                    ),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the `buf.validate` extension from the options of a descriptor.
+fn decode_options<T>(name: &str, options: &DynamicMessage) -> Result<T, ValidationError>
+where
+    T: Message + Default,
+{
+    T::decode(options.encode_to_vec().as_slice()).map_err(|err| {
+        ValidationErrorKind::InvalidConstraint {
+            name: name.to_owned(),
+            message: format!("failed to decode the 'buf.validate' options: {}", err),
+        }
+        .into()
+    })
+}
+
+fn invalid_constraint(field: &FieldDescriptor, message: impl Into<String>) -> ValidationError {
+    ValidationErrorKind::InvalidConstraint {
+        name: field.full_name().to_owned(),
+        message: message.into(),
+    }
+    .into()
+}
+
+fn mismatched_rules(field: &FieldDescriptor, rules: &Rules) -> ValidationError {
+    invalid_constraint(
+        field,
+        format!(
+            "the '{}' rules cannot be applied to a field of type '{:?}'",
+            rules.name(),
+            field.kind()
+        ),
+    )
+}
+
+/// Gets the name of the rules which apply to values of a kind.
+fn rules_name(kind: &Kind) -> Option<&'static str> {
+    Some(match kind {
+        Kind::Double => "double",
+        Kind::Float => "float",
+        Kind::Int32 => "int32",
+        Kind::Int64 => "int64",
+        Kind::Uint32 => "uint32",
+        Kind::Uint64 => "uint64",
+        Kind::Sint32 => "sint32",
+        Kind::Sint64 => "sint64",
+        Kind::Fixed32 => "fixed32",
+        Kind::Fixed64 => "fixed64",
+        Kind::Sfixed32 => "sfixed32",
+        Kind::Sfixed64 => "sfixed64",
+        Kind::Bool => "bool",
+        Kind::String => "string",
+        Kind::Bytes => "bytes",
+        Kind::Enum(_) => "enum",
+        Kind::Message(message) => match message.full_name() {
+            "google.protobuf.Any" => "any",
+            "google.protobuf.Duration" => "duration",
+            "google.protobuf.Timestamp" => "timestamp",
+            "google.protobuf.DoubleValue" => "double",
+            "google.protobuf.FloatValue" => "float",
+            "google.protobuf.Int64Value" => "int64",
+            "google.protobuf.UInt64Value" => "uint64",
+            "google.protobuf.Int32Value" => "int32",
+            "google.protobuf.UInt32Value" => "uint32",
+            "google.protobuf.BoolValue" => "bool",
+            "google.protobuf.StringValue" => "string",
+            "google.protobuf.BytesValue" => "bytes",
+            _ => return None,
+        },
+    })
+}
+
+/// Returns true for message types whose fields are checked by the standard rules, rather than
+/// being validated as nested messages.
+fn is_well_known_value(kind: &Kind) -> bool {
+    matches!(kind, Kind::Message(_)) && rules_name(kind).is_some()
+}
+
+/// Gets the value of a wrapper message such as `google.protobuf.Int32Value`.
+fn unwrap_wrapper<'a>(rules: &Rules, value: &'a Value) -> Cow<'a, Value> {
+    match (rules, value) {
+        (Rules::Any(_) | Rules::Duration(_) | Rules::Timestamp(_), _) => Cow::Borrowed(value),
+        (_, Value::Message(message)) => message
+            .get_field_by_number(1)
+            .unwrap_or(Cow::Borrowed(value)),
+        _ => Cow::Borrowed(value),
+    }
+}
+
+/// Whether the rules for an element of a list or map should be skipped, according to its `ignore`
+/// setting.
+fn skip_element(constraints: &FieldConstraints, value: &Value, field: &FieldDescriptor) -> bool {
+    match constraints.ignore() {
+        Ignore::Always => true,
+        Ignore::IfUnpopulated | Ignore::IfDefaultValue => value.is_default_for_field(field),
+        Ignore::Unspecified => false,
+    }
+}
+
+fn join_path(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn fmt_list<T: fmt::Display>(list: &[T]) -> String {
+    let items: Vec<_> = list.iter().map(|item| item.to_string()).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
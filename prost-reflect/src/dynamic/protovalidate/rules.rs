@@ -0,0 +1,390 @@
+//! Definitions of the `buf.validate` options, decoded directly from the options of each
+//! descriptor so the validation schema does not need to be present in the descriptor pool.
+//!
+//! These mirror the messages in
+//! [`buf/validate/validate.proto`](https://github.com/bufbuild/protovalidate/blob/main/proto/protovalidate/buf/validate/validate.proto).
+//! The fields of the `less_than` and `greater_than` oneofs are decoded as separate optional
+//! fields, which is equivalent on the wire. Each extension has the field number 1159.
+
+use prost::{bytes::Bytes, Message, Oneof};
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct MessageOptions {
+    #[prost(message, optional, tag = "1159")]
+    pub message: Option<MessageConstraints>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct OneofOptions {
+    #[prost(message, optional, tag = "1159")]
+    pub oneof: Option<OneofConstraints>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct FieldOptions {
+    #[prost(message, optional, tag = "1159")]
+    pub field: Option<FieldConstraints>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct MessageConstraints {
+    #[prost(bool, optional, tag = "1")]
+    pub disabled: Option<bool>,
+    #[prost(message, repeated, tag = "3")]
+    pub cel: Vec<Constraint>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct OneofConstraints {
+    #[prost(bool, optional, tag = "1")]
+    pub required: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct Constraint {
+    #[prost(string, optional, tag = "1")]
+    pub id: Option<String>,
+    #[prost(string, optional, tag = "2")]
+    pub message: Option<String>,
+    #[prost(string, optional, tag = "3")]
+    pub expression: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct FieldConstraints {
+    #[prost(message, repeated, tag = "23")]
+    pub cel: Vec<Constraint>,
+    #[prost(bool, optional, tag = "25")]
+    pub required: Option<bool>,
+    #[prost(enumeration = "Ignore", optional, tag = "27")]
+    pub ignore: Option<i32>,
+    #[prost(
+        oneof = "Rules",
+        tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 19, 20, 21, 22"
+    )]
+    pub rules: Option<Rules>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+pub(super) enum Ignore {
+    Unspecified = 0,
+    IfUnpopulated = 1,
+    IfDefaultValue = 2,
+    Always = 3,
+}
+
+#[derive(Clone, PartialEq, Oneof)]
+pub(super) enum Rules {
+    #[prost(message, tag = "1")]
+    Float(FloatRules),
+    #[prost(message, tag = "2")]
+    Double(DoubleRules),
+    #[prost(message, tag = "3")]
+    Int32(Int32Rules),
+    #[prost(message, tag = "4")]
+    Int64(Int64Rules),
+    #[prost(message, tag = "5")]
+    Uint32(UInt32Rules),
+    #[prost(message, tag = "6")]
+    Uint64(UInt64Rules),
+    #[prost(message, tag = "7")]
+    Sint32(SInt32Rules),
+    #[prost(message, tag = "8")]
+    Sint64(SInt64Rules),
+    #[prost(message, tag = "9")]
+    Fixed32(Fixed32Rules),
+    #[prost(message, tag = "10")]
+    Fixed64(Fixed64Rules),
+    #[prost(message, tag = "11")]
+    Sfixed32(SFixed32Rules),
+    #[prost(message, tag = "12")]
+    Sfixed64(SFixed64Rules),
+    #[prost(message, tag = "13")]
+    Bool(BoolRules),
+    #[prost(message, tag = "14")]
+    String(StringRules),
+    #[prost(message, tag = "15")]
+    Bytes(BytesRules),
+    #[prost(message, tag = "16")]
+    Enum(EnumRules),
+    #[prost(message, tag = "18")]
+    Repeated(Box<RepeatedRules>),
+    #[prost(message, tag = "19")]
+    Map(Box<MapRules>),
+    #[prost(message, tag = "20")]
+    Any(AnyRules),
+    #[prost(message, tag = "21")]
+    Duration(DurationRules),
+    #[prost(message, tag = "22")]
+    Timestamp(TimestampRules),
+}
+
+macro_rules! numeric_rules {
+    ($name:ident, $kind:ident, $ty:ty) => {
+        #[derive(Clone, PartialEq, Message)]
+        pub(super) struct $name {
+            #[prost($kind, optional, tag = "1")]
+            pub r#const: Option<$ty>,
+            #[prost($kind, optional, tag = "2")]
+            pub lt: Option<$ty>,
+            #[prost($kind, optional, tag = "3")]
+            pub lte: Option<$ty>,
+            #[prost($kind, optional, tag = "4")]
+            pub gt: Option<$ty>,
+            #[prost($kind, optional, tag = "5")]
+            pub gte: Option<$ty>,
+            #[prost($kind, repeated, packed = "false", tag = "6")]
+            pub r#in: Vec<$ty>,
+            #[prost($kind, repeated, packed = "false", tag = "7")]
+            pub not_in: Vec<$ty>,
+            #[prost(bool, optional, tag = "8")]
+            pub finite: Option<bool>,
+        }
+    };
+}
+
+numeric_rules!(FloatRules, float, f32);
+numeric_rules!(DoubleRules, double, f64);
+numeric_rules!(Int32Rules, int32, i32);
+numeric_rules!(Int64Rules, int64, i64);
+numeric_rules!(UInt32Rules, uint32, u32);
+numeric_rules!(UInt64Rules, uint64, u64);
+numeric_rules!(SInt32Rules, sint32, i32);
+numeric_rules!(SInt64Rules, sint64, i64);
+numeric_rules!(Fixed32Rules, fixed32, u32);
+numeric_rules!(Fixed64Rules, fixed64, u64);
+numeric_rules!(SFixed32Rules, sfixed32, i32);
+numeric_rules!(SFixed64Rules, sfixed64, i64);
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct BoolRules {
+    #[prost(bool, optional, tag = "1")]
+    pub r#const: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct StringRules {
+    #[prost(string, optional, tag = "1")]
+    pub r#const: Option<String>,
+    #[prost(uint64, optional, tag = "19")]
+    pub len: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub min_len: Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    pub max_len: Option<u64>,
+    #[prost(uint64, optional, tag = "20")]
+    pub len_bytes: Option<u64>,
+    #[prost(uint64, optional, tag = "4")]
+    pub min_bytes: Option<u64>,
+    #[prost(uint64, optional, tag = "5")]
+    pub max_bytes: Option<u64>,
+    #[prost(string, optional, tag = "6")]
+    pub pattern: Option<String>,
+    #[prost(string, optional, tag = "7")]
+    pub prefix: Option<String>,
+    #[prost(string, optional, tag = "8")]
+    pub suffix: Option<String>,
+    #[prost(string, optional, tag = "9")]
+    pub contains: Option<String>,
+    #[prost(string, optional, tag = "23")]
+    pub not_contains: Option<String>,
+    #[prost(string, repeated, tag = "10")]
+    pub r#in: Vec<String>,
+    #[prost(string, repeated, tag = "11")]
+    pub not_in: Vec<String>,
+    #[prost(bool, optional, tag = "25")]
+    pub strict: Option<bool>,
+    #[prost(
+        oneof = "WellKnownString",
+        tags = "12, 13, 14, 15, 16, 17, 18, 21, 22, 33, 26, 27, 28, 29, 30, 31, 32, 24"
+    )]
+    pub well_known: Option<WellKnownString>,
+}
+
+#[derive(Clone, Copy, PartialEq, Oneof)]
+pub(super) enum WellKnownString {
+    #[prost(bool, tag = "12")]
+    Email(bool),
+    #[prost(bool, tag = "13")]
+    Hostname(bool),
+    #[prost(bool, tag = "14")]
+    Ip(bool),
+    #[prost(bool, tag = "15")]
+    Ipv4(bool),
+    #[prost(bool, tag = "16")]
+    Ipv6(bool),
+    #[prost(bool, tag = "17")]
+    Uri(bool),
+    #[prost(bool, tag = "18")]
+    UriRef(bool),
+    #[prost(bool, tag = "21")]
+    Address(bool),
+    #[prost(bool, tag = "22")]
+    Uuid(bool),
+    #[prost(bool, tag = "33")]
+    Tuuid(bool),
+    #[prost(bool, tag = "26")]
+    IpWithPrefixlen(bool),
+    #[prost(bool, tag = "27")]
+    Ipv4WithPrefixlen(bool),
+    #[prost(bool, tag = "28")]
+    Ipv6WithPrefixlen(bool),
+    #[prost(bool, tag = "29")]
+    IpPrefix(bool),
+    #[prost(bool, tag = "30")]
+    Ipv4Prefix(bool),
+    #[prost(bool, tag = "31")]
+    Ipv6Prefix(bool),
+    #[prost(bool, tag = "32")]
+    HostAndPort(bool),
+    #[prost(enumeration = "KnownRegex", tag = "24")]
+    WellKnownRegex(i32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+pub(super) enum KnownRegex {
+    Unspecified = 0,
+    HttpHeaderName = 1,
+    HttpHeaderValue = 2,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct BytesRules {
+    #[prost(bytes = "bytes", optional, tag = "1")]
+    pub r#const: Option<Bytes>,
+    #[prost(uint64, optional, tag = "13")]
+    pub len: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub min_len: Option<u64>,
+    #[prost(uint64, optional, tag = "3")]
+    pub max_len: Option<u64>,
+    #[prost(string, optional, tag = "4")]
+    pub pattern: Option<String>,
+    #[prost(bytes = "bytes", optional, tag = "5")]
+    pub prefix: Option<Bytes>,
+    #[prost(bytes = "bytes", optional, tag = "6")]
+    pub suffix: Option<Bytes>,
+    #[prost(bytes = "bytes", optional, tag = "7")]
+    pub contains: Option<Bytes>,
+    #[prost(bytes = "bytes", repeated, tag = "8")]
+    pub r#in: Vec<Bytes>,
+    #[prost(bytes = "bytes", repeated, tag = "9")]
+    pub not_in: Vec<Bytes>,
+    #[prost(bool, optional, tag = "10")]
+    pub ip: Option<bool>,
+    #[prost(bool, optional, tag = "11")]
+    pub ipv4: Option<bool>,
+    #[prost(bool, optional, tag = "12")]
+    pub ipv6: Option<bool>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct EnumRules {
+    #[prost(int32, optional, tag = "1")]
+    pub r#const: Option<i32>,
+    #[prost(bool, optional, tag = "2")]
+    pub defined_only: Option<bool>,
+    #[prost(int32, repeated, packed = "false", tag = "3")]
+    pub r#in: Vec<i32>,
+    #[prost(int32, repeated, packed = "false", tag = "4")]
+    pub not_in: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct RepeatedRules {
+    #[prost(uint64, optional, tag = "1")]
+    pub min_items: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub max_items: Option<u64>,
+    #[prost(bool, optional, tag = "3")]
+    pub unique: Option<bool>,
+    #[prost(message, optional, tag = "4")]
+    pub items: Option<FieldConstraints>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct MapRules {
+    #[prost(uint64, optional, tag = "1")]
+    pub min_pairs: Option<u64>,
+    #[prost(uint64, optional, tag = "2")]
+    pub max_pairs: Option<u64>,
+    #[prost(message, optional, tag = "4")]
+    pub keys: Option<FieldConstraints>,
+    #[prost(message, optional, tag = "5")]
+    pub values: Option<FieldConstraints>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct AnyRules {
+    #[prost(string, repeated, tag = "2")]
+    pub r#in: Vec<String>,
+    #[prost(string, repeated, tag = "3")]
+    pub not_in: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct DurationRules {
+    #[prost(message, optional, tag = "2")]
+    pub r#const: Option<prost_types::Duration>,
+    #[prost(message, optional, tag = "3")]
+    pub lt: Option<prost_types::Duration>,
+    #[prost(message, optional, tag = "4")]
+    pub lte: Option<prost_types::Duration>,
+    #[prost(message, optional, tag = "5")]
+    pub gt: Option<prost_types::Duration>,
+    #[prost(message, optional, tag = "6")]
+    pub gte: Option<prost_types::Duration>,
+    #[prost(message, repeated, tag = "7")]
+    pub r#in: Vec<prost_types::Duration>,
+    #[prost(message, repeated, tag = "8")]
+    pub not_in: Vec<prost_types::Duration>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(super) struct TimestampRules {
+    #[prost(message, optional, tag = "2")]
+    pub r#const: Option<prost_types::Timestamp>,
+    #[prost(message, optional, tag = "3")]
+    pub lt: Option<prost_types::Timestamp>,
+    #[prost(message, optional, tag = "4")]
+    pub lte: Option<prost_types::Timestamp>,
+    #[prost(message, optional, tag = "5")]
+    pub gt: Option<prost_types::Timestamp>,
+    #[prost(message, optional, tag = "6")]
+    pub gte: Option<prost_types::Timestamp>,
+    #[prost(bool, optional, tag = "7")]
+    pub lt_now: Option<bool>,
+    #[prost(bool, optional, tag = "8")]
+    pub gt_now: Option<bool>,
+    #[prost(message, optional, tag = "9")]
+    pub within: Option<prost_types::Duration>,
+}
+
+impl Rules {
+    /// The name of the rules, as used in the prefix of constraint ids such as `string.min_len`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rules::Float(_) => "float",
+            Rules::Double(_) => "double",
+            Rules::Int32(_) => "int32",
+            Rules::Int64(_) => "int64",
+            Rules::Uint32(_) => "uint32",
+            Rules::Uint64(_) => "uint64",
+            Rules::Sint32(_) => "sint32",
+            Rules::Sint64(_) => "sint64",
+            Rules::Fixed32(_) => "fixed32",
+            Rules::Fixed64(_) => "fixed64",
+            Rules::Sfixed32(_) => "sfixed32",
+            Rules::Sfixed64(_) => "sfixed64",
+            Rules::Bool(_) => "bool",
+            Rules::String(_) => "string",
+            Rules::Bytes(_) => "bytes",
+            Rules::Enum(_) => "enum",
+            Rules::Repeated(_) => "repeated",
+            Rules::Map(_) => "map",
+            Rules::Any(_) => "any",
+            Rules::Duration(_) => "duration",
+            Rules::Timestamp(_) => "timestamp",
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Checks for the well-known string formats of `buf.validate.StringRules`.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Checks for an email address, following the definition used by HTML forms.
+pub(super) fn is_email(s: &str) -> bool {
+    let (local, domain) = match s.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".!#$%&'*+/=?^_`{|}~-".contains(c))
+        && is_hostname(domain)
+}
+
+/// Checks for a hostname as defined by RFC 1034, with an optional trailing dot.
+pub(super) fn is_hostname(s: &str) -> bool {
+    let s = s.strip_suffix('.').unwrap_or(s);
+    if s.is_empty() || s.len() > 253 {
+        return false;
+    }
+
+    let mut last_label = "";
+    for label in s.split('.') {
+        if label.is_empty()
+            || label.len() > 63
+            || label.starts_with('-')
+            || label.ends_with('-')
+            || !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return false;
+        }
+        last_label = label;
+    }
+    // The top-level domain must not be entirely numeric.
+    !last_label.chars().all(|c| c.is_ascii_digit())
+}
+
+pub(super) fn is_ip(s: &str, version: Option<u8>) -> bool {
+    match version {
+        None => s.parse::<IpAddr>().is_ok(),
+        Some(4) => s.parse::<Ipv4Addr>().is_ok(),
+        Some(_) => s.parse::<Ipv6Addr>().is_ok(),
+    }
+}
+
+/// Checks for an IP address followed by a prefix length, such as `192.168.0.1/24`. If `strict`
+/// is true, all bits after the prefix must be zero.
+pub(super) fn is_ip_with_prefix_len(s: &str, version: Option<u8>, strict: bool) -> bool {
+    let (addr, prefix_len) = match s.split_once('/') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    if prefix_len.is_empty()
+        || prefix_len.len() > 3
+        || !prefix_len.chars().all(|c| c.is_ascii_digit())
+        || (prefix_len.len() > 1 && prefix_len.starts_with('0'))
+    {
+        return false;
+    }
+    let prefix_len: u32 = match prefix_len.parse() {
+        Ok(len) => len,
+        Err(_) => return false,
+    };
+
+    let (bits, width) = match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(addr)) if version != Some(6) => (u32::from(addr) as u128, 32),
+        Ok(IpAddr::V6(addr)) if version != Some(4) => (u128::from(addr), 128),
+        _ => return false,
+    };
+    if prefix_len > width {
+        return false;
+    }
+    !strict || prefix_len == width || bits & ((1u128 << (width - prefix_len)) - 1) == 0
+}
+
+/// Checks for a host and port pair, such as `example.com:8080` or `[::1]:80`.
+pub(super) fn is_host_and_port(s: &str) -> bool {
+    let (host, port) = match s.rsplit_once(':') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let valid_host = match host.strip_prefix('[') {
+        Some(host) => host
+            .strip_suffix(']')
+            .map_or(false, |ip| is_ip(ip, Some(6))),
+        None => is_hostname(host) || is_ip(host, Some(4)),
+    };
+    valid_host
+        && !port.is_empty()
+        && port.chars().all(|c| c.is_ascii_digit())
+        && (port == "0" || !port.starts_with('0'))
+        && port.parse::<u16>().is_ok()
+}
+
+/// Checks for a UUID in the hyphenated form defined by RFC 4122.
+pub(super) fn is_uuid(s: &str) -> bool {
+    s.len() == 36
+        && s.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+/// Checks for a UUID without hyphens.
+pub(super) fn is_trimmed_uuid(s: &str) -> bool {
+    s.len() == 32 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Checks for an absolute URI as defined by RFC 3986.
+pub(super) fn is_uri(s: &str) -> bool {
+    match s.split_once(':') {
+        Some((scheme, rest)) => is_scheme(scheme) && is_uri_chars(rest),
+        None => false,
+    }
+}
+
+/// Checks for a URI or relative reference as defined by RFC 3986.
+pub(super) fn is_uri_ref(s: &str) -> bool {
+    match s.split_once(':') {
+        // A colon in the first path segment of a relative reference is not allowed.
+        Some((scheme, rest)) if !scheme.contains(['/', '?', '#']) => {
+            is_scheme(scheme) && is_uri_chars(rest)
+        }
+        _ => is_uri_chars(s),
+    }
+}
+
+fn is_scheme(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().map_or(false, |c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+}
+
+fn is_uri_chars(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                if i + 2 >= bytes.len()
+                    || !bytes[i + 1].is_ascii_hexdigit()
+                    || !bytes[i + 2].is_ascii_hexdigit()
+                {
+                    return false;
+                }
+                i += 3;
+                continue;
+            }
+            b if b.is_ascii_alphanumeric() => (),
+            b if b"-._~:/?#[]@!$&'()*+,;=".contains(&b) => (),
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Checks for an HTTP header name as defined by RFC 7230, allowing the pseudo-headers of HTTP/2
+/// such as `:path`. If `strict` is false, any value without NUL, CR or LF characters is allowed.
+pub(super) fn is_header_name(s: &str, strict: bool) -> bool {
+    if !strict {
+        return !s.is_empty() && !s.contains(['\0', '\r', '\n']);
+    }
+    let name = s.strip_prefix(':').unwrap_or(s);
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_|~`".contains(c))
+}
+
+/// Checks for an HTTP header value as defined by RFC 7230. If `strict` is false, any value
+/// without NUL, CR or LF characters is allowed.
+pub(super) fn is_header_value(s: &str, strict: bool) -> bool {
+    if strict {
+        !s.chars().any(|c| (c < '\x20' && c != '\t') || c == '\x7f')
+    } else {
+        !s.contains(['\0', '\r', '\n'])
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use prost::{bytes::Bytes, DecodeError, Message};
+use prost_types::FileDescriptorProto;
+use tonic1::{
+    body::BoxBody,
+    client::{Grpc, GrpcService},
+    codec::ProstCodec,
+    codegen::{futures_core::Stream, http::uri::PathAndQuery, Body, StdError},
+    Code, Request, Status,
+};
+
+use super::{
+    find_symbol_file, ExtensionRequest, MessageRequest, MessageResponse, ServerReflectionRequest,
+    ServerReflectionResponse, V1ALPHA_NAME, V1_NAME,
+};
+use crate::{DescriptorError, DescriptorPool, FileDescriptor};
+
+/// A client for the gRPC server reflection service, which builds a [`DescriptorPool`] from the
+/// files described by a remote server.
+///
+/// Each file is fetched together with its transitive dependencies, and added to the client's
+/// pool, so that queries for symbols in files which were already fetched are answered without
+/// contacting the server. The client first uses the `grpc.reflection.v1.ServerReflection`
+/// service, and falls back to `grpc.reflection.v1alpha.ServerReflection` if the server does not
+/// implement it.
+///
+/// The client can be created from any tonic transport, such as a `tonic::transport::Channel`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::DescriptorPool;
+/// use prost_reflect::reflection::{ReflectionClient, ReflectionService};
+///
+/// # async fn example(pool: DescriptorPool) -> Result<(), Box<dyn std::error::Error>> {
+/// let mut client = ReflectionClient::new(ReflectionService::new(pool));
+/// for service in client.list_services().await? {
+///     client.file_containing_symbol(&service).await?;
+/// }
+/// let service = client.pool().get_service_by_name("package.MyService");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct ReflectionClient<T> {
+    inner: Grpc<T>,
+    host: String,
+    service_name: Option<&'static str>,
+    pool: DescriptorPool,
+}
+
+/// An error that occurred while querying a server reflection service.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct ReflectionError {
+    kind: ReflectionErrorKind,
+}
+
+#[derive(Debug)]
+enum ReflectionErrorKind {
+    Status(Box<Status>),
+    Server { code: i32, message: String },
+    UnexpectedResponse,
+    Decode(DecodeError),
+    Descriptor(DescriptorError),
+}
+
+/// A stream containing a single request.
+struct Once(Option<ServerReflectionRequest>);
+
+impl<T> ReflectionClient<T> {
+    /// Creates a client which sends requests using the given transport, and adds the files it
+    /// fetches to a new, empty [`DescriptorPool`].
+    pub fn new(inner: T) -> Self {
+        ReflectionClient::with_pool(inner, DescriptorPool::new())
+    }
+
+    /// Creates a client which sends requests using the given transport, and adds the files it
+    /// fetches to `pool`.
+    ///
+    /// Files already in `pool` are not fetched from the server.
+    pub fn with_pool(inner: T, pool: DescriptorPool) -> Self {
+        ReflectionClient {
+            inner: Grpc::new(inner),
+            host: String::new(),
+            service_name: None,
+            pool,
+        }
+    }
+
+    /// Sets the host name sent with each request, for servers which serve several hosts.
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// Gets a reference to the pool containing the files fetched so far.
+    pub fn pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+
+    /// Consumes this client, returning the pool containing the files fetched so far.
+    pub fn into_pool(self) -> DescriptorPool {
+        self.pool
+    }
+}
+
+impl<T> ReflectionClient<T>
+where
+    T: GrpcService<BoxBody>,
+    T::Error: Into<StdError>,
+    T::ResponseBody: Body<Data = Bytes> + Send + 'static,
+    <T::ResponseBody as Body>::Error: Into<StdError> + Send,
+{
+    /// Gets the full names of the services exposed by the server.
+    pub async fn list_services(&mut self) -> Result<Vec<String>, ReflectionError> {
+        match self
+            .request(MessageRequest::ListServices(String::new()))
+            .await?
+        {
+            MessageResponse::ListServicesResponse(response) => Ok(response
+                .service
+                .into_iter()
+                .map(|service| service.name)
+                .collect()),
+            _ => Err(ReflectionErrorKind::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Fetches the file with the given name, along with its dependencies, and adds them to the
+    /// pool.
+    pub async fn file_by_filename(
+        &mut self,
+        name: &str,
+    ) -> Result<FileDescriptor, ReflectionError> {
+        if let Some(file) = self.pool.get_file_by_name(name) {
+            return Ok(file);
+        }
+
+        let response = self
+            .request(MessageRequest::FileByFilename(name.to_owned()))
+            .await?;
+        self.add_files(response).await
+    }
+
+    /// Fetches the file which defines the symbol with the given fully-qualified name, along with
+    /// its dependencies, and adds them to the pool.
+    ///
+    /// The symbol may be a message, enum, service, extension, or a member of a message or service,
+    /// such as a field or method.
+    pub async fn file_containing_symbol(
+        &mut self,
+        name: &str,
+    ) -> Result<FileDescriptor, ReflectionError> {
+        if let Some(file) = find_symbol_file(&self.pool, name) {
+            return Ok(file);
+        }
+
+        let response = self
+            .request(MessageRequest::FileContainingSymbol(name.to_owned()))
+            .await?;
+        self.add_files(response).await
+    }
+
+    /// Fetches the file which defines the extension of the message `containing_type` with the
+    /// given field number, along with its dependencies, and adds them to the pool.
+    pub async fn file_containing_extension(
+        &mut self,
+        containing_type: &str,
+        extension_number: u32,
+    ) -> Result<FileDescriptor, ReflectionError> {
+        if let Some(extension) = self
+            .pool
+            .get_message_by_name(containing_type)
+            .and_then(|message| message.get_extension(extension_number))
+        {
+            return Ok(extension.parent_file());
+        }
+
+        let response = self
+            .request(MessageRequest::FileContainingExtension(ExtensionRequest {
+                containing_type: containing_type.to_owned(),
+                extension_number: extension_number as i32,
+            }))
+            .await?;
+        self.add_files(response).await
+    }
+
+    /// Gets the field numbers of all extensions of the message `name` known to the server.
+    ///
+    /// The extensions are not added to the pool. Use
+    /// [`file_containing_extension`][Self::file_containing_extension] to fetch them.
+    pub async fn all_extension_numbers_of_type(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<u32>, ReflectionError> {
+        match self
+            .request(MessageRequest::AllExtensionNumbersOfType(name.to_owned()))
+            .await?
+        {
+            MessageResponse::AllExtensionNumbersResponse(response) => Ok(response
+                .extension_number
+                .into_iter()
+                .map(|number| number as u32)
+                .collect()),
+            _ => Err(ReflectionErrorKind::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Decodes the files in a file descriptor response and adds them to the pool, first fetching
+    /// any dependencies which were omitted by the server and are not already in the pool. Returns
+    /// the first file in the response, which is the one requested.
+    async fn add_files(
+        &mut self,
+        response: MessageResponse,
+    ) -> Result<FileDescriptor, ReflectionError> {
+        let files = decode_files(response)?;
+        let name = match files.first() {
+            Some(file) => file.name().to_owned(),
+            None => return Err(ReflectionErrorKind::UnexpectedResponse.into()),
+        };
+
+        let mut received = HashMap::new();
+        let mut missing = Vec::new();
+        for file in files {
+            missing.extend(file.dependency.iter().cloned());
+            received.insert(file.name().to_owned(), file);
+        }
+
+        while let Some(dependency) = missing.pop() {
+            if received.contains_key(&dependency)
+                || self.pool.get_file_by_name(&dependency).is_some()
+            {
+                continue;
+            }
+
+            let response = self
+                .request(MessageRequest::FileByFilename(dependency))
+                .await?;
+            for file in decode_files(response)? {
+                missing.extend(file.dependency.iter().cloned());
+                received.entry(file.name().to_owned()).or_insert(file);
+            }
+        }
+
+        let pool = &self.pool;
+        let files: Vec<FileDescriptorProto> = received
+            .into_values()
+            .filter(|file| pool.get_file_by_name(file.name()).is_none())
+            .collect();
+        self.pool
+            .add_file_descriptor_protos(files)
+            .map_err(ReflectionErrorKind::Descriptor)?;
+        Ok(self
+            .pool
+            .get_file_by_name(&name)
+            .expect("file should have been added to the pool"))
+    }
+
+    /// Sends a request, using the v1alpha service if the server does not implement the v1 service.
+    async fn request(
+        &mut self,
+        message_request: MessageRequest,
+    ) -> Result<MessageResponse, ReflectionError> {
+        let request = ServerReflectionRequest {
+            host: self.host.clone(),
+            message_request: Some(message_request),
+        };
+
+        let service_name = self.service_name.unwrap_or(V1_NAME);
+        let response = match self.call(service_name, request.clone()).await {
+            Err(err) if err.code() == Some(Code::Unimplemented) && self.service_name.is_none() => {
+                let response = self.call(V1ALPHA_NAME, request).await?;
+                self.service_name = Some(V1ALPHA_NAME);
+                response
+            }
+            result => {
+                let response = result?;
+                self.service_name = Some(service_name);
+                response
+            }
+        };
+
+        match response.message_response {
+            Some(MessageResponse::ErrorResponse(error)) => Err(ReflectionErrorKind::Server {
+                code: error.error_code,
+                message: error.error_message,
+            }
+            .into()),
+            Some(response) => Ok(response),
+            None => Err(ReflectionErrorKind::UnexpectedResponse.into()),
+        }
+    }
+
+    async fn call(
+        &mut self,
+        service_name: &str,
+        request: ServerReflectionRequest,
+    ) -> Result<ServerReflectionResponse, ReflectionError> {
+        self.inner.ready().await.map_err(|err| {
+            Status::new(
+                Code::Unknown,
+                format!("service was not ready: {}", err.into()),
+            )
+        })?;
+
+        let path: PathAndQuery = format!("/{}/ServerReflectionInfo", service_name)
+            .parse()
+            .expect("service name should be a valid path");
+        let mut responses = self
+            .inner
+            .streaming(
+                Request::new(Once(Some(request))),
+                path,
+                ProstCodec::default(),
+            )
+            .await?
+            .into_inner();
+        match responses.message().await? {
+            Some(response) => Ok(response),
+            None => Err(ReflectionErrorKind::UnexpectedResponse.into()),
+        }
+    }
+}
+
+fn decode_files(response: MessageResponse) -> Result<Vec<FileDescriptorProto>, ReflectionError> {
+    match response {
+        MessageResponse::FileDescriptorResponse(response) => response
+            .file_descriptor_proto
+            .iter()
+            .map(|bytes| {
+                FileDescriptorProto::decode(bytes.as_slice())
+                    .map_err(|err| ReflectionErrorKind::Decode(err).into())
+            })
+            .collect(),
+        _ => Err(ReflectionErrorKind::UnexpectedResponse.into()),
+    }
+}
+
+impl Stream for Once {
+    type Item = ServerReflectionRequest;
+
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.take())
+    }
+}
+
+impl ReflectionError {
+    /// Gets the gRPC status code of the error, if it was returned by the server.
+    ///
+    /// This includes both failed calls and error responses to individual requests, such as for a
+    /// file or symbol which the server does not know about, which typically have the code
+    /// [`NotFound`][Code::NotFound].
+    pub fn code(&self) -> Option<Code> {
+        match &self.kind {
+            ReflectionErrorKind::Status(status) => Some(status.code()),
+            ReflectionErrorKind::Server { code, .. } => Some(Code::from_i32(*code)),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReflectionErrorKind> for ReflectionError {
+    fn from(kind: ReflectionErrorKind) -> Self {
+        ReflectionError { kind }
+    }
+}
+
+impl From<Status> for ReflectionError {
+    fn from(status: Status) -> Self {
+        ReflectionErrorKind::Status(Box::new(status)).into()
+    }
+}
+
+impl fmt::Display for ReflectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ReflectionErrorKind::Status(status) => write!(
+                f,
+                "reflection request failed with status {:?}: {}",
+                status.code(),
+                status.message()
+            ),
+            ReflectionErrorKind::Server { code, message } => write!(
+                f,
+                "reflection request failed with status {:?}: {}",
+                Code::from_i32(*code),
+                message
+            ),
+            ReflectionErrorKind::UnexpectedResponse => {
+                write!(f, "the server sent an unexpected reflection response")
+            }
+            ReflectionErrorKind::Decode(err) => {
+                write!(f, "failed to decode file descriptor: {}", err)
+            }
+            ReflectionErrorKind::Descriptor(err) => {
+                write!(f, "invalid file descriptor: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for ReflectionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ReflectionErrorKind::Status(err) => Some(err.as_ref()),
+            ReflectionErrorKind::Decode(err) => Some(err),
+            ReflectionErrorKind::Descriptor(err) => Some(err),
+            _ => None,
+        }
+    }
+}
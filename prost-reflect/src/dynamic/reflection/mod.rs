@@ -0,0 +1,134 @@
+mod client;
+mod server;
+
+pub use self::client::{ReflectionClient, ReflectionError};
+pub use self::server::{ReflectionService, ReflectionServiceV1Alpha};
+
+use prost::Message;
+
+use crate::{DescriptorPool, FileDescriptor};
+
+const V1_NAME: &str = "grpc.reflection.v1.ServerReflection";
+const V1ALPHA_NAME: &str = "grpc.reflection.v1alpha.ServerReflection";
+
+/// Finds the file which defines the symbol with the given fully-qualified name, which may be a
+/// message, enum, service, extension, or a member of a message, enum or service.
+fn find_symbol_file(pool: &DescriptorPool, name: &str) -> Option<FileDescriptor> {
+    if let Some(message) = pool.get_message_by_name(name) {
+        return Some(message.parent_file());
+    }
+    if let Some(enum_) = pool.get_enum_by_name(name) {
+        return Some(enum_.parent_file());
+    }
+    if let Some(service) = pool.get_service_by_name(name) {
+        return Some(service.parent_file());
+    }
+    if let Some(extension) = pool.get_extension_by_name(name) {
+        return Some(extension.parent_file());
+    }
+
+    let (parent, member) = name.rsplit_once('.')?;
+    if let Some(message) = pool.get_message_by_name(parent) {
+        if message.get_field_by_name(member).is_some()
+            || message.oneofs().any(|oneof| oneof.name() == member)
+        {
+            return Some(message.parent_file());
+        }
+    }
+    if let Some(service) = pool.get_service_by_name(parent) {
+        if service.methods().any(|method| method.name() == member) {
+            return Some(service.parent_file());
+        }
+    }
+    None
+}
+
+// The request and response messages are identical in `grpc.reflection.v1` and
+// `grpc.reflection.v1alpha`.
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionRequest {
+    #[prost(string, tag = "1")]
+    host: String,
+    #[prost(oneof = "MessageRequest", tags = "3, 4, 5, 6, 7")]
+    message_request: Option<MessageRequest>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum MessageRequest {
+    #[prost(string, tag = "3")]
+    FileByFilename(String),
+    #[prost(string, tag = "4")]
+    FileContainingSymbol(String),
+    #[prost(message, tag = "5")]
+    FileContainingExtension(ExtensionRequest),
+    #[prost(string, tag = "6")]
+    AllExtensionNumbersOfType(String),
+    #[prost(string, tag = "7")]
+    ListServices(String),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ExtensionRequest {
+    #[prost(string, tag = "1")]
+    containing_type: String,
+    #[prost(int32, tag = "2")]
+    extension_number: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServerReflectionResponse {
+    #[prost(string, tag = "1")]
+    valid_host: String,
+    #[prost(message, optional, tag = "2")]
+    original_request: Option<ServerReflectionRequest>,
+    #[prost(oneof = "MessageResponse", tags = "4, 5, 6, 7")]
+    message_response: Option<MessageResponse>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+#[allow(clippy::enum_variant_names)]
+enum MessageResponse {
+    #[prost(message, tag = "4")]
+    FileDescriptorResponse(FileDescriptorResponse),
+    #[prost(message, tag = "5")]
+    AllExtensionNumbersResponse(ExtensionNumberResponse),
+    #[prost(message, tag = "6")]
+    ListServicesResponse(ListServiceResponse),
+    #[prost(message, tag = "7")]
+    ErrorResponse(ErrorResponse),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct FileDescriptorResponse {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    file_descriptor_proto: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ExtensionNumberResponse {
+    #[prost(string, tag = "1")]
+    base_type_name: String,
+    #[prost(int32, repeated, tag = "2")]
+    extension_number: Vec<i32>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ListServiceResponse {
+    #[prost(message, repeated, tag = "1")]
+    service: Vec<ServiceResponse>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ServiceResponse {
+    #[prost(string, tag = "1")]
+    name: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ErrorResponse {
+    #[prost(int32, tag = "1")]
+    error_code: i32,
+    #[prost(string, tag = "2")]
+    error_message: String,
+}
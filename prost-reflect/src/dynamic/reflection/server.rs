@@ -0,0 +1,315 @@
+use std::{
+    collections::HashSet,
+    convert::Infallible,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use prost::Message;
+use tonic1::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{empty_body, futures_core::Stream, http, Body, BoxFuture, Service, StdError},
+    server::{Grpc, NamedService, StreamingService},
+    Code, Request, Response, Status, Streaming,
+};
+
+use super::{
+    find_symbol_file, ErrorResponse, ExtensionNumberResponse, FileDescriptorResponse,
+    ListServiceResponse, MessageRequest, MessageResponse, ServerReflectionRequest,
+    ServerReflectionResponse, ServiceResponse, V1ALPHA_NAME, V1_NAME,
+};
+use crate::{DescriptorPool, FileDescriptor};
+
+/// An implementation of the `grpc.reflection.v1.ServerReflection` service, which describes the
+/// files, services and types of a [`DescriptorPool`] to clients such as
+/// [grpcurl](https://github.com/fullstorydev/grpcurl).
+///
+/// The service can be added to a tonic server alongside the services it describes. Use
+/// [`into_v1alpha`][Self::into_v1alpha] to get the `grpc.reflection.v1alpha.ServerReflection`
+/// service, for clients which do not support the stable version.
+///
+/// Files are returned together with their transitive dependencies, omitting any dependencies which
+/// were already sent earlier in the same stream.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::DescriptorPool;
+/// use prost_reflect::reflection::ReflectionService;
+///
+/// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+/// let service = ReflectionService::new(pool.clone());
+/// let v1alpha_service = ReflectionService::new(pool).into_v1alpha();
+/// // tonic::transport::Server::builder()
+/// //     .add_service(service)
+/// //     .add_service(v1alpha_service)
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct ReflectionService {
+    inner: Reflection,
+}
+
+/// An implementation of the `grpc.reflection.v1alpha.ServerReflection` service.
+///
+/// This behaves identically to [`ReflectionService`], and is created using
+/// [`ReflectionService::into_v1alpha`].
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub struct ReflectionServiceV1Alpha {
+    inner: Reflection,
+}
+
+#[derive(Debug, Clone)]
+struct Reflection {
+    pool: DescriptorPool,
+    max_decoding_message_size: Option<usize>,
+    max_encoding_message_size: Option<usize>,
+}
+
+struct ReflectionInfo {
+    pool: DescriptorPool,
+}
+
+struct ResponseStream {
+    pool: DescriptorPool,
+    requests: Streaming<ServerReflectionRequest>,
+    sent_files: HashSet<String>,
+}
+
+impl ReflectionService {
+    /// Creates a reflection service which describes the contents of `pool`.
+    pub fn new(pool: DescriptorPool) -> Self {
+        ReflectionService {
+            inner: Reflection {
+                pool,
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            },
+        }
+    }
+
+    /// Limits the size of request messages accepted by the service.
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.inner.max_decoding_message_size = Some(limit);
+        self
+    }
+
+    /// Limits the size of response messages sent by the service.
+    pub fn max_encoding_message_size(mut self, limit: usize) -> Self {
+        self.inner.max_encoding_message_size = Some(limit);
+        self
+    }
+
+    /// Converts this into the equivalent `grpc.reflection.v1alpha.ServerReflection` service.
+    pub fn into_v1alpha(self) -> ReflectionServiceV1Alpha {
+        ReflectionServiceV1Alpha { inner: self.inner }
+    }
+}
+
+impl NamedService for ReflectionService {
+    const NAME: &'static str = V1_NAME;
+}
+
+impl NamedService for ReflectionServiceV1Alpha {
+    const NAME: &'static str = V1ALPHA_NAME;
+}
+
+impl<B> Service<http::Request<B>> for ReflectionService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.inner.call(V1_NAME, req)
+    }
+}
+
+impl<B> Service<http::Request<B>> for ReflectionServiceV1Alpha
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        self.inner.call(V1ALPHA_NAME, req)
+    }
+}
+
+impl Reflection {
+    fn call<B>(
+        &self,
+        service_name: &str,
+        req: http::Request<B>,
+    ) -> BoxFuture<http::Response<BoxBody>, Infallible>
+    where
+        B: Body + Send + 'static,
+        B::Error: Into<StdError> + Send + 'static,
+    {
+        let path = req.uri().path();
+        let is_info_method = path
+            .strip_prefix('/')
+            .and_then(|path| path.strip_prefix(service_name))
+            == Some("/ServerReflectionInfo");
+        if !is_info_method {
+            return Box::pin(async move {
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", (Code::Unimplemented as i32).to_string())
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap())
+            });
+        }
+
+        let method = ReflectionInfo {
+            pool: self.pool.clone(),
+        };
+        let mut grpc = Grpc::new(ProstCodec::default()).apply_max_message_size_config(
+            self.max_decoding_message_size,
+            self.max_encoding_message_size,
+        );
+        Box::pin(async move { Ok(grpc.streaming(method, req).await) })
+    }
+}
+
+impl StreamingService<ServerReflectionRequest> for ReflectionInfo {
+    type Response = ServerReflectionResponse;
+    type ResponseStream = ResponseStream;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<ServerReflectionRequest>>) -> Self::Future {
+        let stream = ResponseStream {
+            pool: self.pool.clone(),
+            requests: request.into_inner(),
+            sent_files: HashSet::new(),
+        };
+        Box::pin(async move { Ok(Response::new(stream)) })
+    }
+}
+
+impl Stream for ResponseStream {
+    type Item = Result<ServerReflectionResponse, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.requests).poll_next(cx) {
+            Poll::Ready(Some(Ok(request))) => Poll::Ready(Some(Ok(self.respond(request)))),
+            Poll::Ready(Some(Err(status))) => Poll::Ready(Some(Err(status))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl ResponseStream {
+    fn respond(&mut self, request: ServerReflectionRequest) -> ServerReflectionResponse {
+        let message_response = match &request.message_request {
+            Some(MessageRequest::FileByFilename(name)) => match self.pool.get_file_by_name(name) {
+                Some(file) => self.file_response(file),
+                None => error_response(Code::NotFound, format!("file '{}' not found", name)),
+            },
+            Some(MessageRequest::FileContainingSymbol(name)) => {
+                match find_symbol_file(&self.pool, name) {
+                    Some(file) => self.file_response(file),
+                    None => error_response(Code::NotFound, format!("symbol '{}' not found", name)),
+                }
+            }
+            Some(MessageRequest::FileContainingExtension(request)) => {
+                let extension = self
+                    .pool
+                    .get_message_by_name(&request.containing_type)
+                    .zip(u32::try_from(request.extension_number).ok())
+                    .and_then(|(message, number)| message.get_extension(number));
+                match extension {
+                    Some(extension) => self.file_response(extension.parent_file()),
+                    None => error_response(
+                        Code::NotFound,
+                        format!(
+                            "extension {} of message '{}' not found",
+                            request.extension_number, request.containing_type
+                        ),
+                    ),
+                }
+            }
+            Some(MessageRequest::AllExtensionNumbersOfType(name)) => {
+                match self.pool.get_message_by_name(name) {
+                    Some(message) => {
+                        let mut extension_number: Vec<i32> = message
+                            .extensions()
+                            .map(|extension| extension.number() as i32)
+                            .collect();
+                        extension_number.sort_unstable();
+                        MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+                            base_type_name: name.clone(),
+                            extension_number,
+                        })
+                    }
+                    None => error_response(Code::NotFound, format!("message '{}' not found", name)),
+                }
+            }
+            Some(MessageRequest::ListServices(_)) => {
+                MessageResponse::ListServicesResponse(ListServiceResponse {
+                    service: self
+                        .pool
+                        .services()
+                        .map(|service| ServiceResponse {
+                            name: service.full_name().to_owned(),
+                        })
+                        .collect(),
+                })
+            }
+            None => error_response(Code::InvalidArgument, "no request was set".to_owned()),
+        };
+
+        ServerReflectionResponse {
+            valid_host: request.host.clone(),
+            original_request: Some(request),
+            message_response: Some(message_response),
+        }
+    }
+
+    /// Gets the response for a request for `file`, which includes the file itself followed by any
+    /// of its transitive dependencies which have not been sent yet on this stream.
+    fn file_response(&mut self, file: FileDescriptor) -> MessageResponse {
+        let mut file_descriptor_proto = vec![file.file_descriptor_proto().encode_to_vec()];
+        self.sent_files.insert(file.name().to_owned());
+
+        let mut stack: Vec<FileDescriptor> = file.dependencies().collect();
+        stack.reverse();
+        while let Some(dependency) = stack.pop() {
+            if self.sent_files.insert(dependency.name().to_owned()) {
+                file_descriptor_proto.push(dependency.file_descriptor_proto().encode_to_vec());
+                let len = stack.len();
+                stack.extend(dependency.dependencies());
+                stack[len..].reverse();
+            }
+        }
+
+        MessageResponse::FileDescriptorResponse(FileDescriptorResponse {
+            file_descriptor_proto,
+        })
+    }
+}
+
+fn error_response(code: Code, error_message: String) -> MessageResponse {
+    MessageResponse::ErrorResponse(ErrorResponse {
+        error_code: code as i32,
+        error_message,
+    })
+}
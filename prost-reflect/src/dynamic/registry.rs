@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{DescriptorPool, MessageDescriptor, TypeResolver};
+
+type FetchFn = dyn Fn(&str) -> Option<MessageDescriptor> + Send + Sync;
+type FetchFuture = Pin<Box<dyn Future<Output = Option<MessageDescriptor>> + Send>>;
+type FetchAsyncFn = dyn Fn(String) -> FetchFuture + Send + Sync;
+
+/// A [`TypeResolver`] which caches the message types of `google.protobuf.Any` payloads by type
+/// URL.
+///
+/// Types are looked up in each of the registered [`DescriptorPool`]s in turn, using the type URL
+/// prefixes `type.googleapis.com/` and `type.googleprod.com/`. Types which are not found in any
+/// pool may be fetched using a callback, for example from a remote schema registry. Successful
+/// lookups are cached, so each type URL is only parsed and looked up once.
+///
+/// Clones of a registry share the same cache, so a registry can be cloned cheaply into the
+/// options for each format, or passed to [`DynamicMessage::unpack_any_with_resolver`][crate::DynamicMessage::unpack_any_with_resolver].
+///
+/// Serialization and deserialization are synchronous, so they only use the callback set with
+/// [`fetch`][AnyRegistry::fetch]. Types fetched with [`fetch_async`][AnyRegistry::fetch_async]
+/// can be loaded into the cache ahead of time by calling
+/// [`resolve_async`][AnyRegistry::resolve_async].
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{AnyRegistry, DescriptorPool, TypeResolver};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let fallback_pool = pool.clone();
+/// let registry = AnyRegistry::new()
+///     .pool(pool.clone())
+///     .fetch(move |type_url: &str| {
+///         fallback_pool.get_message_by_name(type_url.strip_prefix("example.com/schemas/")?)
+///     });
+///
+/// assert!(registry.resolve_message_type("type.googleapis.com/package.MyMessage").is_some());
+/// let desc = registry.resolve_message_type("example.com/schemas/package.MyMessage").unwrap();
+/// assert_eq!(desc.full_name(), "package.MyMessage");
+/// assert_eq!(registry.get_cached("example.com/schemas/package.MyMessage"), Some(desc));
+/// ```
+#[derive(Clone, Default)]
+pub struct AnyRegistry {
+    pools: Vec<DescriptorPool>,
+    fetch: Option<Arc<FetchFn>>,
+    fetch_async: Option<Arc<FetchAsyncFn>>,
+    cache: Arc<RwLock<HashMap<String, MessageDescriptor>>>,
+}
+
+impl AnyRegistry {
+    /// Creates a new, empty registry.
+    pub fn new() -> Self {
+        AnyRegistry::default()
+    }
+
+    /// Adds a pool to look up types in.
+    ///
+    /// Pools are searched in the order they are added.
+    pub fn pool(mut self, pool: DescriptorPool) -> Self {
+        self.pools.push(pool);
+        self
+    }
+
+    /// Sets a callback to fetch types which are not found in any pool.
+    ///
+    /// The callback receives the full type URL, and any domain is accepted.
+    pub fn fetch<F>(mut self, fetch: F) -> Self
+    where
+        F: Fn(&str) -> Option<MessageDescriptor> + Send + Sync + 'static,
+    {
+        self.fetch = Some(Arc::new(fetch));
+        self
+    }
+
+    /// Sets an asynchronous callback to fetch types which are not found in any pool, used by
+    /// [`resolve_async`][AnyRegistry::resolve_async].
+    ///
+    /// The callback receives the full type URL, and any domain is accepted.
+    pub fn fetch_async<F, Fut>(mut self, fetch: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<MessageDescriptor>> + Send + 'static,
+    {
+        self.fetch_async = Some(Arc::new(move |type_url| Box::pin(fetch(type_url))));
+        self
+    }
+
+    /// Adds a type to the cache under the given type URL, replacing any existing entry.
+    pub fn insert(&self, type_url: impl Into<String>, desc: MessageDescriptor) {
+        self.write_cache().insert(type_url.into(), desc);
+    }
+
+    /// Gets the type cached for the given type URL, without looking it up.
+    pub fn get_cached(&self, type_url: &str) -> Option<MessageDescriptor> {
+        self.read_cache().get(type_url).cloned()
+    }
+
+    /// Removes all cached types.
+    pub fn clear_cache(&self) {
+        self.write_cache().clear();
+    }
+
+    /// Looks up the type for the given type URL, using the cache, the registered pools and the
+    /// asynchronous fetch callback, falling back to the synchronous one if it is not set.
+    ///
+    /// Types found are added to the cache, so they are available to later synchronous lookups,
+    /// for example while serializing messages containing them.
+    pub async fn resolve_async(&self, type_url: &str) -> Option<MessageDescriptor> {
+        if let Some(desc) = self.resolve_local(type_url) {
+            return Some(desc);
+        }
+
+        let desc = match &self.fetch_async {
+            Some(fetch_async) => fetch_async(type_url.to_owned()).await,
+            None => self.fetch.as_ref().and_then(|fetch| fetch(type_url)),
+        }?;
+        self.insert(type_url, desc.clone());
+        Some(desc)
+    }
+
+    /// Looks up a type in the cache and the pools, caching it if found in a pool.
+    fn resolve_local(&self, type_url: &str) -> Option<MessageDescriptor> {
+        if let Some(desc) = self.get_cached(type_url) {
+            return Some(desc);
+        }
+
+        let desc = self.pools.resolve_message_type(type_url)?;
+        self.insert(type_url, desc.clone());
+        Some(desc)
+    }
+
+    fn read_cache(&self) -> RwLockReadGuard<'_, HashMap<String, MessageDescriptor>> {
+        match self.cache.read() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+
+    fn write_cache(&self) -> RwLockWriteGuard<'_, HashMap<String, MessageDescriptor>> {
+        match self.cache.write() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+impl TypeResolver for AnyRegistry {
+    fn resolve_message_type(&self, type_url: &str) -> Option<MessageDescriptor> {
+        if let Some(desc) = self.resolve_local(type_url) {
+            return Some(desc);
+        }
+
+        let desc = self.fetch.as_ref().and_then(|fetch| fetch(type_url))?;
+        self.insert(type_url, desc.clone());
+        Some(desc)
+    }
+}
+
+impl fmt::Debug for AnyRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyRegistry")
+            .field("pools", &self.pools)
+            .field("cached", &self.read_cache().len())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use prost::{
+    bytes::{Buf, Bytes},
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
+};
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor};
+
+/// The encoding used for a repeated scalar field in the decoded bytes.
+///
+/// See [`DecodeReport::list_encoding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEncoding {
+    /// All occurrences of the field used [packed encoding](https://developers.google.com/protocol-buffers/docs/encoding#packed).
+    Packed,
+    /// All occurrences of the field encoded each element as a separate record.
+    Unpacked,
+    /// The field was seen with both packed and unpacked encodings.
+    Mixed,
+}
+
+/// Information about the encoding of the input, collected by [`DynamicMessage::decode_with_report`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DecodeReport {
+    list_encodings: HashMap<String, ListEncoding>,
+}
+
+impl DynamicMessage {
+    /// Decodes an instance of the message type specified by the [`MessageDescriptor`] from the buffer,
+    /// and reports how the repeated scalar fields in the input were encoded.
+    ///
+    /// Decoding always accepts both packed and unpacked encodings for repeated scalar fields,
+    /// regardless of the value of [`FieldDescriptor::is_packed`], as required by the protobuf
+    /// specification. This method behaves identically to [`decode`][DynamicMessage::decode], but
+    /// additionally records which encoding was seen for each field, which can be useful when
+    /// dealing with writers using different versions of a schema.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, ListEncoding, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyList").unwrap();
+    /// let field = message_descriptor.get_field_by_name("values").unwrap();
+    /// assert!(field.is_packed());
+    ///
+    /// // Unpacked encoding of [1, 2]
+    /// let (message, report) = DynamicMessage::decode_with_report(message_descriptor.clone(), b"\x08\x01\x08\x02".as_ref()).unwrap();
+    /// assert_eq!(message.get_field(&field).as_ref(), &Value::List(vec![Value::I32(1), Value::I32(2)]));
+    /// assert_eq!(report.list_encoding(&field), Some(ListEncoding::Unpacked));
+    /// assert!(report.has_mismatched_encoding(&message_descriptor));
+    ///
+    /// // Packed encoding of [1, 2]
+    /// let (message, report) = DynamicMessage::decode_with_report(message_descriptor.clone(), b"\x0a\x02\x01\x02".as_ref()).unwrap();
+    /// assert_eq!(message.get_field(&field).as_ref(), &Value::List(vec![Value::I32(1), Value::I32(2)]));
+    /// assert_eq!(report.list_encoding(&field), Some(ListEncoding::Packed));
+    /// assert!(!report.has_mismatched_encoding(&message_descriptor));
+    /// ```
+    pub fn decode_with_report<B>(
+        desc: MessageDescriptor,
+        mut buf: B,
+    ) -> Result<(Self, DecodeReport), DecodeError>
+    where
+        B: Buf,
+    {
+        let bytes = buf.copy_to_bytes(buf.remaining());
+        let message = DynamicMessage::decode(desc, bytes.clone())?;
+
+        let mut report = DecodeReport::default();
+        report.scan(&message.desc, bytes)?;
+        Ok((message, report))
+    }
+}
+
+impl DecodeReport {
+    /// Returns the encoding seen for the given repeated scalar field, or `None` if the field did
+    /// not occur in the input.
+    ///
+    /// Occurrences of the field in nested messages are included. Fields of messages
+    /// contained within maps or groups are not tracked.
+    pub fn list_encoding(&self, field_desc: &FieldDescriptor) -> Option<ListEncoding> {
+        self.list_encodings.get(field_desc.full_name()).copied()
+    }
+
+    /// Returns `true` if any repeated scalar field in the input used an encoding different to the
+    /// one specified by its descriptor.
+    pub fn has_mismatched_encoding(&self, desc: &MessageDescriptor) -> bool {
+        self.has_mismatched_encoding_inner(desc, &mut Vec::new())
+    }
+
+    fn has_mismatched_encoding_inner(
+        &self,
+        desc: &MessageDescriptor,
+        visited: &mut Vec<MessageDescriptor>,
+    ) -> bool {
+        if visited.contains(desc) {
+            return false;
+        }
+        visited.push(desc.clone());
+
+        desc.fields().any(|field_desc| {
+            let expected = if field_desc.is_packed() {
+                ListEncoding::Packed
+            } else {
+                ListEncoding::Unpacked
+            };
+            match self.list_encoding(&field_desc) {
+                Some(encoding) if encoding != expected => return true,
+                _ => (),
+            }
+
+            match field_desc.kind() {
+                Kind::Message(message_desc) if !field_desc.is_map() && !field_desc.is_group() => {
+                    self.has_mismatched_encoding_inner(&message_desc, visited)
+                }
+                _ => false,
+            }
+        })
+    }
+
+    fn scan(&mut self, desc: &MessageDescriptor, mut buf: Bytes) -> Result<(), DecodeError> {
+        while buf.has_remaining() {
+            let (number, wire_type) = encoding::decode_key(&mut buf)?;
+            let field_desc = match desc.get_field(number) {
+                Some(field_desc) => field_desc,
+                None => {
+                    encoding::skip_field(wire_type, number, &mut buf, DecodeContext::default())?;
+                    continue;
+                }
+            };
+
+            if field_desc.is_list() && field_desc.is_packable() {
+                let encoding = if wire_type == WireType::LengthDelimited {
+                    ListEncoding::Packed
+                } else {
+                    ListEncoding::Unpacked
+                };
+                self.record(&field_desc, encoding);
+            }
+
+            match field_desc.kind() {
+                Kind::Message(message_desc)
+                    if wire_type == WireType::LengthDelimited && !field_desc.is_map() =>
+                {
+                    let len = encoding::decode_varint(&mut buf)?;
+                    if len > buf.remaining() as u64 {
+                        return Err(DecodeError::new("buffer underflow"));
+                    }
+                    let nested = buf.split_to(len as usize);
+                    self.scan(&message_desc, nested)?;
+                }
+                _ => encoding::skip_field(wire_type, number, &mut buf, DecodeContext::default())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record(&mut self, field_desc: &FieldDescriptor, encoding: ListEncoding) {
+        self.list_encodings
+            .entry(field_desc.full_name().to_owned())
+            .and_modify(|existing| {
+                if *existing != encoding {
+                    *existing = ListEncoding::Mixed;
+                }
+            })
+            .or_insert(encoding);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{fmt, sync::Arc};
+
+use crate::{
+    descriptor::{GOOGLE_APIS_DOMAIN, GOOGLE_PROD_DOMAIN},
+    DescriptorPool, MessageDescriptor,
+};
+
+/// Looks up the message type of a `google.protobuf.Any` payload from its type URL.
+///
+/// When serializing or deserializing JSON, or formatting or parsing the text format, the payload of
+/// an `Any` message is expanded using its message type. By default, the type is looked up in the
+/// [`DescriptorPool`] of the `Any` message, using the type URL prefixes `type.googleapis.com/` and
+/// `type.googleprod.com/`. A custom resolver can be set using the options for each format to look up
+/// types elsewhere, for example in several pools or from a remote schema registry.
+///
+/// This trait is implemented for:
+///
+/// - [`DescriptorPool`], which matches the default behaviour.
+/// - Vectors of resolvers, which return the first match.
+/// - Functions and closures with the signature `Fn(&str) -> Option<MessageDescriptor>`, which
+///   receive the full type URL.
+///
+/// # Examples
+///
+/// ```
+/// # #[cfg(feature = "serde-json")] {
+/// # use prost::Message;
+/// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions, Value};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// let any_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+/// let mut any = DynamicMessage::new(any_descriptor);
+/// any.set_field_by_name("type_url", Value::String("example.com/schemas/package.MyMessage".to_owned()));
+/// any.set_field_by_name("value", Value::Bytes(b"\x08\x96\x01".as_ref().into()));
+///
+/// // The default resolver only recognizes the `type.googleapis.com` domain.
+/// let resolver_pool = pool.clone();
+/// let options = SerializeOptions::new().type_resolver(move |type_url: &str| {
+///     let message_name = type_url.strip_prefix("example.com/schemas/")?;
+///     resolver_pool.get_message_by_name(message_name)
+/// });
+/// let mut serializer = serde_json::Serializer::new(vec![]);
+/// any.serialize_with_options(&mut serializer, &options).unwrap();
+/// assert_eq!(serializer.into_inner(), br#"{"@type":"example.com/schemas/package.MyMessage","foo":150}"#);
+/// # }
+/// ```
+pub trait TypeResolver: Send + Sync {
+    /// Returns the message type for the given type URL, or `None` if it is not known.
+    fn resolve_message_type(&self, type_url: &str) -> Option<MessageDescriptor>;
+}
+
+impl TypeResolver for DescriptorPool {
+    fn resolve_message_type(&self, type_url: &str) -> Option<MessageDescriptor> {
+        let message_name = type_url
+            .strip_prefix(GOOGLE_APIS_DOMAIN)
+            .or_else(|| type_url.strip_prefix(GOOGLE_PROD_DOMAIN))?;
+        self.get_message_by_name(message_name)
+    }
+}
+
+impl<R> TypeResolver for Vec<R>
+where
+    R: TypeResolver,
+{
+    fn resolve_message_type(&self, type_url: &str) -> Option<MessageDescriptor> {
+        self.iter()
+            .find_map(|resolver| resolver.resolve_message_type(type_url))
+    }
+}
+
+impl<F> TypeResolver for F
+where
+    F: Fn(&str) -> Option<MessageDescriptor> + Send + Sync,
+{
+    fn resolve_message_type(&self, type_url: &str) -> Option<MessageDescriptor> {
+        self(type_url)
+    }
+}
+
+impl fmt::Debug for dyn TypeResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TypeResolver")
+    }
+}
+
+/// Looks up the payload type of an `Any` message, using `resolver` if set or `pool` otherwise.
+///
+/// On failure, returns a description of the error.
+pub(crate) fn resolve_any_type(
+    resolver: Option<&Arc<dyn TypeResolver>>,
+    pool: &DescriptorPool,
+    type_url: &str,
+) -> Result<MessageDescriptor, String> {
+    let message_desc = match resolver {
+        Some(resolver) => resolver.resolve_message_type(type_url),
+        None => pool.resolve_message_type(type_url),
+    };
+
+    message_desc.ok_or_else(|| {
+        if resolver.is_none()
+            && !type_url.starts_with(GOOGLE_APIS_DOMAIN)
+            && !type_url.starts_with(GOOGLE_PROD_DOMAIN)
+        {
+            format!("unsupported type url '{}'", type_url)
+        } else {
+            format!(
+                "message '{}' not found",
+                type_url.rsplit('/').next().unwrap_or_default()
+            )
+        }
+    })
+}
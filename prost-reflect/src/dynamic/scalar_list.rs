@@ -0,0 +1,226 @@
+use prost::{
+    bytes::Buf,
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
+};
+use smallvec::SmallVec;
+
+use crate::{Kind, Value};
+
+use super::message::packed_list_len;
+
+/// The values of a decoded repeated numeric, boolean or enum field.
+///
+/// The values are stored as a typed list rather than as a [`Value`] for each element, and lists
+/// of up to 24 bytes, such as three 64-bit numbers, are stored inline without allocating. The
+/// list is converted to a [`Value::List`] when it is read, or replaced by one when it is modified.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum ScalarList {
+    Bool(SmallVec<[bool; 24]>),
+    I32(SmallVec<[i32; 6]>),
+    I64(SmallVec<[i64; 3]>),
+    U32(SmallVec<[u32; 6]>),
+    U64(SmallVec<[u64; 3]>),
+    F32(SmallVec<[f32; 6]>),
+    F64(SmallVec<[f64; 3]>),
+    EnumNumber(SmallVec<[i32; 6]>),
+}
+
+macro_rules! each_list {
+    ($list:expr, $values:ident => $body:expr) => {
+        match $list {
+            ScalarList::Bool($values) => $body,
+            ScalarList::I32($values) => $body,
+            ScalarList::I64($values) => $body,
+            ScalarList::U32($values) => $body,
+            ScalarList::U64($values) => $body,
+            ScalarList::F32($values) => $body,
+            ScalarList::F64($values) => $body,
+            ScalarList::EnumNumber($values) => $body,
+        }
+    };
+}
+
+impl ScalarList {
+    /// Creates an empty list for values of the given type, or returns `None` if the type is a
+    /// string, bytes or message type.
+    pub(super) fn new(kind: &Kind) -> Option<Self> {
+        match kind {
+            Kind::Bool => Some(ScalarList::Bool(SmallVec::new())),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Some(ScalarList::I32(SmallVec::new())),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Some(ScalarList::I64(SmallVec::new())),
+            Kind::Uint32 | Kind::Fixed32 => Some(ScalarList::U32(SmallVec::new())),
+            Kind::Uint64 | Kind::Fixed64 => Some(ScalarList::U64(SmallVec::new())),
+            Kind::Float => Some(ScalarList::F32(SmallVec::new())),
+            Kind::Double => Some(ScalarList::F64(SmallVec::new())),
+            Kind::Enum(_) => Some(ScalarList::EnumNumber(SmallVec::new())),
+            Kind::String | Kind::Bytes | Kind::Message(_) => None,
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        each_list!(self, values => values.len())
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the values are stored inline rather than on the heap.
+    #[cfg(test)]
+    pub(super) fn is_inline(&self) -> bool {
+        each_list!(self, values => !values.spilled())
+    }
+
+    /// Converts the list to a [`Value::List`].
+    pub(super) fn to_value(&self) -> Value {
+        Value::List(match self {
+            ScalarList::Bool(values) => values.iter().map(|&v| Value::Bool(v)).collect(),
+            ScalarList::I32(values) => values.iter().map(|&v| Value::I32(v)).collect(),
+            ScalarList::I64(values) => values.iter().map(|&v| Value::I64(v)).collect(),
+            ScalarList::U32(values) => values.iter().map(|&v| Value::U32(v)).collect(),
+            ScalarList::U64(values) => values.iter().map(|&v| Value::U64(v)).collect(),
+            ScalarList::F32(values) => values.iter().map(|&v| Value::F32(v)).collect(),
+            ScalarList::F64(values) => values.iter().map(|&v| Value::F64(v)).collect(),
+            ScalarList::EnumNumber(values) => {
+                values.iter().map(|&v| Value::EnumNumber(v)).collect()
+            }
+        })
+    }
+
+    /// Returns `true` if the list contains the same values as `values`.
+    pub(super) fn eq_values(&self, values: &[Value]) -> bool {
+        fn eq<T: PartialEq + Copy>(
+            list: &[T],
+            values: &[Value],
+            get: impl Fn(&Value) -> Option<T>,
+        ) -> bool {
+            list.len() == values.len() && list.iter().zip(values).all(|(&a, b)| get(b) == Some(a))
+        }
+
+        match self {
+            ScalarList::Bool(list) => eq(list, values, Value::as_bool),
+            ScalarList::I32(list) => eq(list, values, Value::as_i32),
+            ScalarList::I64(list) => eq(list, values, Value::as_i64),
+            ScalarList::U32(list) => eq(list, values, Value::as_u32),
+            ScalarList::U64(list) => eq(list, values, Value::as_u64),
+            ScalarList::F32(list) => eq(list, values, Value::as_f32),
+            ScalarList::F64(list) => eq(list, values, Value::as_f64),
+            ScalarList::EnumNumber(list) => eq(list, values, Value::as_enum_number),
+        }
+    }
+
+    pub(super) fn push(&mut self, value: Value) {
+        match (self, value) {
+            (ScalarList::Bool(values), Value::Bool(value)) => values.push(value),
+            (ScalarList::I32(values), Value::I32(value)) => values.push(value),
+            (ScalarList::I64(values), Value::I64(value)) => values.push(value),
+            (ScalarList::U32(values), Value::U32(value)) => values.push(value),
+            (ScalarList::U64(values), Value::U64(value)) => values.push(value),
+            (ScalarList::F32(values), Value::F32(value)) => values.push(value),
+            (ScalarList::F64(values), Value::F64(value)) => values.push(value),
+            (ScalarList::EnumNumber(values), Value::EnumNumber(value)) => values.push(value),
+            (list, value) => panic!(
+                "mismatch between DynamicMessage list {:?} and value {:?}",
+                list, value
+            ),
+        }
+    }
+
+    /// Merges a value, or a packed list of values, of the given type into the list.
+    pub(super) fn merge<B>(
+        &mut self,
+        kind: &Kind,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        if wire_type == WireType::LengthDelimited {
+            let len = packed_list_len(kind, buf.chunk());
+            each_list!(&mut *self, values => values.reserve(len));
+            encoding::merge_loop(self, buf, ctx, |list, buf, ctx| {
+                list.merge_value(kind, kind.wire_type(), buf, ctx)
+            })
+        } else {
+            self.merge_value(kind, wire_type, buf, ctx)
+        }
+    }
+
+    fn merge_value<B>(
+        &mut self,
+        kind: &Kind,
+        wire_type: WireType,
+        buf: &mut B,
+        ctx: DecodeContext,
+    ) -> Result<(), DecodeError>
+    where
+        B: Buf,
+    {
+        fn decode<T, B>(
+            merge: fn(WireType, &mut T, &mut B, DecodeContext) -> Result<(), DecodeError>,
+            wire_type: WireType,
+            buf: &mut B,
+            ctx: DecodeContext,
+        ) -> Result<T, DecodeError>
+        where
+            T: Default,
+        {
+            let mut value = T::default();
+            merge(wire_type, &mut value, buf, ctx)?;
+            Ok(value)
+        }
+
+        match (self, kind) {
+            (ScalarList::Bool(values), Kind::Bool) => {
+                values.push(decode(encoding::bool::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I32(values), Kind::Int32) => {
+                values.push(decode(encoding::int32::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I32(values), Kind::Sint32) => {
+                values.push(decode(encoding::sint32::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I32(values), Kind::Sfixed32) => {
+                values.push(decode(encoding::sfixed32::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I64(values), Kind::Int64) => {
+                values.push(decode(encoding::int64::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I64(values), Kind::Sint64) => {
+                values.push(decode(encoding::sint64::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::I64(values), Kind::Sfixed64) => {
+                values.push(decode(encoding::sfixed64::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::U32(values), Kind::Uint32) => {
+                values.push(decode(encoding::uint32::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::U32(values), Kind::Fixed32) => {
+                values.push(decode(encoding::fixed32::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::U64(values), Kind::Uint64) => {
+                values.push(decode(encoding::uint64::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::U64(values), Kind::Fixed64) => {
+                values.push(decode(encoding::fixed64::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::F32(values), Kind::Float) => {
+                values.push(decode(encoding::float::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::F64(values), Kind::Double) => {
+                values.push(decode(encoding::double::merge, wire_type, buf, ctx)?)
+            }
+            (ScalarList::EnumNumber(values), Kind::Enum(_)) => {
+                values.push(decode(encoding::int32::merge, wire_type, buf, ctx)?)
+            }
+            (list, kind) => panic!(
+                "mismatch between DynamicMessage list {:?} and type {:?}",
+                list, kind
+            ),
+        }
+        Ok(())
+    }
+}
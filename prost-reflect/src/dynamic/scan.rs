@@ -0,0 +1,53 @@
+use prost::{
+    bytes::Buf,
+    encoding::{self, DecodeContext, WireType},
+};
+
+/// Returns an iterator over the number and wire type of each top-level field in the encoded
+/// message `buf`, without decoding any field values.
+///
+/// This only parses field keys and skips over the field contents, so it is much cheaper than a
+/// full decode. It can be used to make routing or sampling decisions based on which fields are
+/// present in a message before committing to decoding it.
+///
+/// A field is reported once for each time it occurs in the input, so repeated fields, or fields
+/// which were merged from multiple messages, may be reported more than once. Fields are reported
+/// in the order they occur in the input, which is not necessarily in order of field number.
+///
+/// If the input is malformed, the iterator stops at the first invalid field. The contents of
+/// length-delimited fields are not validated, so a subsequent call to
+/// [`DynamicMessage::decode`][crate::DynamicMessage::decode] may still fail even if all fields
+/// were scanned successfully.
+///
+/// # Examples
+///
+/// ```
+/// # use prost::encoding::WireType;
+/// use prost_reflect::scan_field_numbers;
+///
+/// let fields: Vec<(u32, WireType)> = scan_field_numbers(b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).collect();
+/// assert_eq!(fields, vec![(1, WireType::Varint), (3, WireType::LengthDelimited)]);
+/// ```
+pub fn scan_field_numbers<B>(mut buf: B) -> impl Iterator<Item = (u32, WireType)>
+where
+    B: Buf,
+{
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done || !buf.has_remaining() {
+            return None;
+        }
+
+        let result = encoding::decode_key(&mut buf).and_then(|(number, wire_type)| {
+            encoding::skip_field(wire_type, number, &mut buf, DecodeContext::default())?;
+            Ok((number, wire_type))
+        });
+        match result {
+            Ok(field) => Some(field),
+            Err(_) => {
+                done = true;
+                None
+            }
+        }
+    })
+}
@@ -0,0 +1,131 @@
+use std::io;
+
+use serde_json::ser::Formatter;
+
+use super::ser::widen_f32;
+
+/// A [`Formatter`] for `serde_json` which writes compact JSON, with numbers formatted as required
+/// by [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785).
+///
+/// Numbers are written using the ECMAScript `Number.prototype.toString` algorithm, so for example
+/// `1.0` is written as `1`, `1e21` as `1e+21` and `1e-6` as `0.000001`. Strings are escaped as
+/// required by the RFC by the default `serde_json` implementation.
+///
+/// This formatter only controls the textual form of the output. To produce canonical JSON for a
+/// message, it should be combined with the [`canonical`][crate::SerializeOptions::canonical]
+/// serialization option, which sorts object keys.
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct CanonicalJsonFormatter {
+    _private: (),
+}
+
+impl CanonicalJsonFormatter {
+    /// Creates a new instance of [`CanonicalJsonFormatter`].
+    pub const fn new() -> Self {
+        CanonicalJsonFormatter { _private: () }
+    }
+}
+
+impl Formatter for CanonicalJsonFormatter {
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        self.write_f64(writer, widen_f32(value))
+    }
+
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(format_f64(value).as_bytes())
+    }
+}
+
+/// Formats a finite double using the ECMAScript `Number.prototype.toString` algorithm.
+fn format_f64(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_owned();
+    }
+
+    // The exponential form gives the shortest digits which round trip, as required by ECMAScript.
+    let exponential = format!("{:e}", value.abs());
+    let (mantissa, exponent) = exponential
+        .split_once('e')
+        .expect("invalid exponential format");
+    let digits = mantissa.replace('.', "");
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().expect("invalid exponent") + 1;
+
+    let mut result = String::new();
+    if value < 0.0 {
+        result.push('-');
+    }
+
+    if k <= n && n <= 21 {
+        result.push_str(&digits);
+        result.extend(std::iter::repeat('0').take((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        result.push_str(&digits[..n as usize]);
+        result.push('.');
+        result.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        result.push_str("0.");
+        result.extend(std::iter::repeat('0').take(-n as usize));
+        result.push_str(&digits);
+    } else {
+        result.push_str(&digits[..1]);
+        if k > 1 {
+            result.push('.');
+            result.push_str(&digits[1..]);
+        }
+        result.push('e');
+        result.push(if n > 0 { '+' } else { '-' });
+        result.push_str(&(n - 1).abs().to_string());
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_numbers() {
+        // Examples from appendix B of RFC 8785
+        assert_eq!(format_f64(f64::from_bits(0x0000000000000001)), "5e-324");
+        assert_eq!(format_f64(f64::from_bits(0x8000000000000001)), "-5e-324");
+        assert_eq!(
+            format_f64(f64::from_bits(0x7fefffffffffffff)),
+            "1.7976931348623157e+308"
+        );
+        assert_eq!(
+            format_f64(f64::from_bits(0x4340000000000000)),
+            "9007199254740992"
+        );
+        assert_eq!(format_f64(f64::from_bits(0x444b1ae4d6e2ef50)), "1e+21");
+        assert_eq!(
+            format_f64(f64::from_bits(0x444b1ae4d6e2ef4f)),
+            "999999999999999900000"
+        );
+        assert_eq!(format_f64(f64::from_bits(0x3eb0c6f7a0b5ed8d)), "0.000001");
+        assert_eq!(
+            format_f64(f64::from_bits(0x3eb0c6f7a0b5ed8c)),
+            "9.999999999999997e-7"
+        );
+        assert_eq!(
+            format_f64(f64::from_bits(0x41b3de4355555553)),
+            "333333333.3333332"
+        );
+        assert_eq!(format_f64(f64::from_bits(0x8000000000000000)), "0");
+        assert_eq!(format_f64(1.0), "1");
+        assert_eq!(format_f64(-1.5), "-1.5");
+    }
+
+    #[test]
+    fn widen_float() {
+        assert_eq!(widen_f32(0.1), 0.1f64);
+        assert_eq!(format_f64(widen_f32(1e-7)), "1e-7");
+    }
+}
@@ -1,15 +1,30 @@
-use std::{borrow::Cow, collections::HashMap, convert::TryInto, fmt, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    convert::{TryFrom, TryInto},
+    fmt,
+    str::FromStr,
+};
 
 use prost::bytes::Bytes;
-use serde::de::{DeserializeSeed, Deserializer, Error, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::de::{
+    Deserialize, DeserializeSeed, Deserializer, Error, Expected, IgnoredAny, MapAccess, SeqAccess,
+    Unexpected, Visitor,
+};
+use serde_value::ValueDeserializer;
 
 use crate::{
-    dynamic::{serde::DeserializeOptions, DynamicMessage, MapKey, Value},
+    dynamic::{
+        case::to_snake_case,
+        serde::{is_well_known_type, DeserializeOptions, PathSegment},
+        DynamicMessage, MapKey, Value,
+    },
     EnumDescriptor, Kind, MessageDescriptor, ReflectMessage,
 };
 
 use super::{
-    deserialize_enum, deserialize_message, FieldDescriptorSeed, OptionalFieldDescriptorSeed,
+    deserialize_enum, deserialize_message, FieldDescriptorSeed, MessageSeed,
+    OptionalFieldDescriptorSeed,
 };
 
 pub struct KindSeed<'a>(pub &'a Kind, pub &'a DeserializeOptions);
@@ -21,21 +36,58 @@ impl<'a, 'de> DeserializeSeed<'de> for KindSeed<'a> {
     where
         D: Deserializer<'de>,
     {
-        match self.0 {
-            Kind::Double => deserializer.deserialize_any(DoubleVisitor).map(Value::F64),
-            Kind::Float => deserializer.deserialize_any(FloatVisitor).map(Value::F32),
-            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
-                deserializer.deserialize_any(Int32Visitor).map(Value::I32)
-            }
-            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
-                deserializer.deserialize_any(Int64Visitor).map(Value::I64)
-            }
-            Kind::Uint32 | Kind::Fixed32 => {
-                deserializer.deserialize_any(Uint32Visitor).map(Value::U32)
-            }
-            Kind::Uint64 | Kind::Fixed64 => {
-                deserializer.deserialize_any(Uint64Visitor).map(Value::U64)
+        if self.1.tracks_errors() && is_scalar(self.0) {
+            // Buffer the value so it can be reported if it is invalid.
+            let value = serde_value::Value::deserialize(deserializer)?;
+            return self
+                .deserialize_kind(ValueDeserializer::<D::Error>::new(value.clone()))
+                .map_err(|err| {
+                    self.1.record_error_value(value);
+                    self.1.mark_recoverable();
+                    err
+                });
+        }
+
+        if let Kind::Message(desc) = self.0 {
+            if self.1.collects_errors() {
+                return deserializer
+                    .deserialize_any(RecoverableVisitor {
+                        visitor: MessageSeed(desc, self.1),
+                        seq: false,
+                        options: self.1,
+                    })
+                    .map(Value::Message);
             }
+        }
+
+        self.deserialize_kind(deserializer)
+    }
+}
+
+impl<'a> KindSeed<'a> {
+    fn deserialize_kind<'de, D>(&self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0 {
+            Kind::Double => deserializer
+                .deserialize_any(DoubleVisitor(self.1))
+                .map(Value::F64),
+            Kind::Float => deserializer
+                .deserialize_any(FloatVisitor(self.1))
+                .map(Value::F32),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => deserializer
+                .deserialize_any(Int32Visitor(self.1))
+                .map(Value::I32),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => deserializer
+                .deserialize_any(Int64Visitor(self.1))
+                .map(Value::I64),
+            Kind::Uint32 | Kind::Fixed32 => deserializer
+                .deserialize_any(Uint32Visitor(self.1))
+                .map(Value::U32),
+            Kind::Uint64 | Kind::Fixed64 => deserializer
+                .deserialize_any(Uint64Visitor(self.1))
+                .map(Value::U64),
             Kind::Bool => deserializer.deserialize_any(BoolVisitor).map(Value::Bool),
             Kind::String => deserializer
                 .deserialize_string(StringVisitor)
@@ -44,25 +96,37 @@ impl<'a, 'de> DeserializeSeed<'de> for KindSeed<'a> {
             Kind::Message(desc) => {
                 deserialize_message(desc, deserializer, self.1).map(Value::Message)
             }
-            Kind::Enum(desc) => deserialize_enum(desc, deserializer).map(Value::EnumNumber),
+            Kind::Enum(desc) => deserialize_enum(desc, deserializer, self.1).map(Value::EnumNumber),
         }
     }
 }
 
 pub struct ListVisitor<'a>(pub &'a Kind, pub &'a DeserializeOptions);
 pub struct MapVisitor<'a>(pub &'a Kind, pub &'a DeserializeOptions);
-pub struct DoubleVisitor;
-pub struct FloatVisitor;
-pub struct Int32Visitor;
-pub struct Uint32Visitor;
-pub struct Int64Visitor;
-pub struct Uint64Visitor;
+pub struct DoubleVisitor<'a>(pub &'a DeserializeOptions);
+pub struct FloatVisitor<'a>(pub &'a DeserializeOptions);
+pub struct Int32Visitor<'a>(pub &'a DeserializeOptions);
+pub struct Uint32Visitor<'a>(pub &'a DeserializeOptions);
+pub struct Int64Visitor<'a>(pub &'a DeserializeOptions);
+pub struct Uint64Visitor<'a>(pub &'a DeserializeOptions);
 pub struct StringVisitor;
 pub struct BoolVisitor;
 pub struct BytesVisitor;
 pub struct MessageVisitor<'a>(pub &'a MessageDescriptor, pub &'a DeserializeOptions);
 pub struct MessageVisitorInner<'a>(pub &'a mut DynamicMessage, pub &'a DeserializeOptions);
-pub struct EnumVisitor<'a>(pub &'a EnumDescriptor);
+pub struct EnumVisitor<'a>(pub &'a EnumDescriptor, pub &'a DeserializeOptions);
+/// The key of a message or map entry. Integer and boolean keys, as written by formats such as
+/// CBOR and YAML, are converted to strings.
+pub struct EntryKey<'de>(pub Cow<'de, str>);
+struct EntryKeyVisitor;
+/// Wraps a visitor for a JSON object or array, so that when collecting errors, a value of any
+/// other type is consumed and reported as a recoverable error.
+pub struct RecoverableVisitor<'a, V> {
+    pub visitor: V,
+    /// Whether `visitor` expects an array rather than an object.
+    pub seq: bool,
+    pub options: &'a DeserializeOptions,
+}
 
 impl<'a, 'de> Visitor<'de> for ListVisitor<'a> {
     type Value = Vec<Value>;
@@ -78,8 +142,16 @@ impl<'a, 'de> Visitor<'de> for ListVisitor<'a> {
     {
         let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
 
-        while let Some(value) = seq.next_element_seed(KindSeed(self.0, self.1))? {
-            result.push(value)
+        for index in 0.. {
+            match self.1.visit_value(
+                || PathSegment::Index(index),
+                || seq.next_element_seed(KindSeed(self.0, self.1)),
+            )? {
+                Some(Some(value)) => result.push(value),
+                Some(None) => break,
+                // The element was invalid and has been skipped.
+                None => (),
+            }
         }
 
         Ok(result)
@@ -103,37 +175,47 @@ impl<'a, 'de> Visitor<'de> for MapVisitor<'a> {
         let key_kind = map_entry_message.map_entry_key_field().kind();
         let value_desc = map_entry_message.map_entry_value_field();
 
-        while let Some(key_str) = map.next_key::<Cow<str>>()? {
-            let key = match key_kind {
-                Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
-                    MapKey::I32(i32::from_str(key_str.as_ref()).map_err(Error::custom)?)
-                }
-                Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
-                    MapKey::I64(i64::from_str(key_str.as_ref()).map_err(Error::custom)?)
-                }
-                Kind::Uint32 | Kind::Fixed32 => {
-                    MapKey::U32(u32::from_str(key_str.as_ref()).map_err(Error::custom)?)
-                }
-                Kind::Uint64 | Kind::Fixed64 => {
-                    MapKey::U64(u64::from_str(key_str.as_ref()).map_err(Error::custom)?)
-                }
-                Kind::Bool => {
-                    MapKey::Bool(bool::from_str(key_str.as_ref()).map_err(Error::custom)?)
-                }
-                Kind::String => MapKey::String(key_str.into_owned()),
-                _ => unreachable!("invalid type for map key"),
-            };
+        while let Some(EntryKey(key_str)) = map.next_key()? {
+            let entry = self.1.visit_value(
+                || PathSegment::Key(key_str.clone().into_owned()),
+                || {
+                    let key = match parse_map_key(&key_kind, key_str.as_ref()) {
+                        Ok(key) => key,
+                        Err(err) => {
+                            self.1.record_error_value(serde_value::Value::String(
+                                key_str.clone().into_owned(),
+                            ));
+                            if self.1.collects_errors() {
+                                map.next_value::<IgnoredAny>()?;
+                                self.1.mark_recoverable();
+                            }
+                            return Err(Error::custom(err));
+                        }
+                    };
+
+                    if self.1.deny_duplicate_keys && result.contains_key(&key) {
+                        if self.1.collects_errors() {
+                            map.next_value::<IgnoredAny>()?;
+                            self.1.mark_recoverable();
+                        }
+                        return Err(Error::custom(format!("duplicate map key '{}'", key_str)));
+                    }
 
-            let value = map.next_value_seed(FieldDescriptorSeed(&value_desc, self.1))?;
+                    let value = map.next_value_seed(FieldDescriptorSeed(&value_desc, self.1))?;
+                    Ok((key, value))
+                },
+            )?;
 
-            result.insert(key, value);
+            if let Some((key, value)) = entry {
+                result.insert(key, value);
+            }
         }
 
         Ok(result)
     }
 }
 
-impl<'de> Visitor<'de> for DoubleVisitor {
+impl<'a, 'de> Visitor<'de> for DoubleVisitor<'a> {
     type Value = f64;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -168,17 +250,17 @@ impl<'de> Visitor<'de> for DoubleVisitor {
     where
         E: Error,
     {
-        match f64::from_str(v) {
-            Ok(value) => Ok(value),
-            Err(_) if v == "Infinity" => Ok(f64::INFINITY),
-            Err(_) if v == "-Infinity" => Ok(f64::NEG_INFINITY),
-            Err(_) if v == "NaN" => Ok(f64::NAN),
-            Err(err) => Err(Error::custom(err)),
+        match v {
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            "NaN" => Ok(f64::NAN),
+            _ if !self.0.quoted_numbers => Err(Error::invalid_type(Unexpected::Str(v), &self)),
+            _ => f64::from_str(v).map_err(Error::custom),
         }
     }
 }
 
-impl<'de> Visitor<'de> for FloatVisitor {
+impl<'a, 'de> Visitor<'de> for FloatVisitor<'a> {
     type Value = f32;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -198,7 +280,9 @@ impl<'de> Visitor<'de> for FloatVisitor {
     where
         E: Error,
     {
-        if v < (f32::MIN as f64) || v > (f32::MAX as f64) {
+        // Infinite values can only come from formats with native non-finite floats, such as BSON,
+        // and are not out of range.
+        if v.is_finite() && (v < (f32::MIN as f64) || v > (f32::MAX as f64)) {
             Err(Error::custom("float value out of range"))
         } else {
             Ok(v as f32)
@@ -225,17 +309,17 @@ impl<'de> Visitor<'de> for FloatVisitor {
     where
         E: Error,
     {
-        match f32::from_str(v) {
-            Ok(value) => Ok(value),
-            Err(_) if v == "Infinity" => Ok(f32::INFINITY),
-            Err(_) if v == "-Infinity" => Ok(f32::NEG_INFINITY),
-            Err(_) if v == "NaN" => Ok(f32::NAN),
-            Err(err) => Err(Error::custom(err)),
+        match v {
+            "Infinity" => Ok(f32::INFINITY),
+            "-Infinity" => Ok(f32::NEG_INFINITY),
+            "NaN" => Ok(f32::NAN),
+            _ if !self.0.quoted_numbers => Err(Error::invalid_type(Unexpected::Str(v), &self)),
+            _ => f32::from_str(v).map_err(Error::custom),
         }
     }
 }
 
-impl<'de> Visitor<'de> for Int32Visitor {
+impl<'a, 'de> Visitor<'de> for Int32Visitor<'a> {
     type Value = i32;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -247,7 +331,7 @@ impl<'de> Visitor<'de> for Int32Visitor {
     where
         E: Error,
     {
-        v.parse().map_err(Error::custom)
+        parse_integer_str(v, self.0, &self)
     }
 
     #[inline]
@@ -278,19 +362,11 @@ impl<'de> Visitor<'de> for Int32Visitor {
     where
         E: Error,
     {
-        if v.fract() != 0.0 {
-            return Err(Error::custom("expected integer value"));
-        }
-
-        if v < (i32::MIN as f64) || v > (i32::MAX as f64) {
-            return Err(Error::custom("float value out of range"));
-        }
-
-        Ok(v as i32)
+        integer_from_f64(v)
     }
 }
 
-impl<'de> Visitor<'de> for Uint32Visitor {
+impl<'a, 'de> Visitor<'de> for Uint32Visitor<'a> {
     type Value = u32;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -302,7 +378,7 @@ impl<'de> Visitor<'de> for Uint32Visitor {
     where
         E: Error,
     {
-        v.parse().map_err(Error::custom)
+        parse_integer_str(v, self.0, &self)
     }
 
     #[inline]
@@ -333,19 +409,11 @@ impl<'de> Visitor<'de> for Uint32Visitor {
     where
         E: Error,
     {
-        if v.fract() != 0.0 {
-            return Err(Error::custom("expected integer value"));
-        }
-
-        if v < (u32::MIN as f64) || v > (u32::MAX as f64) {
-            return Err(Error::custom("float value out of range"));
-        }
-
-        Ok(v as u32)
+        integer_from_f64(v)
     }
 }
 
-impl<'de> Visitor<'de> for Int64Visitor {
+impl<'a, 'de> Visitor<'de> for Int64Visitor<'a> {
     type Value = i64;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -357,7 +425,7 @@ impl<'de> Visitor<'de> for Int64Visitor {
     where
         E: Error,
     {
-        v.parse().map_err(Error::custom)
+        parse_integer_str(v, self.0, &self)
     }
 
     #[inline]
@@ -380,19 +448,11 @@ impl<'de> Visitor<'de> for Int64Visitor {
     where
         E: Error,
     {
-        if v.fract() != 0.0 {
-            return Err(Error::custom("expected integer value"));
-        }
-
-        if v < (i64::MIN as f64) || v > (i64::MAX as f64) {
-            return Err(Error::custom("float value out of range"));
-        }
-
-        Ok(v as i64)
+        integer_from_f64(v)
     }
 }
 
-impl<'de> Visitor<'de> for Uint64Visitor {
+impl<'a, 'de> Visitor<'de> for Uint64Visitor<'a> {
     type Value = u64;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -404,38 +464,30 @@ impl<'de> Visitor<'de> for Uint64Visitor {
     where
         E: Error,
     {
-        v.parse().map_err(Error::custom)
+        parse_integer_str(v, self.0, &self)
     }
 
     #[inline]
-    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
     where
         E: Error,
     {
-        Ok(v)
+        v.try_into().map_err(Error::custom)
     }
 
     #[inline]
-    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
     where
         E: Error,
     {
-        v.try_into().map_err(Error::custom)
+        Ok(v)
     }
 
     fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
     where
         E: Error,
     {
-        if v.fract() != 0.0 {
-            return Err(Error::custom("expected integer value"));
-        }
-
-        if v < (u64::MIN as f64) || v > (u64::MAX as f64) {
-            return Err(Error::custom("float value out of range"));
-        }
-
-        Ok(v as u64)
+        integer_from_f64(v)
     }
 }
 
@@ -550,17 +602,50 @@ impl<'a, 'de> Visitor<'de> for MessageVisitorInner<'a> {
         A: MapAccess<'de>,
     {
         let desc = self.0.descriptor();
-        while let Some(key) = map.next_key::<Cow<str>>()? {
-            if let Some(field) = desc
-                .get_field_by_json_name(key.as_ref())
-                .or_else(|| desc.get_field_by_name(key.as_ref()))
-            {
-                if let Some(value) =
-                    map.next_value_seed(OptionalFieldDescriptorSeed(&field, self.1))?
-                {
-                    if let Some(oneof_desc) = field.containing_oneof() {
+        let mut seen_numbers = HashSet::new();
+        while let Some(EntryKey(key)) = map.next_key()? {
+            let segment = || PathSegment::Field(key.clone().into_owned());
+            let number = if self.1.allow_field_numbers {
+                key.parse::<u32>().ok()
+            } else {
+                None
+            };
+
+            let field = number
+                .and_then(|number| desc.get_field(number))
+                .or_else(|| desc.get_field_by_json_key(key.as_ref()));
+            let extension = if field.is_none() {
+                number
+                    .and_then(|number| desc.get_extension(number))
+                    .or_else(|| desc.get_extension_by_json_name(key.as_ref()))
+            } else {
+                None
+            };
+            let field_number = field
+                .as_ref()
+                .map(|field| field.number())
+                .or_else(|| extension.as_ref().map(|extension| extension.number()));
+
+            if let Some(field_number) = field_number {
+                if self.1.deny_duplicate_keys && !seen_numbers.insert(field_number) {
+                    self.1.visit_value(segment, || {
+                        if self.1.collects_errors() {
+                            map.next_value::<IgnoredAny>()?;
+                            self.1.mark_recoverable();
+                        }
+                        Err::<(), _>(Error::custom(format!("duplicate field '{}'", key)))
+                    })?;
+                    continue;
+                }
+            }
+
+            if let Some(field) = field {
+                let value = self.1.visit_value(segment, || {
+                    let value = map.next_value_seed(OptionalFieldDescriptorSeed(&field, self.1))?;
+                    if let (Some(_), Some(oneof_desc)) = (&value, field.containing_oneof()) {
                         for oneof_field in oneof_desc.fields() {
                             if self.0.has_field(&oneof_field) {
+                                self.1.mark_recoverable();
                                 return Err(Error::custom(format!(
                                     "multiple fields provided for oneof '{}'",
                                     oneof_desc.name()
@@ -568,17 +653,29 @@ impl<'a, 'de> Visitor<'de> for MessageVisitorInner<'a> {
                             }
                         }
                     }
-
+                    Ok(value)
+                });
+                if let Some(Some(value)) = value? {
                     self.0.set_field(&field, value);
                 }
-            } else if let Some(extension_desc) = desc.get_extension_by_json_name(key.as_ref()) {
-                if let Some(value) =
-                    map.next_value_seed(OptionalFieldDescriptorSeed(&extension_desc, self.1))?
-                {
+            } else if let Some(extension_desc) = extension {
+                let value = self.1.visit_value(segment, || {
+                    map.next_value_seed(OptionalFieldDescriptorSeed(&extension_desc, self.1))
+                });
+                if let Some(Some(value)) = value? {
                     self.0.set_extension(&extension_desc, value);
                 }
             } else if self.1.deny_unknown_fields {
-                return Err(Error::custom(format!("unrecognized field name '{}'", key)));
+                self.1.visit_value(segment, || {
+                    if self.1.collects_errors() {
+                        map.next_value::<IgnoredAny>()?;
+                        self.1.mark_recoverable();
+                    }
+                    Err::<(), _>(Error::custom(format!("unrecognized field name '{}'", key)))
+                })?;
+            } else if let Some(collector) = &self.1.unknown_field_collector {
+                let value = map.next_value::<serde_value::Value>()?;
+                self.1.record_unknown_field(collector, key.as_ref(), value);
             } else {
                 let _ = map.next_value::<IgnoredAny>()?;
             }
@@ -588,6 +685,160 @@ impl<'a, 'de> Visitor<'de> for MessageVisitorInner<'a> {
     }
 }
 
+impl<'de> Deserialize<'de> for EntryKey<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(EntryKeyVisitor).map(EntryKey)
+    }
+}
+
+impl<'de> Visitor<'de> for EntryKeyVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string or number")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Borrowed(if v { "true" } else { "false" }))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Owned(v.to_string()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Owned(v.to_owned()))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Cow::Owned(v))
+    }
+}
+
+impl<'a, V> RecoverableVisitor<'a, V> {
+    fn invalid_type<'de, E>(&self, unexpected: Unexpected) -> E
+    where
+        V: Visitor<'de>,
+        E: Error,
+    {
+        self.options.mark_recoverable();
+        Error::invalid_type(unexpected, &self.visitor)
+    }
+}
+
+impl<'a, 'de, V> Visitor<'de> for RecoverableVisitor<'a, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.visitor.expecting(f)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Bool(v)))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Signed(v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Unsigned(v)))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Float(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Str(v)))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Bytes(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Err(self.invalid_type(Unexpected::Unit))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        if self.seq {
+            self.visitor.visit_seq(seq)
+        } else {
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
+            Err(self.invalid_type(Unexpected::Seq))
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        if self.seq {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            Err(self.invalid_type(Unexpected::Map))
+        } else {
+            self.visitor.visit_map(map)
+        }
+    }
+}
+
 impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
     type Value = i32;
 
@@ -600,8 +851,12 @@ impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
     where
         E: Error,
     {
-        match self.0.get_value_by_name(v) {
-            Some(e) => Ok(e.number()),
+        if let Some(e) = self.0.get_value_by_name(v) {
+            return Ok(e.number());
+        }
+
+        match find_enum_value(self.0, v, self.1) {
+            Some(number) => Ok(number),
             None => Err(Error::custom(format!("unrecognized enum value '{}'", v))),
         }
     }
@@ -611,6 +866,10 @@ impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
     where
         E: Error,
     {
+        if self.1.closed_enums && self.0.is_closed() && self.0.get_value(v).is_none() {
+            return Err(Error::custom(format!("unrecognized enum value {}", v)));
+        }
+
         Ok(v)
     }
 
@@ -630,3 +889,183 @@ impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
         self.visit_i32(v.try_into().map_err(Error::custom)?)
     }
 }
+
+fn parse_map_key(kind: &Kind, key: &str) -> Result<MapKey, String> {
+    match kind {
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => i32::from_str(key)
+            .map(MapKey::I32)
+            .map_err(|err| err.to_string()),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => i64::from_str(key)
+            .map(MapKey::I64)
+            .map_err(|err| err.to_string()),
+        Kind::Uint32 | Kind::Fixed32 => u32::from_str(key)
+            .map(MapKey::U32)
+            .map_err(|err| err.to_string()),
+        Kind::Uint64 | Kind::Fixed64 => u64::from_str(key)
+            .map(MapKey::U64)
+            .map_err(|err| err.to_string()),
+        Kind::Bool => bool::from_str(key)
+            .map(MapKey::Bool)
+            .map_err(|err| err.to_string()),
+        Kind::String => Ok(MapKey::String(key.to_owned())),
+        _ => unreachable!("invalid type for map key"),
+    }
+}
+
+/// Parses a quoted integer, using the lenient syntax enabled in `options`.
+fn parse_integer_str<T, E>(
+    v: &str,
+    options: &DeserializeOptions,
+    exp: &dyn Expected,
+) -> Result<T, E>
+where
+    T: FromStr + TryFrom<i128>,
+    T::Err: fmt::Display,
+    E: Error,
+{
+    if !options.quoted_numbers {
+        return Err(Error::invalid_type(Unexpected::Str(v), exp));
+    }
+
+    match v.parse() {
+        Ok(value) => Ok(value),
+        Err(err) => match parse_decimal_integer(
+            v,
+            options.allow_integer_fractions,
+            options.allow_integer_exponents,
+        ) {
+            Some(Ok(value)) => {
+                T::try_from(value).map_err(|_| Error::custom("integer value out of range"))
+            }
+            Some(Err(msg)) => Err(Error::custom(msg)),
+            None => Err(Error::custom(err)),
+        },
+    }
+}
+
+/// Parses a decimal number with an optional fractional part and exponent, checking that it is an
+/// exact integer.
+///
+/// Returns `None` if the string does not use the allowed syntax.
+fn parse_decimal_integer(
+    v: &str,
+    allow_fraction: bool,
+    allow_exponent: bool,
+) -> Option<Result<i128, &'static str>> {
+    let (negative, v) = match v.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, v),
+    };
+
+    let (mantissa, exponent) = match v.find(['e', 'E']) {
+        Some(pos) if allow_exponent => (&v[..pos], v[pos + 1..].parse::<i32>().ok()?),
+        Some(_) => return None,
+        None => (v, 0),
+    };
+
+    let (int, frac) = match mantissa.split_once('.') {
+        // A fractional part may be used to write the mantissa in exponent notation, e.g. `1.5e2`.
+        Some(parts) if allow_fraction || mantissa.len() != v.len() => parts,
+        Some(_) => return None,
+        None => (mantissa, ""),
+    };
+
+    if int.is_empty()
+        || !int.bytes().all(|b| b.is_ascii_digit())
+        || !frac.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let digits = format!("{}{}", int, frac);
+    let mut digits = digits.trim_start_matches('0');
+    let mut exponent = i64::from(exponent) - frac.len() as i64;
+    while exponent < 0 {
+        match digits.strip_suffix('0') {
+            Some(rest) => {
+                digits = rest;
+                exponent += 1;
+            }
+            None if digits.is_empty() => exponent = 0,
+            None => return Some(Err("expected integer value")),
+        }
+    }
+
+    if digits.is_empty() {
+        return Some(Ok(0));
+    }
+
+    let value = digits
+        .parse::<i128>()
+        .ok()
+        .zip(u32::try_from(exponent).ok())
+        .and_then(|(value, exponent)| value.checked_mul(10i128.checked_pow(exponent)?));
+    match value {
+        Some(value) if negative => Some(Ok(-value)),
+        Some(value) => Some(Ok(value)),
+        None => Some(Err("integer value out of range")),
+    }
+}
+
+/// Converts a floating point value to an integer, checking that it is exactly representable.
+fn integer_from_f64<T, E>(v: f64) -> Result<T, E>
+where
+    T: TryFrom<i128>,
+    E: Error,
+{
+    if v.fract() != 0.0 {
+        return Err(Error::custom("expected integer value"));
+    }
+
+    // Every integral value in the range of the target type converts exactly, while larger values
+    // saturate and fail the range check.
+    T::try_from(v as i128).map_err(|_| Error::custom("float value out of range"))
+}
+
+/// Looks up an enum value by name using the relaxed matching rules enabled in `options`.
+fn find_enum_value(desc: &EnumDescriptor, name: &str, options: &DeserializeOptions) -> Option<i32> {
+    if !options.case_insensitive_enum_names && !options.allow_unprefixed_enum_names {
+        return None;
+    }
+
+    let ignore_case = options.case_insensitive_enum_names;
+    let prefix = if options.allow_unprefixed_enum_names {
+        let mut prefix = to_snake_case(desc.name(), '_').to_ascii_uppercase();
+        prefix.push('_');
+        prefix
+    } else {
+        String::new()
+    };
+
+    let name = strip_enum_prefix(name, &prefix, ignore_case);
+    desc.values()
+        .find(|value| {
+            let value_name = strip_enum_prefix(value.name(), &prefix, ignore_case);
+            if ignore_case {
+                value_name.eq_ignore_ascii_case(name)
+            } else {
+                value_name == name
+            }
+        })
+        .map(|value| value.number())
+}
+
+fn strip_enum_prefix<'a>(name: &'a str, prefix: &str, ignore_case: bool) -> &'a str {
+    match name.get(..prefix.len()) {
+        Some(start) if start == prefix || (ignore_case && start.eq_ignore_ascii_case(prefix)) => {
+            &name[prefix.len()..]
+        }
+        _ => name,
+    }
+}
+
+/// Returns `true` if values of this kind are deserialized from a single scalar value, rather than
+/// a JSON object or list containing further fields.
+fn is_scalar(kind: &Kind) -> bool {
+    match kind {
+        Kind::Message(desc) => {
+            is_well_known_type(desc.full_name()) && desc.full_name() != "google.protobuf.Any"
+        }
+        _ => true,
+    }
+}
@@ -4,7 +4,9 @@ mod wkt;
 use std::fmt;
 
 use prost::Message;
-use serde::de::{DeserializeSeed, Deserializer, Error, Visitor};
+use serde::de::{
+    value::MapAccessDeserializer, DeserializeSeed, Deserializer, Error, MapAccess, Visitor,
+};
 
 use crate::{
     dynamic::{fields::FieldDescriptorLike, serde::DeserializeOptions, DynamicMessage, Value},
@@ -30,22 +32,22 @@ where
             .deserialize_str(wkt::GoogleProtobufDurationVisitor)
             .and_then(|duration| make_message(desc, duration)),
         "google.protobuf.FloatValue" => deserializer
-            .deserialize_any(kind::FloatVisitor)
+            .deserialize_any(kind::FloatVisitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.DoubleValue" => deserializer
-            .deserialize_any(kind::DoubleVisitor)
+            .deserialize_any(kind::DoubleVisitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.Int32Value" => deserializer
-            .deserialize_any(kind::Int32Visitor)
+            .deserialize_any(kind::Int32Visitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.Int64Value" => deserializer
-            .deserialize_any(kind::Int64Visitor)
+            .deserialize_any(kind::Int64Visitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.UInt32Value" => deserializer
-            .deserialize_any(kind::Uint32Visitor)
+            .deserialize_any(kind::Uint32Visitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.UInt64Value" => deserializer
-            .deserialize_any(kind::Uint64Visitor)
+            .deserialize_any(kind::Uint64Visitor(options))
             .and_then(|v| make_message(desc, v)),
         "google.protobuf.BoolValue" => deserializer
             .deserialize_any(kind::BoolVisitor)
@@ -75,13 +77,17 @@ where
     }
 }
 
-fn deserialize_enum<'de, D>(desc: &EnumDescriptor, deserializer: D) -> Result<i32, D::Error>
+fn deserialize_enum<'de, D>(
+    desc: &EnumDescriptor,
+    deserializer: D,
+    options: &DeserializeOptions,
+) -> Result<i32, D::Error>
 where
     D: Deserializer<'de>,
 {
     match desc.full_name() {
         "google.protobuf.NullValue" => deserializer.deserialize_any(wkt::GoogleProtobufNullVisitor),
-        _ => deserializer.deserialize_any(kind::EnumVisitor(desc)),
+        _ => deserializer.deserialize_any(kind::EnumVisitor(desc, options)),
     }
 }
 
@@ -98,6 +104,21 @@ impl<'a, 'de> DeserializeSeed<'de> for MessageSeed<'a> {
     }
 }
 
+impl<'a, 'de> Visitor<'de> for MessageSeed<'a> {
+    type Value = DynamicMessage;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        deserialize_message(self.0, MapAccessDeserializer::new(map), self.1)
+    }
+}
+
 struct FieldDescriptorSeed<'a, T>(&'a T, &'a DeserializeOptions);
 
 impl<'a, 'de, T> DeserializeSeed<'de> for FieldDescriptorSeed<'a, T>
@@ -111,13 +132,29 @@ where
         D: Deserializer<'de>,
     {
         if self.0.is_list() {
-            deserializer
-                .deserialize_any(kind::ListVisitor(&self.0.kind(), self.1))
-                .map(Value::List)
+            let visitor = kind::ListVisitor(&self.0.kind(), self.1);
+            if self.1.collects_errors() {
+                deserializer.deserialize_any(kind::RecoverableVisitor {
+                    visitor,
+                    seq: true,
+                    options: self.1,
+                })
+            } else {
+                deserializer.deserialize_any(visitor)
+            }
+            .map(Value::List)
         } else if self.0.is_map() {
-            deserializer
-                .deserialize_any(kind::MapVisitor(&self.0.kind(), self.1))
-                .map(Value::Map)
+            let visitor = kind::MapVisitor(&self.0.kind(), self.1);
+            if self.1.collects_errors() {
+                deserializer.deserialize_any(kind::RecoverableVisitor {
+                    visitor,
+                    seq: false,
+                    options: self.1,
+                })
+            } else {
+                deserializer.deserialize_any(visitor)
+            }
+            .map(Value::Map)
         } else {
             kind::KindSeed(&self.0.kind(), self.1).deserialize(deserializer)
         }
@@ -1,5 +1,4 @@
 use std::{
-    borrow::Cow,
     collections::{BTreeMap, HashMap},
     fmt,
 };
@@ -11,18 +10,20 @@ use serde::de::{
 };
 
 use crate::{
-    descriptor::{GOOGLE_APIS_DOMAIN, GOOGLE_PROD_DOMAIN},
     dynamic::{
-        serde::{
-            case::camel_case_to_snake_case, check_duration, check_timestamp, is_well_known_type,
-            DeserializeOptions,
-        },
+        case::camel_case_to_snake_case,
+        resolve_any_type,
+        serde::{check_duration, check_timestamp, is_well_known_type, DeserializeOptions},
         DynamicMessage,
     },
     DescriptorPool,
 };
 
-use super::{deserialize_message, kind::MessageVisitorInner, MessageSeed};
+use super::{
+    deserialize_message,
+    kind::{EntryKey, MessageVisitorInner},
+    MessageSeed,
+};
 
 pub struct GoogleProtobufAnyVisitor<'a>(pub &'a DescriptorPool, pub &'a DeserializeOptions);
 pub struct GoogleProtobufNullVisitor;
@@ -48,7 +49,7 @@ impl<'a, 'de> Visitor<'de> for GoogleProtobufAnyVisitor<'a> {
         let mut buffered_entries = HashMap::new();
 
         let type_url = loop {
-            match map.next_key::<Cow<str>>()? {
+            match map.next_key::<EntryKey>()?.map(|EntryKey(key)| key) {
                 Some(key) if key == "@type" => {
                     break map.next_value::<String>()?;
                 }
@@ -60,74 +61,77 @@ impl<'a, 'de> Visitor<'de> for GoogleProtobufAnyVisitor<'a> {
             }
         };
 
-        if let Some(message_name) = type_url
-            .strip_prefix(GOOGLE_APIS_DOMAIN)
-            .or_else(|| type_url.strip_prefix(GOOGLE_PROD_DOMAIN))
-        {
-            let message_desc = self
-                .0
-                .get_message_by_name(message_name)
-                .ok_or_else(|| Error::custom(format!("message '{}' not found", message_name)))?;
-
-            let payload_message = if is_well_known_type(message_name) {
-                let payload_message = match buffered_entries.remove("value") {
-                    Some(value) => {
-                        deserialize_message(&message_desc, value, self.1).map_err(Error::custom)?
-                    }
-                    None => loop {
-                        match map.next_key::<Cow<str>>()? {
-                            Some(key) if key == "value" => {
-                                break map.next_value_seed(MessageSeed(&message_desc, self.1))?
-                            }
-                            Some(key) => {
-                                if self.1.deny_unknown_fields {
-                                    return Err(Error::custom(format!(
-                                        "unrecognized field name '{}'",
-                                        key
-                                    )));
-                                } else {
-                                    let _ = map.next_value::<IgnoredAny>()?;
-                                }
+        let message_desc = resolve_any_type(self.1.type_resolver.as_ref(), self.0, &type_url)
+            .map_err(Error::custom)?;
+
+        let payload_message = if is_well_known_type(message_desc.full_name()) {
+            let payload_message = match buffered_entries.remove("value") {
+                Some(value) => {
+                    deserialize_message(&message_desc, value, self.1).map_err(Error::custom)?
+                }
+                None => loop {
+                    match map.next_key::<EntryKey>()?.map(|EntryKey(key)| key) {
+                        Some(key) if key == "value" => {
+                            break map.next_value_seed(MessageSeed(&message_desc, self.1))?
+                        }
+                        Some(key) => {
+                            if self.1.deny_unknown_fields {
+                                return Err(Error::custom(format!(
+                                    "unrecognized field name '{}'",
+                                    key
+                                )));
+                            } else if let Some(collector) = &self.1.unknown_field_collector {
+                                self.1.record_unknown_field(
+                                    collector,
+                                    key.as_ref(),
+                                    map.next_value()?,
+                                );
+                            } else {
+                                let _ = map.next_value::<IgnoredAny>()?;
                             }
-                            None => return Err(Error::custom("expected '@type' field")),
                         }
-                    },
-                };
-
-                if self.1.deny_unknown_fields {
-                    if let Some(key) = buffered_entries.keys().next() {
-                        return Err(Error::custom(format!("unrecognized field name '{}'", key)));
+                        None => return Err(Error::custom("expected '@type' field")),
                     }
-                    if let Some(key) = map.next_key::<Cow<str>>()? {
-                        return Err(Error::custom(format!("unrecognized field name '{}'", key)));
-                    }
-                } else {
-                    drop(buffered_entries);
-                    while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
-                }
+                },
+            };
 
-                payload_message
+            if self.1.deny_unknown_fields {
+                if let Some(key) = buffered_entries.keys().next() {
+                    return Err(Error::custom(format!("unrecognized field name '{}'", key)));
+                }
+                if let Some(key) = map.next_key::<EntryKey>()?.map(|EntryKey(key)| key) {
+                    return Err(Error::custom(format!("unrecognized field name '{}'", key)));
+                }
+            } else if let Some(collector) = &self.1.unknown_field_collector {
+                for (key, value) in buffered_entries {
+                    self.1.record_unknown_field(collector, key.as_ref(), value);
+                }
+                while let Some((EntryKey(key), value)) =
+                    map.next_entry::<EntryKey, serde_value::Value>()?
+                {
+                    self.1.record_unknown_field(collector, key.as_ref(), value);
+                }
             } else {
-                let mut payload_message = DynamicMessage::new(message_desc);
+                drop(buffered_entries);
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            }
 
-                buffered_entries
-                    .into_deserializer()
-                    .deserialize_map(MessageVisitorInner(&mut payload_message, self.1))
-                    .map_err(Error::custom)?;
+            payload_message
+        } else {
+            let mut payload_message = DynamicMessage::new(message_desc);
 
-                MessageVisitorInner(&mut payload_message, self.1).visit_map(map)?;
+            buffered_entries
+                .into_deserializer()
+                .deserialize_map(MessageVisitorInner(&mut payload_message, self.1))
+                .map_err(Error::custom)?;
 
-                payload_message
-            };
+            MessageVisitorInner(&mut payload_message, self.1).visit_map(map)?;
 
-            let value = payload_message.encode_to_vec();
-            Ok(prost_types::Any { type_url, value })
-        } else {
-            Err(Error::custom(format!(
-                "unsupported type url '{}'",
-                type_url
-            )))
-        }
+            payload_message
+        };
+
+        let value = payload_message.encode_to_vec();
+        Ok(prost_types::Any { type_url, value })
     }
 }
 
@@ -156,6 +160,14 @@ impl<'de> Visitor<'de> for GoogleProtobufNullVisitor {
     {
         Ok(0)
     }
+
+    #[inline]
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        self.visit_unit()
+    }
 }
 
 impl<'de> Visitor<'de> for GoogleProtobufTimestampVisitor {
@@ -348,6 +360,14 @@ impl<'de> Visitor<'de> for GoogleProtobufValueVisitor {
         })
     }
 
+    #[inline]
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        self.visit_unit()
+    }
+
     fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
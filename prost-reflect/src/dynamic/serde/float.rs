@@ -0,0 +1,348 @@
+use std::{fmt, io};
+
+use serde_json::ser::{CharEscape, CompactFormatter, Formatter};
+
+/// A [`Formatter`] for `serde_json` which controls how floating point numbers are written.
+///
+/// By default, numbers are written using the shortest representation which round trips, in
+/// scientific notation if the decimal exponent is less than `-5` or at least `16`, and with
+/// negative zero written as `-0.0`. Each of these can be customized to match the output of other
+/// protobuf runtimes. Unlike the default `serde_json` formatter, the output does not depend on the
+/// version of `serde_json` in use.
+///
+/// In scientific notation, the exponent is always written with a sign, for example `1e+16` or
+/// `1.5e-7`.
+///
+/// All other output is delegated to an inner formatter, which defaults to [`CompactFormatter`].
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{FloatFormatter, ReflectMessage, SerializeOptions};
+/// let value = (0.1f64 + 0.2).transcode_to_dynamic();
+///
+/// let mut serializer = serde_json::Serializer::new(vec![]);
+/// value.serialize_with_options(&mut serializer, &SerializeOptions::new()).unwrap();
+/// assert_eq!(serializer.into_inner(), b"0.30000000000000004");
+///
+/// let formatter = FloatFormatter::new().precision(3);
+/// let mut serializer = serde_json::Serializer::with_formatter(vec![], formatter);
+/// value.serialize_with_options(&mut serializer, &SerializeOptions::new()).unwrap();
+/// assert_eq!(serializer.into_inner(), b"0.300");
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+pub struct FloatFormatter<F = CompactFormatter> {
+    inner: F,
+    precision: Option<usize>,
+    min_exponent: i32,
+    max_exponent: i32,
+    negative_zero: bool,
+}
+
+impl FloatFormatter {
+    /// Creates a new instance of [`FloatFormatter`] which writes compact JSON.
+    pub fn new() -> Self {
+        FloatFormatter::with_formatter(CompactFormatter)
+    }
+}
+
+impl Default for FloatFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> FloatFormatter<F> {
+    /// Creates a new instance of [`FloatFormatter`], which delegates all output other than
+    /// floating point numbers to `inner`.
+    ///
+    /// For example, [`PrettyFormatter`][serde_json::ser::PrettyFormatter] may be used to produce
+    /// indented output.
+    pub fn with_formatter(inner: F) -> Self {
+        FloatFormatter {
+            inner,
+            precision: None,
+            min_exponent: -5,
+            max_exponent: 16,
+            negative_zero: true,
+        }
+    }
+
+    /// Writes all floating point numbers with exactly `digits` digits after the decimal point,
+    /// rounding if necessary.
+    ///
+    /// In scientific notation, the precision applies to the mantissa, so for example `1.5e-7`
+    /// is written as `1.50e-7` with a precision of 2. A precision of `0` omits the decimal point.
+    ///
+    /// By default, the shortest representation which round trips is used.
+    pub fn precision(mut self, digits: usize) -> Self {
+        self.precision = Some(digits);
+        self
+    }
+
+    /// Sets the range of decimal exponents for which numbers are written in positional notation.
+    ///
+    /// Numbers whose decimal exponent is less than `min_exponent`, or greater than or equal to
+    /// `max_exponent`, are written in scientific notation, for example `1.5e-7`. The exponent of
+    /// zero is taken to be `0`.
+    ///
+    /// The default range is `-5..16`.
+    pub fn scientific_threshold(mut self, min_exponent: i32, max_exponent: i32) -> Self {
+        self.min_exponent = min_exponent;
+        self.max_exponent = max_exponent;
+        self
+    }
+
+    /// Whether to write the sign of negative zero.
+    ///
+    /// If `false`, negative zero is written identically to positive zero. This also applies to
+    /// negative numbers which round to zero due to the [`precision`][Self::precision] option.
+    ///
+    /// The default value is `true`.
+    pub fn negative_zero(mut self, yes: bool) -> Self {
+        self.negative_zero = yes;
+        self
+    }
+
+    fn format<T>(&self, value: T) -> String
+    where
+        T: fmt::Display + fmt::LowerExp,
+    {
+        // The exponential form gives the shortest digits which round trip.
+        let exponential = format!("{:e}", value);
+        let (negative, exponential) = match exponential.strip_prefix('-') {
+            Some(exponential) => (true, exponential),
+            None => (false, exponential.as_str()),
+        };
+        let (mantissa, exponent) = exponential
+            .split_once('e')
+            .expect("invalid exponential format");
+        let exponent: i32 = exponent.parse().expect("invalid exponent");
+        let scientific = exponent < self.min_exponent || exponent >= self.max_exponent;
+
+        let mut result = match self.precision {
+            Some(precision) if scientific => {
+                let result = format!("{:.*e}", precision, value);
+                match result.split_once('e') {
+                    Some((mantissa, exponent)) if !exponent.starts_with('-') => {
+                        format!("{}e+{}", mantissa, exponent)
+                    }
+                    _ => result,
+                }
+            }
+            Some(precision) => format!("{:.*}", precision, value),
+            None => {
+                let mut result = String::new();
+                if negative {
+                    result.push('-');
+                }
+
+                let digits = mantissa.replace('.', "");
+                let len = digits.len() as i32;
+                if scientific {
+                    result.push_str(mantissa);
+                    result.push_str(if exponent < 0 { "e" } else { "e+" });
+                    result.push_str(&exponent.to_string());
+                } else if exponent >= len - 1 {
+                    result.push_str(&digits);
+                    result.extend(std::iter::repeat('0').take((exponent - len + 1) as usize));
+                    result.push_str(".0");
+                } else if exponent >= 0 {
+                    let point = (exponent + 1) as usize;
+                    result.push_str(&digits[..point]);
+                    result.push('.');
+                    result.push_str(&digits[point..]);
+                } else {
+                    result.push_str("0.");
+                    result.extend(std::iter::repeat('0').take((-exponent - 1) as usize));
+                    result.push_str(&digits);
+                }
+                result
+            }
+        };
+
+        if !self.negative_zero && result.starts_with('-') {
+            let mantissa = result[1..].split('e').next().unwrap_or_default();
+            if mantissa.bytes().all(|b| b == b'0' || b == b'.') {
+                result.remove(0);
+            }
+        }
+
+        result
+    }
+}
+
+macro_rules! delegate {
+    ($($name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            #[inline]
+            fn $name<W>(&mut self, writer: &mut W $(, $arg: $ty)*) -> io::Result<()>
+            where
+                W: ?Sized + io::Write,
+            {
+                self.inner.$name(writer $(, $arg)*)
+            }
+        )*
+    };
+}
+
+impl<F> Formatter for FloatFormatter<F>
+where
+    F: Formatter,
+{
+    fn write_f32<W>(&mut self, writer: &mut W, value: f32) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(self.format(value).as_bytes())
+    }
+
+    fn write_f64<W>(&mut self, writer: &mut W, value: f64) -> io::Result<()>
+    where
+        W: ?Sized + io::Write,
+    {
+        writer.write_all(self.format(value).as_bytes())
+    }
+
+    delegate! {
+        write_null();
+        write_bool(value: bool);
+        write_i8(value: i8);
+        write_i16(value: i16);
+        write_i32(value: i32);
+        write_i64(value: i64);
+        write_u8(value: u8);
+        write_u16(value: u16);
+        write_u32(value: u32);
+        write_u64(value: u64);
+        write_number_str(value: &str);
+        begin_string();
+        end_string();
+        write_string_fragment(fragment: &str);
+        write_char_escape(char_escape: CharEscape);
+        begin_array();
+        end_array();
+        begin_array_value(first: bool);
+        end_array_value();
+        begin_object();
+        end_object();
+        begin_object_key(first: bool);
+        end_object_key();
+        begin_object_value();
+        end_object_value();
+        write_raw_fragment(fragment: &str);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+    use serde_json::ser::PrettyFormatter;
+
+    use super::*;
+
+    fn to_string<T, F>(value: T, formatter: &F) -> String
+    where
+        T: serde::Serialize,
+        F: Formatter + Clone,
+    {
+        let mut serializer = serde_json::Serializer::with_formatter(Vec::new(), formatter.clone());
+        value.serialize(&mut serializer).unwrap();
+        String::from_utf8(serializer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn default_format() {
+        let cases: &[(f64, &str)] = &[
+            (0.0, "0.0"),
+            (-0.0, "-0.0"),
+            (1.0, "1.0"),
+            (-1.5, "-1.5"),
+            (100.0, "100.0"),
+            (0.1, "0.1"),
+            (0.00001, "0.00001"),
+            (0.000001, "1e-6"),
+            (1.5e-7, "1.5e-7"),
+            (1e15, "1000000000000000.0"),
+            (1e16, "1e+16"),
+            (1.2345e20, "1.2345e+20"),
+            (f64::MAX, "1.7976931348623157e+308"),
+            (f64::MIN_POSITIVE, "2.2250738585072014e-308"),
+        ];
+        for &(value, expected) in cases {
+            assert_eq!(to_string(value, &FloatFormatter::new()), expected);
+        }
+
+        assert_eq!(to_string(0.1f32, &FloatFormatter::new()), "0.1");
+        assert_eq!(to_string(16777216f32, &FloatFormatter::new()), "16777216.0");
+        assert_eq!(to_string(1e-7f32, &FloatFormatter::new()), "1e-7");
+    }
+
+    #[test]
+    fn precision() {
+        let formatter = FloatFormatter::new().precision(2);
+        assert_eq!(to_string(1.0, &formatter), "1.00");
+        assert_eq!(to_string(0.125, &formatter), "0.12");
+        assert_eq!(to_string(-2.555f32, &formatter), "-2.56");
+        assert_eq!(to_string(1.5e-7, &formatter), "1.50e-7");
+        assert_eq!(to_string(1e20, &formatter), "1.00e+20");
+
+        let formatter = FloatFormatter::new().precision(0);
+        assert_eq!(to_string(2.5, &formatter), "2");
+        assert_eq!(to_string(3.7, &formatter), "4");
+    }
+
+    #[test]
+    fn scientific_threshold() {
+        let formatter = FloatFormatter::new().scientific_threshold(-7, 21);
+        assert_eq!(to_string(1.5e-7, &formatter), "0.00000015");
+        assert_eq!(to_string(1e-8, &formatter), "1e-8");
+        assert_eq!(to_string(1e20, &formatter), "100000000000000000000.0");
+        assert_eq!(to_string(1e21, &formatter), "1e+21");
+
+        let formatter = FloatFormatter::new().scientific_threshold(0, 0);
+        assert_eq!(to_string(0.0, &formatter), "0e+0");
+        assert_eq!(to_string(123.0, &formatter), "1.23e+2");
+    }
+
+    #[test]
+    fn negative_zero() {
+        let formatter = FloatFormatter::new().negative_zero(false);
+        assert_eq!(to_string(-0.0, &formatter), "0.0");
+        assert_eq!(to_string(-0.0f32, &formatter), "0.0");
+        assert_eq!(to_string(-1.0, &formatter), "-1.0");
+
+        let formatter = formatter.precision(1);
+        assert_eq!(to_string(-0.01, &formatter), "0.0");
+        assert_eq!(to_string(-0.1, &formatter), "-0.1");
+
+        let formatter = formatter.scientific_threshold(0, 0);
+        assert_eq!(to_string(-0.0, &formatter), "0.0e+0");
+    }
+
+    #[test]
+    fn delegates_to_inner() {
+        let formatter = FloatFormatter::with_formatter(PrettyFormatter::new()).precision(1);
+        assert_eq!(
+            to_string(vec![1.0, 2.25], &formatter),
+            "[\n  1.0,\n  2.2\n]"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn default_roundtrip_f64(value: f64) {
+            prop_assume!(value.is_finite());
+            let json = to_string(value, &FloatFormatter::new());
+            prop_assert_eq!(json.parse::<f64>().unwrap().to_bits(), value.to_bits());
+        }
+
+        #[test]
+        fn default_roundtrip_f32(value: f32) {
+            prop_assume!(value.is_finite());
+            let json = to_string(value, &FloatFormatter::new());
+            prop_assert_eq!(json.parse::<f32>().unwrap().to_bits(), value.to_bits());
+        }
+    }
+}
@@ -1,13 +1,38 @@
-mod case;
+#[cfg(feature = "serde-json")]
+mod canonical;
 mod de;
+#[cfg(feature = "serde-json")]
+mod float;
+#[cfg(feature = "serde-json")]
+mod parse;
+mod positional;
 mod ser;
 
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
 use serde::{
-    de::{DeserializeSeed, Deserializer},
+    de::{DeserializeOwned, DeserializeSeed, Deserializer},
     ser::{Serialize, Serializer},
 };
 
-use crate::{DynamicMessage, MessageDescriptor};
+use prost_types::FieldMask;
+
+use crate::{
+    dynamic::case::to_snake_case, DynamicMessage, FieldDescriptor, MessageDescriptor, TypeResolver,
+    UsageObserver,
+};
+
+use self::ser::FieldMaskTree;
+
+#[cfg(feature = "serde-json")]
+pub(crate) use self::parse::{deserialize_json, JsonParseError};
+#[cfg(feature = "serde-json")]
+pub use self::{canonical::CanonicalJsonFormatter, float::FloatFormatter};
 
 /// Options to control serialization of messages.
 ///
@@ -19,6 +44,16 @@ pub struct SerializeOptions {
     use_enum_numbers: bool,
     use_proto_field_name: bool,
     skip_default_fields: bool,
+    emit_nulls_for_absent_optionals: bool,
+    canonical: bool,
+    timestamp_precision: TimestampPrecision,
+    out_of_range_policy: OutOfRangePolicy,
+    bytes_encoding: Base64Encoding,
+    field_mask: Option<Arc<FieldMaskTree>>,
+    positional: bool,
+    use_field_numbers: bool,
+    native_values: bool,
+    type_resolver: Option<Arc<dyn TypeResolver>>,
 }
 
 /// Options to control deserialization of messages.
@@ -28,6 +63,171 @@ pub struct SerializeOptions {
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
 pub struct DeserializeOptions {
     deny_unknown_fields: bool,
+    deny_duplicate_keys: bool,
+    unknown_field_collector: Option<UnknownFieldCollector>,
+    /// The path to the value currently being deserialized, outermost segment first. Only set
+    /// while collecting unknown fields, and created afresh for each call, so that concurrent
+    /// deserializations sharing a collector do not interfere.
+    unknown_field_path: Option<Arc<Mutex<Vec<PathSegment>>>>,
+    error_context: Option<Arc<Mutex<ErrorContext>>>,
+    case_insensitive_enum_names: bool,
+    allow_unprefixed_enum_names: bool,
+    closed_enums: bool,
+    quoted_numbers: bool,
+    allow_integer_fractions: bool,
+    allow_integer_exponents: bool,
+    positional: bool,
+    allow_field_numbers: bool,
+    type_resolver: Option<Arc<dyn TypeResolver>>,
+    usage_observer: Option<Arc<dyn UsageObserver>>,
+}
+
+/// A strategy for choosing the JSON names of message fields.
+///
+/// A strategy is installed for all messages in a pool with
+/// [`DescriptorPool::set_field_name_strategy()`][crate::DescriptorPool::set_field_name_strategy].
+/// Fields are then serialized using the name chosen by the strategy, and deserialization accepts
+/// that name as well as the JSON name and proto name of the field.
+#[derive(Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub enum FieldNameStrategy {
+    /// Use the [`json_name`][FieldDescriptor::json_name] of the field, which is lowerCamelCase
+    /// unless overridden in the proto file. This matches the standard JSON mapping.
+    JsonName,
+    /// Use the [`name`][FieldDescriptor::name] of the field as written in the proto file.
+    ProtoName,
+    /// Convert the field name to snake_case.
+    SnakeCase,
+    /// Convert the field name to kebab-case.
+    KebabCase,
+    /// Compute the name of each field with a custom function.
+    Custom(Arc<dyn Fn(&FieldDescriptor) -> String + Send + Sync>),
+}
+
+/// The number of fractional second digits used when serializing `google.protobuf.Timestamp`
+/// values.
+///
+/// Used by [`SerializeOptions::timestamp_precision()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub enum TimestampPrecision {
+    /// Use 0, 3, 6 or 9 fractional digits, whichever is the fewest needed to represent the value
+    /// exactly. This matches the standard JSON mapping.
+    Standard,
+    /// Use the fewest fractional digits needed to represent the value exactly, between 0 and 9.
+    Minimal,
+    /// Always omit the fractional seconds.
+    Seconds,
+    /// Always use 3 fractional digits.
+    Millis,
+    /// Always use 6 fractional digits.
+    Micros,
+    /// Always use 9 fractional digits.
+    Nanos,
+}
+
+/// How to serialize `google.protobuf.Timestamp` and `google.protobuf.Duration` values which are
+/// outside the range supported by the JSON mapping.
+///
+/// Timestamps must be between `0001-01-01T00:00:00Z` and `9999-12-31T23:59:59.999999999Z`, and
+/// durations must be between `-315576000000.999999999s` and `315576000000.999999999s`.
+///
+/// Used by [`SerializeOptions::out_of_range_policy()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub enum OutOfRangePolicy {
+    /// Fail serialization with an error. This matches the standard JSON mapping.
+    Error,
+    /// Replace the value with the closest value in the supported range.
+    Clamp,
+    /// Serialize the value as a regular message, as an object containing its `seconds` and `nanos`
+    /// fields.
+    Raw,
+}
+
+/// The base64 alphabet and padding used when serializing `bytes` fields.
+///
+/// Deserialization always accepts both the standard and URL-safe alphabets, with or without
+/// padding.
+///
+/// Used by [`SerializeOptions::bytes_encoding()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub enum Base64Encoding {
+    /// The standard alphabet, with padding. This matches the standard JSON mapping.
+    Standard,
+    /// The standard alphabet, without padding.
+    StandardNoPad,
+    /// The URL-safe alphabet, with padding.
+    UrlSafe,
+    /// The URL-safe alphabet, without padding.
+    UrlSafeNoPad,
+}
+
+/// A handle which records fields that were ignored during deserialization.
+///
+/// Used by [`DeserializeOptions::collect_unknown_fields()`]. This type is reference counted, so
+/// a clone can be kept by the caller to retrieve the fields once deserialization is complete.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub struct UnknownFieldCollector {
+    inner: Arc<Mutex<UnknownFieldCollectorInner>>,
+}
+
+#[derive(Debug, Default)]
+struct UnknownFieldCollectorInner {
+    fields: Vec<UnknownJsonField>,
+}
+
+/// A field in the input which did not match any field of the message being deserialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub struct UnknownJsonField {
+    path: String,
+    value: serde_value::Value,
+}
+
+/// An error which occurred while deserializing a message, along with its location in the input.
+///
+/// Returned by [`DynamicMessage::deserialize_with_error_path()`].
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub struct DeserializeError<E> {
+    path: String,
+    value: Option<serde_value::Value>,
+    error: E,
+}
+
+/// The location of an error, recorded while unwinding from it.
+#[derive(Debug, Default)]
+struct ErrorContext {
+    /// The path to the error, innermost segment first.
+    path: Vec<PathSegment>,
+    value: Option<serde_value::Value>,
+    /// Whether to continue past errors which leave the deserializer in a consistent state.
+    collect_errors: bool,
+    /// The path to the value currently being deserialized, outermost segment first. Only
+    /// maintained when collecting errors.
+    current_path: Vec<PathSegment>,
+    /// Set when the error being returned can be recovered from, because the value which caused it
+    /// has been consumed entirely.
+    recoverable: bool,
+    errors: Vec<RecoveredError>,
+}
+
+/// An error which deserialization continued past.
+#[derive(Debug)]
+struct RecoveredError {
+    path: String,
+    value: Option<serde_value::Value>,
+    message: String,
+}
+
+#[derive(Debug)]
+enum PathSegment {
+    Field(String),
+    Index(usize),
+    Key(String),
 }
 
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
@@ -56,6 +256,21 @@ impl Serialize for DynamicMessage {
     }
 }
 
+/// Adapts [`DynamicMessage::serialize_with_options`] for serde functions which take a
+/// [`Serialize`] value rather than a [`Serializer`].
+#[cfg(any(feature = "bson", feature = "cbor"))]
+pub(crate) struct SerializeWithOptions<'a>(pub &'a DynamicMessage, pub &'a SerializeOptions);
+
+#[cfg(any(feature = "bson", feature = "cbor"))]
+impl<'a> Serialize for SerializeWithOptions<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize_with_options(serializer, self.1)
+    }
+}
+
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
 impl<'de> DeserializeSeed<'de> for MessageDescriptor {
     type Value = DynamicMessage;
@@ -114,7 +329,54 @@ impl DynamicMessage {
     where
         S: Serializer,
     {
-        ser::serialize_message(self, serializer, options)
+        if options.positional {
+            positional::serialize_message(self, serializer)
+        } else {
+            ser::serialize_message(self, serializer, options)
+        }
+    }
+
+    /// Serialize this message as JSON, appending the output to `buf`.
+    ///
+    /// Reusing the same buffer across calls avoids allocating for each message: scalar
+    /// values, map keys and well-known types are formatted directly into `buf`. If an error
+    /// occurs, `buf` is truncated back to its original length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_types::FileDescriptorSet;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, SerializeOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// let options = SerializeOptions::new();
+    /// let mut buf = Vec::new();
+    ///
+    /// dynamic_message.set_field_by_name("foo", Value::I32(150));
+    /// dynamic_message.serialize_json_into(&mut buf, &options).unwrap();
+    /// assert_eq!(buf, b"{\"foo\":150}");
+    ///
+    /// buf.clear();
+    /// dynamic_message.set_field_by_name("foo", Value::I32(42));
+    /// dynamic_message.serialize_json_into(&mut buf, &options).unwrap();
+    /// assert_eq!(buf, b"{\"foo\":42}");
+    /// ```
+    #[cfg(feature = "serde-json")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde-json")))]
+    pub fn serialize_json_into(
+        &self,
+        buf: &mut Vec<u8>,
+        options: &SerializeOptions,
+    ) -> Result<(), serde_json::Error> {
+        let len = buf.len();
+        let mut serializer = serde_json::Serializer::new(&mut *buf);
+        let result = self.serialize_with_options(&mut serializer, options);
+        if result.is_err() {
+            buf.truncate(len);
+        }
+        result
     }
 
     /// Deserialize an instance of the message type described by `desc` from `deserializer`.
@@ -170,7 +432,149 @@ impl DynamicMessage {
     where
         D: Deserializer<'de>,
     {
-        de::deserialize_message(&desc, deserializer, options)
+        if options.unknown_field_collector.is_some() && options.unknown_field_path.is_none() {
+            let options = DeserializeOptions {
+                unknown_field_path: Some(Default::default()),
+                ..options.clone()
+            };
+            return Self::deserialize_with_options(desc, deserializer, &options);
+        }
+
+        let message = if options.positional {
+            positional::deserialize_message(&desc, deserializer)?
+        } else {
+            de::deserialize_message(&desc, deserializer, options)?
+        };
+
+        if let Some(observer) = &options.usage_observer {
+            message.observe_usage(observer.as_ref());
+        }
+        Ok(message)
+    }
+
+    /// Deserialize an instance of the message type described by `desc` from `deserializer`, using
+    /// the encoding specified by `options`, and report where in the input any error occurred.
+    ///
+    /// This behaves identically to [`deserialize_with_options`][Self::deserialize_with_options],
+    /// except that if deserialization fails, the returned [`DeserializeError`] also records the
+    /// path to the field which could not be deserialized, and, for scalar values, the value
+    /// itself. To allow this, scalar values are buffered before being deserialized, so this may be
+    /// slightly slower.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let json = r#"{ "nested": { "nested": { "bar": "1.5" } } }"#;
+    /// let mut deserializer = serde_json::de::Deserializer::from_str(json);
+    /// let err = DynamicMessage::deserialize_with_error_path(message_descriptor, &mut deserializer, &DeserializeOptions::new()).unwrap_err();
+    ///
+    /// assert_eq!(err.path(), "nested.nested.bar");
+    /// assert_eq!(err.value::<String>().unwrap().unwrap(), "1.5");
+    /// assert_eq!(err.to_string(), "nested.nested.bar: invalid digit found in string at line 1 column 40");
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    pub fn deserialize_with_error_path<'de, D>(
+        desc: MessageDescriptor,
+        deserializer: D,
+        options: &DeserializeOptions,
+    ) -> Result<Self, DeserializeError<D::Error>>
+    where
+        D: Deserializer<'de>,
+    {
+        let context = Arc::new(Mutex::new(ErrorContext::default()));
+        let options = DeserializeOptions {
+            error_context: Some(context.clone()),
+            ..options.clone()
+        };
+
+        Self::deserialize_with_options(desc, deserializer, &options).map_err(|error| {
+            let context = match context.lock() {
+                Ok(mut guard) => std::mem::take(&mut *guard),
+                Err(err) => std::mem::take(&mut *err.into_inner()),
+            };
+            DeserializeError {
+                path: context.format_path(),
+                value: context.value,
+                error,
+            }
+        })
+    }
+
+    /// Deserialize an instance of the message type described by `desc` from `deserializer`, using
+    /// the encoding specified by `options`, continuing past any recoverable errors.
+    ///
+    /// Errors caused by a single value, such as an unrecognized enum name, a value of the wrong
+    /// type, an out of range number or an unrecognized field when
+    /// [`deny_unknown_fields`][DeserializeOptions::deny_unknown_fields] is set, are recorded and
+    /// the value is skipped. On success, this returns the partially populated message along with
+    /// the errors encountered, in the order they occurred. Each error records its location as
+    /// described for [`deserialize_with_error_path`][Self::deserialize_with_error_path].
+    ///
+    /// Errors which leave the input in an unknown state, such as syntax errors, still stop
+    /// deserialization and are returned as an [`Err`]. The underlying errors of recovered
+    /// errors are created with [`Error::custom`][serde::de::Error::custom] from the original
+    /// error message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let json = r#"{ "foo": "one", "nested": { "bar": 2, "baz": 3 } }"#;
+    /// let mut deserializer = serde_json::de::Deserializer::from_str(json);
+    /// let (message, errors) = DynamicMessage::deserialize_collecting_errors(message_descriptor, &mut deserializer, &DeserializeOptions::new()).unwrap();
+    /// deserializer.end().unwrap();
+    ///
+    /// let nested = message.get_field_by_name("nested").unwrap();
+    /// assert_eq!(nested.as_message().unwrap().get_field_by_name("bar").unwrap().as_ref(), &Value::I32(2));
+    ///
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].to_string(), "foo: invalid digit found in string");
+    /// assert_eq!(errors[1].to_string(), "nested.baz: unrecognized field name 'baz'");
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+    #[allow(clippy::type_complexity)]
+    pub fn deserialize_collecting_errors<'de, D>(
+        desc: MessageDescriptor,
+        deserializer: D,
+        options: &DeserializeOptions,
+    ) -> Result<(Self, Vec<DeserializeError<D::Error>>), DeserializeError<D::Error>>
+    where
+        D: Deserializer<'de>,
+    {
+        let context = Arc::new(Mutex::new(ErrorContext {
+            collect_errors: true,
+            ..Default::default()
+        }));
+        let options = DeserializeOptions {
+            error_context: Some(context.clone()),
+            ..options.clone()
+        };
+
+        let result = Self::deserialize_with_options(desc, deserializer, &options);
+        let context = std::mem::take(&mut *lock_error_context(&context));
+        let errors = context
+            .errors
+            .into_iter()
+            .map(|err| DeserializeError {
+                path: err.path,
+                value: err.value,
+                error: <D::Error as serde::de::Error>::custom(err.message),
+            })
+            .collect();
+
+        match result {
+            Ok(message) => Ok((message, errors)),
+            Err(error) => Err(DeserializeError {
+                path: format_path(context.path.iter().rev()),
+                value: context.value,
+                error,
+            }),
+        }
     }
 }
 
@@ -180,9 +584,35 @@ impl DeserializeOptions {
     pub const fn new() -> Self {
         DeserializeOptions {
             deny_unknown_fields: true,
+            deny_duplicate_keys: false,
+            unknown_field_collector: None,
+            unknown_field_path: None,
+            error_context: None,
+            case_insensitive_enum_names: false,
+            allow_unprefixed_enum_names: false,
+            closed_enums: false,
+            quoted_numbers: true,
+            allow_integer_fractions: false,
+            allow_integer_exponents: false,
+            positional: false,
+            allow_field_numbers: false,
+            type_resolver: None,
+            usage_observer: None,
         }
     }
 
+    /// Creates a new instance of [`DeserializeOptions`] which matches the expectations of the
+    /// protobuf [conformance tests](https://github.com/protocolbuffers/protobuf/tree/main/conformance)
+    /// as closely as possible.
+    ///
+    /// This is the same as [`new`][Self::new], except that
+    /// [`deny_duplicate_keys`][Self::deny_duplicate_keys] is enabled. Input which the JSON
+    /// mapping doesn't allow, such as a `google.protobuf.Any` without an `@type` field, or a
+    /// `google.protobuf.Timestamp` outside the supported range, is always rejected.
+    pub const fn conformance() -> Self {
+        DeserializeOptions::new().deny_duplicate_keys(true)
+    }
+
     /// Whether to error during deserialization when encountering unknown message fields.
     ///
     /// The default value is `true`.
@@ -190,6 +620,382 @@ impl DeserializeOptions {
         self.deny_unknown_fields = yes;
         self
     }
+
+    /// Whether to error during deserialization when a field is given more than once in the same
+    /// object, or a key is given more than once in a map field.
+    ///
+    /// Fields are compared by number, so a field given under both its JSON name and its proto name
+    /// is also rejected. If `false`, the last value is used.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let json = r#"{ "foo": 150, "foo": 151 }"#;
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_str(json);
+    /// let message = DynamicMessage::deserialize(message_descriptor.clone(), &mut deserializer).unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(151));
+    ///
+    /// let options = DeserializeOptions::new().deny_duplicate_keys(true);
+    /// let mut deserializer = serde_json::Deserializer::from_str(json);
+    /// let err = DynamicMessage::deserialize_with_options(message_descriptor, &mut deserializer, &options).unwrap_err();
+    /// assert!(err.to_string().contains("duplicate field 'foo'"));
+    /// ```
+    pub const fn deny_duplicate_keys(mut self, yes: bool) -> Self {
+        self.deny_duplicate_keys = yes;
+        self
+    }
+
+    /// Records fields which are ignored during deserialization in `collector`.
+    ///
+    /// This only has an effect if [`deny_unknown_fields`][Self::deny_unknown_fields] is `false`.
+    /// Each unrecognized key is recorded along with its value and the path leading to it from the
+    /// top-level message, so that callers can log exactly what was dropped. Paths are formatted
+    /// as described for [`DeserializeError::path`], for example `items[2].extra`.
+    ///
+    /// The collector may be shared between several deserializations, including concurrent ones.
+    /// Each field is recorded with the path from its own top-level message.
+    ///
+    /// By default, unknown fields are discarded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, UnknownFieldCollector};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let json = r#"{ "foo": 150, "nested": { "baz": [1, 2] } }"#;
+    /// let mut deserializer = serde_json::de::Deserializer::from_str(json);
+    /// let collector = UnknownFieldCollector::new();
+    /// let options = DeserializeOptions::new()
+    ///     .deny_unknown_fields(false)
+    ///     .collect_unknown_fields(collector.clone());
+    /// DynamicMessage::deserialize_with_options(message_descriptor, &mut deserializer, &options).unwrap();
+    /// deserializer.end().unwrap();
+    ///
+    /// let unknown_fields = collector.take();
+    /// assert_eq!(unknown_fields.len(), 1);
+    /// assert_eq!(unknown_fields[0].path(), "nested.baz");
+    /// assert_eq!(unknown_fields[0].value::<serde_json::Value>().unwrap(), serde_json::json!([1, 2]));
+    /// ```
+    pub fn collect_unknown_fields(mut self, collector: UnknownFieldCollector) -> Self {
+        self.unknown_field_collector = Some(collector);
+        self
+    }
+
+    /// Whether to ignore ASCII case when matching enum value names.
+    ///
+    /// An exact match is always preferred. Otherwise, if several values match, the first one
+    /// declared in the enum is used.
+    ///
+    /// The default value is `false`.
+    pub const fn case_insensitive_enum_names(mut self, yes: bool) -> Self {
+        self.case_insensitive_enum_names = yes;
+        self
+    }
+
+    /// Whether to match enum value names with or without the conventional prefix derived from the
+    /// enum name.
+    ///
+    /// Following the protobuf style guide, values are often prefixed with the name of their enum
+    /// in UPPER_SNAKE_CASE. If this option is set, the prefix may be omitted, so `"ACTIVE"` is
+    /// accepted for the value `STATUS_ACTIVE` of the enum `Status`. The prefix may also be added to
+    /// values which are declared without it. This may be combined with
+    /// [`case_insensitive_enum_names`][Self::case_insensitive_enum_names].
+    ///
+    /// An exact match is always preferred. Otherwise, if several values match, the first one
+    /// declared in the enum is used.
+    ///
+    /// The default value is `false`.
+    pub const fn allow_unprefixed_enum_names(mut self, yes: bool) -> Self {
+        self.allow_unprefixed_enum_names = yes;
+        self
+    }
+
+    /// Whether to reject numbers which are not defined by a closed enum.
+    ///
+    /// Enums defined in proto2 files are closed, so the
+    /// [`closed_enums`][crate::DecodeOptions::closed_enums] decode option keeps undefined numbers
+    /// out of their fields. If this option is set, an undefined number for a closed enum field,
+    /// list element or map value is a deserialization error. Open enums, defined in proto3 files,
+    /// are unaffected, as are enum values given by name.
+    ///
+    /// The default value is `false`.
+    pub const fn closed_enums(mut self, yes: bool) -> Self {
+        self.closed_enums = yes;
+        self
+    }
+
+    /// Whether to accept values of integer and floating point fields given as JSON strings, such
+    /// as `"42"` or `"1.5"`.
+    ///
+    /// The standard JSON mapping accepts quoted numbers for every numeric field, including the
+    /// wrapper types such as `google.protobuf.Int32Value`. If this option is unset, only JSON
+    /// numbers are accepted, except for the special floating point values `"NaN"`, `"Infinity"`
+    /// and `"-Infinity"`, which can only be written as strings.
+    ///
+    /// The default value is `true`.
+    pub const fn quoted_numbers(mut self, yes: bool) -> Self {
+        self.quoted_numbers = yes;
+        self
+    }
+
+    /// Whether to accept quoted values of integer fields with a fractional part of zero, such as
+    /// `"42.0"`.
+    ///
+    /// JSON numbers such as `42.0` are always accepted for integer fields, because most JSON
+    /// parsers do not distinguish them from `42`. This option extends the same leniency to
+    /// quoted numbers. The value must be an exact integer within the range of the field, so
+    /// `"42.5"` is still rejected.
+    ///
+    /// The default value is `false`.
+    pub const fn allow_integer_fractions(mut self, yes: bool) -> Self {
+        self.allow_integer_fractions = yes;
+        self
+    }
+
+    /// Whether to accept quoted values of integer fields written in exponent notation, such as
+    /// `"1e3"` or `"1.5e2"`.
+    ///
+    /// As with [`allow_integer_fractions`][Self::allow_integer_fractions], JSON numbers in
+    /// exponent notation are always accepted. Quoted values are parsed exactly rather than via a
+    /// floating point number, so large values keep full precision, and the result must be an exact
+    /// integer within the range of the field.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let options = DeserializeOptions::new()
+    ///     .allow_integer_fractions(true)
+    ///     .allow_integer_exponents(true);
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"{ "foo": "1.5e2" }"#);
+    /// let message = DynamicMessage::deserialize_with_options(message_descriptor.clone(), &mut deserializer, &options).unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    ///
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"{ "foo": "1.55e1" }"#);
+    /// assert!(DynamicMessage::deserialize_with_options(message_descriptor, &mut deserializer, &options).is_err());
+    /// ```
+    pub const fn allow_integer_exponents(mut self, yes: bool) -> Self {
+        self.allow_integer_exponents = yes;
+        self
+    }
+
+    /// Whether to deserialize messages from tuples of field values, for serde formats which are
+    /// not self-describing, such as [bincode](https://docs.rs/bincode).
+    ///
+    /// This reads the representation written by [`SerializeOptions::positional`], and requires
+    /// exactly the same message descriptor the data was written with. Fields are read in the order
+    /// they are declared in the message descriptor, so the format does not need to support
+    /// [`deserialize_any`][Deserializer::deserialize_any] or provide field names. All other
+    /// deserialization options are ignored.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, SerializeOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor.clone(), b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    ///
+    /// // Any serde data format may be used, such as bincode or postcard.
+    /// let serialized = dynamic_message
+    ///     .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new().positional(true))
+    ///     .unwrap();
+    /// assert_eq!(serialized.to_string(), "[150,null,[0,66,null]]");
+    /// let deserialized = DynamicMessage::deserialize_with_options(
+    ///     message_descriptor,
+    ///     serialized,
+    ///     &DeserializeOptions::new().positional(true),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(deserialized, dynamic_message);
+    /// ```
+    pub const fn positional(mut self, yes: bool) -> Self {
+        self.positional = yes;
+        self
+    }
+
+    /// Whether to accept field numbers as message keys, in addition to field names.
+    ///
+    /// If `true`, keys may be integers, or strings containing a decimal field number, as written by
+    /// [`SerializeOptions::use_field_numbers`]. Numbers are looked up as fields of the message,
+    /// then as extensions. Keys which are not valid field numbers are treated as unknown fields.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, DeserializeOptions, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let options = DeserializeOptions::new().allow_field_numbers(true);
+    /// let mut deserializer = serde_json::Deserializer::from_str(r#"{ "1": 150, "nested": { "2": 66 } }"#);
+    /// let message = DynamicMessage::deserialize_with_options(message_descriptor, &mut deserializer, &options).unwrap();
+    /// assert_eq!(message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    pub const fn allow_field_numbers(mut self, yes: bool) -> Self {
+        self.allow_field_numbers = yes;
+        self
+    }
+
+    /// Sets the resolver used to look up the payload type of `google.protobuf.Any` messages from
+    /// the `@type` field.
+    ///
+    /// By default, payload types are looked up in the pool of the `Any` message. See
+    /// [`TypeResolver`] for details.
+    pub fn type_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: TypeResolver + 'static,
+    {
+        self.type_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Sets an observer to report the deprecated fields and enum values set in each deserialized
+    /// message.
+    ///
+    /// The observer is called once deserialization has finished successfully, for all fields in
+    /// the message. See [`UsageObserver`] for details.
+    ///
+    /// By default, no observer is set.
+    pub fn usage_observer<O>(mut self, observer: O) -> Self
+    where
+        O: UsageObserver + 'static,
+    {
+        self.usage_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Records an unrecognized field in `collector`, at the current path.
+    fn record_unknown_field(
+        &self,
+        collector: &UnknownFieldCollector,
+        name: &str,
+        value: serde_value::Value,
+    ) {
+        let field = PathSegment::Field(name.to_owned());
+        let path = match &self.unknown_field_path {
+            Some(path) => format_path(lock_path(path).iter().chain(Some(&field))),
+            None => format_path(Some(&field).into_iter()),
+        };
+        collector
+            .lock()
+            .fields
+            .push(UnknownJsonField { path, value });
+    }
+
+    fn tracks_errors(&self) -> bool {
+        self.error_context.is_some()
+    }
+
+    /// Records the next segment of the path to an error, while unwinding from it.
+    fn record_error_path(&self, segment: impl FnOnce() -> PathSegment) {
+        if let Some(context) = &self.error_context {
+            lock_error_context(context).path.push(segment());
+        }
+    }
+
+    /// Records the value which caused an error, if no value has been recorded already.
+    fn record_error_value(&self, value: serde_value::Value) {
+        if let Some(context) = &self.error_context {
+            lock_error_context(context).value.get_or_insert(value);
+        }
+    }
+
+    fn collects_errors(&self) -> bool {
+        match &self.error_context {
+            Some(context) => lock_error_context(context).collect_errors,
+            None => false,
+        }
+    }
+
+    /// Marks the error about to be returned as recoverable, because the value which caused it
+    /// has been consumed entirely.
+    fn mark_recoverable(&self) {
+        if let Some(context) = &self.error_context {
+            let mut context = lock_error_context(context);
+            context.recoverable = context.collect_errors;
+        }
+    }
+
+    /// Deserializes the value at `segment` using `f`, recording the location of any error.
+    ///
+    /// When collecting errors, a recoverable error is recorded and `Ok(None)` is returned, so
+    /// the caller can skip the value and continue.
+    fn visit_value<T, E>(
+        &self,
+        segment: impl Fn() -> PathSegment,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Option<T>, E>
+    where
+        E: serde::de::Error,
+    {
+        if let Some(path) = &self.unknown_field_path {
+            lock_path(path).push(segment());
+            let result = self.visit_value_inner(segment, f);
+            lock_path(path).pop();
+            result
+        } else {
+            self.visit_value_inner(segment, f)
+        }
+    }
+
+    fn visit_value_inner<T, E>(
+        &self,
+        segment: impl Fn() -> PathSegment,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Option<T>, E>
+    where
+        E: serde::de::Error,
+    {
+        let context = match &self.error_context {
+            Some(context) if lock_error_context(context).collect_errors => context,
+            _ => {
+                return f().map(Some).map_err(|err| {
+                    self.record_error_path(segment);
+                    err
+                })
+            }
+        };
+
+        lock_error_context(context).current_path.push(segment());
+        let result = f();
+        let mut context = lock_error_context(context);
+        let result = match result {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if context.recoverable => {
+                let recovered = RecoveredError {
+                    path: format_path(context.current_path.iter()),
+                    value: context.value.take(),
+                    message: err.to_string(),
+                };
+                context.errors.push(recovered);
+                context.recoverable = false;
+                context.path.clear();
+                Ok(None)
+            }
+            Err(err) => {
+                context.path.push(segment());
+                Err(err)
+            }
+        };
+        context.current_path.pop();
+        result
+    }
 }
 
 impl Default for DeserializeOptions {
@@ -207,16 +1013,53 @@ impl SerializeOptions {
             use_enum_numbers: false,
             use_proto_field_name: false,
             skip_default_fields: true,
+            emit_nulls_for_absent_optionals: false,
+            canonical: false,
+            timestamp_precision: TimestampPrecision::Standard,
+            out_of_range_policy: OutOfRangePolicy::Error,
+            bytes_encoding: Base64Encoding::Standard,
+            field_mask: None,
+            positional: false,
+            use_field_numbers: false,
+            native_values: false,
+            type_resolver: None,
         }
     }
 
+    /// Creates a new instance of [`SerializeOptions`] which produces the output expected by the
+    /// protobuf [conformance tests](https://github.com/protocolbuffers/protobuf/tree/main/conformance).
+    ///
+    /// The default options already follow the JSON mapping, so this is the same as
+    /// [`new`][Self::new]. It is provided for symmetry with [`DeserializeOptions::conformance`].
+    /// Values which cannot be represented, such as a `google.protobuf.Timestamp` outside the
+    /// supported range, or a `google.protobuf.Any` whose type cannot be resolved, cause
+    /// serialization to fail.
+    pub const fn conformance() -> Self {
+        SerializeOptions::new()
+    }
+
     /// Whether to encode 64-bit integral types as strings.
     ///
     /// The spec requires encoding 64-bit integers as strings, to prevent loss of precision in JSON
     /// when the value cannot be represented exactly by a double. If this option is disabled, all
-    /// numbers will be serialized as their corresponding serde types instead.
+    /// numbers will be serialized as their corresponding serde types instead. This applies to all
+    /// 64-bit integer field types, as well as the `google.protobuf.Int64Value` and
+    /// `google.protobuf.UInt64Value` wrapper types. Map keys are always serialized as strings.
     ///
     /// The default value is `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{ReflectMessage, SerializeOptions};
+    /// # use serde_json::json;
+    /// let value = 9007199254740993i64.transcode_to_dynamic();
+    /// assert_eq!(serde_json::to_value(&value).unwrap(), json!("9007199254740993"));
+    ///
+    /// let options = SerializeOptions::new().stringify_64_bit_integers(false);
+    /// let json = value.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!(9007199254740993i64));
+    /// ```
     pub const fn stringify_64_bit_integers(mut self, yes: bool) -> Self {
         self.stringify_64_bit_integers = yes;
         self
@@ -225,7 +1068,12 @@ impl SerializeOptions {
     /// Whether to encode enum values as their numeric value.
     ///
     /// If `true`, enum values will be serialized as their integer values. Otherwise, they will be
-    /// serialized as the string value specified in the proto file.
+    /// serialized as the string value specified in the proto file. Values which are not defined
+    /// in the enum are always serialized as integers, and `google.protobuf.NullValue` is always
+    /// serialized as `null`.
+    ///
+    /// This is the alternate representation permitted by the JSON mapping. Deserialization always
+    /// accepts both the string and integer forms, so no corresponding option is needed there.
     ///
     /// The default value is `false`.
     pub const fn use_enum_numbers(mut self, yes: bool) -> Self {
@@ -235,12 +1083,27 @@ impl SerializeOptions {
 
     /// Whether to use the proto field name instead of the lowerCamelCase name in JSON field names.
     ///
+    /// This is equivalent to the `preserve_proto_field_names` option of the C++ implementation.
+    /// It applies to the fields of nested messages as well as the top-level message, but does not
+    /// affect map keys. Deserialization always accepts both the proto field name and the JSON name,
+    /// so no corresponding option is needed there.
+    ///
+    /// If set, this takes precedence over the [`FieldNameStrategy`] of the pool.
+    ///
     /// The default value is `false`.
     pub const fn use_proto_field_name(mut self, yes: bool) -> Self {
         self.use_proto_field_name = yes;
         self
     }
 
+    fn field_name<'a>(&self, field: &'a FieldDescriptor) -> &'a str {
+        if self.use_proto_field_name {
+            field.name()
+        } else {
+            field.json_key()
+        }
+    }
+
     /// Whether to skip fields which have their default value.
     ///
     /// If `true`, any fields for which [`has_field`][DynamicMessage::has_field] returns `false` will
@@ -251,6 +1114,263 @@ impl SerializeOptions {
         self.skip_default_fields = yes;
         self
     }
+
+    /// Whether to serialize fields which support presence, but are not set, as `null`.
+    ///
+    /// If `true`, any fields for which [`supports_presence`][FieldDescriptor::supports_presence]
+    /// returns `true` but [`has_field`][DynamicMessage::has_field] returns `false` will be
+    /// serialized with the value `null`, so that consumers can distinguish a field which is absent
+    /// from one which is set to its default value. This includes message fields, proto3 `optional`
+    /// fields and the unset fields of a oneof. Other fields are not affected by this option.
+    ///
+    /// Note that when deserializing, `null` is treated as an absent value for all fields except
+    /// those of type `google.protobuf.Value` or `google.protobuf.NullValue`, where it is parsed as
+    /// a null value.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01".as_ref()).unwrap();
+    /// let mut serializer = serde_json::Serializer::new(vec![]);
+    /// let options = SerializeOptions::new().emit_nulls_for_absent_optionals(true);
+    /// dynamic_message.serialize_with_options(&mut serializer, &options).unwrap();
+    /// assert_eq!(serializer.into_inner(), b"{\"foo\":150,\"bar\":null,\"nested\":null}");
+    /// ```
+    pub const fn emit_nulls_for_absent_optionals(mut self, yes: bool) -> Self {
+        self.emit_nulls_for_absent_optionals = yes;
+        self
+    }
+
+    /// Whether to produce the canonical form of the JSON output, as defined by
+    /// [RFC 8785](https://www.rfc-editor.org/rfc/rfc8785) (the JSON Canonicalization Scheme).
+    ///
+    /// If `true`, the keys of every JSON object, including message fields, maps and
+    /// `google.protobuf.Struct` values, are sorted by their UTF-16 code units, and `float` values
+    /// are widened to the double with the same shortest decimal representation. Together with
+    /// [`CanonicalJsonFormatter`], which formats numbers as required by the RFC, this produces
+    /// byte-stable output suitable for signing or deduplication.
+    ///
+    /// Other options still apply in canonical mode, so for example 64-bit integers are
+    /// serialized as strings unless [`stringify_64_bit_integers`][Self::stringify_64_bit_integers]
+    /// is disabled.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions, CanonicalJsonFormatter};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x02".as_ref()).unwrap();
+    /// let mut serializer = serde_json::Serializer::with_formatter(vec![], CanonicalJsonFormatter::new());
+    /// let options = SerializeOptions::new().canonical(true);
+    /// dynamic_message.serialize_with_options(&mut serializer, &options).unwrap();
+    /// assert_eq!(serializer.into_inner(), b"{\"foo\":150,\"nested\":{\"bar\":2}}");
+    /// ```
+    pub const fn canonical(mut self, yes: bool) -> Self {
+        self.canonical = yes;
+        self
+    }
+
+    /// Sets the number of fractional second digits used when serializing
+    /// `google.protobuf.Timestamp` values.
+    ///
+    /// If the precision is lower than needed to represent a value exactly, the extra digits are
+    /// truncated.
+    ///
+    /// The default value is [`TimestampPrecision::Standard`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{ReflectMessage, SerializeOptions, TimestampPrecision};
+    /// let timestamp = prost_types::Timestamp { seconds: 1_000_000_000, nanos: 120_000_000 }.transcode_to_dynamic();
+    ///
+    /// let json = serde_json::to_string(&timestamp).unwrap();
+    /// assert_eq!(json, r#""2001-09-09T01:46:40.120Z""#);
+    ///
+    /// let mut serializer = serde_json::Serializer::new(vec![]);
+    /// let options = SerializeOptions::new().timestamp_precision(TimestampPrecision::Minimal);
+    /// timestamp.serialize_with_options(&mut serializer, &options).unwrap();
+    /// assert_eq!(serializer.into_inner(), br#""2001-09-09T01:46:40.12Z""#);
+    ///
+    /// let mut serializer = serde_json::Serializer::new(vec![]);
+    /// let options = SerializeOptions::new().timestamp_precision(TimestampPrecision::Nanos);
+    /// timestamp.serialize_with_options(&mut serializer, &options).unwrap();
+    /// assert_eq!(serializer.into_inner(), br#""2001-09-09T01:46:40.120000000Z""#);
+    /// ```
+    pub const fn timestamp_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.timestamp_precision = precision;
+        self
+    }
+
+    /// Sets how `google.protobuf.Timestamp` and `google.protobuf.Duration` values outside the
+    /// range supported by the JSON mapping are serialized.
+    ///
+    /// Note that output produced with [`OutOfRangePolicy::Raw`] cannot be deserialized, since
+    /// deserialization always expects the standard string representation.
+    ///
+    /// The default value is [`OutOfRangePolicy::Error`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{ReflectMessage, SerializeOptions, OutOfRangePolicy};
+    /// # use serde_json::json;
+    /// let timestamp = prost_types::Timestamp { seconds: i64::MIN, nanos: 0 }.transcode_to_dynamic();
+    /// assert!(serde_json::to_value(&timestamp).is_err());
+    ///
+    /// let options = SerializeOptions::new().out_of_range_policy(OutOfRangePolicy::Clamp);
+    /// let json = timestamp.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!("0001-01-01T00:00:00Z"));
+    ///
+    /// let options = SerializeOptions::new().out_of_range_policy(OutOfRangePolicy::Raw);
+    /// let json = timestamp.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!({ "seconds": "-9223372036854775808" }));
+    /// ```
+    pub const fn out_of_range_policy(mut self, policy: OutOfRangePolicy) -> Self {
+        self.out_of_range_policy = policy;
+        self
+    }
+
+    /// Sets the base64 alphabet and padding used when serializing `bytes` fields and
+    /// `google.protobuf.BytesValue` values.
+    ///
+    /// The default value is [`Base64Encoding::Standard`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{ReflectMessage, SerializeOptions, Base64Encoding};
+    /// # use serde_json::json;
+    /// let value = b"\xfb\xff".to_vec().transcode_to_dynamic();
+    /// assert_eq!(serde_json::to_value(&value).unwrap(), json!("+/8="));
+    ///
+    /// let options = SerializeOptions::new().bytes_encoding(Base64Encoding::UrlSafeNoPad);
+    /// let json = value.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!("-_8"));
+    /// ```
+    pub const fn bytes_encoding(mut self, encoding: Base64Encoding) -> Self {
+        self.bytes_encoding = encoding;
+        self
+    }
+
+    /// Only serialize the fields selected by `mask`, as when applying a
+    /// [read mask](https://google.aip.dev/157) to a response.
+    ///
+    /// Each path in the mask is a sequence of field names separated by `.`, and selects the named
+    /// field along with all of its contents. Paths may continue into the fields of singular and
+    /// repeated message fields, and into map fields, where the next segment is the map key. A
+    /// segment may be quoted with backticks if it contains a `.`, for example `` labels.`a.b` ``.
+    /// The segment `*` selects every field, element or map entry at that position, so the path
+    /// `*` selects the whole message and `books.*.title` selects the `title` field of every element
+    /// of `books`. Paths continuing into a well-known type, or a scalar field, select the whole
+    /// value.
+    ///
+    /// Only fields which would otherwise be serialized are included, so fields with default values
+    /// are still omitted unless [`skip_default_fields`][Self::skip_default_fields] is `false`.
+    /// Paths naming fields which do not exist are ignored. Extension fields are selected by their
+    /// full name, quoted with backticks.
+    ///
+    /// By default, or if `mask` has no paths, all fields are serialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions};
+    /// # use prost_types::FieldMask;
+    /// # use serde_json::json;
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let mask = FieldMask { paths: vec!["nested.bar".to_owned()] };
+    /// let options = SerializeOptions::new().field_mask(mask);
+    /// let json = dynamic_message.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!({ "nested": { "bar": 66 } }));
+    /// ```
+    pub fn field_mask(mut self, mask: FieldMask) -> Self {
+        self.field_mask = FieldMaskTree::new(&mask);
+        self
+    }
+
+    /// Whether to serialize messages as tuples of field values, for serde formats which are not
+    /// self-describing, such as [bincode](https://docs.rs/bincode).
+    ///
+    /// If `true`, each message is serialized as a tuple containing a value for every field, in the
+    /// order they are declared in the message descriptor, instead of using the JSON mapping. Fields
+    /// which support presence are serialized as options, and repeated and map fields as sequences
+    /// and maps. The output can be read back with [`DeserializeOptions::positional`].
+    ///
+    /// This representation does not include field names or numbers, so it can only be read using
+    /// exactly the same message descriptor it was written with. Extension fields and unknown fields
+    /// are not serialized, and all other serialization options are ignored.
+    ///
+    /// The default value is `false`.
+    pub const fn positional(mut self, yes: bool) -> Self {
+        self.positional = yes;
+        self
+    }
+
+    /// Whether to key message fields by their field number instead of their name.
+    ///
+    /// If `true`, fields and extensions are serialized with integer keys, which gives a more
+    /// compact encoding for binary formats such as CBOR. Formats which only support string keys,
+    /// such as JSON, write the number as a string. The `@type` entry of `google.protobuf.Any`
+    /// messages is still keyed by name. The output can be read back with
+    /// [`DeserializeOptions::allow_field_numbers`].
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, SerializeOptions};
+    /// # use serde_json::json;
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let options = SerializeOptions::new().use_field_numbers(true);
+    /// let json = dynamic_message.serialize_with_options(serde_json::value::Serializer, &options).unwrap();
+    /// assert_eq!(json, json!({ "1": 150, "3": { "2": 66 } }));
+    /// ```
+    pub const fn use_field_numbers(mut self, yes: bool) -> Self {
+        self.use_field_numbers = yes;
+        self
+    }
+
+    /// Whether to serialize values using the native types of binary formats such as BSON, rather
+    /// than their JSON representations.
+    ///
+    /// Bytes are serialized with [`Serializer::serialize_bytes`][serde::Serializer::serialize_bytes],
+    /// non-finite floats as floats, and `google.protobuf.Timestamp` messages as a newtype struct
+    /// named [`NATIVE_TIMESTAMP`] containing the number of milliseconds since the Unix epoch.
+    /// Implies `stringify_64_bit_integers(false)`.
+    #[cfg(feature = "bson")]
+    pub(crate) const fn native_values(mut self, yes: bool) -> Self {
+        self.native_values = yes;
+        if yes {
+            self.stringify_64_bit_integers = false;
+        }
+        self
+    }
+
+    /// Sets the resolver used to look up the payload type of `google.protobuf.Any` messages.
+    ///
+    /// By default, payload types are looked up in the pool of the `Any` message. See
+    /// [`TypeResolver`] for details.
+    pub fn type_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: TypeResolver + 'static,
+    {
+        self.type_resolver = Some(Arc::new(resolver));
+        self
+    }
 }
 
 impl Default for SerializeOptions {
@@ -259,6 +1379,169 @@ impl Default for SerializeOptions {
     }
 }
 
+impl UnknownFieldCollector {
+    /// Creates a new, empty collector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes and returns all fields recorded so far.
+    pub fn take(&self) -> Vec<UnknownJsonField> {
+        std::mem::take(&mut self.lock().fields)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, UnknownFieldCollectorInner> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+fn lock_path(path: &Mutex<Vec<PathSegment>>) -> std::sync::MutexGuard<'_, Vec<PathSegment>> {
+    match path.lock() {
+        Ok(guard) => guard,
+        Err(err) => err.into_inner(),
+    }
+}
+
+fn lock_error_context(context: &Mutex<ErrorContext>) -> std::sync::MutexGuard<'_, ErrorContext> {
+    match context.lock() {
+        Ok(guard) => guard,
+        Err(err) => err.into_inner(),
+    }
+}
+
+impl ErrorContext {
+    fn format_path(&self) -> String {
+        format_path(self.path.iter().rev())
+    }
+}
+
+/// Formats a path to a value, given its segments from the outermost.
+fn format_path<'a>(segments: impl Iterator<Item = &'a PathSegment>) -> String {
+    let mut result = String::new();
+    for segment in segments {
+        match segment {
+            PathSegment::Field(name) => {
+                if !result.is_empty() {
+                    result.push('.');
+                }
+                result.push_str(name);
+            }
+            PathSegment::Index(index) => {
+                result.push('[');
+                result.push_str(&index.to_string());
+                result.push(']');
+            }
+            PathSegment::Key(key) => {
+                result.push('[');
+                result.push_str(key);
+                result.push(']');
+            }
+        }
+    }
+    result
+}
+
+impl<E> DeserializeError<E> {
+    /// Returns the path to the value which could not be deserialized, from the top-level message.
+    ///
+    /// The path is built from the keys in the input, with fields separated by `.`, and list
+    /// indices and map keys in square brackets, for example `orders[3].item.price` or
+    /// `labels[env]`. If the error did not occur within a field, for example because the input
+    /// was not a valid JSON object, the path is empty.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Deserializes the value which caused the error, for example into a `serde_json::Value`.
+    ///
+    /// Returns `None` if the error was not caused by a scalar value, for example if it was caused
+    /// by an unrecognized field or a syntax error.
+    pub fn value<T>(&self) -> Option<Result<T, impl Error>>
+    where
+        T: DeserializeOwned,
+    {
+        self.value.clone().map(|value| value.deserialize_into())
+    }
+
+    /// Returns the underlying error returned by the deserializer.
+    pub fn inner(&self) -> &E {
+        &self.error
+    }
+
+    /// Consumes this error, returning the underlying error returned by the deserializer.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E> fmt::Display for DeserializeError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.error)
+        } else {
+            write!(f, "{}: {}", self.path, self.error)
+        }
+    }
+}
+
+impl<E> Error for DeserializeError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl UnknownJsonField {
+    /// Returns the path to this field from the top-level message, as a `.`-separated list of the
+    /// keys in the input.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Deserializes the value of this field, for example into a `serde_json::Value`.
+    pub fn value<T>(&self) -> Result<T, impl Error>
+    where
+        T: DeserializeOwned,
+    {
+        self.value.clone().deserialize_into()
+    }
+}
+
+impl FieldNameStrategy {
+    pub(crate) fn field_name<'a>(&self, field: &'a FieldDescriptor) -> Cow<'a, str> {
+        match self {
+            FieldNameStrategy::JsonName => Cow::Borrowed(field.json_name()),
+            FieldNameStrategy::ProtoName => Cow::Borrowed(field.name()),
+            FieldNameStrategy::SnakeCase => Cow::Owned(to_snake_case(field.name(), '_')),
+            FieldNameStrategy::KebabCase => Cow::Owned(to_snake_case(field.name(), '-')),
+            FieldNameStrategy::Custom(f) => Cow::Owned(f(field)),
+        }
+    }
+}
+
+impl fmt::Debug for FieldNameStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldNameStrategy::JsonName => write!(f, "JsonName"),
+            FieldNameStrategy::ProtoName => write!(f, "ProtoName"),
+            FieldNameStrategy::SnakeCase => write!(f, "SnakeCase"),
+            FieldNameStrategy::KebabCase => write!(f, "KebabCase"),
+            FieldNameStrategy::Custom(_) => write!(f, "Custom(..)"),
+        }
+    }
+}
+
+/// The name of the newtype struct used to serialize timestamps when native values are enabled.
+pub(crate) const NATIVE_TIMESTAMP: &str = "Timestamp";
+
 const MAX_DURATION_SECONDS: u64 = 315_576_000_000;
 const MAX_DURATION_NANOS: u32 = 999_999_999;
 
@@ -0,0 +1,31 @@
+use crate::{DeserializeOptions, DynamicMessage, MessageDescriptor};
+
+/// The error returned when a JSON document cannot be parsed by [`deserialize_json`].
+#[cfg(not(feature = "simd-json"))]
+pub(crate) type JsonParseError = serde_json::Error;
+/// The error returned when a JSON document cannot be parsed by [`deserialize_json`].
+#[cfg(feature = "simd-json")]
+pub(crate) type JsonParseError = simd_json1::Error;
+
+/// Deserializes a message of type `desc` from a complete JSON document.
+///
+/// This is used wherever the crate parses JSON input itself, rather than being given a
+/// deserializer by the caller. If the `simd-json` feature is enabled the document is parsed with
+/// simd-json, which uses `json` as scratch space, so its contents are unspecified afterwards.
+/// Otherwise it is parsed with serde_json.
+pub(crate) fn deserialize_json(
+    desc: MessageDescriptor,
+    json: &mut [u8],
+    options: &DeserializeOptions,
+) -> Result<DynamicMessage, JsonParseError> {
+    #[cfg(feature = "simd-json")]
+    {
+        DynamicMessage::from_simd_json_slice_with_options(desc, json, options)
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        DynamicMessage::deserialize_with_options(desc, &mut deserializer, options)
+            .and_then(|message| deserializer.end().map(|()| message))
+    }
+}
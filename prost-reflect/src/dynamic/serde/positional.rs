@@ -0,0 +1,362 @@
+use std::{collections::HashMap, fmt};
+
+use prost::bytes::Bytes;
+use serde::{
+    de::{DeserializeSeed, Deserializer, Error, MapAccess, SeqAccess, Visitor},
+    ser::{Serialize, SerializeMap, SerializeSeq, SerializeTuple, Serializer},
+    Deserialize,
+};
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor, Value};
+
+pub(super) fn serialize_message<S>(
+    message: &DynamicMessage,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    PositionalMessage(message).serialize(serializer)
+}
+
+pub(super) fn deserialize_message<'de, D>(
+    desc: &MessageDescriptor,
+    deserializer: D,
+) -> Result<DynamicMessage, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    MessageSeed(desc).deserialize(deserializer)
+}
+
+struct PositionalMessage<'a>(&'a DynamicMessage);
+
+impl<'a> Serialize for PositionalMessage<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let desc = &self.0.desc;
+        let mut tuple = serializer.serialize_tuple(desc.fields().len())?;
+        for field in desc.fields() {
+            let value = self.0.get_field(&field);
+            if is_optional(&field) {
+                let value = if self.0.has_field(&field) {
+                    Some(PositionalValue(&value))
+                } else {
+                    None
+                };
+                tuple.serialize_element(&value)?;
+            } else {
+                tuple.serialize_element(&PositionalValue(&value))?;
+            }
+        }
+        tuple.end()
+    }
+}
+
+struct PositionalValue<'a>(&'a Value);
+
+impl<'a> Serialize for PositionalValue<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::I32(value) | Value::EnumNumber(value) => serializer.serialize_i32(*value),
+            Value::I64(value) => serializer.serialize_i64(*value),
+            Value::U32(value) => serializer.serialize_u32(*value),
+            Value::U64(value) => serializer.serialize_u64(*value),
+            Value::F32(value) => serializer.serialize_f32(*value),
+            Value::F64(value) => serializer.serialize_f64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Bytes(value) => serializer.serialize_bytes(value),
+            Value::Message(message) => PositionalMessage(message).serialize(serializer),
+            Value::List(values) => {
+                let mut list = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    list.serialize_element(&PositionalValue(value))?;
+                }
+                list.end()
+            }
+            Value::Map(values) => {
+                // Sort entries so the output is deterministic.
+                let mut entries: Vec<_> = values.iter().collect();
+                entries.sort_unstable_by_key(|&(key, _)| key);
+
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&PositionalMapKey(key), &PositionalValue(value))?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct PositionalMapKey<'a>(&'a MapKey);
+
+impl<'a> Serialize for PositionalMapKey<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            MapKey::Bool(value) => serializer.serialize_bool(*value),
+            MapKey::I32(value) => serializer.serialize_i32(*value),
+            MapKey::I64(value) => serializer.serialize_i64(*value),
+            MapKey::U32(value) => serializer.serialize_u32(*value),
+            MapKey::U64(value) => serializer.serialize_u64(*value),
+            MapKey::String(value) => serializer.serialize_str(value),
+        }
+    }
+}
+
+struct MessageSeed<'a>(&'a MessageDescriptor);
+
+impl<'a, 'de> DeserializeSeed<'de> for MessageSeed<'a> {
+    type Value = DynamicMessage;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(self.0.fields().len(), self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for MessageSeed<'a> {
+    type Value = DynamicMessage;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a tuple of fields for message {}", self.0.full_name())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut message = DynamicMessage::new(self.0.clone());
+        for (index, field) in self.0.fields().enumerate() {
+            match seq.next_element_seed(FieldSeed(&field))? {
+                Some(Some(value)) => message.set_field(&field, value),
+                Some(None) => (),
+                None => return Err(Error::invalid_length(index, &self)),
+            }
+        }
+        Ok(message)
+    }
+}
+
+/// Deserializes the value of a field, returning `None` if the field should be left unset.
+struct FieldSeed<'a>(&'a FieldDescriptor);
+
+impl<'a, 'de> DeserializeSeed<'de> for FieldSeed<'a> {
+    type Value = Option<Value>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let kind = self.0.kind();
+        if self.0.is_map() {
+            let entry = kind.as_message().expect("map entry must be a message");
+            let key_kind = entry.map_entry_key_field().kind();
+            let value_kind = entry.map_entry_value_field().kind();
+            let map = deserializer.deserialize_map(MapVisitor(&key_kind, &value_kind))?;
+            Ok(if map.is_empty() {
+                None
+            } else {
+                Some(Value::Map(map))
+            })
+        } else if self.0.is_list() {
+            let list = deserializer.deserialize_seq(ListVisitor(&kind))?;
+            Ok(if list.is_empty() {
+                None
+            } else {
+                Some(Value::List(list))
+            })
+        } else if is_optional(self.0) {
+            deserializer.deserialize_option(OptionVisitor(&kind))
+        } else {
+            let value = KindSeed(&kind).deserialize(deserializer)?;
+            Ok(if value.is_default(&kind) {
+                None
+            } else {
+                Some(value)
+            })
+        }
+    }
+}
+
+struct KindSeed<'a>(&'a Kind);
+
+impl<'a, 'de> DeserializeSeed<'de> for KindSeed<'a> {
+    type Value = Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0 {
+            Kind::Double => f64::deserialize(deserializer).map(Value::F64),
+            Kind::Float => f32::deserialize(deserializer).map(Value::F32),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+                i32::deserialize(deserializer).map(Value::I32)
+            }
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+                i64::deserialize(deserializer).map(Value::I64)
+            }
+            Kind::Uint32 | Kind::Fixed32 => u32::deserialize(deserializer).map(Value::U32),
+            Kind::Uint64 | Kind::Fixed64 => u64::deserialize(deserializer).map(Value::U64),
+            Kind::Bool => bool::deserialize(deserializer).map(Value::Bool),
+            Kind::String => String::deserialize(deserializer).map(Value::String),
+            Kind::Bytes => deserializer
+                .deserialize_byte_buf(BytesVisitor)
+                .map(Value::Bytes),
+            Kind::Enum(_) => i32::deserialize(deserializer).map(Value::EnumNumber),
+            Kind::Message(desc) => MessageSeed(desc)
+                .deserialize(deserializer)
+                .map(Value::Message),
+        }
+    }
+}
+
+struct MapKeySeed<'a>(&'a Kind);
+
+impl<'a, 'de> DeserializeSeed<'de> for MapKeySeed<'a> {
+    type Value = MapKey;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0 {
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+                i32::deserialize(deserializer).map(MapKey::I32)
+            }
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+                i64::deserialize(deserializer).map(MapKey::I64)
+            }
+            Kind::Uint32 | Kind::Fixed32 => u32::deserialize(deserializer).map(MapKey::U32),
+            Kind::Uint64 | Kind::Fixed64 => u64::deserialize(deserializer).map(MapKey::U64),
+            Kind::Bool => bool::deserialize(deserializer).map(MapKey::Bool),
+            Kind::String => String::deserialize(deserializer).map(MapKey::String),
+            _ => Err(Error::custom(format!("invalid map key type: {:?}", self.0))),
+        }
+    }
+}
+
+struct OptionVisitor<'a>(&'a Kind);
+
+impl<'a, 'de> Visitor<'de> for OptionVisitor<'a> {
+    type Value = Option<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option")
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        KindSeed(self.0).deserialize(deserializer).map(Some)
+    }
+}
+
+struct ListVisitor<'a>(&'a Kind);
+
+impl<'a, 'de> Visitor<'de> for ListVisitor<'a> {
+    type Value = Vec<Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element_seed(KindSeed(self.0))? {
+            result.push(value);
+        }
+        Ok(result)
+    }
+}
+
+struct MapVisitor<'a>(&'a Kind, &'a Kind);
+
+impl<'a, 'de> Visitor<'de> for MapVisitor<'a> {
+    type Value = HashMap<MapKey, Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut result = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(key) = map.next_key_seed(MapKeySeed(self.0))? {
+            let value = map.next_value_seed(KindSeed(self.1))?;
+            result.insert(key, value);
+        }
+        Ok(result)
+    }
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Bytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "bytes")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Bytes::copy_from_slice(v))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(v.into())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut result = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            result.push(byte);
+        }
+        Ok(result.into())
+    }
+}
+
+fn is_optional(field: &FieldDescriptor) -> bool {
+    field.supports_presence() && !field.is_list() && !field.is_map()
+}
@@ -0,0 +1,148 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use prost_types::FieldMask;
+
+use crate::dynamic::field_mask::parse_path;
+
+/// The paths of a [`FieldMask`], arranged as a tree of path segments.
+#[derive(Debug, Clone, Default)]
+pub(in crate::dynamic::serde) struct FieldMaskTree {
+    /// If `true`, the whole value is selected and `children` is empty.
+    all: bool,
+    children: BTreeMap<String, Arc<FieldMaskTree>>,
+}
+
+impl FieldMaskTree {
+    /// Builds the tree for `mask`, or returns `None` if it selects every field.
+    pub(in crate::dynamic::serde) fn new(mask: &FieldMask) -> Option<Arc<Self>> {
+        let mut tree = FieldMaskTree::default();
+        for path in &mask.paths {
+            tree.insert(parse_path(path));
+        }
+
+        if mask.paths.is_empty() || tree.all {
+            None
+        } else {
+            Some(Arc::new(tree))
+        }
+    }
+
+    /// Returns `true` if the whole value is selected.
+    pub(super) fn is_all(&self) -> bool {
+        self.all
+    }
+
+    /// Returns the tree for the field or map entry `name`, or `None` if it is not selected.
+    ///
+    /// A `*` segment matches any name.
+    pub(super) fn child(&self, name: &str) -> Option<Arc<Self>> {
+        if self.all {
+            return Some(Arc::new(self.clone()));
+        }
+
+        match (self.children.get(name), self.children.get("*")) {
+            (Some(exact), Some(wildcard)) => {
+                let mut merged = FieldMaskTree::clone(exact);
+                merged.merge(wildcard);
+                Some(Arc::new(merged))
+            }
+            (Some(child), None) | (None, Some(child)) => Some(child.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// Returns the tree for the elements of a repeated field.
+    ///
+    /// Paths may either name fields of the element directly, as in `books.title`, or use a
+    /// wildcard segment for the element, as in `books.*.title`.
+    pub(super) fn elements(self: &Arc<Self>) -> Arc<Self> {
+        match self.children.get("*") {
+            Some(wildcard) => wildcard.clone(),
+            None => self.clone(),
+        }
+    }
+
+    fn insert(&mut self, mut segments: Vec<String>) {
+        // A trailing wildcard selects every field of the parent, which is the same as selecting
+        // the parent itself.
+        while segments.last().map(String::as_str) == Some("*") {
+            segments.pop();
+        }
+
+        let mut node = self;
+        for segment in segments {
+            if node.all {
+                return;
+            }
+            node = Arc::make_mut(node.children.entry(segment).or_default());
+        }
+        node.all = true;
+        node.children.clear();
+    }
+
+    fn merge(&mut self, other: &FieldMaskTree) {
+        if self.all {
+            return;
+        }
+        if other.all {
+            self.all = true;
+            self.children.clear();
+            return;
+        }
+
+        for (name, child) in &other.children {
+            Arc::make_mut(self.children.entry(name.clone()).or_default()).merge(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_quoted_path() {
+        assert_eq!(parse_path("a.b"), vec!["a", "b"]);
+        assert_eq!(parse_path("a.`b.c`.d"), vec!["a", "b.c", "d"]);
+        assert_eq!(parse_path("a.`b``c`"), vec!["a", "b`c"]);
+        assert_eq!(parse_path("a.*.b"), vec!["a", "*", "b"]);
+    }
+
+    #[test]
+    fn overlapping_paths() {
+        let tree = FieldMaskTree::new(&FieldMask {
+            paths: vec!["a.b".to_owned(), "a".to_owned(), "a.c".to_owned()],
+        })
+        .unwrap();
+        assert!(tree.child("a").unwrap().is_all());
+        assert!(tree.child("b").is_none());
+
+        let tree = FieldMaskTree::new(&FieldMask {
+            paths: vec!["a.*".to_owned()],
+        })
+        .unwrap();
+        assert!(tree.child("a").unwrap().is_all());
+
+        assert!(FieldMaskTree::new(&FieldMask { paths: vec![] }).is_none());
+        assert!(FieldMaskTree::new(&FieldMask {
+            paths: vec!["a".to_owned(), "*".to_owned()]
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn wildcard_merge() {
+        let tree = FieldMaskTree::new(&FieldMask {
+            paths: vec!["*.a".to_owned(), "x.b".to_owned()],
+        })
+        .unwrap();
+        let x = tree.child("x").unwrap();
+        assert!(x.child("a").unwrap().is_all());
+        assert!(x.child("b").unwrap().is_all());
+        assert!(x.child("c").is_none());
+
+        let y = tree.child("y").unwrap();
+        assert!(y.child("a").unwrap().is_all());
+        assert!(y.child("b").is_none());
+    }
+}
@@ -1,15 +1,34 @@
+mod mask;
 mod wkt;
 
-use base64::{display::Base64Display, prelude::BASE64_STANDARD};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    fmt::{self, Write},
+    str,
+    sync::Arc,
+};
+
+use base64::{
+    display::Base64Display,
+    engine::GeneralPurpose,
+    prelude::{BASE64_STANDARD, BASE64_STANDARD_NO_PAD, BASE64_URL_SAFE, BASE64_URL_SAFE_NO_PAD},
+};
 
 use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
 
 use crate::{
     descriptor::Kind,
-    dynamic::{fields::ValueAndDescriptor, serde::SerializeOptions, DynamicMessage, MapKey, Value},
-    ReflectMessage,
+    dynamic::{
+        fields::ValueAndDescriptor,
+        serde::{Base64Encoding, SerializeOptions},
+        DynamicMessage, MapKey, Value,
+    },
+    FieldDescriptor, ReflectMessage,
 };
 
+pub(super) use self::mask::FieldMaskTree;
+
 struct SerializeWrapper<'a, T> {
     value: &'a T,
     options: &'a SerializeOptions,
@@ -37,10 +56,15 @@ impl<'a> Serialize for SerializeWrapper<'a, DynamicMessage> {
     {
         let message_desc = self.value.descriptor();
         if let Some(serialize) = wkt::get_well_known_type_serializer(message_desc.full_name()) {
-            serialize(self.value, serializer, self.options)
+            if self.options.field_mask.is_some() {
+                // Well-known types have special representations, so they cannot be masked.
+                serialize(self.value, serializer, &self.options.with_field_mask(None))
+            } else {
+                serialize(self.value, serializer, self.options)
+            }
         } else {
             let mut map = serializer.serialize_map(None)?;
-            serialize_dynamic_message_fields(&mut map, self.value, self.options)?;
+            serialize_dynamic_message_fields(&mut map, None, self.value, self.options)?;
             map.end()
         }
     }
@@ -48,71 +72,299 @@ impl<'a> Serialize for SerializeWrapper<'a, DynamicMessage> {
 
 fn serialize_dynamic_message_fields<S>(
     map: &mut S,
+    type_url: Option<&str>,
     value: &DynamicMessage,
     options: &SerializeOptions,
 ) -> Result<(), S::Error>
 where
     S: SerializeMap,
 {
+    let mut fields = FieldSerializer {
+        map,
+        options,
+        entries: Vec::new(),
+    };
+
+    if let Some(type_url) = type_url {
+        fields.serialize_entry(
+            FieldKey::Name("@type".into()),
+            FieldEntry::TypeUrl(type_url),
+        )?;
+    }
+
     if options.skip_default_fields {
         for field in value.fields.iter(&value.desc) {
-            let (name, value, ref kind) = match field {
-                ValueAndDescriptor::Field(value, ref field_desc) => {
-                    let name = if options.use_proto_field_name {
-                        field_desc.name()
-                    } else {
-                        field_desc.json_name()
-                    };
-                    (name, value, field_desc.kind())
+            fields.serialize_field(field)?;
+        }
+    } else {
+        for field in value.fields.iter_include_default(&value.desc) {
+            fields.serialize_field(field)?;
+        }
+    }
+
+    if options.emit_nulls_for_absent_optionals {
+        for field_desc in value.desc.fields() {
+            if field_desc.supports_presence()
+                && !value.has_field(&field_desc)
+                && options.is_selected(field_desc.name())
+            {
+                fields.serialize_entry(options.field_key(&field_desc), FieldEntry::Null)?;
+            }
+        }
+    }
+
+    fields.end()
+}
+
+/// Serializes the entries of a message, buffering and sorting them in canonical mode.
+struct FieldSerializer<'a, 'b, S> {
+    map: &'b mut S,
+    options: &'b SerializeOptions,
+    entries: Vec<(FieldKey<'static>, FieldEntry<'a>)>,
+}
+
+/// The key of a message entry.
+enum FieldKey<'a> {
+    Name(Cow<'a, str>),
+    Number(u32),
+}
+
+enum FieldEntry<'a> {
+    /// A field value, with the options to serialize it with if they differ from those of the
+    /// message.
+    Value(Cow<'a, Value>, Kind, Option<SerializeOptions>),
+    Null,
+    TypeUrl(&'a str),
+}
+
+impl<'a, 'b, S> FieldSerializer<'a, 'b, S>
+where
+    S: SerializeMap,
+{
+    fn serialize_field(&mut self, field: ValueAndDescriptor<'a>) -> Result<(), S::Error> {
+        match field {
+            ValueAndDescriptor::Field(value, field_desc) => {
+                match self.options.field_options(field_desc.name()) {
+                    Some(options) => self.serialize_entry(
+                        self.options.field_key(&field_desc),
+                        FieldEntry::Value(value, field_desc.kind(), options),
+                    ),
+                    None => Ok(()),
                 }
-                ValueAndDescriptor::Extension(value, ref extension_desc) => {
-                    (extension_desc.json_name(), value, extension_desc.kind())
+            }
+            ValueAndDescriptor::Extension(value, extension_desc) => {
+                match self.options.field_options(extension_desc.full_name()) {
+                    Some(options) => {
+                        let key = if self.options.use_field_numbers {
+                            FieldKey::Number(extension_desc.number())
+                        } else {
+                            FieldKey::Name(extension_desc.json_name().into())
+                        };
+                        self.serialize_entry(
+                            key,
+                            FieldEntry::Value(value, extension_desc.kind(), options),
+                        )
+                    }
+                    None => Ok(()),
                 }
-                ValueAndDescriptor::Unknown(_, _) => continue,
-            };
+            }
+            ValueAndDescriptor::Unknown(_, _) => Ok(()),
+        }
+    }
 
-            map.serialize_entry(
-                name,
+    fn serialize_entry(
+        &mut self,
+        key: FieldKey<'_>,
+        entry: FieldEntry<'a>,
+    ) -> Result<(), S::Error> {
+        if self.options.canonical {
+            self.entries.push((key.into_owned(), entry));
+            Ok(())
+        } else {
+            self.map.serialize_entry(
+                &key,
                 &SerializeWrapper {
-                    value: &ValueAndKind {
-                        value: value.as_ref(),
-                        kind,
-                    },
-                    options,
+                    value: &entry,
+                    options: self.options,
                 },
-            )?;
+            )
         }
-    } else {
-        for field in value.fields.iter_include_default(&value.desc) {
-            let (name, value, ref kind) = match field {
-                ValueAndDescriptor::Field(value, ref field_desc) => {
-                    let name = if options.use_proto_field_name {
-                        field_desc.name()
-                    } else {
-                        field_desc.json_name()
-                    };
-                    (name, value, field_desc.kind())
-                }
-                ValueAndDescriptor::Extension(value, ref extension_desc) => {
-                    (extension_desc.json_name(), value, extension_desc.kind())
-                }
-                ValueAndDescriptor::Unknown(_, _) => continue,
-            };
+    }
 
-            map.serialize_entry(
-                name,
+    fn end(mut self) -> Result<(), S::Error> {
+        self.entries.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+        for (key, entry) in &self.entries {
+            self.map.serialize_entry(
+                key,
                 &SerializeWrapper {
-                    value: &ValueAndKind {
-                        value: value.as_ref(),
-                        kind,
-                    },
-                    options,
+                    value: entry,
+                    options: self.options,
                 },
             )?;
         }
+        Ok(())
+    }
+}
+
+impl<'a, 'b> Serialize for SerializeWrapper<'b, FieldEntry<'a>> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.value {
+            FieldEntry::Value(value, kind, options) => SerializeWrapper {
+                value: &ValueAndKind { value, kind },
+                options: options.as_ref().unwrap_or(self.options),
+            }
+            .serialize(serializer),
+            FieldEntry::Null => serializer.serialize_unit(),
+            FieldEntry::TypeUrl(type_url) => serializer.serialize_str(type_url),
+        }
+    }
+}
+
+impl<'a> FieldKey<'a> {
+    fn into_owned(self) -> FieldKey<'static> {
+        match self {
+            FieldKey::Name(name) => FieldKey::Name(Cow::Owned(name.into_owned())),
+            FieldKey::Number(number) => FieldKey::Number(number),
+        }
+    }
+
+    /// Compares keys in canonical order. Names are sorted before numbers, so the `@type` entry of
+    /// an `Any` message comes first.
+    fn cmp(&self, other: &FieldKey) -> Ordering {
+        match (self, other) {
+            (FieldKey::Name(lhs), FieldKey::Name(rhs)) => cmp_canonical_keys(lhs, rhs),
+            (FieldKey::Name(_), FieldKey::Number(_)) => Ordering::Less,
+            (FieldKey::Number(_), FieldKey::Name(_)) => Ordering::Greater,
+            (FieldKey::Number(lhs), FieldKey::Number(rhs)) => lhs.cmp(rhs),
+        }
+    }
+}
+
+impl<'a> Serialize for FieldKey<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            FieldKey::Name(name) => serializer.serialize_str(name),
+            FieldKey::Number(number) => serializer.serialize_u32(*number),
+        }
+    }
+}
+
+/// Compares object keys by their UTF-16 code units, as required by RFC 8785.
+fn cmp_canonical_keys(lhs: &str, rhs: &str) -> Ordering {
+    lhs.encode_utf16().cmp(rhs.encode_utf16())
+}
+
+/// Returns the JSON representation of a map key.
+fn map_key_string(key: &MapKey) -> Cow<'_, str> {
+    match key {
+        MapKey::Bool(value) => Cow::Owned(value.to_string()),
+        MapKey::I32(value) => Cow::Owned(value.to_string()),
+        MapKey::I64(value) => Cow::Owned(value.to_string()),
+        MapKey::U32(value) => Cow::Owned(value.to_string()),
+        MapKey::U64(value) => Cow::Owned(value.to_string()),
+        MapKey::String(value) => Cow::Borrowed(value),
+    }
+}
+
+/// Returns the UTF-16 code units of the JSON representation of a map key, for sorting.
+fn map_key_utf16(key: &MapKey) -> Vec<u16> {
+    map_key_string(key).encode_utf16().collect()
+}
+
+impl SerializeOptions {
+    fn with_field_mask(&self, field_mask: Option<Arc<FieldMaskTree>>) -> Self {
+        SerializeOptions {
+            field_mask: field_mask.filter(|mask| !mask.is_all()),
+            ..self.clone()
+        }
+    }
+
+    fn field_key<'a>(&self, field: &'a FieldDescriptor) -> FieldKey<'a> {
+        if self.use_field_numbers {
+            FieldKey::Number(field.number())
+        } else {
+            FieldKey::Name(self.field_name(field).into())
+        }
+    }
+
+    /// Returns `true` if the field or map entry `name` is selected by the field mask.
+    fn is_selected(&self, name: &str) -> bool {
+        match &self.field_mask {
+            Some(mask) => mask.child(name).is_some(),
+            None => true,
+        }
+    }
+
+    /// Returns the options for serializing the field or map entry `name`, or `None` if it is not
+    /// selected by the field mask. The inner value is `None` if the current options may be used.
+    fn field_options(&self, name: &str) -> Option<Option<SerializeOptions>> {
+        match &self.field_mask {
+            Some(mask) => mask
+                .child(name)
+                .map(|child| Some(self.with_field_mask(Some(child)))),
+            None => Some(None),
+        }
     }
+}
+
+/// Converts a float to the double with the same shortest decimal representation, as a reader
+/// parsing the serialized value would.
+pub(super) fn widen_f32(value: f32) -> f64 {
+    with_formatted(&value, |s| s.parse().unwrap_or_else(|_| value.into()))
+}
 
-    Ok(())
+/// Formats `value` into a stack buffer and passes the result to `f`, only allocating if the
+/// formatted value does not fit.
+fn with_formatted<R>(value: &dyn fmt::Display, f: impl FnOnce(&str) -> R) -> R {
+    let mut buf = InlineString {
+        buf: [0; 64],
+        len: 0,
+    };
+    if write!(buf, "{}", value).is_ok() {
+        f(buf.as_str())
+    } else {
+        f(&value.to_string())
+    }
+}
+
+struct InlineString {
+    buf: [u8; 64],
+    len: usize,
+}
+
+impl InlineString {
+    fn as_str(&self) -> &str {
+        str::from_utf8(&self.buf[..self.len]).expect("buffer contains whole UTF-8 strings")
+    }
+}
+
+impl Write for InlineString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl Base64Encoding {
+    fn engine(self) -> &'static GeneralPurpose {
+        match self {
+            Base64Encoding::Standard => &BASE64_STANDARD,
+            Base64Encoding::StandardNoPad => &BASE64_STANDARD_NO_PAD,
+            Base64Encoding::UrlSafe => &BASE64_URL_SAFE,
+            Base64Encoding::UrlSafeNoPad => &BASE64_URL_SAFE_NO_PAD,
+        }
+    }
 }
 
 struct ValueAndKind<'a> {
@@ -144,7 +396,9 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                 }
             }
             Value::F32(value) => {
-                if value.is_finite() {
+                if value.is_finite() && self.options.canonical {
+                    serializer.serialize_f64(widen_f32(*value))
+                } else if value.is_finite() || self.options.native_values {
                     serializer.serialize_f32(*value)
                 } else if *value == f32::INFINITY {
                     serializer.serialize_str("Infinity")
@@ -156,7 +410,7 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                 }
             }
             Value::F64(value) => {
-                if value.is_finite() {
+                if value.is_finite() || self.options.native_values {
                     serializer.serialize_f64(*value)
                 } else if *value == f64::INFINITY {
                     serializer.serialize_str("Infinity")
@@ -168,9 +422,11 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                 }
             }
             Value::String(value) => serializer.serialize_str(value),
-            Value::Bytes(value) => {
-                serializer.collect_str(&Base64Display::new(value, &BASE64_STANDARD))
-            }
+            Value::Bytes(value) if self.options.native_values => serializer.serialize_bytes(value),
+            Value::Bytes(value) => serializer.collect_str(&Base64Display::new(
+                value,
+                self.options.bytes_encoding.engine(),
+            )),
             Value::EnumNumber(number) => {
                 let enum_ty = match self.value.kind {
                     Kind::Enum(enum_ty) => enum_ty,
@@ -192,6 +448,12 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
             }
             Value::Message(message) => message.serialize_with_options(serializer, self.options),
             Value::List(values) => {
+                let element_options = self
+                    .options
+                    .field_mask
+                    .as_ref()
+                    .map(|mask| self.options.with_field_mask(Some(mask.elements())));
+
                 let mut list = serializer.serialize_seq(Some(values.len()))?;
                 for value in values {
                     list.serialize_element(&SerializeWrapper {
@@ -199,7 +461,7 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                             value,
                             kind: self.value.kind,
                         },
-                        options: self.options,
+                        options: element_options.as_ref().unwrap_or(self.options),
                     })?;
                 }
                 list.end()
@@ -215,11 +477,43 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                     ),
                 };
 
+                if !self.options.canonical && self.options.field_mask.is_none() {
+                    // Fast path: serialize entries in iteration order without buffering them.
+                    let mut map = serializer.serialize_map(Some(values.len()))?;
+                    for (key, value) in values {
+                        map.serialize_entry(
+                            &SerializeWrapper {
+                                value: key,
+                                options: self.options,
+                            },
+                            &SerializeWrapper {
+                                value: &ValueAndKind {
+                                    value,
+                                    kind: &value_kind,
+                                },
+                                options: self.options,
+                            },
+                        )?;
+                    }
+                    return map.end();
+                }
+
+                let mut values: Vec<_> = values
+                    .iter()
+                    .filter_map(|(key, value)| {
+                        let options = self.options.field_options(&map_key_string(key))?;
+                        Some((key, value, options))
+                    })
+                    .collect();
+                if self.options.canonical {
+                    values.sort_by_cached_key(|(key, _, _)| map_key_utf16(key));
+                }
+
                 let mut map = serializer.serialize_map(Some(values.len()))?;
-                for (key, value) in values {
+                for (key, value, options) in &values {
                     map.serialize_entry(
                         &SerializeWrapper {
-                            value: key,
+                            value: *key,
                             options: self.options,
                         },
                         &SerializeWrapper {
@@ -227,7 +521,7 @@ impl<'a> Serialize for SerializeWrapper<'a, ValueAndKind<'a>> {
                                 value,
                                 kind: &value_kind,
                             },
-                            options: self.options,
+                            options: options.as_ref().unwrap_or(self.options),
                         },
                     )?;
                 }
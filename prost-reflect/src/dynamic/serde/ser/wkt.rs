@@ -1,19 +1,25 @@
-use base64::{display::Base64Display, prelude::BASE64_STANDARD};
+use base64::display::Base64Display;
 use prost::{DecodeError, Message};
 use serde::ser::{Error, Serialize, SerializeMap, SerializeSeq, Serializer};
 
 use crate::{
     dynamic::{
+        case::snake_case_to_camel_case,
+        resolve_any_type,
         serde::{
-            case::snake_case_to_camel_case, check_duration, check_timestamp, is_well_known_type,
-            SerializeOptions,
+            check_duration, check_timestamp, is_well_known_type, OutOfRangePolicy,
+            SerializeOptions, TimestampPrecision, MAX_DURATION_NANOS, MAX_DURATION_SECONDS,
+            MAX_TIMESTAMP_SECONDS, MIN_TIMESTAMP_SECONDS, NATIVE_TIMESTAMP,
         },
         DynamicMessage,
     },
     ReflectMessage,
 };
 
-use super::{serialize_dynamic_message_fields, SerializeWrapper};
+use super::{
+    cmp_canonical_keys, serialize_dynamic_message_fields, widen_f32, with_formatted,
+    SerializeWrapper,
+};
 
 #[allow(type_alias_bounds)]
 type WellKnownTypeSerializer<S: Serializer> =
@@ -58,84 +64,169 @@ where
 {
     let raw: prost_types::Any = msg.transcode_to().map_err(decode_to_ser_err)?;
 
-    if let Some(message_name) = raw.type_url.strip_prefix("type.googleapis.com/") {
-        let message_desc = msg
-            .descriptor()
-            .parent_pool()
-            .get_message_by_name(message_name)
-            .ok_or_else(|| Error::custom(format!("message '{}' not found", message_name)))?;
-
-        let mut payload_message = DynamicMessage::new(message_desc);
-        payload_message
-            .merge(raw.value.as_ref())
-            .map_err(decode_to_ser_err)?;
-
-        if is_well_known_type(message_name) {
-            let mut map = serializer.serialize_map(Some(2))?;
-            map.serialize_entry("@type", &raw.type_url)?;
-            map.serialize_entry(
-                "value",
-                &SerializeWrapper {
-                    value: &payload_message,
-                    options,
-                },
-            )?;
-            map.end()
-        } else {
-            let mut map = serializer.serialize_map(None)?;
-            map.serialize_entry("@type", &raw.type_url)?;
-            serialize_dynamic_message_fields(&mut map, &payload_message, options)?;
-            map.end()
-        }
+    let message_desc = resolve_any_type(
+        options.type_resolver.as_ref(),
+        msg.descriptor().parent_pool(),
+        &raw.type_url,
+    )
+    .map_err(Error::custom)?;
+
+    let mut payload_message = DynamicMessage::new(message_desc);
+    payload_message
+        .merge(raw.value.as_ref())
+        .map_err(decode_to_ser_err)?;
+
+    if is_well_known_type(payload_message.descriptor().full_name()) {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("@type", &raw.type_url)?;
+        map.serialize_entry(
+            "value",
+            &SerializeWrapper {
+                value: &payload_message,
+                options,
+            },
+        )?;
+        map.end()
     } else {
-        Err(Error::custom(format!(
-            "unsupported type url '{}'",
-            raw.type_url
-        )))
+        let mut map = serializer.serialize_map(None)?;
+        serialize_dynamic_message_fields(&mut map, Some(&raw.type_url), &payload_message, options)?;
+        map.end()
     }
 }
 
 fn serialize_timestamp<S>(
     msg: &DynamicMessage,
     serializer: S,
-    _options: &SerializeOptions,
+    options: &SerializeOptions,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let timestamp: prost_types::Timestamp = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let mut timestamp: prost_types::Timestamp = transcode_small(msg).map_err(decode_to_ser_err)?;
+
+    if let Err(err) = check_timestamp(&timestamp) {
+        match options.out_of_range_policy {
+            OutOfRangePolicy::Error => return Err(Error::custom(err)),
+            OutOfRangePolicy::Clamp if timestamp.seconds < MIN_TIMESTAMP_SECONDS => {
+                timestamp = prost_types::Timestamp {
+                    seconds: MIN_TIMESTAMP_SECONDS,
+                    nanos: 0,
+                };
+            }
+            OutOfRangePolicy::Clamp => {
+                timestamp = prost_types::Timestamp {
+                    seconds: MAX_TIMESTAMP_SECONDS,
+                    nanos: 999_999_999,
+                };
+            }
+            OutOfRangePolicy::Raw => return serialize_raw(msg, serializer, options),
+        }
+    }
 
-    check_timestamp(&timestamp).map_err(Error::custom)?;
+    timestamp.normalize();
+    if options.native_values {
+        let millis = timestamp.seconds * 1000 + i64::from(timestamp.nanos / 1_000_000);
+        return serializer.serialize_newtype_struct(NATIVE_TIMESTAMP, &millis);
+    }
 
-    serializer.collect_str(&timestamp)
+    let digits = match options.timestamp_precision {
+        TimestampPrecision::Standard if timestamp.nanos == 0 => 0,
+        TimestampPrecision::Standard if timestamp.nanos % 1_000_000 == 0 => 3,
+        TimestampPrecision::Standard if timestamp.nanos % 1_000 == 0 => 6,
+        TimestampPrecision::Standard => 9,
+        TimestampPrecision::Minimal if timestamp.nanos == 0 => 0,
+        TimestampPrecision::Minimal => {
+            let mut digits = 9;
+            let mut nanos = timestamp.nanos;
+            while nanos % 10 == 0 {
+                nanos /= 10;
+                digits -= 1;
+            }
+            digits
+        }
+        TimestampPrecision::Seconds => 0,
+        TimestampPrecision::Millis => 3,
+        TimestampPrecision::Micros => 6,
+        TimestampPrecision::Nanos => 9,
+    };
+
+    let fraction = timestamp.nanos / 10i32.pow(9 - digits);
+    timestamp.nanos = 0;
+    with_formatted(&timestamp, |whole| {
+        let whole = whole.strip_suffix('Z').unwrap_or(whole);
+        if digits == 0 {
+            serializer.collect_str(&format_args!("{}Z", whole))
+        } else {
+            serializer.collect_str(&format_args!(
+                "{}.{:0width$}Z",
+                whole,
+                fraction,
+                width = digits as usize
+            ))
+        }
+    })
 }
 
 fn serialize_duration<S>(
     msg: &DynamicMessage,
     serializer: S,
-    _options: &SerializeOptions,
+    options: &SerializeOptions,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let duration: prost_types::Duration = msg.transcode_to().map_err(decode_to_ser_err)?;
-
-    check_duration(&duration).map_err(Error::custom)?;
+    let mut duration: prost_types::Duration = transcode_small(msg).map_err(decode_to_ser_err)?;
+
+    if let Err(err) = check_duration(&duration) {
+        match options.out_of_range_policy {
+            OutOfRangePolicy::Error => return Err(Error::custom(err)),
+            OutOfRangePolicy::Clamp => {
+                duration.normalize();
+                let max_seconds = MAX_DURATION_SECONDS as i64;
+                let max_nanos = MAX_DURATION_NANOS as i32;
+                if duration.seconds > max_seconds {
+                    duration.seconds = max_seconds;
+                    duration.nanos = max_nanos;
+                } else if duration.seconds < -max_seconds {
+                    duration.seconds = -max_seconds;
+                    duration.nanos = -max_nanos;
+                }
+            }
+            OutOfRangePolicy::Raw => return serialize_raw(msg, serializer, options),
+        }
+    }
 
     serializer.collect_str(&duration)
 }
 
+fn serialize_raw<S>(
+    msg: &DynamicMessage,
+    serializer: S,
+    options: &SerializeOptions,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map = serializer.serialize_map(None)?;
+    serialize_dynamic_message_fields(&mut map, None, msg, options)?;
+    map.end()
+}
+
 fn serialize_float<S>(
     msg: &DynamicMessage,
     serializer: S,
-    _options: &SerializeOptions,
+    options: &SerializeOptions,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let raw: f32 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: f32 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
-    serializer.serialize_f32(raw)
+    if options.canonical {
+        serializer.serialize_f64(widen_f32(raw))
+    } else {
+        serializer.serialize_f32(raw)
+    }
 }
 
 fn serialize_double<S>(
@@ -146,7 +237,7 @@ fn serialize_double<S>(
 where
     S: Serializer,
 {
-    let raw: f64 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: f64 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     serializer.serialize_f64(raw)
 }
@@ -159,7 +250,7 @@ fn serialize_int32<S>(
 where
     S: Serializer,
 {
-    let raw: i32 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: i32 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     serializer.serialize_i32(raw)
 }
@@ -172,7 +263,7 @@ fn serialize_int64<S>(
 where
     S: Serializer,
 {
-    let raw: i64 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: i64 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     if options.stringify_64_bit_integers {
         serializer.collect_str(&raw)
@@ -189,7 +280,7 @@ fn serialize_uint32<S>(
 where
     S: Serializer,
 {
-    let raw: u32 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: u32 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     serializer.serialize_u32(raw)
 }
@@ -202,7 +293,7 @@ fn serialize_uint64<S>(
 where
     S: Serializer,
 {
-    let raw: u64 = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: u64 = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     if options.stringify_64_bit_integers {
         serializer.collect_str(&raw)
@@ -219,7 +310,7 @@ fn serialize_bool<S>(
 where
     S: Serializer,
 {
-    let raw: bool = msg.transcode_to().map_err(decode_to_ser_err)?;
+    let raw: bool = transcode_small(msg).map_err(decode_to_ser_err)?;
 
     serializer.serialize_bool(raw)
 }
@@ -240,14 +331,17 @@ where
 fn serialize_bytes<S>(
     msg: &DynamicMessage,
     serializer: S,
-    _options: &SerializeOptions,
+    options: &SerializeOptions,
 ) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
     let raw: Vec<u8> = msg.transcode_to().map_err(decode_to_ser_err)?;
 
-    serializer.collect_str(&Base64Display::new(&raw, &BASE64_STANDARD))
+    if options.native_values {
+        return serializer.serialize_bytes(&raw);
+    }
+    serializer.collect_str(&Base64Display::new(&raw, options.bytes_encoding.engine()))
 }
 
 fn serialize_field_mask<S>(
@@ -378,7 +472,11 @@ where
     S: Serializer,
 {
     let mut map = serializer.serialize_map(Some(raw.fields.len()))?;
-    for (key, value) in &raw.fields {
+    let mut fields: Vec<_> = raw.fields.iter().collect();
+    if options.canonical {
+        fields.sort_by(|(lhs, _), (rhs, _)| cmp_canonical_keys(lhs, rhs));
+    }
+    for (key, value) in fields {
         map.serialize_entry(key, &SerializeWrapper { value, options })?;
     }
     map.end()
@@ -399,6 +497,23 @@ where
     list.end()
 }
 
+/// Converts a small, fixed-size well-known type such as `Timestamp` or `Int64Value`, encoding it
+/// into a stack buffer rather than allocating.
+fn transcode_small<T>(msg: &DynamicMessage) -> Result<T, DecodeError>
+where
+    T: Message + Default,
+{
+    let mut buf = [0u8; 64];
+    let len = msg.encoded_len();
+    if len > buf.len() {
+        return msg.transcode_to();
+    }
+
+    msg.encode(&mut &mut buf[..len])
+        .expect("buffer has sufficient capacity");
+    T::decode(&buf[..len])
+}
+
 fn decode_to_ser_err<E>(err: DecodeError) -> E
 where
     E: Error,
@@ -0,0 +1,46 @@
+use crate::{DeserializeOptions, DynamicMessage, MessageDescriptor};
+
+impl DynamicMessage {
+    /// Deserializes a message of type `desc` from a JSON document using
+    /// [simd-json](https://docs.rs/simd-json), with the default [`DeserializeOptions`].
+    ///
+    /// The document is parsed in place, so the contents of `json` are unspecified afterwards.
+    /// The input must contain exactly one JSON value, optionally surrounded by whitespace.
+    ///
+    /// Deserialization is not tied to a particular parser: any other [`serde::Deserializer`]
+    /// may be passed to [`deserialize_with_options`](DynamicMessage::deserialize_with_options).
+    /// When this feature is enabled, JSON parsed by the crate itself, such as JSON lines streams
+    /// and JSON request bodies in the axum extractor, also uses simd-json.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut json = br#"{ "foo": 150, "nested": { "bar": 66 } }"#.to_vec();
+    /// let dynamic_message = DynamicMessage::from_simd_json_slice(message_descriptor, &mut json).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "simd-json")))]
+    pub fn from_simd_json_slice(
+        desc: MessageDescriptor,
+        json: &mut [u8],
+    ) -> Result<Self, simd_json1::Error> {
+        Self::from_simd_json_slice_with_options(desc, json, &DeserializeOptions::new())
+    }
+
+    /// Deserializes a message of type `desc` from a JSON document using
+    /// [simd-json](https://docs.rs/simd-json), with the given [`DeserializeOptions`].
+    ///
+    /// See [`from_simd_json_slice`](DynamicMessage::from_simd_json_slice) for details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "simd-json")))]
+    pub fn from_simd_json_slice_with_options(
+        desc: MessageDescriptor,
+        json: &mut [u8],
+        options: &DeserializeOptions,
+    ) -> Result<Self, simd_json1::Error> {
+        let mut deserializer = simd_json1::Deserializer::from_slice(json)?;
+        Self::deserialize_with_options(desc, &mut deserializer, options)
+    }
+}
@@ -0,0 +1,318 @@
+use std::{cmp::Ordering, error::Error, fmt};
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value};
+
+/// An error that occurred while sorting or deduplicating a repeated message field.
+#[derive(Debug)]
+pub struct SortError {
+    kind: SortErrorKind,
+}
+
+#[derive(Debug)]
+enum SortErrorKind {
+    NotRepeatedMessage {
+        field: String,
+    },
+    UnknownField {
+        path: String,
+        message: String,
+        name: String,
+    },
+    RepeatedField {
+        path: String,
+        field: String,
+    },
+    NotScalar {
+        path: String,
+    },
+    InvalidElement {
+        field: String,
+        message: String,
+    },
+}
+
+impl DynamicMessage {
+    /// Sorts the elements of a repeated message field by the values at one or more field paths.
+    ///
+    /// Each path is a sequence of field names separated by `.`, relative to the element type of
+    /// the list, for example `nested.foo`. Elements are compared by the value at the first path,
+    /// falling back to the next path if they are equal. Values are compared according to the type
+    /// of the field, as by [`Value::compare`], and unset fields are treated as having their
+    /// default value. Floating point `NaN` values are ordered after all other values.
+    ///
+    /// The sort is stable, so elements with equal keys keep their original relative order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `field_desc` is not a repeated message field, if any path does not
+    /// refer to a singular scalar or enum field reached through singular message fields, or if
+    /// an element of the list is not a message of the field's type. The field is left unchanged
+    /// in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyList").unwrap();
+    /// // messages: [{ foo: 2 }, { foo: 1, bar: 5 }, { foo: 1, bar: 3 }]
+    /// let mut list = DynamicMessage::decode(
+    ///     message_descriptor.clone(),
+    ///     b"\x12\x02\x08\x02\x12\x04\x08\x01\x10\x05\x12\x04\x08\x01\x10\x03".as_ref(),
+    /// ).unwrap();
+    /// let field = message_descriptor.get_field_by_name("messages").unwrap();
+    ///
+    /// list.sort_field_by_paths(&field, &["foo", "bar"]).unwrap();
+    /// let bars: Vec<_> = list.get_field(&field).as_list().unwrap().iter()
+    ///     .map(|message| message.as_message().unwrap().get_field_by_name("bar").unwrap().into_owned())
+    ///     .collect();
+    /// assert_eq!(bars, vec![Value::I32(3), Value::I32(5), Value::I32(0)]);
+    /// ```
+    pub fn sort_field_by_paths(
+        &mut self,
+        field_desc: &FieldDescriptor,
+        paths: &[&str],
+    ) -> Result<(), SortError> {
+        let (element_desc, key_paths) = resolve_key_paths(field_desc, paths)?;
+        let list = match self.get_field_mut(field_desc).as_list_mut() {
+            Some(list) => list,
+            None => return Err(SortError::invalid_element(field_desc, &element_desc)),
+        };
+
+        let keys = extract_keys(list, field_desc, &element_desc, &key_paths)?;
+        let mut keyed: Vec<(Vec<Value>, Value)> = keys.into_iter().zip(list.drain(..)).collect();
+        keyed.sort_by(|(lhs, _), (rhs, _)| compare_keys(lhs, rhs, &key_paths));
+        list.extend(keyed.into_iter().map(|(_, element)| element));
+        Ok(())
+    }
+
+    /// Removes elements of a repeated message field which have the same values at the given field
+    /// paths as an earlier element.
+    ///
+    /// Paths are interpreted in the same way as in
+    /// [`sort_field_by_paths`][DynamicMessage::sort_field_by_paths]. Unlike [`Vec::dedup`],
+    /// duplicates do not need to be adjacent, and the first occurrence of each key is retained
+    /// in its original position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as
+    /// [`sort_field_by_paths`][DynamicMessage::sort_field_by_paths], leaving the field unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyList").unwrap();
+    /// // messages: [{ foo: 2 }, { foo: 1, bar: 5 }, { foo: 1, bar: 3 }]
+    /// let mut list = DynamicMessage::decode(
+    ///     message_descriptor.clone(),
+    ///     b"\x12\x02\x08\x02\x12\x04\x08\x01\x10\x05\x12\x04\x08\x01\x10\x03".as_ref(),
+    /// ).unwrap();
+    /// let field = message_descriptor.get_field_by_name("messages").unwrap();
+    ///
+    /// list.dedup_field_by_paths(&field, &["foo"]).unwrap();
+    /// assert_eq!(list.encode_to_vec(), b"\x12\x02\x08\x02\x12\x04\x08\x01\x10\x05");
+    /// ```
+    pub fn dedup_field_by_paths(
+        &mut self,
+        field_desc: &FieldDescriptor,
+        paths: &[&str],
+    ) -> Result<(), SortError> {
+        let (element_desc, key_paths) = resolve_key_paths(field_desc, paths)?;
+        let list = match self.get_field_mut(field_desc).as_list_mut() {
+            Some(list) => list,
+            None => return Err(SortError::invalid_element(field_desc, &element_desc)),
+        };
+
+        let keys = extract_keys(list, field_desc, &element_desc, &key_paths)?;
+
+        // Sort the indices by key, so duplicates are adjacent. The sort is stable, so the first
+        // index in each run of equal keys is the earliest occurrence.
+        let mut indices: Vec<usize> = (0..keys.len()).collect();
+        indices.sort_by(|&lhs, &rhs| compare_keys(&keys[lhs], &keys[rhs], &key_paths));
+
+        let mut retain = vec![true; keys.len()];
+        for run in indices.windows(2) {
+            if compare_keys(&keys[run[0]], &keys[run[1]], &key_paths) == Ordering::Equal {
+                retain[run[1]] = false;
+            }
+        }
+
+        let mut retain = retain.into_iter();
+        list.retain(|_| retain.next().unwrap());
+        Ok(())
+    }
+}
+
+type KeyPaths = Vec<Vec<FieldDescriptor>>;
+
+fn resolve_key_paths(
+    field_desc: &FieldDescriptor,
+    paths: &[&str],
+) -> Result<(MessageDescriptor, KeyPaths), SortError> {
+    let element_desc = match field_desc.kind() {
+        Kind::Message(element_desc) if field_desc.is_list() => element_desc,
+        _ => {
+            return Err(SortError {
+                kind: SortErrorKind::NotRepeatedMessage {
+                    field: field_desc.full_name().to_owned(),
+                },
+            })
+        }
+    };
+
+    let key_paths = paths
+        .iter()
+        .map(|path| resolve_key_path(&element_desc, path))
+        .collect::<Result<_, _>>()?;
+    Ok((element_desc, key_paths))
+}
+
+fn resolve_key_path(
+    desc: &MessageDescriptor,
+    path: &str,
+) -> Result<Vec<FieldDescriptor>, SortError> {
+    let mut desc = desc.clone();
+    let mut fields = Vec::new();
+    let mut names = path.split('.').peekable();
+    while let Some(name) = names.next() {
+        let field_desc = match desc.get_field_by_name(name) {
+            Some(field_desc) => field_desc,
+            None => {
+                return Err(SortError {
+                    kind: SortErrorKind::UnknownField {
+                        path: path.to_owned(),
+                        message: desc.full_name().to_owned(),
+                        name: name.to_owned(),
+                    },
+                })
+            }
+        };
+        if field_desc.is_list() || field_desc.is_map() {
+            return Err(SortError {
+                kind: SortErrorKind::RepeatedField {
+                    path: path.to_owned(),
+                    field: field_desc.full_name().to_owned(),
+                },
+            });
+        }
+
+        match (field_desc.kind(), names.peek().is_some()) {
+            (Kind::Message(message_desc), true) => desc = message_desc,
+            (Kind::Message(_), false) | (_, true) => {
+                return Err(SortError {
+                    kind: SortErrorKind::NotScalar {
+                        path: path.to_owned(),
+                    },
+                })
+            }
+            (_, false) => (),
+        }
+        fields.push(field_desc);
+    }
+    Ok(fields)
+}
+
+/// Extracts the key of each element of `list`, checking that they are all messages of type
+/// `element_desc`.
+fn extract_keys(
+    list: &[Value],
+    field_desc: &FieldDescriptor,
+    element_desc: &MessageDescriptor,
+    key_paths: &[Vec<FieldDescriptor>],
+) -> Result<Vec<Vec<Value>>, SortError> {
+    list.iter()
+        .map(|element| match element.as_message() {
+            Some(message) if message.descriptor() == *element_desc => Ok(key_paths
+                .iter()
+                .map(|path| extract_value(message, path))
+                .collect()),
+            _ => Err(SortError::invalid_element(field_desc, element_desc)),
+        })
+        .collect()
+}
+
+fn extract_value(message: &DynamicMessage, path: &[FieldDescriptor]) -> Value {
+    let (field_desc, rest) = path.split_first().expect("empty path");
+    let value = message.get_field(field_desc);
+    if rest.is_empty() {
+        value.into_owned()
+    } else {
+        extract_value(value.as_message().expect("field is not a message"), rest)
+    }
+}
+
+fn compare_keys(lhs: &[Value], rhs: &[Value], key_paths: &[Vec<FieldDescriptor>]) -> Ordering {
+    lhs.iter()
+        .zip(rhs)
+        .zip(key_paths)
+        .map(|((lhs, rhs), path)| {
+            let kind = path.last().expect("empty path").kind();
+            lhs.compare(rhs, &kind)
+                .unwrap_or_else(|| compare_nan(lhs, rhs))
+        })
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Orders `NaN` after all other values, so that the ordering used for sorting is total.
+fn compare_nan(lhs: &Value, rhs: &Value) -> Ordering {
+    fn is_nan(value: &Value) -> bool {
+        match *value {
+            Value::F32(value) => value.is_nan(),
+            Value::F64(value) => value.is_nan(),
+            _ => false,
+        }
+    }
+
+    is_nan(lhs).cmp(&is_nan(rhs))
+}
+
+impl SortError {
+    fn invalid_element(field_desc: &FieldDescriptor, element_desc: &MessageDescriptor) -> Self {
+        SortError {
+            kind: SortErrorKind::InvalidElement {
+                field: field_desc.full_name().to_owned(),
+                message: element_desc.full_name().to_owned(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            SortErrorKind::NotRepeatedMessage { field } => {
+                write!(f, "field '{}' is not a repeated message field", field)
+            }
+            SortErrorKind::UnknownField {
+                path,
+                message,
+                name,
+            } => write!(
+                f,
+                "invalid path '{}': message '{}' has no field named '{}'",
+                path, message, name
+            ),
+            SortErrorKind::RepeatedField { path, field } => {
+                write!(f, "invalid path '{}': field '{}' is repeated", path, field)
+            }
+            SortErrorKind::NotScalar { path } => write!(
+                f,
+                "invalid path '{}': path does not refer to a scalar or enum field",
+                path
+            ),
+            SortErrorKind::InvalidElement { field, message } => write!(
+                f,
+                "field '{}' contains a value which is not a message of type '{}'",
+                field, message
+            ),
+        }
+    }
+}
+
+impl Error for SortError {}
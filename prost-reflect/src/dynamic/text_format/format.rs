@@ -1,16 +1,21 @@
-use std::fmt::{self, Write};
+use std::{
+    borrow::Cow,
+    fmt::{self, Write},
+    sync::Arc,
+};
 
+#[cfg(feature = "text-format")]
+use base64::{display::Base64Display, prelude::BASE64_STANDARD};
 use prost::Message;
 
 use crate::{
-    descriptor::{GOOGLE_APIS_DOMAIN, GOOGLE_PROD_DOMAIN},
     dynamic::{
         fields::ValueAndDescriptor,
-        fmt_string,
-        text_format::FormatOptions,
+        fmt_string, resolve_any_type,
+        text_format::{BytesFormat, FormatOptions, UnsetFields},
         unknown::{UnknownField, UnknownFieldSet},
     },
-    DynamicMessage, Kind, MapKey, Value,
+    DynamicMessage, FieldDescriptor, Kind, MapKey, TypeResolver, Value,
 };
 
 pub(in crate::dynamic) struct Writer<'a, W> {
@@ -33,7 +38,7 @@ where
 
     pub fn fmt_message(&mut self, message: &DynamicMessage) -> fmt::Result {
         if self.options.expand_any {
-            if let Some((type_url, body)) = as_any(message) {
+            if let Some((type_url, body)) = as_any(message, self.options.type_resolver.as_ref()) {
                 self.f.write_char('[')?;
                 self.f.write_str(&type_url)?;
                 self.f.write_str("]")?;
@@ -42,15 +47,79 @@ where
             }
         }
 
-        let fields = message.fields.iter(&message.desc);
-        if self.options.skip_unknown_fields {
-            self.fmt_delimited(
-                fields.filter(|f| !matches!(f, ValueAndDescriptor::Unknown(..))),
-                Writer::fmt_message_field,
-            )
-        } else {
-            self.fmt_delimited(fields, Writer::fmt_message_field)
+        let entries = self.message_entries(message);
+        self.fmt_delimited(entries.into_iter(), Writer::fmt_entry)
+    }
+
+    fn message_entries<'m>(&self, message: &'m DynamicMessage) -> Vec<Entry<'m>> {
+        let mut entries: Vec<_> = message
+            .fields
+            .iter(&message.desc)
+            .filter(|f| {
+                !(self.options.skip_unknown_fields && matches!(f, ValueAndDescriptor::Unknown(..)))
+            })
+            .map(Entry::Value)
+            .collect();
+
+        let mut has_defaults = false;
+        if !self.options.skip_default_fields || self.options.unset_fields != UnsetFields::Skip {
+            for field in message.desc.fields() {
+                if message.has_field(&field) {
+                    continue;
+                }
+
+                if !field.supports_presence() {
+                    if !self.options.skip_default_fields {
+                        let value = Cow::Owned(Value::default_value_for_field(&field));
+                        entries.push(Entry::Value(ValueAndDescriptor::Field(value, field)));
+                        has_defaults = true;
+                    }
+                    continue;
+                }
+
+                match self.options.unset_fields {
+                    UnsetFields::Skip => (),
+                    UnsetFields::Comment => {
+                        if self.is_multi_line() {
+                            entries.push(Entry::Unset {
+                                field,
+                                comment: true,
+                            });
+                            has_defaults = true;
+                        }
+                    }
+                    UnsetFields::Default => {
+                        if field.containing_oneof().is_none()
+                            || field.field_descriptor_proto().proto3_optional()
+                        {
+                            entries.push(Entry::Unset {
+                                field,
+                                comment: false,
+                            });
+                            has_defaults = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if !self.options.sort_fields_by_number {
+            // Fields are stored in order of number, so a stable sort keeps extensions and unknown
+            // fields sorted by number after the normal fields.
+            let declared = &message.desc.descriptor_proto().field;
+            entries.sort_by_cached_key(|entry| match entry {
+                Entry::Value(ValueAndDescriptor::Field(_, desc))
+                | Entry::Unset { field: desc, .. } => declared
+                    .iter()
+                    .position(|field| field.number() == desc.number() as i32)
+                    .unwrap_or(usize::MAX),
+                _ => usize::MAX,
+            });
+        } else if has_defaults {
+            entries.sort_by_key(|entry| entry.number());
         }
+
+        entries
     }
 
     pub fn fmt_value(&mut self, value: &Value, kind: Option<&Kind>) -> fmt::Result {
@@ -75,7 +144,7 @@ where
                 }
             }
             Value::String(s) => self.fmt_string(s.as_bytes()),
-            Value::Bytes(s) => self.fmt_string(s.as_ref()),
+            Value::Bytes(s) => self.fmt_bytes(s.as_ref()),
             Value::EnumNumber(value) => {
                 if let Some(Kind::Enum(desc)) = kind {
                     if let Some(value) = desc.get_value(*value) {
@@ -85,16 +154,14 @@ where
                 write!(self.f, "{}", value)
             }
             Value::Message(message) => {
-                if message.fields.iter(&message.desc).all(|f| {
-                    self.options.skip_unknown_fields && matches!(f, ValueAndDescriptor::Unknown(..))
-                }) {
+                if self.message_entries(message).is_empty() {
                     self.f.write_str("{}")
                 } else if self.options.pretty {
                     self.f.write_char('{')?;
-                    self.indent_level += 2;
+                    self.indent_level += self.options.indent_width;
                     self.fmt_newline()?;
                     self.fmt_message(message)?;
-                    self.indent_level -= 2;
+                    self.indent_level -= self.options.indent_width;
                     self.fmt_newline()?;
                     self.f.write_char('}')
                 } else {
@@ -110,23 +177,32 @@ where
                 let value_kind = kind
                     .and_then(|k| k.as_message())
                     .map(|m| m.map_entry_value_field().kind());
+                let (key_name, value_name) = if self.options.use_field_numbers {
+                    ("1", "2")
+                } else {
+                    ("key", "value")
+                };
                 self.fmt_list(map.iter(), |this, (key, value)| {
                     if this.options.pretty {
                         this.f.write_str("{")?;
-                        this.indent_level += 2;
+                        this.indent_level += this.options.indent_width;
                         this.fmt_newline()?;
-                        this.f.write_str("key: ")?;
+                        this.f.write_str(key_name)?;
+                        this.f.write_str(": ")?;
                         this.fmt_map_key(key)?;
                         this.fmt_newline()?;
-                        this.f.write_str("value")?;
+                        this.f.write_str(value_name)?;
                         this.fmt_field_value(value, value_kind.as_ref())?;
-                        this.indent_level -= 2;
+                        this.indent_level -= this.options.indent_width;
                         this.fmt_newline()?;
                         this.f.write_char('}')
                     } else {
-                        this.f.write_str("{key:")?;
+                        this.f.write_char('{')?;
+                        this.f.write_str(key_name)?;
+                        this.f.write_char(':')?;
                         this.fmt_map_key(key)?;
-                        this.f.write_str(",value")?;
+                        this.f.write_char(',')?;
+                        this.f.write_str(value_name)?;
                         this.fmt_field_value(value, value_kind.as_ref())?;
                         this.f.write_char('}')
                     }
@@ -135,7 +211,7 @@ where
         }
     }
 
-    fn fmt_map_key(&mut self, value: &MapKey) -> fmt::Result {
+    pub fn fmt_map_key(&mut self, value: &MapKey) -> fmt::Result {
         match value {
             MapKey::Bool(value) => write!(self.f, "{}", value),
             MapKey::I32(value) => write!(self.f, "{}", value),
@@ -146,19 +222,50 @@ where
         }
     }
 
+    fn fmt_entry(&mut self, entry: Entry) -> fmt::Result {
+        match entry {
+            Entry::Value(field) => self.fmt_message_field(field),
+            Entry::Unset { field, comment } => {
+                if comment {
+                    self.f.write_str("# ")?;
+                }
+                // The contents of unset messages are not expanded, since message types may be
+                // recursive.
+                if field.kind().as_message().is_some() {
+                    self.fmt_field_name(&field)?;
+                    self.fmt_padding()?;
+                    self.f.write_str("{}")
+                } else {
+                    let value = Cow::Owned(Value::default_value_for_field(&field));
+                    self.fmt_message_field(ValueAndDescriptor::Field(value, field))
+                }
+            }
+        }
+    }
+
+    fn fmt_field_name(&mut self, desc: &FieldDescriptor) -> fmt::Result {
+        if self.options.use_field_numbers {
+            write!(self.f, "{}", desc.number())
+        } else if desc.is_group() {
+            write!(self.f, "{}", desc.kind().as_message().unwrap().name())
+        } else {
+            write!(self.f, "{}", desc.name())
+        }
+    }
+
     fn fmt_message_field(&mut self, field: ValueAndDescriptor) -> fmt::Result {
         match field {
             ValueAndDescriptor::Field(value, desc) => {
-                if desc.is_group() {
-                    write!(self.f, "{}", desc.kind().as_message().unwrap().name())?;
-                } else {
-                    write!(self.f, "{}", desc.name())?;
-                }
-                self.fmt_field_value(&value, Some(&desc.kind()))
+                self.fmt_field(&value, &desc.kind(), |this| this.fmt_field_name(&desc))
             }
             ValueAndDescriptor::Extension(value, desc) => {
-                write!(self.f, "[{}]", desc.full_name())?;
-                self.fmt_field_value(&value, Some(&desc.kind()))
+                self.fmt_field(&value, &desc.kind(), |this| {
+                    if this.options.use_field_numbers {
+                        write!(this.f, "{}", desc.number())
+                    } else {
+                        write!(this.f, "[{}]", desc.full_name())
+                    }
+                })
             }
             ValueAndDescriptor::Unknown(number, values) => self.fmt_delimited(
                 values.iter().map(|value| (number, value)),
@@ -167,6 +274,33 @@ where
         }
     }
 
+    /// Writes a field, or a comment containing the field if its value is written as base64.
+    fn fmt_field(
+        &mut self,
+        value: &Value,
+        kind: &Kind,
+        fmt_name: impl FnOnce(&mut Self) -> fmt::Result,
+    ) -> fmt::Result {
+        #[cfg(feature = "text-format")]
+        if self.options.bytes_format == BytesFormat::Base64Comment
+            && matches!(kind, Kind::Bytes)
+            && self.is_multi_line()
+        {
+            self.f.write_str("# ")?;
+            fmt_name(self)?;
+            self.f.write_str(" (base64): ")?;
+            return match value {
+                Value::List(values) => {
+                    self.fmt_list(values.iter(), |this, value| this.fmt_base64(value))
+                }
+                value => self.fmt_base64(value),
+            };
+        }
+
+        fmt_name(self)?;
+        self.fmt_field_value(value, Some(kind))
+    }
+
     fn fmt_field_value(&mut self, value: &Value, kind: Option<&Kind>) -> fmt::Result {
         if !matches!(value, Value::Message(_)) {
             self.f.write_char(':')?;
@@ -217,10 +351,10 @@ where
             self.f.write_str("{}")
         } else if self.options.pretty {
             self.f.write_char('{')?;
-            self.indent_level += 2;
+            self.indent_level += self.options.indent_width;
             self.fmt_newline()?;
             self.fmt_delimited(set.fields(), Writer::fmt_unknown_field)?;
-            self.indent_level -= 2;
+            self.indent_level -= self.options.indent_width;
             self.fmt_newline()?;
             self.f.write_char('}')
         } else {
@@ -234,6 +368,52 @@ where
         fmt_string(&mut self.f, bytes)
     }
 
+    fn fmt_bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+        let (bytes, truncated) = self.truncate_bytes(bytes);
+        if self.options.bytes_format == BytesFormat::Hex {
+            self.f.write_char('"')?;
+            for byte in bytes {
+                write!(self.f, "\\x{:02x}", byte)?;
+            }
+            self.f.write_char('"')?;
+        } else {
+            self.fmt_string(bytes)?;
+        }
+
+        if truncated {
+            self.f.write_str("...")?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "text-format")]
+    fn fmt_base64(&mut self, value: &Value) -> fmt::Result {
+        let bytes = value.as_bytes().expect("expected bytes value");
+        let (bytes, truncated) = self.truncate_bytes(bytes);
+        write!(
+            self.f,
+            "\"{}\"",
+            Base64Display::new(bytes, &BASE64_STANDARD)
+        )?;
+        if truncated {
+            self.f.write_str("...")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the prefix of `bytes` to print, and whether it was truncated.
+    fn truncate_bytes<'b>(&self, bytes: &'b [u8]) -> (&'b [u8], bool) {
+        match self.options.max_bytes_length {
+            Some(len) if bytes.len() > len => (&bytes[..len], true),
+            _ => (bytes, false),
+        }
+    }
+
+    /// Returns `true` if each field is written on its own line, so comments may be written.
+    fn is_multi_line(&self) -> bool {
+        self.options.pretty && !self.options.single_line
+    }
+
     fn fmt_delimited<T>(
         &mut self,
         mut iter: impl Iterator<Item = T>,
@@ -279,6 +459,10 @@ where
     }
 
     fn fmt_newline(&mut self) -> fmt::Result {
+        if self.options.single_line {
+            return self.f.write_char(' ');
+        }
+
         self.f.write_char('\n')?;
         for _ in 0..self.indent_level {
             self.f.write_char(' ')?;
@@ -287,21 +471,39 @@ where
     }
 }
 
-fn as_any(message: &DynamicMessage) -> Option<(String, DynamicMessage)> {
+/// A field to print as part of a message.
+enum Entry<'a> {
+    Value(ValueAndDescriptor<'a>),
+    /// A field which supports presence but is not set, printed with its default value, or as a
+    /// comment if `comment` is `true`.
+    Unset {
+        field: FieldDescriptor,
+        comment: bool,
+    },
+}
+
+impl<'a> Entry<'a> {
+    fn number(&self) -> u32 {
+        match self {
+            Entry::Value(ValueAndDescriptor::Field(_, desc)) | Entry::Unset { field: desc, .. } => {
+                desc.number()
+            }
+            Entry::Value(ValueAndDescriptor::Extension(_, desc)) => desc.number(),
+            Entry::Value(ValueAndDescriptor::Unknown(number, _)) => *number,
+        }
+    }
+}
+
+fn as_any(
+    message: &DynamicMessage,
+    resolver: Option<&Arc<dyn TypeResolver>>,
+) -> Option<(String, DynamicMessage)> {
     if message.desc.full_name() != "google.protobuf.Any" {
         return None;
     }
 
     let any = message.transcode_to::<prost_types::Any>().ok()?;
-    let message_name = any
-        .type_url
-        .strip_prefix(GOOGLE_APIS_DOMAIN)
-        .or_else(|| any.type_url.strip_prefix(GOOGLE_PROD_DOMAIN))?;
-
-    let desc = message
-        .desc
-        .parent_pool()
-        .get_message_by_name(message_name)?;
+    let desc = resolve_any_type(resolver, message.desc.parent_pool(), &any.type_url).ok()?;
     let body = DynamicMessage::decode(desc, any.value.as_slice()).ok()?;
     Some((any.type_url, body))
 }
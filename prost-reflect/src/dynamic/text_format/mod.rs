@@ -4,6 +4,10 @@ mod parse;
 
 #[cfg(feature = "text-format")]
 pub use self::parse::ParseError;
+
+use std::sync::Arc;
+
+use crate::TypeResolver;
 #[cfg(feature = "text-format")]
 use crate::{DynamicMessage, MessageDescriptor};
 
@@ -16,8 +20,77 @@ pub(super) use self::format::Writer;
 #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
 pub struct FormatOptions {
     pretty: bool,
+    single_line: bool,
+    indent_width: u32,
+    sort_fields_by_number: bool,
+    #[cfg_attr(not(feature = "text-format"), allow(dead_code))]
+    trailing_newline: bool,
     skip_unknown_fields: bool,
+    skip_default_fields: bool,
+    unset_fields: UnsetFields,
+    use_field_numbers: bool,
+    bytes_format: BytesFormat,
+    max_bytes_length: Option<usize>,
     expand_any: bool,
+    type_resolver: Option<Arc<dyn TypeResolver>>,
+}
+
+/// How to print fields which support presence but are not set.
+///
+/// Used by [`FormatOptions::unset_fields()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
+#[cfg_attr(not(feature = "text-format"), allow(dead_code))]
+pub enum UnsetFields {
+    /// Unset fields are not printed.
+    Skip,
+    /// Unset fields are printed as comments containing their default value, such as `# foo: 0`.
+    ///
+    /// Comments can only be written in multi-line output, so unset fields are skipped unless
+    /// [`pretty`][FormatOptions::pretty] is set and [`single_line`][FormatOptions::single_line]
+    /// is not.
+    Comment,
+    /// Unset fields are printed with their default value, such as `foo: 0`.
+    ///
+    /// Parsing the output sets these fields. Unset fields in a `oneof` are skipped, since at most
+    /// one of them may be set.
+    Default,
+}
+
+/// How to print the values of `bytes` fields.
+///
+/// Used by [`FormatOptions::bytes_format()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
+#[cfg_attr(not(feature = "text-format"), allow(dead_code))]
+pub enum BytesFormat {
+    /// Printable ASCII characters are written unchanged, and other bytes are written as C-style
+    /// or octal escapes, such as `"ab\001"`. This matches the output of `protoc`.
+    Escaped,
+    /// Every byte is written as a hexadecimal escape, such as `"\x61\x62\x01"`.
+    Hex,
+    /// Fields are written as a comment containing the base64 encoding of their value, such as
+    /// `# data (base64): "YWIB"`. Such fields are ignored when the output is parsed.
+    ///
+    /// Comments can only be written in multi-line output, so values are escaped unless
+    /// [`pretty`][FormatOptions::pretty] is set and [`single_line`][FormatOptions::single_line]
+    /// is not. The values of map entries are also escaped.
+    #[cfg(feature = "text-format")]
+    Base64Comment,
+}
+
+/// Options to control parsing of the protobuf text format.
+///
+/// Used by [`DynamicMessage::parse_text_format_with_options()`].
+#[derive(Debug, Clone)]
+#[cfg(feature = "text-format")]
+#[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
+pub struct ParseOptions {
+    deny_duplicate_fields: bool,
+    allow_unknown_fields: bool,
+    allow_field_numbers: bool,
+    preserve_unknown_fields: bool,
+    type_resolver: Option<Arc<dyn TypeResolver>>,
 }
 
 #[cfg(feature = "text-format")]
@@ -36,8 +109,31 @@ impl DynamicMessage {
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
     pub fn parse_text_format(desc: MessageDescriptor, input: &str) -> Result<Self, ParseError> {
+        Self::parse_text_format_with_options(desc, input, &ParseOptions::new())
+    }
+
+    /// Parse a [`DynamicMessage`] from the given message encoded using the [text format](https://developers.google.com/protocol-buffers/docs/text-format-spec),
+    /// with custom options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::ParseOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let options = ParseOptions::new().deny_duplicate_fields(false);
+    /// let dynamic_message = DynamicMessage::parse_text_format_with_options(message_descriptor, "foo: 1 foo: 150", &options).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
+    pub fn parse_text_format_with_options(
+        desc: MessageDescriptor,
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
         let mut message = DynamicMessage::new(desc);
-        message.merge_text_format(input)?;
+        message.merge_text_format_with_options(input, options)?;
         Ok(message)
     }
 
@@ -56,7 +152,30 @@ impl DynamicMessage {
     /// ```
     #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
     pub fn merge_text_format(&mut self, input: &str) -> Result<(), ParseError> {
-        parse::Parser::new(input)
+        self.merge_text_format_with_options(input, &ParseOptions::new())
+    }
+
+    /// Merges the given message encoded using the [text format](https://developers.google.com/protocol-buffers/docs/text-format-spec) into this message,
+    /// with custom options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::ParseOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// dynamic_message.merge_text_format_with_options("foo: 150", &ParseOptions::new()).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
+    pub fn merge_text_format_with_options(
+        &mut self,
+        input: &str,
+        options: &ParseOptions,
+    ) -> Result<(), ParseError> {
+        parse::Parser::new(input, options.clone())
             .parse_message(self)
             .map_err(|kind| ParseError::new(kind, input))
     }
@@ -99,6 +218,10 @@ impl DynamicMessage {
         format::Writer::new(options.clone(), &mut result)
             .fmt_message(self)
             .expect("writing to string cannot fail");
+        if options.pretty && !options.single_line && options.trailing_newline && !result.is_empty()
+        {
+            result.push('\n');
+        }
         result
     }
 }
@@ -119,6 +242,67 @@ impl FormatOptions {
         self
     }
 
+    /// Whether to print prettified output on a single line.
+    ///
+    /// If set to `true` along with [`pretty`][Self::pretty], fields and nested messages are
+    /// separated by single spaces instead of new lines, matching the single line mode of the C++
+    /// `TextFormat` printer. This has no effect unless [`pretty`][Self::pretty] is set.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, text_format::FormatOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let options = FormatOptions::new().pretty(true).single_line(true);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), "foo: 150 nested { bar: 66 }");
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn single_line(mut self, yes: bool) -> Self {
+        self.single_line = yes;
+        self
+    }
+
+    /// Sets the number of spaces used to indent each level of nested messages in prettified
+    /// output.
+    ///
+    /// The default value is `2`.
+    #[cfg(feature = "text-format")]
+    pub fn indent_width(mut self, width: u32) -> Self {
+        self.indent_width = width;
+        self
+    }
+
+    /// Whether to print fields in order of their field number.
+    ///
+    /// If set to `false`, fields are printed in the order they are declared in the message
+    /// definition, followed by any extensions and unknown fields in order of field number.
+    ///
+    /// The default value is `true`, which matches the output of `protoc` and the C++ `TextFormat`
+    /// printer.
+    #[cfg(feature = "text-format")]
+    pub fn sort_fields_by_number(mut self, yes: bool) -> Self {
+        self.sort_fields_by_number = yes;
+        self
+    }
+
+    /// Whether to end multi-line output with a new line.
+    ///
+    /// If set to `true` along with [`pretty`][Self::pretty], the output of a non-empty message
+    /// ends with a new line, as in the output of `protoc --decode`. This has no effect on single
+    /// line output.
+    ///
+    /// The default value is `false`.
+    #[cfg(feature = "text-format")]
+    pub fn trailing_newline(mut self, yes: bool) -> Self {
+        self.trailing_newline = yes;
+        self
+    }
+
     /// Whether to include unknown fields in the output.
     ///
     /// If set to `false`, unknown fields will be printed. The protobuf format does not include type information,
@@ -145,6 +329,136 @@ impl FormatOptions {
         self
     }
 
+    /// Whether to skip fields which have their default value.
+    ///
+    /// If set to `false`, fields which do not support presence, such as proto3 scalar fields and
+    /// repeated fields, are printed even if they have their default value. Unset fields which do
+    /// support presence are controlled by [`unset_fields`][Self::unset_fields].
+    ///
+    /// The default value is `true`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, text_format::FormatOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::new(message_descriptor);
+    /// assert_eq!(dynamic_message.to_text_format(), "");
+    /// let options = FormatOptions::new().skip_default_fields(false);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), "foo:0");
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn skip_default_fields(mut self, yes: bool) -> Self {
+        self.skip_default_fields = yes;
+        self
+    }
+
+    /// Sets how to print fields which support presence but are not set, such as message fields
+    /// and `optional` fields.
+    ///
+    /// Together with [`skip_default_fields`][Self::skip_default_fields], this can be used to
+    /// print a complete template of a message type.
+    ///
+    /// The default value is [`UnsetFields::Skip`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, text_format::{FormatOptions, UnsetFields}};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::new(message_descriptor);
+    /// let options = FormatOptions::new()
+    ///     .pretty(true)
+    ///     .skip_default_fields(false)
+    ///     .unset_fields(UnsetFields::Comment);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), "foo: 0\n# bar: 0\n# nested {}");
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn unset_fields(mut self, style: UnsetFields) -> Self {
+        self.unset_fields = style;
+        self
+    }
+
+    /// Whether to print fields by their number rather than their name.
+    ///
+    /// If set to `true`, fields and extensions are printed as `1: 150` rather than `foo: 150`, and
+    /// map entries use `1` and `2` for their key and value. The output can be parsed against any
+    /// compatible message descriptor using [`ParseOptions::allow_field_numbers`]. Expanded
+    /// `google.protobuf.Any` messages are still printed using their type URL.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, text_format::FormatOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let options = FormatOptions::new().use_field_numbers(true);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), "1:150,3{2:66}");
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn use_field_numbers(mut self, yes: bool) -> Self {
+        self.use_field_numbers = yes;
+        self
+    }
+
+    /// Sets how to print the values of `bytes` fields.
+    ///
+    /// The default value is [`BytesFormat::Escaped`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::{BytesFormat, FormatOptions}, bytes::Bytes};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// dynamic_message.set_field_by_name("value", Value::Bytes(Bytes::from_static(b"ab\x01")));
+    ///
+    /// assert_eq!(dynamic_message.to_text_format(), r#"value:"ab\001""#);
+    /// let options = FormatOptions::new().bytes_format(BytesFormat::Hex);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), r#"value:"\x61\x62\x01""#);
+    /// let options = FormatOptions::new().pretty(true).bytes_format(BytesFormat::Base64Comment);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), r#"# value (base64): "YWIB""#);
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn bytes_format(mut self, format: BytesFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+
+    /// Sets the maximum number of bytes to print for the value of a `bytes` field.
+    ///
+    /// Longer values are truncated, and followed by `...` to mark that they are incomplete.
+    /// Output containing truncated values cannot be parsed.
+    ///
+    /// The default value is `None`, which prints values in full.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::FormatOptions, bytes::Bytes};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// let message_descriptor = pool.get_message_by_name("google.protobuf.Any").unwrap();
+    /// let mut dynamic_message = DynamicMessage::new(message_descriptor);
+    /// dynamic_message.set_field_by_name("value", Value::Bytes(Bytes::from_static(b"abcdef")));
+    ///
+    /// let options = FormatOptions::new().max_bytes_length(Some(3));
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&options), r#"value:"abc"..."#);
+    /// ```
+    #[cfg(feature = "text-format")]
+    pub fn max_bytes_length(mut self, len: Option<usize>) -> Self {
+        self.max_bytes_length = len;
+        self
+    }
+
     /// Whether to use the expanded form of the `google.protobuf.Any` type.
     ///
     /// If set to `true`, `Any` fields will use an expanded form:
@@ -185,14 +499,171 @@ impl FormatOptions {
         self.expand_any = yes;
         self
     }
+
+    /// Sets the resolver used to look up the payload type of `google.protobuf.Any` messages when
+    /// [`expand_any`][Self::expand_any] is enabled.
+    ///
+    /// By default, payload types are looked up in the pool of the `Any` message. See
+    /// [`TypeResolver`] for details.
+    #[cfg(feature = "text-format")]
+    pub fn type_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: TypeResolver + 'static,
+    {
+        self.type_resolver = Some(Arc::new(resolver));
+        self
+    }
 }
 
 impl Default for FormatOptions {
     fn default() -> Self {
         FormatOptions {
             pretty: false,
+            single_line: false,
+            indent_width: 2,
+            sort_fields_by_number: true,
+            trailing_newline: false,
             skip_unknown_fields: true,
+            skip_default_fields: true,
+            unset_fields: UnsetFields::Skip,
+            use_field_numbers: false,
+            bytes_format: BytesFormat::Escaped,
+            max_bytes_length: None,
             expand_any: true,
+            type_resolver: None,
+        }
+    }
+}
+
+#[cfg(feature = "text-format")]
+impl ParseOptions {
+    /// Creates a new instance of [`ParseOptions`], with the default options chosen to match the
+    /// behaviour of `protoc`.
+    pub fn new() -> Self {
+        ParseOptions::default()
+    }
+
+    /// Whether to return an error if a non-repeated field, or more than one field from a oneof,
+    /// is set multiple times.
+    ///
+    /// If set to `false`, the last value given for the field is used.
+    ///
+    /// The default value is `true`.
+    pub fn deny_duplicate_fields(mut self, yes: bool) -> Self {
+        self.deny_duplicate_fields = yes;
+        self
+    }
+
+    /// Whether to skip fields and extensions which are not defined in the message descriptor,
+    /// rather than returning an error.
+    ///
+    /// The values of unknown fields are discarded, including any nested messages or lists. Since
+    /// the type of the field is unknown, values are only checked to be syntactically valid. This
+    /// matches the `AllowUnknownField` and `AllowUnknownExtension` options of the C++ parser.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::ParseOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let input = "foo: 150 baz { qux: [1, 2] quux: \"x\" } [ext.field]: -inf";
+    /// assert!(DynamicMessage::parse_text_format(message_descriptor.clone(), input).is_err());
+    ///
+    /// let options = ParseOptions::new().allow_unknown_fields(true);
+    /// let dynamic_message = DynamicMessage::parse_text_format_with_options(message_descriptor, input, &options).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    pub fn allow_unknown_fields(mut self, yes: bool) -> Self {
+        self.allow_unknown_fields = yes;
+        self
+    }
+
+    /// Whether to allow fields and extensions to be identified by their number, as in `1: 150`,
+    /// as well as by their name.
+    ///
+    /// This allows parsing the output of [`FormatOptions::use_field_numbers`], and matches the
+    /// `AllowFieldNumber` option of the C++ parser.
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::ParseOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let options = ParseOptions::new().allow_field_numbers(true);
+    /// let dynamic_message = DynamicMessage::parse_text_format_with_options(message_descriptor, "1: 150 3 { 2: 66 }", &options).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    pub fn allow_field_numbers(mut self, yes: bool) -> Self {
+        self.allow_field_numbers = yes;
+        self
+    }
+
+    /// Whether to store fields identified by a number which is not defined in the message
+    /// descriptor as unknown fields, rather than skipping them or returning an error.
+    ///
+    /// This allows the unknown fields written by [`FormatOptions::skip_unknown_fields`] to be
+    /// preserved when text format is parsed and printed again, for example by tools which edit
+    /// configuration files. Integers are stored as varints, hexadecimal integers with exactly 8
+    /// or 16 digits as fixed-width values, floating point numbers as 64-bit values, and strings
+    /// and nested messages as length-delimited values. Groups are therefore re-encoded as
+    /// length-delimited values. Setting this option also allows fields to be identified by number,
+    /// as with [`allow_field_numbers`][Self::allow_field_numbers].
+    ///
+    /// Fields identified by a name which is not defined in the message descriptor have no field
+    /// number, so cannot be preserved. They are controlled by
+    /// [`allow_unknown_fields`][Self::allow_unknown_fields].
+    ///
+    /// The default value is `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost::Message;
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, text_format::{FormatOptions, ParseOptions}};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let options = ParseOptions::new().preserve_unknown_fields(true);
+    /// let dynamic_message = DynamicMessage::parse_text_format_with_options(message_descriptor, "foo: 150 10: 5 11 { 1: \"x\" }", &options).unwrap();
+    /// assert_eq!(dynamic_message.encode_to_vec(), b"\x08\x96\x01\x50\x05\x5a\x03\x0a\x01x");
+    ///
+    /// let format_options = FormatOptions::new().skip_unknown_fields(false);
+    /// assert_eq!(dynamic_message.to_text_format_with_options(&format_options), "foo:150,10:5,11{1:\"x\"}");
+    /// ```
+    pub fn preserve_unknown_fields(mut self, yes: bool) -> Self {
+        self.preserve_unknown_fields = yes;
+        self
+    }
+
+    /// Sets the resolver used to look up the payload type of `google.protobuf.Any` messages written
+    /// in the expanded form, `[type_url] { ... }`.
+    ///
+    /// By default, payload types are looked up in the pool of the `Any` message, and only the
+    /// `type.googleapis.com` and `type.googleprod.com` domains are supported. If a resolver is set,
+    /// it is passed the full type URL, and any domain is accepted. See [`TypeResolver`] for details.
+    pub fn type_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: TypeResolver + 'static,
+    {
+        self.type_resolver = Some(Arc::new(resolver));
+        self
+    }
+}
+
+#[cfg(feature = "text-format")]
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            deny_duplicate_fields: true,
+            allow_unknown_fields: false,
+            allow_field_numbers: false,
+            preserve_unknown_fields: false,
+            type_resolver: None,
         }
     }
 }
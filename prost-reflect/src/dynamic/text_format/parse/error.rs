@@ -1,33 +1,148 @@
 use logos::Span;
 use std::{
     error::Error,
-    fmt::{self, Display},
+    fmt::{self, Display, Write},
+    ops::Range,
 };
 
 /// An error that may occur while parsing the protobuf text format.
+///
+/// Besides the error message, this records where in the input the error occurred, to help find
+/// mistakes in large inputs.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DynamicMessage, DescriptorPool};
+/// # let pool = DescriptorPool::decode(include_bytes!("../../../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// let err = DynamicMessage::parse_text_format(message_descriptor, "foo: 1\nnested {\n  bar: 'x'\n}").unwrap_err();
+/// assert_eq!(err.to_string(), "expected an integer, but found '\"x\"'");
+/// assert_eq!(err.line(), Some(3));
+/// assert_eq!(err.column(), Some(8));
+/// assert_eq!(err.span(), Some(23..26));
+/// assert_eq!(err.token(), Some("'x'"));
+/// assert_eq!(err.snippet().unwrap(), "3 |   bar: 'x'\n  |        ^^^\n");
+/// ```
 #[derive(Debug)]
 #[cfg_attr(docsrs, doc(cfg(feature = "text-format")))]
 pub struct ParseError {
     kind: Box<ParseErrorKind>,
+    location: Option<Box<Location>>,
     #[cfg(feature = "miette")]
     source: String,
 }
 
+/// The position of the input which caused a [`ParseError`].
+#[derive(Debug)]
+struct Location {
+    span: Range<usize>,
+    /// The one-based line number of the start of the span.
+    line: usize,
+    /// The one-based column number, in characters, of the start of the span.
+    column: usize,
+    /// The text of the line containing the start of the span.
+    line_text: String,
+    token: String,
+}
+
 impl ParseError {
     #[cfg(feature = "miette")]
     pub(crate) fn new(kind: ParseErrorKind, source: &str) -> Self {
         ParseError {
+            location: Location::new(&kind, source),
             kind: Box::new(kind),
             source: source.to_owned(),
         }
     }
 
     #[cfg(not(feature = "miette"))]
-    pub(crate) fn new(kind: ParseErrorKind, _: &str) -> Self {
+    pub(crate) fn new(kind: ParseErrorKind, source: &str) -> Self {
         ParseError {
+            location: Location::new(&kind, source),
             kind: Box::new(kind),
         }
     }
+
+    /// Returns the range of bytes in the input which caused this error, if known.
+    ///
+    /// This is `None` if the error was caused by reaching the end of the input.
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.location.as_ref().map(|location| location.span.clone())
+    }
+
+    /// Returns the one-based line number of the start of the input which caused this error, if
+    /// known.
+    pub fn line(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.line)
+    }
+
+    /// Returns the one-based column number of the start of the input which caused this error,
+    /// if known. Columns are counted in characters.
+    pub fn column(&self) -> Option<usize> {
+        self.location.as_ref().map(|location| location.column)
+    }
+
+    /// Returns the text of the input which caused this error, if known.
+    pub fn token(&self) -> Option<&str> {
+        self.location
+            .as_ref()
+            .map(|location| location.token.as_str())
+    }
+
+    /// Renders the line of input containing this error, with a caret marker underneath the text
+    /// which caused it, or returns `None` if the location is not known.
+    ///
+    /// If the text spans multiple lines, only the first line is shown.
+    pub fn snippet(&self) -> Option<String> {
+        let location = self.location.as_ref()?;
+
+        let line_number = location.line.to_string();
+        let marker_width = location
+            .token
+            .lines()
+            .next()
+            .map(|line| line.chars().count())
+            .unwrap_or(0)
+            .max(1);
+
+        let mut result = String::new();
+        writeln!(result, "{} | {}", line_number, location.line_text).unwrap();
+        writeln!(
+            result,
+            "{:width$} | {:column$}{}",
+            "",
+            "",
+            "^".repeat(marker_width),
+            width = line_number.len(),
+            column = location.column - 1,
+        )
+        .unwrap();
+        Some(result)
+    }
+}
+
+impl Location {
+    fn new(kind: &ParseErrorKind, source: &str) -> Option<Box<Self>> {
+        let span = kind.span()?;
+        let token = source.get(span.clone())?;
+
+        let line_start = source[..span.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |index| span.start + index);
+        let line_text = source[line_start..line_end].trim_end_matches('\r');
+
+        Some(Box::new(Location {
+            line: source[..span.start].matches('\n').count() + 1,
+            column: source[line_start..span.start].chars().count() + 1,
+            line_text: line_text.to_owned(),
+            token: token.to_owned(),
+            span,
+        }))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -190,7 +305,30 @@ impl Display for ParseErrorKind {
                 f,
                 "the field type must be 'google.protobuf.Any' to use Any expansion syntax"
             ),
-            ParseErrorKind::InvalidMapKey { .. } => write!(f, "invalid value type for map key"),
+            ParseErrorKind::InvalidMapKey => write!(f, "invalid value type for map key"),
+        }
+    }
+}
+
+impl ParseErrorKind {
+    fn span(&self) -> Option<Span> {
+        match self {
+            ParseErrorKind::InvalidToken { span }
+            | ParseErrorKind::InvalidStringCharacters { span }
+            | ParseErrorKind::InvalidStringEscape { span }
+            | ParseErrorKind::InvalidUtf8String { span }
+            | ParseErrorKind::NoSpaceBetweenIntAndIdent { span }
+            | ParseErrorKind::UnexpectedToken { span, .. }
+            | ParseErrorKind::IntegerValueOutOfRange { span, .. }
+            | ParseErrorKind::FieldNotFound { span, .. }
+            | ParseErrorKind::FieldAlreadySet { span, .. }
+            | ParseErrorKind::OneofAlreadySet { span, .. }
+            | ParseErrorKind::ExtensionNotFound { span, .. }
+            | ParseErrorKind::UnknownTypeUrlDomain { span, .. }
+            | ParseErrorKind::MessageNotFound { span, .. }
+            | ParseErrorKind::EnumValueNotFound { span, .. }
+            | ParseErrorKind::InvalidTypeForAny { span } => Some(span.clone()),
+            ParseErrorKind::UnexpectedEof { .. } | ParseErrorKind::InvalidMapKey => None,
         }
     }
 }
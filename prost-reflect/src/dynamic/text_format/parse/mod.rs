@@ -16,26 +16,37 @@ use crate::{
     descriptor::{
         GOOGLE_APIS_DOMAIN, GOOGLE_PROD_DOMAIN, MAP_ENTRY_KEY_NUMBER, MAP_ENTRY_VALUE_NUMBER,
     },
-    dynamic::fields::FieldDescriptorLike,
+    dynamic::{
+        fields::FieldDescriptorLike,
+        unknown::{UnknownField, UnknownFieldSet},
+    },
     DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MapKey, MessageDescriptor, Value,
 };
 
+use super::ParseOptions;
+
 pub(in crate::dynamic::text_format) struct Parser<'a> {
     lexer: Lexer<'a, Token<'a>>,
     peek: Option<Result<(Token<'a>, Span), ParseErrorKind>>,
+    options: ParseOptions,
 }
 
+/// The largest valid field number.
+const MAX_FIELD_NUMBER: u32 = (1 << 29) - 1;
+
 enum FieldName {
     Ident(String),
+    Number(u32),
     Extension(String),
     Any(String, String),
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(input: &'a str) -> Self {
+    pub fn new(input: &'a str, options: ParseOptions) -> Self {
         Parser {
             lexer: Token::lexer(input),
             peek: None,
+            options,
         }
     }
 
@@ -59,6 +70,9 @@ impl<'a> Parser<'a> {
         loop {
             match self.peek()? {
                 Some((Token::Ident(_) | Token::LeftBracket, _)) => self.parse_field(message)?,
+                Some((Token::IntLiteral(_), _)) if self.allow_field_numbers() => {
+                    self.parse_field(message)?
+                }
                 Some((tok, _)) if tok == terminator => {
                     let end = self.bump();
                     return Ok(join_span(start, end));
@@ -80,38 +94,65 @@ impl<'a> Parser<'a> {
         };
 
         match name {
-            FieldName::Ident(field_name) => {
-                let field = find_field(&message.desc, &field_name).ok_or_else(|| {
-                    ParseErrorKind::FieldNotFound {
+            FieldName::Ident(field_name) => match find_field(&message.desc, &field_name) {
+                Some(field) => self.parse_field_value(message, &field)?,
+                None if self.options.allow_unknown_fields => self.skip_value()?,
+                None => {
+                    return Err(ParseErrorKind::FieldNotFound {
                         field_name,
                         message_name: message.desc.full_name().to_owned(),
                         span,
+                    })
+                }
+            },
+            FieldName::Number(number) => match message.desc.get_field(number) {
+                Some(field) => self.parse_field_value(message, &field)?,
+                None => match message.desc.get_extension(number) {
+                    Some(extension) => self.parse_field_value(message, &extension)?,
+                    None if self.options.preserve_unknown_fields => {
+                        check_field_number(number, span)?;
+                        for value in self.parse_unknown_value()? {
+                            message.fields.add_unknown(number, value);
+                        }
                     }
-                })?;
-
-                self.parse_field_value(message, &field)?;
-            }
+                    None if self.options.allow_unknown_fields => self.skip_value()?,
+                    None => {
+                        return Err(ParseErrorKind::FieldNotFound {
+                            field_name: number.to_string(),
+                            message_name: message.desc.full_name().to_owned(),
+                            span,
+                        })
+                    }
+                },
+            },
             FieldName::Extension(extension_name) => {
-                let extension = message
-                    .desc
-                    .get_extension_by_full_name(&extension_name)
-                    .ok_or_else(|| ParseErrorKind::ExtensionNotFound {
-                        extension_name,
-                        message_name: message.desc.full_name().to_owned(),
-                        span,
-                    })?;
-
-                self.parse_field_value(message, &extension)?;
+                match message.desc.get_extension_by_full_name(&extension_name) {
+                    Some(extension) => self.parse_field_value(message, &extension)?,
+                    None if self.options.allow_unknown_fields => self.skip_value()?,
+                    None => {
+                        return Err(ParseErrorKind::ExtensionNotFound {
+                            extension_name,
+                            message_name: message.desc.full_name().to_owned(),
+                            span,
+                        })
+                    }
+                }
             }
-            FieldName::Any(domain, message_name)
-                if domain == GOOGLE_APIS_DOMAIN.trim_end_matches('/')
-                    || domain == GOOGLE_PROD_DOMAIN.trim_end_matches('/') =>
-            {
-                let value_message = match message
-                    .desc
-                    .parent_pool()
-                    .get_message_by_name(&message_name)
-                {
+            FieldName::Any(domain, message_name) => {
+                let type_url = format!("{}/{}", domain, message_name);
+                let value_message = match &self.options.type_resolver {
+                    Some(resolver) => resolver.resolve_message_type(&type_url),
+                    None if domain == GOOGLE_APIS_DOMAIN.trim_end_matches('/')
+                        || domain == GOOGLE_PROD_DOMAIN.trim_end_matches('/') =>
+                    {
+                        message
+                            .desc
+                            .parent_pool()
+                            .get_message_by_name(&message_name)
+                    }
+                    None => return Err(ParseErrorKind::UnknownTypeUrlDomain { domain, span }),
+                };
+                let value_message = match value_message {
                     Some(msg) => msg,
                     None => return Err(ParseErrorKind::MessageNotFound { message_name, span }),
                 };
@@ -119,7 +160,6 @@ impl<'a> Parser<'a> {
                 let mut value = DynamicMessage::new(value_message);
                 self.parse_message_value(&mut value)?;
 
-                let type_url = format!("{}/{}", domain, message_name);
                 let value = value.encode_to_vec();
 
                 if !(message.desc.full_name() == "google.protobuf.Any"
@@ -133,9 +173,6 @@ impl<'a> Parser<'a> {
                     return Err(ParseErrorKind::InvalidTypeForAny { span });
                 }
             }
-            FieldName::Any(domain, _) => {
-                return Err(ParseErrorKind::UnknownTypeUrlDomain { domain, span })
-            }
         }
 
         if matches!(self.peek()?, Some((Token::Comma | Token::Semicolon, _))) {
@@ -148,6 +185,16 @@ impl<'a> Parser<'a> {
     fn parse_field_name(&mut self) -> Result<(FieldName, Span), ParseErrorKind> {
         match self.peek()? {
             Some((Token::Ident(ident), _)) => Ok((FieldName::Ident(ident.to_owned()), self.bump())),
+            Some((Token::IntLiteral(Int { value, radix: 10 }), _))
+                if self.allow_field_numbers() =>
+            {
+                // Numbers too large to be a field number will not be found as a field name.
+                let name = match value.parse() {
+                    Ok(number) => FieldName::Number(number),
+                    Err(_) => FieldName::Ident(value.to_owned()),
+                };
+                Ok((name, self.bump()))
+            }
             Some((Token::LeftBracket, _)) => {
                 let start = self.bump();
 
@@ -225,18 +272,20 @@ impl<'a> Parser<'a> {
             let kind = field.kind();
             let (value, span) = self.parse_value(&kind)?;
 
-            if message.fields.has(field) {
-                return Err(ParseErrorKind::FieldAlreadySet {
-                    field_name: field.text_name().to_owned(),
-                    span,
-                });
-            } else if let Some(oneof) = field.containing_oneof() {
-                for oneof_field in oneof.fields() {
-                    if message.has_field(&oneof_field) {
-                        return Err(ParseErrorKind::OneofAlreadySet {
-                            oneof_name: oneof.name().to_owned(),
-                            span,
-                        });
+            if self.options.deny_duplicate_fields {
+                if message.fields.has(field) {
+                    return Err(ParseErrorKind::FieldAlreadySet {
+                        field_name: field.text_name().to_owned(),
+                        span,
+                    });
+                } else if let Some(oneof) = field.containing_oneof() {
+                    for oneof_field in oneof.fields() {
+                        if message.has_field(&oneof_field) {
+                            return Err(ParseErrorKind::OneofAlreadySet {
+                                oneof_name: oneof.name().to_owned(),
+                                span,
+                            });
+                        }
                     }
                 }
             }
@@ -249,6 +298,10 @@ impl<'a> Parser<'a> {
         match self.peek()? {
             Some((Token::LeftBracket, _)) => {
                 let start = self.bump();
+                if let Some((Token::RightBracket, _)) = self.peek()? {
+                    let end = self.bump();
+                    return Ok((Value::List(vec![]), join_span(start, end)));
+                }
 
                 let mut result = vec![self.parse_value(kind)?.0];
                 loop {
@@ -322,6 +375,210 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Skips over the value of an unknown field, which may be a scalar, a list or a message.
+    fn skip_value(&mut self) -> Result<(), ParseErrorKind> {
+        match self.peek()? {
+            Some((Token::LeftBrace, _)) => {
+                self.bump();
+                self.skip_message(Token::RightBrace)
+            }
+            Some((Token::LeftAngleBracket, _)) => {
+                self.bump();
+                self.skip_message(Token::RightAngleBracket)
+            }
+            Some((Token::LeftBracket, _)) => {
+                self.bump();
+                if let Some((Token::RightBracket, _)) = self.peek()? {
+                    self.bump();
+                    return Ok(());
+                }
+
+                loop {
+                    self.skip_value()?;
+                    match self.peek()? {
+                        Some((Token::Comma, _)) => {
+                            self.bump();
+                        }
+                        Some((Token::RightBracket, _)) => {
+                            self.bump();
+                            return Ok(());
+                        }
+                        _ => self.unexpected_token("',' or ']'")?,
+                    }
+                }
+            }
+            Some((Token::StringLiteral(_), _)) => {
+                self.parse_bytes()?;
+                Ok(())
+            }
+            Some((Token::Minus, _)) => {
+                self.bump();
+                match self.peek()? {
+                    Some((Token::IntLiteral(_) | Token::FloatLiteral(_) | Token::Ident(_), _)) => {
+                        self.bump();
+                        Ok(())
+                    }
+                    _ => self.unexpected_token("a number"),
+                }
+            }
+            Some((Token::IntLiteral(_) | Token::FloatLiteral(_) | Token::Ident(_), _)) => {
+                self.bump();
+                Ok(())
+            }
+            _ => self.unexpected_token("a value"),
+        }
+    }
+
+    /// Skips over the fields of an unknown message, after its opening delimiter.
+    fn skip_message(&mut self, terminator: Token) -> Result<(), ParseErrorKind> {
+        loop {
+            match self.peek()? {
+                Some((Token::Ident(_) | Token::LeftBracket, _)) => self.skip_field()?,
+                Some((Token::IntLiteral(_), _)) if self.allow_field_numbers() => {
+                    self.skip_field()?
+                }
+                Some((tok, _)) if tok == terminator => {
+                    self.bump();
+                    return Ok(());
+                }
+                _ => self.unexpected_token(format!("'{}' or a field name", terminator))?,
+            }
+        }
+    }
+
+    /// Skips over a field of an unknown message.
+    fn skip_field(&mut self) -> Result<(), ParseErrorKind> {
+        self.parse_field_name()?;
+        match self.peek()? {
+            Some((Token::Colon, _)) => {
+                self.bump();
+            }
+            Some((Token::LeftBrace | Token::LeftAngleBracket, _)) => (),
+            _ => self.unexpected_token("':' or a message value")?,
+        };
+
+        self.skip_value()?;
+        if matches!(self.peek()?, Some((Token::Comma | Token::Semicolon, _))) {
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn allow_field_numbers(&self) -> bool {
+        self.options.allow_field_numbers || self.options.preserve_unknown_fields
+    }
+
+    /// Parses the value of an unknown field, in the format written by the text format printer.
+    fn parse_unknown_value(&mut self) -> Result<Vec<UnknownField>, ParseErrorKind> {
+        match self.peek()? {
+            Some((Token::LeftBrace, _)) => {
+                self.bump();
+                Ok(vec![self.parse_unknown_message(Token::RightBrace)?])
+            }
+            Some((Token::LeftAngleBracket, _)) => {
+                self.bump();
+                Ok(vec![self.parse_unknown_message(Token::RightAngleBracket)?])
+            }
+            Some((Token::LeftBracket, _)) => {
+                self.bump();
+                let mut result = Vec::new();
+                if let Some((Token::RightBracket, _)) = self.peek()? {
+                    self.bump();
+                    return Ok(result);
+                }
+
+                loop {
+                    result.extend(self.parse_unknown_value()?);
+                    match self.peek()? {
+                        Some((Token::Comma, _)) => {
+                            self.bump();
+                        }
+                        Some((Token::RightBracket, _)) => {
+                            self.bump();
+                            return Ok(result);
+                        }
+                        _ => self.unexpected_token("',' or ']'")?,
+                    }
+                }
+            }
+            Some((Token::StringLiteral(_), _)) => {
+                let (bytes, _) = self.parse_bytes()?;
+                Ok(vec![UnknownField::LengthDelimited(bytes.into())])
+            }
+            Some((Token::FloatLiteral(_) | Token::Ident(_), _)) => {
+                let (value, _) = self.parse_float()?;
+                Ok(vec![UnknownField::SixtyFourBit(value.to_le_bytes())])
+            }
+            Some((Token::Minus, start)) => {
+                self.bump();
+                if let Some((Token::IntLiteral(_), _)) = self.peek()? {
+                    let (value, end) = self.parse_u64()?;
+                    if value > i64::MAX as u64 + 1 {
+                        return Err(ParseErrorKind::IntegerValueOutOfRange {
+                            expected: "a signed 64-bit integer".to_owned(),
+                            actual: format!("-{}", value),
+                            min: i64::MIN.to_string(),
+                            max: i64::MAX.to_string(),
+                            span: join_span(start, end),
+                        });
+                    }
+                    Ok(vec![UnknownField::Varint(value.wrapping_neg())])
+                } else {
+                    let (value, _) = self.parse_float()?;
+                    Ok(vec![UnknownField::SixtyFourBit((-value).to_le_bytes())])
+                }
+            }
+            Some((Token::IntLiteral(Int { value, radix: 16 }), _)) if value.len() == 8 => {
+                let (value, _) = self.parse_u32()?;
+                Ok(vec![UnknownField::ThirtyTwoBit(value.to_le_bytes())])
+            }
+            Some((Token::IntLiteral(Int { value, radix: 16 }), _)) if value.len() == 16 => {
+                let (value, _) = self.parse_u64()?;
+                Ok(vec![UnknownField::SixtyFourBit(value.to_le_bytes())])
+            }
+            Some((Token::IntLiteral(_), _)) => {
+                let (value, _) = self.parse_u64()?;
+                Ok(vec![UnknownField::Varint(value)])
+            }
+            _ => self.unexpected_token("a value"),
+        }
+    }
+
+    /// Parses the fields of an unknown message, after its opening delimiter, and returns them as
+    /// a length-delimited unknown field.
+    fn parse_unknown_message(&mut self, terminator: Token) -> Result<UnknownField, ParseErrorKind> {
+        let mut set = UnknownFieldSet::default();
+        loop {
+            match self.peek()? {
+                Some((Token::IntLiteral(Int { value, radix: 10 }), span)) => {
+                    self.bump();
+                    let number = value.parse().unwrap_or(0);
+                    check_field_number(number, span)?;
+
+                    match self.peek()? {
+                        Some((Token::Colon, _)) => {
+                            self.bump();
+                        }
+                        Some((Token::LeftBrace | Token::LeftAngleBracket, _)) => (),
+                        _ => self.unexpected_token("':' or a message value")?,
+                    };
+
+                    for value in self.parse_unknown_value()? {
+                        set.push(number, value);
+                    }
+                    if matches!(self.peek()?, Some((Token::Comma | Token::Semicolon, _))) {
+                        self.bump();
+                    }
+                }
+                Some((tok, _)) if tok == terminator => {
+                    self.bump();
+                    return Ok(UnknownField::LengthDelimited(set.encode_to_vec().into()));
+                }
+                _ => self.unexpected_token(format!("'{}' or a field number", terminator))?,
+            }
+        }
+    }
+
     fn parse_float(&mut self) -> Result<(f64, Span), ParseErrorKind> {
         let (negative, start) = match self.peek()? {
             Some((Token::Minus, _)) => (true, self.bump()),
@@ -637,6 +894,20 @@ fn fmt_expected<'a>(ts: impl Iterator<Item = Token<'a>>) -> String {
     s
 }
 
+fn check_field_number(number: u32, span: Span) -> Result<(), ParseErrorKind> {
+    if (1..=MAX_FIELD_NUMBER).contains(&number) {
+        Ok(())
+    } else {
+        Err(ParseErrorKind::IntegerValueOutOfRange {
+            expected: "a field number".to_owned(),
+            actual: number.to_string(),
+            min: 1.to_string(),
+            max: MAX_FIELD_NUMBER.to_string(),
+            span,
+        })
+    }
+}
+
 fn join_span(start: Span, end: Span) -> Span {
     start.start..end.end
 }
@@ -0,0 +1,856 @@
+pub mod openapi;
+mod template;
+
+use std::{error::Error, fmt, future::Future};
+
+use http::{header, HeaderValue, Method, Request, Response, StatusCode};
+use prost::{bytes::Bytes, Message};
+use prost_types::FieldMask;
+use serde_json::{Map, Value as JsonValue};
+use tonic1::{metadata::MetadataMap, Code, Status};
+
+use crate::{
+    DescriptorPool, DeserializeOptions, DynamicMessage, FieldDescriptor, Kind, MessageDescriptor,
+    MethodDescriptor, SerializeOptions, ServiceDescriptor, Value,
+};
+
+use self::template::{percent_decode, Binding, PathTemplate};
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// A rule mapping HTTP requests to a gRPC method, equivalent to a
+/// [`google.api.HttpRule`](https://github.com/googleapis/googleapis/blob/master/google/api/http.proto).
+///
+/// # Examples
+///
+/// ```
+/// use prost_reflect::transcoding::HttpRule;
+/// use http::Method;
+///
+/// let rule = HttpRule::new(Method::PATCH, "/v1/{book.name=shelves/*/books/*}").body("book");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub struct HttpRule {
+    method: Method,
+    path: String,
+    body: Option<String>,
+    response_body: Option<String>,
+}
+
+/// Maps HTTP/JSON requests to gRPC methods according to their [`HttpRule`]s, and serializes the
+/// responses using the [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json).
+///
+/// Rules are usually read from the `google.api.http` option of each method, using
+/// [`from_pool`][Transcoder::from_pool] or [`add_service`][Transcoder::add_service], but can also
+/// be added explicitly with [`add_rule`][Transcoder::add_rule]. Requests are matched against the
+/// rules in the order they were added.
+///
+/// The request message is built as follows:
+///
+/// - If the rule's body is `*`, the request body is deserialized as the whole message. If the body
+///   names a field, the request body is deserialized as the value of that field.
+/// - Any query parameters are bound to the fields named by their keys, which may be dot-separated
+///   paths to the fields of nested messages. Repeated fields may be given several times. Query
+///   parameters are ignored if the rule's body is `*`, and it is an error to pass a parameter which
+///   does not name a field.
+/// - The variables in the path template are bound to the fields they name, taking precedence over
+///   the body and query parameters.
+///
+/// Streaming methods are not supported.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::{DescriptorPool, DynamicMessage};
+/// use prost_reflect::{tonic, transcoding::Transcoder};
+///
+/// # async fn example(pool: DescriptorPool, request: http::Request<Vec<u8>>) {
+/// let transcoder = Transcoder::from_pool(&pool).unwrap();
+///
+/// let response = transcoder
+///     .handle(request, |method, request| async move {
+///         // Invoke the method, for example using a `DynamicClient`.
+///         Ok(tonic::Response::new(DynamicMessage::new(method.output())))
+///     })
+///     .await;
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub struct Transcoder {
+    routes: Vec<Route>,
+    serialize_options: SerializeOptions,
+    deserialize_options: DeserializeOptions,
+}
+
+/// An HTTP request which has been matched to a method by a [`Transcoder`], and converted to its
+/// request message.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub struct TranscodedRequest {
+    method: MethodDescriptor,
+    message: DynamicMessage,
+    response_body: Option<FieldDescriptor>,
+}
+
+/// An error that may occur while adding rules to a [`Transcoder`], or while transcoding a request
+/// or response.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub struct TranscodeError {
+    kind: TranscodeErrorKind,
+}
+
+#[derive(Debug)]
+enum TranscodeErrorKind {
+    InvalidTemplate {
+        method: String,
+        template: String,
+        message: String,
+    },
+    InvalidRule {
+        method: String,
+        message: String,
+    },
+    NotFound {
+        method: Method,
+        path: String,
+    },
+    InvalidParameter {
+        name: String,
+        message: String,
+    },
+    UnknownParameter {
+        name: String,
+    },
+    InvalidBody {
+        message: String,
+    },
+    InvalidResponse {
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    method: MethodDescriptor,
+    http_method: Method,
+    template: PathTemplate,
+    body: Option<String>,
+    response_body: Option<FieldDescriptor>,
+}
+
+impl HttpRule {
+    /// Creates a rule matching requests with the given HTTP method and path template.
+    ///
+    /// The template uses the syntax of `google.api.HttpRule`, for example
+    /// `/v1/{name=shelves/*/books/*}`. It is validated when the rule is added to a [`Transcoder`].
+    pub fn new(method: Method, path: impl Into<String>) -> Self {
+        HttpRule {
+            method,
+            path: path.into(),
+            body: None,
+            response_body: None,
+        }
+    }
+
+    /// Sets the field of the request message which the request body is mapped to, or `*` to map
+    /// the body to the whole message.
+    ///
+    /// By default, the request body is ignored.
+    pub fn body(mut self, field: impl Into<String>) -> Self {
+        self.body = Some(field.into());
+        self
+    }
+
+    /// Sets the field of the response message which is serialized as the response body.
+    ///
+    /// By default, the whole response message is serialized.
+    pub fn response_body(mut self, field: impl Into<String>) -> Self {
+        self.response_body = Some(field.into());
+        self
+    }
+
+    /// Reads the rules in the `google.api.http` option of a method, including any additional
+    /// bindings.
+    ///
+    /// Returns an empty list if the method has no `google.api.http` option.
+    pub fn from_method(method: &MethodDescriptor) -> Result<Vec<Self>, TranscodeError> {
+        let options = method.options().encode_to_vec();
+        let options = MethodOptions::decode(options.as_slice()).map_err(|err| {
+            TranscodeErrorKind::InvalidRule {
+                method: method.full_name().to_owned(),
+                message: format!("failed to decode the 'google.api.http' option: {}", err),
+            }
+        })?;
+
+        let mut rules = Vec::new();
+        if let Some(rule) = options.http {
+            rule.collect_into(method, &mut rules)?;
+        }
+        Ok(rules)
+    }
+}
+
+impl Transcoder {
+    /// Creates a transcoder with no rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a transcoder with the rules of every non-streaming method in `pool` which has a
+    /// `google.api.http` option.
+    pub fn from_pool(pool: &DescriptorPool) -> Result<Self, TranscodeError> {
+        let mut transcoder = Transcoder::new();
+        for service in pool.services() {
+            transcoder.add_service(&service)?;
+        }
+        Ok(transcoder)
+    }
+
+    /// Adds the rules of every non-streaming method in `service` which has a `google.api.http`
+    /// option.
+    pub fn add_service(&mut self, service: &ServiceDescriptor) -> Result<(), TranscodeError> {
+        for method in service.methods() {
+            if method.is_client_streaming() || method.is_server_streaming() {
+                continue;
+            }
+
+            for rule in HttpRule::from_method(&method)? {
+                self.add_rule(method.clone(), rule)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a rule mapping HTTP requests to `method`.
+    ///
+    /// Returns an error if the method is streaming, if the path template is invalid, or if the
+    /// template or rule refers to fields which do not exist.
+    pub fn add_rule(
+        &mut self,
+        method: MethodDescriptor,
+        rule: HttpRule,
+    ) -> Result<(), TranscodeError> {
+        let invalid_rule = |message: String| TranscodeErrorKind::InvalidRule {
+            method: method.full_name().to_owned(),
+            message,
+        };
+
+        if method.is_client_streaming() || method.is_server_streaming() {
+            return Err(invalid_rule("streaming methods cannot be transcoded".to_owned()).into());
+        }
+
+        let template = PathTemplate::parse(&rule.path).map_err(|message| {
+            TranscodeErrorKind::InvalidTemplate {
+                method: method.full_name().to_owned(),
+                template: rule.path.clone(),
+                message,
+            }
+        })?;
+        for field_path in template.field_paths() {
+            let field = resolve_field_path(&method.input(), field_path).map_err(invalid_rule)?;
+            if field.is_list() || field.is_map() || field.kind().as_message().is_some() {
+                return Err(invalid_rule(format!(
+                    "path variable '{}' must refer to a singular scalar field",
+                    field_path
+                ))
+                .into());
+            }
+        }
+
+        if let Some(body) = &rule.body {
+            if body != "*" {
+                find_field(&method.input(), body).map_err(invalid_rule)?;
+            }
+        }
+        let response_body = match &rule.response_body {
+            Some(response_body) => {
+                Some(find_field(&method.output(), response_body).map_err(invalid_rule)?)
+            }
+            None => None,
+        };
+
+        self.routes.push(Route {
+            method,
+            http_method: rule.method,
+            template,
+            body: rule.body,
+            response_body,
+        });
+        Ok(())
+    }
+
+    /// Sets the options used to serialize response messages.
+    pub fn serialize_options(mut self, options: SerializeOptions) -> Self {
+        self.serialize_options = options;
+        self
+    }
+
+    /// Sets the options used to deserialize request bodies and parameters.
+    ///
+    /// Numbers given as path or query parameters are always accepted, regardless of
+    /// [`DeserializeOptions::quoted_numbers`].
+    pub fn deserialize_options(mut self, options: DeserializeOptions) -> Self {
+        self.deserialize_options = options;
+        self
+    }
+
+    /// Finds the method matching an HTTP request, and builds its request message from the path,
+    /// query parameters and body.
+    pub fn transcode_request<B>(
+        &self,
+        request: &Request<B>,
+    ) -> Result<TranscodedRequest, TranscodeError>
+    where
+        B: AsRef<[u8]>,
+    {
+        let path = request.uri().path();
+        let (route, bindings) = self
+            .routes
+            .iter()
+            .filter(|route| route.http_method == request.method())
+            .find_map(|route| Some((route, route.template.matches(path)?)))
+            .ok_or_else(|| TranscodeErrorKind::NotFound {
+                method: request.method().clone(),
+                path: path.to_owned(),
+            })?;
+
+        let mut message = DynamicMessage::new(route.method.input());
+        let body = request.body().as_ref();
+        match route.body.as_deref() {
+            _ if body.is_empty() => (),
+            Some("*") => {
+                let json = parse_body(body)?;
+                message = DynamicMessage::deserialize_with_options(
+                    message.desc,
+                    json,
+                    &self.deserialize_options,
+                )
+                .map_err(|err| TranscodeErrorKind::InvalidBody {
+                    message: err.to_string(),
+                })?;
+            }
+            Some(field) => {
+                let field = find_field(&message.desc, field)
+                    .expect("body field was checked when adding the rule");
+                let json = parse_body(body)?;
+                self.bind_json(&mut message, &field, json)
+                    .map_err(|message| TranscodeErrorKind::InvalidBody { message })?;
+            }
+            None => (),
+        }
+
+        if route.body.as_deref() != Some("*") {
+            for (name, value) in parse_query(request.uri().query().unwrap_or_default()) {
+                if bindings.iter().any(|binding| binding.field_path == name) {
+                    continue;
+                }
+                self.bind_parameter(&mut message, &name, &value)?;
+            }
+        }
+        for Binding { field_path, value } in &bindings {
+            self.bind_parameter(&mut message, field_path, value)?;
+        }
+
+        Ok(TranscodedRequest {
+            method: route.method.clone(),
+            message,
+            response_body: route.response_body.clone(),
+        })
+    }
+
+    /// Serializes the response to a transcoded request as an HTTP response with a JSON body.
+    ///
+    /// Returns an error if `response` is not of the method's output type.
+    pub fn transcode_response(
+        &self,
+        request: &TranscodedRequest,
+        response: &DynamicMessage,
+    ) -> Result<Response<Bytes>, TranscodeError> {
+        if response.desc != request.method.output() {
+            return Err(TranscodeErrorKind::InvalidResponse {
+                message: format!(
+                    "expected a response of type '{}', but found '{}'",
+                    request.method.output().full_name(),
+                    response.desc.full_name()
+                ),
+            }
+            .into());
+        }
+
+        let json = match &request.response_body {
+            Some(field) => {
+                let options = self.serialize_options.clone().field_mask(FieldMask {
+                    paths: vec![field.name().to_owned()],
+                });
+                match serialize_single_field(response, &options)? {
+                    Some(json) => json,
+                    // The field has its default value, so serializing it does not affect any
+                    // nested messages.
+                    None => serialize_single_field(response, &options.skip_default_fields(false))?
+                        .unwrap_or(JsonValue::Null),
+                }
+            }
+            None => response
+                .serialize_with_options(serde_json::value::Serializer, &self.serialize_options)
+                .map_err(|err| TranscodeErrorKind::InvalidResponse {
+                    message: err.to_string(),
+                })?,
+        };
+
+        Ok(json_response(StatusCode::OK, &json))
+    }
+
+    /// Transcodes an HTTP request, invokes `handler` with the matching method and request message,
+    /// and transcodes its response.
+    ///
+    /// The request headers are passed to the handler as metadata, and the metadata of the
+    /// handler's response is returned as response headers. If the request cannot be transcoded,
+    /// or the handler returns an error, the response has a status code corresponding to the gRPC
+    /// status, and a JSON body in the form of a
+    /// [`google.rpc.Status`](https://github.com/googleapis/googleapis/blob/master/google/rpc/status.proto).
+    pub async fn handle<B, F, Fut>(&self, request: Request<B>, handler: F) -> Response<Bytes>
+    where
+        B: AsRef<[u8]>,
+        F: FnOnce(MethodDescriptor, tonic1::Request<DynamicMessage>) -> Fut,
+        Fut: Future<Output = Result<tonic1::Response<DynamicMessage>, Status>>,
+    {
+        let transcoded = match self.transcode_request(&request) {
+            Ok(transcoded) => transcoded,
+            Err(err) => return err.to_response(),
+        };
+
+        let mut grpc_request = tonic1::Request::new(transcoded.message.clone());
+        *grpc_request.metadata_mut() = MetadataMap::from_headers(request.headers().clone());
+
+        let grpc_response = match handler(transcoded.method.clone(), grpc_request).await {
+            Ok(response) => response,
+            Err(status) => return status_response(status.code(), status.message()),
+        };
+        let (metadata, message, _) = grpc_response.into_parts();
+        let mut response = match self.transcode_response(&transcoded, &message) {
+            Ok(response) => response,
+            Err(err) => return err.to_response(),
+        };
+
+        let mut headers = metadata.into_headers();
+        headers.extend(response.headers_mut().drain());
+        *response.headers_mut() = headers;
+        response
+    }
+
+    fn bind_parameter(
+        &self,
+        message: &mut DynamicMessage,
+        field_path: &str,
+        value: &str,
+    ) -> Result<(), TranscodeError> {
+        let invalid_parameter = |message: String| TranscodeErrorKind::InvalidParameter {
+            name: field_path.to_owned(),
+            message,
+        };
+
+        let (parent_path, name) = match field_path.rsplit_once('.') {
+            Some((parent_path, name)) => (Some(parent_path), name),
+            None => (None, field_path),
+        };
+
+        let mut parent = message;
+        if let Some(parent_path) = parent_path {
+            for name in parent_path.split('.') {
+                let field = find_field(&parent.desc, name).map_err(|_| {
+                    TranscodeErrorKind::UnknownParameter {
+                        name: field_path.to_owned(),
+                    }
+                })?;
+                if field.is_list() || field.is_map() || field.kind().as_message().is_none() {
+                    return Err(invalid_parameter(format!(
+                        "'{}' is not a singular message field",
+                        name
+                    ))
+                    .into());
+                }
+                parent = parent
+                    .get_field_mut(&field)
+                    .as_message_mut()
+                    .expect("field should be a message");
+            }
+        }
+        let field =
+            find_field(&parent.desc, name).map_err(|_| TranscodeErrorKind::UnknownParameter {
+                name: field_path.to_owned(),
+            })?;
+        if field.is_map() {
+            return Err(
+                invalid_parameter("map fields cannot be bound to parameters".to_owned()).into(),
+            );
+        }
+
+        let mut json = parameter_to_json(&field, value);
+        if field.is_list() {
+            json = JsonValue::Array(vec![json]);
+        }
+        self.bind_json(parent, &field, json)
+            .map_err(invalid_parameter)?;
+        Ok(())
+    }
+
+    /// Deserializes `json` as the value of `field`, and sets it on `message`. Values of repeated
+    /// fields are appended to any existing values.
+    fn bind_json(
+        &self,
+        message: &mut DynamicMessage,
+        field: &FieldDescriptor,
+        json: JsonValue,
+    ) -> Result<(), String> {
+        let mut object = Map::new();
+        object.insert(field.name().to_owned(), json);
+
+        let options = self.deserialize_options.clone().quoted_numbers(true);
+        let parsed = DynamicMessage::deserialize_with_options(
+            message.desc.clone(),
+            JsonValue::Object(object),
+            &options,
+        )
+        .map_err(|err| err.to_string())?;
+
+        match parsed.get_field(field).into_owned() {
+            Value::List(values) => message
+                .get_field_mut(field)
+                .as_list_mut()
+                .expect("field should be a list")
+                .extend(values),
+            value => message.set_field(field, value),
+        }
+        Ok(())
+    }
+}
+
+impl TranscodedRequest {
+    /// Gets the method the request was matched to.
+    pub fn method(&self) -> &MethodDescriptor {
+        &self.method
+    }
+
+    /// Gets the request message.
+    pub fn message(&self) -> &DynamicMessage {
+        &self.message
+    }
+
+    /// Converts this request into its request message.
+    pub fn into_message(self) -> DynamicMessage {
+        self.message
+    }
+}
+
+impl TranscodeError {
+    /// Gets the gRPC status code corresponding to this error.
+    ///
+    /// Errors in the request have the code [`InvalidArgument`][Code::InvalidArgument], or
+    /// [`NotFound`][Code::NotFound] if no rule matches it. Other errors have the code
+    /// [`Internal`][Code::Internal].
+    pub fn code(&self) -> Code {
+        match &self.kind {
+            TranscodeErrorKind::NotFound { .. } => Code::NotFound,
+            TranscodeErrorKind::InvalidParameter { .. }
+            | TranscodeErrorKind::UnknownParameter { .. }
+            | TranscodeErrorKind::InvalidBody { .. } => Code::InvalidArgument,
+            TranscodeErrorKind::InvalidTemplate { .. }
+            | TranscodeErrorKind::InvalidRule { .. }
+            | TranscodeErrorKind::InvalidResponse { .. } => Code::Internal,
+        }
+    }
+
+    /// Gets the HTTP status code corresponding to this error.
+    pub fn status_code(&self) -> StatusCode {
+        http_status(self.code())
+    }
+
+    fn to_response(&self) -> Response<Bytes> {
+        status_response(self.code(), &self.to_string())
+    }
+}
+
+impl From<TranscodeErrorKind> for TranscodeError {
+    fn from(kind: TranscodeErrorKind) -> Self {
+        TranscodeError { kind }
+    }
+}
+
+impl From<TranscodeError> for Status {
+    fn from(err: TranscodeError) -> Self {
+        Status::new(err.code(), err.to_string())
+    }
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TranscodeErrorKind::InvalidTemplate {
+                method,
+                template,
+                message,
+            } => write!(
+                f,
+                "invalid path template '{}' for method '{}': {}",
+                template, method, message
+            ),
+            TranscodeErrorKind::InvalidRule { method, message } => {
+                write!(f, "invalid HTTP rule for method '{}': {}", method, message)
+            }
+            TranscodeErrorKind::NotFound { method, path } => {
+                write!(f, "no method found for {} {}", method, path)
+            }
+            TranscodeErrorKind::InvalidParameter { name, message } => {
+                write!(f, "invalid value for parameter '{}': {}", name, message)
+            }
+            TranscodeErrorKind::UnknownParameter { name } => {
+                write!(f, "unknown parameter '{}'", name)
+            }
+            TranscodeErrorKind::InvalidBody { message } => {
+                write!(f, "invalid request body: {}", message)
+            }
+            TranscodeErrorKind::InvalidResponse { message } => {
+                write!(f, "invalid response: {}", message)
+            }
+        }
+    }
+}
+
+impl Error for TranscodeError {}
+
+/// Finds a field of `desc` by its name or JSON name.
+fn find_field(desc: &MessageDescriptor, name: &str) -> Result<FieldDescriptor, String> {
+    desc.get_field_by_name(name)
+        .or_else(|| desc.get_field_by_json_name(name))
+        .ok_or_else(|| {
+            format!(
+                "message '{}' has no field named '{}'",
+                desc.full_name(),
+                name
+            )
+        })
+}
+
+/// Finds the field at a dot-separated path of singular message fields.
+fn resolve_field_path(
+    desc: &MessageDescriptor,
+    field_path: &str,
+) -> Result<FieldDescriptor, String> {
+    let mut names = field_path.split('.');
+    let mut field = find_field(desc, names.next().unwrap_or_default())?;
+    for name in names {
+        let parent = match field.kind() {
+            Kind::Message(parent) if !field.is_list() && !field.is_map() => parent,
+            _ => {
+                return Err(format!(
+                    "field '{}' is not a singular message field",
+                    field.full_name()
+                ))
+            }
+        };
+        field = find_field(&parent, name)?;
+    }
+    Ok(field)
+}
+
+/// Converts a path or query parameter to the JSON value used to deserialize `field`.
+///
+/// Values are passed as strings, which the JSON mapping accepts for most types, except booleans,
+/// which must be JSON booleans, and enum numbers.
+fn parameter_to_json(field: &FieldDescriptor, value: &str) -> JsonValue {
+    let is_bool = match field.kind() {
+        Kind::Bool => true,
+        Kind::Message(message) => message.full_name() == "google.protobuf.BoolValue",
+        _ => false,
+    };
+    if is_bool {
+        match value {
+            "true" => return JsonValue::Bool(true),
+            "false" => return JsonValue::Bool(false),
+            _ => (),
+        }
+    } else if field.kind().as_enum().is_some() {
+        if let Ok(number) = value.parse::<i32>() {
+            return JsonValue::from(number);
+        }
+    }
+    JsonValue::String(value.to_owned())
+}
+
+/// Serializes a message whose serialize options have a field mask selecting a single field, and
+/// returns the value of that field, if it was serialized.
+fn serialize_single_field(
+    message: &DynamicMessage,
+    options: &SerializeOptions,
+) -> Result<Option<JsonValue>, TranscodeError> {
+    let json = message
+        .serialize_with_options(serde_json::value::Serializer, options)
+        .map_err(|err| TranscodeErrorKind::InvalidResponse {
+            message: err.to_string(),
+        })?;
+    match json {
+        JsonValue::Object(map) => Ok(map.into_iter().next().map(|(_, value)| value)),
+        _ => Ok(None),
+    }
+}
+
+fn parse_body(body: &[u8]) -> Result<JsonValue, TranscodeError> {
+    serde_json::from_slice(body).map_err(|err| {
+        TranscodeErrorKind::InvalidBody {
+            message: err.to_string(),
+        }
+        .into()
+    })
+}
+
+/// Splits a query string into decoded key-value pairs.
+fn parse_query(query: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " "), true),
+                percent_decode(&value.replace('+', " "), true),
+            )
+        })
+}
+
+/// Gets the HTTP status code corresponding to a gRPC status code, as specified in
+/// `google/rpc/code.proto`.
+fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).expect("499 is a valid status code"),
+        Code::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+        Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::FailedPrecondition => StatusCode::BAD_REQUEST,
+        Code::Aborted => StatusCode::CONFLICT,
+        Code::OutOfRange => StatusCode::BAD_REQUEST,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+    }
+}
+
+fn status_response(code: Code, message: &str) -> Response<Bytes> {
+    let json = serde_json::json!({
+        "code": code as i32,
+        "message": message,
+        "details": [],
+    });
+    json_response(http_status(code), &json)
+}
+
+fn json_response(status: StatusCode, json: &JsonValue) -> Response<Bytes> {
+    let body = serde_json::to_vec(json).expect("JSON values can always be serialized");
+    let mut response = Response::new(Bytes::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(JSON_CONTENT_TYPE),
+    );
+    response
+}
+
+// The subset of `google.protobuf.MethodOptions` and `google.api.HttpRule` needed to read rules
+// from method options.
+
+#[derive(Clone, PartialEq, Message)]
+struct MethodOptions {
+    #[prost(message, optional, tag = "72295728")]
+    http: Option<HttpRuleProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct HttpRuleProto {
+    #[prost(oneof = "Pattern", tags = "2, 3, 4, 5, 6, 8")]
+    pattern: Option<Pattern>,
+    #[prost(string, tag = "7")]
+    body: String,
+    #[prost(string, tag = "12")]
+    response_body: String,
+    #[prost(message, repeated, tag = "11")]
+    additional_bindings: Vec<HttpRuleProto>,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+enum Pattern {
+    #[prost(string, tag = "2")]
+    Get(String),
+    #[prost(string, tag = "3")]
+    Put(String),
+    #[prost(string, tag = "4")]
+    Post(String),
+    #[prost(string, tag = "5")]
+    Delete(String),
+    #[prost(string, tag = "6")]
+    Patch(String),
+    #[prost(message, tag = "8")]
+    Custom(CustomHttpPattern),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct CustomHttpPattern {
+    #[prost(string, tag = "1")]
+    kind: String,
+    #[prost(string, tag = "2")]
+    path: String,
+}
+
+impl HttpRuleProto {
+    fn collect_into(
+        self,
+        method: &MethodDescriptor,
+        rules: &mut Vec<HttpRule>,
+    ) -> Result<(), TranscodeError> {
+        let (http_method, path) = match self.pattern {
+            Some(Pattern::Get(path)) => (Method::GET, path),
+            Some(Pattern::Put(path)) => (Method::PUT, path),
+            Some(Pattern::Post(path)) => (Method::POST, path),
+            Some(Pattern::Delete(path)) => (Method::DELETE, path),
+            Some(Pattern::Patch(path)) => (Method::PATCH, path),
+            Some(Pattern::Custom(custom)) => {
+                let http_method = Method::from_bytes(custom.kind.as_bytes()).map_err(|_| {
+                    TranscodeErrorKind::InvalidRule {
+                        method: method.full_name().to_owned(),
+                        message: format!("invalid HTTP method '{}'", custom.kind),
+                    }
+                })?;
+                (http_method, custom.path)
+            }
+            None => {
+                return Err(TranscodeErrorKind::InvalidRule {
+                    method: method.full_name().to_owned(),
+                    message: "the rule has no HTTP method and path".to_owned(),
+                }
+                .into())
+            }
+        };
+
+        let mut rule = HttpRule::new(http_method, path);
+        if !self.body.is_empty() {
+            rule = rule.body(self.body);
+        }
+        if !self.response_body.is_empty() {
+            rule = rule.response_body(self.response_body);
+        }
+        rules.push(rule);
+
+        for binding in self.additional_bindings {
+            binding.collect_into(method, rules)?;
+        }
+        Ok(())
+    }
+}
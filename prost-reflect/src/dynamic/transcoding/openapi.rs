@@ -0,0 +1,524 @@
+//! Generating [OpenAPI](https://spec.openapis.org/oas/v3.0.3) documents describing the HTTP/JSON
+//! APIs served by a [`Transcoder`][super::Transcoder].
+//!
+//! Each method is described using the rules in its `google.api.http` option. Methods without the
+//! option are described by the convention of accepting a `POST` request to the path
+//! `/<service>/<method>`, where `<service>` is the full name of the service, with the request
+//! message as the body. Streaming methods are skipped, since they cannot be transcoded.
+//!
+//! Messages and enums are described in the `components` section, using schemas derived from the
+//! [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json), and
+//! well-known types are described by the JSON values they are mapped to. Comments in the source
+//! files are included as descriptions, if the descriptors were built with source code info.
+
+use http::Method;
+use serde_json::{json, Map, Value as JsonValue};
+
+use super::{HttpRule, PathTemplate, TranscodeError, TranscodeErrorKind};
+use crate::{
+    EnumDescriptor, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor, ServiceDescriptor,
+};
+
+const STATUS_SCHEMA: &str = "google.rpc.Status";
+
+/// Options to control the generation of OpenAPI documents.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub struct OpenApiOptions {
+    title: String,
+    version: String,
+    use_proto_field_name: bool,
+}
+
+struct Generator<'a> {
+    options: &'a OpenApiOptions,
+    schemas: Map<String, JsonValue>,
+}
+
+/// Generates an OpenAPI document describing the non-streaming methods of `services`.
+///
+/// Returns an error if a `google.api.http` option is invalid, if it uses an HTTP method which
+/// cannot be described by OpenAPI, or if two methods are mapped to the same HTTP method and path.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use prost_reflect::DescriptorPool;
+/// use prost_reflect::transcoding::openapi::{document, OpenApiOptions};
+///
+/// # fn example(pool: DescriptorPool) {
+/// let options = OpenApiOptions::new("Library API", "1.0.0");
+/// let document = document(pool.services(), &options).unwrap();
+/// println!("{}", serde_json::to_string_pretty(&document).unwrap());
+/// # }
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "transcoding")))]
+pub fn document<I>(services: I, options: &OpenApiOptions) -> Result<JsonValue, TranscodeError>
+where
+    I: IntoIterator<Item = ServiceDescriptor>,
+{
+    let mut generator = Generator {
+        options,
+        schemas: Map::new(),
+    };
+    generator.schemas.insert(
+        STATUS_SCHEMA.to_owned(),
+        json!({
+            "type": "object",
+            "description": "The error returned when a request fails.",
+            "properties": {
+                "code": { "type": "integer", "format": "int32" },
+                "message": { "type": "string" },
+                "details": { "type": "array", "items": any_schema() },
+            },
+        }),
+    );
+
+    let mut tags = Vec::new();
+    let mut paths = Map::new();
+    for service in services {
+        let mut tag = json!({ "name": service.name() });
+        if let Some(description) = comments(&service.parent_file(), service.path()) {
+            tag["description"] = JsonValue::String(description);
+        }
+        tags.push(tag);
+
+        for method in service.methods() {
+            if method.is_client_streaming() || method.is_server_streaming() {
+                continue;
+            }
+
+            let mut rules = HttpRule::from_method(&method)?;
+            if rules.is_empty() {
+                rules.push(
+                    HttpRule::new(
+                        Method::POST,
+                        format!("/{}/{}", service.full_name(), method.name()),
+                    )
+                    .body("*"),
+                );
+            }
+
+            for (index, rule) in rules.into_iter().enumerate() {
+                let invalid_rule = |message: String| TranscodeErrorKind::InvalidRule {
+                    method: method.full_name().to_owned(),
+                    message,
+                };
+
+                let template = PathTemplate::parse(&rule.path).map_err(|message| {
+                    TranscodeErrorKind::InvalidTemplate {
+                        method: method.full_name().to_owned(),
+                        template: rule.path.clone(),
+                        message,
+                    }
+                })?;
+                let http_method = match rule.method {
+                    Method::GET
+                    | Method::PUT
+                    | Method::POST
+                    | Method::DELETE
+                    | Method::OPTIONS
+                    | Method::HEAD
+                    | Method::PATCH
+                    | Method::TRACE => rule.method.as_str().to_ascii_lowercase(),
+                    _ => {
+                        return Err(invalid_rule(format!(
+                            "the HTTP method '{}' cannot be described by OpenAPI",
+                            rule.method
+                        ))
+                        .into())
+                    }
+                };
+
+                let mut operation = Map::new();
+                operation.insert("tags".to_owned(), json!([service.name()]));
+                let operation_id = if index == 0 {
+                    format!("{}_{}", service.name(), method.name())
+                } else {
+                    format!("{}_{}{}", service.name(), method.name(), index)
+                };
+                operation.insert("operationId".to_owned(), JsonValue::String(operation_id));
+                if let Some(description) = comments(&method.parent_file(), method.path()) {
+                    operation.insert("description".to_owned(), JsonValue::String(description));
+                }
+
+                let input = method.input();
+                let path_fields: Vec<&str> = template.field_paths().collect();
+                let mut parameters = Vec::new();
+                for field_path in &path_fields {
+                    let field =
+                        super::resolve_field_path(&input, field_path).map_err(invalid_rule)?;
+                    let mut parameter = json!({
+                        "name": field_path,
+                        "in": "path",
+                        "required": true,
+                        "schema": generator.field_schema(&field),
+                    });
+                    if let Some(description) = field_comments(&field) {
+                        parameter["description"] = JsonValue::String(description);
+                    }
+                    parameters.push(parameter);
+                }
+                if rule.body.as_deref() != Some("*") {
+                    generator.query_parameters(
+                        &input,
+                        "",
+                        "",
+                        &path_fields,
+                        rule.body.as_deref(),
+                        &mut vec![input.full_name().to_owned()],
+                        &mut parameters,
+                    );
+                }
+                if !parameters.is_empty() {
+                    operation.insert("parameters".to_owned(), JsonValue::Array(parameters));
+                }
+
+                let request_schema = match rule.body.as_deref() {
+                    Some("*") => Some(generator.message_schema(&input)),
+                    Some(body) => {
+                        let field = super::find_field(&input, body).map_err(invalid_rule)?;
+                        Some(generator.field_schema(&field))
+                    }
+                    None => None,
+                };
+                if let Some(schema) = request_schema {
+                    operation.insert(
+                        "requestBody".to_owned(),
+                        json!({
+                            "required": true,
+                            "content": { "application/json": { "schema": schema } },
+                        }),
+                    );
+                }
+
+                let output = method.output();
+                let response_schema = match &rule.response_body {
+                    Some(response_body) => {
+                        let field =
+                            super::find_field(&output, response_body).map_err(invalid_rule)?;
+                        generator.field_schema(&field)
+                    }
+                    None => generator.message_schema(&output),
+                };
+                operation.insert(
+                    "responses".to_owned(),
+                    json!({
+                        "200": {
+                            "description": "A successful response.",
+                            "content": { "application/json": { "schema": response_schema } },
+                        },
+                        "default": {
+                            "description": "An error response.",
+                            "content": {
+                                "application/json": { "schema": schema_ref(STATUS_SCHEMA) },
+                            },
+                        },
+                    }),
+                );
+
+                let path = template.openapi_path();
+                let path_item = paths
+                    .entry(path.clone())
+                    .or_insert_with(|| JsonValue::Object(Map::new()))
+                    .as_object_mut()
+                    .expect("path items are objects");
+                if let Some(existing) = path_item.get(&http_method) {
+                    return Err(invalid_rule(format!(
+                        "the route {} {} is also used by operation '{}'",
+                        rule.method,
+                        path,
+                        existing["operationId"].as_str().unwrap_or_default()
+                    ))
+                    .into());
+                }
+                path_item.insert(http_method, JsonValue::Object(operation));
+            }
+        }
+    }
+
+    Ok(json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": options.title,
+            "version": options.version,
+        },
+        "tags": tags,
+        "paths": paths,
+        "components": {
+            "schemas": generator.schemas,
+        },
+    }))
+}
+
+impl OpenApiOptions {
+    /// Creates options for a document with the given title and API version.
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        OpenApiOptions {
+            title: title.into(),
+            version: version.into(),
+            use_proto_field_name: false,
+        }
+    }
+
+    /// Whether to describe fields and query parameters using their names as defined in the
+    /// protobuf definition, instead of their JSON names.
+    ///
+    /// This should match the [`SerializeOptions::use_proto_field_name`][crate::SerializeOptions::use_proto_field_name]
+    /// option used by the transcoder. Path parameters are always named using the field paths in
+    /// the path template.
+    ///
+    /// The default value is `false`.
+    pub fn use_proto_field_name(mut self, yes: bool) -> Self {
+        self.use_proto_field_name = yes;
+        self
+    }
+}
+
+impl<'a> Generator<'a> {
+    fn field_name<'b>(&self, field: &'b FieldDescriptor) -> &'b str {
+        if self.options.use_proto_field_name {
+            field.name()
+        } else {
+            field.json_name()
+        }
+    }
+
+    /// Adds a query parameter for each field of `desc` which is not bound by the path or body,
+    /// expanding the fields of nested messages.
+    #[allow(clippy::too_many_arguments)]
+    fn query_parameters(
+        &mut self,
+        desc: &MessageDescriptor,
+        proto_prefix: &str,
+        prefix: &str,
+        path_fields: &[&str],
+        body: Option<&str>,
+        visited: &mut Vec<String>,
+        parameters: &mut Vec<JsonValue>,
+    ) {
+        for field in desc.fields() {
+            let proto_path = format!("{}{}", proto_prefix, field.name());
+            let name = format!("{}{}", prefix, self.field_name(&field));
+            if path_fields.contains(&proto_path.as_str())
+                || (proto_prefix.is_empty() && body == Some(field.name()))
+                || field.is_map()
+            {
+                continue;
+            }
+
+            if let Kind::Message(message) = field.kind() {
+                if well_known_schema(&message).is_none() {
+                    if field.is_list() || visited.iter().any(|name| name == message.full_name()) {
+                        continue;
+                    }
+
+                    visited.push(message.full_name().to_owned());
+                    self.query_parameters(
+                        &message,
+                        &format!("{}.", proto_path),
+                        &format!("{}.", name),
+                        path_fields,
+                        body,
+                        visited,
+                        parameters,
+                    );
+                    visited.pop();
+                    continue;
+                }
+            }
+
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "schema": self.field_schema(&field),
+            });
+            if let Some(description) = field_comments(&field) {
+                parameter["description"] = JsonValue::String(description);
+            }
+            parameters.push(parameter);
+        }
+    }
+
+    fn field_schema(&mut self, field: &FieldDescriptor) -> JsonValue {
+        if field.is_map() {
+            let value = field
+                .kind()
+                .as_message()
+                .expect("map fields have an entry message")
+                .map_entry_value_field();
+            json!({
+                "type": "object",
+                "additionalProperties": self.kind_schema(&value.kind()),
+            })
+        } else if field.is_list() {
+            json!({
+                "type": "array",
+                "items": self.kind_schema(&field.kind()),
+            })
+        } else {
+            self.kind_schema(&field.kind())
+        }
+    }
+
+    fn kind_schema(&mut self, kind: &Kind) -> JsonValue {
+        match kind {
+            Kind::Message(message) => self.message_schema(message),
+            Kind::Enum(enum_) => self.enum_schema(enum_),
+            _ => scalar_schema(kind),
+        }
+    }
+
+    /// Returns the schema of a message type, adding it to the components if it is not a
+    /// well-known type.
+    fn message_schema(&mut self, desc: &MessageDescriptor) -> JsonValue {
+        if let Some(schema) = well_known_schema(desc) {
+            return schema;
+        }
+
+        if !self.schemas.contains_key(desc.full_name()) {
+            // Insert a placeholder first, so recursive references to this type terminate.
+            self.schemas
+                .insert(desc.full_name().to_owned(), JsonValue::Null);
+
+            let mut properties = Map::new();
+            for field in desc.fields() {
+                let mut schema = self.field_schema(&field);
+                if let Some(description) = field_comments(&field) {
+                    schema = with_description(schema, description);
+                }
+                properties.insert(self.field_name(&field).to_owned(), schema);
+            }
+
+            let mut schema = json!({
+                "type": "object",
+                "properties": properties,
+            });
+            if let Some(description) = comments(&desc.parent_file(), desc.path()) {
+                schema["description"] = JsonValue::String(description);
+            }
+            self.schemas.insert(desc.full_name().to_owned(), schema);
+        }
+        schema_ref(desc.full_name())
+    }
+
+    fn enum_schema(&mut self, desc: &EnumDescriptor) -> JsonValue {
+        if desc.full_name() == "google.protobuf.NullValue" {
+            return json!({ "nullable": true });
+        }
+
+        if !self.schemas.contains_key(desc.full_name()) {
+            let values: Vec<String> = desc.values().map(|value| value.name().to_owned()).collect();
+            let mut schema = json!({
+                "type": "string",
+                "enum": values,
+            });
+            if let Some(description) = comments(&desc.parent_file(), desc.path()) {
+                schema["description"] = JsonValue::String(description);
+            }
+            self.schemas.insert(desc.full_name().to_owned(), schema);
+        }
+        schema_ref(desc.full_name())
+    }
+}
+
+fn scalar_schema(kind: &Kind) -> JsonValue {
+    match kind {
+        Kind::Double => json!({ "type": "number", "format": "double" }),
+        Kind::Float => json!({ "type": "number", "format": "float" }),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => {
+            json!({ "type": "integer", "format": "int32" })
+        }
+        Kind::Uint32 | Kind::Fixed32 => json!({ "type": "integer", "format": "int64" }),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => {
+            json!({ "type": "string", "format": "int64" })
+        }
+        Kind::Uint64 | Kind::Fixed64 => json!({ "type": "string", "format": "uint64" }),
+        Kind::Bool => json!({ "type": "boolean" }),
+        Kind::String => json!({ "type": "string" }),
+        Kind::Bytes => json!({ "type": "string", "format": "byte" }),
+        Kind::Message(_) | Kind::Enum(_) => unreachable!("not a scalar type"),
+    }
+}
+
+/// Returns the schema of a well-known type with a special JSON mapping.
+fn well_known_schema(desc: &MessageDescriptor) -> Option<JsonValue> {
+    let schema = match desc.full_name() {
+        "google.protobuf.Timestamp" => json!({ "type": "string", "format": "date-time" }),
+        "google.protobuf.Duration" | "google.protobuf.FieldMask" => json!({ "type": "string" }),
+        "google.protobuf.Struct" => json!({ "type": "object", "additionalProperties": true }),
+        "google.protobuf.Value" => any_schema(),
+        "google.protobuf.ListValue" => json!({ "type": "array", "items": any_schema() }),
+        "google.protobuf.Empty" => json!({ "type": "object" }),
+        "google.protobuf.Any" => json!({
+            "type": "object",
+            "properties": { "@type": { "type": "string" } },
+            "additionalProperties": true,
+        }),
+        "google.protobuf.BoolValue"
+        | "google.protobuf.StringValue"
+        | "google.protobuf.BytesValue"
+        | "google.protobuf.Int32Value"
+        | "google.protobuf.UInt32Value"
+        | "google.protobuf.Int64Value"
+        | "google.protobuf.UInt64Value"
+        | "google.protobuf.FloatValue"
+        | "google.protobuf.DoubleValue" => {
+            let value = desc.get_field_by_name("value")?;
+            let mut schema = scalar_schema(&value.kind());
+            schema["nullable"] = JsonValue::Bool(true);
+            schema
+        }
+        _ => return None,
+    };
+    Some(schema)
+}
+
+fn any_schema() -> JsonValue {
+    json!({})
+}
+
+fn schema_ref(name: &str) -> JsonValue {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+/// Adds a description to a schema. References cannot have sibling properties in OpenAPI 3.0, so
+/// they are wrapped in `allOf`.
+fn with_description(mut schema: JsonValue, description: String) -> JsonValue {
+    if schema.get("$ref").is_some() {
+        schema = json!({ "allOf": [schema] });
+    }
+    schema["description"] = JsonValue::String(description);
+    schema
+}
+
+fn field_comments(field: &FieldDescriptor) -> Option<String> {
+    comments(&field.parent_file(), field.path())
+}
+
+/// Gets the leading comments, or failing that the trailing comments, of the element at `path`.
+fn comments(file: &FileDescriptor, path: &[i32]) -> Option<String> {
+    let location = file
+        .file_descriptor_proto()
+        .source_code_info
+        .as_ref()?
+        .location
+        .iter()
+        .find(|location| location.path == path)?;
+    let comments = location
+        .leading_comments
+        .as_deref()
+        .or(location.trailing_comments.as_deref())?;
+
+    let comments = comments
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let comments = comments.trim();
+    if comments.is_empty() {
+        None
+    } else {
+        Some(comments.to_owned())
+    }
+}
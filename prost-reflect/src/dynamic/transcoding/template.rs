@@ -0,0 +1,309 @@
+/// A parsed `google.api.http` path template, such as `/v1/{name=shelves/*}/books/{book_id}:get`.
+#[derive(Debug, Clone)]
+pub(super) struct PathTemplate {
+    segments: Vec<Segment>,
+    variables: Vec<Variable>,
+    verb: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `*`, which matches a single segment.
+    Wildcard,
+    /// `**`, which matches zero or more segments.
+    DoubleWildcard,
+}
+
+/// A variable capturing the segments in `start..end` into the field at `field_path`.
+#[derive(Debug, Clone)]
+struct Variable {
+    field_path: String,
+    start: usize,
+    end: usize,
+}
+
+/// The value of a variable captured while matching a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Binding {
+    pub(super) field_path: String,
+    pub(super) value: String,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    segments: Vec<Segment>,
+    variables: Vec<Variable>,
+}
+
+impl PathTemplate {
+    /// Parses a path template, returning a description of the problem if it is invalid.
+    pub(super) fn parse(template: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            input: template,
+            pos: 0,
+            segments: Vec::new(),
+            variables: Vec::new(),
+        };
+
+        if !parser.eat('/') {
+            return Err("the template must start with '/'".to_owned());
+        }
+        parser.parse_segments(false)?;
+
+        let verb = if parser.eat(':') {
+            let verb = parser.parse_literal();
+            if verb.is_empty() {
+                return Err("expected a verb after ':'".to_owned());
+            }
+            Some(verb.to_owned())
+        } else {
+            None
+        };
+        if parser.pos != template.len() {
+            return Err(format!("unexpected character at offset {}", parser.pos));
+        }
+
+        let double_wildcards = parser
+            .segments
+            .iter()
+            .filter(|&segment| *segment == Segment::DoubleWildcard)
+            .count();
+        if double_wildcards > 1 {
+            return Err("the template may contain at most one '**'".to_owned());
+        }
+
+        Ok(PathTemplate {
+            segments: parser.segments,
+            variables: parser.variables,
+            verb,
+        })
+    }
+
+    /// Gets the field paths of the variables in this template.
+    pub(super) fn field_paths(&self) -> impl Iterator<Item = &str> {
+        self.variables
+            .iter()
+            .map(|variable| variable.field_path.as_str())
+    }
+
+    /// Formats this template as an OpenAPI path, in which each variable is replaced by a
+    /// parameter named by its field path.
+    pub(super) fn openapi_path(&self) -> String {
+        let mut path = String::new();
+        let mut variables = self.variables.iter().peekable();
+        let mut index = 0;
+        while index < self.segments.len() {
+            path.push('/');
+            if let Some(variable) = variables.next_if(|variable| variable.start == index) {
+                path.push('{');
+                path.push_str(&variable.field_path);
+                path.push('}');
+                index = variable.end;
+                continue;
+            }
+
+            match &self.segments[index] {
+                Segment::Literal(literal) => path.push_str(literal),
+                Segment::Wildcard => path.push('*'),
+                Segment::DoubleWildcard => path.push_str("**"),
+            }
+            index += 1;
+        }
+        if let Some(verb) = &self.verb {
+            path.push(':');
+            path.push_str(verb);
+        }
+        path
+    }
+
+    /// Matches a request path against this template, returning the values of its variables.
+    ///
+    /// Values of variables which capture a single segment are fully percent-decoded. Values of
+    /// variables which capture several segments keep any encoded `/` characters, so that the
+    /// segments can still be distinguished.
+    pub(super) fn matches(&self, path: &str) -> Option<Vec<Binding>> {
+        let path = path.strip_prefix('/')?;
+        let path = match &self.verb {
+            Some(verb) => path.strip_suffix(verb.as_str())?.strip_suffix(':')?,
+            None => path,
+        };
+        let parts: Vec<&str> = path.split('/').collect();
+
+        // Find the range of `parts` matched by each template segment, where the `**` segment may
+        // match any number of parts.
+        let double_wildcard = self
+            .segments
+            .iter()
+            .position(|segment| *segment == Segment::DoubleWildcard);
+        let extra = match double_wildcard {
+            Some(_) if parts.len() + 1 >= self.segments.len() => {
+                parts.len() + 1 - self.segments.len()
+            }
+            None if parts.len() == self.segments.len() => 0,
+            _ => return None,
+        };
+        let part_start = |index: usize| match double_wildcard {
+            Some(double_wildcard) if index > double_wildcard => index + extra - 1,
+            _ => index,
+        };
+        let part_end = |index: usize| match double_wildcard {
+            Some(double_wildcard) if index == double_wildcard => index + extra,
+            _ => part_start(index) + 1,
+        };
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if parts[part_start(index)] != literal {
+                        return None;
+                    }
+                }
+                Segment::Wildcard => {
+                    if parts[part_start(index)].is_empty() {
+                        return None;
+                    }
+                }
+                Segment::DoubleWildcard => (),
+            }
+        }
+
+        let bindings = self
+            .variables
+            .iter()
+            .map(|variable| {
+                let parts = &parts[part_start(variable.start)..part_end(variable.end - 1)];
+                let value = match &self.segments[variable.start..variable.end] {
+                    [Segment::Wildcard] => percent_decode(parts[0], true),
+                    _ => percent_decode(&parts.join("/"), false),
+                };
+                Binding {
+                    field_path: variable.field_path.clone(),
+                    value,
+                }
+            })
+            .collect();
+        Some(bindings)
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        if self.peek() == Some(ch) {
+            self.pos += ch.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_literal(&mut self) -> &'a str {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            if matches!(ch, '/' | '{' | '}' | '*' | ':' | '=') {
+                break;
+            }
+            self.pos += ch.len_utf8();
+        }
+        &self.input[start..self.pos]
+    }
+
+    fn parse_segments(&mut self, in_variable: bool) -> Result<(), String> {
+        loop {
+            self.parse_segment(in_variable)?;
+            if !self.eat('/') {
+                return Ok(());
+            }
+        }
+    }
+
+    fn parse_segment(&mut self, in_variable: bool) -> Result<(), String> {
+        if self.eat('*') {
+            if self.eat('*') {
+                self.segments.push(Segment::DoubleWildcard);
+            } else {
+                self.segments.push(Segment::Wildcard);
+            }
+        } else if self.eat('{') {
+            if in_variable {
+                return Err("variables cannot be nested".to_owned());
+            }
+            self.parse_variable()?;
+        } else {
+            let literal = self.parse_literal();
+            if literal.is_empty() {
+                return Err(format!("expected a path segment at offset {}", self.pos));
+            }
+            self.segments.push(Segment::Literal(literal.to_owned()));
+        }
+        Ok(())
+    }
+
+    fn parse_variable(&mut self) -> Result<(), String> {
+        let field_path = self.parse_literal();
+        if field_path.is_empty()
+            || field_path
+                .split('.')
+                .any(|name| name.is_empty() || !name.chars().all(is_ident_char))
+        {
+            return Err(format!("invalid field path '{}'", field_path));
+        }
+
+        let start = self.segments.len();
+        if self.eat('=') {
+            self.parse_segments(true)?;
+        } else {
+            self.segments.push(Segment::Wildcard);
+        }
+        if !self.eat('}') {
+            return Err(format!("expected '}}' at offset {}", self.pos));
+        }
+
+        self.variables.push(Variable {
+            field_path: field_path.to_owned(),
+            start,
+            end: self.segments.len(),
+        });
+        Ok(())
+    }
+}
+
+fn is_ident_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+/// Decodes percent-encoded bytes in `value`, optionally leaving encoded `/` characters unchanged.
+pub(super) fn percent_decode(value: &str, decode_slash: bool) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                let byte = hi << 4 | lo;
+                if decode_slash || byte != b'/' {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
@@ -31,6 +31,10 @@ impl UnknownFieldSet {
         self.fields.is_empty()
     }
 
+    pub(crate) fn push(&mut self, number: u32, field: UnknownField) {
+        self.fields.entry(number).or_default().push(field);
+    }
+
     pub(crate) fn fields(&self) -> impl Iterator<Item = (u32, &'_ UnknownField)> {
         self.fields
             .iter()
@@ -63,7 +67,7 @@ impl Message for UnknownFieldSet {
         Self: Sized,
     {
         let field = UnknownField::decode(number, wire_type, buf, ctx)?;
-        self.fields.entry(number).or_default().push(field);
+        self.push(number, field);
         Ok(())
     }
 
@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{
+    DynamicMessage, EnumValueDescriptor, ExtensionDescriptor, FieldDescriptor, Kind, Value,
+};
+
+use super::{encode::join_path, fields::ValueAndDescriptor};
+
+/// Receives reports of deprecated fields and enum values found in incoming messages.
+///
+/// An observer can be set with [`DecodeOptions::usage_observer`][crate::DecodeOptions::usage_observer]
+/// or `DeserializeOptions::usage_observer` when the `serde` feature is enabled. Once a message has been decoded, the observer is
+/// called once for each deprecated field which is set in it, and once for each value of an enum
+/// field which refers to a deprecated enum value, including in nested messages. This can be used
+/// to find out which clients still send deprecated fields before removing them.
+///
+/// Fields and enum values are deprecated with the `deprecated` option in the proto file. Paths
+/// have the format described in [`DynamicMessage::missing_required_fields`]. All methods do
+/// nothing by default.
+///
+/// See [`DeprecatedUsageCounter`] for an implementation which counts the usages of each
+/// deprecated field or enum value.
+pub trait UsageObserver: Send + Sync {
+    /// Called for each deprecated field which is set.
+    fn deprecated_field(&self, path: &str, field: &FieldDescriptor) {
+        let _ = (path, field);
+    }
+
+    /// Called for each deprecated extension field which is set.
+    fn deprecated_extension(&self, path: &str, extension: &ExtensionDescriptor) {
+        let _ = (path, extension);
+    }
+
+    /// Called for each value of an enum field, including elements of lists and values of maps,
+    /// which refers to a deprecated enum value.
+    fn deprecated_enum_value(&self, path: &str, value: &EnumValueDescriptor) {
+        let _ = (path, value);
+    }
+}
+
+/// A [`UsageObserver`] which counts the number of times each deprecated field, extension or enum
+/// value is used.
+///
+/// This type is reference counted, so a clone can be kept by the caller to retrieve the counts
+/// once decoding is complete.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DecodeOptions, DeprecatedUsageCounter, DynamicMessage, ReflectMessage};
+/// # use prost_types::FileOptions;
+/// let counter = DeprecatedUsageCounter::new();
+/// let options = DecodeOptions::new().usage_observer(counter.clone());
+///
+/// // The `java_generate_equals_and_hash` field of `FileOptions` is deprecated.
+/// let desc = FileOptions::default().descriptor();
+/// DynamicMessage::decode_with_options(desc.clone(), b"\xa0\x01\x01".as_ref(), &options).unwrap();
+/// DynamicMessage::decode_with_options(desc, b"\xa0\x01\x00".as_ref(), &options).unwrap();
+///
+/// assert_eq!(
+///     counter.counts().get("google.protobuf.FileOptions.java_generate_equals_and_hash"),
+///     Some(&2),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeprecatedUsageCounter {
+    counts: Arc<Mutex<BTreeMap<String, usize>>>,
+}
+
+impl DynamicMessage {
+    pub(crate) fn observe_usage(&self, observer: &dyn UsageObserver) {
+        self.observe_usage_inner("", observer)
+    }
+
+    fn observe_usage_inner(&self, path: &str, observer: &dyn UsageObserver) {
+        for field in self.fields.iter(&self.desc) {
+            let (value, kind, field_path) = match field {
+                ValueAndDescriptor::Field(value, field_desc) => {
+                    let field_path = join_path(path, field_desc.name());
+                    if field_desc
+                        .field_descriptor_proto()
+                        .options
+                        .as_ref()
+                        .map_or(false, |options| options.deprecated())
+                    {
+                        observer.deprecated_field(&field_path, &field_desc);
+                    }
+                    (value, field_desc.kind(), field_path)
+                }
+                ValueAndDescriptor::Extension(value, extension_desc) => {
+                    let field_path = join_path(path, &format!("[{}]", extension_desc.full_name()));
+                    if extension_desc
+                        .field_descriptor_proto()
+                        .options
+                        .as_ref()
+                        .map_or(false, |options| options.deprecated())
+                    {
+                        observer.deprecated_extension(&field_path, &extension_desc);
+                    }
+                    (value, extension_desc.kind(), field_path)
+                }
+                ValueAndDescriptor::Unknown(..) => continue,
+            };
+
+            match value.as_ref() {
+                Value::List(values) => {
+                    for (index, value) in values.iter().enumerate() {
+                        let element_path = format!("{}[{}]", field_path, index);
+                        observe_value(value, &kind, &element_path, observer);
+                    }
+                }
+                Value::Map(values) => {
+                    let value_kind = match kind.as_message() {
+                        Some(entry) => entry.map_entry_value_field().kind(),
+                        None => continue,
+                    };
+                    let mut entries: Vec<_> = values.iter().collect();
+                    entries.sort_by_key(|(key, _)| *key);
+                    for (key, value) in entries {
+                        let entry_path = format!("{}[{}]", field_path, key);
+                        observe_value(value, &value_kind, &entry_path, observer);
+                    }
+                }
+                value => observe_value(value, &kind, &field_path, observer),
+            }
+        }
+    }
+}
+
+impl DeprecatedUsageCounter {
+    /// Creates a new counter, with no usages recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of times each deprecated field, extension or enum value has been used,
+    /// keyed by its full name.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        self.lock().clone()
+    }
+
+    /// Removes and returns the counts recorded so far.
+    pub fn take(&self) -> BTreeMap<String, usize> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn record(&self, full_name: &str) {
+        *self.lock().entry(full_name.to_owned()).or_default() += 1;
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, usize>> {
+        match self.counts.lock() {
+            Ok(guard) => guard,
+            Err(err) => err.into_inner(),
+        }
+    }
+}
+
+impl UsageObserver for DeprecatedUsageCounter {
+    fn deprecated_field(&self, _: &str, field: &FieldDescriptor) {
+        self.record(field.full_name());
+    }
+
+    fn deprecated_extension(&self, _: &str, extension: &ExtensionDescriptor) {
+        self.record(extension.full_name());
+    }
+
+    fn deprecated_enum_value(&self, _: &str, value: &EnumValueDescriptor) {
+        self.record(value.full_name());
+    }
+}
+
+impl fmt::Debug for dyn UsageObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("UsageObserver")
+    }
+}
+
+fn observe_value(value: &Value, kind: &Kind, path: &str, observer: &dyn UsageObserver) {
+    match (value, kind) {
+        (Value::Message(message), _) => message.observe_usage_inner(path, observer),
+        (Value::EnumNumber(number), Kind::Enum(enum_desc)) => {
+            if let Some(value_desc) = enum_desc.get_value(*number) {
+                if value_desc
+                    .enum_value_descriptor_proto()
+                    .options
+                    .as_ref()
+                    .map_or(false, |options| options.deprecated())
+                {
+                    observer.deprecated_enum_value(path, &value_desc);
+                }
+            }
+        }
+        _ => (),
+    }
+}
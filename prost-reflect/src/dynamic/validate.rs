@@ -0,0 +1,262 @@
+use std::{error::Error, fmt, str};
+
+use crate::{DynamicMessage, Kind, Value};
+
+use super::{
+    encode::join_path, fields::ValueAndDescriptor, unknown::UnknownField, FieldDescriptorLike,
+};
+
+/// An error returned by [`DynamicMessage::validate()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidMessageError {
+    invalid_values: Vec<InvalidValue>,
+}
+
+/// A value in a message which is not valid for its field, as reported by
+/// [`InvalidMessageError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidValue {
+    path: String,
+    reason: String,
+}
+
+impl DynamicMessage {
+    /// Checks that every value in this message, including in nested messages, is valid for its
+    /// field.
+    ///
+    /// Decoding always produces valid messages, but values modified through methods such as
+    /// [`get_field_mut`][DynamicMessage::get_field_mut], or produced by lenient parsers, may not
+    /// be. This method checks that:
+    ///
+    /// - each value has the type expected by its field, including the type of nested messages.
+    /// - each map key has the type expected by its field.
+    /// - integer values of the wrong size are reported as out of range if they don't fit in the
+    ///   field's type, such as an `int64` value in an `int32` field.
+    /// - enum values of [closed enums][crate::EnumDescriptor::is_closed] are defined by the enum.
+    /// - string fields which were kept as unknown fields while decoding with
+    ///   [`Utf8Policy::Permissive`][crate::Utf8Policy::Permissive] contain valid UTF-8.
+    ///
+    /// Unlike [`is_initialized`][DynamicMessage::is_initialized], this does not check that
+    /// required fields are set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, ReflectMessage, Value};
+    /// # use prost_types::Duration;
+    /// let mut message = DynamicMessage::new(Duration::default().descriptor());
+    /// message.set_field_by_name("nanos", Value::I32(5));
+    /// assert!(message.validate().is_ok());
+    ///
+    /// *message.get_field_by_name_mut("nanos").unwrap() = Value::I64(1 << 40);
+    /// let err = message.validate().unwrap_err();
+    /// assert_eq!(err.invalid_values()[0].path(), "nanos");
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "message has invalid values: nanos: 1099511627776 is out of range for type 'int32'",
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), InvalidMessageError> {
+        let mut invalid_values = Vec::new();
+        self.validate_inner("", &mut invalid_values);
+        if invalid_values.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidMessageError { invalid_values })
+        }
+    }
+
+    fn validate_inner(&self, path: &str, invalid_values: &mut Vec<InvalidValue>) {
+        for field in self.fields.iter(&self.desc) {
+            match field {
+                ValueAndDescriptor::Field(value, field_desc) => {
+                    let field_path = join_path(path, field_desc.name());
+                    validate_field(&value, &field_desc, &field_path, invalid_values);
+                }
+                ValueAndDescriptor::Extension(value, extension_desc) => {
+                    let field_path = join_path(path, &format!("[{}]", extension_desc.full_name()));
+                    validate_field(&value, &extension_desc, &field_path, invalid_values);
+                }
+                ValueAndDescriptor::Unknown(number, unknown) => {
+                    let field_desc = match self.desc.get_field(number) {
+                        Some(field_desc) if field_desc.kind() == Kind::String => field_desc,
+                        _ => continue,
+                    };
+
+                    let is_invalid_utf8 = unknown.iter().any(|field| match field {
+                        UnknownField::LengthDelimited(bytes) => str::from_utf8(bytes).is_err(),
+                        _ => false,
+                    });
+                    if is_invalid_utf8 {
+                        invalid_values.push(InvalidValue::new(
+                            join_path(path, field_desc.name()),
+                            "string contains invalid UTF-8".to_owned(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl InvalidMessageError {
+    /// Returns the values which are not valid for their fields.
+    pub fn invalid_values(&self) -> &[InvalidValue] {
+        &self.invalid_values
+    }
+}
+
+impl fmt::Display for InvalidMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "message has invalid values: ")?;
+        for (index, invalid_value) in self.invalid_values.iter().enumerate() {
+            if index != 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", invalid_value)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidMessageError {}
+
+impl InvalidValue {
+    fn new(path: String, reason: String) -> Self {
+        InvalidValue { path, reason }
+    }
+
+    /// Returns the path of the invalid value.
+    ///
+    /// See [`DynamicMessage::missing_required_fields`] for the format of the path.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns a description of why the value is invalid.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl fmt::Display for InvalidValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+fn validate_field(
+    value: &Value,
+    field_desc: &impl FieldDescriptorLike,
+    path: &str,
+    invalid_values: &mut Vec<InvalidValue>,
+) {
+    if field_desc.is_map() {
+        let entry = field_desc.kind();
+        let entry = entry.as_message().expect("map entry is not a message");
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                let reason = format!("expected a map, but found '{}'", value);
+                invalid_values.push(InvalidValue::new(path.to_owned(), reason));
+                return;
+            }
+        };
+
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
+        for (key, value) in entries {
+            let entry_path = format!("{}[{}]", path, key);
+            if !key.is_valid(&key_kind) {
+                let reason = format!(
+                    "expected a key of type '{:?}', but found '{}'",
+                    key_kind, key
+                );
+                invalid_values.push(InvalidValue::new(entry_path.clone(), reason));
+            }
+            validate_value(value, &value_kind, &entry_path, invalid_values);
+        }
+    } else if field_desc.is_list() {
+        let kind = field_desc.kind();
+        match value {
+            Value::List(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    let element_path = format!("{}[{}]", path, index);
+                    validate_value(value, &kind, &element_path, invalid_values);
+                }
+            }
+            _ => {
+                let reason = format!("expected a list, but found '{}'", value);
+                invalid_values.push(InvalidValue::new(path.to_owned(), reason));
+            }
+        }
+    } else {
+        validate_value(value, &field_desc.kind(), path, invalid_values);
+    }
+}
+
+fn validate_value(value: &Value, kind: &Kind, path: &str, invalid_values: &mut Vec<InvalidValue>) {
+    let reason = match (value, kind) {
+        (Value::Message(message), Kind::Message(desc)) => {
+            if message.desc == *desc {
+                message.validate_inner(path, invalid_values);
+                return;
+            }
+
+            format!(
+                "expected a message of type '{}', but found '{}'",
+                desc.full_name(),
+                message.desc.full_name()
+            )
+        }
+        (Value::EnumNumber(number), Kind::Enum(desc)) => {
+            if !desc.is_closed() || desc.get_value(*number).is_some() {
+                return;
+            }
+
+            format!(
+                "{} is not a value of the closed enum '{}'",
+                number,
+                desc.full_name()
+            )
+        }
+        (Value::F64(value), Kind::Float) if value.is_finite() && value.abs() > f32::MAX.into() => {
+            format!("{} is out of range for type '{:?}'", value, kind)
+        }
+        _ if value.is_valid(kind) => return,
+        _ => match (integer_value(value), integer_range(kind)) {
+            (Some(value), Some((min, max))) if value < min || value > max => {
+                format!("{} is out of range for type '{:?}'", value, kind)
+            }
+            _ => format!(
+                "expected a value of type '{:?}', but found '{}'",
+                kind, value
+            ),
+        },
+    };
+
+    invalid_values.push(InvalidValue::new(path.to_owned(), reason));
+}
+
+fn integer_value(value: &Value) -> Option<i128> {
+    match *value {
+        Value::I32(value) => Some(value.into()),
+        Value::I64(value) => Some(value.into()),
+        Value::U32(value) => Some(value.into()),
+        Value::U64(value) => Some(value.into()),
+        _ => None,
+    }
+}
+
+fn integer_range(kind: &Kind) -> Option<(i128, i128)> {
+    match kind {
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Some((i32::MIN.into(), i32::MAX.into())),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Some((i64::MIN.into(), i64::MAX.into())),
+        Kind::Uint32 | Kind::Fixed32 => Some((u32::MIN.into(), u32::MAX.into())),
+        Kind::Uint64 | Kind::Fixed64 => Some((u64::MIN.into(), u64::MAX.into())),
+        _ => None,
+    }
+}
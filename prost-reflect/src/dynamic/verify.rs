@@ -0,0 +1,384 @@
+use std::{error::Error, fmt, str};
+
+use prost::{
+    encoding::{self, DecodeContext, WireType},
+    DecodeError,
+};
+
+use crate::{ExtensionDescriptor, FieldDescriptor, Kind, MessageDescriptor, Syntax, Utf8Policy};
+
+use super::{encode::join_path, fields::FieldDescriptorLike};
+
+/// The default maximum nesting depth of messages, matching the limit used by [`prost`].
+const RECURSION_LIMIT: u32 = 100;
+
+/// Options to control the checks made by [`verify()`].
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    utf8_policy: Utf8Policy,
+    max_depth: u32,
+    deny_unknown_fields: bool,
+}
+
+/// Statistics about an encoded message, returned by [`verify()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WireStats {
+    messages: usize,
+    fields: usize,
+    unknown_fields: usize,
+    unknown_field_bytes: usize,
+    max_depth: u32,
+}
+
+/// An error returned by [`verify()`] if the input is not a valid encoding of the message.
+#[derive(Debug)]
+pub struct VerifyError {
+    offset: usize,
+    path: String,
+    error: DecodeError,
+}
+
+struct Verifier<'a> {
+    input: &'a [u8],
+    options: &'a VerifyOptions,
+    /// The fields containing the message currently being verified.
+    path: Vec<PathSegment>,
+    stats: WireStats,
+}
+
+enum PathSegment {
+    Field(FieldDescriptor),
+    Extension(ExtensionDescriptor),
+}
+
+/// Checks that `buf` is a valid encoding of the message type `desc`, without decoding it.
+///
+/// This makes a single pass over the input, checking that each field has a valid tag and a wire
+/// type matching its type in the descriptor, that strings are valid UTF-8 according to the
+/// [`Utf8Policy`], and that messages are not nested more deeply than the configured limit. It
+/// does not allocate a [`DynamicMessage`][crate::DynamicMessage] or copy any field values, so it
+/// is much cheaper than a full decode, for example in a proxy which only needs to reject invalid
+/// payloads.
+///
+/// With equivalent options, verification fails for any input that
+/// [`DynamicMessage::decode_with_options`][crate::DynamicMessage::decode_with_options] would
+/// reject. On success, statistics about the fields in the input are returned.
+///
+/// # Examples
+///
+/// ```
+/// # use prost_reflect::{DescriptorPool, VerifyOptions};
+/// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+/// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+/// // foo = 150, nested.foo = 2
+/// let stats = prost_reflect::verify(&message_descriptor, b"\x08\x96\x01\x1a\x02\x08\x02", &VerifyOptions::new()).unwrap();
+/// assert_eq!(stats.messages(), 2);
+/// assert_eq!(stats.fields(), 3);
+/// assert_eq!(stats.unknown_fields(), 0);
+///
+/// // The `nested` field is encoded as a varint.
+/// let err = prost_reflect::verify(&message_descriptor, b"\x08\x96\x01\x18\x02", &VerifyOptions::new()).unwrap_err();
+/// assert_eq!(err.offset(), 3);
+/// assert_eq!(err.path(), "nested");
+/// ```
+pub fn verify(
+    desc: &MessageDescriptor,
+    buf: &[u8],
+    options: &VerifyOptions,
+) -> Result<WireStats, VerifyError> {
+    let mut verifier = Verifier {
+        input: buf,
+        options,
+        path: Vec::new(),
+        stats: WireStats::default(),
+    };
+
+    let mut buf = buf;
+    verifier.verify_message(desc, &mut buf, 0, None)?;
+    Ok(verifier.stats)
+}
+
+impl VerifyOptions {
+    /// Creates a new instance of [`VerifyOptions`], with the default options chosen to match
+    /// [`DynamicMessage::decode`][crate::DynamicMessage::decode].
+    pub const fn new() -> Self {
+        VerifyOptions {
+            utf8_policy: Utf8Policy::Strict,
+            max_depth: RECURSION_LIMIT,
+            deny_unknown_fields: false,
+        }
+    }
+
+    /// Sets how to handle string fields containing invalid UTF-8.
+    ///
+    /// Only [`Utf8Policy::Strict`] rejects any input. With [`Utf8Policy::Permissive`], invalid
+    /// strings are rejected in fields defined in proto3 files only.
+    ///
+    /// The default value is [`Utf8Policy::Strict`].
+    pub const fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Sets the maximum nesting depth of messages and groups.
+    ///
+    /// The default value is 100, which is the limit used by [`prost`].
+    pub const fn max_depth(mut self, limit: u32) -> Self {
+        self.max_depth = limit;
+        self
+    }
+
+    /// Whether to reject fields with a number not defined in the message type or its extensions.
+    ///
+    /// The default value is `false`.
+    pub const fn deny_unknown_fields(mut self, yes: bool) -> Self {
+        self.deny_unknown_fields = yes;
+        self
+    }
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        VerifyOptions::new()
+    }
+}
+
+impl WireStats {
+    /// Returns the number of messages in the input, including the top-level message, nested
+    /// messages, groups and map entries.
+    pub fn messages(&self) -> usize {
+        self.messages
+    }
+
+    /// Returns the number of fields defined in the descriptor which occur in the input, including
+    /// in nested messages. A field is counted once for each time it occurs, and packed repeated
+    /// fields are counted once for each run of packed values.
+    pub fn fields(&self) -> usize {
+        self.fields
+    }
+
+    /// Returns the number of fields not defined in the descriptor which occur in the input,
+    /// including in nested messages.
+    pub fn unknown_fields(&self) -> usize {
+        self.unknown_fields
+    }
+
+    /// Returns the total size, in bytes, of the unknown fields in the input, including their tags.
+    pub fn unknown_field_bytes(&self) -> usize {
+        self.unknown_field_bytes
+    }
+
+    /// Returns the deepest nesting of messages and groups in the input. A message with no
+    /// nested messages has a depth of 0, and map entries do not add to the depth.
+    pub fn max_depth(&self) -> u32 {
+        self.max_depth
+    }
+}
+
+impl VerifyError {
+    /// Returns the byte offset in the input of the field which is invalid.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the path of the invalid field, or the path of the message containing it if the
+    /// field's tag could not be decoded.
+    ///
+    /// See [`DynamicMessage::missing_required_fields`][crate::DynamicMessage::missing_required_fields]
+    /// for the format of the path. Unlike that method, the path does not include the indices of
+    /// list elements or the keys of map entries, which are not known without decoding.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.error, self.offset)?;
+        if !self.path.is_empty() {
+            write!(f, " in field '{}'", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for VerifyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<'a> Verifier<'a> {
+    /// Verifies fields until the end of `buf`, or the end of the group with the given number.
+    fn verify_message(
+        &mut self,
+        desc: &MessageDescriptor,
+        buf: &mut &'a [u8],
+        depth: u32,
+        group: Option<u32>,
+    ) -> Result<(), VerifyError> {
+        self.stats.messages += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+
+        while !buf.is_empty() {
+            let start = *buf;
+            let (number, wire_type) =
+                encoding::decode_key(buf).map_err(|err| self.error(start, err))?;
+            if wire_type == WireType::EndGroup {
+                return if group == Some(number) {
+                    Ok(())
+                } else {
+                    Err(self.error(start, DecodeError::new("unexpected end group tag")))
+                };
+            }
+
+            if let Some(field_desc) = desc.get_field(number) {
+                let syntax = field_desc.parent_file().syntax();
+                self.path.push(PathSegment::Field(field_desc.clone()));
+                self.verify_field(&field_desc, syntax, wire_type, buf, start, depth)?;
+                self.path.pop();
+            } else if let Some(extension_desc) = desc.get_extension(number) {
+                let syntax = extension_desc.parent_file().syntax();
+                self.path
+                    .push(PathSegment::Extension(extension_desc.clone()));
+                self.verify_field(&extension_desc, syntax, wire_type, buf, start, depth)?;
+                self.path.pop();
+            } else if self.options.deny_unknown_fields {
+                let err = DecodeError::new(format!("unknown field number {}", number));
+                return Err(self.error(start, err));
+            } else {
+                encoding::skip_field(wire_type, number, buf, DecodeContext::default())
+                    .map_err(|err| self.error(start, err))?;
+                self.stats.unknown_fields += 1;
+                self.stats.unknown_field_bytes += start.len() - buf.len();
+            }
+        }
+
+        if group.is_some() {
+            Err(self.error(buf, DecodeError::new("unexpected end of group")))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn verify_field(
+        &mut self,
+        field_desc: &impl FieldDescriptorLike,
+        syntax: Syntax,
+        wire_type: WireType,
+        buf: &mut &'a [u8],
+        start: &'a [u8],
+        depth: u32,
+    ) -> Result<(), VerifyError> {
+        self.stats.fields += 1;
+
+        let kind = field_desc.kind();
+        if field_desc.is_list()
+            && field_desc.is_packable()
+            && wire_type == WireType::LengthDelimited
+        {
+            let mut values = self.take_length_delimited(buf, start)?;
+            while !values.is_empty() {
+                let value_start = values;
+                self.skip_scalar(&kind, &mut values)
+                    .map_err(|err| self.error(value_start, err))?;
+            }
+            return Ok(());
+        }
+
+        let expected_wire_type = if field_desc.is_group() {
+            WireType::StartGroup
+        } else {
+            kind.wire_type()
+        };
+        if wire_type != expected_wire_type {
+            let err = DecodeError::new(format!(
+                "invalid wire type: {:?} (expected {:?})",
+                wire_type, expected_wire_type
+            ));
+            return Err(self.error(start, err));
+        }
+
+        match kind {
+            Kind::Message(entry_desc) if field_desc.is_map() => {
+                let mut entry = self.take_length_delimited(buf, start)?;
+                self.verify_message(&entry_desc, &mut entry, depth, None)
+            }
+            Kind::Message(nested_desc) => {
+                if depth >= self.options.max_depth {
+                    return Err(self.error(start, DecodeError::new("recursion limit reached")));
+                }
+
+                if field_desc.is_group() {
+                    self.verify_message(&nested_desc, buf, depth + 1, Some(field_desc.number()))
+                } else {
+                    let mut nested = self.take_length_delimited(buf, start)?;
+                    self.verify_message(&nested_desc, &mut nested, depth + 1, None)
+                }
+            }
+            Kind::String => {
+                let bytes = self.take_length_delimited(buf, start)?;
+                let check_utf8 = match self.options.utf8_policy {
+                    Utf8Policy::Strict => true,
+                    Utf8Policy::Lossy => false,
+                    Utf8Policy::Permissive => syntax != Syntax::Proto2,
+                };
+                if check_utf8 && str::from_utf8(bytes).is_err() {
+                    let err = DecodeError::new("invalid string value: data is not UTF-8 encoded");
+                    return Err(self.error(start, err));
+                }
+                Ok(())
+            }
+            Kind::Bytes => self.take_length_delimited(buf, start).map(drop),
+            kind => self
+                .skip_scalar(&kind, buf)
+                .map_err(|err| self.error(start, err)),
+        }
+    }
+
+    fn take_length_delimited(
+        &self,
+        buf: &mut &'a [u8],
+        start: &'a [u8],
+    ) -> Result<&'a [u8], VerifyError> {
+        let len = encoding::decode_varint(buf).map_err(|err| self.error(start, err))?;
+        if len > buf.len() as u64 {
+            return Err(self.error(start, DecodeError::new("buffer underflow")));
+        }
+
+        let (bytes, rest) = buf.split_at(len as usize);
+        *buf = rest;
+        Ok(bytes)
+    }
+
+    fn skip_scalar(&self, kind: &Kind, buf: &mut &'a [u8]) -> Result<(), DecodeError> {
+        let len = match kind.wire_type() {
+            WireType::Varint => return encoding::decode_varint(buf).map(drop),
+            WireType::SixtyFourBit => 8,
+            WireType::ThirtyTwoBit => 4,
+            _ => unreachable!("{:?} is not a scalar type", kind),
+        };
+
+        if buf.len() < len {
+            return Err(DecodeError::new("buffer underflow"));
+        }
+        *buf = &buf[len..];
+        Ok(())
+    }
+
+    fn error(&self, at: &[u8], error: DecodeError) -> VerifyError {
+        VerifyError {
+            offset: at.as_ptr() as usize - self.input.as_ptr() as usize,
+            path: self
+                .path
+                .iter()
+                .fold(String::new(), |path, segment| match segment {
+                    PathSegment::Field(field_desc) => join_path(&path, field_desc.name()),
+                    PathSegment::Extension(extension_desc) => {
+                        join_path(&path, &format!("[{}]", extension_desc.full_name()))
+                    }
+                }),
+            error,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::{self, Write};
+
+use prost::{
+    bytes::Bytes,
+    encoding::{self, WireType},
+    DecodeError,
+};
+
+/// The maximum depth of nested messages and groups to decode, matching the recursion limit used
+/// by `prost`.
+const RECURSION_LIMIT: u32 = 100;
+
+/// A field decoded from the protobuf wire format without a message descriptor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireField {
+    /// The field number.
+    pub number: u32,
+    /// The byte offset of the field's tag, relative to the start of the enclosing message.
+    pub offset: usize,
+    /// The value of the field.
+    pub value: WireValue,
+}
+
+/// The value of a [`WireField`].
+///
+/// The wire format does not record whether a length-delimited field holds a string, bytes or a
+/// nested message, so its contents are classified heuristically. Values which are valid UTF-8
+/// without control characters are treated as strings. Otherwise, values which can be fully
+/// decoded as a message are treated as messages, remaining valid UTF-8 values whose only control
+/// characters are whitespace are treated as strings, and everything else is treated as bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WireValue {
+    /// A field with the `Varint` wire type.
+    Varint(u64),
+    /// A field with the `SixtyFourBit` wire type.
+    Fixed64(u64),
+    /// A field with the `ThirtyTwoBit` wire type.
+    Fixed32(u32),
+    /// A length-delimited field which appears to contain a string.
+    String(String),
+    /// A length-delimited field which appears to contain a nested message.
+    Message(Vec<WireField>),
+    /// A length-delimited field with unrecognized contents.
+    Bytes(Bytes),
+    /// A field with the group wire type.
+    Group(Vec<WireField>),
+}
+
+/// Decodes the fields of a protobuf message without a message descriptor.
+///
+/// Returns an error if `buf` is not a valid encoding of a message. Use [`dump`] to inspect
+/// malformed input.
+///
+/// # Examples
+///
+/// ```
+/// use prost_reflect::wire::{self, WireField, WireValue};
+///
+/// let fields = wire::decode(b"\x08\x96\x01\x1a\x02\x10\x42").unwrap();
+/// assert_eq!(fields, vec![
+///     WireField { number: 1, offset: 0, value: WireValue::Varint(150) },
+///     WireField {
+///         number: 3,
+///         offset: 3,
+///         value: WireValue::Message(vec![
+///             WireField { number: 2, offset: 0, value: WireValue::Varint(66) },
+///         ]),
+///     },
+/// ]);
+/// ```
+pub fn decode(buf: &[u8]) -> Result<Vec<WireField>, DecodeError> {
+    let (fields, error) = decode_fields(buf, RECURSION_LIMIT);
+    match error {
+        Some((_, err)) => Err(err),
+        None => Ok(fields),
+    }
+}
+
+/// Renders the fields of a protobuf message as a diagnostic tree, without a message descriptor.
+///
+/// The output is similar to the text syntax of [protoscope](https://github.com/protocolbuffers/protoscope).
+/// Each field is written on its own line as its number followed by its value. Fixed-width values
+/// are suffixed with `i64` or `i32`, length-delimited values are enclosed in braces, and groups
+/// are written as `!{ ... }`. Bytes are written as hexadecimal between backticks. Length-delimited
+/// values are classified as described for [`WireValue`].
+///
+/// If the input is malformed, the fields decoded before the error are written, followed by a
+/// comment describing the error and the remaining bytes.
+///
+/// # Examples
+///
+/// ```
+/// use prost_reflect::wire;
+///
+/// assert_eq!(
+///     wire::dump(b"\x08\x96\x01\x12\x02hi\x1a\x02\x10\x42\x25\x01\x00\x00\x00"),
+///     "1: 150\n2: {\"hi\"}\n3: {\n  2: 66\n}\n4: 1i32\n",
+/// );
+/// assert_eq!(
+///     wire::dump(b"\x08\x96\x01\x12\x05"),
+///     "1: 150\n# error at offset 3: failed to decode Protobuf message: buffer underflow\n# `1205`\n",
+/// );
+/// ```
+pub fn dump(buf: &[u8]) -> String {
+    let (fields, error) = decode_fields(buf, RECURSION_LIMIT);
+
+    let mut result = String::new();
+    fmt_fields(&mut result, &fields, 0).expect("writing to string should not fail");
+    if let Some((offset, err)) = error {
+        writeln!(result, "# error at offset {}: {}", offset, err)
+            .expect("writing to string should not fail");
+        result.push_str("# ");
+        fmt_hex(&mut result, &buf[offset..]).expect("writing to string should not fail");
+        result.push('\n');
+    }
+    result
+}
+
+/// Decodes fields until the end of `buf`, returning the fields decoded before any error, along
+/// with the offset of the field which could not be decoded.
+fn decode_fields(buf: &[u8], depth: u32) -> (Vec<WireField>, Option<(usize, DecodeError)>) {
+    let mut fields = Vec::new();
+    let mut remaining = buf;
+    while !remaining.is_empty() {
+        let offset = buf.len() - remaining.len();
+        match decode_field(&mut remaining, depth) {
+            Ok((number, value)) => fields.push(WireField {
+                number,
+                offset,
+                value,
+            }),
+            Err(err) => return (fields, Some((offset, err))),
+        }
+    }
+    (fields, None)
+}
+
+fn decode_field(buf: &mut &[u8], depth: u32) -> Result<(u32, WireValue), DecodeError> {
+    let (number, wire_type) = encoding::decode_key(buf)?;
+    let value = match wire_type {
+        WireType::Varint => WireValue::Varint(encoding::decode_varint(buf)?),
+        WireType::SixtyFourBit => {
+            let bytes = take(buf, 8)?;
+            WireValue::Fixed64(u64::from_le_bytes(bytes.try_into().unwrap()))
+        }
+        WireType::ThirtyTwoBit => {
+            let bytes = take(buf, 4)?;
+            WireValue::Fixed32(u32::from_le_bytes(bytes.try_into().unwrap()))
+        }
+        WireType::LengthDelimited => {
+            let len = encoding::decode_varint(buf)?;
+            let len = usize::try_from(len).map_err(|_| DecodeError::new("buffer underflow"))?;
+            classify_length_delimited(take(buf, len)?, depth)
+        }
+        WireType::StartGroup => {
+            if depth == 0 {
+                return Err(DecodeError::new("recursion limit reached"));
+            }
+
+            let start = *buf;
+            let mut fields = Vec::new();
+            loop {
+                if buf.is_empty() {
+                    return Err(DecodeError::new("unexpected end of group"));
+                }
+
+                let offset = start.len() - buf.len();
+                let mut peek = *buf;
+                if let (end_number, WireType::EndGroup) = encoding::decode_key(&mut peek)? {
+                    if end_number != number {
+                        return Err(DecodeError::new("unexpected end group tag"));
+                    }
+                    *buf = peek;
+                    break;
+                }
+
+                let (number, value) = decode_field(buf, depth - 1)?;
+                fields.push(WireField {
+                    number,
+                    offset,
+                    value,
+                });
+            }
+            WireValue::Group(fields)
+        }
+        WireType::EndGroup => return Err(DecodeError::new("unexpected end group tag")),
+    };
+    Ok((number, value))
+}
+
+fn classify_length_delimited(bytes: &[u8], depth: u32) -> WireValue {
+    let string = std::str::from_utf8(bytes).ok();
+    if let Some(string) = string {
+        if !string.chars().any(char::is_control) {
+            return WireValue::String(string.to_owned());
+        }
+    }
+
+    if depth > 0 {
+        if let (fields, None) = decode_fields(bytes, depth - 1) {
+            if !fields.is_empty() {
+                return WireValue::Message(fields);
+            }
+        }
+    }
+
+    match string {
+        Some(string)
+            if !string
+                .chars()
+                .any(|ch| ch.is_control() && !matches!(ch, '\n' | '\r' | '\t')) =>
+        {
+            WireValue::String(string.to_owned())
+        }
+        _ => WireValue::Bytes(Bytes::copy_from_slice(bytes)),
+    }
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodeError> {
+    if buf.len() < len {
+        return Err(DecodeError::new("buffer underflow"));
+    }
+
+    let (head, tail) = buf.split_at(len);
+    *buf = tail;
+    Ok(head)
+}
+
+fn fmt_fields(f: &mut impl Write, fields: &[WireField], indent: usize) -> fmt::Result {
+    for field in fields {
+        write!(f, "{:indent$}{}: ", "", field.number, indent = indent)?;
+        match &field.value {
+            WireValue::Varint(value) => write!(f, "{}", value)?,
+            WireValue::Fixed64(value) => write!(f, "{}i64", value)?,
+            WireValue::Fixed32(value) => write!(f, "{}i32", value)?,
+            WireValue::String(value) => {
+                f.write_char('{')?;
+                crate::dynamic::fmt_string(f, value.as_bytes())?;
+                f.write_char('}')?;
+            }
+            WireValue::Bytes(value) => {
+                f.write_char('{')?;
+                fmt_hex(f, value)?;
+                f.write_char('}')?;
+            }
+            WireValue::Message(fields) => {
+                f.write_str("{\n")?;
+                fmt_fields(f, fields, indent + 2)?;
+                write!(f, "{:indent$}}}", "", indent = indent)?;
+            }
+            WireValue::Group(fields) => {
+                f.write_str("!{\n")?;
+                fmt_fields(f, fields, indent + 2)?;
+                write!(f, "{:indent$}}}", "", indent = indent)?;
+            }
+        }
+        f.write_char('\n')?;
+    }
+    Ok(())
+}
+
+fn fmt_hex(f: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    f.write_char('`')?;
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    f.write_char('`')
+}
@@ -0,0 +1,98 @@
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, Value};
+
+const VALUE_NUMBER: u32 = 1;
+
+impl DynamicMessage {
+    /// Gets the value wrapped by the given field, or `None` if the field is unset.
+    ///
+    /// The field must be a singular field of one of the wrapper types, such as
+    /// `google.protobuf.Int32Value` or `google.protobuf.StringValue`. If it is set, the `value`
+    /// field of the wrapper message is returned, even if it has its default value.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the field is not a singular field of a wrapper type.
+    pub fn get_field_unwrapped(&self, field_desc: &FieldDescriptor) -> Option<Value> {
+        let value_desc = wrapped_field(field_desc);
+        if !self.has_field(field_desc) {
+            return None;
+        }
+        match &*self.get_field(field_desc) {
+            Value::Message(wrapper) => Some(wrapper.get_field(&value_desc).into_owned()),
+            _ => None,
+        }
+    }
+
+    /// Sets the value wrapped by the given field, or clears the field if `value` is `None`.
+    ///
+    /// The field must be a singular field of one of the wrapper types, such as
+    /// `google.protobuf.Int32Value` or `google.protobuf.StringValue`, and `value` must be valid for
+    /// its `value` field.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the field is not a singular field of a wrapper type, or if the value
+    /// type is not compatible with the wrapped type, as defined by [`Value::is_valid_for_field`].
+    pub fn set_field_unwrapped(&mut self, field_desc: &FieldDescriptor, value: Option<Value>) {
+        let value_desc = wrapped_field(field_desc);
+        match value {
+            Some(value) => {
+                let mut wrapper = DynamicMessage::new(value_desc.parent_message().clone());
+                wrapper.set_field(&value_desc, value);
+                self.set_field(field_desc, Value::Message(wrapper));
+            }
+            None => self.clear_field(field_desc),
+        }
+    }
+
+    /// Gets the value wrapped by the field with the given name, or `None` if the field is unset.
+    ///
+    /// If the message has no field with the given name, `None` is returned.
+    ///
+    /// See [`get_field_unwrapped`][Self::get_field_unwrapped] for more details.
+    pub fn get_field_by_name_unwrapped(&self, name: &str) -> Option<Value> {
+        self.desc
+            .get_field_by_name(name)
+            .and_then(|field_desc| self.get_field_unwrapped(&field_desc))
+    }
+
+    /// Sets the value wrapped by the field with the given name, or clears the field if `value` is
+    /// `None`.
+    ///
+    /// If no field with the given name exists, this method does nothing.
+    ///
+    /// See [`set_field_unwrapped`][Self::set_field_unwrapped] for more details.
+    pub fn set_field_by_name_unwrapped(&mut self, name: &str, value: Option<Value>) {
+        if let Some(field_desc) = self.desc.get_field_by_name(name) {
+            self.set_field_unwrapped(&field_desc, value)
+        }
+    }
+}
+
+/// Gets the `value` field of the wrapper type of `field_desc`.
+fn wrapped_field(field_desc: &FieldDescriptor) -> FieldDescriptor {
+    match field_desc.kind() {
+        Kind::Message(desc) if !field_desc.is_list() && is_wrapper_type(&desc) => desc
+            .get_field(VALUE_NUMBER)
+            .expect("wrapper types should have a value field"),
+        _ => panic!(
+            "field '{}' is not a singular field of a wrapper type",
+            field_desc.full_name()
+        ),
+    }
+}
+
+fn is_wrapper_type(desc: &MessageDescriptor) -> bool {
+    matches!(
+        desc.full_name(),
+        "google.protobuf.DoubleValue"
+            | "google.protobuf.FloatValue"
+            | "google.protobuf.Int64Value"
+            | "google.protobuf.UInt64Value"
+            | "google.protobuf.Int32Value"
+            | "google.protobuf.UInt32Value"
+            | "google.protobuf.BoolValue"
+            | "google.protobuf.StringValue"
+            | "google.protobuf.BytesValue"
+    )
+}
@@ -0,0 +1,707 @@
+use std::{borrow::Cow, collections::HashMap, error::Error, fmt};
+
+use base64::{display::Base64Display, prelude::BASE64_STANDARD, Engine};
+use prost::bytes::Bytes;
+use quick_xml::{
+    events::{BytesEnd, BytesStart, BytesText, Event},
+    Reader, Writer,
+};
+
+use crate::{DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value};
+
+/// The name of the elements containing the items of a wrapped list or map field.
+const ITEM_ELEMENT: &str = "item";
+
+/// Options to control how messages are mapped to XML documents.
+///
+/// The same options should be used to read a document as were used to write it.
+#[derive(Debug, Clone)]
+#[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+pub struct XmlOptions {
+    scalars_as_attributes: bool,
+    wrap_repeated: bool,
+    deny_unknown_fields: bool,
+    namespaces: HashMap<String, String>,
+}
+
+/// An error that occurred while converting a message to or from XML.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+pub struct XmlError {
+    kind: XmlErrorKind,
+}
+
+#[derive(Debug)]
+enum XmlErrorKind {
+    Xml(quick_xml::Error),
+    MissingRoot,
+    UnknownField { message: String, name: String },
+    NotAttribute { field: String },
+    InvalidValue { field: String, value: String },
+    UnexpectedText { element: String },
+    UnexpectedElement { element: String },
+    TextOutsideRoot,
+    TrailingElement { element: String },
+}
+
+impl XmlOptions {
+    /// Creates a new instance of [`XmlOptions`], with the default options chosen to be compatible
+    /// with most XML schemas.
+    pub fn new() -> Self {
+        XmlOptions {
+            scalars_as_attributes: false,
+            wrap_repeated: false,
+            deny_unknown_fields: true,
+            namespaces: HashMap::new(),
+        }
+    }
+
+    /// Whether to write fields with a scalar or enum type as attributes of the message element,
+    /// rather than as child elements.
+    ///
+    /// Repeated fields, map fields and message fields are always written as child elements. The
+    /// default value is `false`.
+    pub fn scalars_as_attributes(mut self, yes: bool) -> Self {
+        self.scalars_as_attributes = yes;
+        self
+    }
+
+    /// Whether to wrap the values of repeated and map fields in a single element named after the
+    /// field.
+    ///
+    /// If `true`, a repeated field is written as one element containing an `<item>` element for
+    /// each value. If `false`, it is written as one element per value, each named after the field.
+    /// The default value is `false`.
+    pub fn wrap_repeated(mut self, yes: bool) -> Self {
+        self.wrap_repeated = yes;
+        self
+    }
+
+    /// Whether to error when reading an element or attribute which does not match a field of the
+    /// message.
+    ///
+    /// If `false`, unknown elements and attributes are skipped. Attributes with a namespace
+    /// prefix, such as `xsi:type`, are always skipped. The default value is `true`.
+    pub fn deny_unknown_fields(mut self, yes: bool) -> Self {
+        self.deny_unknown_fields = yes;
+        self
+    }
+
+    /// Assigns an XML namespace to the messages of a protobuf package.
+    ///
+    /// The element of a message, and the elements of its fields, are written in the namespace
+    /// assigned to the message's package, by declaring it as the default namespace. Messages in
+    /// packages without a namespace are written without one. Namespaces are not checked when
+    /// reading a document, and elements are matched by their local name.
+    pub fn namespace(mut self, package: impl Into<String>, uri: impl Into<String>) -> Self {
+        self.namespaces.insert(package.into(), uri.into());
+        self
+    }
+
+    fn namespace_for(&self, desc: &MessageDescriptor) -> Option<&str> {
+        self.namespaces.get(desc.package_name()).map(String::as_str)
+    }
+}
+
+impl Default for XmlOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DynamicMessage {
+    /// Serializes this message to an XML document, using the default [`XmlOptions`].
+    ///
+    /// The root element is named after the message type, and each field that is set is written as
+    /// a child element named after the field. Scalar values use the same representations as the
+    /// [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json), except
+    /// that infinite floating point values are written as `INF` and `-INF` like the `xs:double`
+    /// type. Extension fields and unknown fields are not written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// assert_eq!(
+    ///     dynamic_message.to_xml_string().unwrap(),
+    ///     "<MyMessage><foo>150</foo><nested><bar>66</bar></nested></MyMessage>",
+    /// );
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+    pub fn to_xml_string(&self) -> Result<String, XmlError> {
+        self.to_xml_string_with_options(&XmlOptions::new())
+    }
+
+    /// Serializes this message to an XML document, using the given [`XmlOptions`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value, XmlOptions};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// let options = XmlOptions::new()
+    ///     .scalars_as_attributes(true)
+    ///     .namespace("package", "urn:example:package");
+    /// assert_eq!(
+    ///     dynamic_message.to_xml_string_with_options(&options).unwrap(),
+    ///     r#"<MyMessage xmlns="urn:example:package" foo="150"><nested bar="66"/></MyMessage>"#,
+    /// );
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+    pub fn to_xml_string_with_options(&self, options: &XmlOptions) -> Result<String, XmlError> {
+        let mut writer = Writer::new(Vec::new());
+        write_message(&mut writer, self.descriptor().name(), self, None, options)?;
+        Ok(String::from_utf8(writer.into_inner()).expect("quick-xml should produce valid UTF-8"))
+    }
+
+    /// Deserializes a message of type `desc` from an XML document, using the default
+    /// [`XmlOptions`].
+    ///
+    /// The name of the root element is not checked. Floating point values may be written as
+    /// `INF`, `-INF` or `Infinity`, `-Infinity`, and boolean values as `1` or `0` as well as
+    /// `true` or `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let xml = "<MyMessage><foo>150</foo></MyMessage>";
+    /// let dynamic_message = DynamicMessage::from_xml_str(message_descriptor, xml).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+    pub fn from_xml_str(desc: MessageDescriptor, xml: &str) -> Result<Self, XmlError> {
+        Self::from_xml_str_with_options(desc, xml, &XmlOptions::new())
+    }
+
+    /// Deserializes a message of type `desc` from an XML document, using the given
+    /// [`XmlOptions`].
+    ///
+    /// See [`from_xml_str`][Self::from_xml_str] for details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "xml")))]
+    pub fn from_xml_str_with_options(
+        desc: MessageDescriptor,
+        xml: &str,
+        options: &XmlOptions,
+    ) -> Result<Self, XmlError> {
+        let mut reader = Reader::from_str(xml);
+        reader.expand_empty_elements(true);
+
+        loop {
+            match reader.read_event().map_err(XmlErrorKind::Xml)? {
+                Event::Start(start) => {
+                    let mut parser = Parser { reader, options };
+                    let message = parser.read_message(desc, &start)?;
+                    parser.read_trailing()?;
+                    return Ok(message);
+                }
+                Event::Eof => return Err(XmlErrorKind::MissingRoot.into()),
+                Event::Text(text) if is_whitespace(&text) => (),
+                Event::Text(_) | Event::CData(_) => {
+                    return Err(XmlErrorKind::TextOutsideRoot.into())
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn write_message(
+    writer: &mut Writer<Vec<u8>>,
+    name: &str,
+    message: &DynamicMessage,
+    parent_namespace: Option<&str>,
+    options: &XmlOptions,
+) -> Result<(), XmlError> {
+    let desc = message.descriptor();
+    let namespace = options.namespace_for(&desc);
+
+    let mut start = BytesStart::new(name);
+    if namespace != parent_namespace {
+        start.push_attribute(("xmlns", namespace.unwrap_or_default()));
+    }
+
+    let mut children = Vec::new();
+    for field in desc.fields() {
+        if !message.has_field(&field) {
+            continue;
+        }
+
+        if options.scalars_as_attributes && is_scalar(&field) {
+            let value = message.get_field(&field);
+            start.push_attribute((field.name(), scalar_text(&field.kind(), &value).as_ref()));
+        } else {
+            children.push(field);
+        }
+    }
+
+    if children.is_empty() {
+        writer
+            .write_event(Event::Empty(start))
+            .map_err(XmlErrorKind::Xml)?;
+        return Ok(());
+    }
+
+    writer
+        .write_event(Event::Start(start))
+        .map_err(XmlErrorKind::Xml)?;
+    for field in children {
+        write_field(
+            writer,
+            &field,
+            &message.get_field(&field),
+            namespace,
+            options,
+        )?;
+    }
+    writer
+        .write_event(Event::End(BytesEnd::new(name)))
+        .map_err(XmlErrorKind::Xml)?;
+    Ok(())
+}
+
+fn write_field(
+    writer: &mut Writer<Vec<u8>>,
+    field: &FieldDescriptor,
+    value: &Value,
+    namespace: Option<&str>,
+    options: &XmlOptions,
+) -> Result<(), XmlError> {
+    let values: Vec<Cow<Value>> = match value {
+        Value::List(list) => list.iter().map(Cow::Borrowed).collect(),
+        Value::Map(map) => {
+            let entry = field.kind();
+            let entry = entry.as_message().expect("map entry should be a message");
+
+            let mut map: Vec<_> = map.iter().collect();
+            // Sort entries so the output does not depend on the iteration order of the map.
+            map.sort_by_key(|(key, _)| *key);
+
+            map.into_iter()
+                .map(|(key, value)| {
+                    let mut message = DynamicMessage::new(entry.clone());
+                    message.set_field(&entry.map_entry_key_field(), key.clone().into());
+                    message.set_field(&entry.map_entry_value_field(), value.clone());
+                    Cow::Owned(Value::Message(message))
+                })
+                .collect()
+        }
+        value => {
+            return write_value(
+                writer,
+                field.name(),
+                &field.kind(),
+                value,
+                namespace,
+                options,
+            )
+        }
+    };
+
+    if options.wrap_repeated {
+        writer
+            .write_event(Event::Start(BytesStart::new(field.name())))
+            .map_err(XmlErrorKind::Xml)?;
+        for value in &values {
+            write_value(
+                writer,
+                ITEM_ELEMENT,
+                &field.kind(),
+                value,
+                namespace,
+                options,
+            )?;
+        }
+        writer
+            .write_event(Event::End(BytesEnd::new(field.name())))
+            .map_err(XmlErrorKind::Xml)?;
+    } else {
+        for value in &values {
+            write_value(
+                writer,
+                field.name(),
+                &field.kind(),
+                value,
+                namespace,
+                options,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_value(
+    writer: &mut Writer<Vec<u8>>,
+    name: &str,
+    kind: &Kind,
+    value: &Value,
+    namespace: Option<&str>,
+    options: &XmlOptions,
+) -> Result<(), XmlError> {
+    if let Value::Message(message) = value {
+        return write_message(writer, name, message, namespace, options);
+    }
+
+    writer
+        .create_element(name)
+        .write_text_content(BytesText::new(&scalar_text(kind, value)))
+        .map_err(XmlErrorKind::Xml)?;
+    Ok(())
+}
+
+fn is_scalar(field: &FieldDescriptor) -> bool {
+    !field.is_list() && !field.is_map() && field.kind().as_message().is_none()
+}
+
+fn scalar_text<'a>(kind: &Kind, value: &'a Value) -> Cow<'a, str> {
+    match value {
+        Value::Bool(value) => Cow::Borrowed(if *value { "true" } else { "false" }),
+        Value::I32(value) => Cow::Owned(value.to_string()),
+        Value::I64(value) => Cow::Owned(value.to_string()),
+        Value::U32(value) => Cow::Owned(value.to_string()),
+        Value::U64(value) => Cow::Owned(value.to_string()),
+        Value::F32(value) => float_text(f64::from(*value), value.to_string()),
+        Value::F64(value) => float_text(*value, value.to_string()),
+        Value::String(value) => Cow::Borrowed(value),
+        Value::Bytes(value) => Cow::Owned(Base64Display::new(value, &BASE64_STANDARD).to_string()),
+        Value::EnumNumber(number) => {
+            match kind.as_enum().and_then(|desc| desc.get_value(*number)) {
+                Some(value) => Cow::Owned(value.name().to_owned()),
+                None => Cow::Owned(number.to_string()),
+            }
+        }
+        Value::Message(_) | Value::List(_) | Value::Map(_) => {
+            unreachable!("expected scalar value")
+        }
+    }
+}
+
+fn float_text(value: f64, text: String) -> Cow<'static, str> {
+    if value.is_nan() {
+        Cow::Borrowed("NaN")
+    } else if value == f64::INFINITY {
+        Cow::Borrowed("INF")
+    } else if value == f64::NEG_INFINITY {
+        Cow::Borrowed("-INF")
+    } else {
+        Cow::Owned(text)
+    }
+}
+
+struct Parser<'a, 'o> {
+    reader: Reader<&'a [u8]>,
+    options: &'o XmlOptions,
+}
+
+impl<'a, 'o> Parser<'a, 'o> {
+    fn read_message(
+        &mut self,
+        desc: MessageDescriptor,
+        start: &BytesStart,
+    ) -> Result<DynamicMessage, XmlError> {
+        let mut message = DynamicMessage::new(desc);
+
+        for attribute in start.attributes() {
+            let attribute =
+                attribute.map_err(|err| XmlErrorKind::Xml(quick_xml::Error::InvalidAttr(err)))?;
+            if attribute.key.prefix().is_some() || attribute.key.as_namespace_binding().is_some() {
+                continue;
+            }
+
+            let name = element_name(attribute.key.local_name().as_ref());
+            let field = match self.get_field(&message.descriptor(), &name)? {
+                Some(field) => field,
+                None => continue,
+            };
+            if !is_scalar(&field) {
+                return Err(XmlErrorKind::NotAttribute {
+                    field: field.full_name().to_owned(),
+                }
+                .into());
+            }
+
+            let text = attribute.unescape_value().map_err(XmlErrorKind::Xml)?;
+            let value = parse_scalar(&field, &text)?;
+            message.set_field(&field, value);
+        }
+
+        loop {
+            match self.reader.read_event().map_err(XmlErrorKind::Xml)? {
+                Event::Start(child) => {
+                    let name = element_name(child.local_name().as_ref());
+                    match self.get_field(&message.descriptor(), &name)? {
+                        Some(field) => self.read_field(&mut message, &field, &child)?,
+                        None => self.skip_element(&child)?,
+                    }
+                }
+                Event::End(_) => return Ok(message),
+                Event::Text(text) if is_whitespace(&text) => (),
+                Event::Text(_) | Event::CData(_) => {
+                    return Err(XmlErrorKind::UnexpectedText {
+                        element: element_name(start.local_name().as_ref()),
+                    }
+                    .into())
+                }
+                Event::Eof => {
+                    return Err(
+                        XmlErrorKind::Xml(quick_xml::Error::UnexpectedEof(element_name(
+                            start.name().as_ref(),
+                        )))
+                        .into(),
+                    )
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn read_field(
+        &mut self,
+        message: &mut DynamicMessage,
+        field: &FieldDescriptor,
+        start: &BytesStart,
+    ) -> Result<(), XmlError> {
+        if !field.is_list() && !field.is_map() {
+            let value = self.read_value(field, start)?;
+            message.set_field(field, value);
+            return Ok(());
+        }
+
+        if !self.options.wrap_repeated {
+            let value = self.read_value(field, start)?;
+            return add_item(message, field, value);
+        }
+
+        loop {
+            match self.reader.read_event().map_err(XmlErrorKind::Xml)? {
+                Event::Start(item) => {
+                    let value = self.read_value(field, &item)?;
+                    add_item(message, field, value)?;
+                }
+                Event::End(_) => return Ok(()),
+                Event::Text(text) if is_whitespace(&text) => (),
+                Event::Text(_) | Event::CData(_) => {
+                    return Err(XmlErrorKind::UnexpectedText {
+                        element: field.name().to_owned(),
+                    }
+                    .into())
+                }
+                Event::Eof => {
+                    return Err(XmlErrorKind::Xml(quick_xml::Error::UnexpectedEof(
+                        field.name().to_owned(),
+                    ))
+                    .into())
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn read_value(
+        &mut self,
+        field: &FieldDescriptor,
+        start: &BytesStart,
+    ) -> Result<Value, XmlError> {
+        if let Kind::Message(desc) = field.kind() {
+            return Ok(Value::Message(self.read_message(desc, start)?));
+        }
+
+        let mut text = String::new();
+        loop {
+            match self.reader.read_event().map_err(XmlErrorKind::Xml)? {
+                Event::Text(value) => text.push_str(&value.unescape().map_err(XmlErrorKind::Xml)?),
+                Event::CData(value) => text.push_str(
+                    std::str::from_utf8(&value).map_err(|err| XmlErrorKind::Xml(err.into()))?,
+                ),
+                Event::End(_) => break,
+                Event::Start(_) => {
+                    return Err(XmlErrorKind::UnexpectedElement {
+                        element: field.name().to_owned(),
+                    }
+                    .into())
+                }
+                Event::Eof => {
+                    return Err(XmlErrorKind::Xml(quick_xml::Error::UnexpectedEof(
+                        field.name().to_owned(),
+                    ))
+                    .into())
+                }
+                _ => (),
+            }
+        }
+        parse_scalar(field, &text)
+    }
+
+    fn get_field(
+        &self,
+        desc: &MessageDescriptor,
+        name: &str,
+    ) -> Result<Option<FieldDescriptor>, XmlError> {
+        match desc.get_field_by_name(name) {
+            Some(field) => Ok(Some(field)),
+            None if self.options.deny_unknown_fields => Err(XmlErrorKind::UnknownField {
+                message: desc.full_name().to_owned(),
+                name: name.to_owned(),
+            }
+            .into()),
+            None => Ok(None),
+        }
+    }
+
+    fn skip_element(&mut self, start: &BytesStart) -> Result<(), XmlError> {
+        self.reader
+            .read_to_end(start.name())
+            .map_err(XmlErrorKind::Xml)?;
+        Ok(())
+    }
+
+    fn read_trailing(&mut self) -> Result<(), XmlError> {
+        loop {
+            match self.reader.read_event().map_err(XmlErrorKind::Xml)? {
+                Event::Eof => return Ok(()),
+                Event::Start(start) => {
+                    return Err(XmlErrorKind::TrailingElement {
+                        element: element_name(start.local_name().as_ref()),
+                    }
+                    .into())
+                }
+                Event::Text(text) if is_whitespace(&text) => (),
+                Event::Text(_) | Event::CData(_) => {
+                    return Err(XmlErrorKind::TextOutsideRoot.into())
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn add_item(
+    message: &mut DynamicMessage,
+    field: &FieldDescriptor,
+    value: Value,
+) -> Result<(), XmlError> {
+    match message.get_field_mut(field) {
+        Value::List(list) => list.push(value),
+        Value::Map(map) => {
+            let entry = match value {
+                Value::Message(entry) => entry,
+                _ => unreachable!("map entry should be a message"),
+            };
+            let key = entry
+                .get_field_by_number(1)
+                .and_then(|key| key.into_owned().into_map_key())
+                .expect("map entry should have a valid key");
+            let value = entry
+                .get_field_by_number(2)
+                .expect("map entry should have a value")
+                .into_owned();
+            map.insert(key, value);
+        }
+        _ => unreachable!("expected list or map"),
+    }
+    Ok(())
+}
+
+fn parse_scalar(field: &FieldDescriptor, text: &str) -> Result<Value, XmlError> {
+    let value = match field.kind() {
+        Kind::Double => parse_float(text).map(Value::F64),
+        Kind::Float => parse_float(text).map(|value| Value::F32(value as f32)),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => text.parse().ok().map(Value::I32),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => text.parse().ok().map(Value::I64),
+        Kind::Uint32 | Kind::Fixed32 => text.parse().ok().map(Value::U32),
+        Kind::Uint64 | Kind::Fixed64 => text.parse().ok().map(Value::U64),
+        Kind::Bool => match text {
+            "true" | "1" => Some(Value::Bool(true)),
+            "false" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Kind::String => Some(Value::String(text.to_owned())),
+        Kind::Bytes => BASE64_STANDARD
+            .decode(text)
+            .ok()
+            .map(|bytes| Value::Bytes(Bytes::from(bytes))),
+        Kind::Enum(desc) => match desc.get_value_by_name(text) {
+            Some(value) => Some(Value::EnumNumber(value.number())),
+            None => text.parse().ok().map(Value::EnumNumber),
+        },
+        Kind::Message(_) => None,
+    };
+
+    value.ok_or_else(|| {
+        XmlErrorKind::InvalidValue {
+            field: field.full_name().to_owned(),
+            value: text.to_owned(),
+        }
+        .into()
+    })
+}
+
+fn parse_float(text: &str) -> Option<f64> {
+    match text {
+        "INF" | "Infinity" => Some(f64::INFINITY),
+        "-INF" | "-Infinity" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        _ => text.parse().ok(),
+    }
+}
+
+/// Whitespace between elements is used for indentation, and is ignored outside of scalar values.
+fn is_whitespace(text: &[u8]) -> bool {
+    text.iter().all(u8::is_ascii_whitespace)
+}
+
+fn element_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+impl From<XmlErrorKind> for XmlError {
+    fn from(kind: XmlErrorKind) -> Self {
+        XmlError { kind }
+    }
+}
+
+impl fmt::Display for XmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            XmlErrorKind::Xml(err) => write!(f, "{}", err),
+            XmlErrorKind::MissingRoot => write!(f, "expected a root element"),
+            XmlErrorKind::UnknownField { message, name } => {
+                write!(f, "message '{}' has no field named '{}'", message, name)
+            }
+            XmlErrorKind::NotAttribute { field } => {
+                write!(f, "field '{}' cannot be written as an attribute", field)
+            }
+            XmlErrorKind::InvalidValue { field, value } => {
+                write!(f, "invalid value '{}' for field '{}'", value, field)
+            }
+            XmlErrorKind::UnexpectedText { element } => {
+                write!(f, "unexpected text in element '{}'", element)
+            }
+            XmlErrorKind::UnexpectedElement { element } => {
+                write!(f, "unexpected child element in '{}'", element)
+            }
+            XmlErrorKind::TextOutsideRoot => {
+                write!(f, "unexpected text outside of the root element")
+            }
+            XmlErrorKind::TrailingElement { element } => {
+                write!(f, "unexpected element '{}' after the root element", element)
+            }
+        }
+    }
+}
+
+impl Error for XmlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            XmlErrorKind::Xml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use std::{error::Error, fmt};
+
+use serde_norway::Value;
+
+use crate::{DeserializeOptions, DynamicMessage, MessageDescriptor, SerializeOptions};
+
+/// An error that occurred while converting a message to or from YAML.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+pub struct YamlError {
+    kind: YamlErrorKind,
+}
+
+#[derive(Debug)]
+enum YamlErrorKind {
+    Yaml(serde_norway::Error),
+    MergeKey,
+}
+
+impl DynamicMessage {
+    /// Serializes this message to a YAML document, using the default [`SerializeOptions`].
+    ///
+    /// Messages are written as YAML mappings, using the same field names and value
+    /// representations as the [JSON mapping](https://developers.google.com/protocol-buffers/docs/proto3#json).
+    /// Strings which a YAML parser could read as another type, such as `true`, `1.5` or the
+    /// octal-looking `0755`, are quoted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let dynamic_message = DynamicMessage::decode(message_descriptor, b"\x08\x96\x01\x1a\x02\x10\x42".as_ref()).unwrap();
+    /// assert_eq!(dynamic_message.to_yaml_string().unwrap(), "foo: 150\nnested:\n  bar: 66\n");
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    pub fn to_yaml_string(&self) -> Result<String, YamlError> {
+        self.to_yaml_string_with_options(&SerializeOptions::new())
+    }
+
+    /// Serializes this message to a YAML document, using the given [`SerializeOptions`].
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    pub fn to_yaml_string_with_options(
+        &self,
+        options: &SerializeOptions,
+    ) -> Result<String, YamlError> {
+        let mut buf = Vec::new();
+        self.serialize_with_options(&mut serde_norway::Serializer::new(&mut buf), options)
+            .map_err(YamlErrorKind::Yaml)?;
+        Ok(String::from_utf8(buf).expect("serde_norway should produce valid UTF-8"))
+    }
+
+    /// Deserializes a message of type `desc` from a YAML document, using the default
+    /// [`DeserializeOptions`].
+    ///
+    /// Anchors and aliases are resolved, so a value may be defined once and referred to from
+    /// several fields. Merge keys (`<<`) are rejected, because merging is not part of the JSON
+    /// mapping and ignoring them would silently drop fields. Since the parsed document does not
+    /// record how a key was written, a quoted `'<<'` key is rejected as well.
+    ///
+    /// Mapping keys which are integers or booleans are accepted for map fields, as well as
+    /// strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use prost_reflect::{DynamicMessage, DescriptorPool, Value};
+    /// # let pool = DescriptorPool::decode(include_bytes!("../file_descriptor_set.bin").as_ref()).unwrap();
+    /// # let message_descriptor = pool.get_message_by_name("package.MyMessage").unwrap();
+    /// let yaml = "
+    /// foo: &value 150
+    /// nested:
+    ///   bar: *value
+    /// ";
+    /// let dynamic_message = DynamicMessage::from_yaml_str(message_descriptor.clone(), yaml).unwrap();
+    /// assert_eq!(dynamic_message.get_field_by_name("foo").unwrap().as_ref(), &Value::I32(150));
+    ///
+    /// let err = DynamicMessage::from_yaml_str(message_descriptor, "<<: {foo: 1}").unwrap_err();
+    /// assert_eq!(err.to_string(), "merge keys are not supported");
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    pub fn from_yaml_str(desc: MessageDescriptor, yaml: &str) -> Result<Self, YamlError> {
+        Self::from_yaml_str_with_options(desc, yaml, &DeserializeOptions::new())
+    }
+
+    /// Deserializes a message of type `desc` from a YAML document, using the given
+    /// [`DeserializeOptions`].
+    ///
+    /// See [`from_yaml_str`][Self::from_yaml_str] for details.
+    #[cfg_attr(docsrs, doc(cfg(feature = "yaml")))]
+    pub fn from_yaml_str_with_options(
+        desc: MessageDescriptor,
+        yaml: &str,
+        options: &DeserializeOptions,
+    ) -> Result<Self, YamlError> {
+        let value: Value = serde_norway::from_str(yaml).map_err(YamlErrorKind::Yaml)?;
+        if has_merge_key(&value) {
+            return Err(YamlErrorKind::MergeKey.into());
+        }
+
+        let message = DynamicMessage::deserialize_with_options(desc, value, options)
+            .map_err(YamlErrorKind::Yaml)?;
+        Ok(message)
+    }
+}
+
+fn has_merge_key(value: &Value) -> bool {
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        match value {
+            Value::Mapping(mapping) => {
+                for (key, value) in mapping {
+                    if key.as_str() == Some("<<") {
+                        return true;
+                    }
+                    stack.push(key);
+                    stack.push(value);
+                }
+            }
+            Value::Sequence(sequence) => stack.extend(sequence),
+            Value::Tagged(tagged) => stack.push(&tagged.value),
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => (),
+        }
+    }
+    false
+}
+
+impl From<YamlErrorKind> for YamlError {
+    fn from(kind: YamlErrorKind) -> Self {
+        YamlError { kind }
+    }
+}
+
+impl fmt::Display for YamlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            YamlErrorKind::Yaml(err) => write!(f, "{}", err),
+            YamlErrorKind::MergeKey => write!(f, "merge keys are not supported"),
+        }
+    }
+}
+
+impl Error for YamlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            YamlErrorKind::Yaml(err) => Some(err),
+            YamlErrorKind::MergeKey => None,
+        }
+    }
+}
@@ -17,22 +17,76 @@ mod descriptor;
 mod dynamic;
 mod reflect;
 
+#[cfg(feature = "simd-json")]
+#[cfg_attr(docsrs, doc(cfg(feature = "simd-json")))]
+pub use simd_json1 as simd_json;
+#[cfg(feature = "tonic")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tonic")))]
+pub use tonic1 as tonic;
 pub use {prost, prost::bytes, prost_types};
 
 pub use self::descriptor::{
     Cardinality, DescriptorError, DescriptorPool, EnumDescriptor, EnumValueDescriptor,
-    ExtensionDescriptor, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor,
-    MethodDescriptor, OneofDescriptor, ServiceDescriptor, Syntax,
+    ExtensionDescriptor, FieldDescriptor, FileDescriptor, FileMemoryUsage, Kind,
+    LazyDescriptorPool, MemoryUsage, MessageDescriptor, OneofDescriptor, Syntax, TemplateFormat,
+};
+#[cfg(feature = "services")]
+#[cfg_attr(docsrs, doc(cfg(feature = "services")))]
+pub use self::descriptor::{MethodDescriptor, ServiceDescriptor};
+pub use self::dynamic::{
+    confluent, duration, field_mask, framing, google_type, scan_field_numbers, verify, wire,
+    AnyError, AnyRegistry, DecodeOptions, DecodeReport, DeprecatedUsageCounter, DynamicMessage,
+    EncodeError, EncodeInterceptor, EncodeOptions, InvalidMessageError, InvalidValue, ListEncoding,
+    MapKey, SetFieldError, SizePrefix, SortError, TimeError, TypeResolver, UsageObserver,
+    Utf8Policy, Value, VerifyError, VerifyOptions, WireStats,
 };
-pub use self::dynamic::{DynamicMessage, MapKey, SetFieldError, Value};
 pub use self::reflect::ReflectMessage;
 
 #[cfg(feature = "serde")]
-pub use self::dynamic::{DeserializeOptions, SerializeOptions};
+pub use self::dynamic::{
+    Base64Encoding, DeserializeError, DeserializeOptions, FieldNameStrategy, OutOfRangePolicy,
+    SerializeOptions, TimestampPrecision, UnknownFieldCollector, UnknownJsonField,
+};
 
 #[cfg(feature = "derive")]
 #[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
 pub use prost_reflect_derive::ReflectMessage;
 
+#[cfg(feature = "compat")]
+pub use self::descriptor::compat;
+#[cfg(feature = "lint")]
+pub use self::descriptor::lint;
+#[cfg(feature = "arrow")]
+pub use self::dynamic::arrow;
+#[cfg(feature = "avro")]
+pub use self::dynamic::avro;
+#[cfg(feature = "axum")]
+pub use self::dynamic::axum;
+#[cfg(feature = "cel")]
+pub use self::dynamic::cel;
+#[cfg(feature = "csv")]
+pub use self::dynamic::csv;
+#[cfg(feature = "tokio")]
+pub use self::dynamic::length_delimited;
+#[cfg(feature = "services")]
+pub use self::dynamic::mock;
+#[cfg(feature = "parquet")]
+pub use self::dynamic::parquet;
+#[cfg(feature = "protovalidate")]
+pub use self::dynamic::protovalidate;
 #[cfg(feature = "text-format")]
 pub use self::dynamic::text_format;
+#[cfg(feature = "transcoding")]
+pub use self::dynamic::transcoding;
+#[cfg(feature = "bson")]
+pub use self::dynamic::BsonError;
+#[cfg(feature = "cbor")]
+pub use self::dynamic::CborError;
+#[cfg(feature = "yaml")]
+pub use self::dynamic::YamlError;
+#[cfg(feature = "tonic")]
+pub use self::dynamic::{grpc, reflection};
+#[cfg(feature = "serde-json")]
+pub use self::dynamic::{json_lines, CanonicalJsonFormatter, FloatFormatter, JsonValueError};
+#[cfg(feature = "xml")]
+pub use self::dynamic::{XmlError, XmlOptions};